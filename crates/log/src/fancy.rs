@@ -1,30 +1,81 @@
 use indicatif::{ProgressBar, ProgressStyle};
-use std::time::Duration;
+use serde_json::{json, Value};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 
 use super::*;
 
+/// Output format of the logs.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum LogFormat {
+    /// Colored messages, spinners and progress bars.
+    #[default]
+    Human,
+    /// One JSON event per line, on stderr.
+    Json,
+}
+
 #[derive(Debug, Clone, Default)]
-pub struct FancyLogger {}
+pub struct FancyLogger {
+    format: LogFormat,
+}
+
+impl FancyLogger {
+    pub fn new(format: LogFormat) -> FancyLogger {
+        FancyLogger { format }
+    }
+
+    fn message(&self, level: &str, msg: &str, human: impl FnOnce()) {
+        match self.format {
+            LogFormat::Human => human(),
+            LogFormat::Json => emit_event(json!({
+                "type": "message",
+                "level": level,
+                "message": msg,
+            })),
+        }
+    }
+}
 
 impl Logger for FancyLogger {
     fn scoped(&self, msg: &str, done_msg: Option<&str>) -> Box<dyn ScopedMsg> {
-        Box::new(FancyScoped::new(msg, done_msg))
+        match self.format {
+            LogFormat::Human => Box::new(FancyScoped::new(msg, done_msg)),
+            LogFormat::Json => Box::new(JsonScoped::new(msg, done_msg)),
+        }
+    }
+
+    fn progress(&self, phase: &str) -> Box<dyn Progress> {
+        match self.format {
+            LogFormat::Human => Box::new(FancyProgress::new(phase)),
+            LogFormat::Json => Box::new(JsonProgress::new(phase)),
+        }
     }
 
     fn error(&self, msg: &str) {
-        eprintln!("❗️{}", yansi::Paint::red(msg));
+        self.message("error", msg, || eprintln!("❗️{}", yansi::Paint::red(msg)));
+    }
+
+    fn warning(&self, msg: &str) {
+        self.message("warning", msg, || {
+            eprintln!("⚠️ {}", yansi::Paint::yellow(msg))
+        });
     }
 
     fn success(&self, msg: &str) {
-        println!("✓ {}", yansi::Paint::green(msg));
+        self.message("success", msg, || {
+            println!("✓ {}", yansi::Paint::green(msg))
+        });
     }
 
     fn important(&self, msg: &str) {
-        println!("{}", yansi::Paint::blue(msg));
+        self.message("important", msg, || println!("{}", yansi::Paint::blue(msg)));
     }
 
     fn info(&self, msg: &str) {
-        println!("{msg}");
+        self.message("info", msg, || eprintln!("{msg}"));
     }
 }
 
@@ -63,3 +114,107 @@ impl Drop for FancyScoped {
 }
 
 impl ScopedMsg for FancyScoped {}
+
+/// Progress bar, only drawn if stderr is a terminal.
+#[derive(Debug, Clone)]
+pub struct FancyProgress {
+    pb: ProgressBar,
+}
+
+impl FancyProgress {
+    pub fn new(phase: &str) -> FancyProgress {
+        let pb = ProgressBar::new(0);
+        pb.set_style(
+            ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len}")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        pb.set_message(phase.to_string());
+
+        FancyProgress { pb }
+    }
+}
+
+impl Drop for FancyProgress {
+    fn drop(&mut self) {
+        self.pb.finish_and_clear();
+    }
+}
+
+impl Progress for FancyProgress {
+    fn set_length(&self, len: u64) {
+        self.pb.set_length(len);
+    }
+
+    fn inc(&self, delta: u64) {
+        self.pb.inc(delta);
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonScoped {
+    msg: String,
+    done_msg: Option<String>,
+}
+
+impl JsonScoped {
+    pub fn new(msg: &str, done_msg: Option<&str>) -> JsonScoped {
+        emit_event(json!({ "type": "task_started", "message": msg }));
+
+        JsonScoped {
+            msg: msg.to_string(),
+            done_msg: done_msg.map(|msg| msg.to_string()),
+        }
+    }
+}
+
+impl Drop for JsonScoped {
+    fn drop(&mut self) {
+        let msg = self.done_msg.as_ref().unwrap_or(&self.msg);
+        emit_event(json!({ "type": "task_done", "message": msg }));
+    }
+}
+
+impl ScopedMsg for JsonScoped {}
+
+#[derive(Debug)]
+pub struct JsonProgress {
+    phase: String,
+    done: AtomicU64,
+    total: AtomicU64,
+}
+
+impl JsonProgress {
+    pub fn new(phase: &str) -> JsonProgress {
+        JsonProgress {
+            phase: phase.to_string(),
+            done: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+        }
+    }
+
+    fn emit(&self, done: u64) {
+        emit_event(json!({
+            "type": "progress",
+            "phase": self.phase,
+            "done": done,
+            "total": self.total.load(Ordering::Relaxed),
+        }));
+    }
+}
+
+impl Progress for JsonProgress {
+    fn set_length(&self, len: u64) {
+        self.total.store(len, Ordering::Relaxed);
+        self.emit(self.done.load(Ordering::Relaxed));
+    }
+
+    fn inc(&self, delta: u64) {
+        let done = self.done.fetch_add(delta, Ordering::Relaxed) + delta;
+        self.emit(done);
+    }
+}
+
+fn emit_event(event: Value) {
+    eprintln!("{event}");
+}
@@ -9,8 +9,14 @@ pub mod test;
 pub trait Logger: Debug + Send + Sync {
     fn scoped(&self, msg: &str, done_msg: Option<&str>) -> Box<dyn ScopedMsg>;
 
+    /// Report the advancement of a long-running phase. The progress ends when the returned value is
+    /// dropped.
+    fn progress(&self, phase: &str) -> Box<dyn Progress>;
+
     fn error(&self, msg: &str);
 
+    fn warning(&self, msg: &str);
+
     fn success(&self, msg: &str);
 
     fn important(&self, msg: &str);
@@ -19,3 +25,9 @@ pub trait Logger: Debug + Send + Sync {
 }
 
 pub trait ScopedMsg {}
+
+pub trait Progress: Send + Sync {
+    fn set_length(&self, len: u64);
+
+    fn inc(&self, delta: u64);
+}
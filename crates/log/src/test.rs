@@ -8,13 +8,25 @@ impl Logger for TestLogger {
         Box::new(())
     }
 
+    fn progress(&self, _phase: &str) -> Box<dyn Progress> {
+        Box::new(())
+    }
+
     fn info(&self, _msg: &str) {}
 
     fn error(&self, _msg: &str) {}
 
+    fn warning(&self, _msg: &str) {}
+
     fn important(&self, _msg: &str) {}
 
     fn success(&self, _msg: &str) {}
 }
 
 impl ScopedMsg for () {}
+
+impl Progress for () {
+    fn set_length(&self, _len: u64) {}
+
+    fn inc(&self, _delta: u64) {}
+}
@@ -0,0 +1,120 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use sylver_core::land::{
+    cmds::{RuleResult, RulesExecution},
+    ruleset::RuleCategory,
+    Land,
+};
+
+use crate::stream::{write_pretty_json, ReportWriter};
+
+const BADGE_LABEL: &str = "sylver";
+
+/// Badge description read by the shields.io endpoint badge.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShieldsEndpoint {
+    pub schema_version: u8,
+    pub label: String,
+    pub message: String,
+    pub color: String,
+}
+
+impl ShieldsEndpoint {
+    /// Badge showing the number of findings, colored by the most severe category.
+    pub fn new(findings: usize, worst: Option<RuleCategory>) -> ShieldsEndpoint {
+        let message = match findings {
+            0 => "passing".to_string(),
+            1 => "1 finding".to_string(),
+            n => format!("{n} findings"),
+        };
+
+        let color = match worst {
+            None => "brightgreen",
+            Some(RuleCategory::Style) => "yellow",
+            Some(RuleCategory::Smell | RuleCategory::Deprecated) => "orange",
+            Some(RuleCategory::Bug | RuleCategory::Error) => "red",
+        };
+
+        ShieldsEndpoint {
+            schema_version: 1,
+            label: BADGE_LABEL.to_string(),
+            message,
+            color: color.to_string(),
+        }
+    }
+}
+
+/// Summary of a run, as a shields.io endpoint file.
+pub struct BadgeWriter<'a> {
+    land: &'a Land,
+    findings: usize,
+    worst: Option<RuleCategory>,
+}
+
+impl<'a> BadgeWriter<'a> {
+    pub fn new(land: &'a Land) -> BadgeWriter<'a> {
+        BadgeWriter {
+            land,
+            findings: 0,
+            worst: None,
+        }
+    }
+}
+
+impl<'a> ReportWriter for BadgeWriter<'a> {
+    fn begin(&mut self, _out: &mut dyn Write) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn finding(&mut self, _out: &mut dyn Write, result: &RuleResult) -> anyhow::Result<()> {
+        let category = result.rule(self.land).category;
+
+        self.findings += 1;
+        self.worst = self.worst.max(Some(category));
+
+        Ok(())
+    }
+
+    fn end(&mut self, out: &mut dyn Write, _execution: &RulesExecution) -> anyhow::Result<()> {
+        write_pretty_json(out, &ShieldsEndpoint::new(self.findings, self.worst), 0)?;
+        writeln!(out)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge_message_and_color() {
+        let passing = ShieldsEndpoint::new(0, None);
+        assert_eq!(passing.message, "passing");
+        assert_eq!(passing.color, "brightgreen");
+
+        let style = ShieldsEndpoint::new(1, Some(RuleCategory::Style));
+        assert_eq!(style.message, "1 finding");
+        assert_eq!(style.color, "yellow");
+
+        let bugs = ShieldsEndpoint::new(12, Some(RuleCategory::Bug));
+        assert_eq!(bugs.message, "12 findings");
+        assert_eq!(bugs.color, "red");
+    }
+
+    #[test]
+    fn endpoint_schema() {
+        assert_eq!(
+            serde_json::to_value(ShieldsEndpoint::new(0, None)).unwrap(),
+            serde_json::json!({
+                "schemaVersion": 1,
+                "label": "sylver",
+                "message": "passing",
+                "color": "brightgreen",
+            })
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use sylver_core::{
+    land::{
+        cmds::RuleResult,
+        ruleset::{KnownFindings, RuleSetId},
+        Land,
+    },
+    query::SylvaNode,
+    tree::info::{raw::RawTreeInfo, TreeInfo},
+    util::fs::path_to_string,
+};
+
+static BASELINE_FILE_NAME: &str = "baseline.json";
+
+/// Findings accepted during triage, which are no longer reported.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    #[serde(default)]
+    pub findings: BTreeSet<BaselineFinding>,
+    /// Rules disabled for a whole file.
+    #[serde(default)]
+    pub suppressions: BTreeSet<Suppression>,
+}
+
+/// Finding identified by the code of the reported node rather than by its position, so that it
+/// stays accepted when unrelated code is edited.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct BaselineFinding {
+    pub ruleset: String,
+    pub rule: String,
+    pub file: String,
+    /// Code of the node, with normalized whitespace.
+    pub code: String,
+    /// Index of the finding among the findings of the rule with the same code in the same file,
+    /// so that accepting one of them doesn't accept the others.
+    #[serde(default)]
+    pub occurrence: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct Suppression {
+    pub ruleset: String,
+    pub rule: String,
+    pub file: String,
+}
+
+impl BaselineFinding {
+    pub fn new(land: &Land, result: &RuleResult) -> BaselineFinding {
+        let tree = land.sylva_node_tree(result.node);
+
+        // The code of preprocessed files is the one of the original file, which is what users edit.
+        let code = match tree.source.source_map() {
+            Some(map) => {
+                let syntax = &land.sylva_spec(result.node.sylva).syntax;
+                let range = RawTreeInfo::new(tree, syntax).node_pos(result.node.node);
+                map.original_code(tree.source.src(), range)
+            }
+            None => tree.node_code(result.node.node),
+        };
+
+        BaselineFinding {
+            ruleset: land.ruleset(result.ruleset).id().to_string(),
+            rule: result.rule_id.clone(),
+            file: path_to_string(tree.source.path()),
+            code: code.split_whitespace().join(" "),
+            occurrence: 0,
+        }
+    }
+
+    pub fn suppression(&self) -> Suppression {
+        Suppression {
+            ruleset: self.ruleset.clone(),
+            rule: self.rule.clone(),
+            file: self.file.clone(),
+        }
+    }
+}
+
+impl Baseline {
+    pub fn load(sylver_dir: &Path) -> anyhow::Result<Baseline> {
+        let path = baseline_path(sylver_dir);
+
+        if !path.exists() {
+            return Ok(Baseline::default());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read baseline: {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid baseline: {}", path.display()))
+    }
+
+    pub fn save(&self, sylver_dir: &Path) -> anyhow::Result<()> {
+        let path = baseline_path(sylver_dir);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Could not write baseline: {}", path.display()))
+    }
+
+    pub fn contains(&self, finding: &BaselineFinding) -> bool {
+        self.findings.contains(finding) || self.suppressions.contains(&finding.suppression())
+    }
+}
+
+/// Leaves the findings accepted in a baseline out of an evaluation of the rules.
+///
+/// The findings of a rule with the same code in the same file are numbered in evaluation order,
+/// which is stable for a given file.
+pub struct BaselineFilter<'b> {
+    baseline: &'b Baseline,
+    /// Number of matches seen so far for each finding, numbered from 0.
+    seen: Mutex<HashMap<BaselineFinding, usize>>,
+    /// Baseline key of the reported findings.
+    reported: Mutex<HashMap<(RuleSetId, String, SylvaNode), BaselineFinding>>,
+    /// Number of findings of the baseline that were matched again.
+    matched: AtomicUsize,
+}
+
+impl<'b> BaselineFilter<'b> {
+    pub fn new(baseline: &'b Baseline) -> BaselineFilter<'b> {
+        BaselineFilter {
+            baseline,
+            seen: Mutex::default(),
+            reported: Mutex::default(),
+            matched: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of findings of the baseline that were not matched by the evaluation, because the
+    /// code was fixed or removed. Only meaningful once the evaluation is complete.
+    pub fn fixed(&self) -> usize {
+        let matched = self.matched.load(Ordering::SeqCst);
+        self.baseline.findings.len().saturating_sub(matched)
+    }
+
+    /// Baseline key of a finding reported by the evaluation.
+    pub fn finding(&self, land: &Land, result: &RuleResult) -> BaselineFinding {
+        self.reported
+            .lock()
+            .expect("poisoned baseline lock")
+            .get(&(result.ruleset, result.rule_id.clone(), result.node))
+            .cloned()
+            .unwrap_or_else(|| BaselineFinding::new(land, result))
+    }
+}
+
+impl<'b> KnownFindings for BaselineFilter<'b> {
+    fn is_known(&self, land: &Land, ruleset: RuleSetId, rule: &str, node: SylvaNode) -> bool {
+        let result = RuleResult {
+            ruleset,
+            rule_id: rule.to_string(),
+            node,
+        };
+        let mut finding = BaselineFinding::new(land, &result);
+
+        {
+            let mut seen = self.seen.lock().expect("poisoned baseline lock");
+            let count = seen.entry(finding.clone()).or_default();
+            finding.occurrence = *count;
+            *count += 1;
+        }
+
+        if self.baseline.findings.contains(&finding) {
+            self.matched.fetch_add(1, Ordering::SeqCst);
+            return true;
+        }
+
+        if self.baseline.contains(&finding) {
+            return true;
+        }
+
+        self.reported
+            .lock()
+            .expect("poisoned baseline lock")
+            .insert((ruleset, result.rule_id, node), finding);
+
+        false
+    }
+}
+
+pub fn baseline_path(sylver_dir: &Path) -> PathBuf {
+    sylver_dir.join(BASELINE_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    fn finding(file: &str, code: &str) -> BaselineFinding {
+        BaselineFinding {
+            ruleset: "rules.yaml".to_string(),
+            rule: "no-eval".to_string(),
+            file: file.to_string(),
+            code: code.to_string(),
+            occurrence: 0,
+        }
+    }
+
+    #[test]
+    fn accepted_findings() {
+        let mut baseline = Baseline::default();
+        baseline.findings.insert(finding("a.py", "eval(x)"));
+        baseline
+            .suppressions
+            .insert(finding("b.py", "").suppression());
+
+        assert!(baseline.contains(&finding("a.py", "eval(x)")));
+        assert!(!baseline.contains(&finding("a.py", "eval(y)")));
+        assert!(baseline.contains(&finding("b.py", "eval(y)")));
+    }
+
+    #[test]
+    fn occurrences_are_accepted_separately() {
+        let mut baseline = Baseline::default();
+        baseline.findings.insert(finding("a.py", "eval(x)"));
+
+        let second = BaselineFinding {
+            occurrence: 1,
+            ..finding("a.py", "eval(x)")
+        };
+        assert!(!baseline.contains(&second));
+
+        // Baselines written before the occurrences were recorded accept the first occurrence.
+        let legacy = r#"{"findings": [
+            {"ruleset": "rules.yaml", "rule": "no-eval", "file": "a.py", "code": "eval(x)"}
+        ]}"#;
+        let loaded: Baseline = serde_json::from_str(legacy).unwrap();
+        assert_eq!(loaded, baseline);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(Baseline::load(dir.path()).unwrap(), Baseline::default());
+
+        let mut baseline = Baseline::default();
+        baseline.findings.insert(finding("a.py", "eval(x)"));
+        baseline.save(dir.path()).unwrap();
+
+        assert_eq!(Baseline::load(dir.path()).unwrap(), baseline);
+    }
+}
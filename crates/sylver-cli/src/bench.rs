@@ -0,0 +1,301 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use sylver_core::{
+    core::files_spec::FileSpec,
+    land::{cmds::exec_rules, ruleset::StopCondition, sylva::Sylva},
+    specs::loader::SylverLoader,
+    state::SylverState,
+};
+
+use crate::{
+    check::build_check_state,
+    cli::BenchCmd,
+    shared::{load_sources, preprocess_sources, with_parser, SylvaOptions},
+};
+
+/// Timings of the projects of a configuration. Each timing is the best of the measured
+/// iterations.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub parsing: BTreeMap<String, ParseBench>,
+    /// Evaluation time of each rule (in milliseconds), keyed by `ruleset/rule`.
+    pub rules: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParseBench {
+    pub bytes: usize,
+    pub seconds: f64,
+}
+
+impl ParseBench {
+    pub fn throughput(&self) -> f64 {
+        self.bytes as f64 / 1_000_000.0 / self.seconds.max(f64::EPSILON)
+    }
+}
+
+/// Change of a metric between the baseline and the current run.
+#[derive(Debug, Clone, PartialEq)]
+struct Comparison {
+    name: String,
+    baseline: f64,
+    current: f64,
+    /// Slowdown in percent: a positive value is a regression.
+    slowdown: f64,
+}
+
+pub fn bench(state: Arc<SylverState>, loader: &SylverLoader, cmd: &BenchCmd) -> anyhow::Result<()> {
+    let config_path = match &state.locations.config_file {
+        Some(f) => f,
+        None => bail!("Missing configuration file"),
+    };
+
+    let iterations = cmd.iterations.max(1);
+    let report = BenchReport {
+        parsing: bench_parsing(&state, loader, config_path, iterations)?,
+        rules: bench_rules(&state, loader, config_path, iterations)?,
+    };
+
+    print_report(&report);
+
+    if let Some(path) = &cmd.save {
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Could not write benchmark: {}", path.display()))?;
+    }
+
+    if let Some(path) = &cmd.compare {
+        let baseline_content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read benchmark: {}", path.display()))?;
+        let baseline: BenchReport = serde_json::from_str(&baseline_content)
+            .with_context(|| format!("Invalid benchmark: {}", path.display()))?;
+
+        let comparisons = compare(&baseline, &report);
+        print_comparisons(&comparisons);
+
+        if let Some(max) = cmd.max_regression {
+            let regressions = comparisons.iter().filter(|c| c.slowdown > max).count();
+
+            if regressions > 0 {
+                bail!("{regressions} benchmark(s) regressed by more than {max}%");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn bench_parsing(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+    iterations: usize,
+) -> anyhow::Result<BTreeMap<String, ParseBench>> {
+    let config = loader.load_config(config_path)?;
+    let mut parsing = BTreeMap::new();
+
+    for (index, project) in config.projects().enumerate() {
+        let sources = load_sources(
+            state,
+            loader,
+            &FileSpec {
+                root: project.root.clone(),
+                include: project.include.clone(),
+                exclude: project.exclude.clone(),
+                encoding: project.encoding,
+                adapter: project.language.input_adapter(),
+            },
+        )?;
+        let sources = preprocess_sources(state, project.preprocess.as_ref(), sources)?;
+
+        let bytes = sources.iter().map(|s| s.src().len()).sum();
+        let options = SylvaOptions::for_project(state, project);
+
+        // Only the parsing is timed: the aspects of the language are never built, and the
+        // parser is prepared once for all the iterations.
+        let (best, _) = with_parser(loader, &project.language, &options, |parser, _| {
+            let mut best = Duration::MAX;
+
+            for iteration in 0..iterations {
+                state.cancel.check()?;
+
+                let sources = sources.clone();
+                let progress = state.logger.progress("Parsing");
+                let start = Instant::now();
+                let (_, skipped) = Sylva::build_stored(
+                    parser.clone(),
+                    None,
+                    sources,
+                    &options.limits,
+                    &state.cancel,
+                    progress.as_ref(),
+                )?;
+                best = best.min(start.elapsed());
+
+                // Every iteration skips the same files.
+                if iteration == 0 {
+                    for file in skipped {
+                        state.logger.warning(&file.to_string());
+                    }
+                }
+            }
+
+            Ok(best)
+        })?;
+
+        let name = format!("{}#{index}", project.root.as_deref().unwrap_or("."));
+        parsing.insert(
+            name,
+            ParseBench {
+                bytes,
+                seconds: best.as_secs_f64(),
+            },
+        );
+    }
+
+    Ok(parsing)
+}
+
+fn bench_rules(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+    iterations: usize,
+) -> anyhow::Result<BTreeMap<String, f64>> {
+    let land = build_check_state(state, loader, config_path)?.land;
+    let mut rules: BTreeMap<String, f64> = BTreeMap::new();
+
+    for _ in 0..iterations {
+        let execution = {
+            let progress = state.logger.progress("Evaluating rules");
+            exec_rules(
+                &land,
+                StopCondition::default(),
+                &state.cancel,
+                None,
+                None,
+                progress.as_ref(),
+            )?
+        };
+
+        for ((ruleset, rule), duration) in execution.durations {
+            let millis = duration.as_secs_f64() * 1000.0;
+            rules
+                .entry(format!("{}/{rule}", land.ruleset(ruleset).id()))
+                .and_modify(|best| *best = best.min(millis))
+                .or_insert(millis);
+        }
+    }
+
+    Ok(rules)
+}
+
+fn compare(baseline: &BenchReport, current: &BenchReport) -> Vec<Comparison> {
+    let parsing = current.parsing.iter().filter_map(|(name, bench)| {
+        let before = baseline.parsing.get(name)?.throughput();
+        let after = bench.throughput();
+
+        Some(Comparison {
+            name: format!("parse {name} (MB/s)"),
+            baseline: before,
+            current: after,
+            slowdown: percent_change(after, before),
+        })
+    });
+
+    let rules = current.rules.iter().filter_map(|(name, &after)| {
+        let before = *baseline.rules.get(name)?;
+
+        Some(Comparison {
+            name: format!("rule {name} (ms)"),
+            baseline: before,
+            current: after,
+            slowdown: percent_change(before, after),
+        })
+    });
+
+    parsing.chain(rules).collect()
+}
+
+/// Relative increase from `from` to `to`, in percent.
+fn percent_change(from: f64, to: f64) -> f64 {
+    if from <= 0.0 {
+        return 0.0;
+    }
+
+    (to - from) / from * 100.0
+}
+
+fn print_report(report: &BenchReport) {
+    for (name, bench) in &report.parsing {
+        println!(
+            "parse {name}: {:.2} MB/s ({} bytes in {:.3}s)",
+            bench.throughput(),
+            bench.bytes,
+            bench.seconds
+        );
+    }
+
+    for (name, millis) in &report.rules {
+        println!("rule {name}: {millis:.2}ms");
+    }
+}
+
+fn print_comparisons(comparisons: &[Comparison]) {
+    println!();
+
+    for c in comparisons {
+        println!(
+            "{}: {:.2} -> {:.2} ({:+.1}% slower)",
+            c.name, c.baseline, c.current, c.slowdown
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(seconds: f64, rules: &[(&str, f64)]) -> BenchReport {
+        BenchReport {
+            parsing: BTreeMap::from([(
+                "src#0".to_string(),
+                ParseBench {
+                    bytes: 2_000_000,
+                    seconds,
+                },
+            )]),
+            rules: rules
+                .iter()
+                .map(|(name, millis)| (name.to_string(), *millis))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn compare_with_baseline() {
+        let baseline = report(
+            1.0,
+            &[("rules.yaml/no-eval", 10.0), ("rules.yaml/removed", 1.0)],
+        );
+        let current = report(
+            2.0,
+            &[("rules.yaml/no-eval", 5.0), ("rules.yaml/added", 1.0)],
+        );
+
+        let comparisons = compare(&baseline, &current);
+
+        assert_eq!(comparisons.len(), 2);
+        assert_eq!(comparisons[0].baseline, 2.0);
+        assert_eq!(comparisons[0].current, 1.0);
+        assert_eq!(comparisons[0].slowdown, 100.0);
+        assert_eq!(comparisons[1].slowdown, -50.0);
+    }
+}
@@ -1,31 +1,62 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 
 use sylver_core::{
-    core::files_spec::FileSpec,
-    land::{builder::LandBuilder, ruleset::RuleSetId, Land, LandSpecId},
+    core::{
+        files_spec::{parse_file_list, FileSpec},
+        store::ContentStore,
+    },
+    land::{
+        builder::LandBuilder,
+        ruleset::{RuleSetId, StopCondition},
+        Land, LandSpecId,
+    },
     specs::{
         loader::SylverLoader,
-        stem::{location::StemLocation, project::ProjectLang},
+        stem::{
+            location::StemLocation,
+            project::{ProjectLang, ProjectStem, RuleRef},
+        },
+        trust::TrustPolicy,
     },
     state::SylverState,
+    util::fs::path_to_string,
 };
 
 use crate::{
+    baseline::{Baseline, BaselineFilter},
     cli::CheckCmd,
-    shared::{build_sylva, print_land_reports, run_land_rules},
+    fix::apply_fixes,
+    history::{HistoryEntry, HistoryStore},
+    index::update_index,
+    shared::{
+        build_sylva, check_rule_failures, evaluate_land_rules, load_sources, preprocess_sources,
+        run_land_rules, ExitPolicy, SylvaOptions,
+    },
+    triage::triage,
     upload::ReportUploader,
+    watch::watch_check,
 };
 
 pub struct CheckLandData {
     pub land: Land,
     pub langs: HashMap<LandSpecId, ProjectLang>,
     pub rulesets: HashMap<RuleSetId, StemLocation>,
+    /// Rules disabled by the project of each ruleset.
+    pub disabled_rules: HashMap<RuleSetId, Vec<RuleRef>>,
+    /// Trust policy of the project of each ruleset, used when the ruleset is reloaded.
+    pub policies: HashMap<RuleSetId, TrustPolicy>,
 }
 
 pub fn check(state: Arc<SylverState>, loader: &SylverLoader, cmd: &CheckCmd) -> anyhow::Result<()> {
     match &state.locations.config_file {
+        Some(f) if cmd.watch => watch_check(state.clone(), loader, f, cmd),
         Some(f) => run_check(state.clone(), loader, f, cmd),
         None => bail!("Missing configuration file"),
     }
@@ -37,47 +68,224 @@ fn run_check(
     config_path: &Path,
     cmd: &CheckCmd,
 ) -> anyhow::Result<()> {
-    let check_data = build_check_state(loader, config_path)?;
+    let files = cmd.files_from.as_deref().map(read_file_list).transpose()?;
+    let check_data = build_check_state_from(&state, loader, config_path, files.as_deref(), None)?;
 
-    print_land_reports(state.settings.color_output, &check_data.land)?;
-    let res = run_land_rules(state.settings.color_output, &check_data.land)?;
+    if cmd.update_index {
+        update_index(&state, &check_data.land)?;
+    }
 
-    if cmd.upload {
-        ReportUploader::new(loader, cmd, &check_data).upload(&res);
+    let baseline = load_baseline(&state, cmd)?;
+    let filter = baseline.as_ref().map(BaselineFilter::new);
+
+    if cmd.interactive {
+        let filter = filter.expect("the interactive mode uses the baseline");
+        let execution = evaluate_land_rules(
+            &state,
+            &check_data.land,
+            StopCondition::from(&cmd.stop),
+            None,
+            Some(&filter),
+        )?;
+        return triage(&state, &check_data.land, &filter, execution.results);
+    }
+
+    let execution = run_land_rules(
+        &state,
+        &check_data.land,
+        StopCondition::from(&cmd.stop),
+        &cmd.report,
+        None,
+        filter.as_ref(),
+    )?;
+
+    if cmd.fix {
+        apply_fixes(
+            &state,
+            &check_data.land,
+            &execution.results,
+            cmd.fix_strategy.into(),
+        )?;
+    }
+
+    if cmd.record_history {
+        HistoryStore::new(&state.locations.sylver_dir)
+            .append(&HistoryEntry::from_results(&check_data, &execution.results))?;
     }
 
-    if !res.is_empty() {
-        std::process::exit(1);
+    if cmd.upload {
+        ReportUploader::new(
+            loader,
+            cmd,
+            &check_data,
+            state.settings.pos_encoder,
+            &state.settings.path_map,
+        )
+        .upload(&execution.results);
     }
 
+    let policy = match &filter {
+        Some(filter) if cmd.fail_on_increase => ExitPolicy::Increased {
+            fixed: filter.fixed(),
+        },
+        _ => ExitPolicy::from(&cmd.exit_policy),
+    };
+
+    check_rule_failures(cmd.exit_policy.strict_rules, &execution.failures)?;
+    policy.exit_if_failed(&check_data.land, &execution.results);
+
     Ok(())
 }
 
-fn build_check_state(loader: &SylverLoader, config_path: &Path) -> anyhow::Result<CheckLandData> {
-    let config = loader.load_config(config_path)?;
+/// Load the baseline if the findings it accepts must be left out of the results.
+pub fn load_baseline(state: &SylverState, cmd: &CheckCmd) -> anyhow::Result<Option<Baseline>> {
+    (cmd.baseline || cmd.interactive || cmd.fail_on_increase)
+        .then(|| Baseline::load(&state.locations.sylver_dir))
+        .transpose()
+}
+
+pub fn build_check_state(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+) -> anyhow::Result<CheckLandData> {
+    build_check_state_from(state, loader, config_path, None, None)
+}
+
+/// Same as `build_check_state`, reusing the trees of the store for the unchanged contents.
+pub fn build_stored_check_state(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+    store: &ContentStore,
+) -> anyhow::Result<CheckLandData> {
+    build_check_state_from(state, loader, config_path, None, Some(store))
+}
+
+/// Same as `build_stored_check_state`, with the relative paths of the configuration resolved
+/// against `root` instead of the current directory.
+pub fn build_rooted_check_state(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+    root: &Path,
+    store: &ContentStore,
+) -> anyhow::Result<CheckLandData> {
+    build_projects_state(state, loader, config_path, None, Some(store), Some(root))
+}
+
+/// Build the land of the projects. If a list of files is given, the projects only contain the
+/// listed files that match their patterns.
+pub fn build_check_state_from(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+    files: Option<&[PathBuf]>,
+    store: Option<&ContentStore>,
+) -> anyhow::Result<CheckLandData> {
+    build_projects_state(state, loader, config_path, files, store, None)
+}
+
+fn build_projects_state(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+    files: Option<&[PathBuf]>,
+    store: Option<&ContentStore>,
+    root: Option<&Path>,
+) -> anyhow::Result<CheckLandData> {
+    let mut config = loader.load_config(config_path)?;
+
+    if let Some(root) = root {
+        config
+            .projects_mut()
+            .for_each(|project| rebase_project(project, root));
+    }
+
     let mut builder = LandBuilder::new();
     let mut sylva_langs = HashMap::new();
     let mut rulesets = HashMap::new();
+    let mut disabled_rules = HashMap::new();
+    let mut policies = HashMap::new();
 
     for project in config.projects() {
-        let sources = loader.load_file_spec(&FileSpec {
+        state.cancel.check()?;
+
+        let file_spec = FileSpec {
             root: project.root.clone(),
             include: project.include.clone(),
             exclude: project.exclude.clone(),
-        })?;
+            encoding: project.encoding,
+            adapter: project.language.input_adapter(),
+        };
 
-        let sylva = build_sylva(loader, &mut builder, &project.language, sources)?;
+        let sources = match files {
+            Some(files) => file_spec.load_listed(files)?,
+            None => load_sources(state, loader, &file_spec)?,
+        };
+        let sources = preprocess_sources(state, project.preprocess.as_ref(), sources)?;
 
+        let sylva = build_sylva(
+            state,
+            loader,
+            &mut builder,
+            &project.language,
+            &SylvaOptions::for_project(state, project).with_store(store),
+            sources,
+        )?;
+
+        builder.set_sylva_root(sylva, project.root.as_deref().unwrap_or(".").into());
+        if let Some(tokens) = project.min_clone_tokens {
+            builder.set_min_clone_tokens(sylva, tokens);
+        }
         sylva_langs.insert(sylva, project.language.clone());
 
+        let policy = TrustPolicy::new(project.trust.clone(), state.settings.require_signed);
+
         for ruleset in &project.rulesets {
-            let rule_set_id = builder.add_ruleset(sylva, &loader.load_ruleset(ruleset)?)?;
+            let mut stem = loader.load_ruleset(ruleset, &policy)?;
+            stem.disable_rules(&project.disabled_rules);
+            let rule_set_id = builder.add_ruleset(sylva, &stem)?;
             rulesets.insert(rule_set_id, ruleset.clone());
+            disabled_rules.insert(rule_set_id, project.disabled_rules.clone());
+            policies.insert(rule_set_id, policy.clone());
         }
     }
 
     let land = builder.build();
 
+    warn_renamed_kinds(
+        state,
+        rulesets
+            .iter()
+            .map(|(id, location)| (location.to_string(), land.ruleset(*id))),
+    );
+
+    // A ruleset shared by several projects is only reported once.
+    let deprecated_rules: BTreeSet<(String, &str, Option<&str>)> = rulesets
+        .iter()
+        .flat_map(|(ruleset_id, location)| {
+            land.ruleset(*ruleset_id)
+                .deprecated_rules()
+                .map(move |(rule, replacement)| (location.to_string(), rule, replacement))
+        })
+        .collect();
+
+    for (location, rule, replacement) in deprecated_rules {
+        let guidance = match replacement {
+            Some(replacement) => format!(
+                "use {replacement} instead. Run `sylver migrate-config` to update the configuration"
+            ),
+            None => {
+                "add it to the `disabled_rules` of the project to stop evaluating it".to_string()
+            }
+        };
+
+        state.logger.warning(&format!(
+            "{location}: rule {rule} is deprecated, {guidance}"
+        ));
+    }
+
     let langs = sylva_langs
         .into_iter()
         .map(|(sylva, lang)| (land.sylva_spec_id(sylva), lang))
@@ -87,5 +295,45 @@ fn build_check_state(loader: &SylverLoader, config_path: &Path) -> anyhow::Resul
         land,
         langs,
         rulesets,
+        disabled_rules,
+        policies,
     })
 }
+
+/// Resolve the relative paths of a project (root, local rulesets and language spec) against the
+/// given directory, in which its preprocessor also runs.
+fn rebase_project(project: &mut ProjectStem, root: &Path) {
+    let project_root = root.join(project.root.as_deref().unwrap_or(""));
+    project.root = Some(path_to_string(&project_root));
+
+    let language = match &mut project.language {
+        ProjectLang::Custom(location) => Some(location),
+        _ => None,
+    };
+
+    for location in project.rulesets.iter_mut().chain(language) {
+        if let StemLocation::Local(path) = location {
+            *path = root.join(&*path);
+        }
+    }
+
+    if let Some(preprocessor) = &mut project.preprocess {
+        preprocessor.dir = Some(root.to_path_buf());
+    }
+}
+
+/// Read a NUL-delimited list of files from the given file, or from stdin if the path is `-`.
+fn read_file_list(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut list = vec![];
+
+    if path == Path::new("-") {
+        std::io::stdin()
+            .read_to_end(&mut list)
+            .context("Could not read the file list from stdin")?;
+    } else {
+        list = std::fs::read(path)
+            .with_context(|| format!("Could not read file list: {}", path.display()))?;
+    }
+
+    Ok(parse_file_list(&list))
+}
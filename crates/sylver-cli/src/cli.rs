@@ -1,8 +1,12 @@
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
-use clap::{ArgGroup, Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 
-use sylver_core::specs::stem::project::ProjectLang;
+use sylver_core::{
+    core::{pos::ColumnEncoding, source::SourceEncoding},
+    specs::stem::project::ProjectLang,
+    util::{edits::FixStrategy, fs::PathPrefixMapping},
+};
 
 #[derive(Parser, Debug)]
 #[clap(version)]
@@ -19,6 +23,81 @@ pub struct Cli {
     /// Override server URL.
     #[clap(long, default_value_t = String::from("https://api.sylver.dev"))]
     pub server_url: String,
+
+    /// Unit used to count the columns of the reported positions.
+    #[clap(long, global = true, value_enum, default_value_t = ColumnEncodingArg::Utf8)]
+    pub column_encoding: ColumnEncodingArg,
+
+    /// Width of a tab character when computing columns.
+    #[clap(long, global = true)]
+    pub tab_width: Option<usize>,
+
+    /// Abort the command if it hasn't completed after this number of seconds.
+    #[clap(long, global = true)]
+    pub timeout: Option<u64>,
+
+    /// Report messages and progress as JSON lines on stderr.
+    #[clap(long, global = true)]
+    pub log_json: bool,
+
+    /// Report the slowest rules and the hit rate of the memoized values.
+    #[clap(long, global = true)]
+    pub profile: bool,
+
+    /// Reject the downloaded rulesets that are not signed by one of the trusted keys of the
+    /// project.
+    #[clap(long, global = true)]
+    pub require_signed: bool,
+
+    /// Replace the FROM prefix of the reported paths with TO. Can be repeated, the last matching
+    /// mapping is applied.
+    #[clap(long, global = true, value_name = "FROM=TO")]
+    pub path_prefix_map: Vec<PathPrefixMapping>,
+
+    /// Run the preprocess commands of the projects. Without it, analyzing a project with a
+    /// preprocess command fails, so that a configuration can't run commands unnoticed.
+    #[clap(long, global = true)]
+    pub allow_preprocess: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ColumnEncodingArg {
+    Utf8,
+    Utf16,
+    Char,
+}
+
+impl From<ColumnEncodingArg> for ColumnEncoding {
+    fn from(arg: ColumnEncodingArg) -> Self {
+        match arg {
+            ColumnEncodingArg::Utf8 => ColumnEncoding::Utf8,
+            ColumnEncodingArg::Utf16 => ColumnEncoding::Utf16,
+            ColumnEncodingArg::Char => ColumnEncoding::Char,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SourceEncodingArg {
+    Auto,
+    Utf8,
+    Latin1,
+    Utf16le,
+    Utf16be,
+    ShiftJis,
+}
+
+impl From<SourceEncodingArg> for SourceEncoding {
+    fn from(arg: SourceEncodingArg) -> Self {
+        match arg {
+            SourceEncodingArg::Auto => SourceEncoding::Auto,
+            SourceEncodingArg::Utf8 => SourceEncoding::Utf8,
+            SourceEncodingArg::Latin1 => SourceEncoding::Latin1,
+            SourceEncodingArg::Utf16le => SourceEncoding::Utf16Le,
+            SourceEncodingArg::Utf16be => SourceEncoding::Utf16Be,
+            SourceEncodingArg::ShiftJis => SourceEncoding::ShiftJis,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -29,10 +108,45 @@ pub enum Commands {
     Check(CheckCmd),
     /// Parse a single file.
     Parse(ParseCmd),
+    /// Format files using the format templates of their language spec.
+    Format(FormatCmd),
+    /// Restore the files written by the last run of `sylver format` or `sylver check --fix`.
+    Rollback,
     /// Start a repl session, or run a given query against a set of files.
     Query(QueryCmd),
+    /// Print the nodes matching a query pattern, grep-style. Exits with status 1 if no node
+    /// matches.
+    Grep(GrepCmd),
     /// Install and run rulesets.
     Ruleset(RulesetCmd),
+    /// Show how the number of findings evolved across the recorded runs.
+    Trends(TrendsCmd),
+    /// Inspect language specs.
+    Spec(SpecCmd),
+    /// Report the duplicated code regions of the configured projects.
+    Clones(ClonesCmd),
+    /// Export the semantic analyses of the configured projects.
+    Export(ExportCmd),
+    /// Print the definitions of the symbol referenced at a position, from the symbol index.
+    Definition(SymbolPositionCmd),
+    /// Print the references to the symbol declared or referenced at a position, from the symbol
+    /// index.
+    References(SymbolPositionCmd),
+    /// Serve analyses over JSON-RPC, to run sylver as a shared service.
+    Serve(ServeCmd),
+    /// Run a language server over the standard input and output, to report the findings of the
+    /// configured projects in an editor.
+    Lsp(LspCmd),
+    /// Measure the parsing throughput and the evaluation time of the rules of the configured
+    /// projects.
+    Bench(BenchCmd),
+    /// Report the findings introduced and fixed between two git revisions.
+    Compare(CompareCmd),
+    /// Check the given files, for use as a pre-commit hook. Files without findings are cached and
+    /// skipped until they change.
+    Hook(HookCmd),
+    /// Disable the deprecated rules of the configured rulesets in favor of their replacements.
+    MigrateConfig(MigrateConfigCmd),
 }
 
 #[derive(Parser, Debug)]
@@ -40,6 +154,11 @@ pub struct InitCmd {
     /// Override the default configuration path
     #[clap(long, default_value_t = String::from("sylver.yaml"))]
     pub config_path: String,
+
+    /// Don't ask any question, accept the default answers. This is implied when the standard
+    /// input is not a terminal.
+    #[clap(short, long)]
+    pub yes: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -59,6 +178,183 @@ pub struct CheckCmd {
     /// API token for the current repository
     #[clap(long)]
     pub token: Option<String>,
+
+    /// Record the number of findings per rule in the local history.
+    #[clap(long)]
+    pub record_history: bool,
+
+    /// Update the symbol index with the files that changed since the last update.
+    #[clap(long)]
+    pub update_index: bool,
+
+    /// Run the rules again whenever the configuration, a ruleset, a language spec or an analyzed
+    /// file changes.
+    #[clap(long, conflicts_with_all = ["upload", "record_history", "update_index"])]
+    pub watch: bool,
+
+    /// Browse the findings from a prompt, filtering them and adding the accepted ones to the
+    /// baseline.
+    #[clap(long, conflicts_with_all = ["upload", "record_history", "watch"])]
+    pub interactive: bool,
+
+    /// Don't report the findings accepted in the baseline. The interactive mode always leaves
+    /// them out.
+    #[clap(long)]
+    pub baseline: bool,
+
+    /// Only fail if there are more findings than in the baseline, whose findings are then left
+    /// out of the results as with `--baseline`.
+    #[clap(long, conflicts_with_all = ["exit_zero", "fail_on", "interactive"])]
+    pub fail_on_increase: bool,
+
+    /// Only analyze the files of this NUL-delimited list (`-` to read it from stdin), instead of
+    /// expanding the include patterns of the projects.
+    #[clap(long, value_name = "FILE", conflicts_with = "watch")]
+    pub files_from: Option<PathBuf>,
+
+    /// Apply the fixes of the rules that matched. The written files can be restored with
+    /// `sylver rollback`.
+    #[clap(long, conflicts_with_all = ["watch", "interactive"])]
+    pub fix: bool,
+
+    /// Resolution of the overlapping fixes.
+    #[clap(long, value_enum, default_value_t = FixStrategyArg::First, requires = "fix")]
+    pub fix_strategy: FixStrategyArg,
+
+    #[clap(flatten)]
+    pub report: ReportArgs,
+
+    #[clap(flatten)]
+    pub exit_policy: ExitPolicyArgs,
+
+    #[clap(flatten)]
+    pub stop: StopArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReportArgs {
+    /// Output format of the findings.
+    #[clap(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+
+    /// Write the reports other than text to the given file instead of stdout.
+    #[clap(long, value_parser)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FixStrategyArg {
+    /// Apply the fix proposed first.
+    First,
+    /// Apply the fix of the rule of the most severe category.
+    Priority,
+    /// Apply none of the overlapping fixes.
+    SkipConflicts,
+}
+
+impl From<FixStrategyArg> for FixStrategy {
+    fn from(arg: FixStrategyArg) -> Self {
+        match arg {
+            FixStrategyArg::First => FixStrategy::First,
+            FixStrategyArg::Priority => FixStrategy::Priority,
+            FixStrategyArg::SkipConflicts => FixStrategy::SkipConflicts,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    /// Findings and analysis statistics, as a single JSON document.
+    Json,
+    /// TeamCity inspection service messages.
+    Teamcity,
+    /// SARIF 2.1.0 log, with the CWE and OWASP taxonomies of the rules.
+    Sarif,
+    /// Standalone HTML page, with the findings of each CWE and OWASP classification.
+    Html,
+    /// JUnit XML document, with one failed test case per finding.
+    Junit,
+    /// Number of findings, as a shields.io endpoint badge.
+    Badge,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct StopArgs {
+    /// Stop at the first error-severity finding.
+    #[clap(long)]
+    pub fail_fast: bool,
+
+    /// Stop once this number of findings has been reached.
+    #[clap(long)]
+    pub max_findings: Option<NonZeroUsize>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExitPolicyArgs {
+    /// Always exit with a success status, even if rules are violated.
+    #[clap(long, conflicts_with = "fail_on")]
+    pub exit_zero: bool,
+
+    /// Minimum severity of a violation for the command to fail.
+    #[clap(long, value_enum, default_value_t = FailOn::Help)]
+    pub fail_on: FailOn,
+
+    /// Fail if a rule cannot be evaluated, instead of only reporting the error.
+    #[clap(long)]
+    pub strict_rules: bool,
+}
+
+/// Severity threshold used to decide the exit code.
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FailOn {
+    #[value(alias = "style")]
+    Help,
+    #[value(aliases = ["smell", "deprecated"])]
+    Warning,
+    Bug,
+    Error,
+}
+
+#[derive(Parser, Debug)]
+pub struct BenchCmd {
+    /// Override the default config file location
+    #[clap(short, long, value_parser)]
+    pub config: Option<PathBuf>,
+
+    /// Number of measured runs, the best one is kept.
+    #[clap(long, default_value_t = 3)]
+    pub iterations: usize,
+
+    /// Save the timings to a file, to use it as a baseline.
+    #[clap(long, value_parser)]
+    pub save: Option<PathBuf>,
+
+    /// Compare the timings with a saved baseline.
+    #[clap(long, value_parser)]
+    pub compare: Option<PathBuf>,
+
+    /// Fail if a timing is slower than the baseline by more than the given percentage.
+    #[clap(long, requires = "compare")]
+    pub max_regression: Option<f64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ServeCmd {
+    /// Port on which the requests are received, on the loopback interface.
+    #[clap(short, long)]
+    pub port: u16,
+
+    /// Secret that clients must send in an `authenticate` request before any other request.
+    #[clap(long, env = "SYLVER_SERVE_TOKEN", hide_env_values = true)]
+    pub token: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct LspCmd {
+    /// Override the default config file location.
+    #[clap(short, long, value_parser)]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -76,6 +372,25 @@ pub struct ParseCmd {
     pub rule: Option<String>,
 }
 
+#[derive(Parser, Debug)]
+pub struct FormatCmd {
+    /// Path to the language spec.
+    #[clap(short, long, value_parser)]
+    pub spec: PathBuf,
+
+    /// Paths of the files to format.
+    #[clap(required = true, value_parser)]
+    pub files: Vec<PathBuf>,
+
+    /// Override the default starting rule.
+    #[clap(short, long)]
+    pub rule: Option<String>,
+
+    /// Don't write the files, fail if some of them are not formatted.
+    #[clap(long)]
+    pub check: bool,
+}
+
 #[derive(Parser, Debug)]
 pub struct QueryCmd {
     /// Path to the language spec.
@@ -90,11 +405,37 @@ pub struct QueryCmd {
     #[clap(long, num_args = 1..)]
     pub exclude: Vec<String>,
 
+    /// Encoding of the files (detected from the byte order mark by default).
+    #[clap(long, value_enum, default_value_t = SourceEncodingArg::Auto)]
+    pub encoding: SourceEncodingArg,
+
     /// Query to execute.
     #[clap(short, long)]
     pub query: Option<String>,
 }
 
+#[derive(Parser, Debug)]
+pub struct GrepCmd {
+    /// Node pattern to match, such as `FunctionDef(name: /^test_/)`.
+    pub pattern: String,
+
+    /// Glob patterns of the files to search.
+    #[clap(required = true, num_args = 1..)]
+    pub files: Vec<String>,
+
+    /// Language of the files.
+    #[clap(short, long, value_parser)]
+    pub language: ProjectLang,
+
+    /// Glob patterns of the files to exlude
+    #[clap(long, num_args = 1..)]
+    pub exclude: Vec<String>,
+
+    /// Encoding of the files (detected from the byte order mark by default).
+    #[clap(long, value_enum, default_value_t = SourceEncodingArg::Auto)]
+    pub encoding: SourceEncodingArg,
+}
+
 #[derive(Parser, Debug)]
 pub struct RulesetCmd {
     #[clap(subcommand)]
@@ -120,4 +461,232 @@ pub struct RulesetRun {
     /// Glob patterns of the files to exlude
     #[clap(long, num_args = 1..)]
     pub exclude: Vec<String>,
+
+    /// Encoding of the files (detected from the byte order mark by default).
+    #[clap(long, value_enum, default_value_t = SourceEncodingArg::Auto)]
+    pub encoding: SourceEncodingArg,
+
+    #[clap(flatten)]
+    pub report: ReportArgs,
+
+    #[clap(flatten)]
+    pub exit_policy: ExitPolicyArgs,
+
+    #[clap(flatten)]
+    pub stop: StopArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct CompareCmd {
+    /// Override the default config file location
+    #[clap(short, long, value_parser)]
+    pub config: Option<PathBuf>,
+
+    /// Revision used as the reference.
+    #[clap(long)]
+    pub from: String,
+
+    /// Revision whose findings are compared with the reference. Uncommitted changes are ignored.
+    #[clap(long, default_value_t = String::from("HEAD"))]
+    pub to: String,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = CompareFormat::Text)]
+    pub format: CompareFormat,
+
+    /// Fail if findings were introduced.
+    #[clap(long)]
+    pub fail_on_new: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct HookCmd {
+    /// Override the default config file location
+    #[clap(short, long, value_parser)]
+    pub config: Option<PathBuf>,
+
+    /// Files to check, as passed by pre-commit.
+    pub files: Vec<PathBuf>,
+
+    #[clap(flatten)]
+    pub exit_policy: ExitPolicyArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct MigrateConfigCmd {
+    /// Override the default config file location
+    #[clap(short, long, value_parser)]
+    pub config: Option<PathBuf>,
+
+    /// Print the migrations without rewriting the configuration file.
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CompareFormat {
+    Text,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+pub struct TrendsCmd {
+    /// Number of recorded runs to consider.
+    #[clap(long, default_value_t = 10)]
+    pub last: usize,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = TrendsFormat::Text)]
+    pub format: TrendsFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TrendsFormat {
+    Text,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+pub struct ClonesCmd {
+    /// Override the default config file location
+    #[clap(short, long, value_parser)]
+    pub config: Option<PathBuf>,
+
+    /// Minimum size (in tokens) of the reported regions, instead of the `min_clone_tokens` of
+    /// each project (50 by default).
+    #[clap(long)]
+    pub min_tokens: Option<usize>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportCmd {
+    /// Override the default config file location
+    #[clap(short, long, value_parser)]
+    pub config: Option<PathBuf>,
+
+    /// Analysis to export.
+    #[clap(long, value_enum)]
+    pub artifact: ExportArtifact,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = ExportFormat::Json)]
+    pub format: ExportFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExportArtifact {
+    /// Scopes, with their declarations and resolved references.
+    ScopeGraph,
+    /// Calls between declarations.
+    CallGraph,
+    /// Declarations and their references.
+    Symbols,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExportFormat {
+    Json,
+}
+
+#[derive(Parser, Debug)]
+pub struct SymbolPositionCmd {
+    /// File containing the symbol.
+    #[clap(short, long, value_parser)]
+    pub file: PathBuf,
+
+    /// Line of the symbol (starting at 1).
+    #[clap(long)]
+    pub line: usize,
+
+    /// Column of the symbol (starting at 1).
+    #[clap(long)]
+    pub column: usize,
+}
+
+#[derive(Parser, Debug)]
+pub struct SpecCmd {
+    #[clap(subcommand)]
+    pub command: SpecCmds,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SpecCmds {
+    /// Describe the node kinds, fields and inheritance of a language spec.
+    Describe(SpecDescribe),
+    /// Generate type stubs for the node kinds of a language spec.
+    Stubs(SpecStubs),
+    /// Check that random trees printed from the rules of a spec parse back to the same trees.
+    Roundtrip(SpecRoundtrip),
+    /// Report the unused and unreachable declarations of a language spec.
+    Lint(SpecLint),
+}
+
+#[derive(Parser, Debug)]
+pub struct SpecDescribe {
+    /// Builtin language name or path to the language spec.
+    #[clap(short, long, value_parser)]
+    pub language: ProjectLang,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = SpecFormat::Text)]
+    pub format: SpecFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SpecFormat {
+    Text,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+pub struct SpecStubs {
+    /// Builtin language name or path to the language spec.
+    #[clap(short, long, value_parser)]
+    pub language: ProjectLang,
+
+    /// Scripting language of the stubs.
+    #[clap(long, value_enum, default_value_t = StubsLang::Python)]
+    pub lang: StubsLang,
+
+    /// Write the stubs to the given file instead of stdout.
+    #[clap(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SpecRoundtrip {
+    /// Path to the language spec.
+    #[clap(short, long, value_parser)]
+    pub language: ProjectLang,
+
+    /// Rule from which the trees are generated.
+    #[clap(long, default_value = "main")]
+    pub rule: String,
+
+    /// Number of generated trees.
+    #[clap(long, default_value_t = 100)]
+    pub iterations: usize,
+
+    /// Depth after which the generated trees are completed as quickly as possible.
+    #[clap(long, default_value_t = 8)]
+    pub max_depth: usize,
+
+    /// Seed of the generator, to reproduce a previous run.
+    #[clap(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SpecLint {
+    /// Path to the language spec.
+    pub spec: PathBuf,
+
+    /// Rule from which the parsing starts.
+    #[clap(long, default_value = "main")]
+    pub rule: String,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StubsLang {
+    Python,
 }
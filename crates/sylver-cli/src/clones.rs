@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use anyhow::bail;
+
+use sylver_core::{
+    land::{clones::SylvaClones, Land},
+    query::SylvaNode,
+    specs::loader::SylverLoader,
+    state::SylverState,
+    tree::info::{raw::RawTreeInfo, TreeInfo},
+    util::fs::{path_to_string, PathPrefixMap},
+};
+
+use crate::{check::build_check_state, cli::ClonesCmd};
+
+pub fn clones(
+    state: Arc<SylverState>,
+    loader: &SylverLoader,
+    cmd: &ClonesCmd,
+) -> anyhow::Result<()> {
+    let config_path = match &state.locations.config_file {
+        Some(f) => f,
+        None => bail!("Missing configuration file"),
+    };
+
+    let land = build_check_state(&state, loader, config_path)?.land;
+
+    for sylva in land.sylvae() {
+        state.cancel.check()?;
+
+        let min_tokens = cmd
+            .min_tokens
+            .unwrap_or_else(|| land.min_clone_tokens(sylva));
+        let clones = SylvaClones::detect(&land, sylva, min_tokens);
+
+        for group in clones.groups() {
+            println!(
+                "Duplicated region ({} tokens, {} occurrences):",
+                group.tokens,
+                group.nodes.len()
+            );
+
+            for &node in &group.nodes {
+                println!(
+                    "  {}",
+                    render_location(&land, &state.settings.path_map, node)
+                );
+            }
+
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+fn render_location(land: &Land, path_map: &PathPrefixMap, node: SylvaNode) -> String {
+    let tree = land.sylva_node_tree(node);
+    let info = RawTreeInfo::new(tree, &land.sylva_spec(node.sylva).syntax);
+    let (start, end) = info.node_pos(node.node).line_range();
+
+    format!(
+        "{}:{start}-{end}",
+        path_to_string(&path_map.remap(tree.source.path()))
+    )
+}
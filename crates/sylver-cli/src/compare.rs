@@ -0,0 +1,234 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+use temp_dir::TempDir;
+
+use sylver_core::{
+    core::store::ContentStore,
+    land::ruleset::StopCondition,
+    specs::loader::SylverLoader,
+    state::SylverState,
+    util::git::{GitClient, Worktree},
+};
+
+use crate::{
+    baseline::BaselineFinding,
+    check::build_rooted_check_state,
+    cli::{CompareCmd, CompareFormat},
+    report::JsonFinding,
+    shared::evaluate_land_rules,
+};
+
+/// Findings introduced and fixed between two revisions.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FindingsDiff {
+    pub from: String,
+    pub to: String,
+    /// Findings of `to` that are not reported at `from`.
+    pub introduced: Vec<JsonFinding>,
+    /// Findings of `from` that are no longer reported at `to`.
+    pub fixed: Vec<JsonFinding>,
+}
+
+/// Location of the configuration file and of the working directory, relative to the root of the
+/// repository, so that they can be found in the worktrees.
+struct RepoPaths {
+    config: PathBuf,
+    cwd: PathBuf,
+}
+
+pub fn compare(
+    state: Arc<SylverState>,
+    loader: &SylverLoader,
+    cmd: &CompareCmd,
+) -> anyhow::Result<()> {
+    let config_path = match &state.locations.config_file {
+        Some(f) => f,
+        None => bail!("Missing configuration file"),
+    };
+
+    let paths = repo_paths(config_path)?;
+    // Most files are identical in both revisions: their trees are only built once.
+    let store = ContentStore::default();
+
+    let from = revision_findings(&state, loader, &store, &paths, &cmd.from)?;
+    let to = revision_findings(&state, loader, &store, &paths, &cmd.to)?;
+    let (introduced, fixed) = diff_findings(from, to);
+
+    let diff = FindingsDiff {
+        from: cmd.from.clone(),
+        to: cmd.to.clone(),
+        introduced,
+        fixed,
+    };
+
+    match cmd.format {
+        CompareFormat::Text => print!("{}", render_diff(&diff)),
+        CompareFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+    }
+
+    if cmd.fail_on_new && !diff.introduced.is_empty() {
+        bail!(
+            "{} finding(s) introduced since {}",
+            diff.introduced.len(),
+            diff.from
+        );
+    }
+
+    Ok(())
+}
+
+fn repo_paths(config_path: &Path) -> anyhow::Result<RepoPaths> {
+    let toplevel = GitClient::toplevel().context("Not in a git repository")?;
+    let toplevel = toplevel.canonicalize()?;
+    let cwd = std::env::current_dir()?.canonicalize()?;
+    let config = cwd.join(config_path).canonicalize()?;
+
+    Ok(RepoPaths {
+        config: config
+            .strip_prefix(&toplevel)
+            .context("The configuration file is outside of the git repository")?
+            .to_path_buf(),
+        cwd: cwd
+            .strip_prefix(&toplevel)
+            .context("The current directory is outside of the git repository")?
+            .to_path_buf(),
+    })
+}
+
+/// Run the configured rulesets on a worktree of the given revision.
+fn revision_findings(
+    state: &SylverState,
+    loader: &SylverLoader,
+    store: &ContentStore,
+    paths: &RepoPaths,
+    rev: &str,
+) -> anyhow::Result<Vec<(BaselineFinding, JsonFinding)>> {
+    // Dropped after the worktree, which is checked out inside.
+    let worktree_dir = TempDir::new().context("Could not create a temporary directory")?;
+    let worktree_path = worktree_dir.path().join("worktree");
+
+    let worktree = {
+        let _checkout = state.logger.scoped(
+            &format!("Checking out {rev}"),
+            Some(&format!("Checked out {rev}")),
+        );
+        Worktree::add(worktree_path, rev)
+            .with_context(|| format!("Could not check out revision {rev}"))?
+    };
+
+    let root = worktree.path().join(&paths.cwd);
+    let config = worktree.path().join(&paths.config);
+    let land = build_rooted_check_state(state, loader, &config, &root, store)?.land;
+    let execution = evaluate_land_rules(state, &land, StopCondition::default(), None, None)?;
+
+    Ok(execution
+        .results
+        .iter()
+        .map(|r| {
+            let mut finding = BaselineFinding::new(&land, r);
+            let mut json = JsonFinding::new(&state.settings.pos_encoder, &land, r);
+            finding.file = relative_file(&finding.file, &root);
+            json.file = relative_file(&json.file, &root);
+            json.file = state.settings.path_map.remap_str(&json.file);
+            (finding, json)
+        })
+        .collect())
+}
+
+/// Path of a file of a worktree, relative to the directory matching the current one if it is
+/// absolute.
+fn relative_file(file: &str, worktree: &Path) -> String {
+    match Path::new(file).strip_prefix(worktree) {
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => file.to_string(),
+    }
+}
+
+/// Return the values of `to` whose key doesn't appear in `from` and the values of `from` whose key
+/// doesn't appear in `to`. Keys are matched as a multiset: a key reported twice in `to` and once in
+/// `from` is introduced once.
+fn diff_findings<K: Ord, T>(from: Vec<(K, T)>, to: Vec<(K, T)>) -> (Vec<T>, Vec<T>) {
+    let mut remaining: BTreeMap<K, Vec<T>> = BTreeMap::new();
+
+    for (key, value) in from {
+        remaining.entry(key).or_default().push(value);
+    }
+
+    let mut introduced = vec![];
+
+    for (key, value) in to {
+        if remaining.get_mut(&key).and_then(Vec::pop).is_none() {
+            introduced.push(value);
+        }
+    }
+
+    let fixed = remaining.into_values().flatten().collect();
+
+    (introduced, fixed)
+}
+
+fn render_diff(diff: &FindingsDiff) -> String {
+    let mut rendered = format!(
+        "{}..{}: {} introduced, {} fixed\n",
+        diff.from,
+        diff.to,
+        diff.introduced.len(),
+        diff.fixed.len()
+    );
+
+    let findings = diff
+        .introduced
+        .iter()
+        .map(|f| ('+', f))
+        .chain(diff.fixed.iter().map(|f| ('-', f)));
+
+    for (sign, f) in findings {
+        rendered.push_str(&format!(
+            "{sign} {}:{}:{} [{}/{}] {}\n",
+            f.file, f.position.start_line, f.position.start_col, f.ruleset, f.rule, f.message
+        ));
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_introduced_and_fixed() {
+        let from = vec![
+            ("a.py:eval(x)", 1),
+            ("a.py:eval(y)", 2),
+            ("b.py:exec(z)", 3),
+        ];
+        let to = vec![
+            ("a.py:eval(x)", 4),
+            ("a.py:eval(x)", 5),
+            ("b.py:exec(z)", 6),
+        ];
+
+        let (introduced, fixed) = diff_findings(from, to);
+
+        assert_eq!(introduced, vec![5]);
+        assert_eq!(fixed, vec![2]);
+    }
+
+    #[test]
+    fn absolute_worktree_files() {
+        let worktree = Path::new("/tmp/sylver-compare-1-HEAD");
+
+        assert_eq!(
+            relative_file("/tmp/sylver-compare-1-HEAD/src/main.py", worktree),
+            "src/main.py"
+        );
+        assert_eq!(relative_file("src/main.py", worktree), "src/main.py");
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::bail;
+use serde::Serialize;
+
+use sylver_core::{
+    core::pos::PosEncoder,
+    dashboard::api::Position,
+    land::{
+        passes::{run_passes, AnalysisPass},
+        sylva::SylvaId,
+        Land,
+    },
+    query::SylvaNode,
+    semantic::{calls::sylva_calls, names::SGraph},
+    specs::loader::SylverLoader,
+    state::SylverState,
+    tree::info::{raw::RawTreeInfo, TreeInfo},
+    util::fs::path_to_string,
+};
+
+use crate::{
+    check::build_check_state,
+    cli::{ExportArtifact, ExportCmd, ExportFormat},
+    report::encode_position,
+};
+
+/// Scope graph of a project.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonScopeGraph {
+    pub scopes: Vec<JsonScope>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonScope {
+    pub id: usize,
+    /// Scopes in which the names that are not declared in this scope are looked up.
+    pub parents: Vec<usize>,
+    pub declarations: Vec<JsonName>,
+    pub references: Vec<JsonReference>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonName {
+    pub name: String,
+    #[serde(flatten)]
+    pub location: JsonLocation,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonReference {
+    pub name: String,
+    #[serde(flatten)]
+    pub location: JsonLocation,
+    /// Declarations the reference resolves to.
+    pub declarations: Vec<JsonLocation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonSymbol {
+    pub name: String,
+    /// Kind of the declaration node.
+    pub kind: String,
+    #[serde(flatten)]
+    pub location: JsonLocation,
+    pub references: Vec<JsonLocation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonCall {
+    /// Innermost declaration containing the call, `None` for top-level calls.
+    pub caller: Option<JsonName>,
+    pub callee: JsonName,
+    #[serde(flatten)]
+    pub location: JsonLocation,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonLocation {
+    pub file: String,
+    pub position: Position,
+    /// Notebook cell of the node, for notebook sources.
+    pub cell: Option<usize>,
+}
+
+pub fn export(
+    state: Arc<SylverState>,
+    loader: &SylverLoader,
+    cmd: &ExportCmd,
+) -> anyhow::Result<()> {
+    let config_path = match &state.locations.config_file {
+        Some(f) => f,
+        None => bail!("Missing configuration file"),
+    };
+
+    let land = build_check_state(&state, loader, config_path)?.land;
+    let exporter = Exporter {
+        land: &land,
+        pos_encoder: &state.settings.pos_encoder,
+    };
+
+    for sylva in land.sylvae() {
+        run_passes(&land, sylva, [AnalysisPass::ScopeGraph], &state.cancel)?;
+    }
+
+    let artifact = match cmd.artifact {
+        ExportArtifact::ScopeGraph => {
+            serde_json::to_value(exporter.collect(|_, graph| vec![exporter.scope_graph(graph)])?)?
+        }
+        ExportArtifact::CallGraph => {
+            serde_json::to_value(exporter.collect(|sylva, graph| exporter.calls(sylva, graph))?)?
+        }
+        ExportArtifact::Symbols => {
+            serde_json::to_value(exporter.collect(|_, graph| exporter.symbols(graph))?)?
+        }
+    };
+
+    match cmd.format {
+        ExportFormat::Json => println!("{}", serde_json::to_string_pretty(&artifact)?),
+    }
+
+    Ok(())
+}
+
+struct Exporter<'l> {
+    land: &'l Land,
+    pos_encoder: &'l PosEncoder,
+}
+
+impl<'l> Exporter<'l> {
+    /// Concatenate the values extracted from the scope graph of every sylva.
+    fn collect<T>(&self, extract: impl Fn(SylvaId, &SGraph) -> Vec<T>) -> anyhow::Result<Vec<T>> {
+        let mut values = vec![];
+
+        for sylva in self.land.sylvae() {
+            values.extend(extract(sylva, self.land.sylva_scopes_mut(sylva).graph()?));
+        }
+
+        Ok(values)
+    }
+
+    fn scope_graph(&self, graph: &SGraph) -> JsonScopeGraph {
+        let scopes = graph
+            .scopes()
+            .map(|scope| JsonScope {
+                id: scope.index(),
+                parents: graph.parent_scopes(scope).map(|p| p.index()).collect(),
+                declarations: graph
+                    .scope_decls(scope)
+                    .map(|(node, name)| self.name(node, name))
+                    .collect(),
+                references: graph
+                    .scope_refs(scope)
+                    .map(|(node, name)| JsonReference {
+                        name: name.to_string(),
+                        location: self.location(node),
+                        declarations: self.locations(graph.referenced_decls(node)),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        JsonScopeGraph { scopes }
+    }
+
+    fn symbols(&self, graph: &SGraph) -> Vec<JsonSymbol> {
+        graph
+            .scopes()
+            .flat_map(|scope| graph.scope_decls(scope))
+            .map(|(node, name)| JsonSymbol {
+                name: name.to_string(),
+                kind: self.kind_name(node).to_string(),
+                location: self.location(node),
+                references: self.locations(graph.node_refs(node)),
+            })
+            .collect()
+    }
+
+    fn calls(&self, sylva: SylvaId, graph: &SGraph) -> Vec<JsonCall> {
+        let names: HashMap<SylvaNode, &str> = graph
+            .scopes()
+            .flat_map(|scope| graph.scope_decls(scope))
+            .collect();
+
+        // The declarations of the other sylvae have no name in this graph.
+        let name = |node: SylvaNode| names.get(&node).map(|name| self.name(node, name));
+
+        sylva_calls(self.land, sylva, graph)
+            .into_iter()
+            .filter_map(|call| {
+                Some(JsonCall {
+                    caller: call.caller.and_then(name),
+                    callee: name(call.callee)?,
+                    location: self.location(call.site),
+                })
+            })
+            .collect()
+    }
+
+    fn name(&self, node: SylvaNode, name: &str) -> JsonName {
+        JsonName {
+            name: name.to_string(),
+            location: self.location(node),
+        }
+    }
+
+    fn locations(&self, nodes: Option<&[SylvaNode]>) -> Vec<JsonLocation> {
+        nodes
+            .unwrap_or_default()
+            .iter()
+            .map(|&n| self.location(n))
+            .collect()
+    }
+
+    fn location(&self, node: SylvaNode) -> JsonLocation {
+        let tree = self.land.sylva_node_tree(node);
+        let info = RawTreeInfo::new(tree, &self.land.sylva_spec(node.sylva).syntax);
+        let (position, cell) =
+            encode_position(self.pos_encoder, &tree.source, info.node_pos(node.node));
+
+        JsonLocation {
+            file: path_to_string(tree.source.path()),
+            position,
+            cell,
+        }
+    }
+
+    fn kind_name(&self, node: SylvaNode) -> &'l str {
+        let tree = self.land.sylva_node_tree(node);
+        let syntax = &self.land.sylva_spec(node.sylva).syntax;
+        syntax.kind_name(tree.tree[node.node].kind)
+    }
+}
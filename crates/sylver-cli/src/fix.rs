@@ -0,0 +1,102 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use sylver_core::{
+    core::source::Source,
+    land::{cmds::rules::RuleResult, Land},
+    state::SylverState,
+    util::edits::{FixPlan, FixStrategy},
+};
+
+/// Apply the fixes of the rules that matched, resolving the overlapping edits with the given
+/// strategy. All the files are written together, and can be restored with `sylver rollback`.
+pub fn apply_fixes(
+    state: &SylverState,
+    land: &Land,
+    results: &[RuleResult],
+    strategy: FixStrategy,
+) -> Result<()> {
+    let mut edits = vec![];
+    let mut sources: HashMap<PathBuf, &Source> = HashMap::new();
+    let mut unfixable: HashMap<PathBuf, bool> = HashMap::new();
+
+    for res in results {
+        let edit = match res.fix_edit(land) {
+            None => continue,
+            Some(Ok(edit)) => edit,
+            Some(Err(e)) => {
+                state.logger.warning(&format!(
+                    "Could not render the fix of {} in {}: {e}",
+                    res.rule_id,
+                    res.source(land).path().display()
+                ));
+                continue;
+            }
+        };
+
+        let source = res.source(land);
+        let skipped = *unfixable
+            .entry(edit.file.clone())
+            .or_insert_with(|| !is_fixable(source));
+
+        if skipped {
+            continue;
+        }
+
+        sources.insert(edit.file.clone(), source);
+        edits.push(edit);
+    }
+
+    for (path, skipped) in &unfixable {
+        if *skipped {
+            state.logger.warning(&format!(
+                "Not fixing {}: the analyzed text differs from the file",
+                path.display()
+            ));
+        }
+    }
+
+    let plan = FixPlan::new(edits, strategy);
+
+    for conflict in &plan.conflicts {
+        let edits = conflict
+            .edits
+            .iter()
+            .map(|e| {
+                let status = if e.applied { "applied" } else { "skipped" };
+                format!("{} at {:?} ({status})", e.rule, e.span)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        state.logger.warning(&format!(
+            "Overlapping fixes in {}: {edits}",
+            conflict.file.display()
+        ));
+    }
+
+    if plan.edits.is_empty() {
+        return Ok(());
+    }
+
+    let transaction = plan.transaction(|path: &Path| Ok(sources[path].src().clone()))?;
+    let written = transaction.len();
+    transaction.commit(&state.locations.fix_journal)?;
+
+    state.logger.success(&format!(
+        "Applied {} fix(es) to {written} file(s), run `sylver rollback` to restore them",
+        plan.edits.len()
+    ));
+
+    Ok(())
+}
+
+/// The fixes are computed on the analyzed text, which must be the content of the file.
+fn is_fixable(source: &Source) -> bool {
+    source.source_map().is_none()
+        && source.notebook_cells().is_none()
+        && std::fs::read(source.path()).map_or(false, |content| content == source.src().as_bytes())
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+
+use sylver_core::{
+    core::{
+        source::Source,
+        spec::{spec_from_files, DEFAULT_START_RULE},
+    },
+    parsing::{parser_runner::ParserRunner, roundtrip::same_tree},
+    pretty_print::format::format_tree,
+    state::SylverState,
+    util::transaction::{rollback, FileTransaction},
+};
+
+use crate::{cli::FormatCmd, parse::print_reports};
+
+pub fn format(state: Arc<SylverState>, cmd: &FormatCmd) -> Result<()> {
+    let spec = spec_from_files(&state.script_engine, None, &cmd.spec)?;
+    let parser_runner = ParserRunner::new(
+        cmd.rule.as_deref().unwrap_or(DEFAULT_START_RULE),
+        &spec.syntax,
+    )?;
+
+    let mut unformatted = 0;
+    // The files are only written once all of them are formatted, and together.
+    let mut transaction = FileTransaction::new();
+
+    for path in &cmd.files {
+        let code = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let res = parser_runner.run_once(Source::file(code, path.clone()));
+
+        if !res.reports.is_empty() {
+            print_reports(state.clone(), &res.reports, &res.tree.source);
+            bail!("Could not parse {}", path.display());
+        }
+
+        let formatted = format_tree(&spec.syntax, &res.tree);
+
+        if &formatted == res.tree.source.src() {
+            continue;
+        }
+
+        // A template that drops or misplaces a token would silently change the code.
+        let reparsed = parser_runner.run_once(Source::file(formatted.clone(), path.clone()));
+        if !reparsed.reports.is_empty() || !same_tree(&spec.syntax, &res.tree, &reparsed.tree) {
+            bail!(
+                "The format templates change the syntax tree of {}",
+                path.display()
+            );
+        }
+
+        if cmd.check {
+            state
+                .logger
+                .info(&format!("{} is not formatted", path.display()));
+            unformatted += 1;
+        } else {
+            transaction.write(path, formatted);
+        }
+    }
+
+    if !transaction.is_empty() {
+        let written = transaction.len();
+        transaction.commit(&state.locations.fix_journal)?;
+        state.logger.success(&format!(
+            "Formatted {written} file(s), run `sylver rollback` to restore them"
+        ));
+    }
+
+    if unformatted > 0 {
+        bail!("{unformatted} file(s) are not formatted");
+    }
+
+    Ok(())
+}
+
+/// Restore the files written by the last command that modified files.
+pub fn rollback_files(state: &SylverState) -> Result<()> {
+    let restored = rollback(&state.locations.fix_journal)?;
+
+    for path in &restored {
+        state.logger.info(&format!("Restored {}", path.display()));
+    }
+
+    state
+        .logger
+        .success(&format!("Restored {} file(s)", restored.len()));
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+
+use sylver_core::{
+    core::files_spec::FileSpec,
+    land::{builder::LandBuilder, cmds::filter_sylva},
+    query::language::compile::compile,
+    script::python::PythonScriptEngine,
+    specs::loader::SylverLoader,
+    state::SylverState,
+    tree::info::{raw::RawTreeInfo, TreeInfo},
+    util::fs::path_to_string,
+};
+use sylver_dsl::sylq::parse_query;
+
+use crate::{
+    cli::GrepCmd,
+    shared::{build_sylva, load_sources, SylvaOptions},
+};
+
+/// Print the nodes matching a query pattern as `path:line:text`, where `text` is the line on
+/// which the node starts. Like grep, exit with status 1 when nothing matches.
+pub fn grep(state: Arc<SylverState>, loader: &SylverLoader, cmd: &GrepCmd) -> anyhow::Result<()> {
+    let query = parse_query(query_code(&cmd.pattern)).context("Failed to parse pattern")?;
+
+    let sources = load_sources(
+        &state,
+        loader,
+        &FileSpec {
+            root: None,
+            include: cmd.files.clone(),
+            exclude: cmd.exclude.clone(),
+            encoding: cmd.encoding.into(),
+            adapter: cmd.language.input_adapter(),
+        },
+    )?;
+
+    let mut builder = LandBuilder::new();
+    let sylva = build_sylva(
+        &state,
+        loader,
+        &mut builder,
+        &cmd.language,
+        &SylvaOptions::global(&state),
+        sources,
+    )?;
+    let land = builder.build();
+    let spec = land.sylva_spec(sylva);
+
+    let predicate = compile(spec, &query).context("Failed to compile pattern")?;
+
+    let mut matches = filter_sylva(
+        &land,
+        PythonScriptEngine::default(),
+        sylva,
+        &predicate,
+        &state.cancel,
+    )?
+    .into_iter()
+    .map(|node| {
+        let tree = land.sylva_node_tree(node);
+        let start = RawTreeInfo::new(tree, &spec.syntax)
+            .node_pos(node.node)
+            .start();
+        (
+            tree.source.path(),
+            start.line,
+            start.txt_pos,
+            tree.source.src(),
+        )
+    })
+    .collect::<Vec<_>>();
+
+    if matches.is_empty() {
+        std::process::exit(1);
+    }
+
+    matches.sort_by_key(|(path, _, txt_pos, _)| (*path, *txt_pos));
+
+    for (path, line, _, src) in matches {
+        let text = src.lines().nth(line - 1).unwrap_or_default();
+        let path = state.settings.path_map.remap(path);
+        println!("{}:{line}:{text}", path_to_string(&path));
+    }
+
+    Ok(())
+}
+
+/// The `match` keyword is optional on the command line.
+fn query_code(pattern: &str) -> String {
+    let pattern = pattern.trim();
+
+    match pattern.strip_prefix("match") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => pattern.to_string(),
+        _ => format!("match {pattern}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn optional_match_keyword() {
+        assert_eq!(query_code("FunctionDef"), "match FunctionDef");
+        assert_eq!(query_code(" match FunctionDef f "), "match FunctionDef f");
+        assert_eq!(query_code("matcher"), "match matcher");
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use sylver_core::{
+    core::store::ContentKey,
+    land::{cmds::RuleResult, Land},
+    state::SylverState,
+    util::git::GitClient,
+};
+
+use crate::{
+    check::CheckLandData,
+    cli::{TrendsCmd, TrendsFormat},
+};
+
+static HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// Number of findings per rule for a single run of `sylver check`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    pub commit: Option<String>,
+    /// Key of the analyzed files: runs with the same snapshot analyzed the same code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<ContentKey>,
+    pub counts: BTreeMap<String, usize>,
+}
+
+impl HistoryEntry {
+    pub fn from_results(check_data: &CheckLandData, results: &[RuleResult]) -> HistoryEntry {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+
+        for res in results {
+            let ruleset = check_data
+                .rulesets
+                .get(&res.ruleset)
+                .map(|location| location.to_string())
+                .unwrap_or_else(|| res.ruleset.to_string());
+
+            *counts
+                .entry(format!("{ruleset}::{}", res.rule_id))
+                .or_default() += 1;
+        }
+
+        HistoryEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            commit: GitClient::current_commit().ok(),
+            snapshot: Some(snapshot(&check_data.land)),
+            counts,
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+/// Append-only store of the `HistoryEntry`s, one json object per line.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(sylver_dir: &Path) -> HistoryStore {
+        HistoryStore {
+            path: sylver_dir.join(HISTORY_FILE_NAME),
+        }
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Could not open history file: {}", self.path.display()))?;
+
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Could not read history file: {}", self.path.display()))?
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).context("Invalid history entry"))
+            .collect()
+    }
+}
+
+pub fn trends(state: &SylverState, cmd: &TrendsCmd) -> anyhow::Result<()> {
+    let entries = HistoryStore::new(&state.locations.sylver_dir).entries()?;
+    let skipped = entries.len().saturating_sub(cmd.last);
+    let entries = &entries[skipped..];
+
+    match cmd.format {
+        TrendsFormat::Text => print!("{}", render_trends(entries)),
+        TrendsFormat::Json => println!("{}", serde_json::to_string_pretty(entries)?),
+    }
+
+    Ok(())
+}
+
+fn snapshot(land: &Land) -> ContentKey {
+    let contents: BTreeMap<&Path, ContentKey> = land
+        .sylvae()
+        .flat_map(|id| land.sylva(id).iter())
+        .map(|(_, tree)| {
+            let source = &tree.tree.source;
+            (source.path(), ContentKey::of(source.src().as_bytes()))
+        })
+        .collect();
+
+    ContentKey::of_set(&contents)
+}
+
+fn render_trends(entries: &[HistoryEntry]) -> String {
+    if entries.is_empty() {
+        return "No recorded runs. Use `sylver check --record-history` to record one.\n".into();
+    }
+
+    let rules: BTreeSet<&String> = entries.iter().flat_map(|e| e.counts.keys()).collect();
+
+    let mut lines = vec![render_trend_line(
+        "Total",
+        entries.iter().map(HistoryEntry::total),
+    )];
+
+    for rule in rules {
+        lines.push(render_trend_line(
+            rule,
+            entries
+                .iter()
+                .map(|e| e.counts.get(rule).copied().unwrap_or_default()),
+        ));
+    }
+
+    lines.into_iter().map(|l| format!("{l}\n")).collect()
+}
+
+fn render_trend_line(name: &str, counts: impl Iterator<Item = usize>) -> String {
+    let counts: Vec<usize> = counts.collect();
+    let first = counts.first().copied().unwrap_or_default() as i64;
+    let last = counts.last().copied().unwrap_or_default() as i64;
+
+    format!(
+        "{name}: {} ({:+})",
+        counts.iter().map(|c| c.to_string()).join(" -> "),
+        last - first
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(counts: BTreeMap<String, usize>) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: 0,
+            commit: None,
+            snapshot: None,
+            counts,
+        }
+    }
+
+    #[test]
+    fn render_empty_trends() {
+        assert!(render_trends(&[]).starts_with("No recorded runs"));
+    }
+
+    #[test]
+    fn render_trends_per_rule() {
+        let entries = [
+            entry(BTreeMap::from([
+                ("rs::a".to_string(), 3),
+                ("rs::b".to_string(), 1),
+            ])),
+            entry(BTreeMap::from([("rs::a".to_string(), 1)])),
+        ];
+
+        assert_eq!(
+            render_trends(&entries),
+            "Total: 4 -> 1 (-3)\nrs::a: 3 -> 1 (-2)\nrs::b: 1 -> 0 (-1)\n"
+        );
+    }
+}
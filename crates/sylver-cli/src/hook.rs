@@ -0,0 +1,212 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
+
+use sylver_core::{
+    core::store::ContentKey,
+    land::ruleset::{RuleSet, StopCondition},
+    specs::{loader::SylverLoader, stem::project::ProjectLang, trust::TrustPolicy},
+    state::SylverState,
+    util::fs::path_to_string,
+};
+
+use crate::{
+    baseline::baseline_path,
+    check::build_check_state_from,
+    cli::HookCmd,
+    report::encode_position,
+    shared::{check_rule_failures, evaluate_land_rules, ExitPolicy},
+};
+
+/// Files that had no finding in a previous run, with the key of their content. The cache is
+/// dropped when the configuration, the rulesets, the language specs, the baseline or the version
+/// of sylver change. It is left empty when some rules resolve names or clones across files, since
+/// the findings of a file then depend on the others.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+struct HookCache {
+    fingerprint: u64,
+    clean_files: BTreeMap<PathBuf, ContentKey>,
+}
+
+impl HookCache {
+    fn load(path: &Path, fingerprint: u64) -> HookCache {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<HookCache>(&content).ok())
+            .filter(|cache| cache.fingerprint == fingerprint)
+            .unwrap_or(HookCache {
+                fingerprint,
+                clean_files: BTreeMap::new(),
+            })
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Could not write hook cache: {}", path.display()))
+    }
+
+    fn is_clean(&self, file: &Path, key: &ContentKey) -> bool {
+        self.clean_files.get(file) == Some(key)
+    }
+}
+
+pub fn hook(state: Arc<SylverState>, loader: &SylverLoader, cmd: &HookCmd) -> anyhow::Result<()> {
+    let config_path = match &state.locations.config_file {
+        Some(f) => f,
+        None => bail!("Missing configuration file"),
+    };
+
+    if cmd.files.is_empty() {
+        return Ok(());
+    }
+
+    let cache_path = &state.locations.hook_cache;
+    let fingerprint = config_fingerprint(&state, loader, config_path)?;
+    let mut cache = HookCache::load(cache_path, fingerprint);
+
+    let keys: BTreeMap<&PathBuf, ContentKey> = cmd
+        .files
+        .iter()
+        .filter_map(|f| Some((f, ContentKey::of(&std::fs::read(f).ok()?))))
+        .collect();
+
+    let mut files: Vec<PathBuf> = keys
+        .iter()
+        .filter(|(file, key)| !cache.is_clean(file, key))
+        .map(|(file, _)| file.to_path_buf())
+        .collect();
+
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let mut land = build_check_state_from(&state, loader, config_path, Some(&files), None)?.land;
+    let cacheable = land
+        .sylvae()
+        .flat_map(|sylva| land.sylva_rulesets(sylva))
+        .all(RuleSet::is_file_local);
+
+    if !cacheable && files.len() < keys.len() {
+        files = keys.keys().map(|file| file.to_path_buf()).collect();
+        land = build_check_state_from(&state, loader, config_path, Some(&files), None)?.land;
+    }
+
+    let execution = evaluate_land_rules(&state, &land, StopCondition::default(), None, None)?;
+
+    let mut flagged = HashSet::new();
+    let pos_encoder = &state.settings.pos_encoder;
+
+    let reports = execution
+        .results
+        .iter()
+        .map(|r| (r.to_report(&land), r.source(&land)))
+        .chain(
+            execution
+                .failures
+                .iter()
+                .map(|f| (f.to_report(&land), f.source(&land))),
+        );
+
+    for (report, source) in reports {
+        let (position, _) = encode_position(pos_encoder, source, report.position);
+        println!(
+            "{}:{}:{}: [{}] {}",
+            path_to_string(&state.settings.path_map.remap(&report.file_path)),
+            position.start_line,
+            position.start_col,
+            report.code,
+            report.message
+        );
+        flagged.insert(report.file_path);
+    }
+
+    for file in &files {
+        if !cacheable || flagged.contains(file) {
+            cache.clean_files.remove(file);
+        } else {
+            cache.clean_files.insert(file.clone(), keys[file].clone());
+        }
+    }
+
+    cache.save(cache_path)?;
+
+    check_rule_failures(cmd.exit_policy.strict_rules, &execution.failures)?;
+    ExitPolicy::from(&cmd.exit_policy).exit_if_failed(&land, &execution.results);
+
+    Ok(())
+}
+
+/// Hash of the inputs that change the results of unmodified files.
+fn config_fingerprint(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+) -> anyhow::Result<u64> {
+    let config = loader.load_config(config_path)?;
+    let mut hasher = FxHasher::default();
+
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    config.hash(&mut hasher);
+    std::fs::read(baseline_path(&state.locations.sylver_dir))
+        .ok()
+        .hash(&mut hasher);
+
+    for project in config.projects() {
+        let policy = TrustPolicy::new(project.trust.clone(), state.settings.require_signed);
+
+        match &project.language {
+            ProjectLang::Builtin(_) => {}
+            ProjectLang::Custom(location) => {
+                loader.language_spec_files(location)?.hash(&mut hasher);
+            }
+            ProjectLang::External(grammar) => {
+                for path in [&grammar.library, &grammar.mappings] {
+                    std::fs::read(path).ok().hash(&mut hasher);
+                }
+            }
+        }
+
+        for ruleset in &project.rulesets {
+            loader.load_ruleset(ruleset, &policy)?.hash(&mut hasher);
+        }
+    }
+
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn cache_is_dropped_on_config_change() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("hook_cache.json");
+
+        let clean = ContentKey::of(b"x = 1\n");
+        let edited = ContentKey::of(b"x = 2\n");
+
+        let mut cache = HookCache::load(&path, 1);
+        cache.clean_files.insert("main.py".into(), clean.clone());
+        cache.save(&path).unwrap();
+
+        let reloaded = HookCache::load(&path, 1);
+        assert!(reloaded.is_clean(Path::new("main.py"), &clean));
+        assert!(!reloaded.is_clean(Path::new("main.py"), &edited));
+
+        assert!(!HookCache::load(&path, 2).is_clean(Path::new("main.py"), &clean));
+    }
+}
@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, io::Write};
+
+use sylver_core::{
+    core::pos::PosEncoder,
+    land::{
+        cmds::{RuleResult, RulesExecution},
+        ruleset::RuleTaxonomy,
+        Land,
+    },
+    util::fs::{path_to_string, PathPrefixMap},
+};
+
+use crate::{report::encode_position, stream::ReportWriter, teamcity::category_name};
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f3f3f3; }
+.error, .bug { color: #b00020; }
+.smell, .deprecated { color: #b36200; }
+.style { color: #5f6368; }";
+
+/// Hide the findings that don't belong to the classification selected in the summary.
+const SCRIPT: &str = "function filterFindings(taxon) {
+  for (const row of document.querySelectorAll('#findings tbody tr')) {
+    row.hidden = taxon !== '' && !row.dataset.taxa.split(' ').includes(taxon);
+  }
+}";
+
+/// Findings of a run as a standalone HTML page.
+///
+/// The findings are written as the rows of a table, with the CWE and OWASP classifications of
+/// their rule. The page ends with the number of findings of each classification, which can be
+/// used to only show the findings of one classification, and with the rules that failed.
+pub struct HtmlWriter<'a> {
+    pos_encoder: &'a PosEncoder,
+    land: &'a Land,
+    path_map: &'a PathPrefixMap,
+    findings: usize,
+    by_cwe: BTreeMap<u32, usize>,
+    by_owasp: BTreeMap<String, usize>,
+}
+
+impl<'a> HtmlWriter<'a> {
+    pub fn new(
+        pos_encoder: &'a PosEncoder,
+        land: &'a Land,
+        path_map: &'a PathPrefixMap,
+    ) -> HtmlWriter<'a> {
+        HtmlWriter {
+            pos_encoder,
+            land,
+            path_map,
+            findings: 0,
+            by_cwe: BTreeMap::new(),
+            by_owasp: BTreeMap::new(),
+        }
+    }
+
+    fn write_summary(&self, out: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(out, "<h2>Classifications</h2>")?;
+        writeln!(out, "<table id=\"classifications\">")?;
+        writeln!(
+            out,
+            "<thead><tr><th>Classification</th><th>Findings</th></tr></thead>"
+        )?;
+        writeln!(out, "<tbody>")?;
+        writeln!(
+            out,
+            "<tr><td><a href=\"#\" onclick=\"filterFindings(''); return false\">All</a></td>\
+             <td>{}</td></tr>",
+            self.findings
+        )?;
+
+        let cwe = self
+            .by_cwe
+            .iter()
+            .map(|(id, &count)| (cwe_taxon(*id), count));
+        let owasp = self
+            .by_owasp
+            .iter()
+            .map(|(id, &count)| (owasp_taxon(id), count));
+
+        for (taxon, count) in cwe.chain(owasp) {
+            writeln!(
+                out,
+                "<tr><td><a href=\"#\" onclick=\"filterFindings('{0}'); return false\">{0}</a>\
+                 </td><td>{count}</td></tr>",
+                escape(&taxon)
+            )?;
+        }
+
+        writeln!(out, "</tbody>\n</table>")?;
+
+        Ok(())
+    }
+
+    fn write_failures(
+        &self,
+        out: &mut dyn Write,
+        execution: &RulesExecution,
+    ) -> anyhow::Result<()> {
+        if execution.failures.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(out, "<h2>Rule errors</h2>")?;
+        writeln!(out, "<table id=\"errors\">")?;
+        writeln!(
+            out,
+            "<thead><tr><th>Location</th><th>Rule</th><th>Error</th></tr></thead>"
+        )?;
+        writeln!(out, "<tbody>")?;
+
+        for failure in &execution.failures {
+            let report = failure.to_report(self.land);
+            let (position, _) =
+                encode_position(self.pos_encoder, failure.source(self.land), report.position);
+            let file = path_to_string(&self.path_map.remap(&report.file_path));
+
+            writeln!(
+                out,
+                "<tr><td>{}:{}</td><td>{}/{}</td><td>{}</td></tr>",
+                escape(&file),
+                position.start_line,
+                escape(self.land.ruleset(failure.ruleset).id()),
+                escape(&failure.rule_id),
+                escape(&failure.message())
+            )?;
+        }
+
+        writeln!(out, "</tbody>\n</table>")?;
+
+        Ok(())
+    }
+}
+
+impl<'a> ReportWriter for HtmlWriter<'a> {
+    fn begin(&mut self, out: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+        )?;
+        writeln!(out, "<title>Sylver report</title>")?;
+        writeln!(
+            out,
+            "<style>\n{STYLE}\n</style>\n<script>\n{SCRIPT}\n</script>"
+        )?;
+        writeln!(out, "</head>\n<body>\n<h1>Sylver report</h1>")?;
+        writeln!(out, "<table id=\"findings\">")?;
+        writeln!(
+            out,
+            "<thead><tr><th>Location</th><th>Rule</th><th>Category</th><th>CWE</th><th>OWASP</th>\
+             <th>Message</th></tr></thead>"
+        )?;
+        writeln!(out, "<tbody>")?;
+
+        Ok(())
+    }
+
+    fn finding(&mut self, out: &mut dyn Write, result: &RuleResult) -> anyhow::Result<()> {
+        let rule = result.rule(self.land);
+        let report = result.to_report(self.land);
+        let (position, cell) =
+            encode_position(self.pos_encoder, result.source(self.land), report.position);
+
+        let mut location = format!(
+            "{}:{}",
+            path_to_string(&self.path_map.remap(&report.file_path)),
+            position.start_line
+        );
+        if let Some(cell) = cell {
+            location.push_str(&format!(" (cell {cell})"));
+        }
+
+        let rule_name = format!(
+            "{}/{}",
+            self.land.ruleset(result.ruleset).id(),
+            result.rule_id
+        );
+        let rule_cell = match &rule.doc_url {
+            Some(url) => format!("<a href=\"{}\">{}</a>", escape(url), escape(&rule_name)),
+            None => escape(&rule_name),
+        };
+
+        let cwe = rule
+            .taxonomy
+            .cwe
+            .map(|id| {
+                format!("<a href=\"https://cwe.mitre.org/data/definitions/{id}.html\">CWE-{id}</a>")
+            })
+            .unwrap_or_default();
+        let owasp = rule
+            .taxonomy
+            .owasp
+            .as_deref()
+            .map(escape)
+            .unwrap_or_default();
+
+        let category = category_name(rule.category);
+
+        writeln!(
+            out,
+            "<tr data-taxa=\"{}\"><td>{}</td><td>{rule_cell}</td><td class=\"{}\">{category}</td>\
+             <td>{cwe}</td><td>{owasp}</td><td>{}</td></tr>",
+            escape(&taxa(&rule.taxonomy).join(" ")),
+            escape(&location),
+            category.to_lowercase(),
+            escape(&report.message)
+        )?;
+
+        self.findings += 1;
+        if let Some(cwe) = rule.taxonomy.cwe {
+            *self.by_cwe.entry(cwe).or_default() += 1;
+        }
+        if let Some(owasp) = &rule.taxonomy.owasp {
+            *self.by_owasp.entry(owasp.clone()).or_default() += 1;
+        }
+
+        Ok(())
+    }
+
+    fn end(&mut self, out: &mut dyn Write, execution: &RulesExecution) -> anyhow::Result<()> {
+        writeln!(out, "</tbody>\n</table>")?;
+        self.write_summary(out)?;
+        self.write_failures(out, execution)?;
+        writeln!(out, "</body>\n</html>")?;
+
+        Ok(())
+    }
+}
+
+/// Classifications of a rule, as the identifiers used to filter the findings.
+fn taxa(taxonomy: &RuleTaxonomy) -> Vec<String> {
+    let cwe = taxonomy.cwe.map(cwe_taxon);
+    let owasp = taxonomy.owasp.as_deref().map(owasp_taxon);
+
+    cwe.into_iter().chain(owasp).collect()
+}
+
+fn cwe_taxon(id: u32) -> String {
+    format!("CWE-{id}")
+}
+
+/// The identifiers are listed in an attribute, separated by spaces.
+fn owasp_taxon(id: &str) -> String {
+    format!("OWASP-{}", id.replace(' ', "_"))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markup() {
+        assert_eq!(
+            escape("<a href=\"x\">'&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn taxonomy_filters() {
+        let taxonomy = RuleTaxonomy {
+            cwe: Some(89),
+            owasp: Some("A03:2021".to_string()),
+        };
+
+        assert_eq!(taxa(&taxonomy), vec!["CWE-89", "OWASP-A03:2021"]);
+        assert!(taxa(&RuleTaxonomy::default()).is_empty());
+    }
+}
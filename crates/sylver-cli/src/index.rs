@@ -0,0 +1,67 @@
+use anyhow::bail;
+
+use sylver_core::{
+    land::Land,
+    semantic::index::{SymbolIndex, SymbolLocation},
+    state::SylverState,
+    util::fs::{path_to_string, PathPrefixMap},
+};
+
+use crate::cli::SymbolPositionCmd;
+
+/// Index the files of the land that changed since the last update.
+pub fn update_index(state: &SylverState, land: &Land) -> anyhow::Result<()> {
+    let _indexing = state
+        .logger
+        .scoped("Updating symbol index", Some("Symbol index updated"));
+
+    let path = &state.locations.symbol_index;
+    let mut index = SymbolIndex::load(path);
+    index.update(land, &state.cancel)?;
+    index.save(path)
+}
+
+pub fn definition(state: &SylverState, cmd: &SymbolPositionCmd) -> anyhow::Result<()> {
+    let index = load_index_with_file(state, cmd)?;
+
+    for location in index.definitions_at(&cmd.file, cmd.line, cmd.column) {
+        print_location(&state.settings.path_map, location);
+    }
+
+    Ok(())
+}
+
+pub fn references(state: &SylverState, cmd: &SymbolPositionCmd) -> anyhow::Result<()> {
+    let index = load_index_with_file(state, cmd)?;
+
+    for location in index.references_at(&cmd.file, cmd.line, cmd.column) {
+        print_location(&state.settings.path_map, &location);
+    }
+
+    Ok(())
+}
+
+fn load_index_with_file(
+    state: &SylverState,
+    cmd: &SymbolPositionCmd,
+) -> anyhow::Result<SymbolIndex> {
+    let index = SymbolIndex::load(&state.locations.symbol_index);
+
+    if index.file(&cmd.file).is_none() {
+        bail!(
+            "{} is not indexed, run `sylver check --update-index` first",
+            cmd.file.display()
+        );
+    }
+
+    Ok(index)
+}
+
+fn print_location(path_map: &PathPrefixMap, location: &SymbolLocation) {
+    println!(
+        "{}:{}:{}",
+        path_to_string(&path_map.remap(&location.file)),
+        location.position.start_line,
+        location.position.start_col
+    );
+}
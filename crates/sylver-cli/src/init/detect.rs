@@ -9,7 +9,10 @@ use semver::Version;
 
 use sylver_core::{
     builtin_langs::{get_builtin_langs, get_detection_script, BuiltinLang},
-    core::files_spec::FileSpec,
+    core::{
+        files_spec::FileSpec,
+        source::{InputAdapter, SourceEncoding},
+    },
     dashboard::api::ReportLanguage,
     script::{ScriptEngine, ScriptError, ScriptValue},
     specs::{
@@ -65,6 +68,13 @@ impl<S> DetectableLanguage<S> {
 
         Ok(ProjectStem {
             language,
+            language_version: None,
+            trust: Default::default(),
+            parse_limits: Default::default(),
+            encoding: Default::default(),
+            preprocess: None,
+            disabled_rules: vec![],
+            min_clone_tokens: None,
             root,
             include: project.file_spec.include,
             exclude: project.file_spec.exclude,
@@ -347,6 +357,8 @@ fn project_from_value(value: ScriptValue) -> anyhow::Result<DetectedProject> {
                 .context("root should be a string")?,
             include: as_str_list(include_value).context("invalid 'include' field for file spec")?,
             exclude: as_str_list(exclude_value).context("invalid 'exlude' field for file spec")?,
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
         },
     })
 }
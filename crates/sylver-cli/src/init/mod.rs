@@ -1,41 +1,236 @@
-use std::sync::Arc;
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+use itertools::Itertools;
 use log::Logger;
-use rustyline::hint::Hint;
 
 use sylver_core::{
     script::python::PythonScriptEngine,
-    specs::{stem::project::ProjectConfigStem, stem::project::ProjectStem},
+    specs::stem::project::{ProjectConfigStem, ProjectLang, ProjectStem},
     state::SylverState,
 };
 
-use crate::{cli::InitCmd, init::detect::ProjectDetector};
+use crate::{
+    cli::InitCmd,
+    init::{
+        detect::ProjectDetector,
+        prompt::{DefaultPrompter, Prompter, TerminalPrompter},
+    },
+};
 
 mod detect;
+mod prompt;
+
+pub(crate) static CONFIG_HEADER: &str = "\
+# Sylver configuration file.
+# Each project associates a language and a set of files with the rulesets to run on them.
+# Run `sylver check` to verify all of the projects.
+# Documentation: https://docs.sylver.dev/docs/getting_started
+";
+
+static PRE_COMMIT_HOOK: &str = "\
+#!/bin/sh
+# Installed by `sylver init`.
+exec sylver check --no-color
+";
+
+static CI_WORKFLOW: &str = "\
+name: sylver
+
+on: [push, pull_request]
+
+jobs:
+  check:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: Install sylver
+        run: curl -s https://sylver.dev/install.sh | bash
+      - name: Run sylver
+        run: sylver check --no-color
+";
+
+static PRE_COMMIT_CONFIG: &str = "\
+# pre-commit.com configuration, created by `sylver init`.
+repos:
+  - repo: local
+    hooks:
+      - id: sylver
+        name: sylver
+        entry: sylver --no-color hook
+        language: system
+        pass_filenames: true
+        require_serial: true
+";
+
+static PRE_COMMIT_HOOK_PATH: &str = ".git/hooks/pre-commit";
+static PRE_COMMIT_CONFIG_PATH: &str = ".pre-commit-config.yaml";
+static CI_WORKFLOW_PATH: &str = ".github/workflows/sylver.yaml";
+static SYLVER_DIR: &str = ".sylver";
+
+/// Files created by `sylver init`, in addition to the configuration file and the `.sylver`
+/// directory.
+#[derive(Debug, Clone, Copy, Default)]
+struct Scaffolding {
+    pre_commit_hook: bool,
+    pre_commit_config: bool,
+    ci_workflow: bool,
+}
 
 pub fn init(state: Arc<SylverState>, cmd: &InitCmd) -> anyhow::Result<()> {
     let detection_root = std::env::current_dir()?;
 
+    // Without a terminal to ask the questions, the default answers are used.
+    let mut prompter: Box<dyn Prompter> = if cmd.yes || !std::io::stdin().is_terminal() {
+        Box::new(DefaultPrompter)
+    } else {
+        Box::new(TerminalPrompter::new()?)
+    };
+
     let detector = ProjectDetector::new(
         state.settings.backend_url.clone(),
         Box::new(state.logger.clone()),
         PythonScriptEngine::default(),
     );
 
-    let project_stems = detector.detect_builtin_lang_projects(&detection_root);
+    let detected_stems = detector.detect_builtin_lang_projects(&detection_root);
+
+    let project_stems = select_projects(prompter.as_mut(), detected_stems)?;
+    let scaffolding = select_scaffolding(state.clone(), prompter.as_mut(), &detection_root)?;
+
+    // Nothing is written unless all of the files can be created.
+    check_new_paths(&new_paths(cmd, &detection_root, scaffolding))?;
+
+    create_dir_and_config(state.clone(), cmd, project_stems)?;
+
+    if scaffolding.pre_commit_hook {
+        install_pre_commit_hook(state.clone(), &detection_root)?;
+    }
+
+    if scaffolding.pre_commit_config {
+        write_new_file(
+            &detection_root.join(PRE_COMMIT_CONFIG_PATH),
+            PRE_COMMIT_CONFIG,
+        )?;
+        state.logger.success(&format!(
+            "Created pre-commit configuration at {PRE_COMMIT_CONFIG_PATH}"
+        ));
+    }
+
+    if scaffolding.ci_workflow {
+        write_new_file(&detection_root.join(CI_WORKFLOW_PATH), CI_WORKFLOW)?;
+        state
+            .logger
+            .success(&format!("Created CI workflow at {CI_WORKFLOW_PATH}"));
+    }
+
+    Ok(())
+}
+
+fn select_scaffolding(
+    state: Arc<SylverState>,
+    prompter: &mut dyn Prompter,
+    root: &Path,
+) -> anyhow::Result<Scaffolding> {
+    let mut scaffolding = Scaffolding::default();
+
+    if root.join(".git").is_dir() {
+        scaffolding.pre_commit_hook = prompter.confirm(
+            "Install a git pre-commit hook running `sylver check`?",
+            false,
+        )?;
+    } else {
+        state
+            .logger
+            .info("Not a git repository, skipping pre-commit hook installation");
+    }
+
+    scaffolding.pre_commit_config = prompter.confirm(
+        "Create a pre-commit.com configuration running `sylver hook`?",
+        false,
+    )?;
+    scaffolding.ci_workflow = prompter.confirm(
+        "Create a GitHub Actions workflow running `sylver check`?",
+        false,
+    )?;
 
-    create_dir_and_config(state, cmd, project_stems)?;
+    Ok(scaffolding)
+}
+
+/// Paths of the files and directories created by the command.
+fn new_paths(cmd: &InitCmd, root: &Path, scaffolding: Scaffolding) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(SYLVER_DIR), PathBuf::from(&cmd.config_path)];
+
+    if scaffolding.pre_commit_hook {
+        paths.push(root.join(PRE_COMMIT_HOOK_PATH));
+    }
+
+    if scaffolding.pre_commit_config {
+        paths.push(root.join(PRE_COMMIT_CONFIG_PATH));
+    }
+
+    if scaffolding.ci_workflow {
+        paths.push(root.join(CI_WORKFLOW_PATH));
+    }
+
+    paths
+}
+
+fn check_new_paths(paths: &[PathBuf]) -> anyhow::Result<()> {
+    let existing = paths.iter().filter(|p| p.exists()).collect::<Vec<_>>();
+
+    if !existing.is_empty() {
+        bail!(
+            "Already initialized, remove the following files first: {}",
+            existing.iter().map(|p| p.display().to_string()).join(", ")
+        );
+    }
 
     Ok(())
 }
 
+fn select_projects(
+    prompter: &mut dyn Prompter,
+    detected: Vec<ProjectStem>,
+) -> anyhow::Result<Vec<ProjectStem>> {
+    let mut selected = vec![];
+
+    for mut project in detected {
+        let language = match &project.language {
+            ProjectLang::Builtin(b) => b.to_string(),
+            ProjectLang::Custom(location) => location.to_string(),
+            ProjectLang::External(grammar) => grammar.name.clone(),
+        };
+        let root = project.root.as_deref().unwrap_or(".");
+
+        if !prompter.confirm(&format!("Enable {language} project in {root}?"), true)? {
+            continue;
+        }
+
+        let mut rulesets = vec![];
+        for ruleset in std::mem::take(&mut project.rulesets) {
+            if prompter.confirm(&format!("Enable ruleset {ruleset}?"), true)? {
+                rulesets.push(ruleset);
+            }
+        }
+        project.rulesets = rulesets;
+
+        selected.push(project);
+    }
+
+    Ok(selected)
+}
+
 fn create_dir_and_config(
     state: Arc<SylverState>,
     cmd: &InitCmd,
     project_stems: Vec<ProjectStem>,
 ) -> anyhow::Result<()> {
-    std::fs::create_dir(".sylver").context("failed to create .sylver dir")?;
+    std::fs::create_dir(SYLVER_DIR).context("failed to create .sylver dir")?;
     state.logger.success("Created .sylver directory");
 
     let top_project = ProjectConfigStem::Nested {
@@ -44,14 +239,47 @@ fn create_dir_and_config(
 
     std::fs::write(
         &cmd.config_path,
-        serde_yaml::to_string(&top_project).unwrap(),
+        format!(
+            "{CONFIG_HEADER}\n{}",
+            serde_yaml::to_string(&top_project).unwrap()
+        ),
     )
     .context("Failed to write config file")?;
 
+    state
+        .logger
+        .success(&format!("Created config file at {}", cmd.config_path));
+
+    Ok(())
+}
+
+fn install_pre_commit_hook(state: Arc<SylverState>, root: &Path) -> anyhow::Result<()> {
+    let hook_path = root.join(PRE_COMMIT_HOOK_PATH);
+    write_new_file(&hook_path, PRE_COMMIT_HOOK)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))
+            .context("Failed to make the pre-commit hook executable")?;
+    }
+
     state.logger.success(&format!(
-        "Created config file at {}",
-        cmd.config_path.display()
+        "Installed pre-commit hook at {PRE_COMMIT_HOOK_PATH}"
     ));
 
     Ok(())
 }
+
+fn write_new_file(path: &Path, content: &str) -> anyhow::Result<()> {
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,71 @@
+use anyhow::Context;
+use rustyline::Editor;
+
+/// Asks yes/no questions to the user.
+pub trait Prompter {
+    fn confirm(&mut self, question: &str, default: bool) -> anyhow::Result<bool>;
+}
+
+/// Reads the answers from the terminal.
+pub struct TerminalPrompter {
+    editor: Editor<()>,
+}
+
+impl TerminalPrompter {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(TerminalPrompter {
+            editor: Editor::new().context("Could not build prompt")?,
+        })
+    }
+}
+
+impl Prompter for TerminalPrompter {
+    fn confirm(&mut self, question: &str, default: bool) -> anyhow::Result<bool> {
+        let choices = if default { "[Y/n]" } else { "[y/N]" };
+
+        loop {
+            let answer = self.editor.readline(&format!("{question} {choices} "))?;
+
+            match parse_answer(&answer, default) {
+                Some(a) => return Ok(a),
+                None => println!("Please answer 'y' or 'n'."),
+            }
+        }
+    }
+}
+
+/// Always selects the default answer.
+pub struct DefaultPrompter;
+
+impl Prompter for DefaultPrompter {
+    fn confirm(&mut self, _question: &str, default: bool) -> anyhow::Result<bool> {
+        Ok(default)
+    }
+}
+
+fn parse_answer(answer: &str, default: bool) -> Option<bool> {
+    match answer.trim().to_lowercase().as_str() {
+        "" => Some(default),
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answer_default() {
+        assert_eq!(parse_answer("", true), Some(true));
+        assert_eq!(parse_answer("  ", false), Some(false));
+    }
+
+    #[test]
+    fn answer_explicit() {
+        assert_eq!(parse_answer("Y", false), Some(true));
+        assert_eq!(parse_answer("no", true), Some(false));
+        assert_eq!(parse_answer("maybe", true), None);
+    }
+}
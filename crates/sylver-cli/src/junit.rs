@@ -0,0 +1,197 @@
+use std::io::Write;
+
+use sylver_core::{
+    core::{pos::PosEncoder, source::Source},
+    land::{
+        cmds::{parsing_errors, RuleResult, RulesExecution},
+        Land,
+    },
+    report::{Report, PARSE_ERROR_CODE},
+    util::fs::{path_to_string, PathPrefixMap},
+};
+
+use crate::{report::encode_position, stream::ReportWriter, teamcity::category_name};
+
+/// Parsing errors, findings and rule failures as a JUnit XML document, with one failed test case
+/// per parsing error or finding, and one errored test case per rule failure.
+///
+/// The test cases are streamed, so the test suite doesn't carry the number of tests and
+/// failures, which are only known at the end of the run.
+pub struct JunitWriter<'a> {
+    pos_encoder: &'a PosEncoder,
+    land: &'a Land,
+    path_map: &'a PathPrefixMap,
+}
+
+impl<'a> JunitWriter<'a> {
+    pub fn new(
+        pos_encoder: &'a PosEncoder,
+        land: &'a Land,
+        path_map: &'a PathPrefixMap,
+    ) -> JunitWriter<'a> {
+        JunitWriter {
+            pos_encoder,
+            land,
+            path_map,
+        }
+    }
+
+    fn test_case(
+        &self,
+        name: &str,
+        source: &Source,
+        report: &Report,
+        outcome: &str,
+        kind: &str,
+    ) -> String {
+        let (position, cell) = encode_position(self.pos_encoder, source, report.position);
+        let file = path_to_string(&self.path_map.remap(&report.file_path));
+
+        let mut location = format!("{file}:{}:{}", position.start_line, position.start_col);
+        if let Some(cell) = cell {
+            location = format!("{location} (cell {cell})");
+        }
+
+        test_case(name, &file, outcome, kind, &report.message, &location)
+    }
+}
+
+impl<'a> ReportWriter for JunitWriter<'a> {
+    fn begin(&mut self, out: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(out, "<testsuites name=\"sylver\">")?;
+        writeln!(out, "  <testsuite name=\"sylver\">")?;
+
+        let mut parse_errors: Vec<(&Source, &Report)> = parsing_errors(self.land)
+            .into_iter()
+            .flat_map(|(source, reports)| reports.iter().map(move |r| (source, r)))
+            .collect();
+        parse_errors.sort_by_key(|(_, r)| (&r.file_path, r.position.start().txt_pos()));
+
+        for (source, report) in parse_errors {
+            let test_case = self.test_case(
+                PARSE_ERROR_CODE,
+                source,
+                report,
+                "failure",
+                PARSE_ERROR_CODE,
+            );
+            writeln!(out, "{test_case}")?;
+        }
+
+        Ok(())
+    }
+
+    fn finding(&mut self, out: &mut dyn Write, result: &RuleResult) -> anyhow::Result<()> {
+        let name = format!(
+            "{}/{}",
+            self.land.ruleset(result.ruleset).id(),
+            result.rule_id
+        );
+        let category = category_name(result.rule(self.land).category);
+
+        let test_case = self.test_case(
+            &name,
+            result.source(self.land),
+            &result.to_report(self.land),
+            "failure",
+            category,
+        );
+        writeln!(out, "{test_case}")?;
+
+        Ok(())
+    }
+
+    fn end(&mut self, out: &mut dyn Write, execution: &RulesExecution) -> anyhow::Result<()> {
+        for failure in &execution.failures {
+            let name = format!(
+                "{}/{}",
+                self.land.ruleset(failure.ruleset).id(),
+                failure.rule_id
+            );
+
+            let test_case = self.test_case(
+                &name,
+                failure.source(self.land),
+                &failure.to_report(self.land),
+                "error",
+                "RuleError",
+            );
+            writeln!(out, "{test_case}")?;
+        }
+
+        writeln!(out, "  </testsuite>")?;
+        writeln!(out, "</testsuites>")?;
+
+        Ok(())
+    }
+}
+
+/// Test case of a file, whose outcome (`failure` or `error`) gives the message and location.
+fn test_case(
+    name: &str,
+    file: &str,
+    outcome: &str,
+    kind: &str,
+    message: &str,
+    location: &str,
+) -> String {
+    format!(
+        "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\">\n      <{outcome} message=\"{}\" \
+         type=\"{}\">{}</{outcome}>\n    </testcase>",
+        escape(name),
+        escape(file),
+        escape(file),
+        escape(message),
+        escape(kind),
+        escape(location)
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_xml() {
+        assert_eq!(
+            escape("a < b && c > 'd'\n"),
+            "a &lt; b &amp;&amp; c &gt; &apos;d&apos;&#10;"
+        );
+    }
+
+    #[test]
+    fn render_test_case() {
+        assert_eq!(
+            test_case(
+                "rs/rule",
+                "src/a.py",
+                "failure",
+                "Bug",
+                "Use \"is\"",
+                "src/a.py:3:5"
+            ),
+            "    <testcase name=\"rs/rule\" classname=\"src/a.py\" file=\"src/a.py\">\n      \
+             <failure message=\"Use &quot;is&quot;\" type=\"Bug\">src/a.py:3:5</failure>\n    \
+             </testcase>"
+        );
+    }
+}
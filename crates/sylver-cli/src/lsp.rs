@@ -0,0 +1,726 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+
+use sylver_core::{
+    core::{
+        pos::{ColumnEncoding, InclPosRange, PosEncoder},
+        source::Source,
+    },
+    land::{
+        cmds::{exec_rules, parsing_errors, RuleResult},
+        ruleset::{MetadataValue, Rule, RuleCategory, StopCondition},
+        Land,
+    },
+    report::PARSE_ERROR_CODE,
+    specs::loader::SylverLoader,
+    state::SylverState,
+};
+
+use crate::{
+    cli::LspCmd,
+    serve::{
+        parse_request, RpcError, RpcRequest, RpcResponse, INVALID_PARAMS, INVALID_REQUEST,
+        METHOD_NOT_FOUND,
+    },
+    watch::LiveCheck,
+};
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const SEVERITY_INFORMATION: u8 = 3;
+
+const TAG_UNNECESSARY: u8 = 1;
+const TAG_DEPRECATED: u8 = 2;
+
+/// Values of the `tags` metadata of a rule whose findings are unused or unreachable code.
+const UNNECESSARY_TAGS: &[&str] = &["unnecessary", "unused", "unreachable"];
+
+/// Run a language server over the standard input and output.
+///
+/// The server analyzes the files of the workspace, with its configuration: the files are analyzed
+/// when the client is initialized and each time a document is opened, edited, saved or closed,
+/// and the findings of each file are published as diagnostics. The open documents are analyzed
+/// from the text of their editor buffer, saved or not. The diagnostics of the files that no
+/// longer have findings are cleared. The severity of a diagnostic follows the category of its
+/// rule, and the findings of the rules that have a fix come with a quick-fix code action.
+///
+/// As in `check --watch`, the land is kept between analyses and only the modified files are
+/// loaded again. Notebooks and preprocessed sources are not reported, as their positions don't
+/// match the documents of the editor.
+pub fn lsp(state: Arc<SylverState>, loader: &SylverLoader, _cmd: &LspCmd) -> anyhow::Result<()> {
+    let Some(config) = &state.locations.config_file else {
+        bail!("Missing configuration file");
+    };
+
+    let config = config
+        .canonicalize()
+        .with_context(|| format!("Invalid configuration file: {}", config.display()))?;
+
+    let mut reader = BufReader::new(io::stdin().lock());
+    let mut writer = BufWriter::new(io::stdout().lock());
+
+    let mut server = LanguageServer::new(&state, loader, config);
+
+    while let Some(body) = read_message(&mut reader)? {
+        let request = match parse_request(&body) {
+            Ok(request) => request,
+            Err(error) => {
+                write_message(&mut writer, &RpcResponse::error(Value::Null, error))?;
+                continue;
+            }
+        };
+
+        if request.method == "exit" {
+            if server.shutdown {
+                return Ok(());
+            }
+
+            bail!("The client exited without shutting down the server");
+        }
+
+        let id = request.id.clone();
+        let handled = server.handle(request, &mut writer);
+
+        if let Some(id) = id {
+            let response = match handled {
+                Ok(result) => RpcResponse::result(id, result),
+                Err(error) => RpcResponse::error(id, error),
+            };
+
+            write_message(&mut writer, &response)?;
+        } else if let Err(error) = handled {
+            show_message(&mut writer, &error.message)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Diagnostic of a finding, with the edit of the fix of its rule.
+#[derive(Debug, Clone)]
+struct FileDiagnostic {
+    diagnostic: Value,
+    rule: String,
+    fix: Option<Value>,
+}
+
+struct LanguageServer<'s> {
+    state: &'s SylverState,
+    loader: &'s SylverLoader,
+    config: PathBuf,
+    live: Option<LiveCheck>,
+    /// Diagnostics of the last analysis, keyed by the URI of their file.
+    diagnostics: BTreeMap<String, Vec<FileDiagnostic>>,
+    shutdown: bool,
+}
+
+impl<'s> LanguageServer<'s> {
+    fn new(state: &'s SylverState, loader: &'s SylverLoader, config: PathBuf) -> Self {
+        LanguageServer {
+            state,
+            loader,
+            config,
+            live: None,
+            diagnostics: BTreeMap::new(),
+            shutdown: false,
+        }
+    }
+
+    fn handle(&mut self, request: RpcRequest, writer: &mut impl Write) -> Result<Value, RpcError> {
+        if self.shutdown {
+            return Err(RpcError::new(
+                INVALID_REQUEST,
+                "The server is shutting down",
+            ));
+        }
+
+        match request.method.as_str() {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 1, "save": {} },
+                    "codeActionProvider": { "codeActionKinds": ["quickfix"] },
+                },
+                "serverInfo": { "name": "sylver", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "initialized" | "textDocument/didSave" => {
+                self.analyze(writer, &[]).map_err(analysis_error)?;
+                Ok(Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let params: DidOpenParams = parse_params(request.params)?;
+                let document = params.text_document;
+                self.edit(writer, &document.uri, Some(document.text))
+            }
+            "textDocument/didChange" => {
+                let params: DidChangeParams = parse_params(request.params)?;
+
+                // The documents are synchronized in full: the last change holds the whole text.
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.edit(writer, &params.text_document.uri, Some(change.text)),
+                    None => Ok(Value::Null),
+                }
+            }
+            "textDocument/didClose" => {
+                let params: DidCloseParams = parse_params(request.params)?;
+                self.edit(writer, &params.text_document.uri, None)
+            }
+            "textDocument/codeAction" => {
+                let params: CodeActionParams = parse_params(request.params)?;
+                Ok(self.code_actions(&params).into())
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            method if request.id.is_none() || method.starts_with("$/") => Ok(Value::Null),
+            method => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {method}"),
+            )),
+        }
+    }
+
+    /// Analyze a document with the text of its editor buffer, or with its content on disk once
+    /// the document is closed (`text` is `None`).
+    fn edit(
+        &mut self,
+        writer: &mut impl Write,
+        uri: &str,
+        text: Option<String>,
+    ) -> Result<Value, RpcError> {
+        let path = uri_path(uri).ok_or_else(|| {
+            RpcError::new(INVALID_PARAMS, format!("Unsupported document URI: {uri}"))
+        })?;
+
+        match text {
+            Some(text) => self.loader.files().insert(path.clone(), text),
+            None => self.loader.files().remove(&path),
+        };
+
+        self.analyze(writer, &[path]).map_err(analysis_error)?;
+        Ok(Value::Null)
+    }
+
+    /// Evaluate the rules of the workspace, and publish the diagnostics of the files whose
+    /// diagnostics changed. The `edited` files are loaded again even if they were not modified on
+    /// disk.
+    fn analyze(&mut self, writer: &mut impl Write, edited: &[PathBuf]) -> anyhow::Result<()> {
+        let (state, loader) = (self.state, self.loader);
+        let root = self.config.parent().unwrap_or(Path::new("/")).to_owned();
+
+        let live = match &mut self.live {
+            Some(live) => {
+                live.update_edited(state, loader, edited)?;
+                live
+            }
+            live => live.insert(LiveCheck::new(state, loader, &self.config, Some(&root))?),
+        };
+
+        // The previous diagnostics are kept until the files are fixed.
+        if let Some(failure) = &live.failure {
+            bail!("The workspace could not be reloaded: {failure}");
+        }
+
+        let land = &live.data.land;
+        let execution = {
+            let progress = state.logger.progress("Evaluating rules");
+            exec_rules(
+                land,
+                StopCondition::default(),
+                &state.cancel,
+                Some(&live.cache),
+                None,
+                progress.as_ref(),
+            )?
+        };
+
+        for failure in &execution.failures {
+            let report = failure.to_report(land);
+            state.logger.warning(&format!(
+                "{}: {}",
+                report.file_path.display(),
+                report.message
+            ));
+        }
+
+        let diagnostics = land_diagnostics(state, land, &execution.results);
+        let previous = std::mem::replace(&mut self.diagnostics, diagnostics);
+
+        let uris: BTreeSet<&String> = previous.keys().chain(self.diagnostics.keys()).collect();
+
+        for uri in uris {
+            let diagnostics: Vec<&Value> = self
+                .diagnostics
+                .get(uri)
+                .into_iter()
+                .flatten()
+                .map(|d| &d.diagnostic)
+                .collect();
+
+            let unchanged = previous.get(uri).map_or(false, |p| {
+                p.iter()
+                    .map(|d| &d.diagnostic)
+                    .eq(diagnostics.iter().copied())
+            });
+
+            if !unchanged {
+                write_message(
+                    writer,
+                    &json!({
+                        "jsonrpc": "2.0",
+                        "method": "textDocument/publishDiagnostics",
+                        "params": { "uri": uri, "diagnostics": diagnostics },
+                    }),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Quick fixes of the findings of a document that overlap the given range.
+    fn code_actions(&self, params: &CodeActionParams) -> Vec<Value> {
+        let Some(diagnostics) = self.diagnostics.get(&params.text_document.uri) else {
+            return vec![];
+        };
+
+        diagnostics
+            .iter()
+            .filter(|d| overlaps(&d.diagnostic["range"], &params.range))
+            .filter_map(|d| {
+                let fix = d.fix.as_ref()?;
+
+                let mut changes = Map::new();
+                changes.insert(params.text_document.uri.clone(), json!([fix]));
+
+                Some(json!({
+                    "title": format!("Fix {}", d.rule),
+                    "kind": "quickfix",
+                    "diagnostics": [d.diagnostic],
+                    "isPreferred": true,
+                    "edit": { "changes": changes },
+                }))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CodeActionParams {
+    text_document: TextDocumentIdentifier,
+    range: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidOpenParams {
+    text_document: TextDocumentItem,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDocumentItem {
+    uri: String,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidChangeParams {
+    text_document: TextDocumentIdentifier,
+    content_changes: Vec<TextDocumentChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDocumentChange {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidCloseParams {
+    text_document: TextDocumentIdentifier,
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn analysis_error(error: anyhow::Error) -> RpcError {
+    RpcError::new(INVALID_REQUEST, format!("{error:#}"))
+}
+
+/// Diagnostics of the parsing errors and the findings of the land, keyed by file URI.
+fn land_diagnostics(
+    state: &SylverState,
+    land: &Land,
+    results: &[RuleResult],
+) -> BTreeMap<String, Vec<FileDiagnostic>> {
+    let pos_encoder = PosEncoder::new(ColumnEncoding::Utf16, None);
+    let mut diagnostics: BTreeMap<String, Vec<FileDiagnostic>> = BTreeMap::new();
+
+    for (source, reports) in parsing_errors(land) {
+        if !reported(source) {
+            continue;
+        }
+
+        for report in reports {
+            diagnostics
+                .entry(file_uri(source.path()))
+                .or_default()
+                .push(FileDiagnostic {
+                    diagnostic: json!({
+                        "range": range(&pos_encoder, source.src(), report.position),
+                        "severity": SEVERITY_ERROR,
+                        "code": PARSE_ERROR_CODE,
+                        "source": "sylver",
+                        "message": report.message,
+                    }),
+                    rule: PARSE_ERROR_CODE.to_string(),
+                    fix: None,
+                });
+        }
+    }
+
+    for result in results {
+        let source = result.source(land);
+        if !reported(source) {
+            continue;
+        }
+
+        let rule = result.rule(land);
+        let rule_name = format!("{}/{}", land.ruleset(result.ruleset).id(), result.rule_id);
+        let report = result.to_report(land);
+
+        let mut diagnostic = json!({
+            "range": range(&pos_encoder, source.src(), report.position),
+            "severity": severity(rule.category),
+            "code": rule_name,
+            "source": "sylver",
+            "message": report.message,
+        });
+
+        if let Some(url) = &rule.doc_url {
+            diagnostic["codeDescription"] = json!({ "href": url });
+        }
+
+        let tags = tags(rule);
+        if !tags.is_empty() {
+            diagnostic["tags"] = tags.into();
+        }
+
+        let fix = match result.fix_edit(land) {
+            Some(Ok(edit)) => Some(json!({
+                "range": {
+                    "start": position(&pos_encoder, source.src(), edit.span.start),
+                    "end": position(&pos_encoder, source.src(), edit.span.end),
+                },
+                "newText": edit.replacement,
+            })),
+            Some(Err(e)) => {
+                state
+                    .logger
+                    .warning(&format!("Could not render the fix of {rule_name}: {e}"));
+                None
+            }
+            None => None,
+        };
+
+        diagnostics
+            .entry(file_uri(source.path()))
+            .or_default()
+            .push(FileDiagnostic {
+                diagnostic,
+                rule: rule_name,
+                fix,
+            });
+    }
+
+    diagnostics
+}
+
+/// Notebooks and preprocessed sources are analyzed from a text that differs from their file.
+fn reported(source: &Source) -> bool {
+    source.notebook_cells().is_none() && source.source_map().is_none()
+}
+
+fn severity(category: RuleCategory) -> u8 {
+    match category {
+        RuleCategory::Style => SEVERITY_INFORMATION,
+        RuleCategory::Smell | RuleCategory::Deprecated => SEVERITY_WARNING,
+        RuleCategory::Bug | RuleCategory::Error => SEVERITY_ERROR,
+    }
+}
+
+/// Tags of the diagnostics of a rule: the findings of `deprecated` rules are uses of deprecated
+/// code, and the rules tagged as finding unused code report unnecessary code.
+fn tags(rule: &Rule) -> Vec<u8> {
+    let mut tags = vec![];
+
+    let unnecessary = match rule.metadata.get("tags") {
+        Some(MetadataValue::Array(values)) => values.iter().any(
+            |v| matches!(v, MetadataValue::String(s) if UNNECESSARY_TAGS.contains(&s.as_str())),
+        ),
+        _ => false,
+    };
+
+    if unnecessary {
+        tags.push(TAG_UNNECESSARY);
+    }
+
+    if rule.category == RuleCategory::Deprecated {
+        tags.push(TAG_DEPRECATED);
+    }
+
+    tags
+}
+
+fn range(pos_encoder: &PosEncoder, src: &str, range: InclPosRange) -> Value {
+    json!({
+        "start": position(pos_encoder, src, range.start().txt_pos),
+        "end": position(pos_encoder, src, range.end().txt_pos),
+    })
+}
+
+/// Zero-based line and UTF-16 character of the byte at `txt_pos` in `src`.
+fn position(pos_encoder: &PosEncoder, src: &str, txt_pos: usize) -> Value {
+    let prefix = src.get(..txt_pos.min(src.len())).unwrap_or_default();
+    let line = prefix.matches('\n').count();
+
+    json!({ "line": line, "character": pos_encoder.column(src, txt_pos) - 1 })
+}
+
+/// Return true if the given LSP ranges overlap. An empty range overlaps the ranges that contain
+/// its position.
+fn overlaps(a: &Value, b: &Value) -> bool {
+    let pos = |v: &Value| (v["line"].as_u64(), v["character"].as_u64());
+
+    pos(&a["start"]) <= pos(&b["end"]) && pos(&b["start"]) <= pos(&a["end"])
+}
+
+/// `file` URI of a path, relative to the current directory if it is not absolute.
+fn file_uri(path: &Path) -> String {
+    let path = std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_owned());
+
+    let mut uri = String::from("file://");
+
+    for component in path.components() {
+        let text = component.as_os_str().to_string_lossy();
+
+        if text.ends_with(['/', '\\']) {
+            // Root of the path.
+            uri.push('/');
+            continue;
+        }
+
+        if !uri.ends_with('/') {
+            uri.push('/');
+        }
+
+        for byte in text.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    uri.push(byte as char)
+                }
+                byte => uri.push_str(&format!("%{byte:02X}")),
+            }
+        }
+    }
+
+    uri
+}
+
+/// Path of a `file` URI, or `None` for the other schemes.
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+
+    while i < encoded.len() {
+        let escaped = encoded
+            .get(i + 1..i + 3)
+            .filter(|hex| encoded[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+
+    let path = String::from_utf8(bytes).ok()?;
+
+    // Windows paths are written as `file:///C:/dir/file`.
+    match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => Some(PathBuf::from(&path[1..])),
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+/// Read the body of the next message, framed by its `Content-Length` header. Return `None` at
+/// the end of the stream.
+fn read_message(reader: &mut impl BufRead) -> anyhow::Result<Option<String>> {
+    let mut content_length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+
+    let length = content_length.ok_or_else(|| anyhow!("Missing Content-Length header"))?;
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(String::from_utf8(body)?))
+}
+
+fn write_message(writer: &mut impl Write, message: &impl serde::Serialize) -> anyhow::Result<()> {
+    let body = serde_json::to_string(message)?;
+
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn show_message(writer: &mut impl Write, message: &str) -> anyhow::Result<()> {
+    write_message(
+        writer,
+        &json!({
+            "jsonrpc": "2.0",
+            "method": "window/showMessage",
+            "params": { "type": 1, "message": message },
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn message_framing() {
+        let mut out = vec![];
+        write_message(&mut out, &json!({ "id": 1 })).unwrap();
+        assert_eq!(out, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+
+        let mut input = Cursor::new(
+            b"Content-Type: application/vscode-jsonrpc\r\ncontent-length: 8\r\n\r\n{\"id\":1}"
+                .to_vec(),
+        );
+        assert_eq!(
+            read_message(&mut input).unwrap().as_deref(),
+            Some("{\"id\":1}")
+        );
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        let mut missing = Cursor::new(b"\r\n{}".to_vec());
+        assert!(read_message(&mut missing).is_err());
+    }
+
+    #[test]
+    fn utf16_positions() {
+        let encoder = PosEncoder::new(ColumnEncoding::Utf16, None);
+        let src = "a = 1\nb = '😀' + c\n";
+
+        assert_eq!(
+            position(&encoder, src, 0),
+            json!({ "line": 0, "character": 0 })
+        );
+        assert_eq!(
+            position(&encoder, src, 6),
+            json!({ "line": 1, "character": 0 })
+        );
+
+        let after_emoji = src.find("' +").unwrap() + 1;
+        assert_eq!(
+            position(&encoder, src, after_emoji),
+            json!({ "line": 1, "character": 8 })
+        );
+    }
+
+    #[test]
+    fn severities() {
+        assert_eq!(severity(RuleCategory::Style), SEVERITY_INFORMATION);
+        assert_eq!(severity(RuleCategory::Deprecated), SEVERITY_WARNING);
+        assert_eq!(severity(RuleCategory::Bug), SEVERITY_ERROR);
+    }
+
+    #[test]
+    fn range_overlaps() {
+        let range = |l1: u64, c1: u64, l2: u64, c2: u64| {
+            json!({
+                "start": { "line": l1, "character": c1 },
+                "end": { "line": l2, "character": c2 },
+            })
+        };
+
+        assert!(overlaps(&range(1, 4, 1, 10), &range(1, 6, 1, 6)));
+        assert!(overlaps(&range(1, 4, 3, 0), &range(2, 0, 2, 5)));
+        assert!(!overlaps(&range(1, 4, 1, 10), &range(2, 0, 2, 5)));
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(
+            file_uri(Path::new("/home/me/my project/main.py")),
+            "file:///home/me/my%20project/main.py"
+        );
+        assert_eq!(file_uri(Path::new("/src/é.py")), "file:///src/%C3%A9.py");
+    }
+
+    #[test]
+    fn uri_paths() {
+        assert_eq!(
+            uri_path("file:///home/me/my%20project/main.py"),
+            Some(PathBuf::from("/home/me/my project/main.py"))
+        );
+        assert_eq!(
+            uri_path("file:///src/%C3%A9.py"),
+            Some(PathBuf::from("/src/é.py"))
+        );
+        assert_eq!(
+            uri_path("file:///c%3A/code/main.py"),
+            Some(PathBuf::from("c:/code/main.py"))
+        );
+        assert_eq!(uri_path("untitled:Untitled-1"), None);
+    }
+}
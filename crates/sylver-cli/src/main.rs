@@ -1,13 +1,15 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
 use clap::Parser;
 
-use log::{FancyLogger, Logger};
+use log::{FancyLogger, LogFormat, Logger};
 
 use sylver_core::{
+    core::pos::PosEncoder,
     specs::loader::SylverLoader,
     state::{SylverSettings, SylverState},
+    util::{cancel::CancellationToken, fs::PathPrefixMap},
 };
 
 use crate::{
@@ -16,25 +18,60 @@ use crate::{
     query::query,
 };
 
+mod badge;
+mod baseline;
+mod bench;
 mod check;
 mod cli;
+mod clones;
+mod compare;
+mod export;
+mod fix;
+mod format;
+mod grep;
+mod history;
+mod hook;
+mod html;
+mod index;
 mod init;
+mod junit;
+mod lsp;
+mod migrate;
 mod parse;
 mod query;
 mod repl;
+mod report;
 mod ruleset;
+mod sarif;
+mod serve;
 mod shared;
+mod spec;
+mod stream;
+mod teamcity;
+mod triage;
 mod upload;
+mod watch;
 
 fn main() -> Result<()> {
-    let logger = FancyLogger::default();
-
     let cmd = cli::Cli::parse();
 
+    let logger = FancyLogger::new(if cmd.log_json {
+        LogFormat::Json
+    } else {
+        LogFormat::Human
+    });
+
     let conf = build_conf(&cmd);
 
     let res = {
         let state = Arc::new(SylverState::with_settings(logger.clone(), conf)?);
+
+        handle_interruptions(&state.cancel)?;
+
+        if let Some(timeout) = cmd.timeout {
+            state.cancel.cancel_after(Duration::from_secs(timeout));
+        }
+
         eval(state, cmd)
     };
 
@@ -46,6 +83,21 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Cancel the running command on Ctrl-C, and exit immediately if it is pressed again.
+fn handle_interruptions(cancel: &CancellationToken) -> Result<()> {
+    let cancel = cancel.clone();
+
+    ctrlc::set_handler(move || {
+        if cancel.is_cancelled() {
+            std::process::exit(130);
+        }
+
+        cancel.cancel();
+    })?;
+
+    Ok(())
+}
+
 fn eval(state: Arc<SylverState>, cmd: Cli) -> Result<()> {
     let loader = SylverLoader::from_state(state.clone());
 
@@ -53,8 +105,23 @@ fn eval(state: Arc<SylverState>, cmd: Cli) -> Result<()> {
         Commands::Init(cmd) => init::init(state, &cmd)?,
         Commands::Check(cmd) => check::check(state, &loader, &cmd)?,
         Commands::Parse(cmd) => parse(state, &cmd)?,
+        Commands::Format(cmd) => format::format(state, &cmd)?,
+        Commands::Rollback => format::rollback_files(&state)?,
         Commands::Query(cmd) => query(state, &loader, &cmd)?,
+        Commands::Grep(cmd) => grep::grep(state, &loader, &cmd)?,
         Commands::Ruleset(cmd) => ruleset::ruleset_cmd(state, &loader, &cmd)?,
+        Commands::Trends(cmd) => history::trends(&state, &cmd)?,
+        Commands::Spec(cmd) => spec::spec_cmd(&state, &loader, &cmd)?,
+        Commands::Clones(cmd) => clones::clones(state, &loader, &cmd)?,
+        Commands::Export(cmd) => export::export(state, &loader, &cmd)?,
+        Commands::Definition(cmd) => index::definition(&state, &cmd)?,
+        Commands::References(cmd) => index::references(&state, &cmd)?,
+        Commands::Serve(cmd) => serve::serve(state, &loader, &cmd)?,
+        Commands::Lsp(cmd) => lsp::lsp(state, &loader, &cmd)?,
+        Commands::Bench(cmd) => bench::bench(state, &loader, &cmd)?,
+        Commands::Compare(cmd) => compare::compare(state, &loader, &cmd)?,
+        Commands::Hook(cmd) => hook::hook(state, &loader, &cmd)?,
+        Commands::MigrateConfig(cmd) => migrate::migrate_config(&state, &loader, &cmd)?,
     };
 
     Ok(())
@@ -63,6 +130,13 @@ fn eval(state: Arc<SylverState>, cmd: Cli) -> Result<()> {
 fn build_conf(cmd: &Cli) -> SylverSettings {
     let config_override = match &cmd.command {
         Commands::Check(cmd) => cmd.config.clone(),
+        Commands::Clones(cmd) => cmd.config.clone(),
+        Commands::Export(cmd) => cmd.config.clone(),
+        Commands::Bench(cmd) => cmd.config.clone(),
+        Commands::Compare(cmd) => cmd.config.clone(),
+        Commands::Hook(cmd) => cmd.config.clone(),
+        Commands::Lsp(cmd) => cmd.config.clone(),
+        Commands::MigrateConfig(cmd) => cmd.config.clone(),
         _ => None,
     };
 
@@ -70,5 +144,10 @@ fn build_conf(cmd: &Cli) -> SylverSettings {
         color_output: !cmd.no_color,
         backend_url: cmd.server_url.clone(),
         config_override,
+        pos_encoder: PosEncoder::new(cmd.column_encoding.into(), cmd.tab_width),
+        profile: cmd.profile,
+        require_signed: cmd.require_signed,
+        path_map: PathPrefixMap::new(cmd.path_prefix_map.clone()),
+        allow_preprocess: cmd.allow_preprocess,
     }
 }
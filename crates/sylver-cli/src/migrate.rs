@@ -0,0 +1,507 @@
+use anyhow::{bail, Context};
+
+use sylver_core::{
+    specs::{
+        loader::SylverLoader,
+        stem::{
+            project::{ProjectConfigStem, ProjectStem, RuleRef},
+            ruleset::RuleSetStem,
+        },
+        trust::TrustPolicy,
+    },
+    state::SylverState,
+};
+
+use crate::cli::MigrateConfigCmd;
+
+/// Outcome of the migration of a deprecated rule enabled in a project.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Migration {
+    /// The rule was disabled, and its replacement enabled.
+    Replaced { rule: RuleRef, replacement: String },
+    /// The rule has no replacement in its ruleset, so it was left enabled.
+    Kept { rule: RuleRef },
+}
+
+pub fn migrate_config(
+    state: &SylverState,
+    loader: &SylverLoader,
+    cmd: &MigrateConfigCmd,
+) -> anyhow::Result<()> {
+    let Some(config_path) = &state.locations.config_file else {
+        bail!("Missing configuration file");
+    };
+
+    let text = std::fs::read_to_string(config_path)
+        .with_context(|| format!("Could not read config file: {}", config_path.display()))?;
+    let mut config = loader.load_config(config_path)?;
+    let mut migrations = vec![];
+
+    for project in config.projects_mut() {
+        let policy = TrustPolicy::new(project.trust.clone(), state.settings.require_signed);
+
+        for location in project.rulesets.clone() {
+            let ruleset = loader.load_ruleset(&location, &policy)?;
+            migrations.extend(migrate_project(project, &ruleset));
+        }
+    }
+
+    let mut replaced = 0;
+
+    for migration in &migrations {
+        match migration {
+            Migration::Replaced { rule, replacement } => {
+                replaced += 1;
+                state.logger.success(&format!(
+                    "{}: replaced deprecated rule {} by {replacement}",
+                    rule.ruleset, rule.rule
+                ));
+            }
+            Migration::Kept { rule } => state.logger.warning(&format!(
+                "{}: deprecated rule {} has no replacement and stays enabled",
+                rule.ruleset, rule.rule
+            )),
+        }
+    }
+
+    if replaced == 0 {
+        state.logger.info("The configuration is up to date");
+    } else if !cmd.dry_run {
+        let disabled = config
+            .projects()
+            .map(|p| p.disabled_rules.clone())
+            .collect::<Vec<_>>();
+        let updated = update_disabled_rules(&text, &disabled)
+            .with_context(|| format!("Could not update config file: {}", config_path.display()))?;
+
+        std::fs::write(config_path, updated)
+            .with_context(|| format!("Could not write config file: {}", config_path.display()))?;
+
+        state
+            .logger
+            .success(&format!("Updated {}", config_path.display()));
+    }
+
+    Ok(())
+}
+
+/// Disable the deprecated rules of the ruleset that are enabled in the project, and enable their
+/// replacements instead.
+fn migrate_project(project: &mut ProjectStem, ruleset: &RuleSetStem) -> Vec<Migration> {
+    let mut migrations = vec![];
+
+    for rule in ruleset.rules.iter().filter(|r| r.is_deprecated()) {
+        let rule_ref = RuleRef {
+            ruleset: ruleset.id.clone(),
+            rule: rule.id.clone(),
+        };
+
+        if project.disabled_rules.contains(&rule_ref) {
+            continue;
+        }
+
+        let replacement = rule
+            .replaced_by
+            .as_ref()
+            .filter(|id| ruleset.rules.iter().any(|r| &r.id == *id));
+
+        let Some(replacement) = replacement else {
+            migrations.push(Migration::Kept { rule: rule_ref });
+            continue;
+        };
+
+        project
+            .disabled_rules
+            .retain(|disabled| !disabled.is(&ruleset.id, replacement));
+        project.disabled_rules.push(rule_ref.clone());
+
+        migrations.push(Migration::Replaced {
+            rule: rule_ref,
+            replacement: replacement.clone(),
+        });
+    }
+
+    migrations
+}
+
+/// Lines of the configuration file holding a project.
+#[derive(Debug, Clone, Copy)]
+struct ProjectLines {
+    start: usize,
+    end: usize,
+    /// Indentation of the keys of the project.
+    indent: usize,
+}
+
+/// Replace the `disabled_rules` lists of the projects of a configuration file by the given ones.
+/// The file is edited in place, so that its comments, key order and formatting are kept.
+fn update_disabled_rules(config: &str, disabled: &[Vec<RuleRef>]) -> anyhow::Result<String> {
+    let current: ProjectConfigStem = serde_yaml::from_str(config)?;
+    let mut lines = config.lines().map(str::to_string).collect::<Vec<_>>();
+
+    let projects = project_lines(&lines, matches!(current, ProjectConfigStem::Nested { .. }))?;
+
+    let current_projects = current.projects().collect::<Vec<_>>();
+    if projects.len() != current_projects.len() || projects.len() != disabled.len() {
+        bail!("Unexpected number of projects");
+    }
+
+    // Edit the last projects first, so that the lines of the others don't move.
+    for i in (0..projects.len()).rev() {
+        let current = &current_projects[i].disabled_rules;
+        if current != &disabled[i] {
+            update_project(&mut lines, projects[i], current, &disabled[i])?;
+        }
+    }
+
+    let mut updated = lines.join("\n");
+    if config.ends_with('\n') {
+        updated.push('\n');
+    }
+
+    let mut expected = current;
+    for (project, target) in expected.projects_mut().zip(disabled) {
+        project.disabled_rules = target.clone();
+    }
+
+    if serde_yaml::from_str::<ProjectConfigStem>(&updated)? != expected {
+        bail!("Unsupported layout of the disabled_rules lists");
+    }
+
+    Ok(updated)
+}
+
+fn update_project(
+    lines: &mut Vec<String>,
+    project: ProjectLines,
+    current: &[RuleRef],
+    target: &[RuleRef],
+) -> anyhow::Result<()> {
+    let added = target.iter().filter(|r| !current.contains(r));
+
+    let key = (project.start..project.end)
+        .find(|&i| key_value(&lines[i], project.indent, "disabled_rules").is_some());
+
+    let Some(key) = key else {
+        let at = last_line(lines, project.start, project.end) + 1;
+        let mut new_lines = vec![format!("{}disabled_rules:", " ".repeat(project.indent))];
+        for rule in added {
+            new_lines.extend(entry_lines(rule, project.indent + 2)?);
+        }
+        lines.splice(at..at, new_lines);
+        return Ok(());
+    };
+
+    let prefix = lines[key][..project.indent].to_string();
+
+    match key_value(&lines[key], project.indent, "disabled_rules") {
+        Some("") => {}
+        Some("[]") => lines[key] = format!("{prefix}disabled_rules:"),
+        _ => bail!("disabled_rules must be a block list"),
+    }
+
+    let end = (key + 1..project.end)
+        .find(|&i| {
+            let line = &lines[i];
+            let indent = indentation(line);
+            !is_blank(line)
+                && (indent < project.indent
+                    || (indent == project.indent && !line.trim_start().starts_with('-')))
+        })
+        .unwrap_or(project.end);
+
+    let (items, item_indent) = list_items(lines, key + 1, end);
+    let item_indent = item_indent.unwrap_or(project.indent + 2);
+
+    let at = items.last().map_or(key + 1, |&(_, end)| end);
+    let mut new_lines = vec![];
+    for rule in added {
+        new_lines.extend(entry_lines(rule, item_indent)?);
+    }
+    lines.splice(at..at, new_lines);
+
+    for &(start, end) in items.iter().rev() {
+        let item = lines[start..end]
+            .iter()
+            .map(|line| dedent(line, item_indent))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let rules: Vec<RuleRef> = serde_yaml::from_str(&item)?;
+
+        if rules.iter().any(|r| !target.contains(r)) {
+            lines.drain(start..end);
+        }
+    }
+
+    if target.is_empty() {
+        lines[key] = format!("{prefix}disabled_rules: []");
+    }
+
+    Ok(())
+}
+
+/// Find the projects of the configuration file, which either is a single project or holds a
+/// `projects` list.
+fn project_lines(lines: &[String], nested: bool) -> anyhow::Result<Vec<ProjectLines>> {
+    if !nested {
+        return Ok(vec![ProjectLines {
+            start: 0,
+            end: lines.len(),
+            indent: 0,
+        }]);
+    }
+
+    let Some(key) = lines
+        .iter()
+        .position(|line| key_value(line, 0, "projects") == Some(""))
+    else {
+        bail!("projects must be a block list");
+    };
+
+    let end = (key + 1..lines.len())
+        .find(|&i| {
+            !is_blank(&lines[i]) && indentation(&lines[i]) == 0 && !lines[i].starts_with('-')
+        })
+        .unwrap_or(lines.len());
+
+    let (items, item_indent) = list_items(lines, key + 1, end);
+    let item_indent = item_indent.unwrap_or_default();
+
+    Ok(items
+        .into_iter()
+        .map(|(start, end)| {
+            let rest = &lines[start][item_indent + 1..];
+            let indent = if is_blank(rest) {
+                indentation(&lines[first_line(lines, start + 1, end)])
+            } else {
+                item_indent + 1 + indentation(rest)
+            };
+
+            ProjectLines { start, end, indent }
+        })
+        .collect())
+}
+
+/// Lines of the items of the block list found between `start` and `end`, and the indentation of
+/// their dashes. Blank lines and comments following an item are not part of it.
+fn list_items(lines: &[String], start: usize, end: usize) -> (Vec<(usize, usize)>, Option<usize>) {
+    let first = first_line(lines, start, end);
+    if first == end {
+        return (vec![], None);
+    }
+
+    let indent = indentation(&lines[first]);
+    let starts = (first..end)
+        .filter(|&i| indentation(&lines[i]) == indent && lines[i].trim_start().starts_with('-'))
+        .collect::<Vec<_>>();
+
+    let items = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let next = starts.get(i + 1).copied().unwrap_or(end);
+            (start, last_line(lines, start, next) + 1)
+        })
+        .collect();
+
+    (items, Some(indent))
+}
+
+fn entry_lines(rule: &RuleRef, indent: usize) -> anyhow::Result<[String; 2]> {
+    let indent = " ".repeat(indent);
+    let scalar = |value: &str| -> anyhow::Result<String> {
+        Ok(serde_yaml::to_string(value)?.trim_end().to_string())
+    };
+
+    Ok([
+        format!("{indent}- ruleset: {}", scalar(&rule.ruleset)?),
+        format!("{indent}  rule: {}", scalar(&rule.rule)?),
+    ])
+}
+
+/// Value of the key if it is defined by the line at the given indentation, possibly after the
+/// dash of a list item.
+fn key_value<'l>(line: &'l str, indent: usize, key: &str) -> Option<&'l str> {
+    let prefix = line.get(..indent)?.trim();
+    if !prefix.is_empty() && prefix != "-" {
+        return None;
+    }
+
+    let value = line[indent..].strip_prefix(key)?.strip_prefix(':')?;
+    let value = value.split(" #").next().unwrap_or_default().trim();
+
+    Some(value)
+}
+
+/// Index of the first line that is not blank, or `end`.
+fn first_line(lines: &[String], start: usize, end: usize) -> usize {
+    (start..end).find(|&i| !is_blank(&lines[i])).unwrap_or(end)
+}
+
+/// Index of the last line that is not blank, or `start`.
+fn last_line(lines: &[String], start: usize, end: usize) -> usize {
+    (start..end)
+        .rev()
+        .find(|&i| !is_blank(&lines[i]))
+        .unwrap_or(start)
+}
+
+fn dedent(line: &str, indent: usize) -> &str {
+    if indentation(line) >= indent {
+        &line[indent..]
+    } else {
+        line.trim_start()
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Blank lines, comments and document markers.
+fn is_blank(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#') || line == "---"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_ref(rule: &str) -> RuleRef {
+        RuleRef {
+            ruleset: "rules".to_string(),
+            rule: rule.to_string(),
+        }
+    }
+
+    #[test]
+    fn deprecated_rules_are_replaced() {
+        let ruleset: RuleSetStem = serde_yaml::from_str(
+            r#"
+id: rules
+language: python
+rules:
+  - id: old
+    message: Old
+    query: match Identifier
+    category: style
+    replaced_by: new
+  - id: new
+    message: New
+    query: match Identifier
+    category: style
+  - id: obsolete
+    message: Obsolete
+    query: match Identifier
+    category: style
+    deprecated: true
+"#,
+        )
+        .unwrap();
+
+        let mut project: ProjectStem = serde_yaml::from_str(
+            r#"
+language: python
+include: ['**/*.py']
+disabled_rules:
+  - ruleset: rules
+    rule: new
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            migrate_project(&mut project, &ruleset),
+            vec![
+                Migration::Replaced {
+                    rule: rule_ref("old"),
+                    replacement: "new".to_string(),
+                },
+                Migration::Kept {
+                    rule: rule_ref("obsolete"),
+                },
+            ]
+        );
+        assert_eq!(project.disabled_rules, vec![rule_ref("old")]);
+
+        // Once migrated, the disabled rule is left alone.
+        assert_eq!(
+            migrate_project(&mut project, &ruleset),
+            vec![Migration::Kept {
+                rule: rule_ref("obsolete"),
+            }]
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_appended() {
+        let config = r#"# Sylver configuration file.
+language: python # main language
+include: ['**/*.py']
+
+# Rulesets to run
+rulesets:
+  - rules.yml
+"#;
+
+        assert_eq!(
+            update_disabled_rules(config, &[vec![rule_ref("old")]]).unwrap(),
+            format!("{config}disabled_rules:\n  - ruleset: rules\n    rule: old\n")
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_edited_in_place() {
+        let config = r#"projects:
+  # Python sources
+  - language: python
+    include: ['**/*.py']
+    disabled_rules:
+      - ruleset: rules
+        rule: new # Too noisy
+
+  - language: python
+    include: ['scripts/*.py']
+    disabled_rules: []
+"#;
+
+        assert_eq!(
+            update_disabled_rules(config, &[vec![rule_ref("old")], vec![rule_ref("old")]]).unwrap(),
+            r#"projects:
+  # Python sources
+  - language: python
+    include: ['**/*.py']
+    disabled_rules:
+      - ruleset: rules
+        rule: old
+
+  - language: python
+    include: ['scripts/*.py']
+    disabled_rules:
+      - ruleset: rules
+        rule: old
+"#
+        );
+    }
+
+    #[test]
+    fn emptied_disabled_rules() {
+        let config = r#"language: python
+include: ['**/*.py']
+disabled_rules:
+- ruleset: rules
+  rule: new
+exclude: ['tests/**']
+"#;
+
+        assert_eq!(
+            update_disabled_rules(config, &[vec![]]).unwrap(),
+            r#"language: python
+include: ['**/*.py']
+disabled_rules: []
+exclude: ['tests/**']
+"#
+        );
+    }
+}
@@ -44,7 +44,13 @@ pub fn print_reports(state: Arc<SylverState>, reports: &[Report], source: &Sourc
     for r in reports {
         println!(
             "{}",
-            render_report(state.settings.color_output, r, source).unwrap()
+            render_report(
+                state.settings.color_output,
+                &state.settings.pos_encoder,
+                r,
+                source
+            )
+            .unwrap()
         );
     }
 }
@@ -17,15 +17,15 @@ use sylver_dsl::sylq::parse_query;
 use crate::{
     cli::QueryCmd,
     repl::start_repl,
-    shared::{build_sylva, print_land_reports},
+    shared::{build_sylva, load_sources, print_land_reports, SylvaOptions},
 };
 
 pub fn query(state: Arc<SylverState>, loader: &SylverLoader, cmd: &QueryCmd) -> anyhow::Result<()> {
-    let land = build_land(loader, cmd)?;
+    let land = build_land(&state, loader, cmd)?;
     let sylva = land.sylvae().next().expect("Missing sylva");
     let spec = land.sylva_spec(sylva);
 
-    print_land_reports(state.settings.color_output, &land)?;
+    print_land_reports(&state.settings, &land)?;
 
     if let Some(query_str) = &cmd.query {
         let query = parse_query(query_str).context("Failed to parse query")?;
@@ -36,6 +36,7 @@ pub fn query(state: Arc<SylverState>, loader: &SylverLoader, cmd: &QueryCmd) ->
             PythonScriptEngine::default(),
             sylva,
             &query_predicate,
+            &state.cancel,
         )? {
             let tree = land.sylva_node_tree(sylva_node);
             println!("{}", render_node(spec, tree, sylva_node.node));
@@ -47,16 +48,29 @@ pub fn query(state: Arc<SylverState>, loader: &SylverLoader, cmd: &QueryCmd) ->
     }
 }
 
-fn build_land(loader: &SylverLoader, cmd: &QueryCmd) -> anyhow::Result<Land> {
+fn build_land(state: &SylverState, loader: &SylverLoader, cmd: &QueryCmd) -> anyhow::Result<Land> {
     let mut builder = LandBuilder::new();
 
-    let sources = loader.load_file_spec(&FileSpec {
-        root: None,
-        include: cmd.files.clone(),
-        exclude: cmd.exclude.clone(),
-    })?;
+    let sources = load_sources(
+        state,
+        loader,
+        &FileSpec {
+            root: None,
+            include: cmd.files.clone(),
+            exclude: cmd.exclude.clone(),
+            encoding: cmd.encoding.into(),
+            adapter: cmd.language.input_adapter(),
+        },
+    )?;
 
-    build_sylva(loader, &mut builder, &cmd.language, sources)?;
+    build_sylva(
+        state,
+        loader,
+        &mut builder,
+        &cmd.language,
+        &SylvaOptions::global(state),
+        sources,
+    )?;
 
     Ok(builder.build())
 }
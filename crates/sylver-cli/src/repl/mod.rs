@@ -12,6 +12,7 @@ use sylver_core::{
     query::{language::compile::compile, SylvaNode},
     script::python::PythonScriptEngine,
     tree::info::{raw::RawTreeInfo, TreeInfo},
+    util::cancel::CancellationToken,
 };
 
 use sylver_dsl::sylq::parse_query;
@@ -113,6 +114,8 @@ fn do_run_query(ctx: &mut ReplCtx, query_code: &str) -> anyhow::Result<()> {
         PythonScriptEngine::default(),
         ctx.sylva,
         &query_predicate,
+        // Queries run from the repl are not cancellable.
+        &CancellationToken::default(),
     )? {
         let cache_id = ctx.nodes_cache.push(sylva_node);
         let tree = ctx.land.sylva_node_tree(sylva_node);
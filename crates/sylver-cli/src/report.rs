@@ -0,0 +1,330 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use sylver_core::{
+    core::{
+        pos::{InclPosRange, PosEncoder},
+        source::Source,
+    },
+    dashboard::api::Position,
+    land::{
+        cmds::{parsing_errors, RuleFailure, RuleResult, RulesExecution},
+        ruleset::{FindingMetadata, RuleCategory, RuleSetId, RuleTaxonomy},
+        Land,
+    },
+    util::fs::{path_to_string, PathPrefixMap},
+};
+
+use crate::stream::{write_pretty_json, ReportWriter};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonFinding {
+    pub file: String,
+    pub ruleset: String,
+    pub rule: String,
+    pub category: RuleCategory,
+    pub message: String,
+    pub note: Option<String>,
+    pub doc_url: Option<String>,
+    pub position: Position,
+    /// Notebook cell of the finding, for notebook sources.
+    pub cell: Option<usize>,
+    /// Custom metadata of the rule.
+    #[serde(skip_serializing_if = "FindingMetadata::is_empty")]
+    pub properties: FindingMetadata,
+    /// CWE and OWASP classifications of the rule.
+    #[serde(skip_serializing_if = "RuleTaxonomy::is_empty")]
+    pub taxonomy: RuleTaxonomy,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonDiagnostic {
+    pub file: String,
+    pub code: String,
+    pub message: String,
+    pub position: Position,
+    pub cell: Option<usize>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct JsonToolError {
+    pub file: String,
+    pub ruleset: String,
+    pub rule: String,
+    pub error: String,
+    /// Python traceback, for script errors.
+    pub traceback: Option<String>,
+    pub position: Position,
+    pub cell: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Statistics {
+    pub files_analyzed: usize,
+    pub parse_errors: usize,
+    pub rules: Vec<RuleStatistics>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleStatistics {
+    pub ruleset: String,
+    pub rule: String,
+    pub findings: usize,
+    pub evaluation_seconds: f64,
+}
+
+/// Streamed JSON document holding the findings of a run, the parsing errors, the rules that
+/// failed to evaluate, and statistics about the analysis.
+pub struct JsonReportWriter<'a> {
+    pos_encoder: &'a PosEncoder,
+    land: &'a Land,
+    path_map: &'a PathPrefixMap,
+    written_findings: usize,
+}
+
+impl<'a> JsonReportWriter<'a> {
+    pub fn new(
+        pos_encoder: &'a PosEncoder,
+        land: &'a Land,
+        path_map: &'a PathPrefixMap,
+    ) -> JsonReportWriter<'a> {
+        JsonReportWriter {
+            pos_encoder,
+            land,
+            path_map,
+            written_findings: 0,
+        }
+    }
+
+    fn diagnostics(&self) -> Vec<JsonDiagnostic> {
+        let mut diagnostics: Vec<JsonDiagnostic> = parsing_errors(self.land)
+            .into_iter()
+            .flat_map(|(source, reports)| {
+                reports.iter().map(move |report| {
+                    let (position, cell) =
+                        encode_position(self.pos_encoder, source, report.position);
+
+                    JsonDiagnostic {
+                        file: self.path_map.remap_str(&path_to_string(&report.file_path)),
+                        code: report.code.clone(),
+                        message: report.message.clone(),
+                        position,
+                        cell,
+                    }
+                })
+            })
+            .collect();
+
+        diagnostics.sort_by(|d1, d2| {
+            (&d1.file, d1.position.start_txt_pos).cmp(&(&d2.file, d2.position.start_txt_pos))
+        });
+
+        diagnostics
+    }
+}
+
+impl<'a> ReportWriter for JsonReportWriter<'a> {
+    fn begin(&mut self, out: &mut dyn Write) -> anyhow::Result<()> {
+        write!(out, "{{\n  \"findings\": [")?;
+        Ok(())
+    }
+
+    fn finding(&mut self, out: &mut dyn Write, result: &RuleResult) -> anyhow::Result<()> {
+        let mut finding = JsonFinding::new(self.pos_encoder, self.land, result);
+        finding.file = self.path_map.remap_str(&finding.file);
+
+        let separator = if self.written_findings == 0 { "" } else { "," };
+        write!(out, "{separator}\n    ")?;
+        write_pretty_json(out, &finding, 2)?;
+        self.written_findings += 1;
+
+        Ok(())
+    }
+
+    fn end(&mut self, out: &mut dyn Write, execution: &RulesExecution) -> anyhow::Result<()> {
+        if self.written_findings > 0 {
+            write!(out, "\n  ")?;
+        }
+
+        let tool_errors: Vec<JsonToolError> = execution
+            .failures
+            .iter()
+            .map(|f| {
+                let mut error = JsonToolError::new(self.pos_encoder, self.land, f);
+                error.file = self.path_map.remap_str(&error.file);
+                error
+            })
+            .collect();
+
+        write!(out, "],\n  \"diagnostics\": ")?;
+        write_pretty_json(out, &self.diagnostics(), 1)?;
+        write!(out, ",\n  \"tool_errors\": ")?;
+        write_pretty_json(out, &tool_errors, 1)?;
+        write!(out, ",\n  \"statistics\": ")?;
+        write_pretty_json(
+            out,
+            &Statistics::new(self.land, &execution.durations, &execution.results),
+            1,
+        )?;
+        writeln!(out, "\n}}")?;
+
+        Ok(())
+    }
+}
+
+impl JsonFinding {
+    pub fn new(pos_encoder: &PosEncoder, land: &Land, result: &RuleResult) -> JsonFinding {
+        let report = result.to_report(land);
+        let source = result.source(land);
+        let (position, cell) = encode_position(pos_encoder, source, report.position);
+        let rule = result.rule(land);
+
+        JsonFinding {
+            file: path_to_string(&report.file_path),
+            ruleset: land.ruleset(result.ruleset).id().to_string(),
+            rule: report.code,
+            category: rule.category,
+            message: report.message,
+            note: report.note,
+            doc_url: report.doc_url,
+            position,
+            cell,
+            properties: rule.metadata.clone(),
+            taxonomy: rule.taxonomy.clone(),
+        }
+    }
+}
+
+impl JsonToolError {
+    pub fn new(pos_encoder: &PosEncoder, land: &Land, failure: &RuleFailure) -> JsonToolError {
+        let report = failure.to_report(land);
+        let source = failure.source(land);
+        let (position, cell) = encode_position(pos_encoder, source, report.position);
+
+        JsonToolError {
+            file: path_to_string(&report.file_path),
+            ruleset: land.ruleset(failure.ruleset).id().to_string(),
+            rule: failure.rule_id.clone(),
+            error: failure.message(),
+            traceback: failure.traceback().map(str::to_string),
+            position,
+            cell,
+        }
+    }
+}
+
+impl Statistics {
+    fn new(
+        land: &Land,
+        durations: &HashMap<(RuleSetId, String), Duration>,
+        results: &[RuleResult],
+    ) -> Statistics {
+        let mut findings: HashMap<(RuleSetId, &str), usize> = HashMap::new();
+
+        for result in results {
+            *findings
+                .entry((result.ruleset, &result.rule_id))
+                .or_default() += 1;
+        }
+
+        let rules: BTreeMap<&(RuleSetId, String), RuleStatistics> = durations
+            .iter()
+            .map(|(key @ (ruleset, rule), duration)| {
+                let stats = RuleStatistics {
+                    ruleset: land.ruleset(*ruleset).id().to_string(),
+                    rule: rule.clone(),
+                    findings: findings
+                        .get(&(*ruleset, rule.as_str()))
+                        .copied()
+                        .unwrap_or_default(),
+                    evaluation_seconds: duration.as_secs_f64(),
+                };
+                (key, stats)
+            })
+            .collect();
+
+        Statistics {
+            files_analyzed: land.sylvae().map(|s| land.sylva(s).iter().count()).sum(),
+            parse_errors: parsing_errors(land).values().map(|r| r.len()).sum(),
+            rules: rules.into_values().collect(),
+        }
+    }
+}
+
+/// Encode the given range of the source. For notebooks, the lines and offsets are relative to the
+/// cell, which is returned as well. For preprocessed sources, the position is the one of the
+/// original file.
+pub(crate) fn encode_position(
+    pos_encoder: &PosEncoder,
+    source: &Source,
+    range: InclPosRange,
+) -> (Position, Option<usize>) {
+    let mut position: Position = match source.source_map() {
+        Some(map) => pos_encoder
+            .encode_range(map.original(), map.original_range(source.src(), range))
+            .into(),
+        None => pos_encoder.encode_range(source.src(), range).into(),
+    };
+
+    let cell_position = source
+        .notebook_cells()
+        .and_then(|cells| cells.cell_position(position.start_line));
+
+    match cell_position {
+        Some((cell, line)) => {
+            let offset = position.start_line - line;
+            let cell_start: usize = source
+                .src()
+                .split_inclusive('\n')
+                .take(offset)
+                .map(str::len)
+                .sum();
+
+            position.start_line -= offset;
+            position.end_line -= offset;
+            position.start_txt_pos -= cell_start;
+            position.end_txt_pos -= cell_start;
+            (position, Some(cell))
+        }
+        None => (position, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sylver_core::core::{
+        notebook::notebook_code,
+        pos::{ColumnEncoding, Pos},
+    };
+
+    use super::*;
+
+    #[test]
+    fn notebook_positions_are_relative_to_the_cell() {
+        let notebook = r#"{
+            "cells": [
+                { "cell_type": "code", "source": "import os\n" },
+                { "cell_type": "code", "source": "x = 1\ny = 2\n" }
+            ]
+        }"#;
+        let (code, cells) = notebook_code(notebook).unwrap();
+        let source = Source::notebook(code.clone(), "nb.ipynb".into(), cells);
+
+        let start = code.find('y').unwrap();
+        let range =
+            InclPosRange::new(Pos::new((3, 1), start), Pos::new((3, 6), start + 5)).unwrap();
+        let encoder = PosEncoder::new(ColumnEncoding::Utf8, None);
+
+        let (position, cell) = encode_position(&encoder, &source, range);
+
+        assert_eq!(cell, Some(2));
+        assert_eq!((position.start_line, position.start_col), (2, 1));
+        assert_eq!(position.start_txt_pos, "x = 1\n".len());
+        assert_eq!(position.end_txt_pos, "x = 1\ny = 2".len());
+    }
+}
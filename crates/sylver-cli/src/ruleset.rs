@@ -4,17 +4,18 @@ use itertools::*;
 
 use sylver_core::{
     core::files_spec::FileSpec,
-    land::{builder::LandBuilder, Land},
+    land::{builder::LandBuilder, ruleset::StopCondition, Land},
     specs::{
         loader::SylverLoader,
         stem::{location::StemLocation, ruleset::RuleSetStem},
+        trust::TrustPolicy,
     },
     state::SylverState,
 };
 
 use crate::{
     cli::{RulesetCmd, RulesetCmds, RulesetRun},
-    shared::{build_sylva, verify_land},
+    shared::{build_sylva, load_sources, verify_land, warn_renamed_kinds, SylvaOptions},
 };
 
 pub fn ruleset_cmd(
@@ -32,22 +33,40 @@ fn run_ruleset(
     loader: &SylverLoader,
     cmd: &RulesetRun,
 ) -> anyhow::Result<()> {
-    verify_land(state.settings.color_output, &build_land(loader, cmd)?)
+    verify_land(
+        &state,
+        &build_land(&state, loader, cmd)?,
+        &cmd.exit_policy,
+        StopCondition::from(&cmd.stop),
+        &cmd.report,
+    )
 }
 
-fn build_land(loader: &SylverLoader, cmd: &RulesetRun) -> anyhow::Result<Land> {
-    let sources = loader.load_file_spec(&FileSpec {
-        root: None,
-        include: cmd.files.clone(),
-        exclude: cmd.exclude.clone(),
-    })?;
+fn build_land(
+    state: &SylverState,
+    loader: &SylverLoader,
+    cmd: &RulesetRun,
+) -> anyhow::Result<Land> {
+    let sources = load_sources(
+        state,
+        loader,
+        &FileSpec {
+            root: None,
+            include: cmd.files.clone(),
+            exclude: cmd.exclude.clone(),
+            encoding: cmd.encoding.into(),
+            adapter: Default::default(),
+        },
+    )?;
 
     let mut builder = LandBuilder::new();
 
+    let policy = TrustPolicy::global(state.settings.require_signed);
+
     let ruleset_stems: Vec<RuleSetStem> = cmd
         .rulesets
         .iter()
-        .map(|path| loader.load_ruleset(&StemLocation::from(path.as_str())))
+        .map(|path| loader.load_ruleset(&StemLocation::from(path.as_str()), &policy))
         .collect::<anyhow::Result<Vec<RuleSetStem>>>()?;
 
     let ruleset_per_lang = ruleset_stems
@@ -55,13 +74,32 @@ fn build_land(loader: &SylverLoader, cmd: &RulesetRun) -> anyhow::Result<Land> {
         .map(|stem| (stem.language.clone(), stem))
         .into_group_map();
 
+    let mut ruleset_ids = vec![];
+
     for (language, rulesets) in ruleset_per_lang {
-        let sylva_id = build_sylva(loader, &mut builder, &language, sources.clone())?;
+        let sylva_id = build_sylva(
+            state,
+            loader,
+            &mut builder,
+            &language,
+            &SylvaOptions::global(state),
+            sources.clone(),
+        )?;
 
         for rs in rulesets {
-            builder.add_ruleset(sylva_id, &rs)?;
+            let id = builder.add_ruleset(sylva_id, &rs)?;
+            ruleset_ids.push((id, rs.id));
         }
     }
 
-    Ok(builder.build())
+    let land = builder.build();
+
+    warn_renamed_kinds(
+        state,
+        ruleset_ids
+            .into_iter()
+            .map(|(id, ruleset)| (ruleset, land.ruleset(id))),
+    );
+
+    Ok(land)
 }
@@ -0,0 +1,360 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use sylver_core::{
+    core::{pos::PosEncoder, source::Source},
+    dashboard::api::Position,
+    land::{
+        cmds::{parsing_errors, RuleResult, RulesExecution},
+        ruleset::{FindingMetadata, Rule, RuleCategory, RuleTaxonomy},
+        Land,
+    },
+    report::{Report, PARSE_ERROR_CODE},
+    util::fs::{path_to_string, PathPrefixMap},
+};
+
+use crate::{
+    report::encode_position,
+    stream::{write_pretty_json, ReportWriter},
+};
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const CWE_TAXONOMY: &str = "CWE";
+const OWASP_TAXONOMY: &str = "OWASP";
+
+/// Findings of a run as a SARIF 2.1.0 log, with one run.
+///
+/// The results are streamed first: the descriptors of the rules that have findings, and the CWE
+/// and OWASP taxonomies they belong to, are only known at the end of the run, so they follow the
+/// results in the document. Parsing errors and rule failures are reported as tool notifications.
+pub struct SarifWriter<'a> {
+    pos_encoder: &'a PosEncoder,
+    land: &'a Land,
+    path_map: &'a PathPrefixMap,
+    rules: Vec<SarifRule>,
+    rule_indices: HashMap<String, usize>,
+    written_results: usize,
+}
+
+impl<'a> SarifWriter<'a> {
+    pub fn new(
+        pos_encoder: &'a PosEncoder,
+        land: &'a Land,
+        path_map: &'a PathPrefixMap,
+    ) -> SarifWriter<'a> {
+        SarifWriter {
+            pos_encoder,
+            land,
+            path_map,
+            rules: vec![],
+            rule_indices: HashMap::new(),
+            written_results: 0,
+        }
+    }
+
+    /// Index of the descriptor of the given rule, which is added on its first finding.
+    fn rule_index(&mut self, id: String, rule: &Rule) -> usize {
+        if let Some(&index) = self.rule_indices.get(&id) {
+            return index;
+        }
+
+        let index = self.rules.len();
+        self.rules.push(SarifRule::new(id.clone(), rule));
+        self.rule_indices.insert(id, index);
+
+        index
+    }
+
+    fn location(&self, source: &Source, report: &Report) -> Value {
+        let (position, cell) = encode_position(self.pos_encoder, source, report.position);
+        let uri = path_to_string(&self.path_map.remap(&report.file_path));
+
+        location(&uri, &position, cell)
+    }
+
+    fn notifications(&self, execution: &RulesExecution) -> Vec<Value> {
+        let mut parse_errors: Vec<(&Source, &Report)> = parsing_errors(self.land)
+            .into_iter()
+            .flat_map(|(source, reports)| reports.iter().map(move |r| (source, r)))
+            .collect();
+        parse_errors.sort_by_key(|(_, r)| (&r.file_path, r.position.start().txt_pos()));
+
+        let parse_errors = parse_errors.into_iter().map(|(source, report)| {
+            json!({
+                "descriptor": { "id": PARSE_ERROR_CODE },
+                "level": "error",
+                "message": { "text": report.message },
+                "locations": [self.location(source, report)],
+            })
+        });
+
+        let failures = execution.failures.iter().map(|failure| {
+            let ruleset = self.land.ruleset(failure.ruleset).id();
+            let report = failure.to_report(self.land);
+
+            json!({
+                "descriptor": { "id": failure.rule_id },
+                "associatedRule": { "id": rule_id(ruleset, &failure.rule_id) },
+                "level": "error",
+                "message": { "text": failure.message() },
+                "locations": [self.location(failure.source(self.land), &report)],
+            })
+        });
+
+        parse_errors.chain(failures).collect()
+    }
+}
+
+impl<'a> ReportWriter for SarifWriter<'a> {
+    fn begin(&mut self, out: &mut dyn Write) -> anyhow::Result<()> {
+        write!(
+            out,
+            "{{\n  \"$schema\": \"{SARIF_SCHEMA}\",\n  \"version\": \"2.1.0\",\n  \"runs\": [\n    \
+             {{\n      \"results\": ["
+        )?;
+        Ok(())
+    }
+
+    fn finding(&mut self, out: &mut dyn Write, result: &RuleResult) -> anyhow::Result<()> {
+        let rule = result.rule(self.land);
+        let id = rule_id(self.land.ruleset(result.ruleset).id(), &result.rule_id);
+        let separator = if self.written_results == 0 { "" } else { "," };
+        let rule_index = self.rule_index(id.clone(), rule);
+
+        let report = result.to_report(self.land);
+        let mut sarif_result = json!({
+            "ruleId": id,
+            "ruleIndex": rule_index,
+            "level": level(rule.category),
+            "message": { "text": report.message },
+            "locations": [self.location(result.source(self.land), &report)],
+        });
+
+        if !rule.metadata.is_empty() {
+            sarif_result["properties"] = serde_json::to_value(&rule.metadata)?;
+        }
+
+        write!(out, "{separator}\n        ")?;
+        write_pretty_json(out, &sarif_result, 4)?;
+        self.written_results += 1;
+
+        Ok(())
+    }
+
+    fn end(&mut self, out: &mut dyn Write, execution: &RulesExecution) -> anyhow::Result<()> {
+        if self.written_results > 0 {
+            write!(out, "\n      ")?;
+        }
+
+        let tool = json!({
+            "driver": {
+                "name": "sylver",
+                "informationUri": "https://sylver.dev",
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": self.rules,
+            }
+        });
+
+        let notifications = self.notifications(execution);
+        let invocations = json!([{
+            "executionSuccessful": execution.failures.is_empty(),
+            "toolExecutionNotifications": notifications,
+        }]);
+
+        write!(out, "],\n      \"tool\": ")?;
+        write_pretty_json(out, &tool, 3)?;
+        write!(out, ",\n      \"taxonomies\": ")?;
+        write_pretty_json(out, &taxonomies(self.rules.iter().map(|r| &r.taxonomy)), 3)?;
+        write!(out, ",\n      \"invocations\": ")?;
+        write_pretty_json(out, &invocations, 3)?;
+        writeln!(out, "\n    }}\n  ]\n}}")?;
+
+        Ok(())
+    }
+}
+
+/// Reporting descriptor of a rule.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRule {
+    id: String,
+    short_description: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    full_description: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_uri: Option<String>,
+    default_configuration: Value,
+    /// The CWE and OWASP taxa of the rule.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    relationships: Vec<Value>,
+    #[serde(skip_serializing_if = "FindingMetadata::is_empty")]
+    properties: FindingMetadata,
+    #[serde(skip)]
+    taxonomy: RuleTaxonomy,
+}
+
+impl SarifRule {
+    fn new(id: String, rule: &Rule) -> SarifRule {
+        SarifRule {
+            id,
+            short_description: json!({ "text": rule.message }),
+            full_description: rule.note.as_ref().map(|note| json!({ "text": note })),
+            help_uri: rule.doc_url.clone(),
+            default_configuration: json!({ "level": level(rule.category) }),
+            relationships: taxa(&rule.taxonomy)
+                .map(|(taxonomy, id)| {
+                    json!({
+                        "target": { "id": id, "toolComponent": { "name": taxonomy } },
+                        "kinds": ["superset"],
+                    })
+                })
+                .collect(),
+            properties: rule.metadata.clone(),
+            taxonomy: rule.taxonomy.clone(),
+        }
+    }
+}
+
+/// `(taxonomy name, taxon id)` pairs of the classifications of a rule.
+fn taxa(taxonomy: &RuleTaxonomy) -> impl Iterator<Item = (&'static str, String)> + '_ {
+    let cwe = taxonomy.cwe.map(|cwe| (CWE_TAXONOMY, cwe.to_string()));
+    let owasp = taxonomy.owasp.clone().map(|owasp| (OWASP_TAXONOMY, owasp));
+
+    cwe.into_iter().chain(owasp)
+}
+
+/// Taxonomies referenced by the given rules, with the taxa they use.
+fn taxonomies<'t>(rules: impl Iterator<Item = &'t RuleTaxonomy>) -> Vec<Value> {
+    let mut cwe = BTreeSet::new();
+    let mut owasp = BTreeSet::new();
+
+    for (taxonomy, id) in rules.flat_map(taxa) {
+        match taxonomy {
+            CWE_TAXONOMY => cwe.insert(id),
+            _ => owasp.insert(id),
+        };
+    }
+
+    let mut taxonomies = vec![];
+
+    if !cwe.is_empty() {
+        taxonomies.push(json!({
+            "name": CWE_TAXONOMY,
+            "organization": "MITRE",
+            "informationUri": "https://cwe.mitre.org/",
+            "taxa": cwe
+                .iter()
+                .map(|id| json!({
+                    "id": id,
+                    "name": format!("CWE-{id}"),
+                    "helpUri": format!("https://cwe.mitre.org/data/definitions/{id}.html"),
+                }))
+                .collect::<Vec<_>>(),
+        }));
+    }
+
+    if !owasp.is_empty() {
+        taxonomies.push(json!({
+            "name": OWASP_TAXONOMY,
+            "organization": "OWASP",
+            "informationUri": "https://owasp.org/Top10/",
+            "taxa": owasp.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+        }));
+    }
+
+    taxonomies
+}
+
+fn rule_id(ruleset: &str, rule: &str) -> String {
+    format!("{ruleset}/{rule}")
+}
+
+/// Physical location of a range. The lines of the findings in notebooks are relative to their
+/// cell, which is given as a property.
+fn location(uri: &str, position: &Position, cell: Option<usize>) -> Value {
+    let mut location = json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": uri },
+            "region": {
+                "startLine": position.start_line,
+                "startColumn": position.start_col,
+                "endLine": position.end_line,
+                "endColumn": position.end_col,
+            },
+        }
+    });
+
+    if let Some(cell) = cell {
+        location["properties"] = json!({ "cell": cell });
+    }
+
+    location
+}
+
+fn level(category: RuleCategory) -> &'static str {
+    match category {
+        RuleCategory::Style => "note",
+        RuleCategory::Smell | RuleCategory::Deprecated => "warning",
+        RuleCategory::Bug | RuleCategory::Error => "error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn taxonomy_relationships() {
+        let taxonomy = RuleTaxonomy {
+            cwe: Some(89),
+            owasp: Some("A03:2021".to_string()),
+        };
+
+        assert_eq!(
+            taxa(&taxonomy).collect::<Vec<_>>(),
+            vec![
+                (CWE_TAXONOMY, "89".to_string()),
+                (OWASP_TAXONOMY, "A03:2021".to_string())
+            ]
+        );
+
+        let other = RuleTaxonomy {
+            cwe: Some(79),
+            owasp: None,
+        };
+        let taxonomies = taxonomies([&taxonomy, &other, &RuleTaxonomy::default()].into_iter());
+
+        assert_eq!(taxonomies.len(), 2);
+        assert_eq!(taxonomies[0]["name"], CWE_TAXONOMY);
+        assert_eq!(taxonomies[0]["taxa"][0]["id"], "79");
+        assert_eq!(taxonomies[0]["taxa"][1]["name"], "CWE-89");
+        assert_eq!(taxonomies[1]["taxa"], json!([{ "id": "A03:2021" }]));
+
+        assert!(taxonomies([RuleTaxonomy::default()].iter()).is_empty());
+    }
+
+    #[test]
+    fn region_columns() {
+        let position = Position {
+            start_line: 2,
+            start_col: 5,
+            start_txt_pos: 10,
+            end_line: 2,
+            end_col: 8,
+            end_txt_pos: 13,
+        };
+
+        let location = location("src/main.py", &position, Some(3));
+
+        assert_eq!(
+            location["physicalLocation"]["region"],
+            json!({ "startLine": 2, "startColumn": 5, "endLine": 2, "endColumn": 8 })
+        );
+        assert_eq!(location["properties"]["cell"], 3);
+    }
+}
@@ -0,0 +1,702 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+
+use sylver_core::{
+    core::{source::Source, store::ContentStore},
+    land::{
+        builder::LandBuilder, cache::RuleCache, cmds::exec_rules, ruleset::StopCondition, Land,
+    },
+    specs::{
+        loader::SylverLoader,
+        stem::{location::StemLocation, project::ProjectLang},
+        trust::TrustPolicy,
+    },
+    state::SylverState,
+};
+
+use crate::{
+    cli::ServeCmd,
+    report::JsonFinding,
+    shared::{build_sylva, warn_renamed_kinds, SylvaOptions},
+    watch::LiveCheck,
+};
+
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+const ANALYSIS_ERROR: i64 = -32000;
+const UNAUTHENTICATED: i64 = -32001;
+
+/// Delay between two checks of the cancellation while waiting for connections and messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Serve analyses over TCP with JSON-RPC 2.0, one message per line.
+///
+/// The server only listens on the loopback interface (remote clients go through a tunnel), and a
+/// connection must start with an `authenticate` request giving the token of the server. An
+/// `analyze` request runs the rules of a configuration file, or of rulesets over submitted
+/// sources. Each finding is sent back as a `finding` notification, and the response gives the
+/// number of findings. A `release` request drops the state kept for a configuration file.
+/// Notifications (requests without an `id`) get no response.
+///
+/// Each connection is served on its own thread. One server can serve several workspaces: each
+/// configuration file has its own state, and its relative paths are resolved against its
+/// directory. The land of a workspace is kept between requests and follows the edits of its
+/// files, as in `check --watch`: modified rulesets are recompiled, modified specs and sources are
+/// parsed again, and the cached results of the rules are invalidated accordingly. Idle workspaces
+/// are reloaded in the background, so that an error in an edited ruleset is logged right away.
+/// The trees of the submitted sources are kept until the next request, so that unchanged files
+/// are not parsed again.
+pub fn serve(state: Arc<SylverState>, loader: &SylverLoader, cmd: &ServeCmd) -> anyhow::Result<()> {
+    if cmd.token.is_empty() {
+        bail!("The server token cannot be empty");
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, cmd.port))
+        .with_context(|| format!("Could not listen on port {}", cmd.port))?;
+
+    // Accepting without blocking lets the loop notice a cancellation.
+    listener.set_nonblocking(true)?;
+
+    state
+        .logger
+        .info(&format!("Listening on {}", listener.local_addr()?));
+
+    let workspaces = Workspaces::default();
+
+    thread::scope(|scope| {
+        while !state.cancel.is_cancelled() {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    workspaces.reload(&state, loader);
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(e) => {
+                    state.logger.error(&format!("{e:?}"));
+                    continue;
+                }
+            };
+
+            let (state, workspaces) = (&state, &workspaces);
+            scope.spawn(move || {
+                if let Err(e) = serve_connection(state, loader, workspaces, &cmd.token, stream) {
+                    state.logger.error(&format!("{e:?}"));
+                }
+            });
+        }
+    });
+
+    state.cancel.check()?;
+
+    Ok(())
+}
+
+/// State kept between the requests on a configuration file: its land, once it has been analyzed.
+#[derive(Default)]
+struct Workspace {
+    live: Option<LiveCheck>,
+}
+
+/// Workspaces of the server, keyed by the canonical path of their configuration file. The
+/// submitted sources share a content store of their own.
+#[derive(Default)]
+struct Workspaces {
+    configs: Mutex<HashMap<PathBuf, Arc<Mutex<Workspace>>>>,
+    submitted: Mutex<ContentStore>,
+}
+
+impl Workspaces {
+    fn get(&self, config: &Path) -> Arc<Mutex<Workspace>> {
+        self.configs
+            .lock()
+            .expect("poisoned workspaces lock")
+            .entry(config.to_owned())
+            .or_default()
+            .clone()
+    }
+
+    /// Drop the state of a workspace. Return false if it had none.
+    fn release(&self, config: &Path) -> bool {
+        self.configs
+            .lock()
+            .expect("poisoned workspaces lock")
+            .remove(config)
+            .is_some()
+    }
+
+    /// Apply the modifications of the files of the workspaces that are not being analyzed.
+    fn reload(&self, state: &SylverState, loader: &SylverLoader) {
+        let configs: Vec<(PathBuf, Arc<Mutex<Workspace>>)> = self
+            .configs
+            .lock()
+            .expect("poisoned workspaces lock")
+            .iter()
+            .map(|(config, workspace)| (config.clone(), workspace.clone()))
+            .collect();
+
+        for (config, workspace) in configs {
+            // A busy workspace is brought up to date by its own request.
+            let Ok(mut workspace) = workspace.try_lock() else {
+                continue;
+            };
+
+            let Some(live) = &mut workspace.live else {
+                continue;
+            };
+
+            match live.update(state, loader) {
+                Ok(true) => state.logger.info(&format!("Reloaded {}", config.display())),
+                Ok(false) => {}
+                Err(e) => state.logger.error(&format!("{}: {e:?}", config.display())),
+            }
+        }
+    }
+}
+
+fn serve_connection(
+    state: &SylverState,
+    loader: &SylverLoader,
+    workspaces: &Workspaces,
+    token: &str,
+    stream: TcpStream,
+) -> anyhow::Result<()> {
+    stream.set_nonblocking(false)?;
+    // Reads time out regularly so that an idle connection notices a cancellation.
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let mut authenticated = false;
+    let mut line = vec![];
+
+    while !state.cancel.is_cancelled() {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            // The bytes read before the timeout stay in `line`.
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e.into()),
+        }
+
+        if !line.ends_with(b"\n") {
+            // End of the stream in the middle of a message.
+            break;
+        }
+
+        let message = String::from_utf8_lossy(&line).into_owned();
+        line.clear();
+
+        if message.trim().is_empty() {
+            continue;
+        }
+
+        let response = match parse_request(&message) {
+            Ok(request) => {
+                let id = request.id.clone();
+                let handled = if authenticated {
+                    handle_request(state, loader, workspaces, request, &mut writer)
+                } else {
+                    authenticate(token, request).map(|result| {
+                        authenticated = true;
+                        result
+                    })
+                };
+
+                // Notifications get no response, even when they fail.
+                let Some(id) = id else {
+                    continue;
+                };
+
+                match handled {
+                    Ok(result) => RpcResponse::result(id, result),
+                    Err(error) => RpcResponse::error(id, error),
+                }
+            }
+            Err(error) => RpcResponse::error(Value::Null, error),
+        };
+
+        write_message(&mut writer, &response)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RpcRequest {
+    jsonrpc: String,
+    /// Missing for notifications. A `null` id is still a request.
+    #[serde(default, deserialize_with = "present")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn result(id: Value, result: Value) -> RpcResponse {
+        RpcResponse {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Value, error: RpcError) -> RpcResponse {
+        RpcResponse {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Parameters of an `analyze` request: either a configuration file, or a language, rulesets and
+/// the sources to analyze.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum AnalyzeParams {
+    Config {
+        config: PathBuf,
+    },
+    Sources {
+        language: ProjectLang,
+        rulesets: Vec<StemLocation>,
+        sources: Vec<SubmittedSource>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseParams {
+    config: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmittedSource {
+    path: PathBuf,
+    code: String,
+}
+
+pub(crate) fn parse_request(line: &str) -> Result<RpcRequest, RpcError> {
+    let value: Value =
+        serde_json::from_str(line).map_err(|e| RpcError::new(PARSE_ERROR, e.to_string()))?;
+
+    let request: RpcRequest =
+        serde_json::from_value(value).map_err(|e| RpcError::new(INVALID_REQUEST, e.to_string()))?;
+
+    if request.jsonrpc != "2.0" {
+        return Err(RpcError::new(
+            INVALID_REQUEST,
+            "Unsupported JSON-RPC version",
+        ));
+    }
+
+    Ok(request)
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthenticateParams {
+    token: String,
+}
+
+/// Handle the first request of a connection, which must give the token of the server.
+fn authenticate(token: &str, request: RpcRequest) -> Result<Value, RpcError> {
+    if request.method != "authenticate" {
+        return Err(RpcError::new(
+            UNAUTHENTICATED,
+            "The connection must be authenticated first",
+        ));
+    }
+
+    let params: AuthenticateParams = serde_json::from_value(request.params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+
+    if !same_token(token, &params.token) {
+        return Err(RpcError::new(UNAUTHENTICATED, "Invalid token"));
+    }
+
+    Ok(json!({}))
+}
+
+/// Compare the tokens in a time that doesn't depend on the position of the first difference.
+fn same_token(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (e, g)| diff | (e ^ g))
+            == 0
+}
+
+fn handle_request(
+    state: &SylverState,
+    loader: &SylverLoader,
+    workspaces: &Workspaces,
+    request: RpcRequest,
+    writer: &mut impl Write,
+) -> Result<Value, RpcError> {
+    match request.method.as_str() {
+        "analyze" => {
+            let params: AnalyzeParams = serde_json::from_value(request.params)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+
+            check_submitted(&params).map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+
+            analyze(
+                state,
+                loader,
+                workspaces,
+                request.id.as_ref(),
+                params,
+                writer,
+            )
+            .map_err(|e| RpcError::new(ANALYSIS_ERROR, format!("{e:#}")))
+        }
+        "release" => {
+            let params: ReleaseParams = serde_json::from_value(request.params)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+
+            // A configuration that no longer exists has no workspace either.
+            let released = params
+                .config
+                .canonicalize()
+                .map_or(false, |config| workspaces.release(&config));
+
+            Ok(json!({ "released": released }))
+        }
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {method}"),
+        )),
+    }
+}
+
+/// Submitted sources can only be analyzed with builtin languages and rulesets that don't come
+/// from arbitrary git repositories: custom specs, grammars and git rulesets run code.
+fn check_submitted(params: &AnalyzeParams) -> Result<(), String> {
+    let AnalyzeParams::Sources {
+        language, rulesets, ..
+    } = params
+    else {
+        return Ok(());
+    };
+
+    if !matches!(language, ProjectLang::Builtin(_)) {
+        return Err(
+            "Custom languages and external grammars cannot be used with submitted sources"
+                .to_string(),
+        );
+    }
+
+    if let Some(ruleset) = rulesets
+        .iter()
+        .find(|r| matches!(r, StemLocation::Git { .. }))
+    {
+        return Err(format!(
+            "Git rulesets cannot be used with submitted sources: {ruleset}"
+        ));
+    }
+
+    Ok(())
+}
+
+fn analyze(
+    state: &SylverState,
+    loader: &SylverLoader,
+    workspaces: &Workspaces,
+    request_id: Option<&Value>,
+    params: AnalyzeParams,
+    writer: &mut impl Write,
+) -> anyhow::Result<Value> {
+    match params {
+        AnalyzeParams::Config { config } => {
+            let config = config
+                .canonicalize()
+                .with_context(|| format!("Invalid configuration file: {}", config.display()))?;
+            let root = config.parent().unwrap_or(Path::new("/")).to_owned();
+
+            let workspace = workspaces.get(&config);
+            let mut workspace = workspace.lock().expect("poisoned workspace lock");
+
+            let live = match &mut workspace.live {
+                Some(live) => {
+                    live.update(state, loader)?;
+                    live
+                }
+                live => live.insert(LiveCheck::new(state, loader, &config, Some(&root))?),
+            };
+
+            // A failed reload is only reported once by `update`: the land stays out of date
+            // until the files are fixed.
+            if let Some(failure) = &live.failure {
+                bail!("The workspace could not be reloaded: {failure}");
+            }
+
+            send_findings(
+                state,
+                &live.data.land,
+                Some(&live.cache),
+                request_id,
+                writer,
+            )
+        }
+        AnalyzeParams::Sources {
+            language,
+            rulesets,
+            sources,
+        } => {
+            let store = workspaces
+                .submitted
+                .lock()
+                .expect("poisoned workspace lock");
+
+            let land = build_sources_land(state, loader, &store, &language, &rulesets, sources)?;
+            // The clients resubmit their unchanged files.
+            store.retain_used();
+
+            send_findings(state, &land, None, request_id, writer)
+        }
+    }
+}
+
+/// Evaluate the rules of the land, and send each finding as a notification.
+fn send_findings(
+    state: &SylverState,
+    land: &Land,
+    cache: Option<&RuleCache>,
+    request_id: Option<&Value>,
+    writer: &mut impl Write,
+) -> anyhow::Result<Value> {
+    let execution = {
+        let progress = state.logger.progress("Evaluating rules");
+        exec_rules(
+            land,
+            StopCondition::default(),
+            &state.cancel,
+            cache,
+            None,
+            progress.as_ref(),
+        )?
+    };
+
+    for result in &execution.results {
+        let mut finding = JsonFinding::new(&state.settings.pos_encoder, land, result);
+        finding.file = state.settings.path_map.remap_str(&finding.file);
+
+        write_message(
+            writer,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "finding",
+                "params": { "request": request_id, "finding": finding },
+            }),
+        )?;
+    }
+
+    Ok(json!({ "findings": execution.results.len() }))
+}
+
+fn build_sources_land(
+    state: &SylverState,
+    loader: &SylverLoader,
+    store: &ContentStore,
+    language: &ProjectLang,
+    rulesets: &[StemLocation],
+    sources: Vec<SubmittedSource>,
+) -> anyhow::Result<Land> {
+    let mut builder = LandBuilder::new();
+
+    let sources = sources
+        .into_iter()
+        .map(|s| Source::file(s.code, s.path))
+        .collect();
+
+    let sylva = build_sylva(
+        state,
+        loader,
+        &mut builder,
+        language,
+        &SylvaOptions::global(state).with_store(Some(store)),
+        sources,
+    )?;
+
+    let policy = TrustPolicy::global(state.settings.require_signed);
+
+    let mut ruleset_ids = vec![];
+
+    for location in rulesets {
+        let id = builder.add_ruleset(sylva, &loader.load_ruleset(location, &policy)?)?;
+        ruleset_ids.push((id, location));
+    }
+
+    let land = builder.build();
+
+    warn_renamed_kinds(
+        state,
+        ruleset_ids
+            .into_iter()
+            .map(|(id, location)| (location.to_string(), land.ruleset(id))),
+    );
+
+    Ok(land)
+}
+
+fn write_message(writer: &mut impl Write, message: &impl Serialize) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_requests() {
+        assert_eq!(parse_request("{").unwrap_err().code, PARSE_ERROR);
+        assert_eq!(
+            parse_request(r#"{"jsonrpc": "1.0", "id": 1, "method": "analyze"}"#)
+                .unwrap_err()
+                .code,
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            parse_request(r#"{"id": 1, "params": {}}"#)
+                .unwrap_err()
+                .code,
+            INVALID_REQUEST
+        );
+    }
+
+    #[test]
+    fn notifications() {
+        let notification = parse_request(r#"{"jsonrpc": "2.0", "method": "analyze"}"#).unwrap();
+        assert_eq!(notification.id, None);
+
+        let null_id =
+            parse_request(r#"{"jsonrpc": "2.0", "id": null, "method": "analyze"}"#).unwrap();
+        assert_eq!(null_id.id, Some(Value::Null));
+    }
+
+    #[test]
+    fn analyze_params() {
+        let config: AnalyzeParams =
+            serde_json::from_str(r#"{"config": "repo/sylver.yaml"}"#).unwrap();
+        assert!(matches!(config, AnalyzeParams::Config { .. }));
+
+        let sources: AnalyzeParams = serde_json::from_str(
+            r#"{
+                "language": "python",
+                "rulesets": ["rules.yaml"],
+                "sources": [{"path": "main.py", "code": "eval(x)\n"}]
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(sources, AnalyzeParams::Sources { .. }));
+    }
+
+    #[test]
+    fn authentication() {
+        let request = |line: &str| parse_request(line).unwrap();
+
+        let analyze = request(r#"{"jsonrpc": "2.0", "id": 1, "method": "analyze"}"#);
+        assert_eq!(
+            authenticate("secret", analyze).unwrap_err().code,
+            UNAUTHENTICATED
+        );
+
+        let wrong = request(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": {"token": "secreT"}}"#,
+        );
+        assert_eq!(
+            authenticate("secret", wrong).unwrap_err().code,
+            UNAUTHENTICATED
+        );
+
+        let right = request(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "authenticate", "params": {"token": "secret"}}"#,
+        );
+        assert!(authenticate("secret", right).is_ok());
+
+        assert!(!same_token("secret", "secret2"));
+    }
+
+    #[test]
+    fn workspaces() {
+        let workspaces = Workspaces::default();
+        let first = Path::new("/repo1/sylver.yaml");
+
+        assert!(Arc::ptr_eq(&workspaces.get(first), &workspaces.get(first)));
+        assert!(!Arc::ptr_eq(
+            &workspaces.get(first),
+            &workspaces.get(Path::new("/repo2/sylver.yaml"))
+        ));
+
+        assert!(workspaces.release(first));
+        assert!(!workspaces.release(first));
+    }
+
+    #[test]
+    fn submitted_sources_restrictions() {
+        let params = |language: Value, ruleset: Value| -> AnalyzeParams {
+            serde_json::from_value(json!({
+                "language": language,
+                "rulesets": [ruleset],
+                "sources": [],
+            }))
+            .unwrap()
+        };
+
+        let git = json!({"repo": "https://github.com/org/rules.git", "file": "rules.yaml"});
+
+        assert!(check_submitted(&params(json!("python"), json!("rules.yaml"))).is_ok());
+        assert!(check_submitted(&params(json!("spec/lang.syl"), json!("rules.yaml"))).is_err());
+        assert!(check_submitted(&params(json!("python"), git)).is_err());
+    }
+}
@@ -1,36 +1,140 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use anyhow::bail;
+use itertools::Itertools;
+use rustc_hash::FxHasher;
+
 use sylver_core::{
-    builtin_langs::{get_builtin_lang, parser::BuiltinParserRunner},
+    builtin_langs::{
+        external::load_external_grammar, get_builtin_lang, get_builtin_spec,
+        parser::BuiltinParserRunner, BuiltinLang, MappingConfig,
+    },
     core::{
+        files_spec::FileSpec,
+        overlay::Preprocessor,
         source::Source,
-        spec::{Aspects, Spec, DEFAULT_START_RULE},
+        spec::{Aspects, Spec, Syntax, DEFAULT_START_RULE},
+        store::ContentStore,
     },
     land::{
         builder::LandBuilder,
-        cmds::{exec_rules, parsing_errors, RuleResult},
-        sylva::{Sylva, SylvaId, SylvaParser},
+        cache::RuleCache,
+        cmds::{exec_rules, parsing_errors, RuleFailure, RuleResult, RulesExecution},
+        ruleset::{KnownFindings, RuleCategory, RuleSet, RuleSetId, StopCondition},
+        sylva::{ParseLimits, Sylva, SylvaId, SylvaParser},
         Land, LandSpecId,
     },
     parsing::parser_runner::ParserRunner,
     pretty_print::render_report,
+    query::memo::MemoStats,
     script::python::compile_aspects,
-    specs::{loader::SylverLoader, stem::project::ProjectLang},
+    specs::{
+        loader::SylverLoader,
+        stem::project::{ProjectLang, ProjectStem},
+        trust::TrustPolicy,
+    },
+    state::{SylverSettings, SylverState},
 };
 
-pub fn verify_land(color: bool, land: &Land) -> anyhow::Result<()> {
-    print_land_reports(color, land)?;
-    let res = run_land_rules(color, land)?;
+use crate::{
+    badge::BadgeWriter,
+    baseline::BaselineFilter,
+    cli::{ExitPolicyArgs, FailOn, ReportArgs, ReportFormat, StopArgs},
+    html::HtmlWriter,
+    junit::JunitWriter,
+    report::JsonReportWriter,
+    sarif::SarifWriter,
+    stream::write_report,
+    teamcity::TeamcityWriter,
+};
+
+/// Number of rules listed by `--profile`.
+const PROFILED_RULES: usize = 10;
+
+/// Decides whether a command should fail, given the results of the rules.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExitPolicy {
+    /// Never fail because of rule violations.
+    Never,
+    /// Fail if at least one violation has a category greater or equal to the threshold.
+    Threshold(RuleCategory),
+    /// Fail if there are more findings than in the baseline, which only reports the new ones:
+    /// they must not outnumber the accepted findings that were `fixed`.
+    Increased { fixed: usize },
+}
+
+impl ExitPolicy {
+    pub fn should_fail(&self, categories: impl IntoIterator<Item = RuleCategory>) -> bool {
+        match self {
+            ExitPolicy::Never => false,
+            ExitPolicy::Threshold(min) => categories.into_iter().any(|c| c >= *min),
+            ExitPolicy::Increased { fixed } => categories.into_iter().count() > *fixed,
+        }
+    }
+
+    pub fn exit_if_failed(&self, land: &Land, results: &[RuleResult]) {
+        if self.should_fail(results.iter().map(|r| r.rule(land).category)) {
+            std::process::exit(1);
+        }
+    }
+}
+
+impl From<&ExitPolicyArgs> for ExitPolicy {
+    fn from(args: &ExitPolicyArgs) -> Self {
+        if args.exit_zero {
+            return ExitPolicy::Never;
+        }
 
-    if !res.is_empty() {
-        std::process::exit(1);
+        let threshold = match args.fail_on {
+            FailOn::Help => RuleCategory::Style,
+            FailOn::Warning => RuleCategory::Smell,
+            FailOn::Bug => RuleCategory::Bug,
+            FailOn::Error => RuleCategory::Error,
+        };
+
+        ExitPolicy::Threshold(threshold)
     }
+}
+
+impl From<&StopArgs> for StopCondition {
+    fn from(args: &StopArgs) -> Self {
+        StopCondition {
+            fail_fast: args.fail_fast,
+            max_findings: args.max_findings.map(|m| m.get()),
+        }
+    }
+}
+
+pub fn verify_land(
+    state: &SylverState,
+    land: &Land,
+    policy_args: &ExitPolicyArgs,
+    stop: StopCondition,
+    report_args: &ReportArgs,
+) -> anyhow::Result<()> {
+    let execution = run_land_rules(state, land, stop, report_args, None, None)?;
+
+    check_rule_failures(policy_args.strict_rules, &execution.failures)?;
+    ExitPolicy::from(policy_args).exit_if_failed(land, &execution.results);
 
     Ok(())
 }
 
-pub fn print_land_reports(color: bool, land: &Land) -> anyhow::Result<()> {
+pub fn print_land_reports(settings: &SylverSettings, land: &Land) -> anyhow::Result<()> {
     for (source, reports) in parsing_errors(land) {
         for report in reports {
-            let report_repr = render_report(color, report, source)?;
+            let report = report.clone().with_path_map(&settings.path_map);
+            let report_repr = render_report(
+                settings.color_output,
+                &settings.pos_encoder,
+                &report,
+                source,
+            )?;
             println!("{report_repr}")
         }
     }
@@ -38,8 +142,149 @@ pub fn print_land_reports(color: bool, land: &Land) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn run_land_rules(color: bool, land: &Land) -> anyhow::Result<Vec<RuleResult>> {
-    let mut exec_res = exec_rules(land)?;
+/// Evaluate the rules of the land and report the parsing errors and the findings. The rule
+/// results found in `cache`, if any, are reused.
+pub fn run_land_rules(
+    state: &SylverState,
+    land: &Land,
+    stop: StopCondition,
+    report_args: &ReportArgs,
+    cache: Option<&RuleCache>,
+    baseline: Option<&BaselineFilter>,
+) -> anyhow::Result<RulesExecution> {
+    let settings = &state.settings;
+
+    if report_args.format == ReportFormat::Text {
+        print_land_reports(settings, land)?;
+    }
+
+    let execution = evaluate_land_rules(state, land, stop, cache, baseline)?;
+    let exec_res = &execution.results;
+
+    if report_args.format == ReportFormat::Json {
+        let mut writer = JsonReportWriter::new(&settings.pos_encoder, land, &settings.path_map);
+        write_report(&mut writer, report_args.output.as_deref(), &execution)?;
+        return Ok(execution);
+    }
+
+    if report_args.format == ReportFormat::Sarif {
+        let mut writer = SarifWriter::new(&settings.pos_encoder, land, &settings.path_map);
+        write_report(&mut writer, report_args.output.as_deref(), &execution)?;
+        return Ok(execution);
+    }
+
+    if report_args.format == ReportFormat::Html {
+        let mut writer = HtmlWriter::new(&settings.pos_encoder, land, &settings.path_map);
+        write_report(&mut writer, report_args.output.as_deref(), &execution)?;
+        return Ok(execution);
+    }
+
+    if report_args.format == ReportFormat::Junit {
+        let mut writer = JunitWriter::new(&settings.pos_encoder, land, &settings.path_map);
+        write_report(&mut writer, report_args.output.as_deref(), &execution)?;
+        return Ok(execution);
+    }
+
+    if report_args.format == ReportFormat::Teamcity {
+        let mut writer = TeamcityWriter::new(&settings.pos_encoder, land, &settings.path_map);
+        write_report(&mut writer, report_args.output.as_deref(), &execution)?;
+        log_rule_failures(state, land, &execution.failures);
+        return Ok(execution);
+    }
+
+    if report_args.format == ReportFormat::Badge {
+        let mut writer = BadgeWriter::new(land);
+        write_report(&mut writer, report_args.output.as_deref(), &execution)?;
+        log_rule_failures(state, land, &execution.failures);
+        return Ok(execution);
+    }
+
+    let reports = exec_res
+        .iter()
+        .map(|r| (r.to_report(land), r.source(land)))
+        .chain(
+            execution
+                .failures
+                .iter()
+                .map(|f| (f.to_report(land), f.source(land))),
+        );
+
+    for (report, source) in reports {
+        let report = report.with_path_map(&settings.path_map);
+        let report_repr = render_report(
+            settings.color_output,
+            &settings.pos_encoder,
+            &report,
+            source,
+        )?;
+        println!("{report_repr}")
+    }
+
+    if execution.stopped {
+        state.logger.info(&stop_message(stop, exec_res.len()));
+    }
+
+    Ok(execution)
+}
+
+/// Explain why the evaluation stopped before completion, once `findings` were reported.
+fn stop_message(stop: StopCondition, findings: usize) -> String {
+    match stop.max_findings {
+        Some(max) if findings >= max => {
+            format!("Stopped after reaching the maximum of {max} finding(s).")
+        }
+        _ => {
+            format!("Stopped at the first error-severity finding ({findings} finding(s) reported).")
+        }
+    }
+}
+
+/// Report the rules that failed to evaluate as warnings, for the formats that have no place for
+/// them.
+fn log_rule_failures(state: &SylverState, land: &Land, failures: &[RuleFailure]) {
+    for failure in failures {
+        let report = failure
+            .to_report(land)
+            .with_path_map(&state.settings.path_map);
+        state.logger.warning(&format!(
+            "{}: {}/{}: {}",
+            report.file_path.display(),
+            land.ruleset(failure.ruleset).id(),
+            failure.rule_id,
+            report.message
+        ));
+    }
+}
+
+/// Fail if some rules could not be evaluated, when rule errors are strict.
+pub fn check_rule_failures(strict: bool, failures: &[RuleFailure]) -> anyhow::Result<()> {
+    if strict && !failures.is_empty() {
+        bail!("{} rule evaluation(s) failed", failures.len());
+    }
+
+    Ok(())
+}
+
+/// Evaluate the rules of the land, leaving out the findings accepted in the `baseline` if one is
+/// given. The results are sorted by decreasing category.
+pub fn evaluate_land_rules(
+    state: &SylverState,
+    land: &Land,
+    stop: StopCondition,
+    cache: Option<&RuleCache>,
+    baseline: Option<&BaselineFilter>,
+) -> anyhow::Result<RulesExecution> {
+    let mut execution = {
+        let progress = state.logger.progress("Evaluating rules");
+        let known = baseline.map(|b| b as &dyn KnownFindings);
+        exec_rules(land, stop, &state.cancel, cache, known, progress.as_ref())?
+    };
+
+    if state.settings.profile {
+        log_profile(land, &execution.durations, execution.memo, execution.cache);
+    }
+
+    let exec_res = &mut execution.results;
 
     exec_res.sort_by_key(|r| {
         let category = r.rule(land).category;
@@ -48,46 +293,380 @@ pub fn run_land_rules(color: bool, land: &Land) -> anyhow::Result<Vec<RuleResult
 
     exec_res.reverse();
 
-    for res in &exec_res {
-        let report = res.to_report(land);
-        let source = res.source(land);
-        let report_repr = render_report(color, &report, source)?;
-        println!("{report_repr}")
+    execution.failures.sort_by(|f1, f2| {
+        (f1.ruleset, &f1.rule_id, f1.node).cmp(&(f2.ruleset, &f2.rule_id, f2.node))
+    });
+
+    // Concurrent evaluations may overshoot the limit before noticing it was reached.
+    if let Some(max) = stop.max_findings {
+        exec_res.truncate(max);
+    }
+
+    Ok(execution)
+}
+
+/// Print the slowest rules and the memoization statistics of an execution on stderr, apart from
+/// the findings.
+fn log_profile(
+    land: &Land,
+    durations: &HashMap<(RuleSetId, String), Duration>,
+    memo: MemoStats,
+    cache: MemoStats,
+) {
+    let slowest = durations
+        .iter()
+        .sorted_by_key(|(_, duration)| Reverse(**duration))
+        .take(PROFILED_RULES);
+
+    for ((ruleset, rule), duration) in slowest {
+        let ruleset_id = land.ruleset(*ruleset).id();
+        eprintln!("{ruleset_id}/{rule}: {duration:.2?}");
+    }
+
+    eprintln!(
+        "Memoized values: {} hits, {} misses ({})",
+        memo.hits,
+        memo.misses,
+        format_hit_rate(memo)
+    );
+
+    eprintln!(
+        "Cached rule results: {} hits, {} misses ({})",
+        cache.hits,
+        cache.misses,
+        format_hit_rate(cache)
+    );
+}
+
+fn format_hit_rate(stats: MemoStats) -> String {
+    stats
+        .hit_rate()
+        .map_or("n/a".to_string(), |rate| format!("{:.1}%", rate * 100.0))
+}
+
+/// Warn about the rules that refer to renamed kinds by their former name. The rulesets are given
+/// with their location, and a ruleset shared by several projects is only reported once.
+pub fn warn_renamed_kinds<'r>(
+    state: &SylverState,
+    rulesets: impl IntoIterator<Item = (String, &'r RuleSet)>,
+) {
+    let renames: BTreeSet<(String, &str, &str, &str)> = rulesets
+        .into_iter()
+        .flat_map(|(location, ruleset)| {
+            ruleset
+                .renamed_kinds()
+                .map(move |(rule, former, new)| (location.clone(), rule, former, new))
+        })
+        .collect();
+
+    for (location, rule, former_name, new_name) in renames {
+        state.logger.warning(&format!(
+            "{location}: rule {rule} uses the deprecated kind name {former_name}, renamed to \
+             {new_name}"
+        ));
+    }
+}
+
+/// Return the syntax of the given language.
+pub fn load_syntax(
+    state: &SylverState,
+    loader: &SylverLoader,
+    language: &ProjectLang,
+) -> anyhow::Result<Syntax> {
+    match language {
+        ProjectLang::Custom(location) => {
+            let policy = TrustPolicy::global(state.settings.require_signed);
+            Ok(loader.load_language_spec(location, &policy)?.syntax)
+        }
+        ProjectLang::Builtin(b) => match get_builtin_spec(*b) {
+            Some(spec) => Ok(spec.syntax.clone()),
+            None => Ok(get_builtin_lang(*b).0.into()),
+        },
+        ProjectLang::External(grammar) => Ok(load_external_grammar(grammar)?.2),
     }
+}
 
-    Ok(exec_res)
+/// Load the files matching the given spec.
+pub fn load_sources(
+    state: &SylverState,
+    loader: &SylverLoader,
+    spec: &FileSpec,
+) -> anyhow::Result<Vec<Source>> {
+    let _loading = state.logger.scoped("Loading files", Some("Files loaded"));
+    loader.load_file_spec(spec)
+}
+
+/// Replace the sources with their preprocessed content, if the project has a preprocessor.
+pub fn preprocess_sources(
+    state: &SylverState,
+    preprocessor: Option<&Preprocessor>,
+    sources: Vec<Source>,
+) -> anyhow::Result<Vec<Source>> {
+    let preprocessor = match preprocessor {
+        Some(p) => p,
+        None => return Ok(sources),
+    };
+
+    if !state.settings.allow_preprocess {
+        bail!(
+            "The project preprocesses its files with `{}`, pass --allow-preprocess to run it",
+            preprocessor.command.join(" ")
+        );
+    }
+
+    let _preprocessing = state
+        .logger
+        .scoped("Preprocessing files", Some("Files preprocessed"));
+
+    sources
+        .iter()
+        .map(|source| {
+            state.cancel.check()?;
+            preprocessor.apply(source)
+        })
+        .collect()
+}
+
+/// How the sources of a sylva are parsed.
+#[derive(Debug, Clone, Default)]
+pub struct SylvaOptions<'s> {
+    /// Version of the language, restricting the accepted syntax.
+    pub version: Option<String>,
+    pub limits: ParseLimits,
+    /// Trust requirements of a downloaded language spec.
+    pub trust: TrustPolicy,
+    /// Trees kept by a long-lived command, reused for the unchanged contents.
+    pub store: Option<&'s ContentStore>,
+}
+
+impl<'s> SylvaOptions<'s> {
+    pub fn for_project(state: &SylverState, project: &ProjectStem) -> SylvaOptions<'s> {
+        SylvaOptions {
+            version: project.language_version.clone(),
+            limits: project.parse_limits,
+            trust: TrustPolicy::new(project.trust.clone(), state.settings.require_signed),
+            store: None,
+        }
+    }
+
+    /// Options of the sources that don't belong to a configured project.
+    pub fn global(state: &SylverState) -> SylvaOptions<'s> {
+        SylvaOptions {
+            trust: TrustPolicy::global(state.settings.require_signed),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_store(self, store: Option<&'s ContentStore>) -> SylvaOptions<'s> {
+        SylvaOptions { store, ..self }
+    }
 }
 
 pub fn build_sylva(
+    state: &SylverState,
     loader: &SylverLoader,
     builder: &mut LandBuilder,
     language: &ProjectLang,
+    options: &SylvaOptions,
     sources: Vec<Source>,
 ) -> anyhow::Result<SylvaId> {
+    let (sylva, spec) = with_parser(loader, language, options, |parser, key| {
+        let store = options.store.zip(key);
+        build_limited(state, parser, store, sources, &options.limits)
+    })?;
+
+    let spec_id = match spec {
+        LangSpec::Custom(spec) => LandSpecId::CustomLangId(builder.add_spec(spec)),
+        LangSpec::Builtin(syntax, aspect_code) => {
+            let aspects = match aspect_code {
+                Some(code) => {
+                    Aspects::build(&syntax, compile_aspects(code, "builtin".to_string())?)?
+                }
+                None => Aspects::default(),
+            };
+            LandSpecId::BuiltinLangId(builder.add_spec(Spec::new(aspects, syntax)))
+        }
+        LangSpec::External(syntax) => {
+            LandSpecId::BuiltinLangId(builder.add_spec(Spec::new(Aspects::default(), syntax)))
+        }
+    };
+
+    builder.add_sylva(sylva, spec_id)
+}
+
+/// What remains to build the spec of a language once its sources are parsed.
+pub enum LangSpec {
+    Custom(Spec),
+    /// Syntax of a builtin language and the code of its aspects, compiled after the parsing.
+    Builtin(Syntax, Option<&'static str>),
+    External(Syntax),
+}
+
+/// Prepare the parser of a language and hand it to `parse`, along with the store key of its
+/// trees when they can be shared.
+pub fn with_parser<T>(
+    loader: &SylverLoader,
+    language: &ProjectLang,
+    options: &SylvaOptions,
+    parse: impl FnOnce(SylvaParser, Option<u64>) -> anyhow::Result<T>,
+) -> anyhow::Result<(T, LangSpec)> {
+    let version = options.version.as_deref();
+
     match language {
+        ProjectLang::Custom(_) if version.is_some() => {
+            bail!("Language versions are only supported by builtin languages")
+        }
         ProjectLang::Custom(location) => {
-            let spec = loader.load_language_spec(location)?;
+            let spec = loader.load_language_spec(location, &options.trust)?;
             let parser = ParserRunner::new(DEFAULT_START_RULE, &spec.syntax)?;
-            let sylva = Sylva::build_concurrently(SylvaParser::Custom(parser), sources)?;
-            let spec_id = LandSpecId::CustomLangId(builder.add_spec(spec));
-            builder.add_sylva(sylva, spec_id)
+            let res = parse(SylvaParser::Custom(parser), None)?;
+            Ok((res, LangSpec::Custom(spec)))
         }
         ProjectLang::Builtin(b) => {
+            if let Some(spec) = get_builtin_spec(*b) {
+                if version.is_some() {
+                    bail!("Language versions are not supported by {b}")
+                }
+                let parser = ParserRunner::new(DEFAULT_START_RULE, &spec.syntax)?;
+                let key = builtin_parser_key(*b, version, &options.limits);
+                let res = parse(SylvaParser::Custom(parser), Some(key))?;
+                return Ok((res, LangSpec::Custom(spec.clone())));
+            }
+
             let (mappings, lang, aspects) = get_builtin_lang(*b);
-            let syntax = mappings.types.as_slice().into();
-            let aspects = if let Some(aspect_code) = aspects {
-                Aspects::build(
-                    &syntax,
-                    compile_aspects(aspect_code, "builtin".to_string())?,
-                )?
-            } else {
-                Aspects::default()
-            };
-            let parser = BuiltinParserRunner::new(lang, &syntax, mappings);
-            let sylva = Sylva::build_concurrently(SylvaParser::Builtin(parser), sources)?;
-            let spec = Spec::new(aspects, syntax);
-            let spec_id = LandSpecId::BuiltinLangId(builder.add_spec(spec));
-            builder.add_sylva(sylva, spec_id)
+            let syntax = mappings.into();
+            let parser = versioned_parser(
+                BuiltinParserRunner::new(lang, &syntax, mappings),
+                mappings,
+                version,
+            )?;
+            // Builtin grammars can't change during the life of the process, so their trees can
+            // be shared through the store.
+            let key = builtin_parser_key(*b, version, &options.limits);
+            let res = parse(SylvaParser::Builtin(parser), Some(key))?;
+            Ok((res, LangSpec::Builtin(syntax, aspects)))
+        }
+        ProjectLang::External(grammar) => {
+            let (lang, mappings, syntax) = load_external_grammar(grammar)?;
+            let parser = versioned_parser(
+                BuiltinParserRunner::new(lang, &syntax, &mappings),
+                &mappings,
+                version,
+            )?;
+            let res = parse(SylvaParser::Builtin(parser), None)?;
+            Ok((res, LangSpec::External(syntax)))
         }
     }
 }
+
+/// Parse the sources, logging a warning for every file skipped because of the limits. When a
+/// store is given with the key of the parser, the trees are shared through it.
+fn build_limited(
+    state: &SylverState,
+    parser: SylvaParser,
+    store: Option<(&ContentStore, u64)>,
+    sources: Vec<Source>,
+    limits: &ParseLimits,
+) -> anyhow::Result<Sylva> {
+    let progress = state.logger.progress("Parsing");
+    let (sylva, skipped) = Sylva::build_stored(
+        parser,
+        store,
+        sources,
+        limits,
+        &state.cancel,
+        progress.as_ref(),
+    )?;
+
+    for file in skipped {
+        state.logger.warning(&file.to_string());
+    }
+
+    Ok(sylva)
+}
+
+fn builtin_parser_key(lang: BuiltinLang, version: Option<&str>, limits: &ParseLimits) -> u64 {
+    let mut hasher = FxHasher::default();
+    lang.hash(&mut hasher);
+    version.hash(&mut hasher);
+    limits.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn versioned_parser<'s>(
+    parser: BuiltinParserRunner<'s>,
+    mappings: &MappingConfig,
+    version: Option<&str>,
+) -> anyhow::Result<BuiltinParserRunner<'s>> {
+    match version {
+        Some(version) => parser.with_version(mappings.version(version)?),
+        None => Ok(parser),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sylver_core::query::{expr::EvalError, SylvaNode};
+
+    use super::*;
+
+    #[test]
+    fn never_policy() {
+        assert!(!ExitPolicy::Never.should_fail([RuleCategory::Error]));
+    }
+
+    #[test]
+    fn threshold_policy() {
+        let policy = ExitPolicy::Threshold(RuleCategory::Smell);
+
+        assert!(!policy.should_fail([]));
+        assert!(!policy.should_fail([RuleCategory::Style]));
+        assert!(policy.should_fail([RuleCategory::Style, RuleCategory::Deprecated]));
+        assert!(policy.should_fail([RuleCategory::Error]));
+    }
+
+    #[test]
+    fn increased_policy() {
+        let policy = ExitPolicy::Increased { fixed: 1 };
+
+        assert!(!policy.should_fail([]));
+        assert!(!policy.should_fail([RuleCategory::Error]));
+        assert!(policy.should_fail([RuleCategory::Style, RuleCategory::Style]));
+        assert!(!ExitPolicy::Increased { fixed: 0 }.should_fail([]));
+    }
+
+    #[test]
+    fn strict_rule_failures() {
+        let failures = [RuleFailure {
+            ruleset: 0.into(),
+            rule_id: "no-eval".to_string(),
+            node: SylvaNode {
+                sylva: 0.into(),
+                tree: 0.into(),
+                node: 0.into(),
+            },
+            error: EvalError::IndexError(3),
+        }];
+
+        assert!(check_rule_failures(true, &[]).is_ok());
+        assert!(check_rule_failures(false, &failures).is_ok());
+        assert!(check_rule_failures(true, &failures).is_err());
+    }
+
+    #[test]
+    fn fail_fast_stop_message() {
+        let fail_fast = StopCondition {
+            fail_fast: true,
+            max_findings: Some(10),
+        };
+
+        assert_eq!(
+            stop_message(fail_fast, 3),
+            "Stopped at the first error-severity finding (3 finding(s) reported)."
+        );
+        assert_eq!(
+            stop_message(fail_fast, 10),
+            "Stopped after reaching the maximum of 10 finding(s)."
+        );
+    }
+}
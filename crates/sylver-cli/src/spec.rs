@@ -0,0 +1,151 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use itertools::Itertools;
+use sylver_core::{
+    core::{
+        describe::{FieldDescription, SpecDescription},
+        spec_lint::lint_spec,
+    },
+    parsing::roundtrip::{check_roundtrip, RoundtripConfig},
+    script::python::stubs::python_stubs,
+    specs::{loader::SylverLoader, stem::project::ProjectLang},
+    state::SylverState,
+};
+
+use crate::{
+    cli::{
+        SpecCmd, SpecCmds, SpecDescribe, SpecFormat, SpecLint, SpecRoundtrip, SpecStubs, StubsLang,
+    },
+    shared::load_syntax,
+};
+
+pub fn spec_cmd(state: &SylverState, loader: &SylverLoader, cmd: &SpecCmd) -> anyhow::Result<()> {
+    match &cmd.command {
+        SpecCmds::Describe(d) => describe(state, loader, d),
+        SpecCmds::Stubs(s) => stubs(state, loader, s),
+        SpecCmds::Roundtrip(r) => roundtrip(state, loader, r),
+        SpecCmds::Lint(l) => lint(l),
+    }
+}
+
+fn describe(state: &SylverState, loader: &SylverLoader, cmd: &SpecDescribe) -> anyhow::Result<()> {
+    let syntax = load_syntax(state, loader, &cmd.language)?;
+    let description = SpecDescription::from(&syntax);
+
+    match cmd.format {
+        SpecFormat::Text => print!("{}", render_description(&description)),
+        SpecFormat::Json => println!("{}", serde_json::to_string_pretty(&description)?),
+    }
+
+    Ok(())
+}
+
+fn stubs(state: &SylverState, loader: &SylverLoader, cmd: &SpecStubs) -> anyhow::Result<()> {
+    let syntax = load_syntax(state, loader, &cmd.language)?;
+    let description = SpecDescription::from(&syntax);
+
+    let stubs = match cmd.lang {
+        StubsLang::Python => python_stubs(&description),
+    };
+
+    match &cmd.output {
+        Some(path) => {
+            std::fs::write(path, stubs).with_context(|| format!("Could not write {path}"))?
+        }
+        None => print!("{stubs}"),
+    }
+
+    Ok(())
+}
+
+fn roundtrip(
+    state: &SylverState,
+    loader: &SylverLoader,
+    cmd: &SpecRoundtrip,
+) -> anyhow::Result<()> {
+    if !matches!(cmd.language, ProjectLang::Custom(_)) {
+        bail!("Only the custom language specs have parsing rules");
+    }
+
+    let syntax = load_syntax(state, loader, &cmd.language)?;
+    let seed = cmd.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+
+    let config = RoundtripConfig {
+        iterations: cmd.iterations,
+        max_depth: cmd.max_depth,
+        seed,
+    };
+
+    let failures = check_roundtrip(&syntax, &cmd.rule, &config)?;
+
+    for failure in &failures {
+        println!("{failure}\n");
+    }
+
+    if !failures.is_empty() {
+        bail!(
+            "{} of the {} generated trees did not roundtrip (seed: {seed})",
+            failures.len(),
+            cmd.iterations
+        );
+    }
+
+    println!(
+        "{} generated trees roundtripped (seed: {seed})",
+        cmd.iterations
+    );
+
+    Ok(())
+}
+
+fn lint(cmd: &SpecLint) -> anyhow::Result<()> {
+    let spec = std::fs::read_to_string(&cmd.spec)
+        .with_context(|| format!("Could not read {}", cmd.spec.display()))?;
+
+    let lints = lint_spec(&spec, Some(&cmd.spec), &cmd.rule)?;
+
+    for lint in &lints {
+        match lint.line {
+            Some(line) => println!("{}:{line}: {}", cmd.spec.display(), lint.message),
+            None => println!("{}: {}", cmd.spec.display(), lint.message),
+        }
+    }
+
+    if !lints.is_empty() {
+        bail!("Found {} problems in the spec", lints.len());
+    }
+
+    Ok(())
+}
+
+fn render_description(description: &SpecDescription) -> String {
+    let mut out = String::new();
+
+    for node in &description.nodes {
+        match &node.parent {
+            Some(parent) => out.push_str(&format!("{}: {parent}\n", node.name)),
+            None => out.push_str(&format!("{}\n", node.name)),
+        }
+
+        for field in &node.fields {
+            out.push_str(&format!("  {}: {}\n", field.name, render_field_type(field)));
+        }
+    }
+
+    out
+}
+
+fn render_field_type(field: &FieldDescription) -> String {
+    let types = field.types.iter().join(" | ");
+
+    if field.list {
+        format!("List<{types}>")
+    } else {
+        types
+    }
+}
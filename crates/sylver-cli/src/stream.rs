@@ -0,0 +1,89 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use sylver_core::land::cmds::{RuleResult, RulesExecution};
+
+/// Report written to its output one finding at a time, so that the whole document is never held
+/// in memory.
+pub trait ReportWriter {
+    /// Write the part of the report that precedes the findings.
+    fn begin(&mut self, out: &mut dyn Write) -> anyhow::Result<()>;
+
+    fn finding(&mut self, out: &mut dyn Write, result: &RuleResult) -> anyhow::Result<()>;
+
+    /// Write the part of the report that follows the findings.
+    fn end(&mut self, out: &mut dyn Write, execution: &RulesExecution) -> anyhow::Result<()>;
+}
+
+/// Write the report of the execution to the given file, or to stdout.
+pub fn write_report(
+    writer: &mut dyn ReportWriter,
+    output: Option<&Path>,
+    execution: &RulesExecution,
+) -> anyhow::Result<()> {
+    let destination: Box<dyn Write> = match output {
+        Some(path) => Box::new(
+            File::create(path)
+                .with_context(|| format!("Could not create report file: {}", path.display()))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut out = BufWriter::new(destination);
+
+    writer.begin(&mut out)?;
+
+    for result in &execution.results {
+        writer.finding(&mut out, result)?;
+    }
+
+    writer.end(&mut out, execution)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Write a pretty-printed JSON value, laid out as if it was nested `depth` levels deep in a
+/// pretty-printed document.
+pub fn write_pretty_json(
+    out: &mut dyn Write,
+    value: &impl Serialize,
+    depth: usize,
+) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    let indent = format!("\n{}", "  ".repeat(depth));
+
+    out.write_all(json.replace('\n', &indent).as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn nested_pretty_json() {
+        let elems = [json!({"a": 1, "b": [1, 2]}), json!({"c": "d"})];
+        let mut out = vec![];
+
+        write!(out, "{{\n  \"elems\": [").unwrap();
+        for (i, elem) in elems.iter().enumerate() {
+            write!(out, "{}\n    ", if i == 0 { "" } else { "," }).unwrap();
+            write_pretty_json(&mut out, elem, 2).unwrap();
+        }
+        write!(out, "\n  ]\n}}").unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            serde_json::to_string_pretty(&json!({ "elems": elems })).unwrap()
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::{collections::HashSet, io::Write};
+
+use sylver_core::{
+    core::{pos::PosEncoder, source::Source},
+    land::{
+        cmds::{parsing_errors, RuleResult, RulesExecution},
+        ruleset::RuleCategory,
+        Land,
+    },
+    report::{Report, PARSE_ERROR_CODE},
+    util::fs::{path_to_string, PathPrefixMap},
+};
+
+use crate::{report::encode_position, stream::ReportWriter};
+
+/// Parsing errors and findings, as TeamCity inspection service messages.
+pub struct TeamcityWriter<'a> {
+    pos_encoder: &'a PosEncoder,
+    land: &'a Land,
+    path_map: &'a PathPrefixMap,
+    declared_types: HashSet<String>,
+}
+
+impl<'a> TeamcityWriter<'a> {
+    pub fn new(
+        pos_encoder: &'a PosEncoder,
+        land: &'a Land,
+        path_map: &'a PathPrefixMap,
+    ) -> TeamcityWriter<'a> {
+        TeamcityWriter {
+            pos_encoder,
+            land,
+            path_map,
+            declared_types: HashSet::new(),
+        }
+    }
+
+    /// Inspection of the given report. The line of the findings in notebooks is relative to
+    /// their cell, which is given in the `cell` attribute.
+    fn inspection(
+        &self,
+        type_id: &str,
+        source: &Source,
+        report: &Report,
+        severity: &str,
+    ) -> String {
+        let (position, cell) = encode_position(self.pos_encoder, source, report.position);
+
+        inspection(
+            type_id,
+            &report.message,
+            &path_to_string(&self.path_map.remap(&report.file_path)),
+            position.start_line,
+            cell,
+            severity,
+        )
+    }
+}
+
+impl<'a> ReportWriter for TeamcityWriter<'a> {
+    fn begin(&mut self, out: &mut dyn Write) -> anyhow::Result<()> {
+        let mut parse_errors: Vec<(&Source, &Report)> = parsing_errors(self.land)
+            .into_iter()
+            .flat_map(|(source, reports)| reports.iter().map(move |r| (source, r)))
+            .collect();
+        parse_errors.sort_by_key(|(_, r)| (&r.file_path, r.position.start().txt_pos()));
+
+        for (source, report) in parse_errors {
+            if self.declared_types.insert(PARSE_ERROR_CODE.to_string()) {
+                writeln!(
+                    out,
+                    "{}",
+                    inspection_type(
+                        PARSE_ERROR_CODE,
+                        PARSE_ERROR_CODE,
+                        "Parsing",
+                        "File could not be parsed",
+                    )
+                )?;
+            }
+
+            writeln!(
+                out,
+                "{}",
+                self.inspection(PARSE_ERROR_CODE, source, report, "ERROR")
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn finding(&mut self, out: &mut dyn Write, result: &RuleResult) -> anyhow::Result<()> {
+        let rule = result.rule(self.land);
+        let type_id = format!(
+            "{}/{}",
+            self.land.ruleset(result.ruleset).id(),
+            result.rule_id
+        );
+
+        if self.declared_types.insert(type_id.clone()) {
+            writeln!(
+                out,
+                "{}",
+                inspection_type(
+                    &type_id,
+                    &result.rule_id,
+                    category_name(rule.category),
+                    &rule.message,
+                )
+            )?;
+        }
+
+        writeln!(
+            out,
+            "{}",
+            self.inspection(
+                &type_id,
+                result.source(self.land),
+                &result.to_report(self.land),
+                severity(rule.category),
+            )
+        )?;
+
+        Ok(())
+    }
+
+    fn end(&mut self, _out: &mut dyn Write, _execution: &RulesExecution) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn inspection_type(id: &str, name: &str, category: &str, description: &str) -> String {
+    service_message(
+        "inspectionType",
+        &[
+            ("id", id),
+            ("name", name),
+            ("category", category),
+            ("description", description),
+        ],
+    )
+}
+
+fn inspection(
+    type_id: &str,
+    message: &str,
+    file: &str,
+    line: usize,
+    cell: Option<usize>,
+    severity: &str,
+) -> String {
+    let line = line.to_string();
+    let cell = cell.map(|c| c.to_string());
+
+    let mut attributes = vec![
+        ("typeId", type_id),
+        ("message", message),
+        ("file", file),
+        ("line", line.as_str()),
+    ];
+    if let Some(cell) = &cell {
+        attributes.push(("cell", cell));
+    }
+    attributes.push(("SEVERITY", severity));
+
+    service_message("inspection", &attributes)
+}
+
+pub(crate) fn category_name(category: RuleCategory) -> &'static str {
+    match category {
+        RuleCategory::Style => "Style",
+        RuleCategory::Smell => "Smell",
+        RuleCategory::Deprecated => "Deprecated",
+        RuleCategory::Bug => "Bug",
+        RuleCategory::Error => "Error",
+    }
+}
+
+fn severity(category: RuleCategory) -> &'static str {
+    match category {
+        RuleCategory::Style => "WEAK WARNING",
+        RuleCategory::Smell | RuleCategory::Deprecated => "WARNING",
+        RuleCategory::Bug | RuleCategory::Error => "ERROR",
+    }
+}
+
+fn service_message(name: &str, attributes: &[(&str, &str)]) -> String {
+    let attributes: Vec<String> = attributes
+        .iter()
+        .map(|(key, value)| format!("{key}='{}'", escape(value)))
+        .collect();
+
+    format!("##teamcity[{name} {}]", attributes.join(" "))
+}
+
+/// Escape a value according to the service messages syntax.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            '\u{0085}' => escaped.push_str("|x"),
+            '\u{2028}' => escaped.push_str("|l"),
+            '\u{2029}' => escaped.push_str("|p"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_special_chars() {
+        assert_eq!(escape("a['b'] | c\n"), "a|[|'b|'|] || c|n");
+    }
+
+    #[test]
+    fn notebook_cell_attribute() {
+        assert_eq!(
+            inspection("rs/rule", "msg", "nb.ipynb", 2, Some(4), "ERROR"),
+            "##teamcity[inspection typeId='rs/rule' message='msg' file='nb.ipynb' line='2' \
+             cell='4' SEVERITY='ERROR']"
+        );
+    }
+
+    #[test]
+    fn render_service_message() {
+        assert_eq!(
+            service_message("inspection", &[("typeId", "rs/rule"), ("line", "3")]),
+            "##teamcity[inspection typeId='rs/rule' line='3']"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::Context;
+use rustyline::{error::ReadlineError, Editor};
+
+use sylver_core::{
+    land::{cmds::RuleResult, ruleset::RuleCategory, Land},
+    pretty_print::render_report,
+    state::SylverState,
+};
+
+use crate::{
+    baseline::{Baseline, BaselineFilter, BaselineFinding},
+    report::JsonFinding,
+};
+
+const CATEGORIES: [RuleCategory; 5] = [
+    RuleCategory::Style,
+    RuleCategory::Smell,
+    RuleCategory::Deprecated,
+    RuleCategory::Bug,
+    RuleCategory::Error,
+];
+
+static HELP: &str = "\
+list                    List the findings matching the filter
+show <n>                Show the code of a finding
+filter rule <rule>      Only list the findings of a rule
+filter category <name>  Only list the findings of a category (style, smell, deprecated, bug, error)
+filter file <text>      Only list the findings of the files whose path contains the text
+filter clear            Remove the filter
+baseline <n>            Accept a finding: it is no longer reported
+suppress <n>            Disable the rule of a finding for its whole file
+quit                    Leave the triage";
+
+/// Browse the findings of a run from a prompt, and record the accepted ones in the baseline.
+///
+/// The results must come from an evaluation filtered by `filter`, which gives their baseline keys.
+pub fn triage(
+    state: &SylverState,
+    land: &Land,
+    filter: &BaselineFilter,
+    results: Vec<RuleResult>,
+) -> anyhow::Result<()> {
+    let mut session = TriageSession {
+        state,
+        land,
+        findings: results
+            .into_iter()
+            .map(|r| TriageFinding::new(state, land, filter, r))
+            .collect(),
+        filter: TriageFilter::default(),
+        baseline: Baseline::load(&state.locations.sylver_dir)?,
+    };
+
+    let mut rl = Editor::<()>::new().context("Could not build prompt")?;
+
+    session.list();
+    println!("Type `help` for the list of commands.");
+
+    loop {
+        match rl.readline("triage> ") {
+            Ok(line) => {
+                rl.add_history_entry(&line);
+
+                match parse_command(&line) {
+                    Some(TriageCmd::Quit) => break,
+                    Some(cmd) => {
+                        if let Err(e) = session.run(cmd) {
+                            eprintln!("{e:?}");
+                        }
+                    }
+                    None => println!("Invalid command, type `help` for the list of commands."),
+                }
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            err => {
+                err.context("Readline error")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum TriageCmd {
+    Help,
+    List,
+    Show(usize),
+    Filter(TriageFilter),
+    Baseline(usize),
+    Suppress(usize),
+    Quit,
+}
+
+fn parse_command(line: &str) -> Option<TriageCmd> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    let cmd = match words.as_slice() {
+        ["help"] => TriageCmd::Help,
+        [] | ["list"] => TriageCmd::List,
+        ["show", index] => TriageCmd::Show(index.parse().ok()?),
+        ["filter", "clear"] => TriageCmd::Filter(TriageFilter::default()),
+        ["filter", "rule", rule] => TriageCmd::Filter(TriageFilter {
+            rule: Some(rule.to_string()),
+            ..TriageFilter::default()
+        }),
+        ["filter", "category", name] => TriageCmd::Filter(TriageFilter {
+            category: Some(parse_category(name)?),
+            ..TriageFilter::default()
+        }),
+        ["filter", "file", text] => TriageCmd::Filter(TriageFilter {
+            file: Some(text.to_string()),
+            ..TriageFilter::default()
+        }),
+        ["baseline", index] => TriageCmd::Baseline(index.parse().ok()?),
+        ["suppress", index] => TriageCmd::Suppress(index.parse().ok()?),
+        ["quit"] => TriageCmd::Quit,
+        _ => return None,
+    };
+
+    Some(cmd)
+}
+
+fn parse_category(name: &str) -> Option<RuleCategory> {
+    CATEGORIES
+        .into_iter()
+        .find(|c| category_name(*c) == name.to_lowercase())
+}
+
+fn category_name(category: RuleCategory) -> &'static str {
+    match category {
+        RuleCategory::Style => "style",
+        RuleCategory::Smell => "smell",
+        RuleCategory::Deprecated => "deprecated",
+        RuleCategory::Bug => "bug",
+        RuleCategory::Error => "error",
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+struct TriageFilter {
+    rule: Option<String>,
+    category: Option<RuleCategory>,
+    /// Text contained in the path of the file.
+    file: Option<String>,
+}
+
+impl TriageFilter {
+    fn matches(&self, finding: &JsonFinding) -> bool {
+        self.rule.as_ref().map_or(true, |r| r == &finding.rule)
+            && self.category.map_or(true, |c| c == finding.category)
+            && self
+                .file
+                .as_ref()
+                .map_or(true, |f| finding.file.contains(f.as_str()))
+    }
+}
+
+struct TriageFinding {
+    result: RuleResult,
+    report: JsonFinding,
+    key: BaselineFinding,
+}
+
+impl TriageFinding {
+    fn new(
+        state: &SylverState,
+        land: &Land,
+        filter: &BaselineFilter,
+        result: RuleResult,
+    ) -> TriageFinding {
+        TriageFinding {
+            report: JsonFinding::new(&state.settings.pos_encoder, land, &result),
+            key: filter.finding(land, &result),
+            result,
+        }
+    }
+}
+
+struct TriageSession<'s> {
+    state: &'s SylverState,
+    land: &'s Land,
+    /// Findings that are not accepted yet.
+    findings: Vec<TriageFinding>,
+    filter: TriageFilter,
+    baseline: Baseline,
+}
+
+impl<'s> TriageSession<'s> {
+    fn run(&mut self, cmd: TriageCmd) -> anyhow::Result<()> {
+        match cmd {
+            TriageCmd::Help => println!("{HELP}"),
+            TriageCmd::List => self.list(),
+            TriageCmd::Show(index) => self.show(index)?,
+            TriageCmd::Filter(filter) => {
+                self.filter = filter;
+                self.list();
+            }
+            TriageCmd::Baseline(index) => {
+                let key = self.selected(index)?.key.clone();
+                self.baseline.findings.insert(key);
+                self.accept()?;
+            }
+            TriageCmd::Suppress(index) => {
+                let suppression = self.selected(index)?.key.suppression();
+                self.baseline.suppressions.insert(suppression);
+                self.accept()?;
+            }
+            TriageCmd::Quit => {}
+        }
+
+        Ok(())
+    }
+
+    /// Findings matching the filter, numbered from 1.
+    fn visible(&self) -> impl Iterator<Item = (usize, &TriageFinding)> {
+        self.findings
+            .iter()
+            .filter(|f| self.filter.matches(&f.report))
+            .enumerate()
+            .map(|(i, f)| (i + 1, f))
+    }
+
+    fn selected(&self, index: usize) -> anyhow::Result<&TriageFinding> {
+        self.visible()
+            .find(|(i, _)| *i == index)
+            .map(|(_, f)| f)
+            .with_context(|| format!("No finding with number {index}"))
+    }
+
+    fn list(&self) {
+        let mut count = 0;
+
+        for (index, finding) in self.visible() {
+            let report = &finding.report;
+            println!(
+                "{index:>4} [{}] {}/{} {}:{} {}",
+                category_name(report.category),
+                report.ruleset,
+                report.rule,
+                report.file,
+                report.position.start_line,
+                report.message
+            );
+            count += 1;
+        }
+
+        println!("{count} of {} finding(s)", self.findings.len());
+    }
+
+    fn show(&self, index: usize) -> anyhow::Result<()> {
+        let result = &self.selected(index)?.result;
+        let settings = &self.state.settings;
+
+        let report = render_report(
+            settings.color_output,
+            &settings.pos_encoder,
+            &result
+                .to_report(self.land)
+                .with_path_map(&settings.path_map),
+            result.source(self.land),
+        )?;
+
+        println!("{report}");
+
+        Ok(())
+    }
+
+    /// Save the baseline and drop the findings that it accepts.
+    fn accept(&mut self) -> anyhow::Result<()> {
+        self.baseline.save(&self.state.locations.sylver_dir)?;
+
+        let before = self.findings.len();
+        let baseline = &self.baseline;
+        self.findings.retain(|f| !baseline.contains(&f.key));
+
+        println!("Accepted {} finding(s)", before - self.findings.len());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse_command(""), Some(TriageCmd::List));
+        assert_eq!(parse_command("show 3"), Some(TriageCmd::Show(3)));
+        assert_eq!(
+            parse_command("filter category Bug"),
+            Some(TriageCmd::Filter(TriageFilter {
+                category: Some(RuleCategory::Bug),
+                ..TriageFilter::default()
+            }))
+        );
+        assert_eq!(parse_command("filter category fatal"), None);
+        assert_eq!(parse_command("baseline x"), None);
+    }
+}
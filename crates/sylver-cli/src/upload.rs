@@ -4,7 +4,7 @@ use anyhow::{bail, Context};
 
 use sylver_core::dashboard::api::ReportDTO;
 use sylver_core::{
-    core::source::SourceTree,
+    core::{pos::PosEncoder, source::SourceTree},
     dashboard::api::{NewDiagnosticDTO, NewReportDTO, ReportCustomLanguage, ReportLanguage},
     land::{cmds::RuleResult, ruleset::Rule, LandSpecId},
     query::SylvaNode,
@@ -13,7 +13,11 @@ use sylver_core::{
         stem::project::ProjectLang,
     },
     tree::info::{raw::RawTreeInfo, TreeInfo},
-    util::{fs::path_to_string, git::GitClient, iter::group_by},
+    util::{
+        fs::{path_to_string, PathPrefixMap},
+        git::GitClient,
+        iter::group_by,
+    },
 };
 
 use crate::{check::CheckLandData, cli::CheckCmd};
@@ -22,17 +26,27 @@ pub struct ReportUploader<'s> {
     loader: &'s SylverLoader,
     cmd: &'s CheckCmd,
     check_data: &'s CheckLandData,
+    pos_encoder: PosEncoder,
+    path_map: &'s PathPrefixMap,
     client: reqwest::blocking::Client,
 }
 
 impl<'s> ReportUploader<'s> {
-    pub fn new(loader: &'s SylverLoader, cmd: &'s CheckCmd, check_data: &'s CheckLandData) -> Self {
+    pub fn new(
+        loader: &'s SylverLoader,
+        cmd: &'s CheckCmd,
+        check_data: &'s CheckLandData,
+        pos_encoder: PosEncoder,
+        path_map: &'s PathPrefixMap,
+    ) -> Self {
         let client = reqwest::blocking::Client::new();
 
         Self {
             loader,
             cmd,
             check_data,
+            pos_encoder,
+            path_map,
             client,
         }
     }
@@ -91,14 +105,18 @@ impl<'s> ReportUploader<'s> {
 
     fn build_diagnostic(&self, report: &ReportDTO, r: &&RuleResult) -> NewDiagnosticDTO {
         let info = self.get_tree_info(r.node);
+        let tree = self.get_tree(r.node);
 
         NewDiagnosticDTO {
             report_id: report.id,
             rule_set: self.check_data.rulesets.get(&r.ruleset).unwrap().clone(),
             rule: r.rule_id.to_string(),
             description: self.get_rule(r).message.clone(),
-            file: path_to_string(self.get_tree(r.node).source.path()),
-            position: info.node_pos(r.node.node).into(),
+            file: path_to_string(&self.path_map.remap(tree.source.path())),
+            position: self
+                .pos_encoder
+                .encode_range(tree.source.src(), info.node_pos(r.node.node))
+                .into(),
         }
     }
 
@@ -152,9 +170,15 @@ impl<'s> ReportUploader<'s> {
         let language = match project_language {
             ProjectLang::Builtin(b) => ReportLanguage::Builtin(*b),
             ProjectLang::Custom(stem) => ReportLanguage::Custom(ReportCustomLanguage {
-                name: self.loader.langs.id(stem).unwrap(),
+                name: self.loader.language_id(stem).unwrap(),
                 stem: stem.clone(),
             }),
+            ProjectLang::External(grammar) => {
+                bail!(
+                    "Cannot upload reports for external grammar: {}",
+                    grammar.name
+                )
+            }
         };
 
         Ok(NewReportDTO {
@@ -0,0 +1,304 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use sylver_core::{
+    core::store::ContentStore,
+    land::{
+        cache::RuleCache,
+        ruleset::{RuleSetId, StopCondition},
+    },
+    specs::{
+        loader::SylverLoader,
+        stem::{location::StemLocation, project::ProjectLang},
+    },
+    state::SylverState,
+};
+
+use crate::{
+    baseline::BaselineFilter,
+    check::{build_rooted_check_state, build_stored_check_state, load_baseline, CheckLandData},
+    cli::CheckCmd,
+    shared::{run_land_rules, warn_renamed_kinds},
+};
+
+/// Delay between two checks of the watched files.
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Run the rules of the project every time the configuration, a ruleset, a language spec or a
+/// source file is modified, until the command is interrupted.
+pub fn watch_check(
+    state: Arc<SylverState>,
+    loader: &SylverLoader,
+    config_path: &Path,
+    cmd: &CheckCmd,
+) -> anyhow::Result<()> {
+    let mut live = LiveCheck::new(&state, loader, config_path, None)?;
+
+    loop {
+        // The baseline is loaded again on every run, to follow the triage of another terminal.
+        let result = load_baseline(&state, cmd).and_then(|baseline| {
+            run_land_rules(
+                &state,
+                &live.data.land,
+                StopCondition::from(&cmd.stop),
+                &cmd.report,
+                Some(&live.cache),
+                baseline.as_ref().map(BaselineFilter::new).as_ref(),
+            )
+        });
+
+        if let Err(e) = result {
+            state.logger.error(&format!("{e:?}"));
+        }
+
+        state.logger.info("Watching for changes...");
+
+        let changed = match live.watched.wait_for_changes(&state) {
+            Some(changed) => changed,
+            None => return Ok(()),
+        };
+
+        if let Err(e) = live.apply(&state, loader, &changed) {
+            state.logger.error(&format!("{e:?}"));
+        }
+    }
+}
+
+/// Land of a configuration, kept up to date with the files it depends on.
+///
+/// Edited rulesets are recompiled in place, other changes parse the sources again, apart from the
+/// unchanged files whose trees are kept in the content store. The results of the rules are cached
+/// by subtree, so after a source edit the rules are only evaluated on the modified code. The
+/// cache is cleared when the configuration or a language spec changes.
+///
+/// Files added to a project are only analyzed after the next configuration change.
+pub struct LiveCheck {
+    config_path: PathBuf,
+    /// Directory against which the relative paths of the configuration are resolved, instead of
+    /// the current directory.
+    root: Option<PathBuf>,
+    store: ContentStore,
+    pub cache: RuleCache,
+    pub data: CheckLandData,
+    watched: WatchedFiles,
+    /// Error of the last update, if it failed: the land is then out of date.
+    pub failure: Option<String>,
+}
+
+impl LiveCheck {
+    pub fn new(
+        state: &SylverState,
+        loader: &SylverLoader,
+        config_path: &Path,
+        root: Option<&Path>,
+    ) -> anyhow::Result<LiveCheck> {
+        let store = ContentStore::default();
+        let data = build_live_state(state, loader, config_path, root, &store)?;
+
+        Ok(LiveCheck {
+            config_path: config_path.to_owned(),
+            root: root.map(Path::to_owned),
+            watched: WatchedFiles::new(config_path, &data),
+            store,
+            cache: RuleCache::default(),
+            data,
+            failure: None,
+        })
+    }
+
+    /// Apply the modifications of the watched files since the previous update, without waiting.
+    /// Return true if some files were modified.
+    pub fn update(&mut self, state: &SylverState, loader: &SylverLoader) -> anyhow::Result<bool> {
+        self.update_edited(state, loader, &[])
+    }
+
+    /// Apply the modifications of the watched files, along with those of the given files, whose
+    /// content was edited in the file loader without touching the disk. Return true if some files
+    /// were modified.
+    pub fn update_edited(
+        &mut self,
+        state: &SylverState,
+        loader: &SylverLoader,
+        edited: &[PathBuf],
+    ) -> anyhow::Result<bool> {
+        let mut changed = self.watched.changes();
+        changed.extend_from_slice(edited);
+
+        if changed.is_empty() {
+            return Ok(false);
+        }
+
+        self.apply(state, loader, &changed)?;
+        Ok(true)
+    }
+
+    fn apply(
+        &mut self,
+        state: &SylverState,
+        loader: &SylverLoader,
+        changed: &[PathBuf],
+    ) -> anyhow::Result<()> {
+        let watched = &self.watched;
+
+        if changed
+            .iter()
+            .any(|p| !watched.is_ruleset(p) && !watched.is_source(p))
+        {
+            self.cache = RuleCache::default();
+        }
+
+        let applied = if changed.iter().any(|p| !watched.is_ruleset(p)) {
+            let root = self.root.as_deref();
+            build_live_state(state, loader, &self.config_path, root, &self.store).map(|data| {
+                self.watched = WatchedFiles::new(&self.config_path, &data);
+                self.data = data;
+                // The trees of the edited files are no longer needed.
+                self.store.retain_used();
+            })
+        } else {
+            reload_rulesets(state, loader, &mut self.data, watched, changed)
+        };
+
+        self.failure = applied.as_ref().err().map(|e| format!("{e:#}"));
+        applied
+    }
+}
+
+fn build_live_state(
+    state: &SylverState,
+    loader: &SylverLoader,
+    config_path: &Path,
+    root: Option<&Path>,
+    store: &ContentStore,
+) -> anyhow::Result<CheckLandData> {
+    match root {
+        Some(root) => build_rooted_check_state(state, loader, config_path, root, store),
+        None => build_stored_check_state(state, loader, config_path, store),
+    }
+}
+
+fn reload_rulesets(
+    state: &SylverState,
+    loader: &SylverLoader,
+    check_data: &mut CheckLandData,
+    watched: &WatchedFiles,
+    changed: &[PathBuf],
+) -> anyhow::Result<()> {
+    for (&id, location) in &check_data.rulesets {
+        if watched
+            .rulesets
+            .get(&id)
+            .map_or(false, |p| changed.contains(p))
+        {
+            let policy = check_data.policies.get(&id).cloned().unwrap_or_default();
+            let mut stem = loader.load_ruleset(location, &policy)?;
+            if let Some(disabled) = check_data.disabled_rules.get(&id) {
+                stem.disable_rules(disabled);
+            }
+
+            check_data.land.replace_ruleset(id, &stem)?;
+            warn_renamed_kinds(state, [(location.to_string(), check_data.land.ruleset(id))]);
+        }
+    }
+
+    Ok(())
+}
+
+/// Local files the analysis depends on, with their last modification time.
+struct WatchedFiles {
+    rulesets: HashMap<RuleSetId, PathBuf>,
+    sources: HashSet<PathBuf>,
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl WatchedFiles {
+    fn new(config_path: &Path, check_data: &CheckLandData) -> WatchedFiles {
+        let rulesets: HashMap<_, _> = check_data
+            .rulesets
+            .iter()
+            .filter_map(|(&id, location)| Some((id, local_path(location)?)))
+            .collect();
+
+        let specs = check_data.langs.values().filter_map(|lang| match lang {
+            ProjectLang::Custom(location) => local_path(location),
+            _ => None,
+        });
+
+        let land = &check_data.land;
+        let sources: HashSet<PathBuf> = land
+            .sylvae()
+            .flat_map(|sylva| land.sylva(sylva).iter().map(|(_, tree)| tree.path.clone()))
+            .collect();
+
+        let modified = std::iter::once(config_path.to_owned())
+            .chain(rulesets.values().cloned())
+            .chain(specs)
+            .chain(sources.iter().cloned())
+            .map(|path| {
+                let time = modified_time(&path);
+                (path, time)
+            })
+            .collect();
+
+        WatchedFiles {
+            rulesets,
+            sources,
+            modified,
+        }
+    }
+
+    fn is_ruleset(&self, path: &Path) -> bool {
+        self.rulesets.values().any(|p| p == path)
+    }
+
+    fn is_source(&self, path: &Path) -> bool {
+        self.sources.contains(path)
+    }
+
+    /// Block until at least one of the files is modified, and return the modified files. Return
+    /// `None` if the command is interrupted first.
+    fn wait_for_changes(&mut self, state: &SylverState) -> Option<Vec<PathBuf>> {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+
+            if state.cancel.is_cancelled() {
+                return None;
+            }
+
+            let changed = self.changes();
+
+            if !changed.is_empty() {
+                return Some(changed);
+            }
+        }
+    }
+
+    /// Return the files modified since the previous call.
+    fn changes(&mut self) -> Vec<PathBuf> {
+        self.modified
+            .iter_mut()
+            .filter_map(|(path, time)| {
+                let current = modified_time(path);
+                (current != *time).then(|| {
+                    *time = current;
+                    path.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+fn local_path(location: &StemLocation) -> Option<PathBuf> {
+    match location {
+        StemLocation::Local(path) => Some(path.clone()),
+        _ => None,
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
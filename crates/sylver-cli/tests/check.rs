@@ -61,6 +61,72 @@ fn check_with_violations_fails() {
         .stdout(expected_output);
 }
 
+#[test]
+fn check_with_violations_exit_zero() {
+    let dir = TempDir::new().unwrap();
+
+    create_tmp_child(&dir, "sylver.yaml", VARIABLES_PROJECT).unwrap();
+    create_tmp_child(&dir, "ruleset.yaml", VARIABLES_RULESET).unwrap();
+    create_tmp_child(&dir, "json.syl", JSON_SPEC).unwrap();
+    create_tmp_child(&dir, "invalid_config.json", VARIABLES_INVALID).unwrap();
+
+    let expected_output = include_str!("../test_res/outputs/check/invalid_config_output");
+
+    Command::cargo_bin("sylver")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("--no-color")
+        .arg("check")
+        .arg("--exit-zero")
+        .assert()
+        .success()
+        .stdout(expected_output);
+}
+
+#[test]
+fn check_fail_on_error() {
+    let dir = TempDir::new().unwrap();
+
+    create_tmp_child(&dir, "sylver.yaml", VARIABLES_PROJECT).unwrap();
+    create_tmp_child(&dir, "ruleset.yaml", VARIABLES_RULESET).unwrap();
+    create_tmp_child(&dir, "json.syl", JSON_SPEC).unwrap();
+    create_tmp_child(&dir, "invalid_config.json", VARIABLES_INVALID).unwrap();
+
+    Command::cargo_bin("sylver")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("check")
+        .args(["--fail-on", "error"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn check_max_findings() {
+    let dir = TempDir::new().unwrap();
+
+    create_tmp_child(&dir, "sylver.yaml", VARIABLES_PROJECT).unwrap();
+    create_tmp_child(&dir, "ruleset.yaml", VARIABLES_RULESET).unwrap();
+    create_tmp_child(&dir, "json.syl", JSON_SPEC).unwrap();
+    create_tmp_child(&dir, "invalid_config.json", VARIABLES_INVALID).unwrap();
+
+    let assert = Command::cargo_bin("sylver")
+        .unwrap()
+        .current_dir(dir.path())
+        .arg("--no-color")
+        .arg("check")
+        .args(["--max-findings", "1"])
+        .assert()
+        .failure();
+
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+
+    assert_eq!(stdout.matches("┌─").count(), 1);
+    assert!(!stdout.contains("Stopped"));
+    assert!(stderr.ends_with("Stopped after reaching the maximum of 1 finding(s).\n"));
+}
+
 pub fn create_tmp_child(dir: &TempDir, name: &str, content: &str) -> std::io::Result<PathBuf> {
     let path = dir.child(name);
     std::fs::write(&path, content)?;
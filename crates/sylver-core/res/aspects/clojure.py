@@ -0,0 +1,27 @@
+DEFINING_FORMS = {
+    "def",
+    "defn",
+    "defn-",
+    "defmacro",
+    "defmulti",
+    "defonce",
+    "defprotocol",
+    "defrecord",
+    "deftype",
+    "ns",
+}
+
+@ListLit.sg_gen(depth=2)
+def list_sg_gen(node, scope):
+    forms = [c for c in node.children if c.kind not in ("Comment", "DisExpr")]
+
+    if len(forms) > 1 and forms[0].kind == "SymLit" and forms[1].kind == "SymLit":
+        if forms[0].name.text in DEFINING_FORMS:
+            scope.add_decl(forms[1].name.text, forms[1])
+
+    return scope
+
+@SymLit.sg_gen(depth=1)
+def symbol_sg_gen(node, scope):
+    scope.add_ref(node.name.text, node)
+    return scope
@@ -1,9 +1,9 @@
-@FunctionDefinition.sg_gen()
+@FunctionDefinition.sg_gen(depth=1)
 def fundecl_sg_gen(node, scope):
     scope.add_decl(node.name.text, node)
     return scope
 
-@Identifier.sg_gen()
+@Identifier.sg_gen(depth=0)
 def identifier_sg_gen(node, scope):
     scope.add_ref(node.text, node)
     return scope
\ No newline at end of file
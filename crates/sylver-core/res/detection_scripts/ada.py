@@ -0,0 +1,38 @@
+import path
+import os
+import re
+
+ADA_PATTERN = re.compile(r'^.*\.(adb|ads|ada)$')
+GPR_PATTERN = re.compile(r'^.*\.gpr$')
+ALIRE_PATTERN = re.compile(r'^alire\.toml$')
+
+PATTERNS = [
+    ADA_PATTERN,
+    GPR_PATTERN,
+    ALIRE_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_ada_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/*.adb", "**/*.ads"], "exclude": ["obj/*"]})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_ada_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
@@ -0,0 +1,36 @@
+import path
+import os
+import re
+
+APACHE_CONF_PATTERN = re.compile(r'^(httpd|apache2)\.conf$')
+HTACCESS_PATTERN = re.compile(r'^\.htaccess$')
+
+PATTERNS = [
+    APACHE_CONF_PATTERN,
+    HTACCESS_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_apache_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/httpd.conf", "**/apache2.conf", "**/.htaccess", "**/conf-available/*.conf", "**/conf-enabled/*.conf", "**/sites-available/*.conf", "**/sites-enabled/*.conf"], "exclude": []})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_apache_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
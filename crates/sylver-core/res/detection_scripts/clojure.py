@@ -0,0 +1,40 @@
+import path
+import os
+import re
+
+DEPS_PATTERN = re.compile(r'^deps\.edn$')
+LEININGEN_PATTERN = re.compile(r'^project\.clj$')
+SHADOW_CLJS_PATTERN = re.compile(r'^shadow-cljs\.edn$')
+BABASHKA_PATTERN = re.compile(r'^bb\.edn$')
+
+PATTERNS = [
+    DEPS_PATTERN,
+    LEININGEN_PATTERN,
+    SHADOW_CLJS_PATTERN,
+    BABASHKA_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_clojure_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/*.clj", "**/*.cljs", "**/*.cljc"], "exclude": ["target/**", ".cpcache/**"]})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_clojure_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
\ No newline at end of file
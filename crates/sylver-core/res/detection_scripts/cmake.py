@@ -0,0 +1,36 @@
+import path
+import os
+import re
+
+CMAKE_LISTS_PATTERN = re.compile(r'^CMakeLists\.txt$')
+CMAKE_PATTERN = re.compile(r'^.*\.cmake$')
+
+PATTERNS = [
+    CMAKE_LISTS_PATTERN,
+    CMAKE_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_cmake_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/CMakeLists.txt", "**/*.cmake"], "exclude": ["build/*"]})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_cmake_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
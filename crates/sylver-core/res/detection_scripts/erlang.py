@@ -0,0 +1,38 @@
+import path
+import os
+import re
+
+ERLANG_PATTERN = re.compile(r'^.*\.(erl|hrl)$')
+REBAR_PATTERN = re.compile(r'^rebar\.config$')
+APP_SRC_PATTERN = re.compile(r'^.*\.app\.src$')
+
+PATTERNS = [
+    ERLANG_PATTERN,
+    REBAR_PATTERN,
+    APP_SRC_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_erlang_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/*.erl", "**/*.hrl"], "exclude": ["_build/*", "deps/*"]})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_erlang_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
@@ -0,0 +1,36 @@
+import path
+import os
+import re
+
+NGINX_CONF_PATTERN = re.compile(r'^nginx\.conf$')
+NGINX_SITE_PATTERN = re.compile(r'^.*\.nginx$')
+
+PATTERNS = [
+    NGINX_CONF_PATTERN,
+    NGINX_SITE_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_nginx_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/nginx.conf", "**/*.nginx", "**/conf.d/*.conf", "**/sites-available/*", "**/sites-enabled/*"], "exclude": []})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_nginx_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
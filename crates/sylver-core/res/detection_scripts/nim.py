@@ -0,0 +1,36 @@
+import path
+import os
+import re
+
+NIMBLE_PATTERN = re.compile(r'^.+\.nimble$')
+NIM_CFG_PATTERN = re.compile(r'^(nim\.cfg|config\.nims)$')
+
+PATTERNS = [
+    NIMBLE_PATTERN,
+    NIM_CFG_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_nim_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/*.nim", "**/*.nims"], "exclude": ["nimcache/**", "nimbledeps/**"]})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_nim_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
\ No newline at end of file
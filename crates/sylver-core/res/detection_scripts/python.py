@@ -4,12 +4,14 @@ import re
 
 VENV_PATTERN = re.compile(r'^venv$')
 PY_PATTERN = re.compile(r'^.*\.py$')
+NOTEBOOK_PATTERN = re.compile(r'^.*\.ipynb$')
 PYPROJECT_PATTERN = re.compile(r'^pyproject\.toml$')
 POETRY_PATTERN = re.compile(r'^poetry\.toml$')
 
 PATTERNS = [
     VENV_PATTERN,
     PY_PATTERN,
+    NOTEBOOK_PATTERN,
     PYPROJECT_PATTERN,
     POETRY_PATTERN,
 ]
@@ -29,7 +31,7 @@ def add_project_if_match(projects, detection_root, current_path):
 
     if any(matches_python_patterns(name) for name in childs):
         project_root = path.relpath(current_path, detection_root)
-        projects.append({"root": project_root, "include": ["**/*.py"], "exclude": ["venv/*"]})
+        projects.append({"root": project_root, "include": ["**/*.py", "**/*.ipynb"], "exclude": ["venv/*", "**/.ipynb_checkpoints/**"]})
         return
 
     for child in childs:
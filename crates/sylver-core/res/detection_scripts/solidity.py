@@ -0,0 +1,38 @@
+import path
+import os
+import re
+
+SOLIDITY_PATTERN = re.compile(r'^.*\.sol$')
+FOUNDRY_PATTERN = re.compile(r'^foundry\.toml$')
+HARDHAT_PATTERN = re.compile(r'^hardhat\.config\.(js|ts)$')
+
+PATTERNS = [
+    SOLIDITY_PATTERN,
+    FOUNDRY_PATTERN,
+    HARDHAT_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_solidity_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/*.sol"], "exclude": ["node_modules/*", "lib/*"]})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_solidity_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
@@ -0,0 +1,38 @@
+import path
+import os
+import re
+
+SWIFT_PACKAGE_PATTERN = re.compile(r'^Package\.swift$')
+SWIFT_FILE_PATTERN = re.compile(r'^.*\.swift$')
+XCODE_PROJECT_PATTERN = re.compile(r'^.*\.xcodeproj$')
+
+PATTERNS = [
+    SWIFT_PACKAGE_PATTERN,
+    SWIFT_FILE_PATTERN,
+    XCODE_PROJECT_PATTERN,
+]
+
+
+def detect_projects(root):
+    projects = []
+    add_project_if_match(projects, root, root)
+    return projects
+
+
+def add_project_if_match(projects, detection_root, current_path):
+    if not path.isdir(current_path):
+        return
+
+    childs = os.listdir(current_path)
+
+    if any(matches_swift_patterns(name) for name in childs):
+        project_root = path.relpath(current_path, detection_root)
+        projects.append({"root": project_root, "include": ["**/*.swift"], "exclude": [".build/**", "**/Pods/**"]})
+        return
+
+    for child in childs:
+        add_project_if_match(projects, detection_root, path.join(current_path, child))
+
+
+def matches_swift_patterns(name):
+    return any(pattern.match(name) for pattern in PATTERNS)
\ No newline at end of file
@@ -3,9 +3,11 @@ import os
 import re
 
 YAML_PATTERN = re.compile(r'^.*\.(yaml|yml)$')
+TERRAFORM_PLAN_PATTERN = re.compile(r'^.*\.tfplan\.json$')
 
 PATTERNS = [
-    YAML_PATTERN
+    YAML_PATTERN,
+    TERRAFORM_PLAN_PATTERN,
 ]
 
 
@@ -23,7 +25,7 @@ def add_project_if_match(projects, detection_root, current_path):
 
     if any(matches_yaml_patterns(name) for name in childs):
         project_root = path.relpath(current_path, detection_root)
-        projects.append({"root": project_root, "include": ["**/*.yaml", "**/*.yml"], "exclude": []})
+        projects.append({"root": project_root, "include": ["**/*.yaml", "**/*.yml", "**/*.tfplan.json"], "exclude": []})
         return
 
     for child in childs:
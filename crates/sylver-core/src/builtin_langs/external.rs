@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use libloading::{Library, Symbol};
+use tree_sitter::{Language, LANGUAGE_VERSION, MIN_COMPATIBLE_LANGUAGE_VERSION};
+
+use crate::{
+    builtin_langs::MappingConfig, core::spec::Syntax, specs::stem::project::ExternalGrammar,
+};
+
+/// Load the tree-sitter language, the node mappings and the syntax of an external grammar. The
+/// ABI of the grammar and the consistency of the mappings are checked here, so that parsing
+/// doesn't fail later on.
+pub fn load_external_grammar(
+    grammar: &ExternalGrammar,
+) -> anyhow::Result<(Language, MappingConfig, Syntax)> {
+    let library_path = Path::new(&grammar.library);
+
+    if library_path.extension().map_or(false, |ext| ext == "wasm") {
+        bail!(
+            "Cannot load {}: WebAssembly grammars are not supported, use a shared library",
+            grammar.library
+        );
+    }
+
+    let mappings_str = std::fs::read_to_string(&grammar.mappings)
+        .with_context(|| format!("Could not read grammar mappings: {}", grammar.mappings))?;
+    let mappings: MappingConfig = serde_yaml::from_str(&mappings_str)
+        .with_context(|| format!("Invalid grammar mappings: {}", grammar.mappings))?;
+    let syntax = mappings
+        .syntax()
+        .with_context(|| format!("Invalid grammar mappings: {}", grammar.mappings))?;
+
+    let symbol_name = format!("tree_sitter_{}", grammar.name.replace('-', "_"));
+
+    // SAFETY: the library is expected to be a tree-sitter grammar, exporting a function with the
+    // same signature as the ones generated by the tree-sitter CLI.
+    let language = unsafe {
+        let library = Library::new(library_path)
+            .with_context(|| format!("Could not load grammar library: {}", grammar.library))?;
+
+        let language_fn: Symbol<unsafe extern "C" fn() -> Language> = library
+            .get(symbol_name.as_bytes())
+            .with_context(|| format!("Missing symbol {symbol_name} in {}", grammar.library))?;
+
+        let language = language_fn();
+
+        // The language points to static data of the library, which must therefore never be
+        // unloaded.
+        std::mem::forget(library);
+
+        language
+    };
+
+    let abi = language.version();
+
+    if !(MIN_COMPATIBLE_LANGUAGE_VERSION..=LANGUAGE_VERSION).contains(&abi) {
+        bail!(
+            "Incompatible grammar {}: ABI version {abi}, supported versions are \
+             {MIN_COMPATIBLE_LANGUAGE_VERSION} to {LANGUAGE_VERSION}",
+            grammar.library
+        );
+    }
+
+    Ok((language, mappings, syntax))
+}
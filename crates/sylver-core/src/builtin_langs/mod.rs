@@ -4,20 +4,23 @@ use std::{
     ops::Deref,
 };
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail, Context};
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
 use sylver_dsl::meta::*;
 
-use crate::core::spec::{Syntax, SyntaxBuilder};
+use crate::core::spec::{Spec, Syntax, SyntaxBuilder};
 
+pub mod external;
 pub mod parser;
 
 static PYTHON_MAPPING: Lazy<MappingConfig> =
     Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/python.yaml")).unwrap());
 
 static PYTHON_ASPECTS: Option<&'static str> = Some(include_str!("../../res/aspects/python.py"));
+static CLOJURE_ASPECTS: Option<&'static str> = Some(include_str!("../../res/aspects/clojure.py"));
 
 static JAVASCRIPT_MAPPING: Lazy<MappingConfig> = Lazy::new(|| {
     serde_yaml::from_str(include_str!("../../res/ts_mappings/javascript.yaml")).unwrap()
@@ -26,12 +29,148 @@ static JAVASCRIPT_MAPPING: Lazy<MappingConfig> = Lazy::new(|| {
 static YAML_MAPPING: Lazy<MappingConfig> =
     Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/yaml.yaml")).unwrap());
 
+static CMAKE_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/cmake.yaml")).unwrap());
+
+static SOLIDITY_MAPPING: Lazy<MappingConfig> = Lazy::new(|| {
+    serde_yaml::from_str(include_str!("../../res/ts_mappings/solidity.yaml")).unwrap()
+});
+
+static ERLANG_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/erlang.yaml")).unwrap());
+
+static ASM_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/asm.yaml")).unwrap());
+
+static ADA_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/ada.yaml")).unwrap());
+
+static POWERSHELL_MAPPING: Lazy<MappingConfig> = Lazy::new(|| {
+    serde_yaml::from_str(include_str!("../../res/ts_mappings/powershell.yaml")).unwrap()
+});
+
+static COBOL_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/cobol.yaml")).unwrap());
+
+static LATEX_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/latex.yaml")).unwrap());
+
+static FSHARP_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/fsharp.yaml")).unwrap());
+
+static CRYSTAL_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/crystal.yaml")).unwrap());
+
+static SCHEME_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/scheme.yaml")).unwrap());
+
+static RESCRIPT_MAPPING: Lazy<MappingConfig> = Lazy::new(|| {
+    serde_yaml::from_str(include_str!("../../res/ts_mappings/rescript.yaml")).unwrap()
+});
+
+static JINJA_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/jinja.yaml")).unwrap());
+
+static NGINX_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/nginx.yaml")).unwrap());
+
+static THRIFT_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/thrift.yaml")).unwrap());
+
+static ELM_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/elm.yaml")).unwrap());
+
+static HTML_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/html.yaml")).unwrap());
+
+static XML_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/xml.yaml")).unwrap());
+
+static D_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/d.yaml")).unwrap());
+
+static NIM_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/nim.yaml")).unwrap());
+
+static VLANG_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/vlang.yaml")).unwrap());
+
+static CUE_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/cue.yaml")).unwrap());
+
+static JSONNET_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/jsonnet.yaml")).unwrap());
+
+static CLOJURE_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/clojure.yaml")).unwrap());
+
+static MAKE_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/make.yaml")).unwrap());
+
+static TCL_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/tcl.yaml")).unwrap());
+
+static AWK_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/awk.yaml")).unwrap());
+
+static SWIFT_MAPPING: Lazy<MappingConfig> =
+    Lazy::new(|| serde_yaml::from_str(include_str!("../../res/ts_mappings/swift.yaml")).unwrap());
+
+/// Apache configurations have no maintained tree-sitter grammar, so they are parsed by the Sylver
+/// parser from a bundled spec.
+static APACHE_SPEC: Lazy<Spec> =
+    Lazy::new(|| builtin_spec(include_str!("../../res/specs/apache.syl")).unwrap());
+
+/// Avro IDL has no tree-sitter grammar either.
+static AVRO_IDL_SPEC: Lazy<Spec> =
+    Lazy::new(|| builtin_spec(include_str!("../../res/specs/avro_idl.syl")).unwrap());
+
+/// Shared by the TypeScript and TSX grammars, which only differ by the JSX and type assertion
+/// kinds.
+static TYPESCRIPT_MAPPING: Lazy<MappingConfig> = Lazy::new(|| {
+    serde_yaml::from_str(include_str!("../../res/ts_mappings/typescript.yaml")).unwrap()
+});
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 #[serde(tag = "name", rename_all = "snake_case")]
 pub enum BuiltinLang {
     Python,
     Javascript,
     Yaml,
+    Cmake,
+    Solidity,
+    Erlang,
+    Asm,
+    Ada,
+    #[serde(rename = "powershell")]
+    PowerShell,
+    Cobol,
+    Latex,
+    #[serde(rename = "fsharp")]
+    FSharp,
+    Crystal,
+    Scheme,
+    Rescript,
+    Jinja,
+    Nginx,
+    Apache,
+    Thrift,
+    AvroIdl,
+    Elm,
+    Html,
+    Xml,
+    D,
+    Nim,
+    Vlang,
+    Cue,
+    Jsonnet,
+    Clojure,
+    Make,
+    Tcl,
+    Awk,
+    Swift,
+    Typescript,
+    Tsx,
 }
 
 impl Display for BuiltinLang {
@@ -40,6 +179,38 @@ impl Display for BuiltinLang {
             BuiltinLang::Python => "python",
             BuiltinLang::Javascript => "javascript",
             BuiltinLang::Yaml => "yaml",
+            BuiltinLang::Cmake => "cmake",
+            BuiltinLang::Solidity => "solidity",
+            BuiltinLang::Erlang => "erlang",
+            BuiltinLang::Asm => "asm",
+            BuiltinLang::Ada => "ada",
+            BuiltinLang::PowerShell => "powershell",
+            BuiltinLang::Cobol => "cobol",
+            BuiltinLang::Latex => "latex",
+            BuiltinLang::FSharp => "fsharp",
+            BuiltinLang::Crystal => "crystal",
+            BuiltinLang::Scheme => "scheme",
+            BuiltinLang::Rescript => "rescript",
+            BuiltinLang::Jinja => "jinja",
+            BuiltinLang::Nginx => "nginx",
+            BuiltinLang::Apache => "apache",
+            BuiltinLang::Thrift => "thrift",
+            BuiltinLang::AvroIdl => "avro_idl",
+            BuiltinLang::Elm => "elm",
+            BuiltinLang::Html => "html",
+            BuiltinLang::Xml => "xml",
+            BuiltinLang::D => "d",
+            BuiltinLang::Nim => "nim",
+            BuiltinLang::Vlang => "vlang",
+            BuiltinLang::Cue => "cue",
+            BuiltinLang::Jsonnet => "jsonnet",
+            BuiltinLang::Clojure => "clojure",
+            BuiltinLang::Make => "make",
+            BuiltinLang::Tcl => "tcl",
+            BuiltinLang::Awk => "awk",
+            BuiltinLang::Swift => "swift",
+            BuiltinLang::Typescript => "typescript",
+            BuiltinLang::Tsx => "tsx",
         };
 
         lang_name.fmt(f)
@@ -54,6 +225,38 @@ impl TryFrom<&str> for BuiltinLang {
             "python" => Ok(BuiltinLang::Python),
             "javascript" => Ok(BuiltinLang::Javascript),
             "yaml" => Ok(BuiltinLang::Yaml),
+            "cmake" => Ok(BuiltinLang::Cmake),
+            "solidity" => Ok(BuiltinLang::Solidity),
+            "erlang" => Ok(BuiltinLang::Erlang),
+            "asm" => Ok(BuiltinLang::Asm),
+            "ada" => Ok(BuiltinLang::Ada),
+            "powershell" => Ok(BuiltinLang::PowerShell),
+            "cobol" => Ok(BuiltinLang::Cobol),
+            "latex" => Ok(BuiltinLang::Latex),
+            "fsharp" => Ok(BuiltinLang::FSharp),
+            "crystal" => Ok(BuiltinLang::Crystal),
+            "scheme" => Ok(BuiltinLang::Scheme),
+            "rescript" => Ok(BuiltinLang::Rescript),
+            "jinja" => Ok(BuiltinLang::Jinja),
+            "nginx" => Ok(BuiltinLang::Nginx),
+            "apache" => Ok(BuiltinLang::Apache),
+            "thrift" => Ok(BuiltinLang::Thrift),
+            "avro_idl" => Ok(BuiltinLang::AvroIdl),
+            "elm" => Ok(BuiltinLang::Elm),
+            "html" => Ok(BuiltinLang::Html),
+            "xml" => Ok(BuiltinLang::Xml),
+            "d" => Ok(BuiltinLang::D),
+            "nim" => Ok(BuiltinLang::Nim),
+            "vlang" => Ok(BuiltinLang::Vlang),
+            "cue" => Ok(BuiltinLang::Cue),
+            "jsonnet" => Ok(BuiltinLang::Jsonnet),
+            "clojure" => Ok(BuiltinLang::Clojure),
+            "make" => Ok(BuiltinLang::Make),
+            "tcl" => Ok(BuiltinLang::Tcl),
+            "awk" => Ok(BuiltinLang::Awk),
+            "swift" => Ok(BuiltinLang::Swift),
+            "typescript" => Ok(BuiltinLang::Typescript),
+            "tsx" => Ok(BuiltinLang::Tsx),
             _ => Err(anyhow!("Unsupported language: {}", value)),
         }
     }
@@ -64,6 +267,38 @@ pub fn get_detection_script(lang: BuiltinLang) -> &'static str {
         BuiltinLang::Python => include_str!("../../res/detection_scripts/python.py"),
         BuiltinLang::Javascript => include_str!("../../res/detection_scripts/javascript.py"),
         BuiltinLang::Yaml => include_str!("../../res/detection_scripts/yaml.py"),
+        BuiltinLang::Cmake => include_str!("../../res/detection_scripts/cmake.py"),
+        BuiltinLang::Solidity => include_str!("../../res/detection_scripts/solidity.py"),
+        BuiltinLang::Erlang => include_str!("../../res/detection_scripts/erlang.py"),
+        BuiltinLang::Asm => include_str!("../../res/detection_scripts/asm.py"),
+        BuiltinLang::Ada => include_str!("../../res/detection_scripts/ada.py"),
+        BuiltinLang::PowerShell => include_str!("../../res/detection_scripts/powershell.py"),
+        BuiltinLang::Cobol => include_str!("../../res/detection_scripts/cobol.py"),
+        BuiltinLang::Latex => include_str!("../../res/detection_scripts/latex.py"),
+        BuiltinLang::FSharp => include_str!("../../res/detection_scripts/fsharp.py"),
+        BuiltinLang::Crystal => include_str!("../../res/detection_scripts/crystal.py"),
+        BuiltinLang::Scheme => include_str!("../../res/detection_scripts/scheme.py"),
+        BuiltinLang::Rescript => include_str!("../../res/detection_scripts/rescript.py"),
+        BuiltinLang::Jinja => include_str!("../../res/detection_scripts/jinja.py"),
+        BuiltinLang::Nginx => include_str!("../../res/detection_scripts/nginx.py"),
+        BuiltinLang::Apache => include_str!("../../res/detection_scripts/apache.py"),
+        BuiltinLang::Thrift => include_str!("../../res/detection_scripts/thrift.py"),
+        BuiltinLang::AvroIdl => include_str!("../../res/detection_scripts/avro_idl.py"),
+        BuiltinLang::Elm => include_str!("../../res/detection_scripts/elm.py"),
+        BuiltinLang::Html => include_str!("../../res/detection_scripts/html.py"),
+        BuiltinLang::Xml => include_str!("../../res/detection_scripts/xml.py"),
+        BuiltinLang::D => include_str!("../../res/detection_scripts/d.py"),
+        BuiltinLang::Nim => include_str!("../../res/detection_scripts/nim.py"),
+        BuiltinLang::Vlang => include_str!("../../res/detection_scripts/vlang.py"),
+        BuiltinLang::Cue => include_str!("../../res/detection_scripts/cue.py"),
+        BuiltinLang::Jsonnet => include_str!("../../res/detection_scripts/jsonnet.py"),
+        BuiltinLang::Clojure => include_str!("../../res/detection_scripts/clojure.py"),
+        BuiltinLang::Make => include_str!("../../res/detection_scripts/make.py"),
+        BuiltinLang::Tcl => include_str!("../../res/detection_scripts/tcl.py"),
+        BuiltinLang::Awk => include_str!("../../res/detection_scripts/awk.py"),
+        BuiltinLang::Swift => include_str!("../../res/detection_scripts/swift.py"),
+        BuiltinLang::Typescript => include_str!("../../res/detection_scripts/typescript.py"),
+        BuiltinLang::Tsx => include_str!("../../res/detection_scripts/tsx.py"),
     }
 }
 
@@ -72,9 +307,46 @@ pub fn get_builtin_langs() -> Vec<BuiltinLang> {
         BuiltinLang::Python,
         BuiltinLang::Javascript,
         BuiltinLang::Yaml,
+        BuiltinLang::Cmake,
+        BuiltinLang::Solidity,
+        BuiltinLang::Erlang,
+        BuiltinLang::Asm,
+        BuiltinLang::Ada,
+        BuiltinLang::PowerShell,
+        BuiltinLang::Cobol,
+        BuiltinLang::Latex,
+        BuiltinLang::FSharp,
+        BuiltinLang::Crystal,
+        BuiltinLang::Scheme,
+        BuiltinLang::Rescript,
+        BuiltinLang::Jinja,
+        BuiltinLang::Nginx,
+        BuiltinLang::Apache,
+        BuiltinLang::Thrift,
+        BuiltinLang::AvroIdl,
+        BuiltinLang::Elm,
+        BuiltinLang::Html,
+        BuiltinLang::Xml,
+        BuiltinLang::D,
+        BuiltinLang::Nim,
+        BuiltinLang::Vlang,
+        BuiltinLang::Cue,
+        BuiltinLang::Jsonnet,
+        BuiltinLang::Clojure,
+        BuiltinLang::Make,
+        BuiltinLang::Tcl,
+        BuiltinLang::Awk,
+        BuiltinLang::Swift,
+        BuiltinLang::Typescript,
+        BuiltinLang::Tsx,
     ]
 }
 
+/// Tree-sitter grammar of a builtin language, along with its mappings and the code of its aspects.
+///
+/// # Panics
+///
+/// Panics for the languages parsed from a Sylver spec, see `get_builtin_spec`.
 pub fn get_builtin_lang(
     lang: BuiltinLang,
 ) -> (
@@ -94,22 +366,238 @@ pub fn get_builtin_lang(
             None,
         ),
         BuiltinLang::Yaml => (YAML_MAPPING.deref(), sylver_langs::yaml_language(), None),
+        BuiltinLang::Cmake => (CMAKE_MAPPING.deref(), sylver_langs::cmake_language(), None),
+        BuiltinLang::Solidity => (
+            SOLIDITY_MAPPING.deref(),
+            sylver_langs::solidity_language(),
+            None,
+        ),
+        BuiltinLang::Erlang => (
+            ERLANG_MAPPING.deref(),
+            sylver_langs::erlang_language(),
+            None,
+        ),
+        BuiltinLang::Asm => (ASM_MAPPING.deref(), sylver_langs::asm_language(), None),
+        BuiltinLang::Ada => (ADA_MAPPING.deref(), sylver_langs::ada_language(), None),
+        BuiltinLang::PowerShell => (
+            POWERSHELL_MAPPING.deref(),
+            sylver_langs::powershell_language(),
+            None,
+        ),
+        BuiltinLang::Cobol => (COBOL_MAPPING.deref(), sylver_langs::cobol_language(), None),
+        BuiltinLang::Latex => (LATEX_MAPPING.deref(), sylver_langs::latex_language(), None),
+        BuiltinLang::FSharp => (
+            FSHARP_MAPPING.deref(),
+            sylver_langs::fsharp_language(),
+            None,
+        ),
+        BuiltinLang::Crystal => (
+            CRYSTAL_MAPPING.deref(),
+            sylver_langs::crystal_language(),
+            None,
+        ),
+        BuiltinLang::Scheme => (
+            SCHEME_MAPPING.deref(),
+            sylver_langs::scheme_language(),
+            None,
+        ),
+        BuiltinLang::Rescript => (
+            RESCRIPT_MAPPING.deref(),
+            sylver_langs::rescript_language(),
+            None,
+        ),
+        BuiltinLang::Jinja => (JINJA_MAPPING.deref(), sylver_langs::jinja_language(), None),
+        BuiltinLang::Nginx => (NGINX_MAPPING.deref(), sylver_langs::nginx_language(), None),
+        BuiltinLang::Apache | BuiltinLang::AvroIdl => {
+            panic!("{lang} is parsed from a Sylver spec")
+        }
+        BuiltinLang::Thrift => (
+            THRIFT_MAPPING.deref(),
+            sylver_langs::thrift_language(),
+            None,
+        ),
+        BuiltinLang::Elm => (ELM_MAPPING.deref(), sylver_langs::elm_language(), None),
+        BuiltinLang::Html => (HTML_MAPPING.deref(), sylver_langs::html_language(), None),
+        BuiltinLang::Xml => (XML_MAPPING.deref(), sylver_langs::xml_language(), None),
+        BuiltinLang::D => (D_MAPPING.deref(), sylver_langs::d_language(), None),
+        BuiltinLang::Nim => (NIM_MAPPING.deref(), sylver_langs::nim_language(), None),
+        BuiltinLang::Vlang => (VLANG_MAPPING.deref(), sylver_langs::v_language(), None),
+        BuiltinLang::Cue => (CUE_MAPPING.deref(), sylver_langs::cue_language(), None),
+        BuiltinLang::Jsonnet => (
+            JSONNET_MAPPING.deref(),
+            sylver_langs::jsonnet_language(),
+            None,
+        ),
+        BuiltinLang::Clojure => (
+            CLOJURE_MAPPING.deref(),
+            sylver_langs::clojure_language(),
+            CLOJURE_ASPECTS,
+        ),
+        BuiltinLang::Make => (MAKE_MAPPING.deref(), sylver_langs::make_language(), None),
+        BuiltinLang::Tcl => (TCL_MAPPING.deref(), sylver_langs::tcl_language(), None),
+        BuiltinLang::Awk => (AWK_MAPPING.deref(), sylver_langs::awk_language(), None),
+        BuiltinLang::Swift => (SWIFT_MAPPING.deref(), sylver_langs::swift_language(), None),
+        BuiltinLang::Typescript => (
+            TYPESCRIPT_MAPPING.deref(),
+            sylver_langs::typescript_language(),
+            None,
+        ),
+        BuiltinLang::Tsx => (
+            TYPESCRIPT_MAPPING.deref(),
+            sylver_langs::tsx_language(),
+            None,
+        ),
+    }
+}
+
+/// Spec of the builtin languages that are parsed by the Sylver parser rather than a tree-sitter
+/// grammar.
+pub fn get_builtin_spec(lang: BuiltinLang) -> Option<&'static Spec> {
+    match lang {
+        BuiltinLang::Apache => Some(APACHE_SPEC.deref()),
+        BuiltinLang::AvroIdl => Some(AVRO_IDL_SPEC.deref()),
+        _ => None,
     }
 }
 
+fn builtin_spec(source: &str) -> anyhow::Result<Spec> {
+    let syntax = SyntaxBuilder::new().build(sylver_dsl::meta::parse(source)?)?;
+    Ok(Spec::new(Default::default(), syntax))
+}
+
 pub fn builtin_lang_mappings(lang: BuiltinLang) -> &'static [NodeMapping] {
     match lang {
         BuiltinLang::Python => PYTHON_MAPPING.types.as_slice(),
         BuiltinLang::Javascript => JAVASCRIPT_MAPPING.types.as_slice(),
         BuiltinLang::Yaml => YAML_MAPPING.types.as_slice(),
+        BuiltinLang::Cmake => CMAKE_MAPPING.types.as_slice(),
+        BuiltinLang::Solidity => SOLIDITY_MAPPING.types.as_slice(),
+        BuiltinLang::Erlang => ERLANG_MAPPING.types.as_slice(),
+        BuiltinLang::Asm => ASM_MAPPING.types.as_slice(),
+        BuiltinLang::Ada => ADA_MAPPING.types.as_slice(),
+        BuiltinLang::PowerShell => POWERSHELL_MAPPING.types.as_slice(),
+        BuiltinLang::Cobol => COBOL_MAPPING.types.as_slice(),
+        BuiltinLang::Latex => LATEX_MAPPING.types.as_slice(),
+        BuiltinLang::FSharp => FSHARP_MAPPING.types.as_slice(),
+        BuiltinLang::Crystal => CRYSTAL_MAPPING.types.as_slice(),
+        BuiltinLang::Scheme => SCHEME_MAPPING.types.as_slice(),
+        BuiltinLang::Rescript => RESCRIPT_MAPPING.types.as_slice(),
+        BuiltinLang::Jinja => JINJA_MAPPING.types.as_slice(),
+        BuiltinLang::Nginx => NGINX_MAPPING.types.as_slice(),
+        BuiltinLang::Apache | BuiltinLang::AvroIdl => &[],
+        BuiltinLang::Thrift => THRIFT_MAPPING.types.as_slice(),
+        BuiltinLang::Elm => ELM_MAPPING.types.as_slice(),
+        BuiltinLang::Html => HTML_MAPPING.types.as_slice(),
+        BuiltinLang::Xml => XML_MAPPING.types.as_slice(),
+        BuiltinLang::D => D_MAPPING.types.as_slice(),
+        BuiltinLang::Nim => NIM_MAPPING.types.as_slice(),
+        BuiltinLang::Vlang => VLANG_MAPPING.types.as_slice(),
+        BuiltinLang::Cue => CUE_MAPPING.types.as_slice(),
+        BuiltinLang::Jsonnet => JSONNET_MAPPING.types.as_slice(),
+        BuiltinLang::Clojure => CLOJURE_MAPPING.types.as_slice(),
+        BuiltinLang::Make => MAKE_MAPPING.types.as_slice(),
+        BuiltinLang::Tcl => TCL_MAPPING.types.as_slice(),
+        BuiltinLang::Awk => AWK_MAPPING.types.as_slice(),
+        BuiltinLang::Swift => SWIFT_MAPPING.types.as_slice(),
+        BuiltinLang::Typescript | BuiltinLang::Tsx => TYPESCRIPT_MAPPING.types.as_slice(),
     }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct MappingConfig {
     pub types: Vec<NodeMapping>,
+    #[serde(default)]
     pub aliases: Vec<NodeAlias>,
+    #[serde(default)]
     pub fields: Vec<FieldSettings>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub versions: Vec<LanguageVersion>,
+    /// Kinds renamed since a previous version of the mapping, so that the queries using their
+    /// former name keep working.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renamed_kinds: Vec<KindRename>,
+}
+
+impl MappingConfig {
+    /// Build the syntax of the mappings, reporting inconsistent mappings as errors. The `From`
+    /// conversion is reserved to the builtin mappings, which are known to be valid.
+    pub fn syntax(&self) -> anyhow::Result<Syntax> {
+        let syntax = syntax_from_mappings(&self.types)?.with_renamed_kinds(
+            self.renamed_kinds
+                .iter()
+                .map(|r| (r.from.clone(), r.to.clone())),
+        );
+
+        self.check_kinds(&syntax)?;
+
+        Ok(syntax)
+    }
+
+    /// Check that the kinds referenced by the aliases, fields, versions and renames exist.
+    fn check_kinds(&self, syntax: &Syntax) -> anyhow::Result<()> {
+        let check = |kind: &str, context: &str| {
+            if syntax.list_stripped_kind_id(kind).is_none() {
+                bail!("Unknown kind {kind} in {context}");
+            }
+            Ok(())
+        };
+
+        for alias in &self.aliases {
+            if !self
+                .types
+                .iter()
+                .any(|m| m.ts_name.as_ref() == Some(&alias.ts_name))
+            {
+                bail!(
+                    "Unknown tree-sitter node {} in the alias {}",
+                    alias.ts_name,
+                    alias.alias
+                );
+            }
+        }
+
+        for field in &self.fields {
+            check(&field.parent_kind, "fields")?;
+            check(&field.new_kind, "fields")?;
+        }
+
+        for version in &self.versions {
+            for kind in &version.unavailable {
+                check(kind, &format!("version {}", version.name))?;
+            }
+        }
+
+        for rename in &self.renamed_kinds {
+            check(&rename.to, &format!("the rename of {}", rename.from))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn version(&self, name: &str) -> anyhow::Result<&LanguageVersion> {
+        self.versions
+            .iter()
+            .find(|v| v.name == name)
+            .ok_or_else(|| {
+                let available = self.versions.iter().map(|v| v.name.as_str()).join(", ");
+                anyhow!("Unsupported language version: {name} (available versions: {available})")
+            })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct KindRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// Version of a builtin language, restricting the accepted syntax.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct LanguageVersion {
+    pub name: String,
+    /// Kinds of the nodes whose syntax is not available in this version.
+    #[serde(default)]
+    pub unavailable: Vec<String>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -141,6 +629,12 @@ pub struct NodeMapping {
     fields: Vec<NodeMappingField>,
     is_list: bool,
     is_terminal: bool,
+    /// Regex matching the text of a terminal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
+    /// Text of a terminal, matched literally.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    literal: Option<String>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -153,56 +647,124 @@ struct NodeMappingField {
 
 impl From<&[NodeMapping]> for Syntax {
     fn from(mappings: &[NodeMapping]) -> Self {
-        let decls = mappings.iter().map(|m| {
+        syntax_from_mappings(mappings).expect("invalid builtin mappings")
+    }
+}
+
+impl From<&MappingConfig> for Syntax {
+    fn from(config: &MappingConfig) -> Self {
+        config.syntax().expect("invalid builtin mappings")
+    }
+}
+
+fn syntax_from_mappings(mappings: &[NodeMapping]) -> anyhow::Result<Syntax> {
+    let decls = mappings
+        .iter()
+        .map(|m| {
             if m.is_terminal {
-                Decl::Terminal(term_decl_from_mapping(m))
+                term_decl_from_mapping(m).map(Decl::Terminal)
             } else {
-                Decl::Node(node_decl_from_mapping(m))
+                node_decl_from_mapping(m).map(Decl::Node)
             }
-        });
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
-        SyntaxBuilder::new().build(decls).unwrap()
-    }
+    Ok(SyntaxBuilder::new().build(decls)?)
 }
 
-fn term_decl_from_mapping(m: &NodeMapping) -> TermDecl {
-    TermDecl {
+/// Terminals without an explicit pattern or literal are matched by their name.
+fn term_decl_from_mapping(m: &NodeMapping) -> anyhow::Result<TermDecl> {
+    let reg = match (&m.pattern, &m.literal) {
+        (Some(_), Some(_)) => bail!("Terminal {} has both a pattern and a literal", m.name),
+        (Some(pattern), None) => TermContent::Regex(
+            fancy_regex::Regex::new(pattern)
+                .with_context(|| format!("Invalid pattern for terminal {}", m.name))?,
+        ),
+        (None, literal) => {
+            let text = literal.as_ref().unwrap_or(&m.name);
+            TermContent::Literal(fancy_regex::Regex::new(&fancy_regex::escape(text))?)
+        }
+    };
+
+    Ok(TermDecl {
         name: m.name.clone(),
-        reg: TermContent::Literal(fancy_regex::Regex::new(&m.name).unwrap()),
+        reg,
         data: None,
-    }
+    })
 }
 
-fn node_decl_from_mapping(m: &NodeMapping) -> NodeDecl {
-    NodeDecl {
+fn node_decl_from_mapping(m: &NodeMapping) -> anyhow::Result<NodeDecl> {
+    let fields = m
+        .fields
+        .iter()
+        .map(|f| {
+            if f.types.is_empty() {
+                bail!("Field {} of {} has no type", f.name, m.name);
+            }
+            Ok((f.name.to_string(), field_type_from_mapping(f)))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(NodeDecl {
         name: m.name.clone(),
         parent_type: m.inherits.clone(),
-        fields: m
-            .fields
-            .iter()
-            .map(|f| {
-                let field_type = TypeLit::from_simple_types(
-                    f.types.iter().map(|n| SimpleTypeLit::from_name(n.clone())),
-                )
-                .expect("node field missing associated types");
-
-                let lit = if f.list {
-                    TypeLit::list_of(field_type)
-                } else {
-                    field_type
-                };
+        fields,
+    })
+}
 
-                (f.name.to_string(), lit)
-            })
-            .collect(),
+/// Type of a mapped field: the union of its types, in a list if the field is a list. The types
+/// can be written as type literals (`List<A | B>`), whose list elements join the union.
+fn field_type_from_mapping(f: &NodeMappingField) -> TypeLit {
+    let mut is_list = f.list;
+    let mut alts: Vec<SimpleTypeLit> = vec![];
+
+    for type_str in &f.types {
+        // Types that are not type literals (such as the operator tokens of a field) are kept as
+        // opaque names.
+        let lit = parse_type_lit(type_str).unwrap_or_else(|_| TypeLit::from_name(type_str.clone()));
+
+        let elems = match list_elems_type(&lit) {
+            Some(elems) => {
+                is_list = true;
+                elems.clone()
+            }
+            None => lit,
+        };
+
+        let elem_alts = match elems {
+            TypeLit::Simple(simple) => vec![simple],
+            TypeLit::Or(or) => or.alts.iter().cloned().collect(),
+        };
+
+        for alt in elem_alts {
+            if !alts.contains(&alt) {
+                alts.push(alt);
+            }
+        }
+    }
+
+    let field_type = TypeLit::from_simple_types(alts).expect("node field missing associated types");
+
+    if is_list {
+        TypeLit::list_of(field_type)
+    } else {
+        field_type
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::collections::{HashMap, HashSet};
+
     use crate::{
-        builtin_langs::parser::BuiltinParserRunner, core::source::Source,
-        pretty_print::tree::TreePPrint, tree::info::raw::RawTreeInfo,
+        builtin_langs::parser::BuiltinParserRunner,
+        core::{
+            source::Source,
+            spec::{DEFAULT_START_RULE, ERROR_KIND},
+        },
+        parsing::parser_runner::ParserRunner,
+        pretty_print::tree::TreePPrint,
+        tree::info::{raw::RawTreeInfo, TreeInfo},
     };
     use indoc::indoc;
     use tree_sitter::Language;
@@ -290,6 +852,91 @@ mod test {
         );
     }
 
+    #[test]
+    fn javascript_jsx() {
+        let syntax: Syntax = JAVASCRIPT_MAPPING.types.as_slice().into();
+        let runner = BuiltinParserRunner::new(
+            sylver_langs::javascript_language(),
+            &syntax,
+            &JAVASCRIPT_MAPPING,
+        );
+        let source = Source::inline(
+            "<App style={{ color: 'red' }} {...props}>{name}<br /></App>".to_string(),
+            "BUFFER".to_string(),
+        );
+        let result = runner.run(source);
+        let info = RawTreeInfo::new(&result.tree, &syntax);
+
+        let kinds: HashSet<&str> = info
+            .node_ids()
+            .map(|n| info.kind_name(info.node(n).kind))
+            .collect();
+
+        for kind in [
+            "JsxElement",
+            "JsxOpeningElement",
+            "JsxClosingElement",
+            "JsxSelfClosingElement",
+            "JsxAttribute",
+            "JsxExpression",
+            "SpreadElement",
+        ] {
+            assert!(kinds.contains(kind), "missing {kind}");
+        }
+
+        assert!(result.reports.is_empty());
+    }
+
+    #[test]
+    fn tsx_elements() {
+        let syntax: Syntax = TYPESCRIPT_MAPPING.types.as_slice().into();
+        let runner =
+            BuiltinParserRunner::new(sylver_langs::tsx_language(), &syntax, &TYPESCRIPT_MAPPING);
+        let source = Source::inline(
+            indoc!(
+                "
+                interface Props { name: string; count?: number }
+
+                export function Greeting({ name }: Props): JSX.Element {
+                    const [count, setCount] = useState<number>(0);
+                    return <div style={{ color: 'red' }} onClick={() => setCount(count + 1)}>
+                        Hello {name as string}!
+                    </div>;
+                }"
+            )
+            .to_string(),
+            "BUFFER".to_string(),
+        );
+        let result = runner.run(source);
+        let info = RawTreeInfo::new(&result.tree, &syntax);
+
+        let kinds: HashSet<&str> = info
+            .node_ids()
+            .map(|n| info.kind_name(info.node(n).kind))
+            .collect();
+
+        for kind in [
+            "InterfaceDeclaration",
+            "PropertySignature",
+            "RequiredParameter",
+            "TypeAnnotation",
+            "TypeArguments",
+            "AsExpression",
+            "JsxElement",
+            "JsxOpeningElement",
+            "JsxAttribute",
+            "JsxExpression",
+            "JsxText",
+        ] {
+            assert!(kinds.contains(kind), "missing {kind}");
+        }
+
+        assert!(info
+            .node_ids()
+            .all(|n| info.node(n).kind != ERROR_KIND.into()));
+        assert!(result.reports.is_empty());
+    }
+
     #[test]
     fn yaml_simple() {
         let expected = indoc!(
@@ -325,6 +972,142 @@ mod test {
         );
     }
 
+    #[test]
+    fn language_versions_use_existing_kinds() {
+        for lang in get_builtin_langs() {
+            if get_builtin_spec(lang).is_some() {
+                continue;
+            }
+
+            let (mappings, _, _) = get_builtin_lang(lang);
+            let syntax: Syntax = mappings.types.as_slice().into();
+
+            for kind in mappings.versions.iter().flat_map(|v| &v.unavailable) {
+                assert!(
+                    syntax.kind_id(kind).is_some(),
+                    "{lang}: unknown kind {kind}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn apache_config() {
+        let kinds = parsed_kinds(
+            BuiltinLang::Apache,
+            indoc!(
+                r#"
+                # Main configuration
+                ServerRoot "/etc/httpd"
+                Listen 80
+
+                <VirtualHost *:443>
+                    SSLProtocol -all +TLSv1.2 \
+                        +TLSv1.3
+                    Header always set Strict-Transport-Security "max-age=63072000"
+
+                    <Directory />
+                        Require all denied
+                    </Directory>
+                </VirtualHost>
+                "#
+            ),
+        );
+
+        assert_eq!(kinds["SimpleDirective"], 5);
+        assert_eq!(kinds["Section"], 2);
+        assert_eq!(kinds["QuotedString"], 2);
+    }
+
+    #[test]
+    fn avro_idl_protocol() {
+        let kinds = parsed_kinds(
+            BuiltinLang::AvroIdl,
+            indoc!(
+                r#"
+                @namespace("org.example")
+                protocol Simple {
+                    import schema "common.avsc";
+
+                    /** A card suit. */
+                    enum Suit { SPADES, HEARTS, DIAMONDS, CLUBS } = SPADES;
+
+                    fixed MD5(16);
+
+                    record Card {
+                        Suit suit;
+                        int number = 0;
+                        union { null, string } nickname = null;
+                        array<long> ids = [];
+                        map<string> @order("ignore") attributes;
+                        decimal(9, 2) price;
+                        string? comment;
+                    }
+
+                    error Kaboom {
+                        string explanation;
+                    }
+
+                    void ping() oneway;
+                    string hello(string greeting, int times = 1) throws Kaboom;
+                }
+                "#
+            ),
+        );
+
+        assert_eq!(kinds["Record"], 1);
+        assert_eq!(kinds["ErrorRecord"], 1);
+        assert_eq!(kinds["Field"], 8);
+        assert_eq!(kinds["Message"], 2);
+        assert_eq!(kinds["Annotation"], 2);
+    }
+
+    #[test]
+    fn avro_idl_schema_file() {
+        let kinds = parsed_kinds(
+            BuiltinLang::AvroIdl,
+            indoc!(
+                r#"
+                namespace org.example;
+                schema Event;
+
+                record Event {
+                    timestamp_ms at;
+                    int `error` = -1;
+                }
+                "#
+            ),
+        );
+
+        assert_eq!(kinds["Namespace"], 1);
+        assert_eq!(kinds["MainSchema"], 1);
+        assert_eq!(kinds["Field"], 2);
+    }
+
+    /// Number of nodes of each kind in the tree of the given source, parsed with the spec of a
+    /// builtin language.
+    fn parsed_kinds(lang: BuiltinLang, src: &str) -> HashMap<String, usize> {
+        let spec = get_builtin_spec(lang).unwrap();
+        let runner = ParserRunner::new(DEFAULT_START_RULE, &spec.syntax).unwrap();
+        let res = runner.run_once(Source::inline(src.to_string(), "BUFFER".to_string()));
+        assert!(res.reports.is_empty());
+
+        let tree = &res.tree.tree;
+        tree.top_down_node_ids()
+            .map(|id| {
+                spec.syntax
+                    .kind_name(tree.get_existing_node(id).kind)
+                    .to_string()
+            })
+            .counts()
+    }
+
+    #[test]
+    fn unknown_language_version() {
+        assert!(PYTHON_MAPPING.version("3.12").is_ok());
+        assert!(PYTHON_MAPPING.version("4.0").is_err());
+    }
+
     fn test_builtin_parser(
         language: Language,
         syntax: Syntax,
@@ -339,4 +1122,370 @@ mod test {
 
         assert_eq!(expected, pprint.render());
     }
+
+    #[test]
+    fn terminal_text() {
+        let mappings: Vec<NodeMapping> = serde_yaml::from_str(indoc!(
+            r"
+            - name: Arrow
+              is_list: false
+              is_terminal: true
+              literal: '->'
+              fields: []
+            - name: Number
+              is_list: false
+              is_terminal: true
+              pattern: '[0-9]+'
+              fields: []
+            - name: Dot
+              is_list: false
+              is_terminal: true
+              fields: []
+            "
+        ))
+        .unwrap();
+
+        let syntax = Syntax::from(mappings.as_slice());
+        let regex = |name: &str| {
+            syntax
+                .terminal(name)
+                .unwrap()
+                .reg
+                .regex()
+                .as_str()
+                .to_string()
+        };
+
+        assert_eq!(regex("Arrow"), fancy_regex::escape("->"));
+        assert_eq!(regex("Number"), "[0-9]+");
+        assert_eq!(regex("Dot"), "Dot");
+    }
+
+    #[test]
+    fn invalid_mappings() {
+        let syntax = |yaml: &str| {
+            serde_yaml::from_str::<MappingConfig>(yaml)
+                .unwrap()
+                .syntax()
+                .map(|_| ())
+        };
+        let node = "{ name: Node, ts_name: node, is_list: false, is_terminal: false, fields: [] }";
+
+        assert!(syntax(&format!("types: [{node}]")).is_ok());
+
+        // Invalid terminals.
+        assert!(syntax(
+            "types: [{ name: T, is_list: false, is_terminal: true, pattern: '(', fields: [] }]"
+        )
+        .is_err());
+        assert!(syntax(
+            "types: [{ name: T, is_list: false, is_terminal: true, pattern: a, literal: a, \
+             fields: [] }]"
+        )
+        .is_err());
+
+        // References to unknown nodes and kinds.
+        assert!(syntax(&format!(
+            "{{ types: [{node}], aliases: [{{ ts_name: other, alias: alias }}] }}"
+        ))
+        .is_err());
+        assert!(syntax(&format!(
+            "{{ types: [{node}], fields: [{{ parent_kind: Node, ts_kind: x, new_kind: X }}] }}"
+        ))
+        .is_err());
+        assert!(syntax(&format!(
+            "{{ types: [{node}], versions: [{{ name: '1', unavailable: [X] }}] }}"
+        ))
+        .is_err());
+        assert!(syntax(&format!(
+            "{{ types: [{node}], renamed_kinds: [{{ from: Old, to: X }}] }}"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn builtin_field_types() {
+        let field_type = |lang: BuiltinLang, kind: &str, field: &str| {
+            let syntax: Syntax = get_builtin_lang(lang).0.into();
+            let kind = syntax.kind_id(kind).unwrap();
+            let pos = syntax.field_position(kind, field).unwrap();
+            syntax.field_type(kind, pos).unwrap().to_string()
+        };
+
+        assert_eq!(
+            field_type(BuiltinLang::Javascript, "ClassBody", "member"),
+            "List<FieldDefinition | MethodDefinition>"
+        );
+        assert_eq!(
+            field_type(BuiltinLang::Javascript, "ClassBody", "template"),
+            "List<GlimmerTemplate>"
+        );
+        assert_eq!(
+            field_type(BuiltinLang::Python, "BooleanOperator", "operator"),
+            "And | Or"
+        );
+        assert_eq!(
+            field_type(BuiltinLang::Python, "BooleanOperator", "left"),
+            "Expression"
+        );
+    }
+
+    #[test]
+    fn field_type_composition() {
+        let field = |types: &[&str], list: bool| NodeMappingField {
+            name: "field".to_string(),
+            types: types.iter().map(|t| t.to_string()).collect(),
+            list,
+            mappings: None,
+        };
+
+        let rendered = |f: NodeMappingField| field_type_from_mapping(&f).to_string();
+
+        assert_eq!(rendered(field(&["Expression"], false)), "Expression");
+        assert_eq!(rendered(field(&["Expression"], true)), "List<Expression>");
+        assert_eq!(rendered(field(&["A", "B"], true)), "List<A | B>");
+        assert_eq!(
+            rendered(field(&["List<A | B>", "C", "A"], true)),
+            "List<A | B | C>"
+        );
+    }
+
+    #[test]
+    fn python_node_types() {
+        assert_node_types(&PYTHON_MAPPING, sylver_langs::python_node_types());
+    }
+
+    #[test]
+    fn javascript_node_types() {
+        assert_node_types(&JAVASCRIPT_MAPPING, sylver_langs::javascript_node_types());
+    }
+
+    #[test]
+    fn typescript_node_types() {
+        assert_node_types(&TYPESCRIPT_MAPPING, sylver_langs::typescript_node_types());
+        assert_node_types(&TYPESCRIPT_MAPPING, sylver_langs::tsx_node_types());
+    }
+
+    #[test]
+    fn solidity_node_types() {
+        assert_node_types(&SOLIDITY_MAPPING, sylver_langs::solidity_node_types());
+    }
+
+    #[test]
+    fn erlang_node_types() {
+        assert_node_types(&ERLANG_MAPPING, sylver_langs::erlang_node_types());
+    }
+
+    #[test]
+    fn swift_node_types() {
+        assert_node_types(&SWIFT_MAPPING, sylver_langs::swift_node_types());
+    }
+
+    #[test]
+    fn thrift_node_types() {
+        assert_node_types(&THRIFT_MAPPING, sylver_langs::thrift_node_types());
+    }
+
+    #[test]
+    fn elm_node_types() {
+        assert_node_types(&ELM_MAPPING, sylver_langs::elm_node_types());
+    }
+
+    #[test]
+    fn d_node_types() {
+        assert_node_types(&D_MAPPING, sylver_langs::d_node_types());
+    }
+
+    #[test]
+    fn cue_node_types() {
+        assert_node_types(&CUE_MAPPING, sylver_langs::cue_node_types());
+    }
+
+    #[test]
+    fn node_types_mismatches() {
+        let node_types = r#"[
+            {
+                "type": "binary",
+                "named": true,
+                "fields": {
+                    "left": {
+                        "multiple": false,
+                        "required": true,
+                        "types": [{ "type": "identifier", "named": true }]
+                    },
+                    "operator": {
+                        "multiple": false,
+                        "required": true,
+                        "types": [
+                            { "type": "+", "named": false },
+                            { "type": "-", "named": false }
+                        ]
+                    },
+                    "right": {
+                        "multiple": true,
+                        "required": true,
+                        "types": [{ "type": "number", "named": true }]
+                    }
+                }
+            },
+            { "type": "identifier", "named": true },
+            { "type": "number", "named": true }
+        ]"#;
+
+        let config: MappingConfig = serde_yaml::from_str(indoc! {"
+            types:
+              - { name: Expr, is_list: false, is_terminal: false, fields: [] }
+              - { name: Plus, is_list: false, is_terminal: false, fields: [] }
+              - { name: Minus, is_list: false, is_terminal: false, fields: [] }
+              - name: Identifier
+                ts_name: identifier
+                inherits: Expr
+                is_list: false
+                is_terminal: false
+                fields: []
+              - { name: Number, ts_name: number, is_list: false, is_terminal: false, fields: [] }
+              - name: Binary
+                ts_name: binary
+                is_list: false
+                is_terminal: false
+                fields:
+                  - { name: left, types: [Expr], list: false, mappings: null }
+                  - { name: operator, types: [Plus], list: false, mappings: null }
+                  - { name: right, types: [Expr], list: false, mappings: null }
+                  - { name: middle, types: [Expr], list: false, mappings: null }
+            fields:
+              - { parent_kind: Binary, ts_kind: '+', new_kind: Plus }
+              - { parent_kind: Binary, ts_kind: '-', new_kind: Minus }
+        "})
+        .unwrap();
+
+        assert_eq!(
+            node_types_diff(&config, node_types),
+            vec![
+                "Binary.operator: Minus (-) is not a Plus",
+                "Binary.right: the grammar allows several nodes, but the field is not a list",
+                "Binary.right: Number (number) is not a Expr",
+                "Binary.middle: binary has no such field",
+            ]
+        );
+    }
+
+    /// Node type of a tree-sitter grammar, as described by its `node-types.json` file.
+    #[derive(Deserialize)]
+    struct TsNodeType {
+        #[serde(rename = "type")]
+        kind: String,
+        named: bool,
+        #[serde(default)]
+        fields: BTreeMap<String, TsField>,
+    }
+
+    #[derive(Deserialize)]
+    struct TsField {
+        multiple: bool,
+        types: Vec<TsTypeRef>,
+    }
+
+    #[derive(Deserialize)]
+    struct TsTypeRef {
+        #[serde(rename = "type")]
+        kind: String,
+        named: bool,
+    }
+
+    fn assert_node_types(config: &MappingConfig, node_types: &str) {
+        let diff = node_types_diff(config, node_types);
+        assert!(diff.is_empty(), "{}", diff.join("\n"));
+    }
+
+    /// Differences between the fields of a mapping and the node types of its grammar: each mapped
+    /// field must exist in the grammar, and accept the nodes that the grammar puts in it.
+    fn node_types_diff(config: &MappingConfig, node_types: &str) -> Vec<String> {
+        let node_types: Vec<TsNodeType> = serde_json::from_str(node_types).unwrap();
+
+        let mut names: HashMap<&str, &str> = config
+            .types
+            .iter()
+            .filter_map(|m| Some((m.ts_name.as_deref()?, m.name.as_str())))
+            .collect();
+        for alias in &config.aliases {
+            if let Some(&name) = names.get(alias.ts_name.as_str()) {
+                names.insert(&alias.alias, name);
+            }
+        }
+
+        let mappings: HashMap<&str, &NodeMapping> =
+            config.types.iter().map(|m| (m.name.as_str(), m)).collect();
+
+        let is_a = |kind: &str, alts: &[String]| {
+            let mut current = Some(kind);
+            while let Some(kind) = current {
+                if alts.iter().any(|a| a == kind) {
+                    return true;
+                }
+                current = mappings.get(kind).and_then(|m| m.inherits.as_deref());
+            }
+            false
+        };
+
+        let mut diff = vec![];
+
+        for node_type in node_types.iter().filter(|t| t.named) {
+            let Some(&name) = names.get(node_type.kind.as_str()) else {
+                continue;
+            };
+
+            for field in &mappings[name].fields {
+                let Some(ts_field) = node_type.fields.get(&field.name) else {
+                    diff.push(format!(
+                        "{name}.{}: {} has no such field",
+                        field.name, node_type.kind
+                    ));
+                    continue;
+                };
+
+                let field_type = field_type_from_mapping(field);
+                let (is_list, elems) = match list_elems_type(&field_type) {
+                    Some(elems) => (true, elems),
+                    None => (false, &field_type),
+                };
+                let alts: Vec<String> = match elems {
+                    TypeLit::Simple(simple) => vec![simple.name.clone()],
+                    TypeLit::Or(or) => or.alts.iter().map(|a| a.name.clone()).collect(),
+                };
+
+                if ts_field.multiple && !is_list {
+                    diff.push(format!(
+                        "{name}.{}: the grammar allows several nodes, but the field is not a list",
+                        field.name
+                    ));
+                }
+
+                for child in &ts_field.types {
+                    // Unnamed nodes are only kept in the fields that give them a kind.
+                    let kind = if child.named {
+                        names.get(child.kind.as_str()).copied()
+                    } else {
+                        config
+                            .fields
+                            .iter()
+                            .find(|f| f.parent_kind == name && f.ts_kind == child.kind)
+                            .map(|f| f.new_kind.as_str())
+                    };
+
+                    match kind {
+                        Some(kind) if !is_a(kind, &alts) => diff.push(format!(
+                            "{name}.{}: {kind} ({}) is not a {}",
+                            field.name,
+                            child.kind,
+                            elems.to_string()
+                        )),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        diff
+    }
 }
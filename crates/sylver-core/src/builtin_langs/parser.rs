@@ -1,15 +1,20 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::Duration,
+};
 
-use tree_sitter::Point;
+use tree_sitter::{InputEdit, Point};
 
 use crate::{
-    builtin_langs::MappingConfig,
+    builtin_langs::{LanguageVersion, MappingConfig},
     core::{
         pos::{InclPosRange, Pos},
         source::{Source, SourceTree},
         spec::{FieldPos, KindId, Syntax, TagId, ERROR_KIND},
     },
     parsing::{parser_runner::ParsingResult, scanner::Token},
+    report::Report,
     tree::{NodeId, Tree, TreeBuilder},
 };
 
@@ -24,12 +29,16 @@ pub struct TsMappings {
     /// For every node matching a (parent sylver kind, tree-sitter kind) pair, create a wrapping
     /// node with the given sylver kind.
     pub field_kinds: HashMap<(KindId, u16), KindId>,
+    /// Tree-sitter kinds whose syntax is not available in the selected language version.
+    pub unavailable: HashSet<u16>,
 }
 
 pub struct TsTreeConverter<'t> {
     builder: TreeBuilder<'t>,
     syntax: &'t Syntax,
     mappings: &'t TsMappings,
+    /// Kind and range of the converted nodes whose syntax is unavailable.
+    unavailable_nodes: Vec<(KindId, tree_sitter::Range)>,
 }
 
 impl<'t> TsTreeConverter<'t> {
@@ -38,9 +47,14 @@ impl<'t> TsTreeConverter<'t> {
             builder: TreeBuilder::new(syntax),
             syntax,
             mappings,
+            unavailable_nodes: vec![],
         }
     }
 
+    pub fn unavailable_nodes(&self) -> &[(KindId, tree_sitter::Range)] {
+        &self.unavailable_nodes
+    }
+
     pub fn convert(&mut self, root: tree_sitter::Node) -> anyhow::Result<(Tree, Vec<Token>)> {
         let (root_id, _) = self.convert_from(root)?;
         self.builder.set_root(root_id)?;
@@ -50,6 +64,11 @@ impl<'t> TsTreeConverter<'t> {
     fn convert_from(&mut self, node: tree_sitter::Node) -> anyhow::Result<(NodeId, Vec<usize>)> {
         let kind_id = if node.kind() == TS_ERROR_KIND {
             ERROR_KIND.into()
+        } else if self.mappings.unavailable.contains(&node.kind_id()) {
+            if let Some(&kind) = self.mappings.kinds.get(&node.kind_id()) {
+                self.unavailable_nodes.push((kind, node.range()));
+            }
+            ERROR_KIND.into()
         } else {
             self.mappings
                 .kinds
@@ -135,6 +154,7 @@ pub struct BuiltinParserRunner<'s> {
     syntax: &'s Syntax,
     language: tree_sitter::Language,
     ts_mappings: TsMappings,
+    version: Option<String>,
 }
 
 impl<'s> BuiltinParserRunner<'s> {
@@ -149,9 +169,37 @@ impl<'s> BuiltinParserRunner<'s> {
             syntax,
             language,
             ts_mappings,
+            version: None,
         }
     }
 
+    /// Report the syntax that isn't available in the given version of the language as errors.
+    pub fn with_version(mut self, version: &LanguageVersion) -> anyhow::Result<Self> {
+        let unavailable_kinds: HashSet<KindId> = version
+            .unavailable
+            .iter()
+            .map(|kind| {
+                self.syntax.list_stripped_kind_id(kind).ok_or_else(|| {
+                    anyhow::anyhow!("Unknown kind {kind} in version {}", version.name)
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        self.ts_mappings.unavailable = self
+            .ts_mappings
+            .kinds
+            .iter()
+            .filter(|(_, kind)| unavailable_kinds.contains(kind))
+            .map(|(&ts_kind, _)| ts_kind)
+            .collect();
+
+        self.version = Some(version.name.clone());
+
+        Ok(self)
+    }
+
+    /// The mappings are validated when they are loaded (see `MappingConfig::syntax`), so the
+    /// entries referring to unknown kinds are ignored.
     fn build_ts_mappings(
         language: &tree_sitter::Language,
         syntax: &Syntax,
@@ -161,28 +209,26 @@ impl<'s> BuiltinParserRunner<'s> {
 
         let kind_names = (0..language.node_kind_count() as u16)
             .filter_map(|n| {
-                language.node_kind_for_id(n).and_then(|k| {
-                    ts_name_to_name
-                        .get(k)
-                        .map(|name| (n, syntax.existing_kind_id(name)))
-                })
+                let name = ts_name_to_name.get(language.node_kind_for_id(n)?)?;
+                Some((n, syntax.list_stripped_kind_id(name)?))
             })
             .collect();
 
         let field_kind = mapping_config
             .fields
             .iter()
-            .map(|field| {
-                let parent_kind_id = syntax.existing_kind_id(&field.parent_kind);
+            .filter_map(|field| {
+                let parent_kind_id = syntax.list_stripped_kind_id(&field.parent_kind)?;
                 let ts_kind_id = language.id_for_node_kind(&field.ts_kind, false);
-                let new_kind_id = syntax.existing_kind_id(&field.new_kind);
-                ((parent_kind_id, ts_kind_id), new_kind_id)
+                let new_kind_id = syntax.list_stripped_kind_id(&field.new_kind)?;
+                Some(((parent_kind_id, ts_kind_id), new_kind_id))
             })
             .collect();
 
         TsMappings {
             kinds: kind_names,
             field_kinds: field_kind,
+            unavailable: HashSet::new(),
         }
     }
 
@@ -194,29 +240,296 @@ impl<'s> BuiltinParserRunner<'s> {
             .collect();
 
         for alias in &mapping_config.aliases {
-            ts_name_to_name.insert(
-                &alias.alias,
-                ts_name_to_name.get(alias.ts_name.as_str()).unwrap(),
-            );
+            if let Some(&name) = ts_name_to_name.get(alias.ts_name.as_str()) {
+                ts_name_to_name.insert(&alias.alias, name);
+            }
         }
 
         ts_name_to_name
     }
 
     pub fn run(&self, source: Source) -> ParsingResult {
+        self.run_with_timeout(source, None)
+            .expect("parsing without a timeout always produces a result")
+    }
+
+    /// Parse the source, or return `None` if parsing takes longer than the given timeout.
+    pub fn run_with_timeout(
+        &self,
+        source: Source,
+        timeout: Option<Duration>,
+    ) -> Option<ParsingResult> {
+        let ts_tree = self.parse_ts(source.src(), None, timeout)?;
+        Some(self.convert_ts_tree(source, ts_tree.as_ref()))
+    }
+
+    /// Parse a new version of a source, or return `None` if parsing takes longer than the given
+    /// timeout. The tree-sitter tree of the previous version is reused for the regions of the
+    /// source that were not edited.
+    pub fn run_incremental(
+        &self,
+        source: Source,
+        previous: Option<&IncrementalTree>,
+        timeout: Option<Duration>,
+    ) -> Option<(ParsingResult, Option<IncrementalTree>)> {
+        let old_tree = previous.map(|p| p.edited(source.src()));
+
+        let ts_tree = self.parse_ts(source.src(), old_tree.as_ref(), timeout)?;
+
+        let result = self.convert_ts_tree(source, ts_tree.as_ref());
+
+        let next = ts_tree.map(|tree| IncrementalTree {
+            src: result.tree.source.src().clone(),
+            tree,
+        });
+
+        Some((result, next))
+    }
+
+    /// Run tree-sitter, returning `None` if it timed out.
+    fn parse_ts(
+        &self,
+        src: &str,
+        old_tree: Option<&tree_sitter::Tree>,
+        timeout: Option<Duration>,
+    ) -> Option<Option<tree_sitter::Tree>> {
         let mut ts_parser = tree_sitter::Parser::new();
-        ts_parser
-            .set_language(self.language)
-            .expect("Builtin language should always be valid !");
 
-        let ts_tree = ts_parser.parse(source.src(), None).unwrap();
-        let (tree, tokens) = TsTreeConverter::new(self.syntax, &self.ts_mappings)
-            .convert(ts_tree.root_node())
-            .unwrap();
+        // The ABI of external grammars is checked when they are loaded: a failure here is
+        // reported as a parse error of the file.
+        if ts_parser.set_language(self.language).is_err() {
+            return Some(None);
+        }
+
+        if let Some(timeout) = timeout {
+            ts_parser.set_timeout_micros(timeout.as_micros().max(1) as u64);
+        }
+
+        let ts_tree = ts_parser.parse(src, old_tree);
+
+        if ts_tree.is_none() && timeout.is_some() {
+            return None;
+        }
+
+        Some(ts_tree)
+    }
+
+    fn convert_ts_tree(
+        &self,
+        source: Source,
+        ts_tree: Option<&tree_sitter::Tree>,
+    ) -> ParsingResult {
+        let converted = ts_tree
+            .ok_or_else(|| anyhow::anyhow!("tree-sitter did not produce a tree"))
+            .and_then(|ts_tree| {
+                let mut converter = TsTreeConverter::new(self.syntax, &self.ts_mappings);
+                let (tree, tokens) = converter.convert(ts_tree.root_node())?;
+                let mut reports = syntax_error_reports(source.path(), ts_tree.root_node());
+                reports.extend(self.unavailable_syntax_reports(source.path(), &converter));
+                Ok((tree, tokens, reports))
+            });
+
+        let (tree, tokens, reports) = converted.unwrap_or_else(|e| {
+            let report = Report::parse_error(
+                source.path(),
+                InclPosRange::new(Pos::default(), Pos::default()).unwrap(),
+                format!("Failed to parse file: {e}"),
+            );
+            (Tree::new(), vec![], vec![report])
+        });
 
         ParsingResult {
             tree: SourceTree::new(source, tokens, tree),
-            reports: vec![],
+            reports,
         }
     }
 }
+
+impl<'s> BuiltinParserRunner<'s> {
+    fn unavailable_syntax_reports(&self, path: &Path, converter: &TsTreeConverter) -> Vec<Report> {
+        let version = self.version.as_deref().unwrap_or_default();
+
+        converter
+            .unavailable_nodes()
+            .iter()
+            .map(|&(kind, range)| {
+                Report::parse_error(
+                    path,
+                    range.into(),
+                    format!(
+                        "{} is not available in version {version}",
+                        self.syntax.kind_name(kind)
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+/// tree-sitter tree of a parsed source, kept to parse the next version of the source
+/// incrementally.
+#[derive(Debug, Clone)]
+pub struct IncrementalTree {
+    src: String,
+    tree: tree_sitter::Tree,
+}
+
+impl IncrementalTree {
+    /// Copy of the tree, in which the edit turning the parsed source into `new_src` is recorded.
+    fn edited(&self, new_src: &str) -> tree_sitter::Tree {
+        let mut tree = self.tree.clone();
+        tree.edit(&source_edit(&self.src, new_src));
+        tree
+    }
+}
+
+/// Smallest edit turning `old` into `new`: the text between their common prefix and suffix is
+/// replaced.
+fn source_edit(old: &str, new: &str) -> InputEdit {
+    let prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(o, n)| o == n)
+        .count();
+
+    let suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(old.len().min(new.len()) - prefix)
+        .take_while(|(o, n)| o == n)
+        .count();
+
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte: old.len() - suffix,
+        new_end_byte: new.len() - suffix,
+        start_position: byte_point(old, prefix),
+        old_end_position: byte_point(old, old.len() - suffix),
+        new_end_position: byte_point(new, new.len() - suffix),
+    }
+}
+
+fn byte_point(src: &str, byte_pos: usize) -> Point {
+    let before = &src.as_bytes()[..byte_pos];
+    let row = before.iter().filter(|&&b| b == b'\n').count();
+    let column = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(byte_pos, |newline| byte_pos - newline - 1);
+
+    Point::new(row, column)
+}
+
+/// Return a report for every error or missing node of the given tree-sitter tree.
+fn syntax_error_reports(path: &Path, root: tree_sitter::Node) -> Vec<Report> {
+    let mut reports = vec![];
+    let mut stack = vec![root];
+
+    while let Some(node) = stack.pop() {
+        if node.is_error() {
+            reports.push(Report::parse_error(
+                path,
+                node.range().into(),
+                "Syntax error".to_string(),
+            ));
+        } else if node.is_missing() {
+            reports.push(Report::parse_error(
+                path,
+                node.range().into(),
+                format!("Missing {}", node.kind()),
+            ));
+        } else if node.has_error() {
+            let mut cursor = node.walk();
+            let mut childs: Vec<_> = node.children(&mut cursor).collect();
+            childs.reverse();
+            stack.extend(childs);
+        }
+    }
+
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builtin_langs::{get_builtin_lang, BuiltinLang},
+        report::PARSE_ERROR_CODE,
+    };
+
+    use super::*;
+
+    fn parse_python(code: &str) -> ParsingResult {
+        let (mappings, lang, _) = get_builtin_lang(BuiltinLang::Python);
+        let syntax: Syntax = mappings.types.as_slice().into();
+        let runner = BuiltinParserRunner::new(lang, &syntax, mappings);
+        runner.run(Source::inline(code.to_string(), "BUFFER".to_string()))
+    }
+
+    #[test]
+    fn valid_code_has_no_reports() {
+        assert!(parse_python("x = 1\n").reports.is_empty());
+    }
+
+    #[test]
+    fn unavailable_syntax_is_reported() {
+        let (mappings, lang, _) = get_builtin_lang(BuiltinLang::Python);
+        let syntax: Syntax = mappings.types.as_slice().into();
+        let code = "if (n := 10) > 5:\n    pass\n";
+
+        let runner = BuiltinParserRunner::new(lang, &syntax, mappings);
+        let result = runner.run(Source::inline(code.to_string(), "BUFFER".to_string()));
+        assert!(result.reports.is_empty());
+
+        let runner = BuiltinParserRunner::new(lang, &syntax, mappings)
+            .with_version(mappings.version("3.7").unwrap())
+            .unwrap();
+        let result = runner.run(Source::inline(code.to_string(), "BUFFER".to_string()));
+        assert_eq!(result.reports.len(), 1);
+        assert_eq!(
+            result.reports[0].message,
+            "NamedExpression is not available in version 3.7"
+        );
+    }
+
+    #[test]
+    fn minimal_source_edit() {
+        let edit = source_edit("x = 1\ny = 2\n", "x = 1\ny = 42\n");
+
+        assert_eq!(edit.start_byte, 10);
+        assert_eq!(edit.old_end_byte, 10);
+        assert_eq!(edit.new_end_byte, 11);
+        assert_eq!(edit.start_position, Point::new(1, 4));
+        assert_eq!(edit.new_end_position, Point::new(1, 5));
+    }
+
+    #[test]
+    fn incremental_parsing() {
+        let (mappings, lang, _) = get_builtin_lang(BuiltinLang::Python);
+        let syntax: Syntax = mappings.types.as_slice().into();
+        let runner = BuiltinParserRunner::new(lang, &syntax, mappings);
+        let source = |code: &str| Source::inline(code.to_string(), "BUFFER".to_string());
+
+        let (_, previous) = runner
+            .run_incremental(source("def f():\n    return 1\n"), None, None)
+            .unwrap();
+        let (result, _) = runner
+            .run_incremental(
+                source("def f():\n    return [1, 2]\n"),
+                previous.as_ref(),
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(result, runner.run(source("def f():\n    return [1, 2]\n")));
+    }
+
+    #[test]
+    fn syntax_errors_are_reported() {
+        let result = parse_python("x = 1\ndef f(:\n    pass\ny = 2\n");
+
+        assert!(!result.reports.is_empty());
+        assert!(result.reports.iter().all(|r| r.code == PARSE_ERROR_CODE));
+        assert_eq!(result.reports[0].position.start().line(), 2);
+    }
+}
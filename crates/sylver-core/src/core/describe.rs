@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use sylver_dsl::meta::{list_elems_type, TypeLit};
+
+use crate::core::spec::Syntax;
+
+/// Serializable description of the node kinds of a language spec.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SpecDescription {
+    pub nodes: Vec<NodeDescription>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NodeDescription {
+    pub name: String,
+    pub parent: Option<String>,
+    pub fields: Vec<FieldDescription>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FieldDescription {
+    pub name: String,
+    /// Possible kinds of the field value (or of the list elements, for list fields).
+    pub types: Vec<String>,
+    pub list: bool,
+}
+
+impl From<&Syntax> for SpecDescription {
+    fn from(syntax: &Syntax) -> Self {
+        let nodes = syntax
+            .nodes(false)
+            .map(|n| NodeDescription {
+                name: n.name.clone(),
+                parent: n.parent_type.clone(),
+                fields: n
+                    .fields
+                    .iter()
+                    .map(|(name, lit)| describe_field(name, lit))
+                    .collect(),
+            })
+            .collect();
+
+        SpecDescription { nodes }
+    }
+}
+
+fn describe_field(name: &str, lit: &TypeLit) -> FieldDescription {
+    let (elem_type, list) = match list_elems_type(lit) {
+        Some(elem) => (elem, true),
+        None => (lit, false),
+    };
+
+    let types = match elem_type {
+        TypeLit::Simple(s) => vec![s.to_string()],
+        TypeLit::Or(o) => o.alts.iter().map(|a| a.to_string()).collect(),
+    };
+
+    FieldDescription {
+        name: name.to_string(),
+        types,
+        list,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::core::spec::test::parse_spec;
+
+    use super::*;
+
+    #[test]
+    fn describe_spec() {
+        let spec = parse_spec(indoc!(
+            "
+            node Expr { }
+            node Add: Expr { left: Expr, right: Expr | Lit }
+            node Lit: Expr { }
+            node Block { stmts: List<Expr> }
+            "
+        ));
+
+        let description = SpecDescription::from(&spec.syntax);
+
+        assert_eq!(
+            description.nodes,
+            vec![
+                NodeDescription {
+                    name: "Expr".to_string(),
+                    parent: None,
+                    fields: vec![],
+                },
+                NodeDescription {
+                    name: "Add".to_string(),
+                    parent: Some("Expr".to_string()),
+                    fields: vec![
+                        FieldDescription {
+                            name: "left".to_string(),
+                            types: vec!["Expr".to_string()],
+                            list: false,
+                        },
+                        FieldDescription {
+                            name: "right".to_string(),
+                            types: vec!["Expr".to_string(), "Lit".to_string()],
+                            list: false,
+                        },
+                    ],
+                },
+                NodeDescription {
+                    name: "Lit".to_string(),
+                    parent: Some("Expr".to_string()),
+                    fields: vec![],
+                },
+                NodeDescription {
+                    name: "Block".to_string(),
+                    parent: None,
+                    fields: vec![FieldDescription {
+                        name: "stmts".to_string(),
+                        types: vec!["Expr".to_string()],
+                        list: true,
+                    }],
+                },
+            ]
+        );
+    }
+}
@@ -1,38 +1,90 @@
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 use anyhow::Context;
+use glob::{MatchOptions, Pattern};
 
-use super::source::{source_from_file, Source};
+use super::source::{source_from_file_with_encoding, InputAdapter, Source, SourceEncoding};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct FileSpec {
     pub root: Option<String>,
     pub include: Vec<String>,
     pub exclude: Vec<String>,
+    pub encoding: SourceEncoding,
+    pub adapter: InputAdapter,
+}
+
+impl FileSpec {
+    /// Load the files of the given list that match the spec. The patterns are only matched
+    /// against the listed paths: the filesystem is not traversed.
+    pub fn load_listed(&self, files: &[PathBuf]) -> anyhow::Result<Vec<Source>> {
+        let include = listed_patterns(self.root.as_ref(), &self.include)?;
+        let exclude = listed_patterns(self.root.as_ref(), &self.exclude)?;
+        let cwd = std::env::current_dir().context("Could not read the working directory")?;
+
+        files
+            .iter()
+            .filter(|path| {
+                path.is_file()
+                    && matches_listed(&include, &cwd, path)
+                    && !matches_listed(&exclude, &cwd, path)
+            })
+            .map(|path| source_from_file_with_encoding(path, self.encoding, self.adapter))
+            .collect::<Result<_, _>>()
+            .context("Failed to build source")
+    }
+}
+
+/// Split a NUL-delimited list of paths, such as the output of `git diff --name-only -z`.
+pub fn parse_file_list(list: &[u8]) -> Vec<PathBuf> {
+    list.split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let path = path_from_bytes(path);
+            match path.strip_prefix(".") {
+                Ok(relative) => relative.to_owned(),
+                Err(_) => path,
+            }
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).as_ref())
 }
 
 pub trait FileSpecLoader {
     fn load(&self, spec: &FileSpec) -> anyhow::Result<Vec<Source>>;
 }
 
-#[derive(Default, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
 pub struct FsFileSpecLoader {}
 
 impl FsFileSpecLoader {
     fn sources_from_globs(
         &self,
-        root: Option<&String>,
+        spec: &FileSpec,
         globs: &[String],
     ) -> anyhow::Result<HashSet<Source>> {
         let mut sources = HashSet::new();
         for glob in globs {
-            let glob_in_root = if let Some(r) = root {
-                Path::new(r).join(glob).to_string_lossy().to_string()
-            } else {
-                glob.clone()
-            };
-
-            sources.extend(sources_from_glob(&glob_in_root)?);
+            sources.extend(sources_from_glob(
+                &glob_in_root(spec.root.as_ref(), glob),
+                spec.encoding,
+                spec.adapter,
+            )?);
         }
         Ok(sources)
     }
@@ -41,20 +93,161 @@ impl FsFileSpecLoader {
 impl FileSpecLoader for FsFileSpecLoader {
     fn load(&self, spec: &FileSpec) -> anyhow::Result<Vec<Source>> {
         Ok(self
-            .sources_from_globs(spec.root.as_ref(), &spec.include)?
-            .difference(&self.sources_from_globs(spec.root.as_ref(), &spec.exclude)?)
+            .sources_from_globs(spec, &spec.include)?
+            .difference(&self.sources_from_globs(spec, &spec.exclude)?)
             .cloned()
             .collect())
     }
 }
 
-fn sources_from_glob(pattern: &str) -> anyhow::Result<Vec<Source>> {
-    glob::glob(pattern)
+/// Loader for files held in memory, such as unsaved editor buffers.
+///
+/// The in-memory files are layered on top of the files loaded by the fallback loader (if any): a
+/// file known to both is loaded with its in-memory content. The clones of a loader share its
+/// in-memory files, so that they can be edited while the loader is in use.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFileSpecLoader<F: FileSpecLoader = FsFileSpecLoader> {
+    files: Arc<RwLock<BTreeMap<PathBuf, String>>>,
+    fallback: Option<F>,
+}
+
+impl<F: FileSpecLoader> MemoryFileSpecLoader<F> {
+    pub fn overlay(fallback: F) -> Self {
+        MemoryFileSpecLoader {
+            files: Arc::default(),
+            fallback: Some(fallback),
+        }
+    }
+
+    /// Set the content of the file at `path`, and return its previous in-memory content.
+    pub fn insert(&self, path: impl Into<PathBuf>, content: impl Into<String>) -> Option<String> {
+        self.files
+            .write()
+            .unwrap()
+            .insert(path.into(), content.into())
+    }
+
+    /// Forget the in-memory content of the file at `path`.
+    pub fn remove(&self, path: &Path) -> Option<String> {
+        self.files.write().unwrap().remove(path)
+    }
+}
+
+impl<F: FileSpecLoader> FileSpecLoader for MemoryFileSpecLoader<F> {
+    fn load(&self, spec: &FileSpec) -> anyhow::Result<Vec<Source>> {
+        let files = self.files.read().unwrap();
+
+        // Without in-memory files, the sources are loaded as by the fallback loader.
+        if files.is_empty() {
+            if let Some(fallback) = &self.fallback {
+                return fallback.load(spec);
+            }
+        }
+
+        let include = patterns_from_globs(spec.root.as_ref(), &spec.include)?;
+        let exclude = patterns_from_globs(spec.root.as_ref(), &spec.exclude)?;
+
+        let mut sources: BTreeMap<PathBuf, Source> = match &self.fallback {
+            Some(fallback) => fallback
+                .load(spec)?
+                .into_iter()
+                .map(|s| (s.path().to_owned(), s))
+                .collect(),
+            None => BTreeMap::new(),
+        };
+
+        for (path, content) in files.iter() {
+            if matches_any(&include, path) && !matches_any(&exclude, path) {
+                sources.insert(path.clone(), Source::file(content.clone(), path.clone()));
+            }
+        }
+
+        Ok(sources.into_values().collect())
+    }
+}
+
+fn glob_in_root(root: Option<&String>, glob: &str) -> String {
+    let glob_in_root = if let Some(r) = root {
+        dunce::simplified(Path::new(r))
+            .join(glob)
+            .to_string_lossy()
+            .to_string()
+    } else {
+        glob.to_string()
+    };
+
+    normalize_glob(&glob_in_root)
+}
+
+fn patterns_from_globs(root: Option<&String>, globs: &[String]) -> anyhow::Result<Vec<Pattern>> {
+    globs
+        .iter()
+        .map(|glob| Pattern::new(&glob_in_root(root, glob)).context("Failed to parse glob pattern"))
+        .collect()
+}
+
+fn matches_any(patterns: &[Pattern], path: &Path) -> bool {
+    let path = normalize_glob(&path.to_string_lossy());
+
+    patterns
+        .iter()
+        .any(|p| p.matches_with(&path, match_options()))
+}
+
+/// Patterns matched against listed paths, which never start with `./`.
+fn listed_patterns(root: Option<&String>, globs: &[String]) -> anyhow::Result<Vec<Pattern>> {
+    globs
+        .iter()
+        .map(|glob| {
+            let glob = glob_in_root(root, glob);
+            Pattern::new(glob.trim_start_matches("./")).context("Failed to parse glob pattern")
+        })
+        .collect()
+}
+
+/// Match a listed path both as given and resolved against the working directory (or relative
+/// to it, for an absolute path), so that relative and absolute patterns match either form.
+fn matches_listed(patterns: &[Pattern], cwd: &Path, path: &Path) -> bool {
+    let other = if path.is_absolute() {
+        path.strip_prefix(cwd).ok().map(Path::to_path_buf)
+    } else {
+        Some(cwd.join(path))
+    };
+
+    matches_any(patterns, path) || other.map_or(false, |other| matches_any(patterns, &other))
+}
+
+fn match_options() -> MatchOptions {
+    MatchOptions {
+        // Match the behavior of the default filesystems of these platforms.
+        case_sensitive: !cfg!(any(windows, target_os = "macos")),
+        ..MatchOptions::new()
+    }
+}
+
+/// Use forward slashes as separators, so that the same patterns work on every platform, and strip
+/// the verbatim prefix of Windows paths (which `glob` doesn't support).
+fn normalize_glob(pattern: &str) -> String {
+    let pattern = pattern.replace('\\', "/");
+
+    match pattern.strip_prefix("//?/") {
+        Some(unc) if unc.starts_with("UNC/") => format!("//{}", &unc[4..]),
+        Some(local) => local.to_string(),
+        None => pattern,
+    }
+}
+
+fn sources_from_glob(
+    pattern: &str,
+    encoding: SourceEncoding,
+    adapter: InputAdapter,
+) -> anyhow::Result<Vec<Source>> {
+    glob::glob_with(pattern, match_options())
         .context("Failed to parse glob pattern")?
         .collect::<Result<Vec<_>, _>>()
         .context("Failed to evaluate glob")?
         .iter()
-        .map(|p| source_from_file(p))
+        .map(|p| source_from_file_with_encoding(p, encoding, adapter))
         .collect::<Result<_, _>>()
         .context("Failed to build source")
 }
@@ -67,7 +260,7 @@ mod tests {
     use temp_dir::TempDir;
 
     use super::*;
-    use crate::util::test::create_tmp_child;
+    use crate::{core::source::source_from_file, util::test::create_tmp_child};
 
     #[test]
     fn fs_file_spec_handles_root_directory() {
@@ -82,6 +275,8 @@ mod tests {
             root: Some(d.path().join("root").to_string_lossy().to_string()),
             include: vec!["*.ok".to_string()],
             exclude: vec![],
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
         };
 
         let loaded = FsFileSpecLoader::default().load(&spec).unwrap();
@@ -107,6 +302,8 @@ mod tests {
             root: None,
             include: vec![format!("{}/*.ok", d.path().display())],
             exclude: vec![],
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
         };
 
         let loaded = FsFileSpecLoader::default().load(&spec).unwrap();
@@ -120,6 +317,100 @@ mod tests {
         )
     }
 
+    #[test]
+    fn fs_file_spec_backslash_globs() {
+        let d = TempDir::new().unwrap();
+
+        std::fs::create_dir(d.path().join("root")).unwrap();
+        std::fs::create_dir(d.path().join("root").join("sub")).unwrap();
+        let match1 = create_tmp_child(&d, "root/sub/match1.ok", "content1").unwrap();
+        create_tmp_child(&d, "root/sub/excluded.ok", "content2").unwrap();
+        create_tmp_child(&d, "root/nomatch.ok", "nomatch_content").unwrap();
+
+        let spec = FileSpec {
+            root: Some(d.path().join("root").to_string_lossy().to_string()),
+            include: vec!["sub\\*.ok".to_string()],
+            exclude: vec!["sub\\excluded*".to_string()],
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
+        };
+
+        let loaded = FsFileSpecLoader::default().load(&spec).unwrap();
+
+        assert_eq!(loaded, vec![source_from_file(&match1).unwrap()]);
+    }
+
+    #[test]
+    fn memory_file_spec() {
+        let loader = MemoryFileSpecLoader::<FsFileSpecLoader>::default();
+        loader.insert("src/main.ok", "main");
+        loader.insert("src/excluded.ok", "excluded");
+        loader.insert("src/other.txt", "other");
+
+        let spec = FileSpec {
+            root: Some("src".to_string()),
+            include: vec!["*.ok".to_string()],
+            exclude: vec!["excluded*".to_string()],
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
+        };
+
+        assert_eq!(
+            loader.load(&spec).unwrap(),
+            vec![Source::file("main".to_string(), "src/main.ok".into())]
+        );
+    }
+
+    #[test]
+    fn memory_file_spec_overlay() {
+        let d = TempDir::new().unwrap();
+
+        let on_disk = create_tmp_child(&d, "on_disk.ok", "disk content").unwrap();
+        let overridden = create_tmp_child(&d, "overridden.ok", "disk content").unwrap();
+        let in_memory = d.path().join("in_memory.ok");
+
+        let loader = MemoryFileSpecLoader::overlay(FsFileSpecLoader::default());
+        loader.insert(&overridden, "buffer content");
+        loader.insert(&in_memory, "buffer content");
+
+        let spec = FileSpec {
+            root: None,
+            include: vec![format!("{}/*.ok", d.path().display())],
+            exclude: vec![],
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
+        };
+
+        assert_eq!(
+            loader
+                .load(&spec)
+                .unwrap()
+                .into_iter()
+                .collect::<HashSet<Source>>(),
+            hashset![
+                source_from_file(&on_disk).unwrap(),
+                Source::file("buffer content".to_string(), overridden),
+                Source::file("buffer content".to_string(), in_memory),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalize_globs() {
+        assert_eq!(normalize_glob("src/**/*.rs"), "src/**/*.rs");
+        assert_eq!(normalize_glob("src\\**\\*.rs"), "src/**/*.rs");
+        assert_eq!(normalize_glob("C:\\code\\*.rs"), "C:/code/*.rs");
+        assert_eq!(normalize_glob("\\\\?\\C:\\code\\*.rs"), "C:/code/*.rs");
+        assert_eq!(
+            normalize_glob("\\\\?\\UNC\\server\\share\\*.rs"),
+            "//server/share/*.rs"
+        );
+        assert_eq!(
+            normalize_glob("\\\\server\\share\\*.rs"),
+            "//server/share/*.rs"
+        );
+    }
+
     #[test]
     fn fs_file_spec_with_exclude() {
         let d = TempDir::new().unwrap();
@@ -133,6 +424,8 @@ mod tests {
             root: None,
             include: vec![format!("{}/*.ok", d.path().display())],
             exclude: vec![format!("{}/excluded*", d.path().display())],
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
         };
 
         let loaded = FsFileSpecLoader::default().load(&spec).unwrap();
@@ -145,4 +438,68 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn listed_files() {
+        let d = TempDir::new().unwrap();
+
+        let match1 = create_tmp_child(&d, "match1.ok", "content1").unwrap();
+        let excluded = create_tmp_child(&d, "excluded.ok", "content2").unwrap();
+        let other = create_tmp_child(&d, "nomatch.other", "content3").unwrap();
+        let _unlisted = create_tmp_child(&d, "unlisted.ok", "content4").unwrap();
+        let deleted = d.path().join("deleted.ok");
+
+        let spec = FileSpec {
+            root: None,
+            include: vec![format!("{}/*.ok", d.path().display())],
+            exclude: vec![format!("{}/excluded*", d.path().display())],
+            encoding: SourceEncoding::default(),
+            adapter: InputAdapter::default(),
+        };
+
+        assert_eq!(
+            spec.load_listed(&[match1.clone(), excluded, other, deleted])
+                .unwrap(),
+            vec![source_from_file(&match1).unwrap()]
+        );
+    }
+
+    #[test]
+    fn nul_delimited_file_list() {
+        assert_eq!(
+            parse_file_list(b"src/main.rs\0./src/lib.rs\0with space.rs\0"),
+            vec![
+                PathBuf::from("src/main.rs"),
+                PathBuf::from("src/lib.rs"),
+                PathBuf::from("with space.rs"),
+            ]
+        );
+        assert!(parse_file_list(b"").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_file_list() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let list = parse_file_list(b"src/caf\xe9.py\0");
+
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].as_os_str().as_bytes(), b"src/caf\xe9.py");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listed_paths_relative_to_cwd() {
+        let relative = listed_patterns(Some(&".".to_string()), &["src/*.py".to_string()]).unwrap();
+        let absolute = listed_patterns(None, &["/work/src/*.py".to_string()]).unwrap();
+        let matches = |patterns: &[Pattern], path: &str| {
+            matches_listed(patterns, Path::new("/work"), Path::new(path))
+        };
+
+        assert!(matches(&relative, "/work/src/main.py"));
+        assert!(matches(&relative, "src/main.py"));
+        assert!(matches(&absolute, "src/main.py"));
+        assert!(!matches(&relative, "/elsewhere/src/main.py"));
+    }
 }
@@ -1,4 +1,10 @@
+pub mod describe;
 pub mod files_spec;
+pub mod notebook;
+pub mod overlay;
 pub mod pos;
 pub mod source;
 pub mod spec;
+pub mod spec_lint;
+pub mod store;
+pub mod terraform_plan;
@@ -0,0 +1,180 @@
+use std::borrow::Cow;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// Location of the code cells of a notebook in the source built from it.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NotebookCells {
+    /// Index of the cell in the notebook (starting at 1) and first line of the cell in the source,
+    /// for every non-empty code cell.
+    cells: Vec<(usize, usize)>,
+    /// Reason why the notebook could not be read, in which case it has no cells.
+    error: Option<String>,
+}
+
+impl NotebookCells {
+    /// Cells of a notebook that could not be read.
+    pub fn invalid(error: String) -> NotebookCells {
+        NotebookCells {
+            cells: vec![],
+            error: Some(error),
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Return the cell index (starting at 1) and the line in this cell of the given source line.
+    pub fn cell_position(&self, line: usize) -> Option<(usize, usize)> {
+        let cell_pos = self.cells.partition_point(|&(_, start)| start <= line);
+        let &(cell, start) = self.cells.get(cell_pos.checked_sub(1)?)?;
+        Some((cell, line - start + 1))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawNotebook {
+    cells: Vec<RawCell>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawCell {
+    cell_type: String,
+    source: RawCellSource,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawCellSource {
+    Lines(Vec<String>),
+    Text(String),
+}
+
+impl RawCellSource {
+    fn text(&self) -> Cow<str> {
+        match self {
+            RawCellSource::Lines(lines) => Cow::Owned(lines.concat()),
+            RawCellSource::Text(text) => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// Concatenate the code cells of the given `.ipynb` document into a single Python source.
+pub fn notebook_code(notebook_json: &str) -> anyhow::Result<(String, NotebookCells)> {
+    let notebook: RawNotebook =
+        serde_json::from_str(notebook_json).context("Invalid Jupyter notebook")?;
+
+    let mut code = String::new();
+    let mut cells = vec![];
+    let mut next_line = 1;
+
+    for (index, cell) in notebook.cells.iter().enumerate() {
+        let text = cell.source.text();
+
+        if cell.cell_type != "code" || text.is_empty() {
+            continue;
+        }
+
+        cells.push((index + 1, next_line));
+
+        // A cell magic (`%%bash`, `%%sql`...) makes the whole cell something else than Python.
+        let cell_magic = text.trim_start().starts_with("%%");
+
+        for line in text.lines() {
+            if cell_magic {
+                code.push_str(&comment_out(line));
+            } else {
+                code.push_str(&disable_magic(line));
+            }
+            code.push('\n');
+            next_line += 1;
+        }
+    }
+
+    Ok((code, NotebookCells { cells, error: None }))
+}
+
+/// IPython magics and shell escapes are not valid Python: turn them into comments of the same
+/// length, so that positions in the cell are preserved.
+fn disable_magic(line: &str) -> Cow<str> {
+    let trimmed = line.trim_start();
+
+    if trimmed.starts_with('%') || trimmed.starts_with('!') {
+        let indent = &line[..line.len() - trimmed.len()];
+        Cow::Owned(format!("{indent}#{}", &trimmed[1..]))
+    } else {
+        Cow::Borrowed(line)
+    }
+}
+
+/// Turn a line of a cell that is not Python into a comment, keeping it empty if it is blank.
+fn comment_out(line: &str) -> Cow<str> {
+    if line.trim().is_empty() {
+        Cow::Borrowed(line)
+    } else {
+        Cow::Owned(format!("#{line}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    static NOTEBOOK: &str = indoc! {r##"
+        {
+          "cells": [
+            { "cell_type": "markdown", "metadata": {}, "source": ["# Title"] },
+            {
+              "cell_type": "code",
+              "metadata": {},
+              "outputs": [],
+              "source": ["%matplotlib inline\n", "import os"]
+            },
+            { "cell_type": "code", "metadata": {}, "outputs": [], "source": [] },
+            { "cell_type": "code", "metadata": {}, "outputs": [], "source": "x = 1\ny = 2\n" }
+          ],
+          "metadata": {},
+          "nbformat": 4,
+          "nbformat_minor": 5
+        }
+    "##};
+
+    #[test]
+    fn code_cells_are_concatenated() {
+        let (code, _) = notebook_code(NOTEBOOK).unwrap();
+        assert_eq!(code, "#matplotlib inline\nimport os\nx = 1\ny = 2\n");
+    }
+
+    #[test]
+    fn lines_are_mapped_to_cells() {
+        let (_, cells) = notebook_code(NOTEBOOK).unwrap();
+
+        assert_eq!(cells.cell_position(1), Some((2, 1)));
+        assert_eq!(cells.cell_position(2), Some((2, 2)));
+        assert_eq!(cells.cell_position(3), Some((4, 1)));
+        assert_eq!(cells.cell_position(4), Some((4, 2)));
+        assert_eq!(cells.cell_position(0), None);
+    }
+
+    #[test]
+    fn cell_magics_disable_the_whole_cell() {
+        let notebook = r#"{
+            "cells": [
+                { "cell_type": "code", "source": ["%%bash\n", "ls -la\n", "\n", "echo done"] },
+                { "cell_type": "code", "source": "x = 1" }
+            ]
+        }"#;
+
+        let (code, _) = notebook_code(notebook).unwrap();
+        assert_eq!(code, "#%%bash\n#ls -la\n\n#echo done\nx = 1\n");
+    }
+
+    #[test]
+    fn invalid_notebook() {
+        assert!(notebook_code("{}").is_err());
+    }
+}
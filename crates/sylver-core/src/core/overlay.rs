@@ -0,0 +1,364 @@
+use std::{
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    pos::{InclPosRange, Pos},
+    source::Source,
+};
+
+/// Command transforming the sources before they are parsed, such as a template renderer or a
+/// macro expander.
+///
+/// The source is written to the standard input of the command, whose standard output is parsed
+/// instead. `{file}` in the arguments is replaced with the path of the source. The command may
+/// emit `#line N` or `# N "file"` markers (as `cpp` and `m4 -s` do) to map the lines that follow
+/// to line `N` of the original source. The lines attributed to other files are left out.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preprocessor {
+    /// Program and arguments of the command.
+    pub command: Vec<String>,
+    /// Directory in which the command runs, the current directory if unset.
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+}
+
+impl Preprocessor {
+    /// Run the command on the given source, and return the transformed source.
+    pub fn apply(&self, source: &Source) -> anyhow::Result<Source> {
+        let path = source.path();
+        let output = self
+            .run(path, source.src())
+            .with_context(|| format!("Failed to preprocess {}", path.display()))?;
+        let (code, map) = strip_line_markers(&output, path, source.src());
+
+        Ok(Source::preprocessed(code, path.to_path_buf(), map))
+    }
+
+    fn run(&self, path: &Path, input: &str) -> anyhow::Result<String> {
+        let (program, args) = match self.command.split_first() {
+            Some(command) => command,
+            None => bail!("Empty preprocessor command"),
+        };
+
+        let file = path.to_string_lossy();
+
+        let mut command = Command::new(program);
+
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+
+        let mut child = command
+            .args(args.iter().map(|a| a.replace("{file}", &file)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Could not run {program}"))?;
+
+        // Written from a separate thread, so that a command writing its output before reading
+        // all of its input doesn't block.
+        let mut stdin = child.stdin.take().context("Missing preprocessor stdin")?;
+        let input = input.to_string();
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+        let output = child.wait_with_output()?;
+        let _ = writer.join();
+
+        if !output.status.success() {
+            bail!(
+                "{program} exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+
+        String::from_utf8(output.stdout).context("Invalid UTF-8 preprocessor output")
+    }
+}
+
+/// Lines of the original source that the lines of a preprocessed source come from.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SourceMap {
+    /// Content of the original file.
+    original: Arc<str>,
+    /// Offset of the start of each line of the original file.
+    original_lines: Vec<usize>,
+    /// First line of a region of the preprocessed source and the original line it comes from,
+    /// sorted by preprocessed line.
+    regions: Vec<(usize, usize)>,
+}
+
+impl SourceMap {
+    /// Map lines of the preprocessed source to the same lines of the original source.
+    pub fn identity(original: &str) -> SourceMap {
+        let original_lines = std::iter::once(0)
+            .chain(original.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        SourceMap {
+            original: original.into(),
+            original_lines,
+            regions: vec![(1, 1)],
+        }
+    }
+
+    /// Map each line of the converted source to the given line of the original source.
+    pub fn from_lines(original: &str, lines: impl IntoIterator<Item = usize>) -> SourceMap {
+        let mut map = SourceMap::identity(original);
+        map.regions = lines
+            .into_iter()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+            .collect();
+        map
+    }
+
+    /// Content of the original file.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// Return the line of the original source the given line of the preprocessed source comes
+    /// from.
+    pub fn original_line(&self, line: usize) -> usize {
+        let region = self.regions.partition_point(|&(start, _)| start <= line);
+
+        match region.checked_sub(1).map(|r| self.regions[r]) {
+            Some((start, original)) => original + line - start,
+            None => line,
+        }
+    }
+
+    /// Map a range of the preprocessed source to the original source. The columns are kept for
+    /// the lines left unchanged by the preprocessor, the other lines are covered entirely.
+    pub fn original_range(&self, preprocessed: &str, range: InclPosRange) -> InclPosRange {
+        let start = self.original_pos(preprocessed, range.start(), false);
+        let end = self.original_pos(preprocessed, range.end(), true);
+
+        InclPosRange::new(start, std::cmp::max(start, end)).expect("ordered positions")
+    }
+
+    /// Code of the original source for a range of the preprocessed source.
+    pub fn original_code(&self, preprocessed: &str, range: InclPosRange) -> &str {
+        let range = self.original_range(preprocessed, range);
+        &self.original[range.start().txt_pos..range.end().txt_pos]
+    }
+
+    fn original_pos(&self, preprocessed: &str, pos: Pos, is_end: bool) -> Pos {
+        let line = self.original_line(pos.line);
+        let original_line = self.line_span(line);
+
+        let txt_pos = pos.txt_pos.min(preprocessed.len());
+        let line_start = preprocessed[..txt_pos].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = preprocessed[txt_pos..]
+            .find('\n')
+            .map_or(preprocessed.len(), |i| txt_pos + i);
+
+        let unchanged = preprocessed[line_start..line_end] == self.original[original_line.clone()];
+
+        let original_pos = if unchanged {
+            original_line.start + txt_pos - line_start
+        } else if is_end {
+            original_line.end
+        } else {
+            original_line.start
+        };
+
+        // The columns are computed again from the offset when the position is encoded.
+        Pos::new((line, pos.col), original_pos)
+    }
+
+    /// Span of the given line of the original source, without its line break. Lines after the
+    /// end of the source are empty.
+    fn line_span(&self, line: usize) -> Range<usize> {
+        let start = match line.checked_sub(1).and_then(|l| self.original_lines.get(l)) {
+            Some(&start) => start,
+            None => return self.original.len()..self.original.len(),
+        };
+
+        let end = self.original[start..]
+            .find('\n')
+            .map_or(self.original.len(), |i| start + i);
+
+        start..end
+    }
+}
+
+/// Blank the line markers of a preprocessor output (keeping the lines of the code in place), and
+/// build the map of the marked lines. The lines that the markers attribute to another file than
+/// the preprocessed one (such as included headers) are blanked as well.
+pub fn strip_line_markers(output: &str, path: &Path, original: &str) -> (String, SourceMap) {
+    let mut code = String::with_capacity(output.len());
+    let mut map = SourceMap::identity(original);
+    let mut foreign = false;
+
+    for (index, line) in output.split_inclusive('\n').enumerate() {
+        match line_marker(line) {
+            Some((original, file)) => {
+                map.regions.push((index + 2, original));
+
+                if let Some(file) = file {
+                    foreign = !is_preprocessed_file(file, path);
+                }
+
+                if line.ends_with('\n') {
+                    code.push('\n');
+                }
+            }
+            None if foreign => {
+                if line.ends_with('\n') {
+                    code.push('\n');
+                }
+            }
+            None => code.push_str(line),
+        }
+    }
+
+    (code, map)
+}
+
+/// Original line of the line following a `#line N` or `# N "file"` marker, with the file name
+/// if the marker has one.
+fn line_marker(line: &str) -> Option<(usize, Option<&str>)> {
+    let directive = line.trim_start().strip_prefix('#')?;
+    let directive = directive
+        .trim_start()
+        .strip_prefix("line")
+        .unwrap_or(directive);
+
+    // `#line` must be followed by a space, `#lines` isn't a marker.
+    if !directive.starts_with(|c: char| c.is_ascii_whitespace()) {
+        return None;
+    }
+
+    let directive = directive.trim_start();
+    let (original, rest) = directive
+        .split_once(|c: char| c.is_ascii_whitespace())
+        .unwrap_or((directive, ""));
+    let original = original.parse().ok()?;
+    let rest = rest.trim();
+
+    if rest.is_empty() {
+        return Some((original, None));
+    }
+
+    let file = rest.strip_prefix('"')?;
+    let file = &file[..file.find('"')?];
+
+    Some((original, Some(file)))
+}
+
+/// Whether a file named by a line marker is the preprocessed file, which the command reads from
+/// its standard input.
+fn is_preprocessed_file(file: &str, path: &Path) -> bool {
+    file == "<stdin>" || file == "-" || path.ends_with(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn markers_are_mapped() {
+        let output = indoc! {r#"
+            # 1 "template.py"
+            import os
+            #line 10
+            x = 1
+            # comment
+            y = 2
+        "#};
+
+        let (code, map) = strip_line_markers(output, Path::new("src/template.py"), "");
+
+        assert_eq!(code, "\nimport os\n\nx = 1\n# comment\ny = 2\n");
+        assert_eq!(map.original_line(2), 1);
+        assert_eq!(map.original_line(4), 10);
+        assert_eq!(map.original_line(6), 12);
+    }
+
+    #[test]
+    fn unmarked_output() {
+        let (code, map) = strip_line_markers("a\nb", Path::new("a.py"), "a\nb");
+
+        assert_eq!(code, "a\nb");
+        assert_eq!(map, SourceMap::identity("a\nb"));
+        assert_eq!(map.original_line(2), 2);
+    }
+
+    #[test]
+    fn foreign_regions_are_dropped() {
+        let output = indoc! {r#"
+            # 1 "<stdin>"
+            # 1 "header.h" 1
+            int helper();
+            # 2 "<stdin>" 2
+            int main();
+        "#};
+
+        let (code, map) = strip_line_markers(output, Path::new("main.c"), "");
+
+        assert_eq!(code, "\n\n\n\nint main();\n");
+        assert_eq!(map.original_line(5), 2);
+    }
+
+    #[test]
+    fn ranges_are_mapped() {
+        let original = "name = '{{ name }}'\nprint(name)\n";
+        let preprocessed = "#line 1\nname = 'world'\nprint(name)\n";
+        let (code, map) = strip_line_markers(preprocessed, Path::new("a.py"), original);
+
+        let range = |start: usize, end: usize| {
+            let pos = |txt_pos: usize| {
+                let line = code[..txt_pos].matches('\n').count() + 1;
+                Pos::new((line, 1), txt_pos)
+            };
+            InclPosRange::new(pos(start), pos(end)).unwrap()
+        };
+
+        // Unchanged line: the columns are kept.
+        let print = code.find("print").unwrap();
+        assert_eq!(map.original_code(&code, range(print, print + 5)), "print");
+        assert_eq!(
+            map.original_range(&code, range(print, print + 5))
+                .start()
+                .line,
+            2
+        );
+
+        // Rewritten line: the whole original line is covered.
+        let world = code.find("world").unwrap();
+        assert_eq!(
+            map.original_code(&code, range(world, world + 5)),
+            "name = '{{ name }}'"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_command() {
+        let preprocessor = Preprocessor {
+            command: vec!["sed".to_string(), "s/{{ name }}/world/".to_string()],
+            dir: None,
+        };
+        let source = Source::file("hello = '{{ name }}'\n".to_string(), "hello.py".into());
+
+        let preprocessed = preprocessor.apply(&source).unwrap();
+
+        assert_eq!(preprocessed.src(), "hello = 'world'\n");
+        assert_eq!(preprocessed.path(), Path::new("hello.py"));
+        assert!(preprocessed.source_map().is_some());
+    }
+}
@@ -112,3 +112,103 @@ impl Display for Pos {
         write!(f, "{}:{}", self.line, self.col)
     }
 }
+
+/// Unit used to count the columns of a position.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum ColumnEncoding {
+    /// UTF-8 bytes.
+    #[default]
+    Utf8,
+    /// UTF-16 code units (used by most editors).
+    Utf16,
+    /// Unicode scalar values.
+    Char,
+}
+
+/// Recomputes the columns of positions according to a `ColumnEncoding`, optionally expanding
+/// tabs to the next multiple of `tab_width`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct PosEncoder {
+    pub encoding: ColumnEncoding,
+    pub tab_width: Option<usize>,
+}
+
+impl PosEncoder {
+    pub fn new(encoding: ColumnEncoding, tab_width: Option<usize>) -> PosEncoder {
+        PosEncoder {
+            encoding,
+            tab_width,
+        }
+    }
+
+    /// Return `pos` with its column computed from its text position in `src`.
+    pub fn encode(&self, src: &str, pos: Pos) -> Pos {
+        pos.set_col(self.column(src, pos.txt_pos))
+    }
+
+    /// Column (starting at 1) of the byte at `txt_pos` in `src`.
+    pub fn column(&self, src: &str, txt_pos: usize) -> usize {
+        let prefix = src.get(..txt_pos.min(src.len())).unwrap_or_default();
+        let line_prefix = &prefix[prefix.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+
+        self.width(line_prefix) + 1
+    }
+
+    pub fn encode_range(&self, src: &str, range: InclPosRange) -> InclPosRange {
+        InclPosRange {
+            start: self.encode(src, range.start),
+            end: self.encode(src, range.end),
+        }
+    }
+
+    fn width(&self, text: &str) -> usize {
+        text.chars().fold(0, |col, c| match (c, self.tab_width) {
+            ('\t', Some(width)) if width > 0 => col + width - col % width,
+            _ => col + self.char_width(c),
+        })
+    }
+
+    fn char_width(&self, c: char) -> usize {
+        match self.encoding {
+            ColumnEncoding::Utf8 => c.len_utf8(),
+            ColumnEncoding::Utf16 => c.len_utf16(),
+            ColumnEncoding::Char => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SRC: &str = "a\n\u{e9}\u{1F600}x";
+
+    fn x_pos() -> Pos {
+        Pos::new((2, 0), SRC.find('x').unwrap())
+    }
+
+    #[test]
+    fn encode_utf8() {
+        let encoder = PosEncoder::new(ColumnEncoding::Utf8, None);
+        assert_eq!(encoder.encode(SRC, x_pos()).col, 7);
+    }
+
+    #[test]
+    fn encode_utf16() {
+        let encoder = PosEncoder::new(ColumnEncoding::Utf16, None);
+        assert_eq!(encoder.encode(SRC, x_pos()).col, 4);
+    }
+
+    #[test]
+    fn encode_char() {
+        let encoder = PosEncoder::new(ColumnEncoding::Char, None);
+        assert_eq!(encoder.encode(SRC, x_pos()).col, 3);
+    }
+
+    #[test]
+    fn encode_tabs() {
+        let src = "\tab\tc";
+        let encoder = PosEncoder::new(ColumnEncoding::Char, Some(4));
+        assert_eq!(encoder.encode(src, Pos::new((1, 0), 4)).col, 9);
+    }
+}
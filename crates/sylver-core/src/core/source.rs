@@ -1,13 +1,22 @@
 use std::{
-    fs::read_to_string,
-    ops::Index,
+    io::Read,
+    ops::{Index, Range},
     path::{Path, PathBuf},
 };
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    core::{pos::Pos, spec::TagId},
+    core::{
+        notebook::{notebook_code, NotebookCells},
+        overlay::SourceMap,
+        pos::Pos,
+        spec::TagId,
+        terraform_plan::{is_terraform_plan, plan_document},
+    },
     parsing::{scanner::Token, sppf::TokenPos},
     tree::{Node, NodeId, Tree},
 };
@@ -15,13 +24,19 @@ use crate::{
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum SourceOrigin {
     File(PathBuf),
+    /// Code cells of a Jupyter notebook.
+    Notebook(PathBuf, NotebookCells),
+    /// Output of the preprocessor of a file.
+    Preprocessed(PathBuf, SourceMap),
     Inline(String),
 }
 
 impl SourceOrigin {
     pub fn path(&self) -> &Path {
         match self {
-            SourceOrigin::File(path) => path,
+            SourceOrigin::File(path)
+            | SourceOrigin::Notebook(path, _)
+            | SourceOrigin::Preprocessed(path, _) => path,
             SourceOrigin::Inline(id) => id.as_ref(),
         }
     }
@@ -42,6 +57,22 @@ impl Source {
         }
     }
 
+    /// Create a new `Source` from the code cells of a notebook.
+    pub fn notebook(src: String, path: PathBuf, cells: NotebookCells) -> Source {
+        Source {
+            src,
+            origin: SourceOrigin::Notebook(path, cells),
+        }
+    }
+
+    /// Create a new `Source` from the preprocessed content of a file.
+    pub fn preprocessed(src: String, path: PathBuf, map: SourceMap) -> Source {
+        Source {
+            src,
+            origin: SourceOrigin::Preprocessed(path, map),
+        }
+    }
+
     /// Create a new `Source` from inline code.
     pub fn inline(src: String, id: String) -> Source {
         Source {
@@ -58,18 +89,147 @@ impl Source {
         &self.src
     }
 
+    /// Return the cells of the notebook this source was extracted from, if any.
+    pub fn notebook_cells(&self) -> Option<&NotebookCells> {
+        match &self.origin {
+            SourceOrigin::Notebook(_, cells) => Some(cells),
+            _ => None,
+        }
+    }
+
+    /// Return the map to the lines of the original file, for preprocessed sources.
+    pub fn source_map(&self) -> Option<&SourceMap> {
+        match &self.origin {
+            SourceOrigin::Preprocessed(_, map) => Some(map),
+            _ => None,
+        }
+    }
+
     pub fn code_between(&self, start: Pos, end: Pos) -> &str {
         &self.src[start.txt_pos()..end.txt_pos()]
     }
 }
 
+/// Encoding of the source files. Files are decoded to UTF-8 when they are loaded, so the spans
+/// and the reported positions are computed on the decoded text.
+#[derive(
+    Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceEncoding {
+    /// UTF-16 if the file starts with a UTF-16 byte order mark, UTF-8 otherwise.
+    #[default]
+    Auto,
+    Utf8,
+    /// ISO-8859-1, decoded as its windows-1252 superset.
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+    ShiftJis,
+}
+
+impl SourceEncoding {
+    pub fn is_auto(&self) -> bool {
+        self == &SourceEncoding::Auto
+    }
+
+    /// Decode the content of a file, without its byte order mark.
+    pub fn decode(self, bytes: &[u8]) -> anyhow::Result<String> {
+        let encoding: &'static Encoding = match self {
+            SourceEncoding::Auto => Encoding::for_bom(bytes).map_or(UTF_8, |(e, _)| e),
+            SourceEncoding::Utf8 => UTF_8,
+            SourceEncoding::Latin1 => WINDOWS_1252,
+            SourceEncoding::Utf16Le => UTF_16LE,
+            SourceEncoding::Utf16Be => UTF_16BE,
+            SourceEncoding::ShiftJis => SHIFT_JIS,
+        };
+
+        let (decoded, had_errors) = encoding.decode_with_bom_removal(bytes);
+
+        if had_errors {
+            bail!("Invalid {} content", encoding.name());
+        }
+
+        Ok(decoded.into_owned())
+    }
+}
+
+/// Conversion of the files that hold something else than code into a document of the project
+/// language.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum InputAdapter {
+    /// Files are read as they are.
+    #[default]
+    None,
+    /// Terraform plans (`*.tfplan.json`) are read as a YAML document listing their resources.
+    TerraformPlan,
+    /// Jupyter notebooks (`*.ipynb`) are read as the Python code of their code cells.
+    Notebook,
+}
+
+/// Maximum size of the decompressed content of a `.gz` source, above which the file is rejected
+/// rather than read in memory.
+const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
 pub fn source_from_file(f: &Path) -> anyhow::Result<Source> {
-    let source_str =
-        read_to_string(f).with_context(|| format!("Can not read source file: {}", f.display()))?;
+    source_from_file_with_encoding(f, SourceEncoding::Auto, InputAdapter::None)
+}
+
+pub fn source_from_file_with_encoding(
+    f: &Path,
+    encoding: SourceEncoding,
+    adapter: InputAdapter,
+) -> anyhow::Result<Source> {
+    let bytes =
+        std::fs::read(f).with_context(|| format!("Can not read source file: {}", f.display()))?;
+
+    let is_gz = f.extension().map_or(false, |ext| ext == "gz");
+
+    // Compressed files are read as their decompressed content, the name without the `.gz`
+    // extension selecting the input adapter.
+    let (content, adapter_path) = if is_gz {
+        let content = gunzip(&bytes, MAX_DECOMPRESSED_SIZE)
+            .with_context(|| format!("Can not decompress source file: {}", f.display()))?;
+        (content, f.with_extension(""))
+    } else {
+        (bytes, f.to_path_buf())
+    };
+
+    let source_str = encoding
+        .decode(&content)
+        .with_context(|| format!("Can not read source file: {}", f.display()))?;
+
+    if adapter == InputAdapter::Notebook
+        && adapter_path.extension().map_or(false, |ext| ext == "ipynb")
+    {
+        // Malformed notebooks are skipped, with a warning, when the sources are parsed.
+        let (code, cells) = notebook_code(&source_str)
+            .unwrap_or_else(|e| (String::new(), NotebookCells::invalid(format!("{e:#}"))));
+        return Ok(Source::notebook(code, f.to_path_buf(), cells));
+    }
+
+    if adapter == InputAdapter::TerraformPlan && is_terraform_plan(&adapter_path) {
+        let (document, map) = plan_document(&source_str)
+            .with_context(|| format!("Can not read Terraform plan: {}", f.display()))?;
+        return Ok(Source::preprocessed(document, f.to_path_buf(), map));
+    }
 
     Ok(Source::file(source_str, f.to_path_buf()))
 }
 
+fn gunzip(bytes: &[u8], max_size: u64) -> anyhow::Result<Vec<u8>> {
+    let mut content = vec![];
+    MultiGzDecoder::new(bytes)
+        .take(max_size + 1)
+        .read_to_end(&mut content)?;
+
+    if content.len() as u64 > max_size {
+        bail!("The decompressed content exceeds {max_size} bytes");
+    }
+
+    Ok(content)
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct SourceTree {
     pub source: Source,
@@ -103,7 +263,21 @@ impl SourceTree {
     /// # panics
     /// If there is no node with the given id.
     pub fn node_text(&self, node_id: NodeId, ignore: &[TagId]) -> &str {
-        self.tokens_code(self.node_tokens(node_id, ignore))
+        &self.source.src()[self.node_text_range(node_id, ignore)]
+    }
+
+    /// Return the byte range of the node's text in the source.
+    /// # panics
+    /// If there is no node with the given id.
+    pub fn node_text_range(&self, node_id: NodeId, ignore: &[TagId]) -> Range<usize> {
+        let tokens = self.node_tokens(node_id, ignore);
+
+        tokens
+            .first()
+            .zip(tokens.last())
+            .map_or(0..0, |(first, last)| {
+                first.pos.start().txt_pos()..last.pos.end().txt_pos()
+            })
     }
 
     /// Return the source code for the given tree node, including ignore tokens.
@@ -173,6 +347,11 @@ impl Index<TokenPos> for SourceTree {
 
 #[cfg(test)]
 pub mod test {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use temp_dir::TempDir;
+
     use crate::{
         core::{pos::InclPosRange, spec::test::test_syntax},
         tree::TreeBuilder,
@@ -180,6 +359,89 @@ pub mod test {
 
     use super::*;
 
+    fn gzip(content: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gzip_source() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("app.conf.gz");
+        std::fs::write(&path, gzip("port: 8080\n")).unwrap();
+
+        let source = source_from_file(&path).unwrap();
+
+        assert_eq!(source.src(), "port: 8080\n");
+        assert_eq!(source.path(), path);
+    }
+
+    #[test]
+    fn gzip_source_without_extension() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("app.log");
+        std::fs::write(&path, gzip("started\n")).unwrap();
+
+        // Only `.gz` files are decompressed.
+        assert!(source_from_file(&path).map_or(true, |s| s.src() != "started\n"));
+    }
+
+    #[test]
+    fn gzip_size_limit() {
+        let compressed = gzip(&"a".repeat(100));
+
+        assert_eq!(gunzip(&compressed, 100).unwrap().len(), 100);
+        assert!(gunzip(&compressed, 99).is_err());
+    }
+
+    #[test]
+    fn plans_are_converted_for_yaml_projects() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("prod.tfplan.json");
+        let plan = r#"{ "values": { "root_module": {} } }"#;
+        std::fs::write(&path, plan).unwrap();
+
+        let converted = source_from_file_with_encoding(
+            &path,
+            SourceEncoding::Auto,
+            InputAdapter::TerraformPlan,
+        )
+        .unwrap();
+        assert_eq!(converted.src(), "resources: []\n");
+        assert_eq!(converted.source_map().unwrap().original(), plan);
+
+        let raw = source_from_file(&path).unwrap();
+        assert_eq!(raw.src(), plan);
+        assert!(raw.source_map().is_none());
+    }
+
+    #[test]
+    fn decode_sources() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.child("legacy.txt");
+
+        std::fs::write(&path, b"caf\xe9\n").unwrap();
+        assert!(source_from_file(&path).is_err());
+        assert_eq!(
+            source_from_file_with_encoding(&path, SourceEncoding::Latin1, InputAdapter::None)
+                .unwrap()
+                .src(),
+            "café\n"
+        );
+
+        std::fs::write(&path, b"\xff\xfeh\x00i\x00").unwrap();
+        assert_eq!(source_from_file(&path).unwrap().src(), "hi");
+
+        std::fs::write(&path, b"\x82\xa0\n").unwrap();
+        assert_eq!(
+            source_from_file_with_encoding(&path, SourceEncoding::ShiftJis, InputAdapter::None)
+                .unwrap()
+                .src(),
+            "あ\n"
+        );
+    }
+
     pub fn create_test_source_tree(code: &str) -> SourceTree {
         let lines = code.lines().count();
         let last_line_len = code.lines().last().map(|l| l.len()).unwrap_or_default();
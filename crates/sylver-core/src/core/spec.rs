@@ -41,6 +41,10 @@ pub enum SpecErr {
     MultipleDecl(String),
     #[error("Unknown symbol: {0}")]
     MissingDecl(String),
+    #[error("Unknown field {1} in the format of {0}")]
+    MissingFormatField(String, String),
+    #[error("Unknown field {1} in the binding of {0}")]
+    MissingBindingField(String, String),
 }
 
 pub type SpecRes<T> = Result<T, SpecErr>;
@@ -124,24 +128,41 @@ pub fn spec_from_files(
     aspects_file: Option<&PathBuf>,
     spec_file: &Path,
 ) -> anyhow::Result<Spec> {
-    let spec_str = read_to_string(spec_file)
+    spec_from_reader(
+        engine,
+        aspects_file.map(PathBuf::as_path),
+        spec_file,
+        &mut |path: &Path| read_to_string(path),
+    )
+}
+
+/// Build a spec like `spec_from_files`, reading the spec, its imports and the aspects with `read`.
+pub fn spec_from_reader(
+    engine: &PythonScriptEngine,
+    aspects_file: Option<&Path>,
+    spec_file: &Path,
+    read: &mut dyn FnMut(&Path) -> std::io::Result<String>,
+) -> anyhow::Result<Spec> {
+    let spec_str = read(spec_file)
         .with_context(|| format!("Could not read spec file: {}", spec_file.display()))?;
 
-    let syntax = SyntaxBuilder::new().build(parse(&spec_str)?)?;
+    let decls = parse_with_imports_using(&spec_str, Some(spec_file), read)?;
+    let syntax = SyntaxBuilder::new().build(decls)?;
 
-    let aspects = Aspects::build(&syntax, raw_aspect_from_file(engine, aspects_file)?)?;
+    let aspects = Aspects::build(&syntax, raw_aspect_from_file(engine, aspects_file, read)?)?;
 
     Ok(Spec::new(aspects, syntax))
 }
 
 fn raw_aspect_from_file(
     engine: &PythonScriptEngine,
-    aspects: Option<&PathBuf>,
+    aspects: Option<&Path>,
+    read: &mut dyn FnMut(&Path) -> std::io::Result<String>,
 ) -> anyhow::Result<HashMap<String, HashMap<String, PythonScript>>> {
     Ok(aspects
         .map(|f| {
-            let aspects_script = read_to_string(f)
-                .with_context(|| format!("Could not read aspects file: {}", f.display()))?;
+            let aspects_script =
+                read(f).with_context(|| format!("Could not read aspects file: {}", f.display()))?;
 
             engine
                 .compile_aspects(&aspects_script, f.display().to_string())
@@ -218,14 +239,65 @@ pub struct Syntax {
     trivial_tags: Vec<TagId>,
     comment_tags_count: usize,
     declarations: StrIdMap<SyntaxDecl>,
+    formats: HashMap<KindId, FormatDecl>,
+    bindings: HashMap<KindId, Vec<Binding>>,
+    /// New name of the kinds that were renamed, by former name.
+    renamed_kinds: HashMap<String, String>,
 }
 
 impl Syntax {
+    /// Accept the given former names of renamed kinds (`(former name, new name)` pairs).
+    pub fn with_renamed_kinds(
+        mut self,
+        renames: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        self.renamed_kinds.extend(renames);
+        self
+    }
+
+    /// Return the current name of a renamed kind, given its former name. Successive renames are
+    /// followed until a declared kind is reached.
+    pub fn renamed_kind(&self, former_name: &str) -> Option<&str> {
+        let mut name = self.renamed_kinds.get(former_name)?;
+
+        // Bounded by the number of renames, so that a cycle of renames terminates.
+        for _ in 0..self.renamed_kinds.len() {
+            if self.kind_id(name).is_some() {
+                break;
+            }
+
+            match self.renamed_kinds.get(name) {
+                Some(next) => name = next,
+                None => break,
+            }
+        }
+
+        Some(name)
+    }
+
     /// Return the ignore tags.
     pub fn ignore_tags(&self) -> &[TagId] {
         &self.trivial_tags[self.comment_tags_count..]
     }
 
+    /// Return the format template of the nodes of the given kind, if the spec declares one.
+    pub fn format(&self, kind: KindId) -> Option<&FormatDecl> {
+        self.formats.get(&kind)
+    }
+
+    /// Return the name bindings of the nodes of the given kind.
+    pub fn bindings(&self, kind: KindId) -> &[Binding] {
+        self.bindings
+            .get(&kind)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Return whether the spec declares name bindings, from which the scope graph is built.
+    pub fn has_bindings(&self) -> bool {
+        !self.bindings.is_empty()
+    }
+
     /// Return the commend tags.
     pub fn comment_tags(&self) -> &[TagId] {
         &self.trivial_tags[0..self.comment_tags_count]
@@ -277,9 +349,14 @@ impl Syntax {
         }
     }
 
-    // Same as `kind_id`, but panics if the requested kind does not exist.
-    pub fn existing_kind_id(&self, name: impl AsRef<str>) -> KindId {
+    /// Same as `kind_id`, but a list kind (`List<Kind>`) gives the kind of its elements.
+    pub fn list_stripped_kind_id(&self, name: impl AsRef<str>) -> Option<KindId> {
         self.kind_id(strip_list_kind(name.as_ref()))
+    }
+
+    // Same as `list_stripped_kind_id`, but panics if the requested kind does not exist.
+    pub fn existing_kind_id(&self, name: impl AsRef<str>) -> KindId {
+        self.list_stripped_kind_id(name.as_ref())
             .unwrap_or_else(|| panic!("Invalid kind name: {}", name.as_ref()))
     }
 
@@ -461,6 +538,9 @@ pub struct SyntaxBuilder {
     declarations: StrIdMap<SyntaxDecl>,
     ignore_tags: Vec<TagId>,
     comment_tags: Vec<TagId>,
+    formats: Vec<FormatDecl>,
+    bindings: Vec<BindingDecl>,
+    aliases: Vec<AliasDecl>,
 }
 
 impl SyntaxBuilder {
@@ -489,6 +569,9 @@ impl SyntaxBuilder {
             declarations,
             ignore_tags: vec![],
             comment_tags: vec![],
+            formats: vec![],
+            bindings: vec![],
+            aliases: vec![],
         }
     }
 
@@ -512,10 +595,54 @@ impl SyntaxBuilder {
             .copied()
             .collect();
 
+        let formats: HashMap<KindId, FormatDecl> = self
+            .formats
+            .into_iter()
+            .filter_map(|f| Some((self.declarations.get_id(&f.node_type)?.into(), f)))
+            .collect();
+
+        let mut declared_bindings: HashMap<String, Vec<Binding>> = HashMap::new();
+
+        for b in self.bindings {
+            declared_bindings
+                .entry(b.node_type)
+                .or_default()
+                .push(b.binding);
+        }
+
+        // The nodes of a kind also have the bindings declared for its parent kinds.
+        let mut bindings: HashMap<KindId, Vec<Binding>> = HashMap::new();
+
+        for (id, decl) in self.declarations.iter() {
+            let mut node = retain_node(decl, true);
+
+            while let Some(n) = node {
+                if let Some(declared) = declared_bindings.get(&n.name) {
+                    bindings
+                        .entry(id.into())
+                        .or_default()
+                        .extend(declared.iter().cloned());
+                }
+
+                node = n
+                    .parent_type
+                    .as_ref()
+                    .and_then(|parent| self.declarations.get_key(parent))
+                    .and_then(|parent| retain_node(parent, true));
+            }
+        }
+
         Ok(Syntax {
             trivial_tags,
             declarations: self.declarations,
             comment_tags_count: self.comment_tags.len(),
+            formats,
+            bindings,
+            renamed_kinds: self
+                .aliases
+                .into_iter()
+                .map(|a| (a.name, a.node_type))
+                .collect(),
         })
     }
 
@@ -535,6 +662,37 @@ impl SyntaxBuilder {
     }
 
     fn insert_decl(&mut self, decl: Decl) -> SpecRes<()> {
+        if let Decl::Format(f) = decl {
+            if self
+                .formats
+                .iter()
+                .any(|other| other.node_type == f.node_type)
+            {
+                return Err(SpecErr::MultipleDecl(format!("format {}", f.node_type)));
+            }
+
+            self.formats.push(f);
+            return Ok(());
+        }
+
+        if let Decl::Binding(b) = decl {
+            if self.bindings.contains(&b) {
+                return Err(SpecErr::MultipleDecl(format!("binding {}", b.node_type)));
+            }
+
+            self.bindings.push(b);
+            return Ok(());
+        }
+
+        if let Decl::Alias(a) = decl {
+            if self.aliases.iter().any(|other| other.name == a.name) {
+                return Err(SpecErr::MultipleDecl(format!("alias {}", a.name)));
+            }
+
+            self.aliases.push(a);
+            return Ok(());
+        }
+
         if let Decl::Rule(r) = &decl {
             self.add_inline_regs(r)?;
         }
@@ -570,9 +728,62 @@ impl SyntaxBuilder {
             }
         }
 
+        for format in &self.formats {
+            self.validate_format(format)?;
+        }
+
+        for binding in &self.bindings {
+            self.validate_binding(binding)?;
+        }
+
+        for alias in &self.aliases {
+            if self.declarations.get_key(&alias.name).is_some() {
+                return Err(SpecErr::MultipleDecl(alias.name.clone()));
+            }
+        }
+
         Ok(())
     }
 
+    fn validate_format(&self, format: &FormatDecl) -> SpecRes<()> {
+        self.assert_node_exists(&format.node_type)?;
+
+        let fields = match self.declarations.get_key(&format.node_type) {
+            Some(SyntaxDecl::Syntactic(Decl::Node(n))) => &n.fields,
+            _ => return Err(SpecErr::MissingDecl(format.node_type.clone())),
+        };
+
+        for part in &format.parts {
+            if let FormatPart::Field(field) = part {
+                if !fields.contains_key(&field.name) {
+                    return Err(SpecErr::MissingFormatField(
+                        format.node_type.clone(),
+                        field.name.clone(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_binding(&self, binding: &BindingDecl) -> SpecRes<()> {
+        self.assert_node_exists(&binding.node_type)?;
+
+        let field = match &binding.binding {
+            Binding::Declaration(Some(f)) | Binding::Reference(Some(f)) => f,
+            _ => return Ok(()),
+        };
+
+        match self.declarations.get_key(&binding.node_type) {
+            Some(SyntaxDecl::Syntactic(Decl::Node(n))) if n.fields.contains_key(field) => Ok(()),
+            _ => Err(SpecErr::MissingBindingField(
+                binding.node_type.clone(),
+                field.clone(),
+            )),
+        }
+    }
+
     fn validate_alternative(&self, alternative: &RuleExpr) -> SpecRes<()> {
         match alternative {
             RuleExpr::Node(n) => self.validate_node_alternative(n),
@@ -1094,6 +1305,135 @@ pub mod test {
         assert_eq!(Err(SpecErr::MissingDecl("HelloNode".into())), res);
     }
 
+    #[test]
+    fn format_decls() {
+        let spec_str = indoc!(
+            r#"
+            node Num { }
+            node Pair { left: Num, right: Num }
+
+            format Pair = "({left}, {right})"
+        "#
+        );
+
+        let syntax = SyntaxBuilder::new().build(parse_decls(spec_str)).unwrap();
+
+        assert!(syntax.format(syntax.kind_id("Pair").unwrap()).is_some());
+        assert!(syntax.format(syntax.kind_id("Num").unwrap()).is_none());
+    }
+
+    #[test]
+    fn format_missing_field() {
+        let spec_str = indoc!(
+            r#"
+            node Num { }
+            node Pair { left: Num, right: Num }
+
+            format Pair = "({left}, {middle})"
+        "#
+        );
+
+        let res = SyntaxBuilder::new().build(parse_decls(spec_str));
+
+        assert_eq!(
+            Err(SpecErr::MissingFormatField("Pair".into(), "middle".into())),
+            res
+        );
+    }
+
+    #[test]
+    fn binding_decls() {
+        let spec_str = indoc!(
+            "
+            node Ident { }
+            node Function { name: Ident, body: Block }
+            node Block { }
+
+            scope Function
+            declare Function.name
+            reference Ident
+        "
+        );
+
+        let syntax = SyntaxBuilder::new().build(parse_decls(spec_str)).unwrap();
+
+        assert!(syntax.has_bindings());
+        assert_eq!(
+            syntax.bindings(syntax.kind_id("Function").unwrap()),
+            &[Binding::Scope, Binding::Declaration(Some("name".into()))]
+        );
+        assert!(syntax.bindings(syntax.kind_id("Block").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn binding_subkinds() {
+        let spec_str = indoc!(
+            "
+            node Expr { }
+            node Ident: Expr { }
+            node QualifiedIdent: Ident { }
+
+            reference Ident
+        "
+        );
+
+        let syntax = SyntaxBuilder::new().build(parse_decls(spec_str)).unwrap();
+
+        assert!(syntax.bindings(syntax.kind_id("Expr").unwrap()).is_empty());
+        assert_eq!(
+            syntax.bindings(syntax.kind_id("QualifiedIdent").unwrap()),
+            &[Binding::Reference(None)]
+        );
+    }
+
+    #[test]
+    fn alias_decls() {
+        let spec_str = indoc!(
+            "
+            node Function { }
+
+            alias FunctionDef = Function
+            alias FunctionDecl = FunctionDef
+        "
+        );
+
+        let syntax = SyntaxBuilder::new().build(parse_decls(spec_str)).unwrap();
+
+        assert_eq!(syntax.renamed_kind("FunctionDecl"), Some("Function"));
+        assert_eq!(syntax.renamed_kind("Function"), None);
+
+        let res = SyntaxBuilder::new().build(parse_decls(indoc!(
+            "
+            node Function { }
+
+            alias Function = Function
+        "
+        )));
+
+        assert_eq!(Err(SpecErr::MultipleDecl("Function".into())), res);
+    }
+
+    #[test]
+    fn binding_missing_field() {
+        let spec_str = indoc!(
+            "
+            node Function { }
+
+            declare Function.name
+        "
+        );
+
+        let res = SyntaxBuilder::new().build(parse_decls(spec_str));
+
+        assert_eq!(
+            Err(SpecErr::MissingBindingField(
+                "Function".into(),
+                "name".into()
+            )),
+            res
+        );
+    }
+
     #[test]
     fn missing_term_decl() {
         let spec_str = indoc!(
@@ -0,0 +1,512 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use itertools::Itertools;
+use sylver_dsl::meta::{
+    parse_with_imports, AltLevelTermExpr, AlternativeComp, CompExpr, Decl, RuleExpr, TermContent,
+    TermDecl, TermExpr,
+};
+
+use crate::{
+    core::spec::{Syntax, SyntaxBuilder},
+    parsing::roundtrip::{alternative_height, min_heights},
+};
+
+/// Problem found in a language spec.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct SpecLint {
+    /// Line of the declaration (starting at 1), if it could be located.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Display for SpecLint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Look for the declarations of the spec that are unused or that can never take effect when
+/// parsing from `start_rule`. `path` is the file the spec was read from, needed to resolve its
+/// relative imports.
+pub fn lint_spec(
+    spec: &str,
+    path: Option<&Path>,
+    start_rule: &str,
+) -> anyhow::Result<Vec<SpecLint>> {
+    let decls = parse_with_imports(spec, path)?;
+    let syntax = SyntaxBuilder::new().build(decls.clone())?;
+
+    let linter = SpecLinter {
+        decls: &decls,
+        syntax: &syntax,
+        lines: decl_lines(spec),
+    };
+
+    let mut lints = vec![];
+    linter.unbuilt_nodes(&mut lints);
+    linter.unused_terminals(&mut lints);
+    linter.unreachable_rules(start_rule, &mut lints);
+    linter.endless_alternatives(&mut lints);
+    linter.overlapping_terminals(&mut lints);
+    linter.unset_fields(&mut lints);
+
+    lints.sort();
+
+    Ok(lints)
+}
+
+struct SpecLinter<'s> {
+    decls: &'s [Decl],
+    syntax: &'s Syntax,
+    lines: HashMap<&'s str, usize>,
+}
+
+impl<'s> SpecLinter<'s> {
+    fn lint(&self, name: &str, message: String) -> SpecLint {
+        SpecLint {
+            line: self.lines.get(name).copied(),
+            message,
+        }
+    }
+
+    fn node_alternatives(&self) -> impl Iterator<Item = (&str, &[AlternativeComp])> {
+        self.syntax
+            .rules()
+            .flat_map(|r| &r.alternatives)
+            .filter_map(RuleExpr::as_node)
+            .map(|n| (n.node_type(), n.comps.as_slice()))
+    }
+
+    fn unbuilt_nodes(&self, lints: &mut Vec<SpecLint>) {
+        let parents: HashMap<&str, &str> = self
+            .decls
+            .iter()
+            .filter_map(|d| match d {
+                Decl::Node(n) => Some((n.name.as_str(), n.parent_type.as_deref()?)),
+                _ => None,
+            })
+            .collect();
+
+        // A node type is used if it is built by a rule, or if it is the parent of such a type.
+        let mut used = HashSet::new();
+        for (node_type, _) in self.node_alternatives() {
+            let mut current = Some(node_type);
+            while let Some(node_type) = current {
+                if !used.insert(node_type) {
+                    break;
+                }
+                current = parents.get(node_type).copied();
+            }
+        }
+
+        for decl in self.decls {
+            if let Decl::Node(n) = decl {
+                if !used.contains(n.name.as_str()) {
+                    lints.push(self.lint(&n.name, format!("Node {} is never built", n.name)));
+                }
+            }
+        }
+    }
+
+    fn unused_terminals(&self, lints: &mut Vec<SpecLint>) {
+        let referenced: HashSet<&str> = self
+            .syntax
+            .rules()
+            .flat_map(|r| &r.alternatives)
+            .flat_map(alternative_refs)
+            .collect();
+
+        for decl in self.decls {
+            if let Decl::Terminal(t) = decl {
+                if t.data.is_none() && !referenced.contains(t.name.as_str()) {
+                    lints.push(self.lint(&t.name, format!("Terminal {} is never used", t.name)));
+                }
+            }
+        }
+    }
+
+    fn unreachable_rules(&self, start_rule: &str, lints: &mut Vec<SpecLint>) {
+        if self.syntax.get_rule_id(start_rule).is_none() {
+            lints.push(self.lint(start_rule, format!("Missing start rule: {start_rule}")));
+            return;
+        }
+
+        let mut reached = HashSet::from([start_rule]);
+        let mut queue = VecDeque::from([start_rule]);
+
+        while let Some(name) = queue.pop_front() {
+            if let Some(Decl::Rule(rule)) = self.syntax.decl_from_name(name) {
+                for reference in rule.alternatives.iter().flat_map(alternative_refs) {
+                    if reached.insert(reference) {
+                        queue.push_back(reference);
+                    }
+                }
+            }
+        }
+
+        for decl in self.decls {
+            if let Decl::Rule(r) = decl {
+                if !reached.contains(r.name.as_str()) {
+                    lints.push(self.lint(
+                        &r.name,
+                        format!("Rule {} is unreachable from {start_rule}", r.name),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Alternatives that can only be parsed from infinite inputs, because all their derivations
+    /// refer back to the alternative.
+    fn endless_alternatives(&self, lints: &mut Vec<SpecLint>) {
+        let heights = min_heights(self.syntax);
+
+        for decl in self.decls {
+            let rule = match decl {
+                Decl::Rule(r) => r,
+                _ => continue,
+            };
+
+            // The alternatives of the built spec, in which the quantified rules are expanded.
+            let alternatives = match self.syntax.decl_from_name(&rule.name) {
+                Some(Decl::Rule(r)) => &r.alternatives,
+                _ => continue,
+            };
+
+            for (index, alternative) in alternatives.iter().enumerate() {
+                if alternative_height(self.syntax, &heights, alternative).is_none() {
+                    lints.push(self.lint(
+                        &rule.name,
+                        format!(
+                            "Alternative {} of rule {} can never be completed",
+                            index + 1,
+                            rule.name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn overlapping_terminals(&self, lints: &mut Vec<SpecLint>) {
+        let mut by_pattern: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+        for (_, term) in self.syntax.terminals() {
+            by_pattern
+                .entry(term.reg.as_str())
+                .or_default()
+                .push(&term.name);
+        }
+
+        for names in by_pattern.values().filter(|names| names.len() > 1) {
+            lints.push(self.lint(
+                names[0],
+                format!(
+                    "Terminals {} match the same text",
+                    names.iter().map(|n| format!("`{n}`")).join(", ")
+                ),
+            ));
+        }
+
+        // Literals entirely matched by the regex of another declared terminal.
+        let declared: Vec<&TermDecl> = self
+            .decls
+            .iter()
+            .filter_map(|d| match d {
+                Decl::Terminal(t) if t.data.is_none() => Some(t),
+                _ => None,
+            })
+            .collect();
+
+        for literal in &declared {
+            let TermContent::Literal(regex) = &literal.reg else {
+                continue;
+            };
+            let text = unescape_literal(regex.as_str());
+
+            for term in &declared {
+                let TermContent::Regex(regex) = &term.reg else {
+                    continue;
+                };
+
+                let anchored = fancy_regex::Regex::new(&format!("^(?:{})$", regex.as_str()));
+
+                if anchored.map_or(false, |r| r.is_match(&text).unwrap_or(false)) {
+                    lints.push(self.lint(
+                        &literal.name,
+                        format!(
+                            "Terminal `{}` also matches the text of `{}`",
+                            term.name, literal.name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn unset_fields(&self, lints: &mut Vec<SpecLint>) {
+        let mut built: HashMap<&str, HashSet<&str>> = HashMap::new();
+
+        for (node_type, comps) in self.node_alternatives() {
+            built.entry(node_type).or_default().extend(
+                comps
+                    .iter()
+                    .filter_map(AlternativeComp::as_full)
+                    .map(|(field, _)| field.as_str()),
+            );
+        }
+
+        for decl in self.decls {
+            let node = match decl {
+                Decl::Node(n) => n,
+                _ => continue,
+            };
+
+            // Nodes that are never built are already reported.
+            let set_fields = match built.get(node.name.as_str()) {
+                Some(fields) => fields,
+                None => continue,
+            };
+
+            for field in node.fields.keys() {
+                if !set_fields.contains(field.as_str()) {
+                    lints.push(self.lint(
+                        &node.name,
+                        format!("Field {field} of node {} is never set", node.name),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Names of the rules and terminals referenced by an alternative.
+fn alternative_refs(alternative: &RuleExpr) -> Vec<&str> {
+    match alternative {
+        RuleExpr::Ref(r) => vec![r.rule_name()],
+        RuleExpr::Node(n) => n.comps.iter().flat_map(comp_refs).collect(),
+    }
+}
+
+fn comp_refs(comp: &AlternativeComp) -> Vec<&str> {
+    match comp {
+        AlternativeComp::RuleRef(name) => vec![name.as_str()],
+        AlternativeComp::TExpr(term) => term_refs(term),
+        AlternativeComp::Full(_, expr) => match expr {
+            CompExpr::Ref(name)
+            | CompExpr::Many(name)
+            | CompExpr::Some(name)
+            | CompExpr::Opt(name) => {
+                vec![name.as_str()]
+            }
+            CompExpr::SepBy(data) => {
+                let mut refs = term_refs(&data.term);
+                refs.push(&data.rule_name);
+                refs
+            }
+        },
+    }
+}
+
+fn term_refs(term: &TermExpr) -> Vec<&str> {
+    match term {
+        TermExpr::Ref(name) => vec![name.as_str()],
+        TermExpr::Content(_) => vec![],
+        TermExpr::Alts(alts) => alts
+            .iter()
+            .filter_map(|alt| match alt {
+                AltLevelTermExpr::Ref(name) => Some(name.as_str()),
+                AltLevelTermExpr::Content(_) => None,
+            })
+            .collect(),
+    }
+}
+
+/// Text matched by the regex of a literal terminal, in which the special characters are escaped.
+fn unescape_literal(regex: &str) -> String {
+    let mut text = String::new();
+    let mut chars = regex.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            c => text.push(c),
+        }
+    }
+
+    text
+}
+
+/// Line of the declaration of each node, terminal and rule of the spec. A declaration starts
+/// with its keyword directly followed by its name, which can't be confused with the fields and
+/// rule references sharing the keyword's name. Strings, literals and regexes are skipped.
+fn decl_lines(spec: &str) -> HashMap<&str, usize> {
+    let mut lines = HashMap::new();
+    let mut tokens = spec_tokens(spec).peekable();
+
+    while let Some((token, _)) = tokens.next() {
+        if !matches!(token, "node" | "term" | "rule") {
+            continue;
+        }
+
+        if let Some(&(name, line)) = tokens.peek() {
+            if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                lines.entry(name).or_insert(line);
+            }
+        }
+    }
+
+    lines
+}
+
+/// Identifiers and punctuation of a spec, with their line (starting at 1).
+fn spec_tokens(spec: &str) -> impl Iterator<Item = (&str, usize)> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = spec.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            // Literals have no escapes, regexes only escape their delimiter.
+            '\'' | '"' | '`' => {
+                while let Some((_, next)) = chars.next() {
+                    let before_delimiter = chars.peek().map(|&(_, n)| n) == Some(c);
+
+                    match next {
+                        '\\' if c == '"' || (c == '`' && before_delimiter) => {
+                            chars.next();
+                        }
+                        '\n' => line += 1,
+                        next if next == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut end = start + c.len_utf8();
+
+                while let Some(&(index, next)) = chars.peek() {
+                    if !(next.is_ascii_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    end = index + next.len_utf8();
+                    chars.next();
+                }
+
+                tokens.push((&spec[start..end], line));
+            }
+            c if c.is_whitespace() => {}
+            c => tokens.push((&spec[start..start + c.len_utf8()], line)),
+        }
+    }
+
+    tokens.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn lint_declarations() {
+        let spec = indoc!(
+            r#"
+            node Num { }
+            node Unused { }
+            node Pair { left: Num, right: Num }
+
+            ignore term WHITESPACE = `\s`
+            term COMMA = ','
+            term SEMICOLON = ';'
+            term COMA = ','
+
+            rule main = Pair { left@num_rule COMMA }
+            rule num_rule = Num { `[0-9]+` }
+            rule orphan = Num { `[a-z]+` }
+            rule endless = Pair { left@endless }
+            "#
+        );
+
+        let lints: Vec<String> = lint_spec(spec, None, "main")
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            lints,
+            vec![
+                "2: Node Unused is never built",
+                "3: Field right of node Pair is never set",
+                "6: Terminals `COMMA`, `COMA` match the same text",
+                "7: Terminal SEMICOLON is never used",
+                "8: Terminal COMA is never used",
+                "12: Rule orphan is unreachable from main",
+                "13: Alternative 1 of rule endless can never be completed",
+                "13: Rule endless is unreachable from main",
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_overlapping_literal() {
+        let spec = indoc!(
+            "
+            node Ident { }
+            node Keyword { }
+
+            term IF = 'if'
+            term IDENT = `[a-z]+`
+            term NUMBER = `[0-9]+`
+
+            rule main = Ident { IDENT } | Keyword { IF } | Ident { NUMBER }
+            "
+        );
+
+        let lints: Vec<String> = lint_spec(spec, None, "main")
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            lints,
+            vec!["4: Terminal `IDENT` also matches the text of `IF`"]
+        );
+    }
+
+    #[test]
+    fn declaration_lines() {
+        let spec = "node A { rule: B } node B { }\nterm X = 'node C'\nrule main =\n  A { rule@b }";
+
+        assert_eq!(
+            decl_lines(spec),
+            HashMap::from([("A", 1), ("B", 1), ("X", 2), ("main", 3)])
+        );
+    }
+
+    #[test]
+    fn lint_with_imports() {
+        let path = Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../sylver-dsl/test_res/imports/main.syl"
+        ));
+        let spec = std::fs::read_to_string(path).unwrap();
+
+        assert!(lint_spec(&spec, Some(path), "main").is_ok());
+        assert!(lint_spec(&spec, None, "main")
+            .unwrap_err()
+            .to_string()
+            .starts_with("1:1: Relative import outside of a spec file"));
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    builtin_langs::parser::IncrementalTree, core::source::Source,
+    parsing::parser_runner::ParsingResult,
+};
+
+/// blake3 hash of a content, hex encoded.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ContentKey(String);
+
+impl ContentKey {
+    pub fn of(content: &[u8]) -> ContentKey {
+        ContentKey(blake3::hash(content).to_hex().to_string())
+    }
+
+    /// Key of a set of named contents, such as the files of a project.
+    pub fn of_set(contents: &BTreeMap<&Path, ContentKey>) -> ContentKey {
+        let mut hasher = blake3::Hasher::new();
+
+        for (path, key) in contents {
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update(&[0]);
+            hasher.update(key.0.as_bytes());
+        }
+
+        ContentKey(hasher.finalize().to_hex().to_string())
+    }
+}
+
+impl Display for ContentKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Parse trees keyed by the content of their source and by the parser that produced them, kept by
+/// the long-lived commands (watch mode, daemon, comparison of revisions) so that a content is only
+/// parsed once.
+///
+/// Only parsers that can't change during the life of the store should use it: their key is the
+/// only thing that tells two parsers apart, so it must cover everything that changes the trees
+/// (language, version, parse limits).
+///
+/// The store only shares the trees: the source texts are still read for every file, and the rule
+/// cache keeps its own keys (see `RuleCache`).
+///
+/// The tree-sitter trees of the builtin parsers are kept too, so that the next version of an
+/// edited file is parsed incrementally from the tree of its previous content.
+#[derive(Debug, Default)]
+pub struct ContentStore {
+    trees: RwLock<FxHashMap<(ContentKey, u64), StoredTree>>,
+    /// Last content parsed for each file.
+    files: RwLock<FxHashMap<(PathBuf, u64), ContentKey>>,
+    /// Keys looked up since the last call to `retain_used`.
+    used: RwLock<FxHashSet<(ContentKey, u64)>>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredTree {
+    result: ParsingResult,
+    incremental: Option<IncrementalTree>,
+}
+
+impl ContentStore {
+    /// Return the tree of the source, parsing it with `parse` if the store has no tree for its
+    /// content. Results are only stored when the parsing succeeds.
+    pub fn parse<E>(
+        &self,
+        parser: u64,
+        source: Source,
+        parse: impl FnOnce(Source) -> Result<ParsingResult, E>,
+    ) -> Result<ParsingResult, E> {
+        self.parse_incremental(parser, source, |s, _| parse(s).map(|res| (res, None)))
+    }
+
+    /// Same as `parse`, `parse` being given the incremental tree of the previous content of the
+    /// file, if any, and returning the one of the new content.
+    pub fn parse_incremental<E>(
+        &self,
+        parser: u64,
+        source: Source,
+        parse: impl FnOnce(
+            Source,
+            Option<&IncrementalTree>,
+        ) -> Result<(ParsingResult, Option<IncrementalTree>), E>,
+    ) -> Result<ParsingResult, E> {
+        let key = (ContentKey::of(source.src().as_bytes()), parser);
+        self.used
+            .write()
+            .expect("poisoned store lock")
+            .insert(key.clone());
+
+        let file = (source.path().to_owned(), parser);
+        let previous_content = self
+            .files
+            .write()
+            .expect("poisoned store lock")
+            .insert(file, key.0.clone());
+
+        let (stored, previous) = {
+            let trees = self.trees.read().expect("poisoned store lock");
+            let previous = previous_content
+                .and_then(|content| trees.get(&(content, parser)))
+                .and_then(|stored| stored.incremental.clone());
+            (
+                trees.get(&key).map(|stored| stored.result.clone()),
+                previous,
+            )
+        };
+
+        match stored {
+            Some(mut res) => {
+                // The same content can come from another file.
+                for report in &mut res.reports {
+                    report.file_path = source.path().to_owned();
+                }
+                res.tree.source = source;
+                Ok(res)
+            }
+            None => {
+                let (result, incremental) = parse(source, previous.as_ref())?;
+                let stored = StoredTree {
+                    result: result.clone(),
+                    incremental,
+                };
+                self.trees
+                    .write()
+                    .expect("poisoned store lock")
+                    .insert(key, stored);
+                Ok(result)
+            }
+        }
+    }
+
+    /// Drop the trees that were not looked up since the previous call, so that the trees of
+    /// edited files don't accumulate.
+    pub fn retain_used(&self) {
+        let mut used = self.used.write().expect("poisoned store lock");
+
+        self.trees
+            .write()
+            .expect("poisoned store lock")
+            .retain(|key, _| used.contains(key));
+
+        self.files
+            .write()
+            .expect("poisoned store lock")
+            .retain(|(_, parser), content| used.contains(&(content.clone(), *parser)));
+
+        used.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.trees.read().expect("poisoned store lock").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, convert::Infallible};
+
+    use crate::{
+        builtin_langs::{get_builtin_lang, parser::BuiltinParserRunner, BuiltinLang},
+        core::{source::test::create_test_source_tree, spec::Syntax},
+    };
+
+    use super::*;
+
+    fn parse_counting(
+        count: &Cell<usize>,
+    ) -> impl '_ + FnOnce(Source) -> Result<ParsingResult, Infallible> {
+        |source| {
+            count.set(count.get() + 1);
+            let mut tree = create_test_source_tree(source.src());
+            tree.source = source;
+            Ok(ParsingResult {
+                tree,
+                reports: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn content_keys() {
+        assert_eq!(ContentKey::of(b"x = 1"), ContentKey::of(b"x = 1"));
+        assert_ne!(ContentKey::of(b"x = 1"), ContentKey::of(b"x = 2"));
+        assert_eq!(ContentKey::of(b"").to_string().len(), 64);
+
+        let key = ContentKey::of(b"x = 1");
+        let set = |path: &'static str| {
+            ContentKey::of_set(&BTreeMap::from([(Path::new(path), key.clone())]))
+        };
+        assert_ne!(set("a.py"), set("b.py"));
+    }
+
+    #[test]
+    fn same_content_is_parsed_once() {
+        let store = ContentStore::default();
+        let count = Cell::new(0);
+
+        let first = store
+            .parse(
+                0,
+                Source::inline("var x = 1;".into(), "a".into()),
+                parse_counting(&count),
+            )
+            .unwrap();
+        let second = store
+            .parse(
+                0,
+                Source::inline("var x = 1;".into(), "b".into()),
+                parse_counting(&count),
+            )
+            .unwrap();
+
+        assert_eq!(count.get(), 1);
+        assert_eq!(first.tree.tree, second.tree.tree);
+        assert_eq!(second.tree.source.path(), Path::new("b"));
+
+        // Another parser parses the content again.
+        store
+            .parse(
+                1,
+                Source::inline("var x = 1;".into(), "a".into()),
+                parse_counting(&count),
+            )
+            .unwrap();
+        assert_eq!(count.get(), 2);
+    }
+
+    #[test]
+    fn unused_trees_are_dropped() {
+        let store = ContentStore::default();
+        let count = Cell::new(0);
+        let source = |code: &str| Source::inline(code.to_string(), "a".into());
+
+        store
+            .parse(0, source("var x = 1;"), parse_counting(&count))
+            .unwrap();
+        store.retain_used();
+        store
+            .parse(0, source("var x = 2;"), parse_counting(&count))
+            .unwrap();
+        store.retain_used();
+
+        assert_eq!(store.len(), 1);
+        store
+            .parse(0, source("var x = 1;"), parse_counting(&count))
+            .unwrap();
+        assert_eq!(count.get(), 3);
+    }
+
+    #[test]
+    fn edited_file_is_parsed_from_its_previous_tree() {
+        let (mappings, lang, _) = get_builtin_lang(BuiltinLang::Python);
+        let syntax: Syntax = mappings.types.as_slice().into();
+        let runner = BuiltinParserRunner::new(lang, &syntax, mappings);
+        let store = ContentStore::default();
+        let previous_trees = Cell::new(0);
+
+        let parse = |code: &str, path: &str| {
+            store
+                .parse_incremental(
+                    0,
+                    Source::inline(code.into(), path.into()),
+                    |s, previous| {
+                        previous_trees.set(previous_trees.get() + previous.is_some() as usize);
+                        Ok::<_, Infallible>(runner.run_incremental(s, previous, None).unwrap())
+                    },
+                )
+                .unwrap()
+        };
+
+        parse("x = 1\n", "a.py");
+        parse("y = 1\n", "b.py");
+        assert_eq!(previous_trees.get(), 0);
+
+        let result = parse("x = [1, 2]\n", "a.py");
+        assert_eq!(previous_trees.get(), 1);
+        assert_eq!(
+            result,
+            runner.run(Source::inline("x = [1, 2]\n".into(), "a.py".into()))
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::core::overlay::SourceMap;
+
+/// Suffix of the files written with `terraform show -json <plan> > <name>.tfplan.json`.
+pub const PLAN_SUFFIX: &str = ".tfplan.json";
+
+pub fn is_terraform_plan(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| name.ends_with(PLAN_SUFFIX))
+}
+
+#[derive(Debug, Deserialize)]
+struct RawPlan {
+    /// Present in plans.
+    planned_values: Option<RawValues>,
+    /// Present in states (`terraform show -json` without a plan file).
+    values: Option<RawValues>,
+    #[serde(default)]
+    resource_changes: Vec<RawResourceChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawValues {
+    root_module: RawModule,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawModule {
+    #[serde(default)]
+    resources: Vec<RawResource>,
+    #[serde(default)]
+    child_modules: Vec<RawModule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawResource {
+    address: String,
+    mode: String,
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+    provider_name: Option<String>,
+    #[serde(default)]
+    values: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawResourceChange {
+    address: String,
+    change: RawChange,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawChange {
+    actions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PlannedResource<'p> {
+    address: &'p str,
+    mode: &'p str,
+    #[serde(rename = "type")]
+    kind: &'p str,
+    name: &'p str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<&'p str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    actions: &'p [String],
+    values: &'p serde_json::Value,
+}
+
+/// Turn the JSON output of `terraform show -json` into a YAML document listing the resources
+/// (from all the modules) with their resolved attributes and planned actions.
+///
+/// The lines of each resource are mapped to the line of the plan where its address first
+/// appears, so that the findings point into the plan.
+pub fn plan_document(plan_json: &str) -> anyhow::Result<(String, SourceMap)> {
+    let plan: RawPlan = serde_json::from_str(plan_json).context("Invalid Terraform plan")?;
+
+    let actions: HashMap<&str, &[String]> = plan
+        .resource_changes
+        .iter()
+        .map(|c| (c.address.as_str(), c.change.actions.as_slice()))
+        .collect();
+
+    let mut resources = vec![];
+
+    if let Some(values) = plan.planned_values.as_ref().or(plan.values.as_ref()) {
+        collect_resources(&values.root_module, &actions, &mut resources);
+    }
+
+    if resources.is_empty() {
+        return Ok((
+            "resources: []\n".to_string(),
+            SourceMap::from_lines(plan_json, [1]),
+        ));
+    }
+
+    let mut document = String::from("resources:\n");
+    let mut lines = vec![1];
+
+    for resource in &resources {
+        let yaml = serde_yaml::to_string(std::slice::from_ref(resource))?;
+        let line = address_line(plan_json, resource.address);
+
+        lines.extend(std::iter::repeat(line).take(yaml.lines().count()));
+        document.push_str(&yaml);
+    }
+
+    Ok((document, SourceMap::from_lines(plan_json, lines)))
+}
+
+/// Line of the plan where the given resource address first appears as a string.
+fn address_line(plan_json: &str, address: &str) -> usize {
+    let quoted = serde_json::to_string(address).expect("serializable address");
+
+    plan_json
+        .find(&quoted)
+        .map_or(1, |offset| plan_json[..offset].matches('\n').count() + 1)
+}
+
+fn collect_resources<'p>(
+    module: &'p RawModule,
+    actions: &HashMap<&str, &'p [String]>,
+    resources: &mut Vec<PlannedResource<'p>>,
+) {
+    resources.extend(module.resources.iter().map(|r| PlannedResource {
+        address: &r.address,
+        mode: &r.mode,
+        kind: &r.kind,
+        name: &r.name,
+        provider: r.provider_name.as_deref(),
+        actions: actions.get(r.address.as_str()).copied().unwrap_or_default(),
+        values: &r.values,
+    }));
+
+    for child in &module.child_modules {
+        collect_resources(child, actions, resources);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    static PLAN: &str = indoc! {r#"
+        {
+          "format_version": "1.2",
+          "planned_values": {
+            "root_module": {
+              "resources": [
+                {
+                  "address": "aws_s3_bucket.logs",
+                  "mode": "managed",
+                  "type": "aws_s3_bucket",
+                  "name": "logs",
+                  "provider_name": "registry.terraform.io/hashicorp/aws",
+                  "values": { "acl": "public-read", "bucket": "logs" }
+                }
+              ],
+              "child_modules": [
+                {
+                  "address": "module.db",
+                  "resources": [
+                    {
+                      "address": "module.db.aws_db_instance.main",
+                      "mode": "managed",
+                      "type": "aws_db_instance",
+                      "name": "main",
+                      "values": { "storage_encrypted": false }
+                    }
+                  ]
+                }
+              ]
+            }
+          },
+          "resource_changes": [
+            { "address": "aws_s3_bucket.logs", "change": { "actions": ["create"] } }
+          ]
+        }
+    "#};
+
+    #[test]
+    fn resources_of_all_modules() {
+        let document: serde_yaml::Value =
+            serde_yaml::from_str(&plan_document(PLAN).unwrap().0).unwrap();
+        let resources = document["resources"].as_sequence().unwrap();
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0]["type"], "aws_s3_bucket");
+        assert_eq!(resources[0]["values"]["acl"], "public-read");
+        assert_eq!(resources[0]["actions"][0], "create");
+        assert_eq!(resources[1]["address"], "module.db.aws_db_instance.main");
+        assert_eq!(resources[1]["values"]["storage_encrypted"], false);
+        assert!(resources[1].get("actions").is_none());
+    }
+
+    #[test]
+    fn resources_are_mapped_to_their_address() {
+        let (document, map) = plan_document(PLAN).unwrap();
+        let line_of = |text: &str| {
+            document
+                .lines()
+                .position(|line| line.contains(text))
+                .unwrap()
+                + 1
+        };
+
+        assert_eq!(map.original_line(1), 1);
+        assert_eq!(map.original_line(line_of("acl: public-read")), 7);
+        assert_eq!(map.original_line(line_of("aws_db_instance.main")), 20);
+        assert_eq!(map.original_line(line_of("storage_encrypted")), 20);
+    }
+
+    #[test]
+    fn plan_suffix() {
+        assert!(is_terraform_plan(Path::new("infra/prod.tfplan.json")));
+        assert!(!is_terraform_plan(Path::new("infra/package.json")));
+    }
+
+    #[test]
+    fn invalid_plan() {
+        assert!(plan_document("[]").is_err());
+    }
+}
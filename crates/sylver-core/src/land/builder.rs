@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 use id_vec::IdVec;
 
@@ -32,9 +32,24 @@ impl LandBuilder {
         let sylva_id = self.land.sylvae.insert(sylva).into();
         self.land.sylva_spec.insert(sylva_id, spec_id);
         self.land.sylva_scopes.insert(sylva_id, Default::default());
+        self.land.sylva_clones.insert(sylva_id, Default::default());
+        self.land
+            .sylva_call_graphs
+            .insert(sylva_id, Default::default());
         Ok(sylva_id)
     }
 
+    /// Set the root directory of the project of the sylva, to which the scripts make paths
+    /// relative.
+    pub fn set_sylva_root(&mut self, sylva: SylvaId, root: PathBuf) {
+        self.land.sylva_roots.insert(sylva, root);
+    }
+
+    /// Set the minimum size (in tokens) of the duplicated regions of the sylva.
+    pub fn set_min_clone_tokens(&mut self, sylva: SylvaId, tokens: usize) {
+        self.land.sylva_min_clone_tokens.insert(sylva, tokens);
+    }
+
     pub fn add_ruleset(&mut self, sylva: SylvaId, stem: &RuleSetStem) -> anyhow::Result<RuleSetId> {
         let spec_id = *self.land.sylva_spec.get(&sylva).unwrap();
         let spec = self.land.spec(spec_id);
@@ -66,6 +81,10 @@ impl Default for LandBuilder {
                 sylva_spec: HashMap::new(),
                 sylva_rules: HashMap::new(),
                 sylva_scopes: HashMap::new(),
+                sylva_clones: HashMap::new(),
+                sylva_call_graphs: HashMap::new(),
+                sylva_roots: HashMap::new(),
+                sylva_min_clone_tokens: HashMap::new(),
             },
         }
     }
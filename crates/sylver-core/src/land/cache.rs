@@ -0,0 +1,286 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{self, AtomicUsize},
+        RwLock,
+    },
+};
+
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+
+use crate::{
+    core::source::SourceTree,
+    land::{sylva::SylvaId, LandSpecId},
+    query::{
+        expr::{DepthNodeGeneratorFn, Expr},
+        memo::MemoStats,
+    },
+    tree::NodeId,
+};
+
+/// Key of a cached rule result: the sylva and spec of the candidate node, the fingerprint of the
+/// predicate and the hash of the subtree.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CacheKey {
+    pub sylva: SylvaId,
+    pub spec: LandSpecId,
+    pub predicate: u64,
+    pub subtree: u64,
+}
+
+/// Results of the rules whose predicate only depends on the subtree of the candidate node. The
+/// cache outlives the land, so that evaluating the rules after an edit only evaluates them on the
+/// modified subtrees. It is meant for watch mode, where the land is rebuilt from the same
+/// configuration.
+///
+/// The hashes use the kind ids of the spec: the cache must be cleared when the language specs
+/// change.
+#[derive(Debug, Default)]
+pub struct RuleCache {
+    results: RwLock<FxHashMap<CacheKey, bool>>,
+    /// Keys looked up since the last call to `retain_used`.
+    used: RwLock<FxHashSet<CacheKey>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl RuleCache {
+    pub fn get(&self, key: CacheKey) -> Option<bool> {
+        self.used.write().expect("poisoned cache lock").insert(key);
+
+        let result = self
+            .results
+            .read()
+            .expect("poisoned cache lock")
+            .get(&key)
+            .copied();
+
+        let counter = if result.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, atomic::Ordering::Relaxed);
+
+        result
+    }
+
+    pub fn insert(&self, key: CacheKey, matched: bool) {
+        self.results
+            .write()
+            .expect("poisoned cache lock")
+            .insert(key, matched);
+    }
+
+    /// Drop the results that were not looked up since the previous call, so that the results of
+    /// edited code don't accumulate, and reset the statistics.
+    pub fn retain_used(&self) {
+        let mut used = self.used.write().expect("poisoned cache lock");
+
+        self.results
+            .write()
+            .expect("poisoned cache lock")
+            .retain(|key, _| used.contains(key));
+
+        used.clear();
+        self.hits.store(0, atomic::Ordering::Relaxed);
+        self.misses.store(0, atomic::Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        MemoStats {
+            hits: self.hits.load(atomic::Ordering::Relaxed),
+            misses: self.misses.load(atomic::Ordering::Relaxed),
+        }
+    }
+}
+
+/// Fingerprint of a rule predicate whose value only depends on the subtree of the candidate node,
+/// or `None` if it can look outside of it (parents, siblings, references...).
+pub fn subtree_fingerprint(predicate: &Expr) -> Option<u64> {
+    is_local(predicate, Locality::Subtree).then(|| {
+        let mut hasher = FxHasher::default();
+        predicate.hash(&mut hasher);
+        hasher.finish()
+    })
+}
+
+/// Return whether the value of the predicate only depends on the file of the candidate node, as
+/// opposed to the names and clones resolved across files.
+pub fn is_file_local(predicate: &Expr) -> bool {
+    is_local(predicate, Locality::File)
+}
+
+/// Part of the land that the value of a predicate can depend on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Locality {
+    /// The subtree of the candidate node.
+    Subtree,
+    /// The file of the candidate node.
+    File,
+}
+
+fn is_local(expr: &Expr, locality: Locality) -> bool {
+    let local = |e: &Expr| is_local(e, locality);
+
+    match expr {
+        Expr::ReferencedDecl(_)
+        | Expr::Resolves(_)
+        | Expr::DeclarationOf(_)
+        | Expr::NodeClones(_)
+        | Expr::ConstantValue(_)
+        | Expr::Callers(_)
+        | Expr::Callees(_)
+        | Expr::MatchedBy(..) => false,
+        Expr::NodeParent(e)
+        | Expr::NodePrevSibling(e)
+        | Expr::NodeNextSibling(e)
+        | Expr::Decorators(e, _)
+        | Expr::HasDecorator(e, ..)
+        | Expr::ReturnAnnotation(e, _)
+        | Expr::Docstring(e, _)
+        | Expr::InferredType(e, _)
+        | Expr::PropertyWrappers(e, _)
+        | Expr::HasPropertyWrapper(e, ..)
+        | Expr::IsViewBody(e, _) => locality == Locality::File && local(e),
+        Expr::Const(_) | Expr::ReadVar(_) => true,
+        Expr::BuildGen(operand, _, gen_fn) => {
+            (locality == Locality::File || matches!(gen_fn, DepthNodeGeneratorFn::Descendants))
+                && local(operand)
+        }
+        Expr::IntConv(e)
+        | Expr::KindAccess(e)
+        | Expr::NodeText(e)
+        | Expr::NormalizedText(e)
+        | Expr::ContainsText(e, _)
+        | Expr::NodeChildren(e)
+        | Expr::NonNullCheck(e)
+        | Expr::Length(e)
+        | Expr::PropAccess(e, _)
+        | Expr::RegexMatch(e, _)
+        | Expr::Not(e) => local(e),
+        Expr::InContext(values, e) => values.iter().all(local) && local(e),
+        Expr::ArrayIndex(l, r)
+        | Expr::And(l, r)
+        | Expr::Or(l, r)
+        | Expr::Lt(l, r)
+        | Expr::Lte(l, r)
+        | Expr::Ht(l, r)
+        | Expr::Hte(l, r)
+        | Expr::EqEq(l, r)
+        | Expr::Neq(l, r) => local(l) && local(r),
+        Expr::CountCheckMin(a, b, c) | Expr::CountCheckMax(a, b, c) | Expr::Ternary(a, b, c) => {
+            local(a) && local(b) && local(c)
+        }
+    }
+}
+
+/// Merkle-style hash of the subtree of each node: the hash of a node combines its kind, the
+/// hashes of its children and the tokens between them.
+pub fn subtree_hashes(tree: &SourceTree) -> FxHashMap<NodeId, u64> {
+    let mut hashes = FxHashMap::default();
+
+    // Children come before their parents in the bottom-up order.
+    for id in tree.tree.bottom_up_node_ids() {
+        let node = &tree[id];
+        let mut hasher = FxHasher::default();
+        node.kind.hash(&mut hasher);
+
+        let mut pos = node.span.start;
+
+        for child in &node.childs {
+            let child_span = &tree[*child].span;
+
+            if let Some(end) = child_span.end() {
+                hash_tokens(tree, pos, child_span.start, &mut hasher);
+                pos = end + 1;
+            }
+
+            hashes.get(child).hash(&mut hasher);
+        }
+
+        if let Some(end) = node.span.end() {
+            hash_tokens(tree, pos, end + 1, &mut hasher);
+        }
+
+        hashes.insert(id, hasher.finish());
+    }
+
+    hashes
+}
+
+fn hash_tokens(tree: &SourceTree, start: usize, end: usize, hasher: &mut FxHasher) {
+    for pos in start..end {
+        tree.tokens[pos].tag.hash(hasher);
+        tree.token_code(pos).hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::source::test::create_test_source_tree;
+
+    use super::*;
+
+    #[test]
+    fn local_predicates() {
+        let text_check = Expr::contains_text(Expr::read_var(0), "eval".to_string());
+        let parent_check = Expr::non_null_check(Expr::node_parent(Expr::read_var(0)));
+
+        assert!(subtree_fingerprint(&text_check).is_some());
+        assert!(subtree_fingerprint(&parent_check).is_none());
+        assert!(subtree_fingerprint(&Expr::and(text_check, parent_check)).is_none());
+    }
+
+    #[test]
+    fn file_local_predicates() {
+        let parent_check = Expr::non_null_check(Expr::node_parent(Expr::read_var(0)));
+        let clones_check = Expr::non_null_check(Expr::node_clones(Expr::read_var(0)));
+
+        assert!(is_file_local(&parent_check));
+        assert!(!is_file_local(&clones_check));
+        assert!(!is_file_local(&Expr::or(parent_check, clones_check)));
+    }
+
+    #[test]
+    fn hashes_follow_code() {
+        let tree = create_test_source_tree("var x = 1;");
+        let same = create_test_source_tree("var x = 1;");
+        let edited = create_test_source_tree("var x = 2;");
+
+        let root_hash = |tree: &SourceTree| subtree_hashes(tree)[&tree.tree.root()];
+
+        assert_eq!(root_hash(&tree), root_hash(&same));
+        assert_ne!(root_hash(&tree), root_hash(&edited));
+    }
+
+    fn key(sylva: usize, predicate: u64, subtree: u64) -> CacheKey {
+        CacheKey {
+            sylva: sylva.into(),
+            spec: LandSpecId::BuiltinLangId(0.into()),
+            predicate,
+            subtree,
+        }
+    }
+
+    #[test]
+    fn unused_results_are_dropped() {
+        let cache = RuleCache::default();
+        cache.insert(key(0, 1, 1), true);
+        cache.insert(key(0, 1, 2), false);
+
+        assert_eq!(cache.get(key(0, 1, 1)), Some(true));
+        cache.retain_used();
+
+        assert_eq!(cache.get(key(0, 1, 2)), None);
+        assert_eq!(cache.get(key(0, 1, 1)), Some(true));
+    }
+
+    #[test]
+    fn results_are_kept_per_sylva() {
+        let cache = RuleCache::default();
+        cache.insert(key(0, 1, 1), true);
+
+        assert_eq!(cache.get(key(1, 1, 1)), None);
+    }
+}
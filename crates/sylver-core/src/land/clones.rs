@@ -0,0 +1,253 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    core::{source::SourceTree, spec::TagId},
+    land::{sylva::SylvaId, Land},
+    query::SylvaNode,
+    tree::NodeId,
+};
+
+/// Minimum size (in tokens) of the regions reported as clones, unless configured otherwise.
+pub const DEFAULT_MIN_CLONE_TOKENS: usize = 50;
+
+/// Set of regions that have the same shape.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CloneGroup {
+    /// Size (in tokens) of the duplicated region.
+    pub tokens: usize,
+    pub nodes: Vec<SylvaNode>,
+}
+
+/// Duplicated regions of a sylva.
+///
+/// Two subtrees are clones if they have the same kinds, the same structure, and if their leaves
+/// have the same text (ignoring trivia).
+#[derive(Debug, Clone, Default)]
+pub struct SylvaClones {
+    groups: Vec<CloneGroup>,
+    node_groups: HashMap<SylvaNode, usize>,
+}
+
+impl SylvaClones {
+    pub fn detect(land: &Land, sylva_id: SylvaId, min_tokens: usize) -> SylvaClones {
+        let sylva = land.sylva(sylva_id);
+        let trivial_tags = land.sylva_spec(sylva_id).syntax.trivial_tags();
+
+        let mut candidates: BTreeMap<u64, Vec<SylvaNode>> = BTreeMap::new();
+
+        for (tree_id, tree) in sylva.iter() {
+            let mut hashes = HashMap::new();
+
+            for node in tree.nodes() {
+                let hash = shape_hash(&tree.tree, trivial_tags, node, &mut hashes);
+
+                if tree.tree.tree[node].span.length >= min_tokens {
+                    candidates.entry(hash).or_default().push(SylvaNode {
+                        sylva: sylva_id,
+                        tree: tree_id,
+                        node,
+                    });
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<SylvaNode>> = candidates
+            .into_values()
+            .flat_map(|nodes| split_by_shape(land, trivial_tags, nodes))
+            .filter(|nodes| nodes.len() > 1)
+            .collect();
+
+        // Only keep the largest duplicated regions: a group whose nodes all have cloned parents is
+        // already covered by the group of the parents.
+        let cloned: HashSet<SylvaNode> = groups.iter().flatten().copied().collect();
+        groups.retain(|nodes| {
+            !nodes.iter().all(|n| {
+                let parent = land.sylva_node_tree(*n).tree[n.node].parent;
+                parent.map_or(false, |p| cloned.contains(&n.with_node_id(p)))
+            })
+        });
+
+        let mut groups: Vec<CloneGroup> = groups
+            .into_iter()
+            .map(|mut nodes| {
+                nodes.sort();
+                let first = nodes[0];
+                let tokens = land.sylva_node_tree(first).tree[first.node].span.length;
+                CloneGroup { tokens, nodes }
+            })
+            .collect();
+
+        groups.sort_by(|g1, g2| g2.tokens.cmp(&g1.tokens).then(g1.nodes.cmp(&g2.nodes)));
+
+        let node_groups = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(index, group)| group.nodes.iter().map(move |&n| (n, index)))
+            .collect();
+
+        SylvaClones {
+            groups,
+            node_groups,
+        }
+    }
+
+    pub fn groups(&self) -> &[CloneGroup] {
+        &self.groups
+    }
+
+    /// Return the regions that are clones of the given node.
+    pub fn clones_of(&self, node: SylvaNode) -> Vec<SylvaNode> {
+        self.node_groups
+            .get(&node)
+            .map(|&index| {
+                self.groups[index]
+                    .nodes
+                    .iter()
+                    .copied()
+                    .filter(|&n| n != node)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn shape_hash(
+    tree: &SourceTree,
+    trivial_tags: &[TagId],
+    node: NodeId,
+    hashes: &mut HashMap<NodeId, u64>,
+) -> u64 {
+    if let Some(&hash) = hashes.get(&node) {
+        return hash;
+    }
+
+    let tree_node = &tree.tree[node];
+    let mut hasher = DefaultHasher::new();
+    tree_node.kind.hash(&mut hasher);
+
+    if tree_node.childs.is_empty() {
+        tree.node_text(node, trivial_tags).hash(&mut hasher);
+    } else {
+        tree_node.childs.len().hash(&mut hasher);
+        for &child in &tree_node.childs {
+            shape_hash(tree, trivial_tags, child, hashes).hash(&mut hasher);
+        }
+    }
+
+    let hash = hasher.finish();
+    hashes.insert(node, hash);
+    hash
+}
+
+/// Split nodes whose shape hashes are equal into the groups of nodes that really have the same
+/// shape, in case the hashes collide.
+fn split_by_shape(
+    land: &Land,
+    trivial_tags: &[TagId],
+    nodes: Vec<SylvaNode>,
+) -> Vec<Vec<SylvaNode>> {
+    let mut groups: Vec<Vec<SylvaNode>> = vec![];
+
+    for node in nodes {
+        let tree = land.sylva_node_tree(node);
+        let group = groups.iter_mut().find(|group| {
+            let first = group[0];
+            same_shape(
+                (land.sylva_node_tree(first), first.node),
+                (tree, node.node),
+                trivial_tags,
+            )
+        });
+
+        match group {
+            Some(group) => group.push(node),
+            None => groups.push(vec![node]),
+        }
+    }
+
+    groups
+}
+
+fn same_shape(
+    (tree1, node1): (&SourceTree, NodeId),
+    (tree2, node2): (&SourceTree, NodeId),
+    trivial_tags: &[TagId],
+) -> bool {
+    let (tree_node1, tree_node2) = (&tree1.tree[node1], &tree2.tree[node2]);
+
+    if tree_node1.kind != tree_node2.kind || tree_node1.childs.len() != tree_node2.childs.len() {
+        return false;
+    }
+
+    if tree_node1.childs.is_empty() {
+        return tree1.node_text(node1, trivial_tags) == tree2.node_text(node2, trivial_tags);
+    }
+
+    tree_node1
+        .childs
+        .iter()
+        .zip(&tree_node2.childs)
+        .all(|(&child1, &child2)| same_shape((tree1, child1), (tree2, child2), trivial_tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        core::{
+            source::Source,
+            spec::{test::parse_spec, DEFAULT_START_RULE},
+        },
+        land::{builder::LandBuilder, sylva::Sylva, LandSpecId},
+    };
+
+    use super::*;
+
+    #[test]
+    fn detect_duplicated_groups() {
+        let spec = parse_spec(indoc!(
+            "
+            node A { }
+            node Group { elems: List<A> }
+            node Program { groups: List<Group> }
+
+            rule main = Program { groups@sepBy1(';', group) }
+
+            rule group = Group { elems@sepBy1(',', a_rule) }
+
+            rule a_rule = A { `a+` }
+            "
+        ));
+
+        let source = Source::inline("a,aa;a,aa;aaa".to_string(), "input".to_string());
+        let sylva = Sylva::build(&spec, DEFAULT_START_RULE, std::iter::once(source)).unwrap();
+
+        let mut builder = LandBuilder::new();
+        let spec_id = builder.add_spec(spec);
+        let sylva_id = builder
+            .add_sylva(sylva, LandSpecId::CustomLangId(spec_id))
+            .unwrap();
+        let land = builder.build();
+
+        let clones = SylvaClones::detect(&land, sylva_id, 3);
+
+        assert_eq!(clones.groups().len(), 1);
+
+        let group = &clones.groups()[0];
+        assert_eq!(group.tokens, 3);
+        assert_eq!(group.nodes.len(), 2);
+        assert_eq!(clones.clones_of(group.nodes[0]), vec![group.nodes[1]]);
+
+        let tree = land.sylva_node_tree(group.nodes[0]);
+        let (first, second) = (group.nodes[0].node, group.nodes[1].node);
+        let childs = &tree.tree[first].childs;
+
+        assert!(same_shape((tree, first), (tree, second), &[]));
+        assert!(!same_shape((tree, childs[0]), (tree, childs[1]), &[]));
+        assert!(!same_shape((tree, first), (tree, childs[0]), &[]));
+    }
+}
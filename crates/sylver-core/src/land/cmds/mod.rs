@@ -11,6 +11,7 @@ use crate::{
         sylva_nodes, RawTreeInfoBuilder, SylvaNode,
     },
     report::Report,
+    util::cancel::CancellationToken,
 };
 
 use crate::script::python::PythonScriptEngine;
@@ -25,6 +26,7 @@ pub fn filter_sylva(
     script_engine: PythonScriptEngine,
     sylva_id: SylvaId,
     predicate: &Expr,
+    cancel: &CancellationToken,
 ) -> Result<Vec<SylvaNode>, EvalError> {
     let sylva = land.sylva(sylva_id);
     let spec = land.sylva_spec(sylva_id);
@@ -34,11 +36,14 @@ pub fn filter_sylva(
         RawTreeInfoBuilder::new(spec, sylva),
         land,
         script_engine,
-    );
+    )
+    .with_cancellation(cancel.clone());
 
     let mut filtered_nodes = vec![];
 
     for node in sylva_nodes(land, sylva_id) {
+        ctx.check_cancelled()?;
+
         if eval_predicate(&mut ctx, node, predicate)? {
             filtered_nodes.push(node);
         }
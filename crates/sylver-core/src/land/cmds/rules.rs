@@ -1,15 +1,29 @@
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashMap, time::Duration};
+
+use log::Progress;
 
 use crate::{
     core::source::Source,
     land::{
-        ruleset::{Rule, RuleSetId},
+        cache::RuleCache,
+        passes::run_passes,
+        ruleset::{
+            kind_index, FindingsBudget, KindIndex, KnownFindings, Rule, RuleSetId,
+            RuleVerification, StopCondition,
+        },
         sylva::SylvaId,
         Land,
     },
-    query::{RawTreeInfoBuilder, SylvaNode},
+    query::{
+        expr::EvalError,
+        memo::{EvalMemo, MemoStats},
+        RawTreeInfoBuilder, SylvaNode,
+    },
     report::{Report, ReportKind},
+    script::ScriptError,
+    semantic::names::NamesError,
     tree::info::{raw::RawTreeInfo, TreeInfo},
+    util::{cancel::CancellationToken, edits::FixEdit},
 };
 
 #[derive(Debug, Clone)]
@@ -39,8 +53,9 @@ impl RuleResult {
             code: self.rule_id.clone(),
             kind,
             position: info.node_pos(self.node.node),
-            message: rule.message.clone(),
+            message: rule.render_message(land, self.node),
             note: rule.note.clone(),
+            doc_url: rule.doc_url.clone(),
         }
     }
 
@@ -50,6 +65,27 @@ impl RuleResult {
         &tree.unwrap().source
     }
 
+    /// Edit replacing the text of the matched node by the fix of the rule, if it has one. The
+    /// fixes of the rules of the most severe categories have the highest priority.
+    pub fn fix_edit(&self, land: &Land) -> Option<Result<FixEdit, EvalError>> {
+        let rule = self.rule(land);
+        let replacement = rule.render_fix(land, self.node)?;
+
+        let spec = land.sylva_spec(self.node.sylva);
+        let tree = land
+            .sylva(self.node.sylva)
+            .source_tree(self.node.tree)
+            .unwrap();
+
+        Some(replacement.map(|replacement| FixEdit {
+            file: tree.source.path().to_owned(),
+            rule: format!("{}/{}", land.ruleset(self.ruleset).id(), self.rule_id),
+            span: tree.node_text_range(self.node.node, spec.syntax.trivial_tags()),
+            replacement,
+            priority: rule.category as u32,
+        }))
+    }
+
     pub fn rule<'l>(&self, land: &'l Land) -> &'l Rule {
         land.ruleset(self.ruleset)
             .get_rule(&self.rule_id)
@@ -57,50 +93,240 @@ impl RuleResult {
     }
 }
 
-pub fn exec_rules(land: &Land) -> anyhow::Result<Vec<RuleResult>> {
-    let res: Vec<RuleResult> = land
+/// Failure of the evaluation of a rule on a node, reported separately from the findings.
+#[derive(Debug, Clone)]
+pub struct RuleFailure {
+    pub ruleset: RuleSetId,
+    pub rule_id: String,
+    pub node: SylvaNode,
+    pub error: EvalError,
+}
+
+impl RuleFailure {
+    pub fn to_report(&self, land: &Land) -> Report {
+        let spec = land.sylva_spec(self.node.sylva);
+
+        let tree = land
+            .sylva(self.node.sylva)
+            .source_tree(self.node.tree)
+            .unwrap();
+
+        let info = RawTreeInfo::new(tree, &spec.syntax);
+
+        Report {
+            file_path: tree.source.path().to_owned(),
+            code: self.rule_id.clone(),
+            kind: ReportKind::Error,
+            position: info.node_pos(self.node.node),
+            message: format!("Rule evaluation failed: {}", self.message()),
+            note: None,
+            doc_url: None,
+        }
+    }
+
+    pub fn source<'l>(&self, land: &'l Land) -> &'l Source {
+        let sylva = land.sylva(self.node.sylva);
+        let tree = sylva.source_tree(self.node.tree);
+        &tree.unwrap().source
+    }
+
+    /// Short description of the error: the exception for script errors.
+    pub fn message(&self) -> String {
+        match self.traceback() {
+            Some(traceback) => traceback
+                .lines()
+                .rev()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or_default()
+                .to_string(),
+            None => self.error.to_string(),
+        }
+    }
+
+    /// Python traceback of script errors.
+    pub fn traceback(&self) -> Option<&str> {
+        match &self.error {
+            EvalError::NameRes(NamesError::Script(ScriptError::RuntimeError(traceback))) => {
+                Some(traceback)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of the evaluation of the rules of a land.
+#[derive(Debug, Clone, Default)]
+pub struct RulesExecution {
+    pub results: Vec<RuleResult>,
+    /// Rules that could not be evaluated on some nodes.
+    pub failures: Vec<RuleFailure>,
+    /// Time spent evaluating each rule, summed over the sylvae it applies to.
+    pub durations: HashMap<(RuleSetId, String), Duration>,
+    /// Lookups in the values memoized across the rules.
+    pub memo: MemoStats,
+    /// Lookups in the cached rule results.
+    pub cache: MemoStats,
+    /// True if the evaluation stopped early because the stop condition was met.
+    pub stopped: bool,
+}
+
+/// Evaluate the rules of the land, stopping early if the given condition is met. Each evaluated
+/// rule is reported to `progress`. The results found in `cache`, if any, are reused, and the
+/// results that were not looked up are dropped from it. The `known` findings are left out of the
+/// results.
+///
+/// Fails if the token is cancelled before the evaluation completes.
+pub fn exec_rules(
+    land: &Land,
+    stop: StopCondition,
+    cancel: &CancellationToken,
+    cache: Option<&RuleCache>,
+    known: Option<&dyn KnownFindings>,
+    progress: &dyn Progress,
+) -> anyhow::Result<RulesExecution> {
+    let budget = FindingsBudget::new(stop, cancel.clone(), known);
+    let memo = EvalMemo::default();
+
+    let rules_count: usize = land
+        .sylva_rules
+        .values()
+        .flat_map(|rulesets| rulesets.iter().map(|&r| land.ruleset(r).len()))
+        .sum();
+    progress.set_length(rules_count as u64);
+
+    // The kind index of a sylva is shared by all the rulesets that apply to it.
+    let sylvae = land
         .sylvae()
         .filter_map(|sylva_id| {
             let rulesets = land.sylva_rules.get(&sylva_id)?;
             Some((sylva_id, rulesets))
         })
-        .flat_map(|(sylva, rulesets)| rulesets.iter().map(move |ruleset| (sylva, ruleset)))
-        .map(|(sylva, &ruleset)| verify_sylva(land, ruleset, sylva))
-        .collect::<anyhow::Result<Vec<Vec<RuleResult>>>>()?
-        .into_iter()
-        .flatten()
-        .collect();
-
-    Ok(res)
+        .map(|(sylva, rulesets)| {
+            let passes = rulesets
+                .iter()
+                .flat_map(|&r| land.ruleset(r).required_passes());
+            run_passes(land, sylva, passes, cancel)
+                .map(|_| (sylva, rulesets, kind_index(land.sylva(sylva))))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let verifications = sylvae
+        .iter()
+        .flat_map(|(sylva, rulesets, index)| {
+            rulesets.iter().map(move |ruleset| (*sylva, ruleset, index))
+        })
+        .filter(|_| !budget.is_exhausted())
+        .map(|(sylva, &ruleset, index)| {
+            verify_sylva(land, ruleset, sylva, index, &budget, &memo, cache, progress)
+                .map(|v| (ruleset, v))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    cancel.check()?;
+
+    let mut execution = RulesExecution {
+        memo: memo.stats(),
+        cache: cache.map(RuleCache::stats).unwrap_or_default(),
+        stopped: budget.is_exhausted(),
+        ..Default::default()
+    };
+
+    if let Some(cache) = cache {
+        cache.retain_used();
+    }
+
+    for (ruleset, verification) in verifications {
+        for (rule_id, rule_verification) in verification {
+            *execution
+                .durations
+                .entry((ruleset, rule_id.clone()))
+                .or_default() += rule_verification.duration;
+
+            execution
+                .results
+                .extend(
+                    rule_verification
+                        .matches
+                        .into_iter()
+                        .map(|node| RuleResult {
+                            ruleset,
+                            rule_id: rule_id.clone(),
+                            node,
+                        }),
+                );
+
+            execution
+                .failures
+                .extend(
+                    rule_verification
+                        .errors
+                        .into_iter()
+                        .map(|(node, error)| RuleFailure {
+                            ruleset,
+                            rule_id: rule_id.clone(),
+                            node,
+                            error,
+                        }),
+                );
+        }
+    }
+
+    Ok(execution)
 }
 
-fn verify_sylva(
-    land: &Land,
+#[allow(clippy::too_many_arguments)]
+fn verify_sylva<'l>(
+    land: &'l Land,
     ruleset_id: RuleSetId,
     sylva_id: SylvaId,
-) -> anyhow::Result<Vec<RuleResult>> {
+    kind_index: &KindIndex,
+    budget: &FindingsBudget,
+    memo: &'l EvalMemo<'l>,
+    cache: Option<&RuleCache>,
+    progress: &dyn Progress,
+) -> anyhow::Result<HashMap<String, RuleVerification>> {
     let sylva = land.sylva(sylva_id);
     let ruleset = land.ruleset(ruleset_id);
     let spec = land.sylva_spec(sylva_id);
 
     let builder = RawTreeInfoBuilder::new(spec, sylva);
 
-    let results = ruleset.verify(builder.clone(), land, sylva_id)?;
-
-    Ok(to_rule_results(ruleset_id, results))
+    ruleset.verify(
+        builder, land, ruleset_id, sylva_id, kind_index, budget, memo, cache, progress,
+    )
 }
 
-fn to_rule_results(
-    ruleset: RuleSetId,
-    results: HashMap<String, HashSet<SylvaNode>>,
-) -> Vec<RuleResult> {
-    results
-        .into_iter()
-        .flat_map(|(rule_id, nodes)| nodes.into_iter().map(move |n| (rule_id.clone(), n)))
-        .map(|(rule_id, node)| RuleResult {
-            ruleset,
-            rule_id,
-            node,
-        })
-        .collect()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(error: EvalError) -> RuleFailure {
+        RuleFailure {
+            ruleset: 0.into(),
+            rule_id: "no-eval".to_string(),
+            node: SylvaNode {
+                sylva: 0.into(),
+                tree: 0.into(),
+                node: 0.into(),
+            },
+            error,
+        }
+    }
+
+    #[test]
+    fn failure_messages() {
+        let traceback = "Traceback (most recent call last):\n  File \"aspects.py\", line 3, in \
+                         sg_gen\nKeyError: 'name'\n";
+        let script_failure = failure(EvalError::NameRes(NamesError::Script(
+            ScriptError::RuntimeError(traceback.to_string()),
+        )));
+
+        assert_eq!(script_failure.message(), "KeyError: 'name'");
+        assert_eq!(script_failure.traceback(), Some(traceback));
+
+        let query_failure = failure(EvalError::IndexError(3));
+
+        assert_eq!(query_failure.message(), "Invalid array index: 3");
+        assert_eq!(query_failure.traceback(), None);
+    }
 }
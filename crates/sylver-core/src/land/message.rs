@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Context};
+use sylver_dsl::sylq::{parse_template_expr, PredicateDef, QueryPattern};
+
+use crate::{
+    core::spec::Spec,
+    land::Land,
+    query::{
+        expr::{EvalCtx, EvalError, Expr, Value},
+        language::compile::compile_template_expr,
+        RawTreeInfoBuilder, SylvaNode,
+    },
+    script::python::PythonScriptEngine,
+};
+
+/// Message of a rule, in which the `{expr}` placeholders are replaced by the value of an
+/// expression evaluated on the matched node. Literal braces are written `{{` and `}}`, except in
+/// the string literals of the placeholders.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MessageTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum TemplatePart {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Segment<'t> {
+    Text(String),
+    Placeholder(&'t str),
+}
+
+impl MessageTemplate {
+    /// Compile the placeholders of the template, in which the binding of the query refers to the
+    /// matched node.
+    pub fn compile(
+        spec: &Spec,
+        predicates: &BTreeMap<String, PredicateDef>,
+        query: &QueryPattern,
+        template: &str,
+    ) -> anyhow::Result<MessageTemplate> {
+        let parts = split_template(template)?
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => Ok(TemplatePart::Text(text)),
+                Segment::Placeholder(code) => {
+                    let expr = parse_template_expr(code)
+                        .with_context(|| format!("Invalid message placeholder: {{{code}}}"))?;
+                    Ok(TemplatePart::Expr(compile_template_expr(
+                        spec, predicates, query, &expr,
+                    )?))
+                }
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(MessageTemplate { parts })
+    }
+
+    pub fn has_placeholders(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|p| matches!(p, TemplatePart::Expr(_)))
+    }
+
+    pub fn render(&self, land: &Land, node: SylvaNode) -> Result<String, EvalError> {
+        let spec = land.sylva_spec(node.sylva);
+        let builder = RawTreeInfoBuilder::new(spec, land.sylva(node.sylva));
+        let mut ctx = EvalCtx::new(spec, builder, land, PythonScriptEngine::default());
+        ctx.push_var(Value::Node(node));
+
+        let mut message = String::new();
+
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => message.push_str(text),
+                TemplatePart::Expr(expr) => {
+                    let value = expr.eval(&mut ctx)?;
+                    message.push_str(&render_value(&ctx, spec, value)?);
+                }
+            }
+        }
+
+        Ok(message)
+    }
+}
+
+fn render_value<'b>(
+    ctx: &EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    spec: &Spec,
+    value: Value<'b>,
+) -> Result<String, EvalError> {
+    let rendered = match value {
+        Value::Node(node) => ctx.node_text(node).to_string(),
+        Value::String(s) => s.into_owned(),
+        Value::Int(i) => i.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Kind(kind) => spec.syntax.kind_name(kind).to_string(),
+        Value::Null => "null".to_string(),
+        list @ (Value::List(_) | Value::Generator(_)) => list
+            .collect_list(ctx)?
+            .into_iter()
+            .map(|v| render_value(ctx, spec, v))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", "),
+    };
+
+    Ok(rendered)
+}
+
+fn split_template(template: &str) -> anyhow::Result<Vec<Segment>> {
+    let mut segments = vec![];
+    let mut text = String::new();
+    let mut chars = template.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|&(_, c)| c == '{').is_some() => text.push('{'),
+            '}' if chars.next_if(|&(_, c)| c == '}').is_some() => text.push('}'),
+            '{' => {
+                let mut depth = 1;
+                let mut quote = None;
+                let (end, _) = chars
+                    .by_ref()
+                    .find(|&(_, c)| {
+                        match (quote, c) {
+                            (Some(q), c) if c == q => quote = None,
+                            (Some(_), _) => {}
+                            (None, '\'' | '"') => quote = Some(c),
+                            (None, '{') => depth += 1,
+                            (None, '}') => depth -= 1,
+                            _ => {}
+                        }
+                        depth == 0
+                    })
+                    .ok_or_else(|| anyhow!("Unclosed placeholder in message: {template}"))?;
+
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+
+                segments.push(Segment::Placeholder(template[start + 1..end].trim()));
+            }
+            '}' => bail!("Unmatched '}}' in message: {template}"),
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use maplit::hashmap;
+    use sylver_dsl::sylq::parse_query;
+
+    use crate::{
+        builtin_langs::{get_builtin_lang, parser::BuiltinParserRunner, BuiltinLang},
+        core::{source::Source, spec::Syntax},
+        land::{builder::LandBuilder, cmds::filter_sylva, sylva::Sylva, LandSpecId},
+        query::language::compile::compile,
+        util::cancel::CancellationToken,
+    };
+
+    use super::*;
+
+    #[test]
+    fn split_placeholders() {
+        assert_eq!(
+            split_template("Function {f.name} has {{{f.parameters.length}}} parameters").unwrap(),
+            vec![
+                Segment::Text("Function ".to_string()),
+                Segment::Placeholder("f.name"),
+                Segment::Text(" has {".to_string()),
+                Segment::Placeholder("f.parameters.length"),
+                Segment::Text("} parameters".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            split_template("{{}} {f.text == '}'}").unwrap(),
+            vec![
+                Segment::Text("{} ".to_string()),
+                Segment::Placeholder("f.text == '}'"),
+            ]
+        );
+
+        assert!(split_template("Unclosed {f.name").is_err());
+        assert!(split_template("Unopened f.name}").is_err());
+    }
+
+    #[test]
+    fn render_python_message() {
+        let (mappings, lang, _) = get_builtin_lang(BuiltinLang::Python);
+        let syntax: Syntax = mappings.types.as_slice().into();
+
+        let runner = BuiltinParserRunner::new(lang, &syntax, mappings);
+        let source = indoc! {"
+            def add(a, b, c):
+                pass
+        "};
+        let tree = runner.run(Source::inline(source.to_string(), "BUFFER".to_string()));
+        let sylva = Sylva::new(hashmap! {"buffer".into() => tree });
+
+        let mut builder = LandBuilder::new();
+        let spec_id = builder.add_spec(Spec::new(Default::default(), syntax));
+        let sylva_id = builder
+            .add_sylva(sylva, LandSpecId::BuiltinLangId(spec_id))
+            .unwrap();
+        let land = builder.build();
+        let spec = land.sylva_spec(sylva_id);
+
+        let query = parse_query("match FunctionDefinition f").unwrap();
+        let template = MessageTemplate::compile(
+            spec,
+            &BTreeMap::new(),
+            &query,
+            "Function {f.name} has {f.parameters.length} parameters",
+        )
+        .unwrap();
+
+        let matches = filter_sylva(
+            &land,
+            PythonScriptEngine::default(),
+            sylva_id,
+            &compile(spec, &query).unwrap(),
+            &CancellationToken::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            template.render(&land, matches[0]).unwrap(),
+            "Function add has 3 parameters"
+        );
+
+        assert!(MessageTemplate::compile(spec, &BTreeMap::new(), &query, "{g.name}").is_err());
+    }
+}
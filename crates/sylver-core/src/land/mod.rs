@@ -1,25 +1,34 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
-use std::sync::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
 
 use id_vec::IdVec;
 
-use crate::semantic::names::SylvaScopes;
+use anyhow::anyhow;
+
+use crate::semantic::{calls::CallGraph, names::SylvaScopes};
 use crate::{
     core::{
         source::SourceTree,
         spec::{Spec, SpecId},
     },
     query::SylvaNode,
+    specs::stem::ruleset::RuleSetStem,
 };
 
 use self::{
+    clones::{SylvaClones, DEFAULT_MIN_CLONE_TOKENS},
     ruleset::{RuleSet, RuleSetId},
     sylva::{Sylva, SylvaId},
 };
 
 pub mod builder;
+pub mod cache;
+pub mod clones;
 pub mod cmds;
+pub mod message;
+pub mod passes;
 pub mod ruleset;
 pub mod sylva;
 
@@ -45,6 +54,13 @@ pub struct Land {
     sylva_spec: HashMap<SylvaId, LandSpecId>,
     sylva_rules: HashMap<SylvaId, HashSet<RuleSetId>>,
     sylva_scopes: HashMap<SylvaId, RwLock<SylvaScopes>>,
+    sylva_clones: HashMap<SylvaId, OnceLock<SylvaClones>>,
+    /// Call graph of each sylva, once computed by the call graph pass.
+    sylva_call_graphs: HashMap<SylvaId, OnceLock<CallGraph>>,
+    /// Root directory of the project of each sylva.
+    sylva_roots: HashMap<SylvaId, PathBuf>,
+    /// Minimum size of the duplicated regions, for the sylvae that don't use the default one.
+    sylva_min_clone_tokens: HashMap<SylvaId, usize>,
 }
 
 impl Land {
@@ -60,6 +76,34 @@ impl Land {
             .expect("poisoned sylva scopes lock")
     }
 
+    /// Return the duplicated regions of the given sylva, detecting them on first use.
+    pub fn sylva_clones(&self, id: SylvaId) -> &SylvaClones {
+        self.sylva_clones
+            .get(&id)
+            .unwrap()
+            .get_or_init(|| SylvaClones::detect(self, id, self.min_clone_tokens(id)))
+    }
+
+    /// Return the call graph of the given sylva, if the call graph pass has been run.
+    pub fn sylva_call_graph(&self, id: SylvaId) -> Option<&CallGraph> {
+        self.sylva_call_graphs.get(&id)?.get()
+    }
+
+    /// Set the call graph of the given sylva, unless it was already computed.
+    pub(crate) fn set_sylva_call_graph(&self, id: SylvaId, graph: CallGraph) {
+        if let Some(lock) = self.sylva_call_graphs.get(&id) {
+            let _ = lock.set(graph);
+        }
+    }
+
+    /// Minimum size (in tokens) of the duplicated regions of the given sylva.
+    pub fn min_clone_tokens(&self, id: SylvaId) -> usize {
+        self.sylva_min_clone_tokens
+            .get(&id)
+            .copied()
+            .unwrap_or(DEFAULT_MIN_CLONE_TOKENS)
+    }
+
     pub fn spec(&self, id: LandSpecId) -> &Spec {
         &self.specs[id.spec_id().into()]
     }
@@ -68,6 +112,21 @@ impl Land {
         &self.rulesets[id.into()]
     }
 
+    /// Recompile a ruleset of the land from an updated stem.
+    pub fn replace_ruleset(&mut self, id: RuleSetId, stem: &RuleSetStem) -> anyhow::Result<()> {
+        let sylva = self
+            .sylva_rules
+            .iter()
+            .find(|(_, rulesets)| rulesets.contains(&id))
+            .map(|(&sylva, _)| sylva)
+            .ok_or_else(|| anyhow!("Ruleset {} is not applied to any sylva", stem.id))?;
+
+        let ruleset = RuleSet::from_stem(self.sylva_spec(sylva), stem)?;
+        self.rulesets[id.into()] = ruleset;
+
+        Ok(())
+    }
+
     pub fn sylva_rulesets(&self, id: SylvaId) -> HashSet<&RuleSet> {
         self.sylva_rules
             .get(&id)
@@ -85,6 +144,11 @@ impl Land {
         *self.sylva_spec.get(&id).unwrap()
     }
 
+    /// Root directory of the project of the sylva, if it belongs to a project.
+    pub fn sylva_root(&self, id: SylvaId) -> Option<&Path> {
+        self.sylva_roots.get(&id).map(PathBuf::as_path)
+    }
+
     pub fn sylvae(&'_ self) -> impl '_ + Iterator<Item = SylvaId> {
         self.sylvae.ids().map(Into::into)
     }
@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    land::{
+        sylva::{SylvaId, SylvaTreeId},
+        Land,
+    },
+    query::{RawTreeInfoBuilder, SylvaNode, TreeInfoBuilder},
+    script::python::PythonScriptEngine,
+    semantic::{
+        calls::{sylva_calls, CallGraph},
+        names::SylvaScopes,
+    },
+    util::cancel::CancellationToken,
+};
+
+/// Analysis whose results are shared by all the rules of a sylva.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisPass {
+    /// Scope graph used for name resolution (`referenced_decls`).
+    ScopeGraph,
+    /// Duplicated regions of the sylva (`clones`).
+    Clones,
+    /// Calls between the declarations of the sylva (`callers`, `callees`).
+    CallGraph,
+}
+
+impl AnalysisPass {
+    /// Passes that must be run before this one.
+    pub fn dependencies(self) -> &'static [AnalysisPass] {
+        match self {
+            AnalysisPass::ScopeGraph | AnalysisPass::Clones => &[],
+            AnalysisPass::CallGraph => &[AnalysisPass::ScopeGraph],
+        }
+    }
+
+    fn run(self, land: &Land, sylva_id: SylvaId, cancel: &CancellationToken) -> anyhow::Result<()> {
+        match self {
+            AnalysisPass::ScopeGraph => run_scope_graph_pass(land, sylva_id, cancel),
+            AnalysisPass::Clones => {
+                land.sylva_clones(sylva_id);
+                Ok(())
+            }
+            AnalysisPass::CallGraph => run_call_graph_pass(land, sylva_id),
+        }
+    }
+}
+
+impl Display for AnalysisPass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AnalysisPass::ScopeGraph => "scope_graph",
+            AnalysisPass::Clones => "clones",
+            AnalysisPass::CallGraph => "call_graph",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// Return the given passes and their dependencies, ordered so that every pass comes after its
+/// dependencies.
+pub fn schedule_passes(passes: impl IntoIterator<Item = AnalysisPass>) -> Vec<AnalysisPass> {
+    let mut scheduled = vec![];
+    let mut visited = BTreeSet::new();
+
+    for pass in passes {
+        visit_pass(pass, &mut visited, &mut scheduled);
+    }
+
+    scheduled
+}
+
+fn visit_pass(
+    pass: AnalysisPass,
+    visited: &mut BTreeSet<AnalysisPass>,
+    scheduled: &mut Vec<AnalysisPass>,
+) {
+    if !visited.insert(pass) {
+        return;
+    }
+
+    for &dependency in pass.dependencies() {
+        visit_pass(dependency, visited, scheduled);
+    }
+
+    scheduled.push(pass);
+}
+
+/// Run the given passes (and their dependencies) once, in topological order.
+pub fn run_passes(
+    land: &Land,
+    sylva_id: SylvaId,
+    passes: impl IntoIterator<Item = AnalysisPass>,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    for pass in schedule_passes(passes) {
+        cancel.check()?;
+        pass.run(land, sylva_id, cancel)?;
+    }
+
+    Ok(())
+}
+
+fn run_scope_graph_pass(
+    land: &Land,
+    sylva_id: SylvaId,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    let sylva = land.sylva(sylva_id);
+    let spec = land.sylva_spec(sylva_id);
+    let mut scopes = land.sylva_scopes_mut(sylva_id);
+    let info_builder = RawTreeInfoBuilder::new(spec, sylva);
+
+    let trees = sylva
+        .iter()
+        .filter(|(tree_id, _)| !scopes.is_computed(*tree_id))
+        .filter_map(|(tree_id, _)| {
+            let node = sylva.tree(tree_id)?.nodes().next()?;
+            let sylva_node = SylvaNode {
+                sylva: sylva_id,
+                tree: tree_id,
+                node,
+            };
+
+            Some((sylva_node, info_builder.info_for_node(sylva_node)))
+        })
+        .collect();
+
+    if scopes.compute_projected_graphs(
+        trees,
+        land,
+        &spec.aspects,
+        PythonScriptEngine::default(),
+        cancel,
+    )? {
+        return Ok(());
+    }
+
+    for (tree_id, _) in sylva.iter() {
+        compute_tree_scopes(land, sylva_id, tree_id, &mut scopes, cancel)?;
+    }
+
+    Ok(())
+}
+
+/// Index the calls of the sylva, whose scope graph has been computed by the scope graph pass.
+fn run_call_graph_pass(land: &Land, sylva_id: SylvaId) -> anyhow::Result<()> {
+    if land.sylva_call_graph(sylva_id).is_some() {
+        return Ok(());
+    }
+
+    let mut scopes = land.sylva_scopes_mut(sylva_id);
+    let calls = sylva_calls(land, sylva_id, scopes.graph()?);
+    land.set_sylva_call_graph(sylva_id, CallGraph::new(&calls));
+
+    Ok(())
+}
+
+/// Add the scopes of the given tree to the scope graph of its sylva, unless already computed.
+pub fn compute_tree_scopes(
+    land: &Land,
+    sylva_id: SylvaId,
+    tree_id: SylvaTreeId,
+    scopes: &mut SylvaScopes,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    let sylva = land.sylva(sylva_id);
+    let spec = land.sylva_spec(sylva_id);
+
+    let Some(node) = sylva.tree(tree_id).and_then(|tree| tree.nodes().next()) else {
+        return Ok(());
+    };
+
+    if scopes.is_computed(tree_id) {
+        return Ok(());
+    }
+
+    let sylva_node = SylvaNode {
+        sylva: sylva_id,
+        tree: tree_id,
+        node,
+    };
+
+    scopes.compute_tree_graph(
+        sylva_node,
+        land,
+        &spec.aspects,
+        RawTreeInfoBuilder::new(spec, sylva).info_for_node(sylva_node),
+        PythonScriptEngine::default(),
+        cancel,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_deduplicates_passes() {
+        assert_eq!(
+            schedule_passes([AnalysisPass::ScopeGraph, AnalysisPass::ScopeGraph]),
+            vec![AnalysisPass::ScopeGraph]
+        );
+    }
+
+    #[test]
+    fn schedule_dependencies_first() {
+        assert_eq!(
+            schedule_passes([AnalysisPass::CallGraph, AnalysisPass::ScopeGraph]),
+            vec![AnalysisPass::ScopeGraph, AnalysisPass::CallGraph]
+        );
+        assert_eq!(AnalysisPass::CallGraph.to_string(), "call_graph");
+    }
+}
@@ -1,26 +1,46 @@
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{self, AtomicBool, AtomicUsize},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail, Context};
 use id_vec::Id;
+use log::Progress;
 use rayon::prelude::*;
+use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use sylver_dsl::sylq::parse_query;
+use sylver_dsl::sylq::{parse_predicate_def, parse_query, PredicateDef};
 
 use crate::{
-    core::spec::Spec,
+    core::spec::{KindId, Spec},
     id_type,
-    land::{sylva::SylvaId, Land},
+    land::{
+        cache::{is_file_local, subtree_fingerprint, subtree_hashes, CacheKey, RuleCache},
+        message::MessageTemplate,
+        passes::AnalysisPass,
+        sylva::{Sylva, SylvaId, SylvaTreeId},
+        Land,
+    },
     query::{
         eval_predicate,
         expr::{EvalCtx, EvalError, Expr},
-        language::compile::compile,
-        sylva_nodes, RawTreeInfoBuilder, SylvaNode,
+        language::{
+            compile::{candidate_kinds, compile_with_renames, BUILTIN_FUNCTIONS},
+            typecheck::check_query,
+        },
+        memo::EvalMemo,
+        RawTreeInfoBuilder, SylvaNode,
     },
     script::python::PythonScriptEngine,
     specs::stem::ruleset::{RuleSetStem, RuleStem},
+    tree::NodeId,
+    util::cancel::CancellationToken,
 };
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
@@ -33,81 +53,546 @@ pub enum RuleCategory {
     Error,
 }
 
+/// Arbitrary structured data attached to the findings of a rule (ticket ids, component names,
+/// CWE ids...).
+#[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FindingMetadata(BTreeMap<String, MetadataValue>);
+
+impl FindingMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&MetadataValue> {
+        self.0.get(key)
+    }
+}
+
+/// JSON value of a finding metadata. Unlike `serde_json::Value`, it is totally ordered and
+/// hashable, and the keys of its objects are sorted.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetadataValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(MetadataFloat),
+    String(String),
+    Array(Vec<MetadataValue>),
+    Object(BTreeMap<String, MetadataValue>),
+}
+
+/// Floating point number compared with `f64::total_cmp`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MetadataFloat(pub f64);
+
+impl PartialEq for MetadataFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MetadataFloat {}
+
+impl PartialOrd for MetadataFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MetadataFloat {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Hash for MetadataFloat {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
+
+/// Standard classifications of the weakness detected by a rule.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleTaxonomy {
+    /// Common Weakness Enumeration id, e.g. `89` for CWE-89.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwe: Option<u32>,
+    /// OWASP Top 10 category, e.g. `A03:2021`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owasp: Option<String>,
+}
+
+impl RuleTaxonomy {
+    pub fn is_empty(&self) -> bool {
+        self == &RuleTaxonomy::default()
+    }
+}
+
+/// Conditions under which the evaluation of the rules stops before completion.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct StopCondition {
+    /// Stop at the first finding with the `Error` category.
+    pub fail_fast: bool,
+    /// Stop once this number of findings has been reached.
+    pub max_findings: Option<usize>,
+}
+
+impl StopCondition {
+    /// Return true if the evaluation should stop after the `findings`-th finding, of the given
+    /// category.
+    pub fn is_met(&self, findings: usize, category: RuleCategory) -> bool {
+        (self.fail_fast && category == RuleCategory::Error)
+            || self.max_findings.map_or(false, |max| findings >= max)
+    }
+}
+
+/// Findings that are already known, such as the ones accepted in a baseline. They are left out of
+/// the results and don't count towards the stop condition.
+pub trait KnownFindings: Sync {
+    /// Return true if the match of the rule on the node is a known finding. Called once for each
+    /// match, in evaluation order for a given rule.
+    fn is_known(&self, land: &Land, ruleset: RuleSetId, rule: &str, node: SylvaNode) -> bool;
+}
+
+/// Findings count shared by the concurrent evaluations of the rules.
+pub struct FindingsBudget<'k> {
+    condition: StopCondition,
+    findings: AtomicUsize,
+    exhausted: AtomicBool,
+    cancel: CancellationToken,
+    known: Option<&'k dyn KnownFindings>,
+}
+
+impl<'k> FindingsBudget<'k> {
+    pub fn new(
+        condition: StopCondition,
+        cancel: CancellationToken,
+        known: Option<&'k dyn KnownFindings>,
+    ) -> FindingsBudget<'k> {
+        FindingsBudget {
+            condition,
+            findings: AtomicUsize::new(0),
+            exhausted: AtomicBool::new(condition.max_findings == Some(0)),
+            cancel,
+            known,
+        }
+    }
+
+    /// Return true if the match is a known finding, which must not be reported.
+    pub fn is_known(&self, land: &Land, ruleset: RuleSetId, rule: &str, node: SylvaNode) -> bool {
+        self.known
+            .map_or(false, |k| k.is_known(land, ruleset, rule, node))
+    }
+
+    pub fn record(&self, category: RuleCategory) {
+        let findings = self.findings.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+
+        if self.condition.is_met(findings, category) {
+            self.exhausted.store(true, atomic::Ordering::SeqCst);
+        }
+    }
+
+    /// Return true if the evaluation should stop, either because the stop condition has been met
+    /// or because it was cancelled.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(atomic::Ordering::SeqCst) || self.cancel.is_cancelled()
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+}
+
+/// Matches of the rules required by other rules, read by the `matched_by` function.
+pub type RuleAnnotations = HashMap<String, HashSet<SylvaNode>>;
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Rule {
     predicate: Expr,
+    /// Fingerprint under which the results are cached, if the predicate only depends on the
+    /// subtree of the candidate node.
+    cache_key: Option<u64>,
+    /// Kinds of the nodes that can match the predicate (`None` if any kind can match).
+    kinds: Option<BTreeSet<KindId>>,
+    /// Message as written in the ruleset, placeholders included.
     pub message: String,
+    message_template: MessageTemplate,
+    /// Replacement of the text of the matched nodes.
+    fix_template: Option<MessageTemplate>,
     pub category: RuleCategory,
     pub note: Option<String>,
+    pub doc_url: Option<String>,
+    pub requires: Vec<AnalysisPass>,
+    /// Rules of the ruleset evaluated before this one, whose matches the query reads.
+    pub required_rules: Vec<String>,
+    pub metadata: FindingMetadata,
+    pub taxonomy: RuleTaxonomy,
+    /// Renamed kinds that the query refers to by their former name, with their new name.
+    pub renamed_kinds: BTreeMap<String, String>,
+    pub deprecated: bool,
+    /// Id of the rule superseding this one.
+    pub replaced_by: Option<String>,
 }
 
 impl Rule {
-    fn from_stem(spec: &Spec, stem: &RuleStem) -> anyhow::Result<Rule> {
+    fn from_stem(
+        spec: &Spec,
+        predicates: &BTreeMap<String, PredicateDef>,
+        stem: &RuleStem,
+    ) -> anyhow::Result<Rule> {
         let query_ast = parse_query(&stem.query)?;
+        let (predicate, renamed_kinds) = compile_with_renames(spec, predicates, &query_ast)?;
+        check_query(spec, &stem.query, &query_ast)
+            .with_context(|| format!("Invalid query for rule {}", stem.id))?;
 
         Ok(Rule {
             message: stem.message.clone(),
-            predicate: compile(spec, &query_ast)?,
+            message_template: MessageTemplate::compile(spec, predicates, &query_ast, &stem.message)
+                .with_context(|| format!("Invalid message for rule {}", stem.id))?,
+            fix_template: stem
+                .fix
+                .as_ref()
+                .map(|fix| MessageTemplate::compile(spec, predicates, &query_ast, fix))
+                .transpose()
+                .with_context(|| format!("Invalid fix for rule {}", stem.id))?,
+            cache_key: subtree_fingerprint(&predicate),
+            predicate,
+            kinds: candidate_kinds(spec, &query_ast)?,
             category: stem.category,
             note: stem.note.clone(),
+            doc_url: stem.doc_url.clone(),
+            requires: stem.required_passes().collect(),
+            required_rules: stem.required_rules().map(str::to_string).collect(),
+            metadata: stem.metadata.clone(),
+            taxonomy: stem.taxonomy.clone(),
+            renamed_kinds,
+            deprecated: stem.is_deprecated(),
+            replaced_by: stem.replaced_by.clone(),
         })
     }
+
+    /// Return the message reported for the given match, or the raw message if a placeholder
+    /// cannot be evaluated. Such matches are also reported as failures of the rule by `verify`.
+    pub fn render_message(&self, land: &Land, node: SylvaNode) -> String {
+        self.message_template
+            .render(land, node)
+            .unwrap_or_else(|_| self.message.clone())
+    }
+
+    /// Return the replacement of the text of the given match, if the rule has a fix.
+    pub fn render_fix(&self, land: &Land, node: SylvaNode) -> Option<Result<String, EvalError>> {
+        self.fix_template
+            .as_ref()
+            .map(|template| template.render(land, node))
+    }
+
+    /// Return false if the predicate cannot match a node of any of the given kinds.
+    fn may_match<'k>(&self, mut kinds: impl Iterator<Item = &'k KindId>) -> bool {
+        match &self.kinds {
+            Some(rule_kinds) => kinds.any(|k| rule_kinds.contains(k)),
+            None => true,
+        }
+    }
 }
 
 id_type!(RuleSetId: RuleSet);
 
+/// Kinds of the nodes of each tree of a sylva, used to skip the trees where a rule can't match.
+pub type KindIndex = HashMap<SylvaTreeId, HashSet<KindId>>;
+
+pub fn kind_index(sylva: &Sylva) -> KindIndex {
+    sylva
+        .iter()
+        .map(|(tree_id, tree)| (tree_id, tree.kinds()))
+        .collect()
+}
+
+/// Outcome of the evaluation of a rule on a sylva.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RuleVerification {
+    pub matches: HashSet<SylvaNode>,
+    /// Nodes on which the evaluation of the rule failed. The evaluation continues with the
+    /// following nodes.
+    pub errors: Vec<(SylvaNode, EvalError)>,
+    pub duration: Duration,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct RuleSet {
+    id: String,
     rules: BTreeMap<String, Rule>,
+    /// Rules grouped so that the rules of a wave only require rules of the previous waves.
+    waves: Vec<Vec<String>>,
+    /// Rules required by other rules, whose matches are recorded as annotations.
+    required: BTreeSet<String>,
 }
 
 impl RuleSet {
-    pub fn new(rules: BTreeMap<String, Rule>) -> RuleSet {
-        RuleSet { rules }
+    pub fn new(id: String, rules: BTreeMap<String, Rule>) -> anyhow::Result<RuleSet> {
+        let mut required = BTreeSet::new();
+
+        for (rule_id, rule) in &rules {
+            for dep in &rule.required_rules {
+                if !rules.contains_key(dep) {
+                    bail!("Rule {rule_id} requires unknown rule {dep} in ruleset {id}");
+                }
+                required.insert(dep.clone());
+            }
+        }
+
+        let mut waves: Vec<Vec<String>> = vec![];
+        let mut scheduled: HashSet<&str> = HashSet::new();
+
+        while scheduled.len() < rules.len() {
+            let wave: Vec<&str> = rules
+                .iter()
+                .filter(|(rule_id, rule)| {
+                    !scheduled.contains(rule_id.as_str())
+                        && rule
+                            .required_rules
+                            .iter()
+                            .all(|dep| scheduled.contains(dep.as_str()))
+                })
+                .map(|(rule_id, _)| rule_id.as_str())
+                .collect();
+
+            if wave.is_empty() {
+                let remaining: Vec<&str> = rules
+                    .keys()
+                    .map(String::as_str)
+                    .filter(|rule_id| !scheduled.contains(rule_id))
+                    .collect();
+                bail!("Rules {} require each other", remaining.join(", "));
+            }
+
+            scheduled.extend(&wave);
+            waves.push(wave.into_iter().map(str::to_string).collect());
+        }
+
+        Ok(RuleSet {
+            id,
+            rules,
+            waves,
+            required,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
     }
 
+    /// Renamed kinds that the rules refer to by their former name, as
+    /// `(rule id, former name, new name)`.
+    pub fn renamed_kinds(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.rules.iter().flat_map(|(id, rule)| {
+            rule.renamed_kinds
+                .iter()
+                .map(move |(from, to)| (id.as_str(), from.as_str(), to.as_str()))
+        })
+    }
+
+    /// Deprecated rules, with the id of the rule replacing them.
+    pub fn deprecated_rules(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.rules
+            .iter()
+            .filter(|(_, rule)| rule.deprecated)
+            .map(|(id, rule)| (id.as_str(), rule.replaced_by.as_deref()))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn verify<'b>(
         &self,
         builder: RawTreeInfoBuilder<'b>,
         land: &'b Land,
+        ruleset_id: RuleSetId,
         sylva_id: SylvaId,
-    ) -> anyhow::Result<HashMap<String, HashSet<SylvaNode>>> {
-        let mut rule_matches: HashMap<String, HashSet<SylvaNode>> = HashMap::new();
-
-        let evaluation_results: Vec<Result<Vec<(String, SylvaNode)>, EvalError>> = self
-            .rules
-            .par_iter()
-            .map(|(name, rule)| {
-                let spec = land.sylva_spec(sylva_id);
-                let mut ctx =
-                    EvalCtx::new(spec, builder.clone(), land, PythonScriptEngine::default());
-                let mut results = Vec::new();
-
-                for sylva_node in sylva_nodes(land, sylva_id) {
-                    let is_match = eval_predicate(&mut ctx, sylva_node, &rule.predicate)?;
-
-                    if is_match {
-                        results.push((name.clone(), sylva_node));
+        kind_index: &KindIndex,
+        budget: &FindingsBudget,
+        memo: &'b EvalMemo<'b>,
+        cache: Option<&RuleCache>,
+        progress: &dyn Progress,
+    ) -> anyhow::Result<HashMap<String, RuleVerification>> {
+        let sylva = land.sylva(sylva_id);
+
+        let subtree_index: HashMap<SylvaTreeId, _> =
+            if cache.is_some() && self.rules.values().any(|r| r.cache_key.is_some()) {
+                sylva
+                    .iter()
+                    .map(|(tree_id, tree)| (tree_id, subtree_hashes(&tree.tree)))
+                    .collect()
+            } else {
+                HashMap::new()
+            };
+
+        let mut annotations = Arc::new(RuleAnnotations::new());
+        let mut verifications = HashMap::new();
+
+        for wave in &self.waves {
+            let evaluation_results: Vec<Result<_, EvalError>> = wave
+                .par_iter()
+                .map(|name| {
+                    let rule = &self.rules[name];
+                    self.verify_rule(
+                        builder.clone(),
+                        land,
+                        ruleset_id,
+                        sylva_id,
+                        name,
+                        rule,
+                        kind_index,
+                        &subtree_index,
+                        budget,
+                        memo,
+                        cache,
+                        annotations.clone(),
+                    )
+                })
+                .collect();
+
+            for result in evaluation_results {
+                let (name, verification, matched) = result?;
+                progress.inc(1);
+
+                if self.required.contains(&name) {
+                    Arc::make_mut(&mut annotations).insert(name.clone(), matched);
+                }
+
+                verifications.insert(name, verification);
+            }
+        }
+
+        Ok(verifications)
+    }
+
+    /// Evaluate a rule on every candidate node of a sylva. Return the verification, and every
+    /// matched node (including the known findings) if the rule is required by other rules.
+    #[allow(clippy::too_many_arguments)]
+    fn verify_rule<'b>(
+        &self,
+        builder: RawTreeInfoBuilder<'b>,
+        land: &'b Land,
+        ruleset_id: RuleSetId,
+        sylva_id: SylvaId,
+        name: &str,
+        rule: &Rule,
+        kind_index: &KindIndex,
+        subtree_index: &HashMap<SylvaTreeId, FxHashMap<NodeId, u64>>,
+        budget: &FindingsBudget,
+        memo: &'b EvalMemo<'b>,
+        cache: Option<&RuleCache>,
+        annotations: Arc<RuleAnnotations>,
+    ) -> Result<(String, RuleVerification, HashSet<SylvaNode>), EvalError> {
+        let sylva = land.sylva(sylva_id);
+        let spec_id = land.sylva_spec_id(sylva_id);
+        let is_required = self.required.contains(name);
+
+        let start = Instant::now();
+        let spec = land.sylva_spec(sylva_id);
+        let mut ctx = EvalCtx::new(spec, builder, land, PythonScriptEngine::default())
+            .with_cancellation(budget.cancellation().clone())
+            .with_memo(memo)
+            .with_annotations(annotations);
+        let mut matches = HashSet::new();
+        let mut matched_nodes = HashSet::new();
+        let mut errors = vec![];
+
+        'trees: for (tree_id, tree) in sylva.iter() {
+            if !rule.may_match(kind_index[&tree_id].iter()) {
+                continue;
+            }
+
+            for node in tree.nodes() {
+                if budget.is_exhausted() {
+                    break 'trees;
+                }
+
+                if !rule.may_match(std::iter::once(&tree.tree[node].kind)) {
+                    continue;
+                }
+
+                let sylva_node = SylvaNode {
+                    sylva: sylva_id,
+                    tree: tree_id,
+                    node,
+                };
+
+                let cache_key = cache.and(rule.cache_key).map(|predicate| CacheKey {
+                    sylva: sylva_id,
+                    spec: spec_id,
+                    predicate,
+                    subtree: subtree_index[&tree_id][&node],
+                });
+
+                let matched = match cache.zip(cache_key).and_then(|(c, k)| c.get(k)) {
+                    Some(matched) => matched,
+                    None => {
+                        let matched = match eval_predicate(&mut ctx, sylva_node, &rule.predicate) {
+                            Ok(matched) => matched,
+                            Err(EvalError::Cancelled) => return Err(EvalError::Cancelled),
+                            Err(e) => {
+                                errors.push((sylva_node, e));
+                                continue;
+                            }
+                        };
+
+                        if let Some((cache, key)) = cache.zip(cache_key) {
+                            cache.insert(key, matched);
+                        }
+
+                        matched
                     }
+                };
+
+                if matched && is_required {
+                    matched_nodes.insert(sylva_node);
                 }
 
-                Ok(results)
-            })
-            .collect();
+                if matched && !budget.is_known(land, ruleset_id, name, sylva_node) {
+                    budget.record(rule.category);
+                    matches.insert(sylva_node);
+
+                    if rule.message_template.has_placeholders() {
+                        if let Err(e) = rule.message_template.render(land, sylva_node) {
+                            errors.push((sylva_node, e));
+                        }
+                    }
+                }
+            }
+        }
+
+        let verification = RuleVerification {
+            matches,
+            errors,
+            duration: start.elapsed(),
+        };
 
-        evaluation_results
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .flatten()
-            .for_each(|(rule_name, sylva_node)| {
-                rule_matches
-                    .entry(rule_name)
-                    .or_default()
-                    .insert(sylva_node);
-            });
+        Ok((name.to_string(), verification, matched_nodes))
+    }
+
+    /// Return whether the findings of every rule on a file only depend on that file.
+    pub fn is_file_local(&self) -> bool {
+        self.rules.values().all(|r| is_file_local(&r.predicate))
+    }
+
+    /// Return the analysis passes required by the rules of this ruleset.
+    pub fn required_passes(&self) -> BTreeSet<AnalysisPass> {
+        self.rules
+            .values()
+            .flat_map(|r| r.requires.iter().copied())
+            .collect()
+    }
 
-        Ok(rule_matches)
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
     }
 
     pub fn get_rule(&self, rule_id: &str) -> Option<&Rule> {
@@ -115,15 +600,98 @@ impl RuleSet {
     }
 
     pub fn from_stem(spec: &Spec, stem: &RuleSetStem) -> anyhow::Result<RuleSet> {
+        let mut predicates = BTreeMap::new();
+
+        for predicate_code in &stem.predicates {
+            let predicate = parse_predicate_def(predicate_code)?;
+            if BUILTIN_FUNCTIONS.contains(&predicate.name.as_str()) {
+                return Err(anyhow!(
+                    "Predicate {} has the name of a builtin function",
+                    predicate.name
+                ));
+            }
+            if let Some(previous) = predicates.insert(predicate.name.clone(), predicate) {
+                return Err(anyhow!(
+                    "Predicate {} is defined multiple times",
+                    previous.name
+                ));
+            }
+        }
+
         let mut rules = BTreeMap::new();
 
         for rule_stem in &stem.rules {
-            let rule = Rule::from_stem(spec, rule_stem)?;
+            let rule = Rule::from_stem(spec, &predicates, rule_stem)?;
             if rules.insert(rule_stem.id.clone(), rule).is_some() {
                 return Err(anyhow!("Rule {} is defined multiple times", rule_stem.id));
             }
         }
 
-        Ok(RuleSet { rules })
+        RuleSet::new(stem.id.clone(), rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        core::spec::test::parse_spec, specs::stem::ruleset::RuleSetStem,
+        util::cancel::CancellationToken,
+    };
+
+    use super::{FindingsBudget, RuleCategory, RuleSet, StopCondition};
+
+    fn ruleset_stem(predicate: &str) -> RuleSetStem {
+        let stem = format!(
+            indoc!(
+                "
+                id: myRuleSet
+                language: dir/language.syl
+                predicates:
+                    - '{}'
+                rules:
+                    - id: rule1Id
+                      message: Rule 1 message
+                      query: match NodeKind n when is_leaf(n)
+                      category: bug
+                "
+            ),
+            predicate
+        );
+
+        serde_yaml::from_str(&stem).unwrap()
+    }
+
+    #[test]
+    fn ruleset_predicates() {
+        let spec = parse_spec("node NodeKind {}");
+
+        let ruleset = RuleSet::from_stem(&spec, &ruleset_stem("def is_leaf(n) = n.parent == null"));
+        assert!(ruleset.is_ok());
+
+        let err = RuleSet::from_stem(&spec, &ruleset_stem("def text(n) = n.parent == null"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Predicate text has the name of a builtin function"
+        );
+    }
+
+    #[test]
+    fn fail_fast_budget() {
+        let stop = StopCondition {
+            fail_fast: true,
+            max_findings: None,
+        };
+        let budget = FindingsBudget::new(stop, CancellationToken::default(), None);
+
+        budget.record(RuleCategory::Smell);
+        budget.record(RuleCategory::Bug);
+        assert!(!budget.is_exhausted());
+
+        budget.record(RuleCategory::Error);
+        assert!(budget.is_exhausted());
     }
 }
@@ -1,22 +1,28 @@
 use std::{
     cmp::Ordering,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use id_vec::{Id, IdVec};
+use log::Progress;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     builtin_langs::parser::BuiltinParserRunner,
     core::{
         source::{Source, SourceTree},
-        spec::Spec,
+        spec::{KindId, Spec},
+        store::ContentStore,
     },
     id_type,
     parsing::parser_runner::{ParserRunner, ParsingResult},
     report::Report,
     tree::NodeId,
+    util::cancel::CancellationToken,
 };
 
 id_type! { SylvaId: Sylva }
@@ -31,10 +37,117 @@ pub enum SylvaParser<'s> {
 }
 
 impl<'s> SylvaParser<'s> {
-    fn run(&self, source: Source) -> ParsingResult {
-        match self {
+    fn run(&self, source: Source, limits: &ParseLimits) -> Result<ParsingResult, SkipReason> {
+        let start = Instant::now();
+
+        let res = match self {
             SylvaParser::Custom(p) => p.run_once(source),
-            SylvaParser::Builtin(p) => p.run(source),
+            SylvaParser::Builtin(p) => p
+                .run_with_timeout(source, limits.max_parse_time())
+                .ok_or(SkipReason::ParseTime)?,
+        };
+
+        // Custom parsers can't be interrupted, so their parse time is only checked afterwards.
+        if limits
+            .max_parse_time()
+            .map_or(false, |max| start.elapsed() > max)
+        {
+            return Err(SkipReason::ParseTime);
+        }
+
+        Ok(res)
+    }
+
+    /// Parse the source, or fetch its tree from the store if the parser has a key.
+    fn parse(
+        &self,
+        source: Source,
+        limits: &ParseLimits,
+        store: Option<(&ContentStore, u64)>,
+    ) -> Result<ParsingResult, SkipReason> {
+        if let Some(error) = source.notebook_cells().and_then(|cells| cells.error()) {
+            return Err(SkipReason::InvalidNotebook(error.to_string()));
+        }
+
+        let res = match (self, store) {
+            // The tree of the previous content of the file is reused for the regions that were
+            // not edited.
+            (SylvaParser::Builtin(p), Some((store, parser_key))) => {
+                store.parse_incremental(parser_key, source, |s, previous| {
+                    p.run_incremental(s, previous, limits.max_parse_time())
+                        .ok_or(SkipReason::ParseTime)
+                })?
+            }
+            (_, Some((store, parser_key))) => {
+                store.parse(parser_key, source, |s| self.run(s, limits))?
+            }
+            (_, None) => self.run(source, limits)?,
+        };
+
+        match limits.max_nodes {
+            Some(max) if res.tree.tree.node_count() > max => {
+                Err(SkipReason::NodeCount(res.tree.tree.node_count()))
+            }
+            _ => Ok(res),
+        }
+    }
+}
+
+/// Limits above which a file is skipped rather than analyzed, to protect the analysis from
+/// pathological inputs such as minified or generated code.
+#[derive(
+    Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize,
+)]
+#[serde(deny_unknown_fields)]
+pub struct ParseLimits {
+    /// Maximum time spent parsing a file, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_parse_time_ms: Option<u64>,
+    /// Maximum number of nodes in the tree of a file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_nodes: Option<usize>,
+}
+
+impl ParseLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self == &ParseLimits::default()
+    }
+
+    fn max_parse_time(&self) -> Option<Duration> {
+        self.max_parse_time_ms.map(Duration::from_millis)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum SkipReason {
+    ParseTime,
+    NodeCount(usize),
+    InvalidNotebook(String),
+}
+
+/// File that was not analyzed because it exceeded the parse limits or could not be read.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+impl Display for SkippedFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.reason {
+            SkipReason::ParseTime => write!(
+                f,
+                "Skipped {}: parsing exceeded the time limit",
+                self.path.display()
+            ),
+            SkipReason::NodeCount(count) => write!(
+                f,
+                "Skipped {}: its tree has {count} nodes, above the limit",
+                self.path.display()
+            ),
+            SkipReason::InvalidNotebook(error) => {
+                write!(f, "Skipped {}: {error}", self.path.display())
+            }
         }
     }
 }
@@ -50,6 +163,11 @@ impl SylvaTree {
     pub fn nodes(&'_ self) -> impl '_ + Iterator<Item = NodeId> {
         self.tree.tree.bottom_up_node_ids()
     }
+
+    /// Return the set of kinds of the nodes in this tree.
+    pub fn kinds(&self) -> HashSet<KindId> {
+        self.nodes().map(|n| self.tree[n].kind).collect()
+    }
 }
 
 /// Parse forest.
@@ -108,20 +226,70 @@ impl Sylva {
             .map(|tree| &tree.tree)
     }
 
+    /// Parse the given sources in parallel, reporting each parsed source to `progress`. Sources
+    /// that haven't been parsed yet are skipped once the token is cancelled.
     pub fn build_concurrently(
         parser: SylvaParser,
-        sources: impl IntoParallelIterator<Item = Source>,
+        sources: Vec<Source>,
+        cancel: &CancellationToken,
+        progress: &dyn Progress,
     ) -> anyhow::Result<Sylva> {
-        let parsing_results = sources
+        let (sylva, _) =
+            Sylva::build_with_limits(parser, sources, &ParseLimits::default(), cancel, progress)?;
+        Ok(sylva)
+    }
+
+    /// Parse the given sources in parallel, like `build_concurrently`. The files exceeding the
+    /// limits are left out of the sylva, and returned along with it.
+    pub fn build_with_limits(
+        parser: SylvaParser,
+        sources: Vec<Source>,
+        limits: &ParseLimits,
+        cancel: &CancellationToken,
+        progress: &dyn Progress,
+    ) -> anyhow::Result<(Sylva, Vec<SkippedFile>)> {
+        Sylva::build_stored(parser, None, sources, limits, cancel, progress)
+    }
+
+    /// Parse the given sources in parallel, like `build_with_limits`. When a store and the key
+    /// of the parser are given, the contents already parsed by the same parser are not parsed
+    /// again.
+    pub fn build_stored(
+        parser: SylvaParser,
+        store: Option<(&ContentStore, u64)>,
+        sources: Vec<Source>,
+        limits: &ParseLimits,
+        cancel: &CancellationToken,
+        progress: &dyn Progress,
+    ) -> anyhow::Result<(Sylva, Vec<SkippedFile>)> {
+        progress.set_length(sources.len() as u64);
+
+        let results: Vec<(PathBuf, Result<ParsingResult, SkipReason>)> = sources
             .into_par_iter()
+            .filter(|_| !cancel.is_cancelled())
             .map(|s| {
                 let path = s.path().into();
-                let res = parser.clone().run(s);
+                let res = parser.clone().parse(s, limits, store);
+                progress.inc(1);
                 (path, res)
             })
             .collect();
 
-        Ok(Sylva::new(parsing_results))
+        cancel.check()?;
+
+        let mut parsing_results = HashMap::new();
+        let mut skipped = vec![];
+
+        for (path, res) in results {
+            match res {
+                Ok(res) => {
+                    parsing_results.insert(path, res);
+                }
+                Err(reason) => skipped.push(SkippedFile { path, reason }),
+            }
+        }
+
+        Ok((Sylva::new(parsing_results), skipped))
     }
 
     pub fn build(
@@ -153,9 +321,12 @@ mod test {
     use once_cell::sync::Lazy;
 
     use crate::{
+        builtin_langs::{get_builtin_lang, BuiltinLang},
         core::{
+            notebook::NotebookCells,
             pos::{InclPosRange, Pos},
             source::test::create_test_source_tree,
+            spec::Syntax,
         },
         report::ReportKind,
     };
@@ -172,6 +343,7 @@ mod test {
         position: InclPosRange::new(Pos::new((1, 1), 0), Pos::new((1, 1), 0)).unwrap(),
         message: "".to_string(),
         note: None,
+        doc_url: None,
     });
 
     static TEST_SYLVA: Lazy<Sylva> = Lazy::new(|| {
@@ -262,6 +434,63 @@ mod test {
         );
     }
 
+    #[test]
+    fn skip_files_above_node_limit() {
+        let (mappings, lang, _) = get_builtin_lang(BuiltinLang::Python);
+        let syntax: Syntax = mappings.types.as_slice().into();
+        let parser = SylvaParser::Builtin(BuiltinParserRunner::new(lang, &syntax, mappings));
+
+        let sources = vec![
+            Source::inline("x = 1\n".to_string(), "small".to_string()),
+            Source::inline(
+                "x = [1, 2, 3, 4, 5, 6, 7, 8]\n".to_string(),
+                "large".to_string(),
+            ),
+        ];
+        let limits = ParseLimits {
+            max_nodes: Some(10),
+            ..Default::default()
+        };
+
+        let (sylva, skipped) =
+            Sylva::build_with_limits(parser, sources, &limits, &CancellationToken::default(), &())
+                .unwrap();
+
+        assert!(sylva.tree_from_path("small").is_some());
+        assert!(sylva.tree_from_path("large").is_none());
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, PathBuf::from("large"));
+        assert!(matches!(skipped[0].reason, SkipReason::NodeCount(_)));
+    }
+
+    #[test]
+    fn skip_invalid_notebooks() {
+        let (mappings, lang, _) = get_builtin_lang(BuiltinLang::Python);
+        let syntax: Syntax = mappings.types.as_slice().into();
+        let parser = SylvaParser::Builtin(BuiltinParserRunner::new(lang, &syntax, mappings));
+
+        let sources = vec![Source::notebook(
+            String::new(),
+            "broken.ipynb".into(),
+            NotebookCells::invalid("Invalid Jupyter notebook".to_string()),
+        )];
+
+        let (sylva, skipped) = Sylva::build_with_limits(
+            parser,
+            sources,
+            &ParseLimits::default(),
+            &CancellationToken::default(),
+            &(),
+        )
+        .unwrap();
+
+        assert!(sylva.tree_from_path("broken.ipynb").is_none());
+        assert_eq!(
+            skipped[0].to_string(),
+            "Skipped broken.ipynb: Invalid Jupyter notebook"
+        );
+    }
+
     #[allow(dead_code)]
     fn create_test_sylva(prefix: Option<&str>) -> Sylva {
         let prefix = prefix.unwrap_or_default();
@@ -274,6 +503,7 @@ mod test {
             position: InclPosRange::new(Pos::new((1, 1), 0), Pos::new((1, 1), 0)).unwrap(),
             message: "".to_string(),
             note: None,
+            doc_url: None,
         };
 
         Sylva::new(hashmap! {
@@ -1,5 +1,6 @@
 pub mod lexer_regex;
 pub mod parser_runner;
+pub mod roundtrip;
 pub mod sppf;
 
 mod grammar;
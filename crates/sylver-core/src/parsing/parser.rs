@@ -256,6 +256,7 @@ impl ParserError {
             ),
             position: InclPosRange::new(pos, pos).unwrap(),
             note: None,
+            doc_url: None,
         }
     }
 
@@ -280,6 +281,7 @@ impl ParserError {
                 ),
                 position: InclPosRange::new(pos, t.updated_pos).unwrap(),
                 note: None,
+                doc_url: None,
             })
             .collect()
     }
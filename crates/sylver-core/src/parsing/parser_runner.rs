@@ -2,6 +2,7 @@ use itertools::Itertools;
 
 use crate::{
     core::{
+        pos::{InclPosRange, Pos},
         source::{Source, SourceTree},
         spec::Syntax,
     },
@@ -46,16 +47,24 @@ impl<'s> ParserRunner<'s> {
         let mut parser = Parser::new(self.syntax, &self.table, &self.scanner, source.src());
         parser.parse();
 
-        let (tree, tokens) =
-            sppf_to_tree(self.syntax, &parser.tree).unwrap_or_else(|_| (Tree::new(), vec![]));
-
-        let reports = parser
+        let mut reports: Vec<Report> = parser
             .collect_errors()
             .iter()
             .flat_map(|e| e.to_reports(&parser, source.path()))
             .unique()
             .collect();
 
+        let (tree, tokens) = sppf_to_tree(self.syntax, &parser.tree).unwrap_or_else(|e| {
+            if reports.is_empty() {
+                reports.push(Report::parse_error(
+                    source.path(),
+                    InclPosRange::new(Pos::default(), Pos::default()).unwrap(),
+                    format!("Failed to build the syntax tree: {e}"),
+                ));
+            }
+            (Tree::new(), vec![])
+        });
+
         ParsingResult {
             tree: SourceTree::new(source, tokens, tree),
             reports,
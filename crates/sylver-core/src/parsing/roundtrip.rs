@@ -0,0 +1,627 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+use anyhow::{anyhow, bail, Context};
+use itertools::Itertools;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use sylver_dsl::meta::{
+    AltLevelTermExpr, AlternativeComp, CompExpr, Decl, RuleExpr, TermContent, TermExpr,
+};
+
+use crate::{
+    core::{
+        source::{Source, SourceTree},
+        spec::Syntax,
+    },
+    parsing::parser_runner::ParserRunner,
+    tree::NodeId,
+};
+
+/// Maximum number of repetitions of the unbounded regex operators (`*`, `+`...) in the generated
+/// tokens.
+const MAX_TOKEN_REPEAT: u32 = 3;
+
+/// Number of samples drawn for a token matching a regex before giving up on avoiding the literal
+/// terminals (keywords...) of the spec.
+const TOKEN_ATTEMPTS: usize = 10;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RoundtripConfig {
+    pub iterations: usize,
+    /// Depth after which the generated trees are completed as quickly as possible.
+    pub max_depth: usize,
+    pub seed: u64,
+}
+
+/// Generated text that doesn't parse back to the tree it was printed from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RoundtripFailure {
+    pub text: String,
+    pub reason: String,
+}
+
+impl Display for RoundtripFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n  input: {}", self.reason, self.text)
+    }
+}
+
+/// Generate random trees from the rules of the spec, print them, parse the printed text with the
+/// same rules and return the texts that don't yield the original tree.
+pub fn check_roundtrip(
+    syntax: &Syntax,
+    start_rule: &str,
+    config: &RoundtripConfig,
+) -> anyhow::Result<Vec<RoundtripFailure>> {
+    let parser = ParserRunner::new(start_rule, syntax)?;
+    let mut generator = TreeGenerator::new(syntax, config)?;
+    let mut failures = vec![];
+
+    for _ in 0..config.iterations {
+        let (expected, text) = generator.generate(start_rule)?;
+        let result = parser.run_once(Source::inline(text.clone(), "roundtrip".to_string()));
+
+        let reason = if let Some(report) = result.reports.first() {
+            Some(format!("Parse error: {}", report.message))
+        } else {
+            let parsed = Shape::from_tree(syntax, &result.tree);
+            (parsed != expected).then(|| format!("Expected {expected}, parsed {parsed}"))
+        };
+
+        if let Some(reason) = reason {
+            failures.push(RoundtripFailure { text, reason });
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Whether the two trees have the same nodes, fields and leaf tokens, regardless of the layout of
+/// their code.
+pub fn same_tree(syntax: &Syntax, tree: &SourceTree, other: &SourceTree) -> bool {
+    Shape::from_tree(syntax, tree) == Shape::from_tree(syntax, other)
+}
+
+/// Kinds and fields of a tree's nodes, without the list nodes (whose elements belong to the
+/// parent node), and the tokens of its leaves.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Shape {
+    kind: String,
+    field: Option<String>,
+    /// Text of the non-ignored tokens of a node without childs.
+    tokens: Vec<String>,
+    childs: Vec<Shape>,
+}
+
+impl Shape {
+    fn list(syntax: &Syntax, childs: Vec<Shape>) -> Shape {
+        Shape {
+            kind: syntax.kind_name(syntax.list_kind()).to_string(),
+            field: None,
+            tokens: vec![],
+            childs,
+        }
+    }
+
+    fn from_tree(syntax: &Syntax, tree: &SourceTree) -> Shape {
+        let mut roots = vec![];
+        Shape::collect(syntax, tree, tree.tree.root(), None, &mut roots);
+
+        match roots.len() {
+            1 => roots.pop().unwrap(),
+            _ => Shape::list(syntax, roots),
+        }
+    }
+
+    fn collect(
+        syntax: &Syntax,
+        tree: &SourceTree,
+        node_id: NodeId,
+        field: Option<&str>,
+        shapes: &mut Vec<Shape>,
+    ) {
+        let node = &tree[node_id];
+        let child_field = |n: usize| {
+            node.nth_child_field_pos(n)
+                .and_then(|pos| syntax.field_name(node.kind, pos))
+        };
+
+        if syntax.is_list_kind(node.kind) {
+            // The elements of a list belong to the field of the list.
+            for &child in &node.childs {
+                Shape::collect(syntax, tree, child, field, shapes);
+            }
+        } else {
+            let mut childs = vec![];
+
+            for (n, &child) in node.childs.iter().enumerate() {
+                Shape::collect(syntax, tree, child, child_field(n), &mut childs);
+            }
+
+            let tokens = if childs.is_empty() {
+                let ignore = syntax.ignore_tags();
+                tree.node_tokens(node_id, ignore)
+                    .iter()
+                    .filter(|t| !ignore.contains(&t.tag))
+                    .map(|&t| tree.code_between(t, t).to_string())
+                    .collect()
+            } else {
+                vec![]
+            };
+
+            shapes.push(Shape {
+                kind: syntax.kind_name(node.kind).to_string(),
+                field: field.map(str::to_string),
+                tokens,
+                childs,
+            });
+        }
+    }
+}
+
+impl Display for Shape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(field) = &self.field {
+            write!(f, "{field}@")?;
+        }
+
+        write!(f, "{}", self.kind)?;
+
+        if !self.tokens.is_empty() {
+            write!(f, "{:?}", self.tokens.join(" "))?;
+        }
+
+        if !self.childs.is_empty() {
+            write!(f, "({})", self.childs.iter().join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct TreeGenerator<'s> {
+    syntax: &'s Syntax,
+    max_depth: usize,
+    /// Height of the smallest tree derived from each rule, for the rules that derive a tree.
+    heights: HashMap<&'s str, usize>,
+    literals: Vec<&'s fancy_regex::Regex>,
+    samplers: HashMap<String, rand_regex::Regex>,
+    /// Printed between the tokens, when it is ignored by the spec.
+    separator: &'static str,
+    rng: StdRng,
+}
+
+impl<'s> TreeGenerator<'s> {
+    fn new(syntax: &'s Syntax, config: &RoundtripConfig) -> anyhow::Result<TreeGenerator<'s>> {
+        let literals = syntax
+            .terminals()
+            .filter_map(|(_, term)| match &term.reg {
+                TermContent::Literal(l) => Some(l),
+                _ => None,
+            })
+            .collect();
+
+        let separator = if syntax
+            .ignore_tags()
+            .iter()
+            .any(|&tag| full_match(syntax[tag].reg.regex(), " "))
+        {
+            " "
+        } else {
+            ""
+        };
+
+        Ok(TreeGenerator {
+            syntax,
+            max_depth: config.max_depth,
+            heights: min_heights(syntax),
+            literals,
+            samplers: HashMap::new(),
+            separator,
+            rng: StdRng::seed_from_u64(config.seed),
+        })
+    }
+
+    fn generate(&mut self, start_rule: &str) -> anyhow::Result<(Shape, String)> {
+        let mut shapes = vec![];
+        let mut tokens = vec![];
+        self.gen_rule(start_rule, 0, &mut shapes, &mut tokens)?;
+
+        let shape = match shapes.len() {
+            1 => shapes.pop().unwrap(),
+            _ => Shape::list(self.syntax, shapes),
+        };
+
+        Ok((shape, tokens.join(self.separator)))
+    }
+
+    fn gen_rule(
+        &mut self,
+        name: &str,
+        depth: usize,
+        shapes: &mut Vec<Shape>,
+        tokens: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        let syntax = self.syntax;
+        let rule = match syntax.decl_from_name(name) {
+            Some(Decl::Rule(rule)) => rule,
+            Some(Decl::Terminal(term)) => {
+                let token = self.gen_token(&term.reg)?;
+                tokens.push(token);
+                return Ok(());
+            }
+            _ => bail!("Unknown rule: {name}"),
+        };
+
+        let candidates: Vec<(&RuleExpr, usize)> = rule
+            .alternatives
+            .iter()
+            .filter_map(|alt| Some((alt, alternative_height(syntax, &self.heights, alt)?)))
+            .collect();
+
+        let alternative = if depth >= self.max_depth {
+            candidates.iter().min_by_key(|(_, height)| *height)
+        } else {
+            candidates.choose(&mut self.rng)
+        }
+        .map(|(alt, _)| *alt)
+        .ok_or_else(|| anyhow!("Rule {name} cannot derive a finite tree"))?;
+
+        match alternative {
+            RuleExpr::Ref(r) => self.gen_rule(&r.rule_name, depth + 1, shapes, tokens),
+            RuleExpr::Node(node) => {
+                if syntax.is_list_kind(syntax.existing_kind_id(&node.node_type)) {
+                    for comp in &node.comps {
+                        self.gen_comp(comp, depth + 1, shapes, tokens)?;
+                    }
+                } else {
+                    let mut childs = vec![];
+                    let first_token = tokens.len();
+
+                    for comp in &node.comps {
+                        self.gen_comp(comp, depth + 1, &mut childs, tokens)?;
+                    }
+
+                    let leaf_tokens = if childs.is_empty() {
+                        tokens[first_token..].to_vec()
+                    } else {
+                        vec![]
+                    };
+
+                    shapes.push(Shape {
+                        kind: node.node_type.clone(),
+                        field: None,
+                        tokens: leaf_tokens,
+                        childs,
+                    });
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn gen_comp(
+        &mut self,
+        comp: &AlternativeComp,
+        depth: usize,
+        shapes: &mut Vec<Shape>,
+        tokens: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        let (field, expr) = match comp {
+            AlternativeComp::RuleRef(name) => return self.gen_rule(name, depth, shapes, tokens),
+            AlternativeComp::TExpr(term) => {
+                let token = self.gen_term_expr(term)?;
+                tokens.push(token);
+                return Ok(());
+            }
+            AlternativeComp::Full(field, expr) => (field, expr),
+        };
+
+        let first_shape = shapes.len();
+        self.gen_comp_expr(expr, depth, shapes, tokens)?;
+
+        for shape in &mut shapes[first_shape..] {
+            shape.field = Some(field.clone());
+        }
+
+        Ok(())
+    }
+
+    fn gen_comp_expr(
+        &mut self,
+        expr: &CompExpr,
+        depth: usize,
+        shapes: &mut Vec<Shape>,
+        tokens: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        match expr {
+            CompExpr::Ref(name) => self.gen_rule(name, depth, shapes, tokens),
+            CompExpr::Opt(name) => {
+                if self.gen_repeat(depth, 0, 1) == 1 {
+                    self.gen_rule(name, depth, shapes, tokens)?;
+                }
+                Ok(())
+            }
+            CompExpr::Many(name) | CompExpr::Some(name) => {
+                let min = usize::from(matches!(expr, CompExpr::Some(_)));
+
+                for _ in 0..self.gen_repeat(depth, min, 3) {
+                    self.gen_rule(name, depth, shapes, tokens)?;
+                }
+                Ok(())
+            }
+            CompExpr::SepBy(data) => {
+                let count = self.gen_repeat(depth, usize::from(!data.allow_empty), 3);
+
+                for i in 0..count {
+                    if i > 0 {
+                        let separator = self.gen_term_expr(&data.term)?;
+                        tokens.push(separator);
+                    }
+                    self.gen_rule(&data.rule_name, depth, shapes, tokens)?;
+                }
+
+                if count > 0 && data.trailing && self.rng.gen_bool(0.5) {
+                    let separator = self.gen_term_expr(&data.term)?;
+                    tokens.push(separator);
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn gen_repeat(&mut self, depth: usize, min: usize, max: usize) -> usize {
+        if depth >= self.max_depth {
+            min
+        } else {
+            self.rng.gen_range(min..=max)
+        }
+    }
+
+    fn gen_term_expr(&mut self, term: &TermExpr) -> anyhow::Result<String> {
+        let syntax = self.syntax;
+        let term_content = |name: &str| {
+            syntax
+                .terminal(name)
+                .map(|t| &t.reg)
+                .ok_or_else(|| anyhow!("Unknown terminal: {name}"))
+        };
+
+        let content = match term {
+            TermExpr::Ref(name) => term_content(name)?,
+            TermExpr::Content(content) => content,
+            TermExpr::Alts(alts) => match alts
+                .choose(&mut self.rng)
+                .ok_or_else(|| anyhow!("Empty terminal alternatives"))?
+            {
+                AltLevelTermExpr::Ref(name) => term_content(name)?,
+                AltLevelTermExpr::Content(content) => content,
+            },
+        };
+
+        self.gen_token(content)
+    }
+
+    fn gen_token(&mut self, content: &TermContent) -> anyhow::Result<String> {
+        let pattern = match content {
+            TermContent::Nested(start, _) => {
+                bail!("Cannot generate nested terminal: {}", start.as_str())
+            }
+            TermContent::Regex(r) | TermContent::Literal(r) => r.as_str(),
+        };
+
+        if !self.samplers.contains_key(pattern) {
+            let sampler = rand_regex::Regex::compile(pattern, MAX_TOKEN_REPEAT)
+                .with_context(|| format!("Cannot generate tokens matching: {pattern}"))?;
+            self.samplers.insert(pattern.to_string(), sampler);
+        }
+
+        let sampler = &self.samplers[pattern];
+        let mut token: String = self.rng.sample(sampler);
+
+        if matches!(content, TermContent::Regex(_)) {
+            for _ in 1..TOKEN_ATTEMPTS {
+                if !self.literals.iter().any(|l| full_match(l, &token)) {
+                    break;
+                }
+                token = self.rng.sample(sampler);
+            }
+        }
+
+        Ok(token)
+    }
+}
+
+fn full_match(regex: &fancy_regex::Regex, text: &str) -> bool {
+    matches!(regex.find(text), Ok(Some(m)) if m.start() == 0 && m.end() == text.len())
+}
+
+/// Height of the smallest tree derived from each rule, computed as a fixpoint. Rules that only
+/// derive infinite trees are missing from the result.
+pub(crate) fn min_heights(syntax: &Syntax) -> HashMap<&str, usize> {
+    let mut heights = HashMap::new();
+
+    loop {
+        let mut changed = false;
+
+        for rule in syntax.rules() {
+            let height = rule
+                .alternatives
+                .iter()
+                .filter_map(|alt| alternative_height(syntax, &heights, alt))
+                .min();
+
+            if let Some(height) = height {
+                if heights
+                    .get(rule.name.as_str())
+                    .map_or(true, |&h| height < h)
+                {
+                    heights.insert(rule.name.as_str(), height);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            return heights;
+        }
+    }
+}
+
+pub(crate) fn alternative_height(
+    syntax: &Syntax,
+    heights: &HashMap<&str, usize>,
+    alternative: &RuleExpr,
+) -> Option<usize> {
+    // Terminals don't add to the height of the tree.
+    let ref_height = |name: &str| match syntax.decl_from_name(name) {
+        Some(Decl::Rule(_)) => heights.get(name).copied(),
+        _ => Some(0),
+    };
+
+    match alternative {
+        RuleExpr::Ref(r) => ref_height(&r.rule_name).map(|h| h + 1),
+        RuleExpr::Node(node) => node
+            .comps
+            .iter()
+            .map(|comp| match comp {
+                AlternativeComp::TExpr(_)
+                | AlternativeComp::Full(_, CompExpr::Opt(_) | CompExpr::Many(_)) => Some(0),
+                AlternativeComp::Full(_, CompExpr::SepBy(data)) if data.allow_empty => Some(0),
+                AlternativeComp::Full(_, CompExpr::SepBy(data)) => ref_height(&data.rule_name),
+                AlternativeComp::RuleRef(name)
+                | AlternativeComp::Full(_, CompExpr::Ref(name) | CompExpr::Some(name)) => {
+                    ref_height(name)
+                }
+            })
+            .try_fold(0, |acc, h| Some(acc.max(h?)))
+            .map(|h| h + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        core::spec::{test::parse_spec, DEFAULT_START_RULE},
+        pretty_print::format::format_tree,
+    };
+
+    use super::*;
+
+    static CONFIG: RoundtripConfig = RoundtripConfig {
+        iterations: 50,
+        max_depth: 6,
+        seed: 0,
+    };
+
+    #[test]
+    fn consistent_rules() {
+        let spec = parse_spec(indoc!(
+            "
+            node Num { }
+            node Identifier { }
+            node ConstDecl { name: Identifier, num: Num }
+            node Program { decls: List<ConstDecl> }
+
+            ignore term WHITESPACE = `\\s`
+
+            rule main = Program { decls@sepBy1(';', const_decl) }
+            rule const_decl = ConstDecl { 'const' name@ident '=' num@num_rule }
+            rule num_rule = Num { `[0-9]+` }
+            rule ident = Identifier { `[a-zA-Z]+` }
+            "
+        ));
+
+        let failures = check_roundtrip(&spec.syntax, DEFAULT_START_RULE, &CONFIG).unwrap();
+
+        assert_eq!(failures, vec![]);
+    }
+
+    #[test]
+    fn merged_tokens() {
+        let spec = parse_spec(indoc!(
+            "
+            node A { }
+            node Pair { left: A, right: A }
+
+            rule main = Pair { left@a_rule right@a_rule }
+            rule a_rule = A { `a+` }
+            "
+        ));
+
+        let failures = check_roundtrip(&spec.syntax, DEFAULT_START_RULE, &CONFIG).unwrap();
+
+        assert_eq!(failures.len(), CONFIG.iterations);
+    }
+
+    #[test]
+    fn swapped_format_template() {
+        let spec_with_template = |template: &str| {
+            parse_spec(&format!(
+                "
+                node A {{ }}
+                node Pair {{ left: A, right: A }}
+
+                ignore term WHITESPACE = `\\s`
+
+                rule main = Pair {{ '(' left@a_rule ',' right@a_rule ')' }}
+                rule a_rule = A {{ `[a-z]+` }}
+
+                format Pair = \"{template}\"
+                "
+            ))
+        };
+
+        let reformat = |template: &str| {
+            let spec = spec_with_template(template);
+            let parser = ParserRunner::new(DEFAULT_START_RULE, &spec.syntax).unwrap();
+            let parse = |code: String| {
+                let result = parser.run_once(Source::inline(code, "code".to_string()));
+                assert!(result.reports.is_empty());
+                result.tree
+            };
+
+            let tree = parse("(a,b)".to_string());
+            let reparsed = parse(format_tree(&spec.syntax, &tree));
+            same_tree(&spec.syntax, &tree, &reparsed)
+        };
+
+        assert!(reformat("({left}, {right})"));
+        assert!(!reformat("({right}, {left})"));
+    }
+
+    #[test]
+    fn token_mismatch() {
+        let spec = parse_spec(indoc!(
+            "
+            node A { }
+            node Pair { left: A, right: A }
+
+            ignore term WHITESPACE = `\\s`
+
+            rule main = Pair { '(' left@a_rule ',' right@a_rule ')' }
+            rule a_rule = A { `[a-z]+` }
+            "
+        ));
+
+        let parser = ParserRunner::new(DEFAULT_START_RULE, &spec.syntax).unwrap();
+        let parse = |code: &str| {
+            let result = parser.run_once(Source::inline(code.to_string(), "code".to_string()));
+            assert!(result.reports.is_empty());
+            result.tree
+        };
+
+        let tree = parse("(a,b)");
+
+        // Same kinds and fields, but a different leaf token.
+        assert!(!same_tree(&spec.syntax, &tree, &parse("(a,c)")));
+        // Ignored tokens are not compared.
+        assert!(same_tree(&spec.syntax, &tree, &parse("( a , b )")));
+    }
+}
@@ -0,0 +1,199 @@
+use sylver_dsl::meta::{FormatDecl, FormatField, FormatPart};
+
+use crate::{
+    core::{source::SourceTree, spec::Syntax},
+    tree::NodeId,
+};
+
+const INDENTATION: &str = "    ";
+
+/// Print a tree using the format templates of its spec. The nodes without template, or
+/// containing comments, keep their original code (with their descendants formatted).
+pub fn format_tree(syntax: &Syntax, tree: &SourceTree) -> String {
+    let formatter = Formatter { syntax, tree };
+    let root = tree.tree.root();
+
+    match formatter.text_range(root) {
+        Some((start, end)) => {
+            let src = tree.source.src();
+            format!(
+                "{}{}{}",
+                &src[..start],
+                formatter.format_node(root),
+                &src[end..]
+            )
+        }
+        None => tree.source.src().to_string(),
+    }
+}
+
+struct Formatter<'t> {
+    syntax: &'t Syntax,
+    tree: &'t SourceTree,
+}
+
+impl<'t> Formatter<'t> {
+    fn format_node(&self, node: NodeId) -> String {
+        let kind = self.tree[node].kind;
+
+        match self.syntax.format(kind) {
+            Some(format) if !self.has_comments(node) => self.format_template(node, format),
+            _ => self.format_verbatim(node),
+        }
+    }
+
+    fn format_template(&self, node: NodeId, format: &FormatDecl) -> String {
+        let mut out = String::new();
+
+        for part in &format.parts {
+            match part {
+                FormatPart::Text(text) => out.push_str(text),
+                FormatPart::Field(field) => {
+                    let formatted = self.format_field(node, field);
+
+                    if field.indent {
+                        out.push_str(&indent(&formatted));
+                    } else {
+                        out.push_str(&formatted);
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    fn format_field(&self, node: NodeId, field: &FormatField) -> String {
+        let node = &self.tree[node];
+
+        let child = self
+            .syntax
+            .field_position(node.kind, &field.name)
+            .and_then(|pos| node.named_childs[usize::from(pos)])
+            .map(|index| node.childs[index]);
+
+        match child {
+            Some(child) if self.syntax.is_list_kind(self.tree[child].kind) => self.tree[child]
+                .childs
+                .iter()
+                .map(|&elem| self.format_node(elem))
+                .collect::<Vec<_>>()
+                .join(&field.separator),
+            Some(child) => self.format_node(child),
+            None => String::new(),
+        }
+    }
+
+    /// Code of the node, in which the code of the children is replaced by their formatted code.
+    fn format_verbatim(&self, node: NodeId) -> String {
+        let (start, end) = match self.text_range(node) {
+            Some(range) => range,
+            None => return String::new(),
+        };
+
+        let src = self.tree.source.src();
+        let mut out = String::new();
+        let mut pos = start;
+
+        for &child in &self.tree[node].childs {
+            if let Some((child_start, child_end)) = self.text_range(child) {
+                out.push_str(&src[pos..child_start]);
+                out.push_str(&self.format_node(child));
+                pos = child_end;
+            }
+        }
+
+        out.push_str(&src[pos..end]);
+
+        out
+    }
+
+    /// Byte range of the node's text (without the leading and trailing ignored tokens).
+    fn text_range(&self, node: NodeId) -> Option<(usize, usize)> {
+        let tokens = self.tree.node_tokens(node, self.syntax.ignore_tags());
+        let first = tokens.first()?;
+        let last = tokens.last()?;
+
+        Some((first.pos.start().txt_pos(), last.pos.end().txt_pos()))
+    }
+
+    fn has_comments(&self, node: NodeId) -> bool {
+        self.tree
+            .node_tokens_with_ignore(node)
+            .iter()
+            .any(|t| self.syntax.comment_tags().contains(&t.tag))
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{INDENTATION}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{
+        core::{
+            source::Source,
+            spec::{test::parse_spec, DEFAULT_START_RULE},
+        },
+        parsing::parser_runner::ParserRunner,
+    };
+
+    use super::*;
+
+    static SPEC: &str = indoc!(
+        r#"
+        node Num { }
+        node Identifier { }
+        node ConstDecl { name: Identifier, num: Num }
+        node Block { decls: List<ConstDecl> }
+
+        ignore term WHITESPACE = `\s`
+        comment term COMMENT = `#[^\n]*`
+
+        rule main = Block { '{' decls@const_decl* '}' }
+        rule const_decl = ConstDecl { 'const' name@ident '=' num@num_rule ';' }
+        rule num_rule = Num { `[0-9]+` }
+        rule ident = Identifier { `[a-zA-Z]+` }
+
+        format Block = "{{\n{>decls:\n}\n}}"
+        format ConstDecl = "const {name} = {num};"
+        "#
+    );
+
+    fn format_code(code: &str) -> String {
+        let spec = parse_spec(SPEC);
+        let parser = ParserRunner::new(DEFAULT_START_RULE, &spec.syntax).unwrap();
+        let result = parser.run_once(Source::inline(code.to_string(), "code".to_string()));
+        assert!(result.reports.is_empty());
+
+        format_tree(&spec.syntax, &result.tree)
+    }
+
+    #[test]
+    fn format_with_templates() {
+        assert_eq!(
+            format_code("{ const a=1;const   b = 2; }\n"),
+            "{\n    const a = 1;\n    const b = 2;\n}\n"
+        );
+    }
+
+    #[test]
+    fn keep_commented_nodes() {
+        assert_eq!(
+            format_code("{ const a= # one\n1;const   b = 2; }"),
+            "{ const a= # one\n1;const b = 2; }"
+        );
+    }
+}
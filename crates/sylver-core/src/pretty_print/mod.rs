@@ -1,3 +1,4 @@
+pub mod format;
 mod report;
 pub mod tree;
 
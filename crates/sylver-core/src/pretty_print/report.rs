@@ -1,6 +1,8 @@
+use std::ops::Range;
+
 use codespan_reporting::{
     diagnostic::{Diagnostic, Label, Severity},
-    files::SimpleFiles,
+    files::{self, Files, SimpleFile},
     term::{
         self,
         termcolor::{BufferWriter, ColorChoice},
@@ -8,20 +10,40 @@ use codespan_reporting::{
 };
 
 use crate::{
-    core::source::Source,
+    core::{pos::PosEncoder, source::Source},
     land::ruleset::RuleCategory,
     report::{Report, ReportKind},
 };
 
 pub fn render_report(
     color: bool,
+    encoder: &PosEncoder,
     report: &Report,
     source: &Source,
 ) -> Result<String, anyhow::Error> {
-    let mut files = SimpleFiles::new();
-    let file_id = files.add(source.path().display().to_string(), source.src());
+    let file = EncodedFile {
+        file: SimpleFile::new(report.file_path.display().to_string(), source.src()),
+        encoder,
+    };
+
+    let mut diagnostic = build_diagnostic(report, ());
+
+    let cell_position = source
+        .notebook_cells()
+        .and_then(|cells| cells.cell_position(report.position.start().line()));
 
-    let diagnostic = build_diagnostic(report, file_id);
+    if let Some((cell, line)) = cell_position {
+        diagnostic
+            .notes
+            .push(format!("in notebook cell {cell}, line {line}"));
+    }
+
+    if let Some(map) = source.source_map() {
+        diagnostic.notes.push(format!(
+            "in the original source, line {}",
+            map.original_line(report.position.start().line())
+        ));
+    }
 
     let color_choice = if color {
         ColorChoice::Auto
@@ -30,11 +52,55 @@ pub fn render_report(
     };
     let mut buffer = BufferWriter::stdout(color_choice).buffer();
 
-    term::emit(&mut buffer, &Default::default(), &files, &diagnostic)?;
+    let mut config = term::Config::default();
+    if let Some(tab_width) = encoder.tab_width {
+        config.tab_width = tab_width;
+    }
+
+    term::emit(&mut buffer, &config, &file, &diagnostic)?;
 
     Ok(String::from_utf8(buffer.into_inner())?)
 }
 
+/// Rendered file whose columns are computed by the encoder of the settings, so that they match
+/// the other output formats.
+struct EncodedFile<'s> {
+    file: SimpleFile<String, &'s str>,
+    encoder: &'s PosEncoder,
+}
+
+impl<'a> Files<'a> for EncodedFile<'_> {
+    type FileId = ();
+    type Name = String;
+    type Source = &'a str;
+
+    fn name(&'a self, id: ()) -> Result<String, files::Error> {
+        Files::name(&self.file, id)
+    }
+
+    fn source(&'a self, id: ()) -> Result<&'a str, files::Error> {
+        Files::source(&self.file, id)
+    }
+
+    fn line_index(&'a self, id: (), byte_index: usize) -> Result<usize, files::Error> {
+        Files::line_index(&self.file, id, byte_index)
+    }
+
+    fn line_range(&'a self, id: (), line_index: usize) -> Result<Range<usize>, files::Error> {
+        Files::line_range(&self.file, id, line_index)
+    }
+
+    fn column_number(
+        &'a self,
+        id: (),
+        _line_index: usize,
+        byte_index: usize,
+    ) -> Result<usize, files::Error> {
+        let src = Files::source(&self.file, id)?;
+        Ok(self.encoder.column(src, byte_index))
+    }
+}
+
 pub fn build_diagnostic<FileId>(report: &Report, file_id: FileId) -> Diagnostic<FileId> {
     Diagnostic::new(report_severity(report))
         .with_code(&report.code)
@@ -43,7 +109,12 @@ pub fn build_diagnostic<FileId>(report: &Report, file_id: FileId) -> Diagnostic<
             file_id,
             report.position.start().txt_pos..report.position.end().txt_pos,
         )])
-        .with_notes(report.note.iter().cloned().collect())
+        .with_notes(report_notes(report))
+}
+
+fn report_notes(report: &Report) -> Vec<String> {
+    let doc_note = report.doc_url.as_ref().map(|url| format!("see: {url}"));
+    report.note.iter().cloned().chain(doc_note).collect()
 }
 
 fn report_severity(report: &Report) -> Severity {
@@ -54,3 +125,32 @@ fn report_severity(report: &Report) -> Severity {
         ReportKind::Category(RuleCategory::Style) => Severity::Help,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pos::{ColumnEncoding, InclPosRange, Pos};
+
+    fn render_with(encoding: ColumnEncoding) -> String {
+        let source = Source::inline("\u{1F600}x = 1\n".to_string(), "test.py".to_string());
+        let x = Pos::new((1, 0), source.src().find('x').unwrap());
+        let report = Report {
+            file_path: "test.py".into(),
+            code: "test".to_string(),
+            kind: ReportKind::Error,
+            position: InclPosRange::new(x, x).unwrap(),
+            message: "message".to_string(),
+            note: None,
+            doc_url: None,
+        };
+
+        render_report(false, &PosEncoder::new(encoding, None), &report, &source).unwrap()
+    }
+
+    #[test]
+    fn encoded_columns() {
+        assert!(render_with(ColumnEncoding::Utf8).contains("test.py:1:5"));
+        assert!(render_with(ColumnEncoding::Utf16).contains("test.py:1:3"));
+        assert!(render_with(ColumnEncoding::Char).contains("test.py:1:2"));
+    }
+}
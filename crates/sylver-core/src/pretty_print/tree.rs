@@ -327,7 +327,13 @@ mod tests {
         for report in parsing_result.reports {
             eprintln!(
                 "{}\n",
-                render_report(true, &report, &parsing_result.tree.source).unwrap()
+                render_report(
+                    true,
+                    &Default::default(),
+                    &report,
+                    &parsing_result.tree.source
+                )
+                .unwrap()
             )
         }
 
@@ -0,0 +1,444 @@
+use std::borrow::Cow;
+
+use crate::query::{
+    expr::{EvalCtx, EvalError, Value},
+    RawTreeInfoBuilder, SylvaNode,
+};
+
+/// Bound on the number of nested folding steps, which stops recursive definitions.
+const MAX_FOLDING_DEPTH: usize = 32;
+
+/// Fields holding the name bound by a declaration or assignment.
+static TARGET_FIELDS: &[&str] = &["left", "name"];
+
+/// Fields holding the value bound by a declaration or assignment.
+static VALUE_FIELDS: &[&str] = &["right", "value"];
+
+/// Fragments of the kind names of the nodes binding a name to a value (`Assignment`,
+/// `VariableDeclarator`, `ConstSpec`...).
+static BINDING_KINDS: &[&str] = &["Assign", "Declar", "Definition", "Binding", "Spec"];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Constant {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+}
+
+impl<'b> From<Constant> for Value<'b> {
+    fn from(constant: Constant) -> Self {
+        match constant {
+            Constant::Bool(b) => Value::Bool(b),
+            Constant::Int(i) => Value::Int(i),
+            Constant::Str(s) => Value::String(Cow::Owned(s)),
+        }
+    }
+}
+
+pub fn eval_constant_value<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+    let constant = ConstantFolder { ctx }.fold(sylva_node, 0)?;
+    Ok(constant.map_or(Value::Null, Into::into))
+}
+
+/// Best-effort folding of constant expressions: literals, string concatenations, integer
+/// arithmetic and variables bound to a constant. Nodes are recognized from the names of their
+/// kinds and fields, so that languages following the usual naming are supported.
+struct ConstantFolder<'c, 'b> {
+    ctx: &'c mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+}
+
+impl<'c, 'b> ConstantFolder<'c, 'b> {
+    fn fold(&mut self, node: SylvaNode, depth: usize) -> Result<Option<Constant>, EvalError> {
+        if depth > MAX_FOLDING_DEPTH {
+            return Ok(None);
+        }
+
+        let depth = depth + 1;
+        let kind = self.ctx.kind_name(node);
+        let text = self.ctx.node_text(node);
+
+        let constant = match kind {
+            "True" | "False" => Some(Constant::Bool(kind == "True")),
+            "ConcatenatedString" => {
+                let mut concatenated = String::new();
+
+                for part in self.ctx.childs(node) {
+                    match self.fold(part, depth)? {
+                        Some(Constant::Str(s)) => concatenated.push_str(&s),
+                        _ => return Ok(None),
+                    }
+                }
+
+                Some(Constant::Str(concatenated))
+            }
+            k if k.contains("String") => {
+                if self.is_interpolated(node) {
+                    None
+                } else {
+                    string_literal_value(text).map(Constant::Str)
+                }
+            }
+            k if k.contains("Integer") || k.contains("Number") => {
+                int_literal_value(text).map(Constant::Int)
+            }
+            k if k.contains("Bool") && matches!(text, "true" | "True" | "false" | "False") => {
+                Some(Constant::Bool(text.eq_ignore_ascii_case("true")))
+            }
+            k if k.contains("Parenthesized") => match self.ctx.childs(node).as_slice() {
+                &[inner] => self.fold(inner, depth)?,
+                _ => None,
+            },
+            k if k.contains("Identifier") => self.fold_variable(node, depth)?,
+            _ => self.fold_operator(node, depth)?,
+        };
+
+        Ok(constant)
+    }
+
+    fn fold_operator(
+        &mut self,
+        node: SylvaNode,
+        depth: usize,
+    ) -> Result<Option<Constant>, EvalError> {
+        let Some(operator) = self.field(node, "operator") else {
+            return Ok(None);
+        };
+        let operator = self.ctx.node_text(operator);
+
+        if let (Some(left), Some(right)) = (self.field(node, "left"), self.field(node, "right")) {
+            let (Some(left), Some(right)) = (self.fold(left, depth)?, self.fold(right, depth)?)
+            else {
+                return Ok(None);
+            };
+
+            return Ok(fold_binary(left, operator, right));
+        }
+
+        let argument = self
+            .field(node, "argument")
+            .or_else(|| self.field(node, "operand"));
+
+        match argument {
+            Some(argument) => Ok(self
+                .fold(argument, depth)?
+                .and_then(|value| fold_unary(operator, value))),
+            None => Ok(None),
+        }
+    }
+
+    /// Value of the constant bound to a variable, found through the scope graph or, if the
+    /// variable isn't a resolved reference, from the last preceding assignment of its name.
+    fn fold_variable(
+        &mut self,
+        node: SylvaNode,
+        depth: usize,
+    ) -> Result<Option<Constant>, EvalError> {
+        let value = match self.ctx.referenced_decls(node)?.as_deref() {
+            Some(&[decl]) => self.declared_value(decl),
+            Some(_) => None,
+            None => self.preceding_value(node),
+        };
+
+        match value {
+            Some(value) => self.fold(value, depth),
+            None => Ok(None),
+        }
+    }
+
+    /// Value bound by the given declaration, or by the declaration whose target it is.
+    fn declared_value(&self, decl: SylvaNode) -> Option<SylvaNode> {
+        if self.is_binding(decl) {
+            return self.value_field(decl);
+        }
+
+        let parent = self.ctx.parent(decl)?;
+
+        (self.is_binding(parent) && self.target_field(parent) == Some(decl))
+            .then(|| self.value_field(parent))
+            .flatten()
+    }
+
+    /// Value of the last binding of the variable's name preceding it in the enclosing nodes.
+    /// There is none if the name is reassigned in between, including in nested blocks whose
+    /// execution is unknown.
+    fn preceding_value(&self, node: SylvaNode) -> Option<SylvaNode> {
+        let name = self.ctx.node_text(node);
+        let mut current = node;
+
+        while let Some(parent) = self.ctx.parent(current) {
+            let siblings = self.ctx.childs(parent);
+            let position = siblings.iter().position(|&s| s == current)?;
+
+            for &sibling in siblings[..position].iter().rev() {
+                let candidates = std::iter::once(sibling).chain(self.ctx.childs(sibling));
+
+                for candidate in candidates {
+                    let is_named_binding = self.is_binding(candidate)
+                        && self
+                            .target_field(candidate)
+                            .map_or(false, |target| self.ctx.node_text(target) == name);
+
+                    if is_named_binding {
+                        return self.value_field(candidate);
+                    }
+                }
+
+                if self.assigns(sibling, name) {
+                    return None;
+                }
+            }
+
+            current = parent;
+        }
+
+        None
+    }
+
+    /// Whether the node or one of its descendants assigns a value to the given name, augmented
+    /// assignments included.
+    fn assigns(&self, node: SylvaNode, name: &str) -> bool {
+        let kind = self.ctx.kind_name(node);
+
+        let assigns_name = BINDING_KINDS.iter().any(|k| kind.contains(k))
+            && self
+                .target_field(node)
+                .map_or(false, |target| self.ctx.node_text(target) == name);
+
+        assigns_name
+            || self
+                .ctx
+                .childs(node)
+                .into_iter()
+                .any(|child| self.assigns(child, name))
+    }
+
+    /// Augmented assignments (`x += 1`) are not bindings, as their value depends on the
+    /// previous one.
+    fn is_binding(&self, node: SylvaNode) -> bool {
+        let kind = self.ctx.kind_name(node);
+
+        BINDING_KINDS.iter().any(|k| kind.contains(k))
+            && !kind.contains("Augmented")
+            && self.field(node, "operator").is_none()
+    }
+
+    /// String literals containing interpolated expressions are not constant.
+    fn is_interpolated(&self, node: SylvaNode) -> bool {
+        self.ctx.childs(node).into_iter().any(|child| {
+            let kind = self.ctx.kind_name(child);
+            kind.contains("Interpolation")
+                || kind.contains("Substitution")
+                || self.is_interpolated(child)
+        })
+    }
+
+    fn target_field(&self, node: SylvaNode) -> Option<SylvaNode> {
+        TARGET_FIELDS.iter().find_map(|f| self.field(node, f))
+    }
+
+    fn value_field(&self, node: SylvaNode) -> Option<SylvaNode> {
+        VALUE_FIELDS.iter().find_map(|f| self.field(node, f))
+    }
+
+    fn field(&self, node: SylvaNode, field: &str) -> Option<SylvaNode> {
+        self.ctx.node_field(node, field).ok()?.try_into().ok()
+    }
+}
+
+fn fold_binary(left: Constant, operator: &str, right: Constant) -> Option<Constant> {
+    let result = match (left, operator, right) {
+        (Constant::Str(l), "+" | "." | "..", Constant::Str(r)) => Constant::Str(l + &r),
+        (Constant::Int(l), op, Constant::Int(r)) => Constant::Int(match op {
+            "+" => l.checked_add(r)?,
+            "-" => l.checked_sub(r)?,
+            "*" => l.checked_mul(r)?,
+            // Division and modulo round differently across languages for negative operands.
+            "//" if l >= 0 && r > 0 => l / r,
+            "%" if l >= 0 && r > 0 => l % r,
+            "&" => l & r,
+            "|" => l | r,
+            "^" => l ^ r,
+            _ => return None,
+        }),
+        (Constant::Bool(l), "&&" | "and", Constant::Bool(r)) => Constant::Bool(l && r),
+        (Constant::Bool(l), "||" | "or", Constant::Bool(r)) => Constant::Bool(l || r),
+        _ => return None,
+    };
+
+    Some(result)
+}
+
+fn fold_unary(operator: &str, value: Constant) -> Option<Constant> {
+    match (operator, value) {
+        ("-", Constant::Int(i)) => i.checked_neg().map(Constant::Int),
+        ("+", Constant::Int(i)) => Some(Constant::Int(i)),
+        ("!" | "not", Constant::Bool(b)) => Some(Constant::Bool(!b)),
+        _ => None,
+    }
+}
+
+/// Value of a string literal: the text between its quotes, with the escape sequences replaced
+/// unless the literal is raw (`r"..."`, `@"..."`).
+fn string_literal_value(literal: &str) -> Option<String> {
+    let quote_start = literal.find(['"', '\'', '`'])?;
+    let (prefix, quoted) = literal.split_at(quote_start);
+
+    if !prefix.chars().all(|c| c.is_ascii_alphabetic() || c == '@') {
+        return None;
+    }
+
+    let quote = ["\"\"\"", "'''", "\"", "'", "`"]
+        .into_iter()
+        .find(|q| quoted.len() >= 2 * q.len() && quoted.starts_with(q) && quoted.ends_with(q))?;
+
+    let content = &quoted[quote.len()..quoted.len() - quote.len()];
+
+    if prefix.contains(['r', 'R', '@']) {
+        Some(content.to_string())
+    } else {
+        Some(unescape(content))
+    }
+}
+
+fn unescape(content: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = content.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('0') => unescaped.push('\0'),
+            Some(c @ ('\\' | '\'' | '"' | '`')) => unescaped.push(c),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+fn int_literal_value(literal: &str) -> Option<i64> {
+    let digits = literal.replace('_', "").to_ascii_lowercase();
+
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, digits.as_str()),
+    };
+
+    i64::from_str_radix(digits, radix).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::query::test::count_python_matches;
+
+    use super::*;
+
+    static PYTHON_SOURCE: &str = indoc! {r#"
+        prefix = "sk_"
+        password = prefix + 'live' "_" + "1234"
+        retries = (2 + 3) * 4
+        name = f"{prefix}x"
+        connect(password, retries, -retries, name)
+        "#
+    };
+
+    static REASSIGNED_SOURCE: &str = indoc! {r#"
+        token = "a"
+        if enabled:
+            token = "b"
+        retries = 3
+        retries += 1
+        send(token, retries)
+        "#
+    };
+
+    fn count_matches(query: &str) -> usize {
+        count_python_matches(PYTHON_SOURCE, query)
+    }
+
+    #[test]
+    fn folded_arguments() {
+        assert_eq!(
+            count_matches(
+                "match Call c when constant_value(c.arguments.children[0]) == 'sk_live_1234'"
+            ),
+            1
+        );
+        assert_eq!(
+            count_matches("match Call c when constant_value(c.arguments.children[1]) == 20"),
+            1
+        );
+        assert_eq!(
+            count_matches("match Call c when constant_value(c.arguments.children[2]) < 0"),
+            1
+        );
+        assert_eq!(
+            count_matches("match Call c when constant_value(c.arguments.children[3]) == null"),
+            1
+        );
+    }
+
+    #[test]
+    fn reassigned_variables() {
+        let query = "match Call c when constant_value(c.arguments.children[0]) == null";
+        assert_eq!(count_python_matches(REASSIGNED_SOURCE, query), 1);
+
+        let query = "match Call c when constant_value(c.arguments.children[1]) == null";
+        assert_eq!(count_python_matches(REASSIGNED_SOURCE, query), 1);
+    }
+
+    #[test]
+    fn string_literals() {
+        assert_eq!(string_literal_value("'a\\'b'"), Some("a'b".into()));
+        assert_eq!(string_literal_value(r#"r"\d""#), Some(r"\d".into()));
+        assert_eq!(string_literal_value(r#""""doc""""#), Some("doc".into()));
+        assert_eq!(string_literal_value("hello"), None);
+    }
+
+    #[test]
+    fn int_literals() {
+        assert_eq!(int_literal_value("1_000"), Some(1000));
+        assert_eq!(int_literal_value("0xFF"), Some(255));
+        assert_eq!(int_literal_value("1.5"), None);
+    }
+
+    #[test]
+    fn binary_operations() {
+        assert_eq!(
+            fold_binary(Constant::Int(7), "%", Constant::Int(4)),
+            Some(Constant::Int(3))
+        );
+        assert_eq!(fold_binary(Constant::Int(-7), "%", Constant::Int(4)), None);
+        assert_eq!(
+            fold_binary(Constant::Str("a".into()), "..", Constant::Str("b".into())),
+            Some(Constant::Str("ab".into()))
+        );
+        assert_eq!(
+            fold_binary(Constant::Str("a".into()), "+", Constant::Int(1)),
+            None
+        );
+        assert_eq!(
+            fold_binary(Constant::Int(i64::MAX), "+", Constant::Int(1)),
+            None
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{borrow::Cow, cmp::Ordering, collections::VecDeque};
+use std::{borrow::Cow, cmp::Ordering, collections::VecDeque, sync::Arc};
 
 use derivative::Derivative;
 use derive_more::From;
@@ -8,11 +8,22 @@ use thiserror::Error;
 
 use crate::{
     core::spec::{KindId, Spec},
-    land::Land,
-    query::{expr::EvalError::InvalidKind, RawTreeInfoBuilder, SylvaNode, TreeInfoBuilder},
-    script::python::PythonScriptEngine,
-    semantic::names::NamesError,
+    land::{passes::AnalysisPass, ruleset::RuleAnnotations, Land},
+    query::{
+        constant::eval_constant_value,
+        expr::EvalError::InvalidKind,
+        memo::{EvalMemo, MemoFunction},
+        python::{
+            eval_decorators, eval_docstring, eval_has_decorator, eval_inferred_type,
+            eval_return_annotation, PythonKinds,
+        },
+        swift::{eval_has_property_wrapper, eval_is_view_body, eval_property_wrappers, SwiftKinds},
+        RawTreeInfoBuilder, SylvaNode, TreeInfoBuilder,
+    },
+    script::{python::PythonScriptEngine, ScriptError},
+    semantic::{calls::CallGraph, names::NamesError},
     tree::{info::TreeInfo, Node, NodeId},
+    util::cancel::CancellationToken,
 };
 
 #[derive(Debug, Clone)]
@@ -22,6 +33,10 @@ pub struct EvalCtx<'v, B: TreeInfoBuilder<'v>> {
     info_builder: B, // TODO: get rid of the builder/info abstraction
     land: &'v Land,
     script_engine: PythonScriptEngine,
+    cancel: CancellationToken,
+    memo: Option<&'v EvalMemo<'v>>,
+    /// Matches of the rules required by the evaluated rule.
+    annotations: Option<Arc<RuleAnnotations>>,
 }
 
 impl<'b> EvalCtx<'b, RawTreeInfoBuilder<'b>> {
@@ -29,6 +44,9 @@ impl<'b> EvalCtx<'b, RawTreeInfoBuilder<'b>> {
         &mut self,
         node: SylvaNode,
     ) -> Result<Option<Vec<SylvaNode>>, EvalError> {
+        // Name resolution may run scripts, which can be arbitrarily slow.
+        self.check_cancelled()?;
+
         let mut scopes = self.land.sylva_scopes_mut(node.sylva);
         scopes
             .referenced_decls(
@@ -37,9 +55,39 @@ impl<'b> EvalCtx<'b, RawTreeInfoBuilder<'b>> {
                 &self.spec.aspects,
                 self.info_builder.info_for_node(node),
                 self.script_engine,
+                &self.cancel,
             )
             .map(|decls| decls.map(|decls| decls.to_vec()))
-            .map_err(EvalError::NameRes)
+            .map_err(|e| match e {
+                NamesError::Script(ScriptError::Cancelled) => EvalError::Cancelled,
+                e => EvalError::NameRes(e),
+            })
+    }
+
+    /// Evaluate an expression whose value only depends on the node its operand evaluates to,
+    /// reusing the value memoized for that node if any. The operand is evaluated once, and its
+    /// value is given to `eval`.
+    fn memoized(
+        &mut self,
+        expr: &Expr,
+        operand: &Expr,
+        eval: impl FnOnce(&mut Self, Value<'b>) -> Result<Value<'b>, EvalError>,
+    ) -> Result<Value<'b>, EvalError> {
+        let operand = operand.eval(self)?;
+
+        let (memo, function, node) = match (self.memo, MemoFunction::of(expr), &operand) {
+            (Some(memo), Some(function), &Value::Node(node)) => (memo, function, node),
+            _ => return eval(self, operand),
+        };
+
+        if let Some(value) = memo.get(&function, node) {
+            return Ok(value);
+        }
+
+        let value = eval(self, operand)?;
+        memo.insert(function, node, value.clone());
+
+        Ok(value)
     }
 }
 
@@ -69,6 +117,14 @@ impl<'b, B: 'b + TreeInfoBuilder<'b>> EvalCtx<'b, B> {
             .map(|next_sibling| node.with_node_id(next_sibling))
     }
 
+    pub fn node_kind(&self, node: SylvaNode) -> KindId {
+        self.tree_node(node).kind
+    }
+
+    pub fn kind_name(&self, node: SylvaNode) -> &'b str {
+        self.spec.syntax.kind_name(self.node_kind(node))
+    }
+
     pub fn node_text(&self, node: SylvaNode) -> &'b str {
         self.info_builder.info_for_node(node).node_text(node.node)
     }
@@ -121,9 +177,34 @@ impl<'b, B: TreeInfoBuilder<'b>> EvalCtx<'b, B> {
             info_builder,
             land,
             script_engine,
+            cancel: CancellationToken::default(),
+            memo: None,
+            annotations: None,
         }
     }
 
+    /// Share the values of the node functions with the other contexts using the same memo.
+    pub fn with_memo(mut self, memo: &'b EvalMemo<'b>) -> Self {
+        self.memo = Some(memo);
+        self
+    }
+
+    /// Give access to the matches of the given rules (`matched_by`).
+    pub fn with_annotations(mut self, annotations: Arc<RuleAnnotations>) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Make the evaluation fail once the given token is cancelled.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn check_cancelled(&self) -> Result<(), EvalError> {
+        self.cancel.check().map_err(|_| EvalError::Cancelled)
+    }
+
     pub fn push_var(&mut self, var: Value<'b>) {
         self.memory.push(var)
     }
@@ -333,6 +414,24 @@ impl<'t> Value<'t> {
     pub fn is_null(&self) -> bool {
         matches!(self, Value::Null)
     }
+
+    /// Return the elements of a list or of a generator.
+    pub fn collect_list<'c, 'b>(
+        self,
+        ctx: &'c EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    ) -> Result<Vec<Value<'b>>, EvalError>
+    where
+        't: 'b,
+    {
+        let mut children = self.try_get_children(ctx)?;
+        let mut values = vec![];
+
+        while let Some(value) = children.next_value(ctx) {
+            values.push(value);
+        }
+
+        Ok(values)
+    }
 }
 
 impl<'t, T: Into<Value<'t>>> From<Option<T>> for Value<'t> {
@@ -434,6 +533,12 @@ pub enum EvalError {
     NotAnInt(String),
     #[error("name resolution error: {0}")]
     NameRes(NamesError),
+    #[error("Evaluation cancelled")]
+    Cancelled,
+    #[error("The {0} pass must be in the requirements of the rule")]
+    MissingPass(AnalysisPass),
+    #[error("Rule {0} must be in the requirements of the rule to use its matches")]
+    MissingRule(String),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -442,11 +547,28 @@ pub enum Expr {
     KindAccess(Box<Expr>),
     Const(Value<'static>),
     NodeText(Box<Expr>),
+    NormalizedText(Box<Expr>),
+    ContainsText(Box<Expr>, String),
     NodeParent(Box<Expr>),
     NodeChildren(Box<Expr>),
     NodePrevSibling(Box<Expr>),
     NodeNextSibling(Box<Expr>),
     ReferencedDecl(Box<Expr>),
+    Resolves(Box<Expr>),
+    DeclarationOf(Box<Expr>),
+    NodeClones(Box<Expr>),
+    ConstantValue(Box<Expr>),
+    Decorators(Box<Expr>, PythonKinds),
+    HasDecorator(Box<Expr>, PythonKinds, String),
+    ReturnAnnotation(Box<Expr>, PythonKinds),
+    Docstring(Box<Expr>, PythonKinds),
+    InferredType(Box<Expr>, PythonKinds),
+    PropertyWrappers(Box<Expr>, SwiftKinds),
+    HasPropertyWrapper(Box<Expr>, SwiftKinds, String),
+    IsViewBody(Box<Expr>, SwiftKinds),
+    Callers(Box<Expr>),
+    Callees(Box<Expr>),
+    MatchedBy(Box<Expr>, String),
     // TODO: useless with `Not` Expr ?
     NonNullCheck(Box<Expr>),
     Length(Box<Expr>),
@@ -492,6 +614,66 @@ impl Expr {
         Expr::unary(Expr::NodeText, operand)
     }
 
+    pub fn normalized_text(operand: Expr) -> Expr {
+        Expr::unary(Expr::NormalizedText, operand)
+    }
+
+    pub fn contains_text(operand: Expr, text: String) -> Expr {
+        Expr::ContainsText(Box::new(operand), text)
+    }
+
+    pub fn node_clones(operand: Expr) -> Expr {
+        Expr::unary(Expr::NodeClones, operand)
+    }
+
+    pub fn decorators(operand: Expr, kinds: PythonKinds) -> Expr {
+        Expr::Decorators(Box::new(operand), kinds)
+    }
+
+    pub fn has_decorator(operand: Expr, kinds: PythonKinds, name: String) -> Expr {
+        Expr::HasDecorator(Box::new(operand), kinds, name)
+    }
+
+    pub fn return_annotation(operand: Expr, kinds: PythonKinds) -> Expr {
+        Expr::ReturnAnnotation(Box::new(operand), kinds)
+    }
+
+    pub fn docstring(operand: Expr, kinds: PythonKinds) -> Expr {
+        Expr::Docstring(Box::new(operand), kinds)
+    }
+
+    pub fn inferred_type(operand: Expr, kinds: PythonKinds) -> Expr {
+        Expr::InferredType(Box::new(operand), kinds)
+    }
+
+    pub fn property_wrappers(operand: Expr, kinds: SwiftKinds) -> Expr {
+        Expr::PropertyWrappers(Box::new(operand), kinds)
+    }
+
+    pub fn has_property_wrapper(operand: Expr, kinds: SwiftKinds, name: String) -> Expr {
+        Expr::HasPropertyWrapper(Box::new(operand), kinds, name)
+    }
+
+    pub fn is_view_body(operand: Expr, kinds: SwiftKinds) -> Expr {
+        Expr::IsViewBody(Box::new(operand), kinds)
+    }
+
+    pub fn callers(operand: Expr) -> Expr {
+        Expr::unary(Expr::Callers, operand)
+    }
+
+    pub fn callees(operand: Expr) -> Expr {
+        Expr::unary(Expr::Callees, operand)
+    }
+
+    pub fn matched_by(operand: Expr, rule: String) -> Expr {
+        Expr::MatchedBy(Box::new(operand), rule)
+    }
+
+    pub fn constant_value(operand: Expr) -> Expr {
+        Expr::unary(Expr::ConstantValue, operand)
+    }
+
     pub fn node_parent(operand: Expr) -> Expr {
         Expr::unary(Expr::NodeParent, operand)
     }
@@ -512,6 +694,14 @@ impl Expr {
         Expr::unary(Expr::ReferencedDecl, operand)
     }
 
+    pub fn resolves(operand: Expr) -> Expr {
+        Expr::unary(Expr::Resolves, operand)
+    }
+
+    pub fn declaration_of(operand: Expr) -> Expr {
+        Expr::unary(Expr::DeclarationOf, operand)
+    }
+
     pub fn non_null_check(operand: Expr) -> Expr {
         Expr::unary(Expr::NonNullCheck, operand)
     }
@@ -621,11 +811,44 @@ impl Expr {
             Expr::KindAccess(op) => eval_kind_access(ctx, op),
             Expr::Const(v) => Ok(v.clone()),
             Expr::NodeText(o) => eval_node_text(ctx, o),
+            Expr::NormalizedText(o) => ctx.memoized(self, o, eval_normalized_text),
+            Expr::ContainsText(o, text) => eval_contains_text(ctx, o, text),
             Expr::NodeParent(n) => eval_node_parent(ctx, n),
             Expr::NodeChildren(n) => eval_node_children(ctx, n),
             Expr::NodePrevSibling(n) => eval_node_prev_sibling(ctx, n),
             Expr::NodeNextSibling(n) => eval_node_next_sibling(ctx, n),
             Expr::ReferencedDecl(n) => eval_referenced_decl(ctx, n),
+            Expr::Resolves(n) => eval_resolves(ctx, n),
+            Expr::DeclarationOf(n) => eval_declaration_of(ctx, n),
+            Expr::NodeClones(n) => ctx.memoized(self, n, eval_node_clones),
+            Expr::ConstantValue(n) => ctx.memoized(self, n, eval_constant_value),
+            Expr::Decorators(n, kinds) => {
+                ctx.memoized(self, n, |ctx, node| eval_decorators(ctx, node, kinds))
+            }
+            Expr::HasDecorator(n, kinds, name) => ctx.memoized(self, n, |ctx, node| {
+                eval_has_decorator(ctx, node, kinds, name)
+            }),
+            Expr::ReturnAnnotation(n, kinds) => ctx.memoized(self, n, |ctx, node| {
+                eval_return_annotation(ctx, node, kinds)
+            }),
+            Expr::Docstring(n, kinds) => {
+                ctx.memoized(self, n, |ctx, node| eval_docstring(ctx, node, kinds))
+            }
+            Expr::InferredType(n, kinds) => {
+                ctx.memoized(self, n, |ctx, node| eval_inferred_type(ctx, node, kinds))
+            }
+            Expr::PropertyWrappers(n, kinds) => ctx.memoized(self, n, |ctx, node| {
+                eval_property_wrappers(ctx, node, kinds)
+            }),
+            Expr::HasPropertyWrapper(n, kinds, name) => ctx.memoized(self, n, |ctx, node| {
+                eval_has_property_wrapper(ctx, node, kinds, name)
+            }),
+            Expr::IsViewBody(n, kinds) => {
+                ctx.memoized(self, n, |ctx, node| eval_is_view_body(ctx, node, kinds))
+            }
+            Expr::Callers(n) => eval_calls(ctx, n, CallGraph::callers),
+            Expr::Callees(n) => eval_calls(ctx, n, CallGraph::callees),
+            Expr::MatchedBy(n, rule) => eval_matched_by(ctx, n, rule),
             Expr::Length(o) => eval_length(ctx, o),
             Expr::InContext(ctx_values, e) => eval_in_context(ctx, ctx_values, e),
             Expr::ReadVar(addr) => eval_read_var(ctx, *addr),
@@ -779,6 +1002,28 @@ fn node_value_text<'b>(
     Ok(Value::String(Cow::Borrowed(text)))
 }
 
+fn eval_normalized_text<'b>(
+    _ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+) -> Result<Value<'b>, EvalError> {
+    let text: Cow<'b, str> = operand.try_into()?;
+    Ok(Value::String(Cow::Owned(normalize_whitespace(&text))))
+}
+
+/// Trim the text and collapse every whitespace sequence into a single space.
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn eval_contains_text<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    op: &Expr,
+    needle: &str,
+) -> Result<Value<'b>, EvalError> {
+    let text: Cow<'b, str> = op.eval(ctx)?.try_into()?;
+    Ok(text.contains(needle).into())
+}
+
 fn eval_node_parent<'b>(
     ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
     op: &Expr,
@@ -828,6 +1073,81 @@ fn eval_referenced_decl<'b>(
     })
 }
 
+/// Whether the node is a reference bound to at least one declaration.
+fn eval_resolves<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    op: &Expr,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = op.eval(ctx)?.try_into()?;
+
+    let decls = ctx.referenced_decls(sylva_node)?;
+
+    Ok(decls.map_or(false, |decls| !decls.is_empty()).into())
+}
+
+/// First declaration the node refers to, or null if it is not a resolved reference.
+fn eval_declaration_of<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    op: &Expr,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = op.eval(ctx)?.try_into()?;
+
+    let decl = ctx
+        .referenced_decls(sylva_node)?
+        .and_then(|decls| decls.first().copied());
+
+    Ok(decl.into())
+}
+
+fn eval_node_clones<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+
+    let clones = ctx
+        .land
+        .sylva_clones(sylva_node.sylva)
+        .clones_of(sylva_node);
+
+    Ok(Value::List(clones.into_iter().map(Into::into).collect()))
+}
+
+/// Declarations calling, or called by, the given declaration, from the call graph pass.
+fn eval_calls<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    op: &Expr,
+    calls: fn(&CallGraph, SylvaNode) -> &[SylvaNode],
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = op.eval(ctx)?.try_into()?;
+
+    let graph = ctx
+        .land
+        .sylva_call_graph(sylva_node.sylva)
+        .ok_or(EvalError::MissingPass(AnalysisPass::CallGraph))?;
+
+    let decls = calls(graph, sylva_node);
+
+    Ok(Value::List(decls.iter().map(|&d| d.into()).collect()))
+}
+
+/// Whether the node is matched by another rule of the ruleset, evaluated beforehand.
+fn eval_matched_by<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    op: &Expr,
+    rule: &str,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = op.eval(ctx)?.try_into()?;
+
+    let matches = ctx
+        .annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(rule))
+        .ok_or_else(|| EvalError::MissingRule(rule.to_string()))?;
+
+    Ok(matches.contains(&sylva_node).into())
+}
+
 fn eval_length<'b>(
     ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
     op: &Expr,
@@ -976,8 +1296,18 @@ fn node_childs_if_list<'b, B: 'b + TreeInfoBuilder<'b>>(
 pub mod test {
     use std::collections::HashMap;
 
+    use std::path::Path;
+
+    use indoc::indoc;
+    use sylver_dsl::sylq::parse_query;
+
     use crate::{
-        land::{builder::LandBuilder, sylva::Sylva},
+        core::{
+            source::Source,
+            spec::{spec_from_reader, DEFAULT_START_RULE},
+        },
+        land::{builder::LandBuilder, cmds::filter_sylva, sylva::Sylva, LandSpecId},
+        query::language::compile::compile,
         tree::{info::tests::TestTreeInfo, Node},
     };
 
@@ -993,6 +1323,23 @@ pub mod test {
         assert_eq!(Ok(true.into()), eval_in_default_ctx(expr));
     }
 
+    #[test]
+    fn normalized_text() {
+        let expr = Expr::normalized_text(Expr::Const(Value::String(" a \n\t b  c ".into())));
+
+        assert_eq!(Ok(Value::String("a b c".into())), eval_in_default_ctx(expr));
+    }
+
+    #[test]
+    fn contains_text() {
+        let expr = Expr::contains_text(
+            Expr::Const(Value::String("// TODO: fix".into())),
+            "TODO".to_string(),
+        );
+
+        assert_eq!(Ok(true.into()), eval_in_default_ctx(expr));
+    }
+
     #[test]
     fn regex_match_notok() {
         let expr = Expr::regex_match(
@@ -1208,6 +1555,102 @@ pub mod test {
         assert_eq!(Err(EvalError::InvalidAddress(1)), eval_in_default_ctx(expr));
     }
 
+    #[test]
+    fn resolve_names_with_custom_aspects() {
+        let files: HashMap<&Path, &str> = HashMap::from([
+            (
+                Path::new("lang.syl"),
+                indoc!(
+                    "
+                    node Identifier { }
+                    node Stmt { }
+                    node Let: Stmt { name: Identifier }
+                    node Use: Stmt { name: Identifier }
+                    node Block { stmts: List<Stmt> }
+
+                    ignore term WHITESPACE = `\\s`
+
+                    rule main = Block { stmts@stmt* }
+                    rule stmt = Let { 'let' name@ident ';' } | Use { 'use' name@ident ';' }
+                    rule ident = Identifier { `[a-z]+` }
+                    "
+                ),
+            ),
+            (
+                Path::new("lang.py"),
+                indoc!(
+                    "
+                    @Let.sg_gen(depth=1)
+                    def let_sg_gen(node, scope):
+                        scope.add_decl(node.name.text, node)
+                        return scope
+
+                    @Use.sg_gen(depth=1)
+                    def use_sg_gen(node, scope):
+                        scope.add_ref(node.name.text, node.name)
+                        return scope
+                    "
+                ),
+            ),
+        ]);
+
+        let spec = spec_from_reader(
+            &PythonScriptEngine::default(),
+            Some(Path::new("lang.py")),
+            Path::new("lang.syl"),
+            &mut |path: &Path| Ok(files[path].to_string()),
+        )
+        .unwrap();
+
+        let source = Source::inline("let a; use a; use b;".to_string(), "input".to_string());
+        let sylva = Sylva::build(&spec, DEFAULT_START_RULE, std::iter::once(source)).unwrap();
+
+        let mut builder = LandBuilder::new();
+        let spec_id = builder.add_spec(spec);
+        let sylva_id = builder
+            .add_sylva(sylva, LandSpecId::CustomLangId(spec_id))
+            .unwrap();
+        let land = builder.build();
+        let spec = land.sylva_spec(sylva_id);
+
+        let matches = |query: &str| {
+            let predicate = compile(spec, &parse_query(query).unwrap()).unwrap();
+            filter_sylva(
+                &land,
+                PythonScriptEngine::default(),
+                sylva_id,
+                &predicate,
+                &CancellationToken::default(),
+            )
+            .unwrap()
+        };
+
+        let declarations = matches("match Let l");
+        let resolved = matches("match Identifier i when resolves(i)");
+        let unresolved = matches("match Use u when declaration_of(u.name) == null");
+
+        assert_eq!(declarations.len(), 1);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(
+            land.sylva_node_tree(resolved[0])
+                .node_text(resolved[0].node, &[]),
+            "a"
+        );
+        assert_eq!(unresolved.len(), 1);
+
+        let mut ctx = EvalCtx::new(
+            spec,
+            RawTreeInfoBuilder::new(spec, land.sylva(sylva_id)),
+            &land,
+            PythonScriptEngine::default(),
+        );
+
+        assert_eq!(
+            Expr::declaration_of(Expr::Const(Value::Node(resolved[0]))).eval(&mut ctx),
+            Ok(Value::Node(declarations[0]))
+        );
+    }
+
     fn eval_binop(
         binop: impl Fn(Expr, Expr) -> Expr,
         left: impl Into<Value<'static>>,
@@ -1,20 +1,52 @@
-use std::{borrow::Cow, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
 
 use itertools::Itertools;
 use thiserror::Error;
 
 use sylver_dsl::sylq::{
-    Arg, ArrayQuantQuant, Expr as SyntaxExpr, KindPattern, NodePatternField, NodePatternFieldDesc,
-    NodePatternFieldValue, NodePatternsWithBinding, Op, QueryPattern,
+    Arg, ArrayQuantQuant, Axis, Expr as SyntaxExpr, ExprRegex, KindPattern, NodePatternField,
+    NodePatternFieldDesc, NodePatternFieldValue, NodePatternsWithBinding, Op, PredicateDef,
+    QueryPattern,
 };
 
 use crate::{
     core::spec::{strip_list_kind, KindId, Spec},
-    query::expr::{DepthNodeGeneratorFn, Expr, Value},
+    query::{
+        expr::{DepthNodeGeneratorFn, Expr, Value},
+        python::PythonKinds,
+        swift::SwiftKinds,
+    },
 };
 
 pub const DEFAULT_INPUT_ADDR: usize = 0;
 
+static NO_PREDICATES: BTreeMap<String, PredicateDef> = BTreeMap::new();
+
+/// Functions provided by the query language, which cannot be redefined by user predicates.
+pub const BUILTIN_FUNCTIONS: &[&str] = &[
+    "text",
+    "normalized_text",
+    "constant_value",
+    "resolves",
+    "declaration_of",
+    "matches_regex",
+    "contains_text",
+    "decorators",
+    "has_decorator",
+    "return_annotation",
+    "docstring",
+    "inferred_type",
+    "property_wrappers",
+    "has_property_wrapper",
+    "is_view_body",
+    "callers",
+    "callees",
+    "matched_by",
+];
+
 #[derive(Debug, Eq, PartialEq, Error)]
 pub enum CompilationErr {
     #[error("Invalid kind name: {0}")]
@@ -27,23 +59,46 @@ pub enum CompilationErr {
     UnexpectedArity(String, usize),
     #[error("Unexpected arg for method {0}. Expected {1}.")]
     UnexpectedArg(String, String),
+    #[error("Invalid regex: {0}")]
+    InvalidRegex(String),
+    #[error("Predicate {0} is recursive")]
+    RecursivePredicate(String),
+    #[error("Function {0} is only available for Python")]
+    PythonOnly(String),
+    #[error("Function {0} is only available for Swift")]
+    SwiftOnly(String),
+    #[error("Kind {0} was renamed to {1}, which is not declared")]
+    InvalidRenamedKind(String, String),
 }
 
 struct Compiler<'s> {
     spec: &'s Spec,
+    predicates: &'s BTreeMap<String, PredicateDef>,
+    /// Predicates whose body is being compiled.
+    expanding: Vec<String>,
     bindings: HashMap<String, usize>,
     reserved_vars: usize,
+    /// Renamed kinds referred to by their former name, with their new name.
+    renamed_kinds: BTreeMap<String, String>,
 }
 
 impl<'s> Compiler<'s> {
     fn for_spec(spec: &'s Spec) -> Compiler<'s> {
         Compiler {
             spec,
+            predicates: &NO_PREDICATES,
+            expanding: vec![],
             bindings: HashMap::new(),
             reserved_vars: 1, // Address 0 is reserved to the input
+            renamed_kinds: BTreeMap::new(),
         }
     }
 
+    fn with_predicates(mut self, predicates: &'s BTreeMap<String, PredicateDef>) -> Self {
+        self.predicates = predicates;
+        self
+    }
+
     fn compile(&mut self, query: &QueryPattern) -> Result<Expr, CompilationErr> {
         let filter_expr = self.compile_query_pattern(DEFAULT_INPUT_ADDR, query)?;
         Ok(filter_expr)
@@ -104,18 +159,19 @@ impl<'s> Compiler<'s> {
         operand: Expr,
         field: &NodePatternField,
     ) -> Result<Expr, CompilationErr> {
-        let field_expr = match &field.desc {
-            NodePatternFieldDesc::Identifier(field) => Expr::prop_access(operand, field.clone()),
-            NodePatternFieldDesc::Index(i) => {
-                Expr::array_index(operand, Expr::Const(Value::Int(*i as i64)))
-            }
-        };
+        let field_expr = field_access(operand, &field.desc);
 
         let field_predicate = match &field.value {
             NodePatternFieldValue::Text(t) => Expr::eq_eq(
                 field_expr,
                 Expr::const_expr(Value::String(Cow::Owned(t.to_string()))),
             ),
+            NodePatternFieldValue::Regex(r) => self.with_value(field_expr, |_, value_adr| {
+                Ok(Expr::and(
+                    Expr::neq(Expr::read_var(value_adr), Expr::const_expr(Value::Null)),
+                    Expr::regex_match(Expr::node_text(Expr::read_var(value_adr)), r.clone()),
+                ))
+            })?,
             NodePatternFieldValue::Pattern(p) => {
                 self.with_value(field_expr, |compiler, value_adr| {
                     let field_pattern_expr = compiler.compile_query_pattern(value_adr, p)?;
@@ -144,6 +200,8 @@ impl<'s> Compiler<'s> {
             }
             SyntaxExpr::DotAccess(safe, op, p) => self.dot_access(*safe, op, p),
             SyntaxExpr::DotCall(safe, op, callee, args) => self.dot_call(*safe, op, callee, args),
+            SyntaxExpr::Call(callee, args) => self.call(callee, args),
+            SyntaxExpr::Let(name, value, body) => self.let_expr(name, value, body),
             SyntaxExpr::Not(e) => Ok(Expr::not_expr(self.expr(e)?)),
             SyntaxExpr::Binop(l, o, r) => {
                 let left = self.expr(l)?;
@@ -164,6 +222,7 @@ impl<'s> Compiler<'s> {
             SyntaxExpr::ArrayQuant(min_count, origin, predicate) => {
                 self.array_quant(min_count, origin, predicate)
             }
+            SyntaxExpr::Axis(operand, axis, pattern) => self.axis(operand, *axis, pattern),
             SyntaxExpr::RegexLit(r) => Ok(Expr::const_expr(Value::String(Cow::Owned(
                 r.as_str().to_string(),
             )))),
@@ -214,6 +273,7 @@ impl<'s> Compiler<'s> {
             "previous_siblings" => make_build_gen(DepthNodeGeneratorFn::PreviousSiblings)?,
             "next_siblings" => make_build_gen(DepthNodeGeneratorFn::NextSiblings)?,
             "referenced_decls" => Expr::referenced_decl(operand.clone()),
+            "clones" => Expr::node_clones(operand.clone()),
             "matches" => {
                 if args.len() != 1 {
                     return Err(CompilationErr::UnexpectedArity(
@@ -250,6 +310,232 @@ impl<'s> Compiler<'s> {
         Ok(make_safe(safe, operand, call_expr))
     }
 
+    fn call(&mut self, callee: &str, args: &[Arg]) -> Result<Expr, CompilationErr> {
+        let args: Vec<&SyntaxExpr> = args
+            .iter()
+            .map(|a| match a {
+                Arg::Unnamed(e) => Ok(e),
+                Arg::Named(name, _) => Err(CompilationErr::UnexpectedArg(
+                    callee.to_string(),
+                    format!("no named argument, got: {name}"),
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let expected_arity = match callee {
+            "text" | "normalized_text" | "constant_value" | "resolves" | "declaration_of"
+            | "callers" | "callees" => 1,
+            "matches_regex" | "contains_text" | "matched_by" => 2,
+            "decorators" | "has_decorator" | "return_annotation" | "docstring"
+            | "inferred_type" => {
+                return self.python_call(callee, &args);
+            }
+            "property_wrappers" | "has_property_wrapper" | "is_view_body" => {
+                return self.swift_call(callee, &args);
+            }
+            _ => return self.predicate_call(callee, &args),
+        };
+
+        if args.len() != expected_arity {
+            return Err(CompilationErr::UnexpectedArity(
+                callee.to_string(),
+                args.len(),
+            ));
+        }
+
+        let operand = self.expr(args[0])?;
+
+        match callee {
+            "constant_value" => return Ok(Expr::constant_value(operand)),
+            "resolves" => return Ok(Expr::resolves(operand)),
+            "declaration_of" => return Ok(Expr::declaration_of(operand)),
+            "callers" => return Ok(Expr::callers(operand)),
+            "callees" => return Ok(Expr::callees(operand)),
+            _ => {}
+        }
+
+        if callee == "matched_by" {
+            return match args[1] {
+                SyntaxExpr::StringLit(rule) => Ok(Expr::matched_by(operand, rule.clone())),
+                _ => Err(CompilationErr::UnexpectedArg(
+                    callee.to_string(),
+                    "rule id".to_string(),
+                )),
+            };
+        }
+
+        let text = Expr::node_text(operand);
+
+        match (callee, args.get(1)) {
+            ("text", _) => Ok(text),
+            ("normalized_text", _) => Ok(Expr::normalized_text(text)),
+            ("matches_regex", Some(SyntaxExpr::RegexLit(r))) => {
+                Ok(Expr::regex_match(text, r.clone()))
+            }
+            ("matches_regex", Some(SyntaxExpr::StringLit(s))) => ExprRegex::new(s)
+                .map(|r| Expr::regex_match(text, r))
+                .map_err(|_| CompilationErr::InvalidRegex(s.clone())),
+            ("contains_text", Some(SyntaxExpr::StringLit(s))) => {
+                Ok(Expr::contains_text(text, s.clone()))
+            }
+            _ => Err(CompilationErr::UnexpectedArg(
+                callee.to_string(),
+                "string literal".to_string(),
+            )),
+        }
+    }
+
+    fn python_call(&mut self, callee: &str, args: &[&SyntaxExpr]) -> Result<Expr, CompilationErr> {
+        let kinds = PythonKinds::from_spec(self.spec)
+            .ok_or_else(|| CompilationErr::PythonOnly(callee.to_string()))?;
+
+        let expected_arity = if callee == "has_decorator" { 2 } else { 1 };
+
+        if args.len() != expected_arity {
+            return Err(CompilationErr::UnexpectedArity(
+                callee.to_string(),
+                args.len(),
+            ));
+        }
+
+        let operand = self.expr(args[0])?;
+
+        match (callee, args.get(1)) {
+            ("decorators", _) => Ok(Expr::decorators(operand, kinds)),
+            ("return_annotation", _) => Ok(Expr::return_annotation(operand, kinds)),
+            ("docstring", _) => Ok(Expr::docstring(operand, kinds)),
+            ("inferred_type", _) => Ok(Expr::inferred_type(operand, kinds)),
+            ("has_decorator", Some(SyntaxExpr::StringLit(name))) => {
+                Ok(Expr::has_decorator(operand, kinds, name.clone()))
+            }
+            _ => Err(CompilationErr::UnexpectedArg(
+                callee.to_string(),
+                "string literal".to_string(),
+            )),
+        }
+    }
+
+    fn swift_call(&mut self, callee: &str, args: &[&SyntaxExpr]) -> Result<Expr, CompilationErr> {
+        let kinds = SwiftKinds::from_spec(self.spec)
+            .ok_or_else(|| CompilationErr::SwiftOnly(callee.to_string()))?;
+
+        let expected_arity = if callee == "has_property_wrapper" {
+            2
+        } else {
+            1
+        };
+
+        if args.len() != expected_arity {
+            return Err(CompilationErr::UnexpectedArity(
+                callee.to_string(),
+                args.len(),
+            ));
+        }
+
+        let operand = self.expr(args[0])?;
+
+        match (callee, args.get(1)) {
+            ("property_wrappers", _) => Ok(Expr::property_wrappers(operand, kinds)),
+            ("is_view_body", _) => Ok(Expr::is_view_body(operand, kinds)),
+            ("has_property_wrapper", Some(SyntaxExpr::StringLit(name))) => {
+                Ok(Expr::has_property_wrapper(operand, kinds, name.clone()))
+            }
+            _ => Err(CompilationErr::UnexpectedArg(
+                callee.to_string(),
+                "string literal".to_string(),
+            )),
+        }
+    }
+
+    fn predicate_call(
+        &mut self,
+        callee: &str,
+        args: &[&SyntaxExpr],
+    ) -> Result<Expr, CompilationErr> {
+        let predicates = self.predicates;
+
+        let def = predicates
+            .get(callee)
+            .ok_or_else(|| CompilationErr::InvalidPropertyName(callee.to_string()))?;
+
+        if args.len() != def.params.len() {
+            return Err(CompilationErr::UnexpectedArity(
+                callee.to_string(),
+                args.len(),
+            ));
+        }
+
+        if self.expanding.iter().any(|p| p == callee) {
+            return Err(CompilationErr::RecursivePredicate(callee.to_string()));
+        }
+
+        let mut values = Vec::with_capacity(args.len());
+        let mut params = HashMap::new();
+
+        // Each argument is evaluated after the previous ones have been pushed to the memory.
+        for (param, arg) in def.params.iter().zip(args) {
+            values.push(self.expr(arg)?);
+            params.insert(param.clone(), self.reserve_var());
+        }
+
+        // The body of the predicate only sees its parameters.
+        let caller_bindings = std::mem::replace(&mut self.bindings, params);
+        self.expanding.push(callee.to_string());
+        let body = self.expr(&def.body);
+        self.expanding.pop();
+        self.bindings = caller_bindings;
+
+        for _ in &values {
+            self.release_var();
+        }
+
+        Ok(Expr::in_context(values, body?))
+    }
+
+    /// Compile the expression with the given bindings, each bound to the value of a path of
+    /// fields of the input node.
+    fn with_field_bindings(
+        &mut self,
+        bindings: &[(&str, Vec<&NodePatternFieldDesc>)],
+        expr: &SyntaxExpr,
+    ) -> Result<Expr, CompilationErr> {
+        let Some(((name, path), rest)) = bindings.split_first() else {
+            return self.expr(expr);
+        };
+
+        let value = path
+            .iter()
+            .fold(Expr::read_var(DEFAULT_INPUT_ADDR), |operand, desc| {
+                field_access(operand, desc)
+            });
+
+        self.with_value(value, |compiler, value_addr| {
+            compiler.bindings.insert(name.to_string(), value_addr);
+            compiler.with_field_bindings(rest, expr)
+        })
+    }
+
+    fn let_expr(
+        &mut self,
+        name: &str,
+        value: &SyntaxExpr,
+        body: &SyntaxExpr,
+    ) -> Result<Expr, CompilationErr> {
+        let compiled_value = self.expr(value)?;
+
+        self.with_value(compiled_value, |compiler, value_addr| {
+            let shadowed = compiler.bindings.insert(name.to_string(), value_addr);
+            let body = compiler.expr(body);
+
+            match shadowed {
+                Some(addr) => compiler.bindings.insert(name.to_string(), addr),
+                None => compiler.bindings.remove(name),
+            };
+
+            body
+        })
+    }
+
     fn make_build_gen(
         prop_name: &str,
         operand: Expr,
@@ -332,7 +618,34 @@ impl<'s> Compiler<'s> {
         pattern: &QueryPattern,
     ) -> Result<Expr, CompilationErr> {
         let origin = self.expr(origin)?;
+        self.quantified(quant, origin, pattern)
+    }
+
+    /// Axes are compiled to an `any` quantifier over the matching node generator, so the
+    /// traversal stops at the first matching node.
+    fn axis(
+        &mut self,
+        operand: &SyntaxExpr,
+        axis: Axis,
+        pattern: &QueryPattern,
+    ) -> Result<Expr, CompilationErr> {
+        let gen_fn = match axis {
+            Axis::Ancestor => DepthNodeGeneratorFn::Parents,
+            Axis::Descendant => DepthNodeGeneratorFn::Descendants,
+            Axis::PrecedingSibling => DepthNodeGeneratorFn::PreviousSiblings,
+            Axis::FollowingSibling => DepthNodeGeneratorFn::NextSiblings,
+        };
 
+        let origin = Expr::build_gen(self.expr(operand)?, (None, None), gen_fn);
+        self.quantified(&ArrayQuantQuant::Any, origin, pattern)
+    }
+
+    fn quantified(
+        &mut self,
+        quant: &ArrayQuantQuant,
+        origin: Expr,
+        pattern: &QueryPattern,
+    ) -> Result<Expr, CompilationErr> {
         self.with_value(origin, |compiler, origin_addr| {
             let children_addr = compiler.reserve_var();
             let predicate = compiler.compile_query_pattern(children_addr, pattern)?;
@@ -392,11 +705,25 @@ impl<'s> Compiler<'s> {
         }
     }
 
-    fn get_kind_id(&self, kind_name: &str) -> Result<KindId, CompilationErr> {
-        match self.spec.syntax.kind_id(kind_name) {
-            Some(id) => Ok(id),
-            None => Err(CompilationErr::InvalidKind(strip_list_kind(kind_name))),
+    fn get_kind_id(&mut self, kind_name: &str) -> Result<KindId, CompilationErr> {
+        if let Some(id) = self.spec.syntax.kind_id(kind_name) {
+            return Ok(id);
         }
+
+        let syntax = &self.spec.syntax;
+
+        let Some(new_name) = syntax.renamed_kind(kind_name) else {
+            return Err(CompilationErr::InvalidKind(strip_list_kind(kind_name)));
+        };
+
+        let id = syntax.kind_id(new_name).ok_or_else(|| {
+            CompilationErr::InvalidRenamedKind(kind_name.to_string(), new_name.to_string())
+        })?;
+
+        self.renamed_kinds
+            .insert(kind_name.to_string(), new_name.to_string());
+
+        Ok(id)
     }
 
     fn reserve_var(&mut self) -> usize {
@@ -414,6 +741,107 @@ pub fn compile(spec: &Spec, query: &QueryPattern) -> Result<Expr, CompilationErr
     Compiler::for_spec(spec).compile(query)
 }
 
+/// Compile a query that may call the given user-defined predicates.
+pub fn compile_with_predicates(
+    spec: &Spec,
+    predicates: &BTreeMap<String, PredicateDef>,
+    query: &QueryPattern,
+) -> Result<Expr, CompilationErr> {
+    compile_with_renames(spec, predicates, query).map(|(expr, _)| expr)
+}
+
+/// Same as `compile_with_predicates`, also returning the renamed kinds that the query refers to by
+/// their former name (former name -> new name).
+pub fn compile_with_renames(
+    spec: &Spec,
+    predicates: &BTreeMap<String, PredicateDef>,
+    query: &QueryPattern,
+) -> Result<(Expr, BTreeMap<String, String>), CompilationErr> {
+    let mut compiler = Compiler::for_spec(spec).with_predicates(predicates);
+    let expr = compiler.compile(query)?;
+    Ok((expr, compiler.renamed_kinds))
+}
+
+fn field_access(operand: Expr, desc: &NodePatternFieldDesc) -> Expr {
+    match desc {
+        NodePatternFieldDesc::Identifier(field) => Expr::prop_access(operand, field.clone()),
+        NodePatternFieldDesc::Index(i) => {
+            Expr::array_index(operand, Expr::Const(Value::Int(*i as i64)))
+        }
+    }
+}
+
+/// Bindings of the field patterns nested in the given pattern, with the path of fields leading
+/// to their node. The fields of patterns with alternatives are left out, as they depend on the
+/// matched alternative.
+fn nested_bindings<'q>(
+    patterns: &'q NodePatternsWithBinding,
+    path: Vec<&'q NodePatternFieldDesc>,
+    bindings: &mut Vec<(&'q str, Vec<&'q NodePatternFieldDesc>)>,
+) {
+    if let Some(binding) = patterns.binding.as_deref().filter(|_| !path.is_empty()) {
+        bindings.push((binding, path.clone()));
+    }
+
+    if !patterns.node_patterns.tail.is_empty() {
+        return;
+    }
+
+    for field in &patterns.node_patterns.head.fields {
+        if let NodePatternFieldValue::Pattern(pattern) = &field.value {
+            let mut field_path = path.clone();
+            field_path.push(&field.desc);
+            nested_bindings(&pattern.node_pattern, field_path, bindings);
+        }
+    }
+}
+
+/// Compile an expression evaluated on the nodes matched by the given query, in which the binding
+/// of the query refers to the matched node, and the bindings of its field patterns to the value
+/// of their field.
+pub fn compile_template_expr(
+    spec: &Spec,
+    predicates: &BTreeMap<String, PredicateDef>,
+    query: &QueryPattern,
+    expr: &SyntaxExpr,
+) -> Result<Expr, CompilationErr> {
+    let mut compiler = Compiler::for_spec(spec).with_predicates(predicates);
+
+    if let Some(binding) = &query.node_pattern.binding {
+        compiler
+            .bindings
+            .insert(binding.clone(), DEFAULT_INPUT_ADDR);
+    }
+
+    let mut bindings = vec![];
+    nested_bindings(&query.node_pattern, vec![], &mut bindings);
+
+    compiler.with_field_bindings(&bindings, expr)
+}
+
+/// Return the kinds of the nodes that can be matched by the given query, or `None` if the query
+/// can match nodes of any kind.
+pub fn candidate_kinds(
+    spec: &Spec,
+    query: &QueryPattern,
+) -> Result<Option<BTreeSet<KindId>>, CompilationErr> {
+    let mut compiler = Compiler::for_spec(spec);
+    let mut kinds = BTreeSet::new();
+
+    for pattern in query.node_pattern.node_patterns.iter() {
+        match &pattern.kind_pattern {
+            KindPattern::KindName(n) => {
+                let kind_id = compiler.get_kind_id(n)?;
+                kinds.insert(kind_id);
+                kinds.extend(spec.child_kinds(kind_id));
+            }
+            KindPattern::Placeholder => return Ok(None),
+        }
+    }
+
+    Ok(Some(kinds))
+}
+
 fn make_kind_check(operand: Expr, kind: KindId) -> Expr {
     Expr::eq_eq(Expr::kind_access(operand), Expr::const_expr(kind.into()))
 }
@@ -434,9 +862,12 @@ fn make_safe(safe: bool, operand: Expr, access_expr: Expr) -> Expr {
 mod tests {
     use indoc::indoc;
 
-    use sylver_dsl::sylq::{parse_expr, parse_query};
+    use sylver_dsl::sylq::{parse_expr, parse_predicate_def, parse_query, parse_template_expr};
 
-    use crate::core::spec::test::parse_spec;
+    use crate::{
+        builtin_langs::{get_builtin_lang, BuiltinLang},
+        core::spec::test::parse_spec,
+    };
 
     use super::*;
 
@@ -467,6 +898,138 @@ mod tests {
         )
     }
 
+    #[test]
+    fn compile_text_helpers() {
+        let mut compiler = Compiler::for_spec(&parse_spec("node NodeKind {}"));
+        compiler.bindings.insert("n".to_string(), 0);
+
+        let text = || Expr::node_text(Expr::read_var(0));
+
+        assert_eq!(
+            compiler.expr(&parse_expr("normalized_text(n)")).unwrap(),
+            Expr::normalized_text(text())
+        );
+
+        assert_eq!(
+            compiler
+                .expr(&parse_expr("contains_text(n, 'TODO')"))
+                .unwrap(),
+            Expr::contains_text(text(), "TODO".to_string())
+        );
+
+        assert_eq!(
+            compiler
+                .expr(&parse_expr("matches_regex(n, '[a-z]+')"))
+                .unwrap(),
+            Expr::regex_match(text(), fancy_regex::Regex::new("[a-z]+").unwrap().into())
+        );
+
+        assert_eq!(
+            compiler.expr(&parse_expr("constant_value(n)")).unwrap(),
+            Expr::constant_value(Expr::read_var(0))
+        );
+    }
+
+    #[test]
+    fn compile_text_helper_arity() {
+        let mut compiler = Compiler::for_spec(&parse_spec("node NodeKind {}"));
+        compiler.bindings.insert("n".to_string(), 0);
+
+        assert_eq!(
+            compiler.expr(&parse_expr("contains_text(n)")),
+            Err(CompilationErr::UnexpectedArity(
+                "contains_text".to_string(),
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn compile_python_helpers() {
+        let (mappings, _, _) = get_builtin_lang(BuiltinLang::Python);
+        let spec = Spec::new(Default::default(), mappings.types.as_slice().into());
+        let kinds = PythonKinds::from_spec(&spec).unwrap();
+
+        let mut compiler = Compiler::for_spec(&spec);
+        compiler.bindings.insert("f".to_string(), 0);
+
+        assert_eq!(
+            compiler.expr(&parse_expr("docstring(f)")).unwrap(),
+            Expr::docstring(Expr::read_var(0), kinds)
+        );
+
+        assert_eq!(
+            compiler
+                .expr(&parse_expr("has_decorator(f, 'staticmethod')"))
+                .unwrap(),
+            Expr::has_decorator(Expr::read_var(0), kinds, "staticmethod".to_string())
+        );
+
+        assert_eq!(
+            compiler.expr(&parse_expr("inferred_type(f)")).unwrap(),
+            Expr::inferred_type(Expr::read_var(0), kinds)
+        );
+
+        assert_eq!(
+            compiler.expr(&parse_expr("has_decorator(f)")),
+            Err(CompilationErr::UnexpectedArity(
+                "has_decorator".to_string(),
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn compile_python_helpers_other_language() {
+        let mut compiler = Compiler::for_spec(&parse_spec("node NodeKind {}"));
+        compiler.bindings.insert("f".to_string(), 0);
+
+        assert_eq!(
+            compiler.expr(&parse_expr("decorators(f)")),
+            Err(CompilationErr::PythonOnly("decorators".to_string()))
+        );
+    }
+
+    #[test]
+    fn compile_swift_helpers() {
+        let (mappings, _, _) = get_builtin_lang(BuiltinLang::Swift);
+        let spec = Spec::new(Default::default(), mappings.types.as_slice().into());
+        let kinds = SwiftKinds::from_spec(&spec).unwrap();
+
+        let mut compiler = Compiler::for_spec(&spec);
+        compiler.bindings.insert("p".to_string(), 0);
+
+        assert_eq!(
+            compiler.expr(&parse_expr("is_view_body(p)")).unwrap(),
+            Expr::is_view_body(Expr::read_var(0), kinds)
+        );
+
+        assert_eq!(
+            compiler
+                .expr(&parse_expr("has_property_wrapper(p, 'State')"))
+                .unwrap(),
+            Expr::has_property_wrapper(Expr::read_var(0), kinds, "State".to_string())
+        );
+
+        assert_eq!(
+            compiler.expr(&parse_expr("has_property_wrapper(p, 1)")),
+            Err(CompilationErr::UnexpectedArg(
+                "has_property_wrapper".to_string(),
+                "string literal".to_string()
+            ))
+        );
+
+        let (python_mappings, _, _) = get_builtin_lang(BuiltinLang::Python);
+        let python_spec = Spec::new(Default::default(), python_mappings.types.as_slice().into());
+        let mut python_compiler = Compiler::for_spec(&python_spec);
+        python_compiler.bindings.insert("p".to_string(), 0);
+
+        assert_eq!(
+            python_compiler.expr(&parse_expr("property_wrappers(p)")),
+            Err(CompilationErr::SwiftOnly("property_wrappers".to_string()))
+        );
+    }
+
     #[test]
     fn compile_safe_regex_match() {
         let compiled = Compiler::for_spec(&parse_spec("node NodeKind {}"))
@@ -618,6 +1181,37 @@ mod tests {
         )
     }
 
+    #[test]
+    fn candidate_kinds_with_child_kinds() {
+        let spec = parse_spec(indoc!(
+            "
+            node ParentNode { }
+            node ChildNode: ParentNode { }
+            node OtherNode { }
+            node UnrelatedNode { }
+        "
+        ));
+
+        let query = parse_query("match ParentNode | OtherNode n when n.kind == n.kind").unwrap();
+
+        let parent_kind = spec.syntax.kind_id("ParentNode").unwrap();
+        let child_kind = spec.syntax.kind_id("ChildNode").unwrap();
+        let other_kind = spec.syntax.kind_id("OtherNode").unwrap();
+
+        assert_eq!(
+            candidate_kinds(&spec, &query).unwrap(),
+            Some(BTreeSet::from([parent_kind, child_kind, other_kind]))
+        );
+    }
+
+    #[test]
+    fn candidate_kinds_placeholder() {
+        let spec = parse_spec("node NodeKind { }");
+        let query = parse_query("match NodeKind | _").unwrap();
+
+        assert_eq!(candidate_kinds(&spec, &query).unwrap(), None);
+    }
+
     #[test]
     fn field_values_pattern() {
         let spec = parse_spec(indoc!(
@@ -706,6 +1300,43 @@ mod tests {
         )
     }
 
+    #[test]
+    fn field_regex_pattern() {
+        let spec = parse_spec(indoc!(
+            "
+            node NodeKind {
+                name: NodeKind
+            }
+        "
+        ));
+
+        let query = parse_query("match NodeKind(name: /^test_/)").unwrap();
+
+        let compiled = compile(&spec, &query).unwrap();
+
+        let node_kind = spec.syntax.kind_id("NodeKind").unwrap();
+
+        assert_eq!(
+            compiled,
+            Expr::and(
+                Expr::eq_eq(
+                    Expr::kind_access(Expr::read_var(0)),
+                    Expr::Const(Value::Kind(node_kind)),
+                ),
+                Expr::in_context(
+                    vec![Expr::prop_access(Expr::read_var(0), "name".to_string())],
+                    Expr::and(
+                        Expr::neq(Expr::read_var(1), Expr::Const(Value::Null)),
+                        Expr::regex_match(
+                            Expr::node_text(Expr::read_var(1)),
+                            fancy_regex::Regex::new("^test_").unwrap().into(),
+                        ),
+                    ),
+                ),
+            )
+        )
+    }
+
     #[test]
     fn compile_array_index() {
         let spec = parse_spec(indoc!("node NodeKind { }"));
@@ -782,6 +1413,71 @@ mod tests {
         )
     }
 
+    #[test]
+    fn compile_renamed_kind() {
+        let mut spec = parse_spec("node NodeKind { }");
+        spec.syntax = spec
+            .syntax
+            .with_renamed_kinds([("OldKind".to_string(), "NodeKind".to_string())]);
+
+        let (renamed, renames) = compile_with_renames(
+            &spec,
+            &BTreeMap::new(),
+            &parse_query("match OldKind").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            renamed,
+            compile(&spec, &parse_query("match NodeKind").unwrap()).unwrap()
+        );
+        assert_eq!(
+            renames,
+            BTreeMap::from([("OldKind".to_string(), "NodeKind".to_string())])
+        );
+    }
+
+    #[test]
+    fn compile_renamed_kind_chain() {
+        let mut spec = parse_spec("node NodeKind { }");
+        spec.syntax = spec.syntax.with_renamed_kinds([
+            ("OldestKind".to_string(), "OldKind".to_string()),
+            ("OldKind".to_string(), "NodeKind".to_string()),
+        ]);
+
+        let (renamed, renames) = compile_with_renames(
+            &spec,
+            &BTreeMap::new(),
+            &parse_query("match OldestKind").unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            renamed,
+            compile(&spec, &parse_query("match NodeKind").unwrap()).unwrap()
+        );
+        assert_eq!(
+            renames,
+            BTreeMap::from([("OldestKind".to_string(), "NodeKind".to_string())])
+        );
+    }
+
+    #[test]
+    fn compile_renamed_to_missing_kind() {
+        let mut spec = parse_spec("node NodeKind { }");
+        spec.syntax = spec
+            .syntax
+            .with_renamed_kinds([("OldKind".to_string(), "MissingKind".to_string())]);
+
+        assert_eq!(
+            compile(&spec, &parse_query("match OldKind").unwrap()),
+            Err(CompilationErr::InvalidRenamedKind(
+                "OldKind".to_string(),
+                "MissingKind".to_string()
+            ))
+        );
+    }
+
     #[test]
     fn compile_or_kind_check() {
         let spec_str = indoc!(
@@ -923,6 +1619,37 @@ mod tests {
         )
     }
 
+    #[test]
+    fn compile_ancestor_axis() {
+        let spec = parse_spec("node NodeKind {}");
+        let query = parse_query("match _ n when n has ancestor::NodeKind").unwrap();
+        let compiled = compile(&spec, &query).unwrap();
+
+        let kind = spec.syntax.kind_id("NodeKind");
+
+        assert_eq!(
+            compiled,
+            Expr::and(
+                Expr::const_expr(true.into()),
+                Expr::in_context(
+                    vec![Expr::build_gen(
+                        Expr::read_var(DEFAULT_INPUT_ADDR),
+                        (None, None),
+                        DepthNodeGeneratorFn::Parents,
+                    )],
+                    Expr::count_check_min(
+                        Expr::const_expr(1.into()),
+                        Expr::read_var(1),
+                        Expr::eq_eq(
+                            Expr::kind_access(Expr::read_var(2)),
+                            Expr::const_expr(kind.into()),
+                        ),
+                    ),
+                ),
+            ),
+        )
+    }
+
     #[test]
     fn compile_kind_check_with_inheritance_child() {
         let spec = parse_spec(indoc!(
@@ -1235,6 +1962,54 @@ mod tests {
         )
     }
 
+    #[test]
+    fn compile_name_resolution_calls() {
+        let spec = parse_spec("node NodeKind { }");
+        let query =
+            parse_query("match n@_ when !resolves(n) || declaration_of(n) == n.parent").unwrap();
+        let compiled = compile(&spec, &query).unwrap();
+
+        assert_eq!(
+            compiled,
+            Expr::and(
+                Expr::const_expr(true.into()),
+                Expr::or(
+                    Expr::not_expr(Expr::resolves(Expr::read_var(DEFAULT_INPUT_ADDR))),
+                    Expr::eq_eq(
+                        Expr::declaration_of(Expr::read_var(DEFAULT_INPUT_ADDR)),
+                        Expr::node_parent(Expr::read_var(DEFAULT_INPUT_ADDR)),
+                    ),
+                )
+            ),
+        )
+    }
+
+    #[test]
+    fn compile_pass_calls() {
+        let spec = parse_spec("node NodeKind { }");
+        let query =
+            parse_query("match n@_ when matched_by(n, 'other') || callers(n) == callees(n)")
+                .unwrap();
+        let compiled = compile(&spec, &query).unwrap();
+
+        assert_eq!(
+            compiled,
+            Expr::and(
+                Expr::const_expr(true.into()),
+                Expr::or(
+                    Expr::matched_by(Expr::read_var(DEFAULT_INPUT_ADDR), "other".to_string()),
+                    Expr::eq_eq(
+                        Expr::callers(Expr::read_var(DEFAULT_INPUT_ADDR)),
+                        Expr::callees(Expr::read_var(DEFAULT_INPUT_ADDR)),
+                    ),
+                )
+            ),
+        );
+
+        let query = parse_query("match n@_ when matched_by(n, n)").unwrap();
+        assert!(compile(&spec, &query).is_err());
+    }
+
     #[test]
     fn compile_nested_is() {
         let spec = parse_spec("node NodeKind {}");
@@ -1265,4 +2040,131 @@ mod tests {
             )
         );
     }
+
+    fn test_predicates(defs: &[&str]) -> BTreeMap<String, PredicateDef> {
+        defs.iter()
+            .map(|d| parse_predicate_def(d).unwrap())
+            .map(|d| (d.name.clone(), d))
+            .collect()
+    }
+
+    #[test]
+    fn compile_let() {
+        let spec = parse_spec("node NodeKind {}");
+        let query = parse_query("match _ n when let p = n.parent in p == null").unwrap();
+        let compiled = compile(&spec, &query).unwrap();
+
+        assert_eq!(
+            compiled,
+            Expr::and(
+                Expr::const_expr(true.into()),
+                Expr::in_context(
+                    vec![Expr::node_parent(Expr::read_var(DEFAULT_INPUT_ADDR))],
+                    Expr::eq_eq(Expr::read_var(1), Expr::const_expr(Value::Null)),
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn compile_predicate_call() {
+        let spec = parse_spec("node NodeKind {}");
+        let predicates = test_predicates(&["def is_root(node) = node.parent == null"]);
+        let query = parse_query("match _ n when is_root(n)").unwrap();
+
+        let compiled = compile_with_predicates(&spec, &predicates, &query).unwrap();
+
+        assert_eq!(
+            compiled,
+            Expr::and(
+                Expr::const_expr(true.into()),
+                Expr::in_context(
+                    vec![Expr::read_var(DEFAULT_INPUT_ADDR)],
+                    Expr::eq_eq(
+                        Expr::node_parent(Expr::read_var(1)),
+                        Expr::const_expr(Value::Null),
+                    ),
+                ),
+            )
+        );
+    }
+
+    #[test]
+    fn compile_predicate_does_not_capture_bindings() {
+        let spec = parse_spec("node NodeKind {}");
+        let predicates = test_predicates(&["def uses_n() = n == null"]);
+        let query = parse_query("match _ n when uses_n()").unwrap();
+
+        assert_eq!(
+            compile_with_predicates(&spec, &predicates, &query),
+            Err(CompilationErr::UnknownIdentifier("n".to_string()))
+        );
+    }
+
+    #[test]
+    fn compile_recursive_predicate() {
+        let spec = parse_spec("node NodeKind {}");
+        let predicates = test_predicates(&["def a(x) = b(x)", "def b(x) = a(x)"]);
+        let query = parse_query("match _ n when a(n)").unwrap();
+
+        assert_eq!(
+            compile_with_predicates(&spec, &predicates, &query),
+            Err(CompilationErr::RecursivePredicate("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn compile_template_expr_binding() {
+        let spec = parse_spec("node NodeKind {}");
+        let query = parse_query("match NodeKind n").unwrap();
+        let compile_template = |code: &str| {
+            let expr = parse_template_expr(code).unwrap();
+            compile_template_expr(&spec, &NO_PREDICATES, &query, &expr)
+        };
+
+        assert_eq!(
+            compile_template("n.parent"),
+            Ok(Expr::node_parent(Expr::read_var(DEFAULT_INPUT_ADDR)))
+        );
+        assert_eq!(
+            compile_template("m.parent"),
+            Err(CompilationErr::UnknownIdentifier("m".to_string()))
+        );
+    }
+
+    #[test]
+    fn compile_template_expr_nested_binding() {
+        let spec = parse_spec(indoc!(
+            "
+            node NodeKind {
+                field1: NodeKind,
+                field2: OtherNodeKind
+            }
+
+            node OtherNodeKind { }
+        "
+        ));
+        let compile_template = |query: &str, code: &str| {
+            let query = parse_query(query).unwrap();
+            let expr = parse_template_expr(code).unwrap();
+            compile_template_expr(&spec, &NO_PREDICATES, &query, &expr)
+        };
+
+        assert_eq!(
+            compile_template("match NodeKind(field1: NodeKind(field2: _ o))", "o.parent"),
+            Ok(Expr::in_context(
+                vec![Expr::prop_access(
+                    Expr::prop_access(Expr::read_var(0), "field1".to_string()),
+                    "field2".to_string()
+                )],
+                Expr::node_parent(Expr::read_var(1))
+            ))
+        );
+
+        // The fields of alternatives depend on the matched alternative.
+        assert_eq!(
+            compile_template("match NodeKind(field2: _ o) | OtherNodeKind", "o.parent"),
+            Err(CompilationErr::UnknownIdentifier("o".to_string()))
+        );
+    }
 }
@@ -1 +1,2 @@
 pub mod compile;
+pub mod typecheck;
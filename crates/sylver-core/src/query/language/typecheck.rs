@@ -0,0 +1,497 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{Display, Formatter},
+};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use sylver_dsl::{
+    meta::TypeLit,
+    sylq::{
+        Arg, Expr as SyntaxExpr, KindPattern, NodePatternFieldDesc, NodePatternFieldValue,
+        NodePatternsWithBinding, Op, QueryPattern,
+    },
+};
+
+use crate::core::spec::{KindId, Spec};
+
+/// Static type of a query expression.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ExprType {
+    /// Node of one of the given kinds, or of any kind.
+    Node(Option<BTreeSet<KindId>>),
+    Bool,
+    Int,
+    Kind,
+    String,
+    Null,
+    /// Type that is only known at runtime, such as the result of a function call.
+    Unknown,
+}
+
+impl ExprType {
+    fn any_node() -> ExprType {
+        ExprType::Node(None)
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ExprType::Node(_) => "a node",
+            ExprType::Bool => "a boolean",
+            ExprType::Int => "an integer",
+            ExprType::Kind => "a node kind",
+            ExprType::String => "a string",
+            ExprType::Null => "null",
+            ExprType::Unknown => "a value",
+        }
+    }
+
+    /// Whether values of the two types can be equal. Nodes are compared to strings by text, and to
+    /// other nodes by identity.
+    fn may_equal(&self, other: &ExprType) -> bool {
+        match (self, other) {
+            (ExprType::Unknown, _) | (_, ExprType::Unknown) => true,
+            (ExprType::Null, _) | (_, ExprType::Null) => true,
+            (ExprType::Node(_), ExprType::String) | (ExprType::String, ExprType::Node(_)) => true,
+            (ExprType::Node(Some(l)), ExprType::Node(Some(r))) => !l.is_disjoint(r),
+            (l, r) => l == r,
+        }
+    }
+
+    fn is_orderable_with(&self, other: &ExprType) -> bool {
+        match (self, other) {
+            (ExprType::Unknown, _) | (_, ExprType::Unknown) => true,
+            (ExprType::Int, ExprType::Int) | (ExprType::String, ExprType::String) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum TypeErr {
+    #[error("No node of kind {kinds} has a field named {field}")]
+    UnknownField { kinds: String, field: String },
+    #[error("Comparing {left} with {right} using {op} is always false")]
+    ImpossibleComparison {
+        left: &'static str,
+        op: &'static str,
+        right: &'static str,
+    },
+}
+
+/// Type error of a query, with the position of the offending token in the query, if it could be
+/// found.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub struct QueryTypeError {
+    pub error: TypeErr,
+    /// Line and column (starting at 1) of the error in the query.
+    pub position: Option<(usize, usize)>,
+}
+
+impl Display for QueryTypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{} (line {line}, column {column})", self.error),
+            None => self.error.fmt(f),
+        }
+    }
+}
+
+/// Check that the fields accessed by the query exist on the kinds of the nodes they are accessed
+/// on, and that its comparisons are not always false. `source` is the code of the query, used to
+/// locate the errors.
+pub fn check_query(spec: &Spec, source: &str, query: &QueryPattern) -> Result<(), QueryTypeError> {
+    let mut checker = TypeChecker {
+        spec,
+        bindings: HashMap::new(),
+        seen_tokens: HashMap::new(),
+    };
+
+    checker
+        .query(query, ExprType::any_node())
+        .map_err(|(error, token, occurrence)| QueryTypeError {
+            error,
+            position: locate_token(source, &token, occurrence),
+        })
+}
+
+/// Type error, with the offending token and the number of occurrences of the token that precede
+/// it in the query.
+type CheckRes<T> = Result<T, (TypeErr, String, usize)>;
+
+struct TypeChecker<'s> {
+    spec: &'s Spec,
+    bindings: HashMap<String, ExprType>,
+    /// Number of times each token was visited, the query being walked in source order.
+    seen_tokens: HashMap<String, usize>,
+}
+
+impl<'s> TypeChecker<'s> {
+    fn query(&mut self, query: &QueryPattern, input: ExprType) -> CheckRes<()> {
+        self.patterns(&query.node_pattern, input)?;
+
+        if let Some(predicate) = &query.predicate {
+            self.expr(predicate)?;
+        }
+
+        Ok(())
+    }
+
+    fn patterns(&mut self, patterns: &NodePatternsWithBinding, input: ExprType) -> CheckRes<()> {
+        let mut all_kinds = Some(BTreeSet::new());
+
+        for pattern in patterns.node_patterns.iter() {
+            let kinds = match &pattern.kind_pattern {
+                KindPattern::KindName(name) => self.kinds_of(name),
+                KindPattern::Placeholder => match &input {
+                    ExprType::Node(kinds) => kinds.clone(),
+                    _ => None,
+                },
+            };
+
+            for field in &pattern.fields {
+                let field_type = match &field.desc {
+                    NodePatternFieldDesc::Identifier(name) => self.field(&kinds, name)?,
+                    NodePatternFieldDesc::Index(_) => ExprType::any_node(),
+                };
+
+                if let NodePatternFieldValue::Pattern(p) = &field.value {
+                    self.query(p, field_type)?;
+                }
+            }
+
+            all_kinds = match (all_kinds, kinds) {
+                (Some(mut all), Some(kinds)) => {
+                    all.extend(kinds);
+                    Some(all)
+                }
+                _ => None,
+            };
+        }
+
+        if let Some(binding) = &patterns.binding {
+            self.bindings
+                .insert(binding.clone(), ExprType::Node(all_kinds));
+        }
+
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &SyntaxExpr) -> CheckRes<ExprType> {
+        let expr_type = match expr {
+            SyntaxExpr::Identifier(i) => self.bindings.get(i).cloned().unwrap_or(ExprType::Unknown),
+            SyntaxExpr::Integer(_) => ExprType::Int,
+            SyntaxExpr::Null => ExprType::Null,
+            SyntaxExpr::StringLit(_) | SyntaxExpr::RegexLit(_) => ExprType::String,
+            SyntaxExpr::DotAccess(_, operand, prop) => {
+                let operand = self.expr(operand)?;
+
+                match prop.as_str() {
+                    "text" => ExprType::String,
+                    "length" => ExprType::Int,
+                    "kind" => ExprType::Kind,
+                    "parent" | "previous_sibling" | "next_sibling" => ExprType::any_node(),
+                    "children" => ExprType::Unknown,
+                    field => match operand {
+                        ExprType::Node(kinds) => self.field(&kinds, field)?,
+                        _ => {
+                            self.visit(field);
+                            ExprType::Unknown
+                        }
+                    },
+                }
+            }
+            SyntaxExpr::DotCall(_, operand, _, args) => {
+                self.expr(operand)?;
+                self.args(args)?;
+                ExprType::Unknown
+            }
+            SyntaxExpr::Call(callee, args) => {
+                self.args(args)?;
+
+                match callee.as_str() {
+                    "resolves" | "matched_by" => ExprType::Bool,
+                    "declaration_of" => ExprType::any_node(),
+                    _ => ExprType::Unknown,
+                }
+            }
+            SyntaxExpr::Let(name, value, body) => {
+                let value_type = self.expr(value)?;
+                let shadowed = self.bindings.insert(name.clone(), value_type);
+                let body_type = self.expr(body)?;
+
+                match shadowed {
+                    Some(previous) => self.bindings.insert(name.clone(), previous),
+                    None => self.bindings.remove(name),
+                };
+
+                body_type
+            }
+            SyntaxExpr::Not(e) => {
+                self.expr(e)?;
+                ExprType::Bool
+            }
+            SyntaxExpr::Binop(l, op, r) => self.binop(l, *op, r)?,
+            SyntaxExpr::Is(operand, pattern) => {
+                let operand = self.expr(operand)?;
+                self.query(pattern, operand)?;
+                ExprType::Bool
+            }
+            SyntaxExpr::ArrayIndex(array, index) => {
+                self.expr(array)?;
+                self.expr(index)?;
+                ExprType::Unknown
+            }
+            SyntaxExpr::ArrayQuant(_, origin, pattern) | SyntaxExpr::Axis(origin, _, pattern) => {
+                self.expr(origin)?;
+                self.query(pattern, ExprType::any_node())?;
+                ExprType::Bool
+            }
+        };
+
+        Ok(expr_type)
+    }
+
+    fn args(&mut self, args: &[Arg]) -> CheckRes<()> {
+        for arg in args {
+            match arg {
+                Arg::Named(_, e) | Arg::Unnamed(e) => self.expr(e)?,
+            };
+        }
+
+        Ok(())
+    }
+
+    fn binop(&mut self, left: &SyntaxExpr, op: Op, right: &SyntaxExpr) -> CheckRes<ExprType> {
+        let left = self.expr(left)?;
+        let op_token = op_token(op);
+        let occurrence = self.visit(op_token);
+        let right = self.expr(right)?;
+
+        let valid = match op {
+            Op::And | Op::Or => true,
+            Op::EqEq | Op::Neq => left.may_equal(&right),
+            Op::Lt | Op::Lte | Op::Ht | Op::Hte => left.is_orderable_with(&right),
+        };
+
+        if !valid {
+            let error = TypeErr::ImpossibleComparison {
+                left: left.describe(),
+                op: op_token,
+                right: right.describe(),
+            };
+            return Err((error, op_token.to_string(), occurrence));
+        }
+
+        Ok(ExprType::Bool)
+    }
+
+    /// Type of the given field of nodes of the given kinds.
+    fn field(&mut self, kinds: &Option<BTreeSet<KindId>>, field: &str) -> CheckRes<ExprType> {
+        let occurrence = self.visit(field);
+
+        let Some(kinds) = kinds else {
+            return Ok(ExprType::any_node());
+        };
+
+        let syntax = &self.spec.syntax;
+
+        let field_types: Vec<&TypeLit> = kinds
+            .iter()
+            .filter(|&&k| !syntax.is_list_kind(k))
+            .filter_map(|&k| syntax.field_type(k, syntax.field_position(k, field)?))
+            .collect();
+
+        if field_types.is_empty() {
+            let error = TypeErr::UnknownField {
+                kinds: kinds.iter().map(|&k| syntax.kind_name(k)).join(" | "),
+                field: field.to_string(),
+            };
+            return Err((error, field.to_string(), occurrence));
+        }
+
+        let mut field_kinds = BTreeSet::new();
+
+        for field_type in field_types {
+            if field_type.is_list() {
+                return Ok(ExprType::any_node());
+            }
+
+            for name in type_names(field_type) {
+                match self.kinds_of(name) {
+                    Some(kinds) => field_kinds.extend(kinds),
+                    None => return Ok(ExprType::any_node()),
+                }
+            }
+        }
+
+        Ok(ExprType::Node(Some(field_kinds)))
+    }
+
+    /// Kind with the given name and its child kinds.
+    fn kinds_of(&self, name: &str) -> Option<BTreeSet<KindId>> {
+        let syntax = &self.spec.syntax;
+        let kind = syntax
+            .kind_id(name)
+            .or_else(|| syntax.kind_id(syntax.renamed_kind(name)?))?;
+
+        let mut kinds: BTreeSet<KindId> = self.spec.child_kinds(kind).into_iter().collect();
+        kinds.insert(kind);
+
+        Some(kinds)
+    }
+
+    /// Record a visit of the token, and return the number of previous visits.
+    fn visit(&mut self, token: &str) -> usize {
+        let count = self.seen_tokens.entry(token.to_string()).or_default();
+        *count += 1;
+        *count - 1
+    }
+}
+
+fn type_names(type_lit: &TypeLit) -> Vec<&str> {
+    match type_lit {
+        TypeLit::Simple(simple) => vec![simple.name.as_str()],
+        TypeLit::Or(or) => or.alts.iter().map(|alt| alt.name.as_str()).collect(),
+    }
+}
+
+fn op_token(op: Op) -> &'static str {
+    match op {
+        Op::Lt => "<",
+        Op::Lte => "<=",
+        Op::Ht => ">",
+        Op::Hte => ">=",
+        Op::Or => "||",
+        Op::And => "&&",
+        Op::EqEq => "==",
+        Op::Neq => "!=",
+    }
+}
+
+/// Line and column of the given occurrence of a token in the query, ignoring literals. Words are
+/// field names, so they must follow a dot or precede a colon.
+fn locate_token(source: &str, token: &str, occurrence: usize) -> Option<(usize, usize)> {
+    let is_field = token.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let is_token_char = |c: char| {
+        if is_field {
+            c.is_alphanumeric() || c == '_'
+        } else {
+            "<>=!&|".contains(c)
+        }
+    };
+
+    let mut delimiter = None;
+    let mut escaped = false;
+    let mut found = 0;
+
+    for (pos, c) in source.char_indices() {
+        if let Some(d) = delimiter {
+            if !escaped && c == d {
+                delimiter = None;
+            }
+            escaped = !escaped && c == '\\';
+            continue;
+        }
+
+        if "'\"`/".contains(c) {
+            delimiter = Some(c);
+            continue;
+        }
+
+        let (before, rest) = source.split_at(pos);
+        let Some(after) = rest.strip_prefix(token) else {
+            continue;
+        };
+
+        let is_standalone = !before.chars().next_back().map_or(false, is_token_char)
+            && !after.chars().next().map_or(false, is_token_char);
+        let is_field_access =
+            before.trim_end().ends_with('.') || after.trim_start().starts_with(':');
+
+        if !is_standalone || (is_field && !is_field_access) {
+            continue;
+        }
+
+        if found == occurrence {
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            let line = before.matches('\n').count() + 1;
+            let column = before[line_start..].chars().count() + 1;
+            return Some((line, column));
+        }
+
+        found += 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+    use sylver_dsl::sylq::parse_query;
+
+    use super::*;
+    use crate::core::spec::test::parse_spec;
+
+    fn check(query: &str) -> Result<(), QueryTypeError> {
+        let spec = parse_spec(indoc!(
+            "
+            node Node { }
+            node Function: Node { name: Identifier, body: List<Statement> }
+            node Statement: Node { }
+            node Return: Statement { value: Identifier }
+            node Identifier: Node { }
+        "
+        ));
+
+        check_query(&spec, query, &parse_query(query).unwrap())
+    }
+
+    #[test]
+    fn valid_queries() {
+        assert!(check("match Function f when f.name.text == 'main'").is_ok());
+        assert!(check("match Function(name: Identifier i) when i == 'main'").is_ok());
+        assert!(check("match Statement s when s.value != null").is_ok());
+        assert!(check("match _ n when n.body.children.length > 2").is_ok());
+        assert!(check("match Node n when n.parent is Function(body: _)").is_ok());
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let error = check("match Function f when f.name.nme == 'main'").unwrap_err();
+
+        assert_eq!(
+            error.error,
+            TypeErr::UnknownField {
+                kinds: "Identifier".to_string(),
+                field: "nme".to_string(),
+            }
+        );
+        assert_eq!(error.position, Some((1, 30)));
+
+        let error = check("match Identifier i when i.parent is Function(vale: _)").unwrap_err();
+        assert_eq!(error.position, Some((1, 46)));
+    }
+
+    #[test]
+    fn impossible_comparisons() {
+        let error =
+            check("match Function f when f.name == 'a' &&\n  f.kind == 'Function'").unwrap_err();
+
+        assert_eq!(
+            error.error,
+            TypeErr::ImpossibleComparison {
+                left: "a node kind",
+                op: "==",
+                right: "a string",
+            }
+        );
+        assert_eq!(error.position, Some((2, 10)));
+
+        assert!(check("match Function f when f.name.text.length < 'a'").is_err());
+        assert!(check("match Function f when f.name.text == 2").is_err());
+        assert!(check("match Function f when f.name == f").is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::{
+    atomic::{self, AtomicUsize},
+    RwLock,
+};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    land::sylva::{SylvaId, SylvaTreeId},
+    query::{
+        expr::{Expr, Value},
+        python::PythonKinds,
+        swift::SwiftKinds,
+        SylvaNode,
+    },
+    tree::NodeId,
+};
+
+/// Maximum number of values memoized for a file. Once it is reached, the values of the file are
+/// dropped and memoized again as they are evaluated.
+const MAX_FILE_VALUES: usize = 4096;
+
+/// Values of the node functions (`docstring`, `decorators`...) already evaluated on a node,
+/// shared by the evaluations of all the rules of a run.
+///
+/// The values are stored per file, so that the memory used is bounded by the number of files.
+#[derive(Debug, Default)]
+pub struct EvalMemo<'b> {
+    files: RwLock<FxHashMap<(SylvaId, SylvaTreeId), FileMemo<'b>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+#[derive(Debug, Default)]
+struct FileMemo<'b> {
+    values: FxHashMap<MemoFunction, FxHashMap<NodeId, Value<'b>>>,
+    len: usize,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct MemoStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl MemoStats {
+    /// Ratio of the lookups that found a value, `None` if there was no lookup.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+impl<'b> EvalMemo<'b> {
+    pub fn get(&self, function: &MemoFunction, node: SylvaNode) -> Option<Value<'b>> {
+        let value = self
+            .files
+            .read()
+            .expect("poisoned memo lock")
+            .get(&(node.sylva, node.tree))
+            .and_then(|file| file.values.get(function))
+            .and_then(|values| values.get(&node.node))
+            .cloned();
+
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, atomic::Ordering::Relaxed);
+
+        value
+    }
+
+    pub fn insert(&self, function: MemoFunction, node: SylvaNode, value: Value<'b>) {
+        let mut files = self.files.write().expect("poisoned memo lock");
+        let file = files.entry((node.sylva, node.tree)).or_default();
+
+        if file.len >= MAX_FILE_VALUES {
+            file.values.clear();
+            file.len = 0;
+        }
+
+        let values = file.values.entry(function).or_default();
+        if values.insert(node.node, value).is_none() {
+            file.len += 1;
+        }
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        MemoStats {
+            hits: self.hits.load(atomic::Ordering::Relaxed),
+            misses: self.misses.load(atomic::Ordering::Relaxed),
+        }
+    }
+}
+
+/// Node function applied by a memoizable expression. The operand is left out, so that the same
+/// function applied to different bindings shares the memoized values. Values are looked up by
+/// the whole function rather than by a hash of it, so two functions never share a value.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum MemoFunction {
+    NormalizedText,
+    NodeClones,
+    ConstantValue,
+    Decorators(PythonKinds),
+    HasDecorator(PythonKinds, String),
+    ReturnAnnotation(PythonKinds),
+    Docstring(PythonKinds),
+    InferredType(PythonKinds),
+    PropertyWrappers(SwiftKinds),
+    HasPropertyWrapper(SwiftKinds, String),
+    IsViewBody(SwiftKinds),
+}
+
+impl MemoFunction {
+    /// Function of an expression whose value only depends on the node its operand evaluates to,
+    /// or `None` if the expression cannot be memoized.
+    pub fn of(expr: &Expr) -> Option<MemoFunction> {
+        let function = match expr {
+            Expr::NormalizedText(_) => MemoFunction::NormalizedText,
+            Expr::NodeClones(_) => MemoFunction::NodeClones,
+            Expr::ConstantValue(_) => MemoFunction::ConstantValue,
+            Expr::Decorators(_, kinds) => MemoFunction::Decorators(*kinds),
+            Expr::HasDecorator(_, kinds, name) => MemoFunction::HasDecorator(*kinds, name.clone()),
+            Expr::ReturnAnnotation(_, kinds) => MemoFunction::ReturnAnnotation(*kinds),
+            Expr::Docstring(_, kinds) => MemoFunction::Docstring(*kinds),
+            Expr::InferredType(_, kinds) => MemoFunction::InferredType(*kinds),
+            Expr::PropertyWrappers(_, kinds) => MemoFunction::PropertyWrappers(*kinds),
+            Expr::HasPropertyWrapper(_, kinds, name) => {
+                MemoFunction::HasPropertyWrapper(*kinds, name.clone())
+            }
+            Expr::IsViewBody(_, kinds) => MemoFunction::IsViewBody(*kinds),
+            _ => return None,
+        };
+
+        Some(function)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        builtin_langs::{get_builtin_lang, BuiltinLang},
+        core::spec::Spec,
+    };
+
+    use super::*;
+
+    #[test]
+    fn function_ignores_operand() {
+        let (mappings, _, _) = get_builtin_lang(BuiltinLang::Python);
+        let spec = Spec::new(Default::default(), mappings.types.as_slice().into());
+        let kinds = PythonKinds::from_spec(&spec).unwrap();
+
+        assert_eq!(
+            MemoFunction::of(&Expr::docstring(Expr::read_var(0), kinds)),
+            MemoFunction::of(&Expr::docstring(Expr::read_var(1), kinds))
+        );
+        assert_ne!(
+            MemoFunction::of(&Expr::docstring(Expr::read_var(0), kinds)),
+            MemoFunction::of(&Expr::decorators(Expr::read_var(0), kinds))
+        );
+        assert_ne!(
+            MemoFunction::of(&Expr::has_decorator(Expr::read_var(0), kinds, "a".into())),
+            MemoFunction::of(&Expr::has_decorator(Expr::read_var(0), kinds, "b".into()))
+        );
+        assert_eq!(
+            MemoFunction::of(&Expr::node_parent(Expr::read_var(0))),
+            None
+        );
+    }
+
+    #[test]
+    fn values_are_bounded_per_file() {
+        let memo = EvalMemo::default();
+        let node = |tree: usize, node: usize| SylvaNode {
+            sylva: 0.into(),
+            tree: tree.into(),
+            node: node.into(),
+        };
+
+        memo.insert(MemoFunction::NodeClones, node(1, 0), Value::Int(0));
+
+        for i in 0..=MAX_FILE_VALUES {
+            memo.insert(
+                MemoFunction::ConstantValue,
+                node(0, i),
+                Value::Int(i as i64),
+            );
+        }
+
+        assert_eq!(memo.get(&MemoFunction::ConstantValue, node(0, 0)), None);
+        assert_eq!(
+            memo.get(&MemoFunction::ConstantValue, node(0, MAX_FILE_VALUES)),
+            Some(Value::Int(MAX_FILE_VALUES as i64))
+        );
+        assert_eq!(
+            memo.get(&MemoFunction::NodeClones, node(1, 0)),
+            Some(Value::Int(0))
+        );
+        assert_eq!(memo.get(&MemoFunction::NodeClones, node(0, 0)), None);
+    }
+
+    #[test]
+    fn hit_rate() {
+        assert_eq!(MemoStats::default().hit_rate(), None);
+        assert_eq!(MemoStats { hits: 3, misses: 1 }.hit_rate(), Some(0.75));
+    }
+}
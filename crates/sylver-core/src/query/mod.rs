@@ -1,5 +1,9 @@
+pub mod constant;
 pub mod expr;
 pub mod language;
+pub mod memo;
+pub mod python;
+pub mod swift;
 
 use crate::{
     core::spec::Spec,
@@ -89,7 +93,51 @@ pub mod test {
     use super::*;
     use std::collections::HashMap;
 
-    use crate::tree::info::tests::TestTreeInfo;
+    use maplit::hashmap;
+    use sylver_dsl::sylq::parse_query;
+
+    use crate::{
+        builtin_langs::{get_builtin_lang, parser::BuiltinParserRunner, BuiltinLang},
+        core::{source::Source, spec::Syntax},
+        land::{builder::LandBuilder, cmds::filter_sylva, LandSpecId},
+        query::language::compile::compile,
+        script::python::PythonScriptEngine,
+        tree::info::tests::TestTreeInfo,
+        util::cancel::CancellationToken,
+    };
+
+    /// Number of nodes of the given Python source matched by the query.
+    pub fn count_python_matches(source: &str, query: &str) -> usize {
+        count_builtin_matches(BuiltinLang::Python, source, query)
+    }
+
+    pub fn count_builtin_matches(builtin: BuiltinLang, source: &str, query: &str) -> usize {
+        let (mappings, lang, _) = get_builtin_lang(builtin);
+        let syntax: Syntax = mappings.types.as_slice().into();
+
+        let runner = BuiltinParserRunner::new(lang, &syntax, mappings);
+        let tree = runner.run(Source::inline(source.to_string(), "BUFFER".to_string()));
+        let sylva = Sylva::new(hashmap! {"buffer".into() => tree });
+
+        let mut builder = LandBuilder::new();
+        let spec_id = builder.add_spec(Spec::new(Default::default(), syntax));
+        let sylva_id = builder
+            .add_sylva(sylva, LandSpecId::BuiltinLangId(spec_id))
+            .unwrap();
+        let land = builder.build();
+
+        let predicate = compile(land.sylva_spec(sylva_id), &parse_query(query).unwrap()).unwrap();
+
+        filter_sylva(
+            &land,
+            PythonScriptEngine::default(),
+            sylva_id,
+            &predicate,
+            &CancellationToken::default(),
+        )
+        .unwrap()
+        .len()
+    }
 
     #[derive(Clone, Default)]
     pub struct TestTreeInfoBuilder<'t> {
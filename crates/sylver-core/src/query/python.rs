@@ -0,0 +1,633 @@
+use std::borrow::Cow;
+
+use crate::{
+    core::spec::{KindId, Spec},
+    query::{
+        expr::{EvalCtx, EvalError, Value},
+        RawTreeInfoBuilder, SylvaNode,
+    },
+};
+
+/// Kinds of the builtin Python language used by the Python helpers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PythonKinds {
+    class_definition: KindId,
+    comment: KindId,
+    decorated_definition: KindId,
+    decorator: KindId,
+    expression_statement: KindId,
+    function_definition: KindId,
+    module: KindId,
+    string: KindId,
+}
+
+impl PythonKinds {
+    /// Return `None` if the spec is not the one of the builtin Python language.
+    pub fn from_spec(spec: &Spec) -> Option<PythonKinds> {
+        let kind = |name: &str| spec.syntax.kind_id(name);
+
+        Some(PythonKinds {
+            class_definition: kind("ClassDefinition")?,
+            comment: kind("Comment")?,
+            decorated_definition: kind("DecoratedDefinition")?,
+            decorator: kind("Decorator")?,
+            expression_statement: kind("ExpressionStatement")?,
+            function_definition: kind("FunctionDefinition")?,
+            module: kind("Module")?,
+            string: kind("String")?,
+        })
+    }
+}
+
+pub fn eval_decorators<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &PythonKinds,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+    let decorators = decorators(ctx, kinds, sylva_node);
+    Ok(Value::List(
+        decorators.into_iter().map(Into::into).collect(),
+    ))
+}
+
+pub fn eval_has_decorator<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &PythonKinds,
+    name: &str,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+
+    let found = decorators(ctx, kinds, sylva_node).into_iter().any(|d| {
+        let decorator = decorator_name(ctx.node_text(d));
+        decorator == name || decorator.rsplit('.').next() == Some(name)
+    });
+
+    Ok(found.into())
+}
+
+pub fn eval_return_annotation<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &PythonKinds,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+
+    match definition(ctx, kinds, sylva_node)? {
+        Some(def) if ctx.node_kind(def) == kinds.function_definition => {
+            ctx.node_field(def, "return_type")
+        }
+        _ => Ok(Value::Null),
+    }
+}
+
+pub fn eval_docstring<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &PythonKinds,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+
+    let body = match definition(ctx, kinds, sylva_node)? {
+        Some(def) if ctx.node_kind(def) == kinds.module => Some(def),
+        Some(def)
+            if [kinds.function_definition, kinds.class_definition]
+                .contains(&ctx.node_kind(def)) =>
+        {
+            ctx.node_field(def, "body")?.try_into().ok()
+        }
+        _ => None,
+    };
+
+    let first_statement = body.and_then(|body| {
+        ctx.childs(body)
+            .into_iter()
+            .find(|&c| ctx.node_kind(c) != kinds.comment)
+    });
+
+    let docstring = first_statement
+        .filter(|&s| ctx.node_kind(s) == kinds.expression_statement)
+        .and_then(|s| match ctx.childs(s).as_slice() {
+            &[string] if ctx.node_kind(string) == kinds.string => Some(string),
+            _ => None,
+        });
+
+    Ok(docstring.into())
+}
+
+pub fn eval_inferred_type<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &PythonKinds,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+    Ok(TypeInference { ctx, kinds }.infer(sylva_node, 0).into())
+}
+
+/// Bound on the number of nested inference steps, which stops recursive definitions.
+const MAX_INFERENCE_DEPTH: usize = 16;
+
+static BUILTIN_CONSTRUCTORS: &[&str] = &[
+    "bool",
+    "bytes",
+    "dict",
+    "float",
+    "frozenset",
+    "int",
+    "list",
+    "set",
+    "str",
+    "tuple",
+];
+
+/// Best-effort inference of the type of Python expressions, from literals, constructor calls,
+/// annotations and the values returned by the functions of the file.
+struct TypeInference<'c, 'b> {
+    ctx: &'c EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    kinds: &'c PythonKinds,
+}
+
+impl<'c, 'b> TypeInference<'c, 'b> {
+    fn infer(&self, node: SylvaNode, depth: usize) -> Option<Cow<'b, str>> {
+        if depth > MAX_INFERENCE_DEPTH {
+            return None;
+        }
+
+        let depth = depth + 1;
+        let builtin = |name: &'static str| Some(Cow::Borrowed(name));
+
+        match self.ctx.kind_name(node) {
+            "Integer" => builtin("int"),
+            "Float" => builtin("float"),
+            "True" | "False" => builtin("bool"),
+            "None" => builtin("None"),
+            "String" => builtin(string_type(self.ctx.node_text(node))),
+            "List" | "ListComprehension" => builtin("list"),
+            "Dictionary" | "DictionaryComprehension" => builtin("dict"),
+            "Set" | "SetComprehension" => builtin("set"),
+            "Tuple" => builtin("tuple"),
+            "ComparisonOperator" | "NotOperator" => builtin("bool"),
+            "ConcatenatedString" | "ParenthesizedExpression" => {
+                self.infer(self.first_child(node)?, depth)
+            }
+            "BooleanOperator" => {
+                let left = self.infer(self.field(node, "left")?, depth)?;
+                let right = self.infer(self.field(node, "right")?, depth)?;
+                (left == right).then_some(left)
+            }
+            "UnaryOperator" => self
+                .infer(self.field(node, "argument")?, depth)
+                .filter(|t| matches!(t.as_ref(), "int" | "float")),
+            "BinaryOperator" => self.infer_binary_operator(node, depth),
+            "Call" => self.infer_call(node, depth),
+            "Await" => {
+                let awaited = self.infer(self.first_child(node)?, depth)?;
+                let result = awaited.strip_prefix("Coroutine[")?.strip_suffix(']')?;
+                Some(Cow::Owned(result.to_string()))
+            }
+            "Identifier" => self.infer_variable(node, depth),
+            _ => None,
+        }
+    }
+
+    fn infer_binary_operator(&self, node: SylvaNode, depth: usize) -> Option<Cow<'b, str>> {
+        let left = self.infer(self.field(node, "left")?, depth)?;
+        let right = self.infer(self.field(node, "right")?, depth)?;
+        let operator = self.ctx.node_text(self.field(node, "operator")?);
+
+        let numeric = |t: &str| matches!(t, "bool" | "int" | "float");
+        let sequence = |t: &str| matches!(t, "str" | "bytes" | "list" | "tuple");
+
+        let result = match (left.as_ref(), operator, right.as_ref()) {
+            (l, "/", r) if numeric(l) && numeric(r) => "float",
+            ("bool" | "int", _, "bool" | "int") => "int",
+            (l, _, r) if numeric(l) && numeric(r) => "float",
+            ("str", "%", _) => "str",
+            (l, "+", r) if sequence(l) && l == r => l,
+            (s, "*", "bool" | "int") | ("bool" | "int", "*", s) if sequence(s) => s,
+            _ => return None,
+        };
+
+        Some(Cow::Owned(result.to_string()))
+    }
+
+    fn infer_call(&self, node: SylvaNode, depth: usize) -> Option<Cow<'b, str>> {
+        let function = self.field(node, "function")?;
+
+        if self.ctx.kind_name(function) != "Identifier" {
+            return None;
+        }
+
+        let name = self.ctx.node_text(function);
+
+        if BUILTIN_CONSTRUCTORS.contains(&name) {
+            return Some(Cow::Borrowed(name));
+        }
+
+        let definition = self.module_definition(node, name)?;
+
+        if self.ctx.node_kind(definition) == self.kinds.class_definition {
+            Some(Cow::Borrowed(name))
+        } else {
+            self.result_type(definition, depth)
+        }
+    }
+
+    /// Type of the value of the given variable reference, from its last assignment in the
+    /// enclosing scopes or from the annotation of the parameter it refers to.
+    fn infer_variable(&self, node: SylvaNode, depth: usize) -> Option<Cow<'b, str>> {
+        let name = self.ctx.node_text(node);
+
+        if let Some(parent) = self.ctx.parent(node) {
+            if self.ctx.kind_name(parent) == "Assignment"
+                && self.field(parent, "left") == Some(node)
+            {
+                return self.assigned_type(parent, depth);
+            }
+        }
+
+        let ancestors: Vec<SylvaNode> =
+            std::iter::successors(self.ctx.parent(node), |&n| self.ctx.parent(n)).collect();
+
+        let scopes = ancestors.iter().copied().filter(|&n| {
+            let kind = self.ctx.node_kind(n);
+            kind == self.kinds.function_definition || kind == self.kinds.module
+        });
+
+        for scope in scopes {
+            let assignment = self
+                .scope_nodes(scope)
+                .into_iter()
+                .take_while(|&n| n != node)
+                .filter(|n| !ancestors.contains(n))
+                .filter(|&n| self.ctx.kind_name(n) == "Assignment")
+                .filter(|&n| {
+                    self.field(n, "left")
+                        .map_or(false, |left| self.ctx.node_text(left) == name)
+                })
+                .last();
+
+            if let Some(assignment) = assignment {
+                return self.assigned_type(assignment, depth);
+            }
+
+            if let Some(parameter_type) = self.parameter_type(scope, name, depth) {
+                return Some(parameter_type);
+            }
+        }
+
+        None
+    }
+
+    fn assigned_type(&self, assignment: SylvaNode, depth: usize) -> Option<Cow<'b, str>> {
+        match self.field(assignment, "type") {
+            Some(annotation) => Some(Cow::Borrowed(self.ctx.node_text(annotation))),
+            None => self.infer(self.field(assignment, "right")?, depth),
+        }
+    }
+
+    fn parameter_type(
+        &self,
+        function: SylvaNode,
+        name: &str,
+        depth: usize,
+    ) -> Option<Cow<'b, str>> {
+        let parameters = self.field(function, "parameters")?;
+
+        self.ctx.childs(parameters).into_iter().find_map(|p| {
+            let parameter_name = match self.ctx.kind_name(p) {
+                "TypedParameter" => self.first_child(p)?,
+                "TypedDefaultParameter" | "DefaultParameter" => self.field(p, "name")?,
+                _ => return None,
+            };
+
+            if self.ctx.node_text(parameter_name) != name {
+                return None;
+            }
+
+            match self.field(p, "type") {
+                Some(annotation) => Some(Cow::Borrowed(self.ctx.node_text(annotation))),
+                None => self.infer(self.field(p, "value")?, depth),
+            }
+        })
+    }
+
+    /// Type of the value returned by a call to the given function.
+    fn result_type(&self, function: SylvaNode, depth: usize) -> Option<Cow<'b, str>> {
+        let returned = match self.field(function, "return_type") {
+            Some(annotation) => Some(Cow::Borrowed(self.ctx.node_text(annotation))),
+            None => self.returned_type(function, depth),
+        };
+
+        if !self.ctx.node_text(function).starts_with("async") {
+            return returned;
+        }
+
+        match returned {
+            Some(t) => Some(Cow::Owned(format!("Coroutine[{t}]"))),
+            None => Some(Cow::Borrowed("Coroutine")),
+        }
+    }
+
+    /// Common type of the values returned by the given function, if they all have the same.
+    fn returned_type(&self, function: SylvaNode, depth: usize) -> Option<Cow<'b, str>> {
+        let mut returned = Cow::Borrowed("None");
+        let mut first = true;
+
+        for node in self.scope_nodes(function) {
+            match self.ctx.kind_name(node) {
+                "Yield" => return None,
+                "ReturnStatement" => {
+                    let value_type = match self.first_child(node) {
+                        Some(value) => self.infer(value, depth)?,
+                        None => Cow::Borrowed("None"),
+                    };
+
+                    if !first && value_type != returned {
+                        return None;
+                    }
+
+                    returned = value_type;
+                    first = false;
+                }
+                _ => {}
+            }
+        }
+
+        Some(returned)
+    }
+
+    /// Return the function or class of the module with the given name.
+    fn module_definition(&self, node: SylvaNode, name: &str) -> Option<SylvaNode> {
+        let module = std::iter::successors(Some(node), |&n| self.ctx.parent(n)).last()?;
+
+        self.scope_nodes(module).into_iter().find(|&n| {
+            self.is_definition(n)
+                && self
+                    .field(n, "name")
+                    .map_or(false, |def_name| self.ctx.node_text(def_name) == name)
+        })
+    }
+
+    /// Nodes of the given scope in source order, without the content of nested definitions.
+    fn scope_nodes(&self, scope: SylvaNode) -> Vec<SylvaNode> {
+        let mut nodes = vec![];
+        let mut stack: Vec<SylvaNode> = self.ctx.childs(scope).into_iter().rev().collect();
+
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+
+            if !self.is_definition(node) && self.ctx.kind_name(node) != "Lambda" {
+                stack.extend(self.ctx.childs(node).into_iter().rev());
+            }
+        }
+
+        nodes
+    }
+
+    fn is_definition(&self, node: SylvaNode) -> bool {
+        let kind = self.ctx.node_kind(node);
+        kind == self.kinds.function_definition || kind == self.kinds.class_definition
+    }
+
+    fn first_child(&self, node: SylvaNode) -> Option<SylvaNode> {
+        self.ctx
+            .childs(node)
+            .into_iter()
+            .find(|&c| self.ctx.node_kind(c) != self.kinds.comment)
+    }
+
+    fn field(&self, node: SylvaNode, field: &str) -> Option<SylvaNode> {
+        self.ctx.node_field(node, field).ok()?.try_into().ok()
+    }
+}
+
+/// Return `bytes` for bytes literals, `str` otherwise.
+fn string_type(literal: &str) -> &'static str {
+    let prefix = literal.split(['"', '\'']).next().unwrap_or_default();
+
+    if prefix.contains(['b', 'B']) {
+        "bytes"
+    } else {
+        "str"
+    }
+}
+
+/// Return the decorators applied to the given definition (or decorated definition).
+fn decorators<'b>(
+    ctx: &EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    kinds: &PythonKinds,
+    node: SylvaNode,
+) -> Vec<SylvaNode> {
+    let decorated = if ctx.node_kind(node) == kinds.decorated_definition {
+        Some(node)
+    } else {
+        ctx.parent(node)
+            .filter(|&p| ctx.node_kind(p) == kinds.decorated_definition)
+    };
+
+    decorated
+        .map(|d| {
+            ctx.childs(d)
+                .into_iter()
+                .filter(|&c| ctx.node_kind(c) == kinds.decorator)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Return the definition wrapped by a decorated definition, or the node itself.
+fn definition<'b>(
+    ctx: &EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    kinds: &PythonKinds,
+    node: SylvaNode,
+) -> Result<Option<SylvaNode>, EvalError> {
+    if ctx.node_kind(node) == kinds.decorated_definition {
+        Ok(ctx.node_field(node, "definition")?.try_into().ok())
+    } else {
+        Ok(Some(node))
+    }
+}
+
+/// Return the name of the decorator, without the `@` and the call arguments.
+fn decorator_name(text: &str) -> String {
+    let text = text.trim_start_matches('@');
+    let callee = text.split('(').next().unwrap_or(text);
+    callee.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::query::test::count_python_matches;
+
+    use super::*;
+
+    static PYTHON_SOURCE: &str = indoc! {r#"
+        import functools
+
+        @staticmethod
+        @functools.lru_cache(maxsize=1)
+        def cached() -> int:
+            """Cached."""
+            return 1
+
+        def plain():
+            # Comment
+            """Plain."""
+            pass
+
+        def undocumented():
+            x = 1
+        "#
+    };
+
+    static TYPED_SOURCE: &str = indoc! {r#"
+        class Point:
+            pass
+
+        def count() -> int:
+            return 1
+
+        def label(n):
+            if n:
+                return "some"
+            return "none"
+
+        async def fetch():
+            return b"data"
+
+        async def main(name: str, retries=3):
+            total = count() + 1.5
+            point = Point()
+            data = await fetch()
+            if name == 1:
+                pass
+            if retries == "3":
+                pass
+            await label(1)
+        "#
+    };
+
+    fn count_matches(query: &str) -> usize {
+        count_python_matches(PYTHON_SOURCE, query)
+    }
+
+    #[test]
+    fn decorators_of_functions() {
+        assert_eq!(
+            count_matches("match FunctionDefinition f when decorators(f).length == 2"),
+            1
+        );
+        assert_eq!(
+            count_matches("match FunctionDefinition f when decorators(f).length == 0"),
+            2
+        );
+    }
+
+    #[test]
+    fn functions_with_decorator() {
+        assert_eq!(
+            count_matches(
+                "match FunctionDefinition f when has_decorator(f, 'functools.lru_cache')"
+            ),
+            1
+        );
+        assert_eq!(
+            count_matches("match FunctionDefinition f when has_decorator(f, 'lru_cache')"),
+            1
+        );
+        assert_eq!(
+            count_matches("match FunctionDefinition f when has_decorator(f, 'cache')"),
+            0
+        );
+    }
+
+    #[test]
+    fn annotated_functions() {
+        assert_eq!(
+            count_matches("match FunctionDefinition f when return_annotation(f) == 'int'"),
+            1
+        );
+    }
+
+    #[test]
+    fn documented_functions() {
+        assert_eq!(
+            count_matches(r#"match FunctionDefinition f when docstring(f) == '"""Plain."""'"#),
+            1
+        );
+        assert_eq!(
+            count_matches("match FunctionDefinition f when docstring(f) == null"),
+            1
+        );
+    }
+
+    #[test]
+    fn inferred_types() {
+        let count_typed = |name: &str, type_name: &str| {
+            count_python_matches(
+                TYPED_SOURCE,
+                &format!(
+                    "match Identifier i when text(i) == '{name}' && inferred_type(i) == '{type_name}'"
+                ),
+            )
+        };
+
+        assert_eq!(count_typed("total", "float"), 1);
+        assert_eq!(count_typed("point", "Point"), 1);
+        assert_eq!(count_typed("data", "bytes"), 1);
+        // Parameters are matched along with their uses.
+        assert_eq!(count_typed("name", "str"), 2);
+        assert_eq!(count_typed("retries", "int"), 2);
+    }
+
+    #[test]
+    fn inferred_call_types() {
+        assert_eq!(
+            count_python_matches(
+                TYPED_SOURCE,
+                "match Call c when inferred_type(c) == 'Coroutine[bytes]'"
+            ),
+            1
+        );
+        assert_eq!(
+            count_python_matches(TYPED_SOURCE, "match Call c when inferred_type(c) == 'str'"),
+            1
+        );
+    }
+
+    #[test]
+    fn mismatched_comparisons() {
+        let query = "match ComparisonOperator c when \
+            (inferred_type(c.left) == 'str' && inferred_type(c.right) == 'int') \
+            || (inferred_type(c.left) == 'int' && inferred_type(c.right) == 'str')";
+
+        assert_eq!(count_python_matches(TYPED_SOURCE, query), 2);
+    }
+
+    #[test]
+    fn awaited_non_coroutines() {
+        let query = "match Await a when inferred_type(a.children[0]) == 'str'";
+        assert_eq!(count_python_matches(TYPED_SOURCE, query), 1);
+    }
+
+    #[test]
+    fn string_types() {
+        assert_eq!(string_type("'a'"), "str");
+        assert_eq!(string_type("f\"{a}\""), "str");
+        assert_eq!(string_type("rb'a'"), "bytes");
+    }
+
+    #[test]
+    fn decorator_names() {
+        assert_eq!(decorator_name("@staticmethod"), "staticmethod");
+        assert_eq!(decorator_name("@ functools.cache"), "functools.cache");
+        assert_eq!(
+            decorator_name("@app.route('/', methods=['GET'])"),
+            "app.route"
+        );
+    }
+}
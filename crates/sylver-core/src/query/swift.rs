@@ -0,0 +1,248 @@
+use crate::{
+    core::spec::{KindId, Spec},
+    query::{
+        expr::{EvalCtx, EvalError, Value},
+        RawTreeInfoBuilder, SylvaNode,
+    },
+};
+
+/// Names under which the SwiftUI `View` protocol is conformed to.
+static VIEW_PROTOCOLS: &[&str] = &["View", "SwiftUI.View"];
+
+/// Kinds of the builtin Swift language used by the Swift helpers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SwiftKinds {
+    attribute: KindId,
+    class_body: KindId,
+    class_declaration: KindId,
+    inheritance_specifier: KindId,
+    modifiers: KindId,
+    property_declaration: KindId,
+    type_annotation: KindId,
+}
+
+impl SwiftKinds {
+    /// Return `None` if the spec is not the one of the builtin Swift language.
+    pub fn from_spec(spec: &Spec) -> Option<SwiftKinds> {
+        let kind = |name: &str| spec.syntax.kind_id(name);
+
+        Some(SwiftKinds {
+            attribute: kind("Attribute")?,
+            class_body: kind("ClassBody")?,
+            class_declaration: kind("ClassDeclaration")?,
+            inheritance_specifier: kind("InheritanceSpecifier")?,
+            modifiers: kind("Modifiers")?,
+            property_declaration: kind("PropertyDeclaration")?,
+            type_annotation: kind("TypeAnnotation")?,
+        })
+    }
+}
+
+pub fn eval_property_wrappers<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &SwiftKinds,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+    let wrappers = property_wrappers(ctx, kinds, sylva_node);
+    Ok(Value::List(wrappers.into_iter().map(Into::into).collect()))
+}
+
+pub fn eval_has_property_wrapper<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &SwiftKinds,
+    name: &str,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+
+    let found = property_wrappers(ctx, kinds, sylva_node)
+        .into_iter()
+        .any(|w| {
+            let wrapper = attribute_name(ctx.node_text(w));
+            wrapper == name || wrapper.rsplit('.').next() == Some(name)
+        });
+
+    Ok(found.into())
+}
+
+/// A view body is the `body` property of a type that conforms to `View`, or a `body` property
+/// whose type is `some View`.
+pub fn eval_is_view_body<'b>(
+    ctx: &mut EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    operand: Value<'b>,
+    kinds: &SwiftKinds,
+) -> Result<Value<'b>, EvalError> {
+    let sylva_node: SylvaNode = operand.try_into()?;
+
+    if ctx.node_kind(sylva_node) != kinds.property_declaration {
+        return Ok(false.into());
+    }
+
+    let name: Option<SylvaNode> = ctx.node_field(sylva_node, "name")?.try_into().ok();
+    if name.map(|n| ctx.node_text(n).trim()) != Some("body") {
+        return Ok(false.into());
+    }
+
+    let opaque_view = ctx
+        .childs(sylva_node)
+        .into_iter()
+        .filter(|&c| ctx.node_kind(c) == kinds.type_annotation)
+        .any(|annotation| {
+            let annotation = compact(ctx.node_text(annotation));
+            let annotated = annotation.trim_start_matches(':');
+            annotated
+                .strip_prefix("some")
+                .map_or(false, |t| VIEW_PROTOCOLS.contains(&t))
+        });
+
+    Ok((opaque_view || in_view_type(ctx, kinds, sylva_node)).into())
+}
+
+/// Return true if the given member is declared in a type (or extension) that conforms to `View`.
+fn in_view_type<'b>(
+    ctx: &EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    kinds: &SwiftKinds,
+    member: SylvaNode,
+) -> bool {
+    let declaration = ctx
+        .parent(member)
+        .filter(|&body| ctx.node_kind(body) == kinds.class_body)
+        .and_then(|body| ctx.parent(body))
+        .filter(|&decl| ctx.node_kind(decl) == kinds.class_declaration);
+
+    declaration.map_or(false, |decl| {
+        ctx.childs(decl)
+            .into_iter()
+            .filter(|&c| ctx.node_kind(c) == kinds.inheritance_specifier)
+            .any(|s| VIEW_PROTOCOLS.contains(&compact(ctx.node_text(s)).as_str()))
+    })
+}
+
+/// Return the attributes (`@State`, `@Binding`...) applied to the given property declaration.
+fn property_wrappers<'b>(
+    ctx: &EvalCtx<'b, RawTreeInfoBuilder<'b>>,
+    kinds: &SwiftKinds,
+    node: SylvaNode,
+) -> Vec<SylvaNode> {
+    if ctx.node_kind(node) != kinds.property_declaration {
+        return vec![];
+    }
+
+    ctx.childs(node)
+        .into_iter()
+        .flat_map(|c| {
+            if ctx.node_kind(c) == kinds.modifiers {
+                ctx.childs(c)
+            } else {
+                vec![c]
+            }
+        })
+        .filter(|&c| ctx.node_kind(c) == kinds.attribute)
+        .collect()
+}
+
+/// Return the name of the attribute, without the `@`, the generic arguments and the call
+/// arguments.
+fn attribute_name(text: &str) -> String {
+    let text = text.trim_start_matches('@');
+    let name = text.split(['(', '<']).next().unwrap_or(text);
+    compact(name)
+}
+
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::{builtin_langs::BuiltinLang, query::test::count_builtin_matches};
+
+    use super::*;
+
+    static SWIFT_SOURCE: &str = indoc! {r#"
+        import SwiftUI
+
+        struct CounterView: View {
+            @State private var count = 0
+            @Environment(\.colorScheme) var colorScheme
+            @SwiftUI.Binding var label: String
+            let title: String
+
+            var body: some View {
+                Text(title)
+            }
+        }
+
+        class Model {
+            @Published var body = ""
+        }
+
+        extension Model {
+            var preview: some View {
+                EmptyView()
+            }
+        }
+        "#
+    };
+
+    fn count_matches(query: &str) -> usize {
+        count_builtin_matches(BuiltinLang::Swift, SWIFT_SOURCE, query)
+    }
+
+    #[test]
+    fn wrapped_properties() {
+        assert_eq!(
+            count_matches("match PropertyDeclaration p when property_wrappers(p).length == 1"),
+            4
+        );
+        assert_eq!(
+            count_matches("match PropertyDeclaration p when property_wrappers(p).length == 0"),
+            3
+        );
+    }
+
+    #[test]
+    fn properties_with_wrapper() {
+        assert_eq!(
+            count_matches("match PropertyDeclaration p when has_property_wrapper(p, 'State')"),
+            1
+        );
+        assert_eq!(
+            count_matches(
+                "match PropertyDeclaration p when has_property_wrapper(p, 'Environment')"
+            ),
+            1
+        );
+        assert_eq!(
+            count_matches("match PropertyDeclaration p when has_property_wrapper(p, 'Binding')"),
+            1
+        );
+        assert_eq!(
+            count_matches("match PropertyDeclaration p when has_property_wrapper(p, 'Bind')"),
+            0
+        );
+    }
+
+    #[test]
+    fn view_bodies() {
+        assert_eq!(
+            count_matches("match PropertyDeclaration p when is_view_body(p)"),
+            1
+        );
+        assert_eq!(
+            count_matches("match ClassDeclaration c when is_view_body(c)"),
+            0
+        );
+    }
+
+    #[test]
+    fn attribute_names() {
+        assert_eq!(attribute_name("@State"), "State");
+        assert_eq!(attribute_name("@Environment(\\.dismiss)"), "Environment");
+        assert_eq!(attribute_name("@SwiftUI.Binding"), "SwiftUI.Binding");
+        assert_eq!(attribute_name("@Wrapper<Int>(x: 1)"), "Wrapper");
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::{core::pos::InclPosRange, land::ruleset::RuleCategory};
+use crate::{core::pos::InclPosRange, land::ruleset::RuleCategory, util::fs::PathPrefixMap};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum ReportKind {
@@ -16,4 +16,32 @@ pub struct Report {
     pub position: InclPosRange,
     pub message: String,
     pub note: Option<String>,
+    /// Link to the documentation of the violated rule.
+    pub doc_url: Option<String>,
+}
+
+/// Code of the reports emitted when a file cannot be parsed correctly.
+pub static PARSE_ERROR_CODE: &str = "parse-error";
+
+impl Report {
+    pub fn parse_error(
+        file_path: impl Into<PathBuf>,
+        position: InclPosRange,
+        message: String,
+    ) -> Report {
+        Report {
+            file_path: file_path.into(),
+            code: PARSE_ERROR_CODE.to_string(),
+            kind: ReportKind::Error,
+            position,
+            message,
+            note: None,
+            doc_url: None,
+        }
+    }
+
+    pub fn with_path_map(mut self, map: &PathPrefixMap) -> Report {
+        self.file_path = map.remap(&self.file_path);
+        self
+    }
 }
@@ -1,19 +1,24 @@
 use std::{
     cell::RefCell,
     collections::{BTreeMap, HashMap},
+    path::Path,
     sync::{Arc, RwLock},
 };
 
 use derivative::Derivative;
 use derive_more::From;
+use num_bigint::BigInt;
 use thiserror::Error;
 
 use crate::{
     query::SylvaNode,
+    script::projection::ProjectedNode,
     semantic::names::{SGraph, ScopeId},
     tree::info::raw::RawTreeInfo,
+    util::cancel::Cancelled,
 };
 
+pub mod projection;
 pub mod python;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Error)]
@@ -30,6 +35,14 @@ pub enum ScriptError {
     InvalidAspectDeclaration,
     #[error("Invalid message type: {0}")]
     InvalidMessageType(String),
+    #[error("Script evaluation cancelled")]
+    Cancelled,
+}
+
+impl From<Cancelled> for ScriptError {
+    fn from(_: Cancelled) -> Self {
+        ScriptError::Cancelled
+    }
 }
 
 /// ScriptError values should never be used concurrently, so it is
@@ -67,18 +80,37 @@ unsafe impl Send for ScriptTreeInfo {}
 #[derive(Debug, Clone, From, Derivative)]
 #[derivative(Eq, PartialEq, Hash)]
 pub enum ScriptValue {
+    /// Python's `None`. It can only be converted to an `Option`: conversions to any other type
+    /// fail, rather than falling back to an empty value.
+    Null,
     Bool(bool),
     Integer(i64),
+    /// Integer that doesn't fit in an `i64`.
+    BigInt(BigInt),
     Str(String),
     Dict(BTreeMap<String, ScriptValue>),
     List(Vec<ScriptValue>),
+    /// Node nested in a value returned by a script evaluated in a query. The tree info is missing
+    /// for the nodes returned by scripts evaluated on projected nodes.
+    Node(
+        SylvaNode,
+        #[derivative(PartialEq = "ignore", Hash = "ignore")] Option<RefCell<ScriptTreeInfo>>,
+    ),
+    /// Node whose data was extracted from the tree before the evaluation of the script.
+    Projected(Arc<ProjectedNode>),
     Scope(
         ScopeId,
         #[derivative(PartialEq = "ignore", Hash = "ignore")] Arc<RwLock<SGraph>>,
-        #[derivative(PartialEq = "ignore", Hash = "ignore")] RefCell<ScriptTreeInfo>,
+        #[derivative(PartialEq = "ignore", Hash = "ignore")] Option<RefCell<ScriptTreeInfo>>,
     ),
 }
 
+impl ScriptValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, ScriptValue::Null)
+    }
+}
+
 impl TryInto<bool> for ScriptValue {
     type Error = ScriptError;
 
@@ -101,6 +133,18 @@ impl TryInto<i64> for ScriptValue {
     }
 }
 
+impl TryInto<BigInt> for ScriptValue {
+    type Error = ScriptError;
+
+    fn try_into(self) -> Result<BigInt, Self::Error> {
+        match self {
+            ScriptValue::Integer(int_value) => Ok(int_value.into()),
+            ScriptValue::BigInt(int_value) => Ok(int_value),
+            _ => Err(ScriptError::InvalidType("integer".to_string(), self)),
+        }
+    }
+}
+
 impl TryInto<String> for ScriptValue {
     type Error = ScriptError;
 
@@ -134,6 +178,18 @@ impl TryInto<Vec<ScriptValue>> for ScriptValue {
     }
 }
 
+impl TryInto<SylvaNode> for ScriptValue {
+    type Error = ScriptError;
+
+    fn try_into(self) -> Result<SylvaNode, Self::Error> {
+        match self {
+            ScriptValue::Node(node, _) => Ok(node),
+            ScriptValue::Projected(projected) => Ok(projected.node),
+            _ => Err(ScriptError::InvalidType("node".to_string(), self)),
+        }
+    }
+}
+
 impl TryInto<Arc<RwLock<SGraph>>> for ScriptValue {
     type Error = ScriptError;
 
@@ -145,14 +201,54 @@ impl TryInto<Arc<RwLock<SGraph>>> for ScriptValue {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, From)]
+/// Conversions in which `Null` maps to `None`, for the scripts that may return no result.
+macro_rules! impl_try_into_option {
+    ($($ty:ty),*) => {
+        $(
+            impl TryInto<Option<$ty>> for ScriptValue {
+                type Error = ScriptError;
+
+                fn try_into(self) -> Result<Option<$ty>, Self::Error> {
+                    match self {
+                        ScriptValue::Null => Ok(None),
+                        value => value.try_into().map(Some),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_try_into_option!(
+    bool,
+    i64,
+    BigInt,
+    String,
+    BTreeMap<String, ScriptValue>,
+    Vec<ScriptValue>,
+    SylvaNode,
+    Arc<RwLock<SGraph>>
+);
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum ScriptQueryValue {
     Simple(ScriptValue),
     Node(SylvaNode),
 }
 
+impl From<ScriptValue> for ScriptQueryValue {
+    fn from(value: ScriptValue) -> Self {
+        match value {
+            ScriptValue::Node(node, _) => ScriptQueryValue::Node(node),
+            ScriptValue::Projected(projected) => ScriptQueryValue::Node(projected.node),
+            value => ScriptQueryValue::Simple(value),
+        }
+    }
+}
+
 pub trait ScriptEngine {
     type Script;
+    type Module;
 
     fn eval(
         &self,
@@ -165,6 +261,7 @@ pub trait ScriptEngine {
         script: &Self::Script,
         args: Vec<ScriptQueryValue>,
         ctx: RefCell<ScriptTreeInfo>,
+        root: Option<&Path>,
     ) -> Result<ScriptQueryValue, ScriptError>;
 
     fn compile_function(
@@ -172,8 +269,26 @@ pub trait ScriptEngine {
         script: &str,
         file_name: String,
         fun_name: String,
+    ) -> Result<Self::Script, ScriptError> {
+        let module = self.compile_module(script, file_name)?;
+        let function = self.module_function(&module, &fun_name);
+        self.release_module(module)?;
+        function
+    }
+
+    /// Execute a script once, so that any number of its functions can be resolved with
+    /// `module_function`. The module must be released once its functions are resolved.
+    fn compile_module(&self, script: &str, file_name: String) -> Result<Self::Module, ScriptError>;
+
+    fn module_function(
+        &self,
+        module: &Self::Module,
+        fun_name: &str,
     ) -> Result<Self::Script, ScriptError>;
 
+    /// Drop the state of the module. The functions resolved from it stay valid.
+    fn release_module(&self, module: Self::Module) -> Result<(), ScriptError>;
+
     fn compile_aspects(
         &self,
         script: &str,
@@ -0,0 +1,82 @@
+use std::{collections::BTreeMap, ops::Range, sync::Arc};
+
+use crate::{
+    query::SylvaNode,
+    tree::info::{raw::RawTreeInfo, TreeInfo},
+};
+
+/// Part of the tree extracted around a node before the evaluation of a script, so that the
+/// script can be evaluated without access to the tree.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NodeProjection {
+    /// Levels of descendants extracted along with the node.
+    pub depth: usize,
+}
+
+impl NodeProjection {
+    /// Project the given node. `source` is the code of its tree, shared by the projected nodes
+    /// of the tree.
+    pub fn project(self, info: &RawTreeInfo, source: &Arc<str>, node: SylvaNode) -> ProjectedNode {
+        project_node(info, source, node, self.depth)
+    }
+}
+
+/// Plain copy of the data of a node.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ProjectedNode {
+    pub node: SylvaNode,
+    pub kind: String,
+    /// Code of the tree of the node.
+    source: Arc<str>,
+    /// Byte range of the text of the node in `source`.
+    range: Range<usize>,
+    /// Children of the node, or `None` if they are beyond the depth of the projection.
+    pub children: Option<Vec<Arc<ProjectedNode>>>,
+    /// Position in `children` of the children bound to a field.
+    pub fields: BTreeMap<String, usize>,
+}
+
+impl ProjectedNode {
+    pub fn text(&self) -> &str {
+        &self.source[self.range.clone()]
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Arc<ProjectedNode>> {
+        let pos = *self.fields.get(name)?;
+        self.children.as_ref()?.get(pos)
+    }
+}
+
+fn project_node(
+    info: &RawTreeInfo,
+    source: &Arc<str>,
+    node: SylvaNode,
+    depth: usize,
+) -> ProjectedNode {
+    let proxy = info.proxy(node.node);
+    let mut fields = BTreeMap::new();
+
+    let children = (depth > 0).then(|| {
+        let mut children = vec![];
+
+        for (name, child) in proxy.children_with_name() {
+            if let Some(name) = name {
+                fields.insert(name.to_string(), children.len());
+            }
+
+            let child_node = node.with_node_id(child.id);
+            children.push(Arc::new(project_node(info, source, child_node, depth - 1)));
+        }
+
+        children
+    });
+
+    ProjectedNode {
+        node,
+        kind: proxy.kind_name().to_string(),
+        source: source.clone(),
+        range: info.node_text_range(node.node),
+        children,
+        fields,
+    }
+}
@@ -1,7 +1,11 @@
 use std::{
     cell::RefCell,
     collections::{BTreeMap, HashMap},
-    sync::{mpsc::Sender, Mutex, OnceLock},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex, OnceLock,
+    },
 };
 
 use id_vec::{Id, IdVec};
@@ -9,21 +13,27 @@ use itertools::Itertools;
 use rustpython_codegen::CompileOpts;
 use rustpython_parser::ast::{self, ExprKind};
 use rustpython_vm::{
-    builtins::{PyBaseExceptionRef, PyDict, PyInt, PyList, PyStr},
+    builtins::{PyBaseExceptionRef, PyDict, PyDictRef, PyInt, PyList, PyStr},
     class::PyClassImpl,
     compiler,
     convert::ToPyObject,
     AsObject, Interpreter, PyObjectRef, PyRef, VirtualMachine,
 };
 
-use super::{ScriptEngine, ScriptError, ScriptQueryValue, ScriptTreeInfo, ScriptValue};
+use super::{
+    projection::NodeProjection, ScriptEngine, ScriptError, ScriptQueryValue, ScriptTreeInfo,
+    ScriptValue,
+};
+use crate::util::cancel::CancellationToken;
 
-use script_node::ScriptNode;
+use script_node::{NodeSource, ScriptNode};
 use script_sg::ScriptSG;
+use stdlib::sylver_path::with_project_root;
 
 mod script_node;
 mod script_sg;
 mod stdlib;
+pub mod stubs;
 
 static PYTHON_CTX: OnceLock<Mutex<Sender<PythonMsg>>> = OnceLock::new();
 
@@ -34,8 +44,19 @@ struct PythonMsg {
 
 enum PythonMsgData {
     Functions(ast::Mod, String, Vec<String>),
+    Module(ast::Mod, String),
+    ModuleFunction(PythonModule, String),
+    ReleaseModule(PythonModule),
     Script(PythonScript, Vec<ScriptValue>),
-    ScriptInQuery(PythonScript, Vec<PythonScriptQueryArg>),
+    /// Call evaluated with the given project root (see `sylver.path.relative_to_root`).
+    ScriptInQuery(PythonScript, Vec<PythonScriptQueryArg>, Option<PathBuf>),
+    /// Calls evaluated one after the other with the given project root, whose results are sent
+    /// back together. The calls following the cancellation of the token are not evaluated.
+    Batch(
+        Vec<(PythonScript, Vec<ScriptValue>)>,
+        Option<PathBuf>,
+        CancellationToken,
+    ),
 }
 
 enum PythonScriptQueryArg {
@@ -47,7 +68,9 @@ enum PythonScriptQueryArg {
 enum PythonResp {
     Scripts(HashMap<String, PythonScript>),
     Script(PythonScript),
+    Module(PythonModule),
     Value(ScriptValue),
+    Values(Vec<Result<ScriptValue, ScriptError>>),
     Error(ScriptError),
 }
 
@@ -63,12 +86,30 @@ impl From<ScriptValue> for PythonResp {
     }
 }
 
+impl From<Vec<Result<ScriptValue, ScriptError>>> for PythonResp {
+    fn from(values: Vec<Result<ScriptValue, ScriptError>>) -> Self {
+        PythonResp::Values(values)
+    }
+}
+
 impl From<PythonScript> for PythonResp {
     fn from(invokable: PythonScript) -> Self {
         PythonResp::Script(invokable)
     }
 }
 
+impl From<PythonModule> for PythonResp {
+    fn from(module: PythonModule) -> Self {
+        PythonResp::Module(module)
+    }
+}
+
+impl From<()> for PythonResp {
+    fn from(_: ()) -> Self {
+        PythonResp::Values(vec![])
+    }
+}
+
 impl<V: Into<PythonResp>> From<Result<V, ScriptError>> for PythonResp {
     fn from(result: Result<V, ScriptError>) -> Self {
         match result {
@@ -89,6 +130,28 @@ impl TryInto<PythonScript> for PythonResp {
     }
 }
 
+impl TryInto<PythonModule> for PythonResp {
+    type Error = ScriptError;
+
+    fn try_into(self) -> Result<PythonModule, ScriptError> {
+        match self {
+            PythonResp::Module(module) => Ok(module),
+            _ => Err(ScriptError::RuntimeError("module id".to_string())),
+        }
+    }
+}
+
+impl TryInto<()> for PythonResp {
+    type Error = ScriptError;
+
+    fn try_into(self) -> Result<(), ScriptError> {
+        match self {
+            PythonResp::Error(error) => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl TryInto<ScriptValue> for PythonResp {
     type Error = ScriptError;
 
@@ -103,6 +166,21 @@ impl TryInto<ScriptValue> for PythonResp {
     }
 }
 
+impl TryInto<Vec<Result<ScriptValue, ScriptError>>> for PythonResp {
+    type Error = ScriptError;
+
+    fn try_into(self) -> Result<Vec<Result<ScriptValue, ScriptError>>, ScriptError> {
+        match self {
+            PythonResp::Values(values) => Ok(values),
+            PythonResp::Error(error) => Err(error),
+            val => Err(ScriptError::RuntimeError(format!(
+                "expected script values, but got: {:?}",
+                val
+            ))),
+        }
+    }
+}
+
 impl TryInto<HashMap<String, PythonScript>> for PythonResp {
     type Error = ScriptError;
 
@@ -117,6 +195,9 @@ impl TryInto<HashMap<String, PythonScript>> for PythonResp {
 pub struct PythonVM {
     interpreter: Interpreter,
     scripts: IdVec<PyObjectRef>,
+    /// Globals of the executed modules, until they are released.
+    modules: HashMap<usize, PyDictRef>,
+    next_module: usize,
 }
 
 impl PythonVM {
@@ -126,38 +207,83 @@ impl PythonVM {
         path: String,
         functions: Vec<String>,
     ) -> Result<HashMap<String, PythonScript>, ScriptError> {
-        self.interpreter.enter(|vm| {
-            let code = rustpython_codegen::compile::compile_top(
-                &ast,
-                path.to_string(),
-                compiler::Mode::BlockExpr,
-                CompileOpts { optimize: 1 },
-            )
-            .map_err(|e| ScriptError::Compilation(path.to_string(), e.to_string()))?;
+        let globals = self.execute(ast, path)?;
 
-            let code_obj = vm.ctx.new_code(code);
+        functions
+            .into_iter()
+            .map(|name| {
+                self.function(&globals, name.clone())
+                    .map(|script| (name, script))
+            })
+            .collect()
+    }
 
-            let scope = vm.new_scope_with_builtins();
-            let scope_copy = scope.clone();
+    /// Execute the module, keeping its globals to resolve its functions.
+    fn module(&mut self, ast: ast::Mod, path: String) -> Result<PythonModule, ScriptError> {
+        let globals = self.execute(ast, path)?;
 
-            if let Err(e) = vm.run_code_obj(code_obj, scope) {
-                return Err(to_script_error(vm, e));
-            };
+        let module = self.next_module;
+        self.next_module += 1;
+        self.modules.insert(module, globals);
 
-            functions
-                .into_iter()
-                .map(|name| {
-                    let f = scope_copy.globals.get_item(&name, vm).map_err(|_| {
-                        ScriptError::RuntimeError(format!("function {} not found", name))
-                    })?;
+        Ok(PythonModule { module })
+    }
 
-                    let script = PythonScript {
-                        invokable: self.scripts.insert(f).index_value(),
-                    };
+    /// Forget the globals of the module. The functions resolved from it keep them alive.
+    fn release_module(&mut self, module: PythonModule) -> Result<(), ScriptError> {
+        self.modules
+            .remove(&module.module)
+            .map(|_| ())
+            .ok_or_else(|| {
+                ScriptError::RuntimeError(format!("invalid module id: {}", module.module))
+            })
+    }
 
-                    Ok((name, script))
-                })
-                .collect::<Result<HashMap<_, _>, _>>()
+    /// Execute the module, returning its globals.
+    fn execute(&mut self, ast: ast::Mod, path: String) -> Result<PyDictRef, ScriptError> {
+        self.interpreter
+            .enter(|vm| -> Result<PyDictRef, ScriptError> {
+                let code = rustpython_codegen::compile::compile_top(
+                    &ast,
+                    path.to_string(),
+                    compiler::Mode::BlockExpr,
+                    CompileOpts { optimize: 1 },
+                )
+                .map_err(|e| ScriptError::Compilation(path.to_string(), e.to_string()))?;
+
+                let code_obj = vm.ctx.new_code(code);
+
+                let scope = vm.new_scope_with_builtins();
+                let globals = scope.globals.clone();
+
+                vm.run_code_obj(code_obj, scope)
+                    .map_err(|e| to_script_error(vm, e))?;
+
+                Ok(globals)
+            })
+    }
+
+    fn module_function(
+        &mut self,
+        module: PythonModule,
+        name: String,
+    ) -> Result<PythonScript, ScriptError> {
+        let globals = self.modules.get(&module.module).cloned().ok_or_else(|| {
+            ScriptError::RuntimeError(format!("invalid module id: {}", module.module))
+        })?;
+
+        self.function(&globals, name)
+    }
+
+    fn function(&mut self, globals: &PyDictRef, name: String) -> Result<PythonScript, ScriptError> {
+        let f = self
+            .interpreter
+            .enter(|vm| globals.get_item(&name, vm))
+            .map_err(|_| ScriptError::RuntimeError(format!("function {} not found", name)))?;
+
+        Ok(PythonScript {
+            invokable: self.scripts.insert(f).index_value(),
+            projection: None,
         })
     }
 
@@ -182,6 +308,20 @@ impl PythonVM {
         value.try_into()
     }
 
+    fn run_batch(
+        &self,
+        calls: Vec<(PythonScript, Vec<ScriptValue>)>,
+        cancel: &CancellationToken,
+    ) -> Vec<Result<ScriptValue, ScriptError>> {
+        calls
+            .into_iter()
+            .map(|(script, args)| {
+                cancel.check()?;
+                self.run_script(script, args)
+            })
+            .collect()
+    }
+
     fn run_script_in_query(
         &self,
         script: PythonScript,
@@ -216,6 +356,8 @@ fn start_python_thread() -> Mutex<Sender<PythonMsg>> {
         let mut ctx = PythonVM {
             interpreter: interpreter_with_stdlib(),
             scripts: IdVec::new(),
+            modules: HashMap::new(),
+            next_module: 0,
         };
 
         for msg in receiver {
@@ -223,22 +365,29 @@ fn start_python_thread() -> Mutex<Sender<PythonMsg>> {
                 PythonMsgData::Functions(ast, path, functions) => {
                     ctx.functions(ast, path, functions).into()
                 }
+                PythonMsgData::Module(ast, path) => ctx.module(ast, path).into(),
+                PythonMsgData::ModuleFunction(module, name) => {
+                    ctx.module_function(module, name).into()
+                }
+                PythonMsgData::ReleaseModule(module) => ctx.release_module(module).into(),
                 PythonMsgData::Script(script, args) => ctx.run_script(script, args).into(),
-                PythonMsgData::ScriptInQuery(script, args) => {
-                    ctx.run_script_in_query(script, args).into()
+                PythonMsgData::ScriptInQuery(script, args, root) => {
+                    with_project_root(root, || ctx.run_script_in_query(script, args)).into()
+                }
+                PythonMsgData::Batch(calls, root, cancel) => {
+                    with_project_root(root, || ctx.run_batch(calls, &cancel)).into()
                 }
             };
 
-            msg.sender
-                .send(resp)
-                .expect("failed to send Python response");
+            // The results of a batch are not awaited if the caller failed in the meantime.
+            let _ = msg.sender.send(resp);
         }
     });
 
     Mutex::new(sender)
 }
 
-fn send_python_msg_sync(msg: PythonMsgData) -> Result<PythonResp, ScriptError> {
+fn send_python_msg(msg: PythonMsgData) -> Result<Receiver<PythonResp>, ScriptError> {
     let (sender, receiver) = std::sync::mpsc::channel();
 
     PYTHON_CTX
@@ -248,6 +397,14 @@ fn send_python_msg_sync(msg: PythonMsgData) -> Result<PythonResp, ScriptError> {
         .send(PythonMsg { data: msg, sender })
         .map_err(|_| ScriptError::RuntimeError("failed to reach Python runtime".to_string()))?;
 
+    Ok(receiver)
+}
+
+fn send_python_msg_sync(msg: PythonMsgData) -> Result<PythonResp, ScriptError> {
+    receive_python_resp(send_python_msg(msg)?)
+}
+
+fn receive_python_resp(receiver: Receiver<PythonResp>) -> Result<PythonResp, ScriptError> {
     receiver
         .recv()
         .map_err(|e| ScriptError::RuntimeError(format!("failed to receive Python response: {}", e)))
@@ -256,6 +413,28 @@ fn send_python_msg_sync(msg: PythonMsgData) -> Result<PythonResp, ScriptError> {
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct PythonScript {
     invokable: usize, // usize instead of id because it must be Send
+    /// For aspects declared with a `depth` argument, the projection of the nodes they receive.
+    projection: Option<NodeProjection>,
+}
+
+impl PythonScript {
+    /// Projection of the node argument, if the script can be evaluated without the tree.
+    pub fn projection(&self) -> Option<NodeProjection> {
+        self.projection
+    }
+}
+
+/// Batch of calls sent to the Python runtime, whose results have not been received yet.
+#[derive(Debug)]
+pub struct PendingBatch {
+    receiver: Receiver<PythonResp>,
+}
+
+impl PendingBatch {
+    /// Block until every call of the batch has been evaluated.
+    pub fn wait(self) -> Result<Vec<Result<ScriptValue, ScriptError>>, ScriptError> {
+        receive_python_resp(self.receiver)?.try_into()
+    }
 }
 
 pub fn compile_aspects(
@@ -271,7 +450,7 @@ pub fn compile_aspects(
     let aspect_funs = aspect_function_ids
         .values()
         .flatten()
-        .map(|(_, f)| f.clone())
+        .map(|(_, f, _)| f.clone())
         .collect_vec();
 
     let aspect_scripts: HashMap<String, PythonScript> =
@@ -279,11 +458,16 @@ pub fn compile_aspects(
             .try_into()?;
 
     for (aspect_name, aspect_impls) in aspect_function_ids {
-        for (kind_name, impl_fn) in aspect_impls {
+        for (kind_name, impl_fn, projection) in aspect_impls {
+            let script = PythonScript {
+                projection,
+                ..aspect_scripts[&impl_fn]
+            };
+
             invokables
                 .entry(aspect_name.clone())
                 .or_default()
-                .insert(kind_name.clone(), aspect_scripts[&impl_fn]);
+                .insert(kind_name.clone(), script);
         }
     }
 
@@ -295,19 +479,39 @@ pub fn compile_function(
     path: String,
     fn_name: String,
 ) -> Result<PythonScript, ScriptError> {
-    let ast = parse_module(code, path.as_str())?;
+    PythonScriptEngine::default().compile_function(code, path, fn_name)
+}
 
-    let invokable: HashMap<String, PythonScript> =
-        send_python_msg_sync(PythonMsgData::Functions(ast, path, vec![fn_name.clone()]))?
-            .try_into()?;
+/// Python module that was executed once, and from which any number of functions can be resolved.
+/// Its globals are kept by the Python runtime until it is released.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PythonModule {
+    module: usize,
+}
+
+impl PythonModule {
+    pub fn function(&self, fn_name: &str) -> Result<PythonScript, ScriptError> {
+        send_python_msg_sync(PythonMsgData::ModuleFunction(*self, fn_name.to_string()))?.try_into()
+    }
 
-    Ok(invokable[&fn_name])
+    /// Forget the globals of the module. The functions already resolved from it stay valid.
+    pub fn release(self) -> Result<(), ScriptError> {
+        send_python_msg_sync(PythonMsgData::ReleaseModule(self))?.try_into()
+    }
 }
 
+pub fn compile_module(code: &str, path: String) -> Result<PythonModule, ScriptError> {
+    let ast = parse_module(code, path.as_str())?;
+    send_python_msg_sync(PythonMsgData::Module(ast, path))?.try_into()
+}
+
+/// Aspect implementations of the module, by aspect name.
+type AspectFunctionIds = HashMap<String, Vec<(String, String, Option<NodeProjection>)>>;
+
 fn collect_aspect_function_ids(
     path: &str,
     ast: &mut ast::Mod,
-) -> Result<HashMap<String, Vec<(String, String)>>, ScriptError> {
+) -> Result<AspectFunctionIds, ScriptError> {
     let ast::Mod::Interactive { ref mut body, .. } = ast else {
         return Err(ScriptError::Compilation(
             path.to_string(),
@@ -315,14 +519,17 @@ fn collect_aspect_function_ids(
         ));
     };
 
-    let mut aspect_fns: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    let mut aspect_fns: AspectFunctionIds = HashMap::new();
 
     for statement in body.iter_mut() {
-        if let Some((aspect_name, kind_name, aspect_fn_name)) = extract_aspect_fn_ids(statement)? {
-            aspect_fns
-                .entry(aspect_name)
-                .or_default()
-                .push((kind_name, aspect_fn_name));
+        if let Some((aspect_name, kind_name, aspect_fn_name, projection)) =
+            extract_aspect_fn_ids(statement)?
+        {
+            aspect_fns.entry(aspect_name).or_default().push((
+                kind_name,
+                aspect_fn_name,
+                projection,
+            ));
         }
     }
 
@@ -335,10 +542,10 @@ fn parse_module(code: &str, path: &str) -> Result<ast::Mod, ScriptError> {
 }
 
 /// If the given statement is an aspect function definition, return a tuple
-/// of the form (aspect_name, kind_name, function_name).
+/// of the form (aspect_name, kind_name, function_name, projection).
 fn extract_aspect_fn_ids(
     statement: &mut ast::Stmt,
-) -> Result<Option<(String, String, String)>, ScriptError> {
+) -> Result<Option<(String, String, String, Option<NodeProjection>)>, ScriptError> {
     if let ast::StmtKind::FunctionDef {
         name: function_name,
         ref mut decorator_list,
@@ -346,8 +553,14 @@ fn extract_aspect_fn_ids(
     } = &mut statement.node
     {
         if let Some((aspect_name, kind_name)) = find_aspect_target_kind_name(decorator_list)? {
+            let projection = aspect_projection(&decorator_list[0])?;
             decorator_list.clear();
-            return Ok(Some((aspect_name, kind_name, function_name.clone())));
+            return Ok(Some((
+                aspect_name,
+                kind_name,
+                function_name.clone(),
+                projection,
+            )));
         }
     }
 
@@ -375,6 +588,33 @@ fn find_aspect_target_kind_name(
     }
 }
 
+/// Projection requested by the `depth` argument of an aspect decorator, as in
+/// `@Kind.sg_gen(depth=1)`.
+fn aspect_projection(decorator: &ast::Expr) -> Result<Option<NodeProjection>, ScriptError> {
+    let ExprKind::Call { keywords, .. } = &decorator.node else {
+        return Ok(None);
+    };
+
+    for keyword in keywords {
+        match (keyword.node.arg.as_deref(), &keyword.node.value.node) {
+            (
+                Some("depth"),
+                ExprKind::Constant {
+                    value: ast::Constant::Int(depth),
+                    ..
+                },
+            ) => {
+                let depth =
+                    usize::try_from(depth).map_err(|_| ScriptError::InvalidAspectDeclaration)?;
+                return Ok(Some(NodeProjection { depth }));
+            }
+            _ => return Err(ScriptError::InvalidAspectDeclaration),
+        }
+    }
+
+    Ok(None)
+}
+
 fn to_script_error(vm: &VirtualMachine, err: PyBaseExceptionRef) -> ScriptError {
     let mut msg = String::new();
     vm.write_exception(&mut msg, &err).unwrap();
@@ -384,8 +624,27 @@ fn to_script_error(vm: &VirtualMachine, err: PyBaseExceptionRef) -> ScriptError
 #[derive(Debug, Default, Copy, Clone)]
 pub struct PythonScriptEngine {}
 
+impl PythonScriptEngine {
+    /// Send the given calls to the Python runtime without waiting for their results, so that
+    /// other work can be done during their evaluation. The arguments can't refer to a tree.
+    /// Once the token is cancelled, the remaining calls fail without being evaluated.
+    pub fn eval_batch(
+        &self,
+        calls: Vec<(PythonScript, Vec<ScriptValue>)>,
+        root: Option<&Path>,
+        cancel: CancellationToken,
+    ) -> Result<PendingBatch, ScriptError> {
+        let msg = PythonMsgData::Batch(calls, root.map(Path::to_path_buf), cancel);
+
+        Ok(PendingBatch {
+            receiver: send_python_msg(msg)?,
+        })
+    }
+}
+
 impl ScriptEngine for PythonScriptEngine {
     type Script = PythonScript;
+    type Module = PythonModule;
 
     fn eval(
         &self,
@@ -400,6 +659,7 @@ impl ScriptEngine for PythonScriptEngine {
         script: &Self::Script,
         args: Vec<ScriptQueryValue>,
         info: RefCell<ScriptTreeInfo>,
+        root: Option<&Path>,
     ) -> Result<ScriptQueryValue, ScriptError> {
         let mut script_args: Vec<PythonScriptQueryArg> = vec![];
         for a in args {
@@ -412,18 +672,26 @@ impl ScriptEngine for PythonScriptEngine {
             script_args.push(b);
         }
 
-        let resp = send_python_msg_sync(PythonMsgData::ScriptInQuery(*script, script_args))?;
+        let msg = PythonMsgData::ScriptInQuery(*script, script_args, root.map(Path::to_path_buf));
+        let resp = send_python_msg_sync(msg)?;
         let value: ScriptValue = resp.try_into()?;
-        Ok(ScriptQueryValue::Simple(value))
+        Ok(value.into())
+    }
+
+    fn compile_module(&self, script: &str, file_name: String) -> Result<PythonModule, ScriptError> {
+        compile_module(script, file_name)
     }
 
-    fn compile_function(
+    fn module_function(
         &self,
-        script: &str,
-        file_name: String,
-        fun_name: String,
+        module: &PythonModule,
+        fun_name: &str,
     ) -> Result<Self::Script, ScriptError> {
-        compile_function(script, file_name, fun_name)
+        module.function(fun_name)
+    }
+
+    fn release_module(&self, module: PythonModule) -> Result<(), ScriptError> {
+        module.release()
     }
 
     fn compile_aspects(
@@ -441,6 +709,7 @@ fn interpreter_with_stdlib() -> Interpreter {
         vm.add_native_module("os".to_string(), Box::new(stdlib::os::make_module));
         vm.add_native_module("path".to_string(), Box::new(stdlib::path::make_module));
         vm.add_native_module("re".to_string(), Box::new(stdlib::re::make_module));
+        vm.add_native_module("sylver".to_string(), Box::new(stdlib::sylver::make_module));
         ScriptNode::make_class(&vm.ctx);
         ScriptSG::make_class(&vm.ctx);
     })
@@ -449,7 +718,9 @@ fn interpreter_with_stdlib() -> Interpreter {
 impl ToPyObject for ScriptValue {
     fn to_pyobject(self, vm: &VirtualMachine) -> PyObjectRef {
         match self {
+            ScriptValue::Null => vm.ctx.none(),
             ScriptValue::Integer(i) => i.to_pyobject(vm),
+            ScriptValue::BigInt(i) => vm.ctx.new_int(i).into(),
             ScriptValue::Bool(b) => b.to_pyobject(vm),
             ScriptValue::Str(s) => s.to_pyobject(vm),
             ScriptValue::List(l) => {
@@ -467,6 +738,9 @@ impl ToPyObject for ScriptValue {
                 }
                 dict.to_pyobject(vm)
             }
+            ScriptValue::Node(node, Some(ctx)) => ScriptNode::new(ctx, node).to_pyobject(vm),
+            ScriptValue::Node(node, None) => ScriptNode::detached(node).to_pyobject(vm),
+            ScriptValue::Projected(projected) => ScriptNode::projected(projected).to_pyobject(vm),
             ScriptValue::Scope(scope_id, scope_graph, ctx) => {
                 ScriptSG::new(ctx, scope_graph, scope_id).to_pyobject(vm)
             }
@@ -478,11 +752,8 @@ impl TryInto<ScriptQueryValue> for PyObjectRef {
     type Error = ScriptError;
 
     fn try_into(self) -> Result<ScriptQueryValue, Self::Error> {
-        if let Some(node) = self.payload::<ScriptNode>() {
-            Ok(ScriptQueryValue::Node(node.node))
-        } else {
-            Ok(ScriptQueryValue::Simple(self.try_into()?))
-        }
+        let value: ScriptValue = self.try_into()?;
+        Ok(value.into())
     }
 }
 
@@ -490,16 +761,24 @@ impl TryInto<ScriptValue> for PyObjectRef {
     type Error = ScriptError;
 
     fn try_into(self) -> Result<ScriptValue, Self::Error> {
-        let value = if self.class().name().to_string() == "bool" {
+        let value = if self.class().name().to_string() == "NoneType" {
+            ScriptValue::Null
+        } else if self.class().name().to_string() == "bool" {
             pybool_to_value(self.payload::<PyInt>().unwrap())
         } else if let Some(pyint) = self.payload::<PyInt>() {
-            pyint_to_value(pyint)?
+            pyint_to_value(pyint)
         } else if let Some(pystr) = self.payload::<PyStr>() {
             pystr_to_value(pystr)
         } else if let Ok(pydict) = self.clone().downcast::<PyDict>() {
             pydict_to_value(pydict)?
         } else if let Some(pylist) = self.payload::<PyList>() {
             pylist_to_value(pylist)?
+        } else if let Some(script_node) = self.payload::<ScriptNode>() {
+            match &script_node.source {
+                NodeSource::Tree(info) => ScriptValue::Node(script_node.node, Some(info.clone())),
+                NodeSource::Projected(projected) => ScriptValue::Projected(projected.clone()),
+                NodeSource::Detached => ScriptValue::Node(script_node.node, None),
+            }
         } else if let Some(script_sg) = self.payload::<ScriptSG>() {
             ScriptValue::Scope(
                 script_sg.scope_id,
@@ -516,15 +795,15 @@ impl TryInto<ScriptValue> for PyObjectRef {
     }
 }
 
-fn pyint_to_value(pyint: &PyInt) -> Result<ScriptValue, ScriptError> {
-    let int_value = i64::try_from(pyint.as_bigint())
-        .map_err(|_| ScriptError::UnsupportedType("big_int".to_string()))?;
-
-    Ok(ScriptValue::Integer(int_value))
+fn pyint_to_value(pyint: &PyInt) -> ScriptValue {
+    match i64::try_from(pyint.as_bigint()) {
+        Ok(int_value) => ScriptValue::Integer(int_value),
+        Err(_) => ScriptValue::BigInt(pyint.as_bigint().clone()),
+    }
 }
 
 fn pybool_to_value(pyint: &PyInt) -> ScriptValue {
-    let bool_value = pyint_to_value(pyint).unwrap() == ScriptValue::Integer(1);
+    let bool_value = pyint_to_value(pyint) == ScriptValue::Integer(1);
     ScriptValue::Bool(bool_value)
 }
 
@@ -561,7 +840,10 @@ fn pydict_to_value(pydict: PyRef<PyDict>) -> Result<ScriptValue, ScriptError> {
 #[cfg(test)]
 mod test {
     use indoc::indoc;
+    use std::sync::Arc;
+
     use maplit::{btreemap, hashmap, hashset};
+    use num_bigint::BigInt;
 
     use crate::{
         builtin_langs::{get_builtin_lang, parser::BuiltinParserRunner, BuiltinLang},
@@ -599,6 +881,97 @@ def hello(file_name: str):
         assert_eq!(value, ScriptValue::Str("directory/file".to_string()));
     }
 
+    #[test]
+    fn functions_from_module() {
+        let python_module = r#"
+calls = []
+
+def first(value: str):
+    calls.append(value)
+    return len(calls)
+
+def second(value: str):
+    return first(value) * 10
+"#;
+
+        let module = compile_module(python_module, "test.py".to_string()).unwrap();
+        let first = module.function("first").unwrap();
+        let second = module.function("second").unwrap();
+
+        let engine = PythonScriptEngine {};
+        let arg = || vec![ScriptValue::Str("a".to_string())];
+
+        // Both functions share the globals of the module, which was only executed once.
+        assert_eq!(engine.eval(&first, arg()).unwrap(), ScriptValue::Integer(1));
+        assert_eq!(
+            engine.eval(&second, arg()).unwrap(),
+            ScriptValue::Integer(20)
+        );
+        assert!(module.function("missing").is_err());
+
+        // The functions outlive the module.
+        module.release().unwrap();
+        assert!(module.function("first").is_err());
+        assert!(module.release().is_err());
+        assert_eq!(engine.eval(&first, arg()).unwrap(), ScriptValue::Integer(3));
+    }
+
+    #[test]
+    fn sylver_helpers() {
+        let python_module = r#"
+import sylver
+
+def helpers(name: str):
+    prefix = sylver.re.match('(té)st_', name)
+    return [
+        prefix.group(1),
+        prefix.end(),
+        sylver.re.match('name', name) is None,
+        sylver.re.search('n(a)(x)?', name).groups(),
+        sylver.re.search('[0-9]+', name) is None,
+        sylver.path.relative_to_root('./src/' + name),
+        sylver.text.levenshtein(name, 'test_main'),
+    ]
+
+def relative(path: str):
+    return sylver.path.relative_to_root(path)
+"#;
+
+        let module = compile_module(python_module, "test.py".to_string()).unwrap();
+        let script = module.function("helpers").unwrap();
+
+        let engine = PythonScriptEngine {};
+
+        let value = engine
+            .eval(&script, vec![ScriptValue::Str("tést_name".to_string())])
+            .unwrap();
+
+        let str = |s: &str| ScriptValue::Str(s.to_string());
+        assert_eq!(
+            value,
+            ScriptValue::List(vec![
+                str("té"),
+                ScriptValue::Integer(5),
+                ScriptValue::Bool(true),
+                ScriptValue::List(vec![str("a"), ScriptValue::Null]),
+                ScriptValue::Bool(true),
+                str("src/tést_name"),
+                ScriptValue::Integer(4),
+            ])
+        );
+
+        // Paths are relative to the root of the project of the evaluated files.
+        let relative = module.function("relative").unwrap();
+        let calls = vec![(relative, vec![str("./src/main.py")])];
+        let results = engine
+            .eval_batch(calls, Some(Path::new("src")), CancellationToken::default())
+            .unwrap()
+            .wait()
+            .unwrap();
+
+        assert_eq!(results, vec![Ok(str("main.py"))]);
+    }
+
     #[test]
     fn collect_aspect() {
         let python_module = r#"
@@ -633,6 +1006,30 @@ def my_aspect_statement():
         );
     }
 
+    #[test]
+    fn aspect_projection() {
+        let python_module = r#"
+@Expr.aspect1(depth=2)
+def my_aspect_expr():
+    return 'Expr'
+
+@Statement.aspect1()
+def my_aspect_statement():
+    return 'Statement'
+"#;
+
+        let invokables = compile_aspects(python_module, "aspect_test.py".to_string()).unwrap();
+
+        assert_eq!(
+            invokables["aspect1"]["Expr"].projection(),
+            Some(NodeProjection { depth: 2 })
+        );
+        assert_eq!(invokables["aspect1"]["Statement"].projection(), None);
+
+        let invalid = "@Expr.aspect1(depth='all')\ndef f():\n    return 1\n";
+        assert!(compile_aspects(invalid, "aspect_test.py".to_string()).is_err());
+    }
+
     #[test]
     fn yaml_loads_string() {
         test_yaml_loads("hello", ScriptValue::Str("hello".to_string()));
@@ -648,6 +1045,11 @@ def my_aspect_statement():
         test_yaml_loads("true", ScriptValue::Bool(true));
     }
 
+    #[test]
+    fn yaml_loads_null() {
+        test_yaml_loads("null", ScriptValue::Null);
+    }
+
     #[test]
     fn yaml_loads_seq() {
         test_yaml_loads(
@@ -702,6 +1104,32 @@ def value(doc):
         assert_eq!(ScriptValue::Integer(42), eval_python_expr("42"));
     }
 
+    #[test]
+    fn python_big_int() {
+        let big_int: BigInt = "1267650600228229401496703205376".parse().unwrap();
+
+        assert_eq!(
+            ScriptValue::BigInt(big_int.clone()),
+            eval_python_expr("2 ** 100")
+        );
+        assert_eq!(ScriptValue::Integer(i64::MIN), eval_python_expr("-2 ** 63"));
+
+        let engine = PythonScriptEngine::default();
+        let script = compile_function(
+            "def half(x):\n    return x // 2\n",
+            "test.py".to_string(),
+            "half".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            engine
+                .eval(&script, vec![ScriptValue::BigInt(big_int.clone())])
+                .unwrap(),
+            ScriptValue::BigInt(big_int / 2)
+        );
+    }
+
     #[test]
     fn python_true_to_bool() {
         assert_eq!(ScriptValue::Bool(true), eval_python_expr("True"));
@@ -712,6 +1140,30 @@ def value(doc):
         assert_eq!(ScriptValue::Bool(false), eval_python_expr("False"));
     }
 
+    #[test]
+    fn python_none_to_null() {
+        assert_eq!(ScriptValue::Null, eval_python_expr("None"));
+        assert_eq!(
+            ScriptValue::List(vec![ScriptValue::Null, ScriptValue::Integer(1)]),
+            eval_python_expr("[None, 1]")
+        );
+    }
+
+    #[test]
+    fn null_to_option() {
+        let none: Result<Option<String>, _> = ScriptValue::Null.try_into();
+        assert_eq!(none, Ok(None));
+
+        let some: Result<Option<String>, _> = ScriptValue::Str("hello".to_string()).try_into();
+        assert_eq!(some, Ok(Some("hello".to_string())));
+
+        let invalid: Result<Option<String>, _> = ScriptValue::Integer(1).try_into();
+        assert!(invalid.is_err());
+
+        let required: Result<String, _> = ScriptValue::Null.try_into();
+        assert!(required.is_err());
+    }
+
     #[test]
     fn python_str_to_str() {
         assert_eq!(
@@ -809,6 +1261,7 @@ def value(doc):
                 &script,
                 vec![ScriptQueryValue::Node(node)],
                 RefCell::new(ScriptTreeInfo::new(&mut tree_info)),
+                None,
             )
             .unwrap();
 
@@ -817,4 +1270,126 @@ def value(doc):
             ScriptQueryValue::Simple(ScriptValue::Str("console.log from Python".to_string()))
         );
     }
+
+    #[test]
+    fn batch_of_projected_nodes() {
+        let script_scr = indoc! {"
+            def callee(node):
+                return node.function.text
+
+            def callee_object(node):
+                return node.function.object.text
+            "
+        };
+
+        let (lang_mappings, lang, _) = get_builtin_lang(BuiltinLang::Javascript);
+
+        let syntax: Syntax = lang_mappings.types.as_slice().into();
+
+        let runner = BuiltinParserRunner::new(lang, &syntax, lang_mappings);
+
+        let source = Source::inline(
+            "console.log(hello).to_string()".to_string(),
+            "BUFFER".to_string(),
+        );
+
+        let tree = runner.run(source);
+        let sylva = Sylva::new(hashmap! {"buffer".into() => tree });
+
+        let spec = Spec::new(Default::default(), syntax);
+
+        let node = SylvaNode {
+            node: 5.into(),
+            tree: 0.into(),
+            sylva: 0.into(),
+        };
+
+        let tree_info = RawTreeInfoBuilder::new(&spec, &sylva).info_for_node(node);
+        let source = tree_info.source_code().into();
+        let projected = ScriptValue::Projected(Arc::new(
+            NodeProjection { depth: 1 }.project(&tree_info, &source, node),
+        ));
+
+        let module = compile_module(script_scr, "test.py".to_string()).unwrap();
+        let calls = vec![
+            (module.function("callee").unwrap(), vec![projected.clone()]),
+            (module.function("callee_object").unwrap(), vec![projected]),
+        ];
+
+        let results = PythonScriptEngine::default()
+            .eval_batch(calls.clone(), None, CancellationToken::default())
+            .unwrap()
+            .wait()
+            .unwrap();
+
+        assert_eq!(results[0], Ok(ScriptValue::Str("console.log".to_string())));
+        // The object of the callee is beyond the depth of the projection.
+        assert!(results[1].is_err());
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let results = PythonScriptEngine::default()
+            .eval_batch(calls, None, cancel)
+            .unwrap()
+            .wait()
+            .unwrap();
+
+        assert!(results.iter().all(|r| r == &Err(ScriptError::Cancelled)));
+    }
+
+    #[test]
+    fn test_nodes_nested_in_result() {
+        let script_scr = indoc! {"
+            def with_reason(node):
+                return [{'node': node, 'reason': 'console call'}]
+            "
+        };
+
+        let (lang_mappings, lang, _) = get_builtin_lang(BuiltinLang::Javascript);
+
+        let syntax: Syntax = lang_mappings.types.as_slice().into();
+
+        let runner = BuiltinParserRunner::new(lang, &syntax, lang_mappings);
+
+        let source = Source::inline(
+            "console.log(hello).to_string()".to_string(),
+            "BUFFER".to_string(),
+        );
+
+        let tree = runner.run(source);
+        let sylva = Sylva::new(hashmap! {"buffer".into() => tree });
+
+        let spec = Spec::new(Default::default(), syntax);
+
+        let node = SylvaNode {
+            node: 5.into(),
+            tree: 0.into(),
+            sylva: 0.into(),
+        };
+
+        let mut tree_info = RawTreeInfoBuilder::new(&spec, &sylva).info_for_node(node);
+
+        let engine = PythonScriptEngine::default();
+        let script =
+            compile_function(script_scr, "test.py".to_string(), "with_reason".to_string()).unwrap();
+
+        let script_result = engine
+            .eval_in_query(
+                &script,
+                vec![ScriptQueryValue::Node(node)],
+                RefCell::new(ScriptTreeInfo::new(&mut tree_info)),
+                None,
+            )
+            .unwrap();
+
+        let ScriptQueryValue::Simple(ScriptValue::List(items)) = script_result else {
+            panic!("Expected a list, got: {script_result:?}");
+        };
+
+        let item: BTreeMap<String, ScriptValue> = items[0].clone().try_into().unwrap();
+        let item_node: SylvaNode = item["node"].clone().try_into().unwrap();
+
+        assert_eq!(item_node, node);
+        assert_eq!(item["reason"], ScriptValue::Str("console call".to_string()));
+    }
 }
@@ -1,23 +1,54 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, sync::Arc};
 
 use rustpython_vm::{
-    builtins::{PyList, PyStrRef},
+    builtins::{PyBaseExceptionRef, PyList, PyStrRef},
     convert::ToPyObject,
     pyclass, PyObject, PyObjectRef, PyPayload, PyResult, VirtualMachine,
 };
 
-use crate::{query::SylvaNode, script::ScriptTreeInfo, tree::info::TreeInfo};
+use crate::{
+    query::SylvaNode,
+    script::{projection::ProjectedNode, ScriptTreeInfo},
+    tree::info::TreeInfo,
+};
+
+/// Where the data of a `ScriptNode` is read from.
+#[derive(Debug, Clone)]
+pub enum NodeSource {
+    Tree(RefCell<ScriptTreeInfo>),
+    Projected(Arc<ProjectedNode>),
+    /// The node was obtained without access to the tree (from a scope graph lookup in a script
+    /// evaluated on projected nodes), so only its identity is known.
+    Detached,
+}
 
 #[pyclass(name = "ScriptNode", module = "sylver")]
 #[derive(Debug, PyPayload)]
 pub struct ScriptNode {
-    info: RefCell<ScriptTreeInfo>,
+    pub source: NodeSource,
     pub node: SylvaNode,
 }
 
 impl ScriptNode {
     pub fn new(info: RefCell<ScriptTreeInfo>, node: SylvaNode) -> Self {
-        Self { info, node }
+        Self {
+            source: NodeSource::Tree(info),
+            node,
+        }
+    }
+
+    pub fn projected(projected: Arc<ProjectedNode>) -> Self {
+        Self {
+            node: projected.node,
+            source: NodeSource::Projected(projected),
+        }
+    }
+
+    pub fn detached(node: SylvaNode) -> Self {
+        Self {
+            source: NodeSource::Detached,
+            node,
+        }
     }
 }
 
@@ -27,71 +58,101 @@ unsafe impl Send for ScriptNode {}
 impl ScriptNode {
     #[pyslot]
     fn getattro(obj: &PyObject, name: PyStrRef, vm: &VirtualMachine) -> PyResult {
-        if let Some(script_node) = obj.payload::<ScriptNode>() {
-            match name.as_str() {
-                "children" => ScriptNode::node_children(script_node, vm),
-                "kind" => Ok(ScriptNode::kind(script_node, vm)),
-                "text" => Ok(script_node.text(vm)),
-                field_name => ScriptNode::node_field(script_node, field_name, vm),
-            }
-        } else {
-            Err(vm.new_type_error("Expected ScriptNode".to_owned()))
+        let Some(script_node) = obj.payload::<ScriptNode>() else {
+            return Err(vm.new_type_error("Expected ScriptNode".to_owned()));
+        };
+
+        match &script_node.source {
+            NodeSource::Tree(info) => match name.as_str() {
+                "children" => ScriptNode::node_children(script_node, info, vm),
+                "kind" => Ok(ScriptNode::kind(script_node, info, vm)),
+                "text" => Ok(script_node.text(info, vm)),
+                field_name => ScriptNode::node_field(script_node, info, field_name, vm),
+            },
+            NodeSource::Projected(projected) => projected_attr(projected, name.as_str(), vm),
+            NodeSource::Detached => Err(vm.new_runtime_error(format!(
+                "Cannot read attribute '{}' of a node obtained from a scope lookup in a \
+                 projected script",
+                name.as_str()
+            ))),
         }
     }
 
-    fn text(&self, vm: &VirtualMachine) -> PyObjectRef {
-        self.info
-            .borrow_mut()
+    fn text(&self, info: &RefCell<ScriptTreeInfo>, vm: &VirtualMachine) -> PyObjectRef {
+        info.borrow_mut()
             .info_mut()
             .node_text(self.node.node)
             .to_pyobject(vm)
     }
 
-    fn kind(&self, vm: &VirtualMachine) -> PyObjectRef {
-        self.info
-            .borrow()
+    fn kind(&self, info: &RefCell<ScriptTreeInfo>, vm: &VirtualMachine) -> PyObjectRef {
+        info.borrow()
             .info()
             .proxy(self.node.node)
             .kind_name()
             .to_pyobject(vm)
     }
 
-    fn node_children(&self, vm: &VirtualMachine) -> PyResult<PyObjectRef> {
+    fn node_children(
+        &self,
+        info: &RefCell<ScriptTreeInfo>,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyObjectRef> {
         let list = PyList::default();
 
-        for c in self
-            .info
-            .borrow()
-            .info()
-            .proxy(self.node.node)
-            .direct_children()
-        {
-            let child = ScriptNode {
-                info: self.info.clone(),
-                node: self.node.with_node_id(c.id),
-            };
+        for c in info.borrow().info().proxy(self.node.node).direct_children() {
+            let child = ScriptNode::new(info.clone(), self.node.with_node_id(c.id));
             list.borrow_vec_mut().push(child.to_pyobject(vm));
         }
 
         Ok(list.to_pyobject(vm))
     }
 
-    fn node_field(&self, field_name: &str, vm: &VirtualMachine) -> PyResult<PyObjectRef> {
-        match self
-            .info
+    fn node_field(
+        &self,
+        info: &RefCell<ScriptTreeInfo>,
+        field_name: &str,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyObjectRef> {
+        match info
             .borrow()
             .info()
             .field_value_from_name(self.node.node, field_name)
         {
-            Some(n) => Ok(ScriptNode {
-                node: self.node.with_node_id(n),
-                info: self.info.clone(),
+            Some(n) => Ok(ScriptNode::new(info.clone(), self.node.with_node_id(n)).to_pyobject(vm)),
+            None => Err(missing_attribute(field_name, vm)),
+        }
+    }
+}
+
+fn projected_attr(projected: &ProjectedNode, name: &str, vm: &VirtualMachine) -> PyResult {
+    match name {
+        "kind" => Ok(projected.kind.clone().to_pyobject(vm)),
+        "text" => Ok(projected.text().to_pyobject(vm)),
+        _ if projected.children.is_none() => Err(vm.new_runtime_error(format!(
+            "Cannot read attribute '{name}' of a {}: it is beyond the depth of the projection",
+            projected.kind
+        ))),
+        "children" => {
+            let list = PyList::default();
+
+            for child in projected.children.iter().flatten() {
+                let child = ScriptNode::projected(child.clone());
+                list.borrow_vec_mut().push(child.to_pyobject(vm));
             }
-            .to_pyobject(vm)),
-            None => Err(vm.new_exception_msg(
-                vm.ctx.exceptions.key_error.to_owned(),
-                format!("Missing attribute: {field_name}"),
-            )),
+
+            Ok(list.to_pyobject(vm))
         }
+        field_name => match projected.field(field_name) {
+            Some(child) => Ok(ScriptNode::projected(child.clone()).to_pyobject(vm)),
+            None => Err(missing_attribute(field_name, vm)),
+        },
     }
 }
+
+fn missing_attribute(field_name: &str, vm: &VirtualMachine) -> PyBaseExceptionRef {
+    vm.new_exception_msg(
+        vm.ctx.exceptions.key_error.to_owned(),
+        format!("Missing attribute: {field_name}"),
+    )
+}
@@ -19,12 +19,13 @@ use super::script_node::ScriptNode;
 pub struct ScriptSG {
     pub scope_graph: Arc<RwLock<SGraph>>,
     pub scope_id: ScopeId,
-    pub ctx: RefCell<ScriptTreeInfo>,
+    /// Missing when the script is evaluated on projected nodes.
+    pub ctx: Option<RefCell<ScriptTreeInfo>>,
 }
 
 impl ScriptSG {
     pub fn new(
-        ctx: RefCell<ScriptTreeInfo>,
+        ctx: Option<RefCell<ScriptTreeInfo>>,
         scope_graph: Arc<RwLock<SGraph>>,
         scope_id: ScopeId,
     ) -> Self {
@@ -89,7 +90,10 @@ impl ScriptSG {
             .expect("poisoned scope graph lock")
             .lookup(self.scope_id, name.to_string().as_str())
             .into_iter()
-            .map(|n| ScriptNode::new(self.ctx.clone(), n));
+            .map(|n| match &self.ctx {
+                Some(ctx) => ScriptNode::new(ctx.clone(), n),
+                None => ScriptNode::detached(n),
+            });
 
         let list = PyList::default();
         for node in nodes {
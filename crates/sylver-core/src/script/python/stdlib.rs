@@ -140,3 +140,257 @@ pub mod yaml {
         }
     }
 }
+
+/// Helpers implemented in Rust, available to the scripts as `import sylver`.
+#[pymodule]
+pub mod sylver {
+    use rustpython_vm::{builtins::PyModule, PyRef, VirtualMachine};
+
+    #[pyattr]
+    fn re(vm: &VirtualMachine) -> PyRef<PyModule> {
+        super::sylver_re::make_module(vm)
+    }
+
+    #[pyattr]
+    fn path(vm: &VirtualMachine) -> PyRef<PyModule> {
+        super::sylver_path::make_module(vm)
+    }
+
+    #[pyattr]
+    fn text(vm: &VirtualMachine) -> PyRef<PyModule> {
+        super::sylver_text::make_module(vm)
+    }
+}
+
+#[pymodule]
+pub mod sylver_re {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use rustpython_vm::{
+        function::OptionalArg, pyclass, PyObjectRef, PyPayload, PyResult, VirtualMachine,
+    };
+
+    /// Number of compiled patterns kept by the Python thread.
+    const MAX_CACHED_REGEXES: usize = 256;
+
+    thread_local! {
+        /// Rules call the same patterns on every node, so they are only compiled once.
+        static REGEXES: RefCell<RegexCache> = RefCell::new(RegexCache::default());
+    }
+
+    /// Compiled patterns, the least recently used one being dropped when the cache is full.
+    #[derive(Default)]
+    struct RegexCache {
+        regexes: HashMap<String, (fancy_regex::Regex, u64)>,
+        clock: u64,
+    }
+
+    impl RegexCache {
+        fn get(&mut self, pattern: &str) -> Result<&fancy_regex::Regex, fancy_regex::Error> {
+            self.clock += 1;
+
+            if !self.regexes.contains_key(pattern) {
+                let regex = fancy_regex::Regex::new(pattern)?;
+
+                if self.regexes.len() >= MAX_CACHED_REGEXES {
+                    let oldest = self
+                        .regexes
+                        .iter()
+                        .min_by_key(|(_, (_, used))| *used)
+                        .map(|(pattern, _)| pattern.clone());
+
+                    if let Some(oldest) = oldest {
+                        self.regexes.remove(&oldest);
+                    }
+                }
+
+                self.regexes.insert(pattern.to_string(), (regex, 0));
+            }
+
+            let (regex, used) = self.regexes.get_mut(pattern).unwrap();
+            *used = self.clock;
+            Ok(regex)
+        }
+    }
+
+    /// Result of a successful match, with the same interface as the match objects of the `re`
+    /// module. Positions are counted in characters.
+    #[pyattr]
+    #[pyclass(module = "sylver.re", name = "Match")]
+    #[derive(Debug, PyPayload)]
+    struct Match {
+        text: String,
+        /// Character range of each group, the whole match being the group 0.
+        groups: Vec<Option<(usize, usize)>>,
+    }
+
+    #[pyclass]
+    impl Match {
+        #[pymethod]
+        fn group(
+            &self,
+            index: OptionalArg<usize>,
+            vm: &VirtualMachine,
+        ) -> PyResult<Option<String>> {
+            Ok(self.span_of(index, vm)?.map(|span| self.text_of(span)))
+        }
+
+        #[pymethod]
+        fn groups(&self) -> Vec<Option<String>> {
+            self.groups[1..]
+                .iter()
+                .map(|span| span.map(|span| self.text_of(span)))
+                .collect()
+        }
+
+        #[pymethod]
+        fn start(&self, index: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<isize> {
+            Ok(self.span(index, vm)?.0)
+        }
+
+        #[pymethod]
+        fn end(&self, index: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<isize> {
+            Ok(self.span(index, vm)?.1)
+        }
+
+        /// Range of the group, or `(-1, -1)` if it did not participate in the match.
+        #[pymethod]
+        fn span(&self, index: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<(isize, isize)> {
+            Ok(self
+                .span_of(index, vm)?
+                .map_or((-1, -1), |(start, end)| (start as isize, end as isize)))
+        }
+    }
+
+    impl Match {
+        fn new(text: String, captures: &fancy_regex::Captures) -> Match {
+            let char_pos = |byte: usize| text[..byte].chars().count();
+
+            let groups = (0..captures.len())
+                .map(|i| {
+                    captures
+                        .get(i)
+                        .map(|m| (char_pos(m.start()), char_pos(m.end())))
+                })
+                .collect();
+
+            Match { text, groups }
+        }
+
+        fn text_of(&self, (start, end): (usize, usize)) -> String {
+            self.text.chars().skip(start).take(end - start).collect()
+        }
+
+        fn span_of(
+            &self,
+            index: OptionalArg<usize>,
+            vm: &VirtualMachine,
+        ) -> PyResult<Option<(usize, usize)>> {
+            self.groups
+                .get(index.unwrap_or(0))
+                .copied()
+                .ok_or_else(|| vm.new_index_error("no such group".to_string()))
+        }
+    }
+
+    /// Return a match object if the pattern matches at the start of the text, or `None`.
+    #[pyfunction(name = "match")]
+    fn match_start(pattern: String, text: String, vm: &VirtualMachine) -> PyResult<PyObjectRef> {
+        let found =
+            find(&pattern, text, vm)?.filter(|m| m.groups[0].map(|(start, _)| start) == Some(0));
+        Ok(to_pyobject(found, vm))
+    }
+
+    /// Return a match object for the first match of the pattern in the text, or `None`.
+    #[pyfunction]
+    fn search(pattern: String, text: String, vm: &VirtualMachine) -> PyResult<PyObjectRef> {
+        Ok(to_pyobject(find(&pattern, text, vm)?, vm))
+    }
+
+    fn to_pyobject(found: Option<Match>, vm: &VirtualMachine) -> PyObjectRef {
+        found.map_or_else(|| vm.ctx.none(), |m| m.into_pyobject(vm))
+    }
+
+    fn find(pattern: &str, text: String, vm: &VirtualMachine) -> PyResult<Option<Match>> {
+        REGEXES.with(|regexes| {
+            let mut regexes = regexes.borrow_mut();
+
+            let regex = regexes
+                .get(pattern)
+                .map_err(|_| vm.new_value_error(format!("Could not compile regex: {pattern}")))?;
+
+            let captures = regex
+                .captures(&text)
+                .map_err(|e| vm.new_value_error(format!("Could not match regex {pattern}: {e}")))?;
+
+            Ok(captures.map(|captures| Match::new(text.clone(), &captures)))
+        })
+    }
+}
+
+#[pymodule]
+pub mod sylver_path {
+    use std::{
+        cell::RefCell,
+        path::{Path, PathBuf},
+    };
+
+    thread_local! {
+        /// Root of the project of the analyzed files, set for each script call.
+        static PROJECT_ROOT: RefCell<Option<PathBuf>> = RefCell::new(None);
+    }
+
+    /// Run `f` with the given project root, used by `relative_to_root`.
+    pub(crate) fn with_project_root<T>(root: Option<PathBuf>, f: impl FnOnce() -> T) -> T {
+        let previous = PROJECT_ROOT.with(|r| r.replace(root));
+        let res = f();
+        PROJECT_ROOT.with(|r| r.replace(previous));
+        res
+    }
+
+    /// Path relative to the root of the project of the analyzed files (or to the directory in
+    /// which sylver runs, outside of a project), or the path unchanged if it is outside of it.
+    #[pyfunction]
+    fn relative_to_root(path: String) -> String {
+        let root = PROJECT_ROOT
+            .with(|r| r.borrow().clone())
+            .unwrap_or_default();
+        let root = root.strip_prefix(".").unwrap_or(&root);
+        let absolute_root = std::env::current_dir().map(|cwd| cwd.join(root)).ok();
+
+        let path = Path::new(&path);
+        let path = path.strip_prefix(".").unwrap_or(path);
+
+        let relative = path
+            .strip_prefix(root)
+            .ok()
+            .or_else(|| path.strip_prefix(absolute_root.as_ref()?).ok())
+            .unwrap_or(path);
+
+        relative.to_string_lossy().to_string()
+    }
+}
+
+#[pymodule]
+pub mod sylver_text {
+    /// Number of single character insertions, deletions or substitutions needed to turn one text
+    /// into the other.
+    #[pyfunction]
+    fn levenshtein(left: String, right: String) -> usize {
+        let right: Vec<char> = right.chars().collect();
+        let mut previous: Vec<usize> = (0..=right.len()).collect();
+
+        for (i, l) in left.chars().enumerate() {
+            let mut current = vec![i + 1];
+
+            for (j, r) in right.iter().enumerate() {
+                let substitution = previous[j] + usize::from(l != *r);
+                current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+            }
+
+            previous = current;
+        }
+
+        previous[right.len()]
+    }
+}
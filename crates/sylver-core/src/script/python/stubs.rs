@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use crate::core::describe::{FieldDescription, SpecDescription};
+
+static BASE_CLASS: &str = "ScriptNode";
+static LIST_CLASS: &str = "ScriptNodeList";
+
+static PRELUDE: &str = r#"# Generated by `sylver spec stubs`, do not edit.
+import typing
+
+T = typing.TypeVar("T")
+
+class ScriptNode:
+    kind: str
+    text: str
+    children: typing.List["ScriptNode"]
+
+class ScriptNodeList(ScriptNode, typing.Generic[T]):
+    children: typing.List[T]
+"#;
+
+static PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// Render `.pyi` stubs declaring one class per node kind of the given spec.
+pub fn python_stubs(description: &SpecDescription) -> String {
+    let kinds: HashSet<&str> = description.nodes.iter().map(|n| n.name.as_str()).collect();
+
+    let mut out = PRELUDE.to_string();
+
+    for node in &description.nodes {
+        let parent = node
+            .parent
+            .as_deref()
+            .filter(|p| kinds.contains(p))
+            .unwrap_or(BASE_CLASS);
+
+        out.push_str(&format!("\nclass {}({parent}):\n", node.name));
+
+        let fields: Vec<&FieldDescription> = node
+            .fields
+            .iter()
+            .filter(|f| !PYTHON_KEYWORDS.contains(&f.name.as_str()))
+            .collect();
+
+        if fields.is_empty() {
+            out.push_str("    ...\n");
+        }
+
+        for field in fields {
+            out.push_str(&format!(
+                "    {}: {}\n",
+                field.name,
+                field_type(&kinds, field)
+            ));
+        }
+    }
+
+    out
+}
+
+fn field_type(kinds: &HashSet<&str>, field: &FieldDescription) -> String {
+    let mut types: Vec<String> = vec![];
+
+    for ty in &field.types {
+        let ty = if kinds.contains(ty.as_str()) {
+            format!("\"{ty}\"")
+        } else {
+            format!("\"{BASE_CLASS}\"")
+        };
+
+        if !types.contains(&ty) {
+            types.push(ty);
+        }
+    }
+
+    let elem_type = match types.as_slice() {
+        [single] => single.clone(),
+        _ => format!("typing.Union[{}]", types.join(", ")),
+    };
+
+    if field.list {
+        format!("{LIST_CLASS}[{elem_type}]")
+    } else {
+        elem_type
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use crate::core::spec::test::parse_spec;
+
+    use super::*;
+
+    #[test]
+    fn stubs_for_spec() {
+        let spec = parse_spec(indoc!(
+            "
+            node Expr { }
+            node Add: Expr { left: Expr, right: Expr | Lit }
+            node Lit: Expr { }
+            node Block { stmts: List<Expr> }
+            "
+        ));
+
+        let stubs = python_stubs(&SpecDescription::from(&spec.syntax));
+
+        assert!(stubs.ends_with(indoc!(
+            "
+            class Expr(ScriptNode):
+                ...
+
+            class Add(Expr):
+                left: \"Expr\"
+                right: typing.Union[\"Expr\", \"Lit\"]
+
+            class Lit(Expr):
+                ...
+
+            class Block(ScriptNode):
+                stmts: ScriptNodeList[\"Expr\"]
+            "
+        )));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    land::{sylva::SylvaId, Land},
+    query::SylvaNode,
+    tree::{
+        info::{raw::RawTreeInfo, TreeInfo},
+        NodeId,
+    },
+};
+
+use super::names::SGraph;
+
+/// Fields holding the called expression in the call nodes of the builtin languages. The first
+/// child of the call is the called expression in the other languages.
+const CALLEE_FIELDS: [&str; 3] = ["function", "callee", "func"];
+
+/// Call of a declaration, found through the scope graph.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Call {
+    /// Innermost declaration containing the call, if any.
+    pub caller: Option<SylvaNode>,
+    /// Declaration referenced by the call.
+    pub callee: SylvaNode,
+    /// Reference to the callee.
+    pub site: SylvaNode,
+}
+
+/// Calls of a sylva, indexed by caller and by callee. The calls made outside of any declaration
+/// have no caller, so they only appear in the callees index.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CallGraph {
+    callers: HashMap<SylvaNode, Vec<SylvaNode>>,
+    callees: HashMap<SylvaNode, Vec<SylvaNode>>,
+}
+
+impl CallGraph {
+    pub fn new(calls: &[Call]) -> CallGraph {
+        let mut graph = CallGraph::default();
+
+        for call in calls {
+            let Some(caller) = call.caller else {
+                continue;
+            };
+
+            let callers = graph.callers.entry(call.callee).or_default();
+            if !callers.contains(&caller) {
+                callers.push(caller);
+            }
+
+            let callees = graph.callees.entry(caller).or_default();
+            if !callees.contains(&call.callee) {
+                callees.push(call.callee);
+            }
+        }
+
+        graph
+    }
+
+    /// Declarations calling the given declaration.
+    pub fn callers(&self, decl: SylvaNode) -> &[SylvaNode] {
+        self.callers.get(&decl).map_or(&[], Vec::as_slice)
+    }
+
+    /// Declarations called by the given declaration.
+    pub fn callees(&self, decl: SylvaNode) -> &[SylvaNode] {
+        self.callees.get(&decl).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Return the calls between the declarations of the given sylva, whose scope graph must have
+/// been computed.
+///
+/// A resolved reference is considered as a call when it ends the called expression of the
+/// closest enclosing node whose kind name contains `Call` (`Call`, `CallExpression`...): `f` in
+/// `f(x)` and `b` in `a.b(x)`, but not `x`.
+pub fn sylva_calls(land: &Land, sylva: SylvaId, graph: &SGraph) -> Vec<Call> {
+    let syntax = &land.sylva_spec(sylva).syntax;
+
+    let decls: HashSet<SylvaNode> = graph
+        .scopes()
+        .flat_map(|scope| graph.scope_decls(scope).map(|(node, _)| node))
+        .collect();
+
+    let mut calls = vec![];
+
+    for scope in graph.scopes() {
+        for (site, _) in graph.scope_refs(scope) {
+            if site.sylva != sylva {
+                continue;
+            }
+
+            let info = RawTreeInfo::new(land.sylva_node_tree(site), syntax);
+
+            if !is_call_target(&info, site.node) {
+                continue;
+            }
+
+            let caller = std::iter::successors(info.parent(site.node), |&n| info.parent(n))
+                .map(|node| SylvaNode { node, ..site })
+                .find(|n| decls.contains(n));
+
+            for &callee in graph.referenced_decls(site).unwrap_or_default() {
+                calls.push(Call {
+                    caller,
+                    callee,
+                    site,
+                });
+            }
+        }
+    }
+
+    calls
+}
+
+fn is_call_target(info: &RawTreeInfo, site: NodeId) -> bool {
+    let Some(call) = std::iter::successors(info.parent(site), |&n| info.parent(n))
+        .find(|&n| info.kind_name(info.node(n).kind).contains("Call"))
+    else {
+        return false;
+    };
+
+    let callee = CALLEE_FIELDS
+        .iter()
+        .find_map(|field| info.field_value_from_name(call, field))
+        .or_else(|| info.proxy(call).direct_children().first().map(|c| c.id()));
+
+    let Some(callee) = callee else {
+        return false;
+    };
+
+    let in_callee = std::iter::successors(Some(site), |&n| info.parent(n)).any(|n| n == callee);
+
+    in_callee && info.node_tokens(site).last() == info.node_tokens(callee).last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: usize) -> SylvaNode {
+        SylvaNode {
+            sylva: 0.into(),
+            tree: 0.into(),
+            node: id.into(),
+        }
+    }
+
+    #[test]
+    fn call_graph_index() {
+        let call = |caller: Option<usize>, callee: usize, site: usize| Call {
+            caller: caller.map(node),
+            callee: node(callee),
+            site: node(site),
+        };
+
+        let graph = CallGraph::new(&[
+            call(Some(1), 2, 10),
+            call(Some(1), 2, 11),
+            call(Some(3), 2, 12),
+            call(Some(2), 3, 13),
+            call(None, 1, 14),
+        ]);
+
+        assert_eq!(graph.callers(node(2)), &[node(1), node(3)]);
+        assert_eq!(graph.callees(node(1)), &[node(2)]);
+        assert_eq!(graph.callees(node(2)), &[node(3)]);
+        assert!(graph.callers(node(1)).is_empty());
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::store::ContentKey,
+    dashboard::api::Position,
+    land::{
+        passes::compute_tree_scopes,
+        sylva::{SylvaId, SylvaTree, SylvaTreeId},
+        Land,
+    },
+    query::SylvaNode,
+    tree::info::{raw::RawTreeInfo, TreeInfo},
+    util::cancel::CancellationToken,
+};
+
+/// Declarations and references of the analyzed files, persisted between runs so that the scope
+/// graph of the unchanged files doesn't have to be rebuilt.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    files: BTreeMap<PathBuf, FileSymbols>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FileSymbols {
+    /// Key of the content of the file when it was indexed.
+    pub content: ContentKey,
+    pub symbols: Vec<IndexedSymbol>,
+    pub references: Vec<IndexedReference>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IndexedSymbol {
+    pub name: String,
+    /// Kind of the declaration node.
+    pub kind: String,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IndexedReference {
+    pub name: String,
+    pub position: Position,
+    pub definitions: Vec<SymbolLocation>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SymbolLocation {
+    pub file: PathBuf,
+    pub position: Position,
+}
+
+impl SymbolIndex {
+    /// Load the index stored at the given path. A missing or unreadable index is considered
+    /// empty, and will be rebuilt.
+    pub fn load(path: &Path) -> SymbolIndex {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Could not write symbol index: {}", path.display()))
+    }
+
+    pub fn file(&self, path: &Path) -> Option<&FileSymbols> {
+        self.files.get(&index_path(path))
+    }
+
+    /// Return the locations of the symbols with the given name.
+    pub fn definitions<'i>(&'i self, name: &'i str) -> impl Iterator<Item = SymbolLocation> + 'i {
+        self.files.iter().flat_map(move |(file, symbols)| {
+            symbols
+                .symbols
+                .iter()
+                .filter(move |s| s.name == name)
+                .map(move |s| SymbolLocation {
+                    file: file.clone(),
+                    position: s.position.clone(),
+                })
+        })
+    }
+
+    /// Return the definitions of the symbol referenced at the given position (1-based line and
+    /// column).
+    pub fn definitions_at(&self, path: &Path, line: usize, column: usize) -> &[SymbolLocation] {
+        self.file(path)
+            .and_then(|file| {
+                file.references
+                    .iter()
+                    .find(|r| contains(&r.position, line, column))
+            })
+            .map(|r| r.definitions.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Return the references resolved to the symbol declared, or referenced, at the given position
+    /// (1-based line and column).
+    pub fn references_at(&self, path: &Path, line: usize, column: usize) -> Vec<SymbolLocation> {
+        let declaration = self.file(path).and_then(|file| {
+            file.symbols
+                .iter()
+                .find(|s| contains(&s.position, line, column))
+        });
+
+        let targets = match declaration {
+            Some(symbol) => vec![SymbolLocation {
+                file: index_path(path),
+                position: symbol.position.clone(),
+            }],
+            None => self.definitions_at(path, line, column).to_vec(),
+        };
+
+        self.files
+            .iter()
+            .flat_map(|(file, symbols)| {
+                symbols
+                    .references
+                    .iter()
+                    .filter(|r| r.definitions.iter().any(|d| targets.contains(d)))
+                    .map(move |r| SymbolLocation {
+                        file: file.clone(),
+                        position: r.position.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Index the files of the land that changed since the last update, and drop the files that
+    /// are no longer part of it. The unchanged files whose references may resolve differently
+    /// are indexed again as well. Return the number of (re)indexed files.
+    pub fn update(&mut self, land: &Land, cancel: &CancellationToken) -> anyhow::Result<usize> {
+        let mut trees = BTreeMap::new();
+
+        for sylva_id in land.sylvae() {
+            for (tree_id, tree) in land.sylva(sylva_id).iter() {
+                let path = index_path(tree.tree.source.path());
+                let content = ContentKey::of(tree.tree.source.src().as_bytes());
+                trees.insert(path, (sylva_id, tree_id, tree, content));
+            }
+        }
+
+        let changed: BTreeSet<PathBuf> = trees
+            .iter()
+            .filter(|(path, (_, _, _, content))| {
+                self.files.get(*path).map(|f| &f.content) != Some(content)
+            })
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.files
+                    .keys()
+                    .filter(|path| !trees.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+
+        // Names declared in the changed files before the update...
+        let mut stale = self.dependent_files(&changed);
+        self.files.retain(|path, _| trees.contains_key(path));
+
+        for path in &changed {
+            if let Some(&(sylva_id, tree_id, tree, ref content)) = trees.get(path) {
+                cancel.check()?;
+                let symbols = index_tree(land, sylva_id, tree_id, tree, content.clone(), cancel)?;
+                self.files.insert(path.clone(), symbols);
+            }
+        }
+
+        // ...and after it.
+        stale.extend(self.dependent_files(&changed));
+
+        for path in &stale {
+            let &(sylva_id, tree_id, tree, ref content) = &trees[path];
+            cancel.check()?;
+            let symbols = index_tree(land, sylva_id, tree_id, tree, content.clone(), cancel)?;
+            self.files.insert(path.clone(), symbols);
+        }
+
+        Ok(changed.iter().filter(|p| trees.contains_key(*p)).count() + stale.len())
+    }
+
+    /// Indexed files, other than the `changed` ones, whose references point into a changed file
+    /// or use a name declared in one.
+    fn dependent_files(&self, changed: &BTreeSet<PathBuf>) -> BTreeSet<PathBuf> {
+        let names: HashSet<&str> = changed
+            .iter()
+            .filter_map(|path| self.files.get(path))
+            .flat_map(|file| file.symbols.iter().map(|s| s.name.as_str()))
+            .collect();
+
+        self.files
+            .iter()
+            .filter(|(path, _)| !changed.contains(*path))
+            .filter(|(_, file)| {
+                file.references.iter().any(|r| {
+                    names.contains(r.name.as_str())
+                        || r.definitions.iter().any(|d| changed.contains(&d.file))
+                })
+            })
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+/// Index the declarations and references of a tree, computing its scope graph if needed.
+fn index_tree(
+    land: &Land,
+    sylva_id: SylvaId,
+    tree_id: SylvaTreeId,
+    tree: &SylvaTree,
+    content: ContentKey,
+    cancel: &CancellationToken,
+) -> anyhow::Result<FileSymbols> {
+    let syntax = &land.sylva_spec(sylva_id).syntax;
+
+    let mut scopes = land.sylva_scopes_mut(sylva_id);
+    compute_tree_scopes(land, sylva_id, tree_id, &mut scopes, cancel)?;
+    let graph = scopes.graph()?;
+
+    let in_tree = |node: &SylvaNode| node.sylva == sylva_id && node.tree == tree_id;
+    let info = RawTreeInfo::new(&tree.tree, syntax);
+
+    let symbols = graph
+        .scopes()
+        .flat_map(|scope| graph.scope_decls(scope))
+        .filter(|(node, _)| in_tree(node))
+        .map(|(node, name)| IndexedSymbol {
+            name: name.to_string(),
+            kind: syntax.kind_name(info.node(node.node).kind).to_string(),
+            position: info.node_pos(node.node).into(),
+        })
+        .collect();
+
+    let references = graph
+        .scopes()
+        .flat_map(|scope| graph.scope_refs(scope))
+        .filter(|(node, _)| in_tree(node))
+        .map(|(node, name)| IndexedReference {
+            name: name.to_string(),
+            position: info.node_pos(node.node).into(),
+            definitions: graph
+                .referenced_decls(node)
+                .unwrap_or_default()
+                .iter()
+                .map(|&decl| symbol_location(land, decl))
+                .collect(),
+        })
+        .collect();
+
+    Ok(FileSymbols {
+        content,
+        symbols,
+        references,
+    })
+}
+
+fn symbol_location(land: &Land, node: SylvaNode) -> SymbolLocation {
+    let tree = land.sylva_node_tree(node);
+    let info = RawTreeInfo::new(tree, &land.sylva_spec(node.sylva).syntax);
+
+    SymbolLocation {
+        file: index_path(tree.source.path()),
+        position: info.node_pos(node.node).into(),
+    }
+}
+
+/// Files are indexed by canonical path, so that they can be looked up from any directory.
+fn index_path(path: &Path) -> PathBuf {
+    dunce::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+fn contains(position: &Position, line: usize, column: usize) -> bool {
+    (position.start_line, position.start_col) <= (line, column)
+        && (line, column) <= (position.end_line, position.end_col)
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+
+    fn position(line: usize, start_col: usize, end_col: usize) -> Position {
+        Position {
+            start_line: line,
+            start_col,
+            start_txt_pos: 0,
+            end_line: line,
+            end_col,
+            end_txt_pos: 0,
+        }
+    }
+
+    fn test_index() -> SymbolIndex {
+        let file = PathBuf::from("/project/main.py");
+        let definition = SymbolLocation {
+            file: file.clone(),
+            position: position(1, 1, 12),
+        };
+
+        SymbolIndex {
+            files: BTreeMap::from([(
+                file,
+                FileSymbols {
+                    content: ContentKey::of(b""),
+                    symbols: vec![IndexedSymbol {
+                        name: "hello".to_string(),
+                        kind: "FunctionDefinition".to_string(),
+                        position: definition.position.clone(),
+                    }],
+                    references: vec![IndexedReference {
+                        name: "hello".to_string(),
+                        position: position(3, 5, 10),
+                        definitions: vec![definition],
+                    }],
+                },
+            )]),
+        }
+    }
+
+    #[test]
+    fn definitions_at_reference() {
+        let index = test_index();
+        let file = Path::new("/project/main.py");
+
+        assert_eq!(index.definitions_at(file, 3, 7).len(), 1);
+        assert!(index.definitions_at(file, 3, 11).is_empty());
+        assert!(index
+            .definitions_at(Path::new("/project/other.py"), 3, 7)
+            .is_empty());
+    }
+
+    #[test]
+    fn references_to_symbol() {
+        let index = test_index();
+        let file = Path::new("/project/main.py");
+
+        let from_declaration = index.references_at(file, 1, 5);
+        assert_eq!(from_declaration.len(), 1);
+        assert_eq!(from_declaration[0].position, position(3, 5, 10));
+
+        assert_eq!(index.references_at(file, 3, 7), from_declaration);
+        assert!(index.references_at(file, 2, 1).is_empty());
+    }
+
+    #[test]
+    fn definitions_by_name() {
+        let index = test_index();
+
+        assert_eq!(index.definitions("hello").count(), 1);
+        assert_eq!(index.definitions("world").count(), 0);
+    }
+
+    #[test]
+    fn edited_definitions_invalidate_references() {
+        let lib = PathBuf::from("/project/lib.py");
+        let main = PathBuf::from("/project/main.py");
+        let other = PathBuf::from("/project/other.py");
+
+        let file = |symbols: Vec<IndexedSymbol>, references: Vec<IndexedReference>| FileSymbols {
+            content: ContentKey::of(b""),
+            symbols,
+            references,
+        };
+        let reference = |name: &str, definitions: Vec<SymbolLocation>| IndexedReference {
+            name: name.to_string(),
+            position: position(1, 1, 5),
+            definitions,
+        };
+
+        let index = SymbolIndex {
+            files: BTreeMap::from([
+                (
+                    lib.clone(),
+                    file(
+                        vec![IndexedSymbol {
+                            name: "helper".to_string(),
+                            kind: "FunctionDefinition".to_string(),
+                            position: position(1, 1, 12),
+                        }],
+                        vec![],
+                    ),
+                ),
+                (
+                    main.clone(),
+                    file(
+                        vec![],
+                        vec![reference(
+                            "alias",
+                            vec![SymbolLocation {
+                                file: lib.clone(),
+                                position: position(1, 1, 12),
+                            }],
+                        )],
+                    ),
+                ),
+                (
+                    other.clone(),
+                    file(vec![], vec![reference("helper", vec![])]),
+                ),
+            ]),
+        };
+
+        assert_eq!(
+            index.dependent_files(&BTreeSet::from([lib])),
+            BTreeSet::from([main.clone(), other])
+        );
+        assert!(index.dependent_files(&BTreeSet::from([main])).is_empty());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("symbols.json");
+
+        assert_eq!(SymbolIndex::load(&path), SymbolIndex::default());
+
+        test_index().save(&path).unwrap();
+        assert_eq!(SymbolIndex::load(&path), test_index());
+    }
+}
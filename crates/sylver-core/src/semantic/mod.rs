@@ -1 +1,3 @@
+pub mod calls;
+pub mod index;
 pub mod names;
@@ -8,14 +8,18 @@ use derive_more::From;
 use thiserror::Error;
 
 use crate::{
-    core::spec::Aspects,
+    core::spec::{Aspects, Binding},
     land::{sylva::SylvaTreeId, Land},
     query::SylvaNode,
     script::{
-        python::PythonScriptEngine, ScriptEngine, ScriptError, ScriptQueryValue, ScriptTreeInfo,
-        ScriptValue,
+        python::{PendingBatch, PythonScriptEngine},
+        ScriptEngine, ScriptError, ScriptQueryValue, ScriptTreeInfo, ScriptValue,
     },
-    tree::info::raw::RawTreeInfo,
+    tree::{
+        info::{raw::RawTreeInfo, TreeInfo},
+        NodeId,
+    },
+    util::cancel::CancellationToken,
 };
 
 static SG_GEN_ASPECT: &str = "sg_gen";
@@ -28,10 +32,21 @@ pub enum NamesError {
     Script(#[from] ScriptError),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct SylvaScopes {
     sgraph: SGraph,
     computed_trees: HashSet<SylvaTreeId>,
+    /// Trees whose aspects are still evaluated by the script engine.
+    pending: Option<PendingGraphs>,
+}
+
+/// Scope graph shared with the batches of aspect calls sent to the script engine, which replaces
+/// the graph of the sylva once every batch has been evaluated.
+#[derive(Debug)]
+struct PendingGraphs {
+    sgraph: Arc<RwLock<SGraph>>,
+    trees: Vec<SylvaTreeId>,
+    batches: Vec<PendingBatch>,
 }
 
 impl SylvaScopes {
@@ -39,9 +54,22 @@ impl SylvaScopes {
         SylvaScopes {
             sgraph: SGraph::new(),
             computed_trees: HashSet::new(),
+            pending: None,
         }
     }
 
+    /// Return whether the scope graph of the given tree has already been computed, or is being
+    /// computed by the script engine.
+    pub fn is_computed(&self, tree: SylvaTreeId) -> bool {
+        self.computed_trees.contains(&tree)
+    }
+
+    /// Scope graph of the trees computed so far.
+    pub fn graph(&mut self) -> Result<&SGraph, NamesError> {
+        self.finish_pending()?;
+        Ok(&self.sgraph)
+    }
+
     pub fn referenced_decls(
         &mut self,
         sylva_node: SylvaNode,
@@ -49,12 +77,15 @@ impl SylvaScopes {
         aspects: &Aspects,
         tree_infos: RawTreeInfo,
         engine: PythonScriptEngine,
+        cancel: &CancellationToken,
     ) -> Result<Option<&[SylvaNode]>, NamesError> {
+        self.finish_pending()?;
+
         if self.computed_trees.contains(&sylva_node.tree) {
             return Ok(self.sgraph.referenced_decls(sylva_node));
         }
 
-        self.compute_tree_graph(sylva_node, land, aspects, tree_infos, engine)
+        self.compute_tree_graph(sylva_node, land, aspects, tree_infos, engine, cancel)
     }
 
     pub fn compute_tree_graph(
@@ -64,52 +95,263 @@ impl SylvaScopes {
         aspects: &Aspects,
         mut tree_infos: RawTreeInfo,
         engine: PythonScriptEngine,
+        cancel: &CancellationToken,
     ) -> Result<Option<&[SylvaNode]>, NamesError> {
+        self.finish_pending()?;
         self.computed_trees.insert(sylva_node.tree);
 
         let sylva = land.sylva(sylva_node.sylva);
-        let Some(gen_aspect) = aspects.get(SG_GEN_ASPECT) else {
-            return Ok(None);
-        };
         let Some(tree) = sylva.tree(sylva_node.tree) else {
             return Ok(None);
         };
+        let Some(gen_aspect) = aspects.get(SG_GEN_ASPECT) else {
+            return Ok(self.compute_bindings_graph(sylva_node, tree_infos));
+        };
         let tree_scope = self.sgraph.add_scope(self.sgraph.root());
-        let mut sgraph = Arc::new(RwLock::new(std::mem::take(&mut self.sgraph)));
-
-        for node in tree.nodes() {
-            if let Some(script) = gen_aspect.get(&tree.tree[node].kind) {
-                let node_arg = ScriptQueryValue::Node(SylvaNode {
-                    sylva: sylva_node.sylva,
-                    tree: sylva_node.tree,
-                    node,
-                });
-
-                let tree_infos = RefCell::new(ScriptTreeInfo::new(&mut tree_infos));
-
-                let scope_arg = ScriptQueryValue::Simple(ScriptValue::Scope(
-                    tree_scope,
-                    sgraph.clone(),
-                    tree_infos.clone(),
-                ));
-
-                sgraph =
-                    match engine.eval_in_query(script, vec![node_arg, scope_arg], tree_infos)? {
-                        ScriptQueryValue::Simple(ScriptValue::Scope(_, sgraph, _)) => sgraph,
-                        _ => return Err(NamesError::UnexpectedEvalType),
-                    };
+        let sgraph = Arc::new(RwLock::new(std::mem::take(&mut self.sgraph)));
+        let root = land.sylva_root(sylva_node.sylva);
+
+        let evaluated = tree.nodes().try_for_each(|node| -> Result<(), NamesError> {
+            let Some(script) = gen_aspect.get(&tree.tree[node].kind) else {
+                return Ok(());
+            };
+
+            cancel.check().map_err(ScriptError::from)?;
+
+            let node_arg = ScriptQueryValue::Node(SylvaNode {
+                sylva: sylva_node.sylva,
+                tree: sylva_node.tree,
+                node,
+            });
+
+            let tree_infos = RefCell::new(ScriptTreeInfo::new(&mut tree_infos));
+
+            let scope_arg = ScriptQueryValue::Simple(ScriptValue::Scope(
+                tree_scope,
+                sgraph.clone(),
+                Some(tree_infos.clone()),
+            ));
+
+            match engine.eval_in_query(script, vec![node_arg, scope_arg], tree_infos, root)? {
+                // The aspect didn't declare anything for this node.
+                ScriptQueryValue::Simple(ScriptValue::Scope(..) | ScriptValue::Null) => Ok(()),
+                _ => Err(NamesError::UnexpectedEvalType),
             }
+        });
+
+        // The graph of the other trees is kept when the aspect fails.
+        self.sgraph = take_shared_graph(sgraph);
+        evaluated?;
+
+        self.sgraph.solve();
+        Ok(self.sgraph.referenced_decls(sylva_node))
+    }
+
+    /// Compute the scope graphs of the given trees, when every `sg_gen` aspect declares a
+    /// projection of its nodes. The projected nodes of each tree are sent to the script engine
+    /// as one batch, which is evaluated while the following trees are projected and while the
+    /// rules that don't resolve names are evaluated: the graph is only awaited by the first
+    /// name resolution.
+    ///
+    /// Return `false`, without computing anything, when some aspect needs access to the tree.
+    /// Once the token is cancelled, the remaining aspect calls fail without being evaluated.
+    pub fn compute_projected_graphs(
+        &mut self,
+        trees: Vec<(SylvaNode, RawTreeInfo)>,
+        land: &Land,
+        aspects: &Aspects,
+        engine: PythonScriptEngine,
+        cancel: &CancellationToken,
+    ) -> Result<bool, NamesError> {
+        let Some(gen_aspect) = aspects.get(SG_GEN_ASPECT) else {
+            return Ok(false);
+        };
+
+        if gen_aspect
+            .values()
+            .any(|script| script.projection().is_none())
+        {
+            return Ok(false);
         }
 
-        let mut sgraph = Arc::into_inner(sgraph)
-            .expect("scope graph reference was kept in the script engine")
-            .into_inner()
-            .expect("poisoned scope graph lock");
+        self.finish_pending()?;
 
-        sgraph.solve();
+        // The scripts fill a copy of the graph, so that it is left as is if one of them fails.
+        let mut pending = PendingGraphs {
+            sgraph: Arc::new(RwLock::new(self.sgraph.clone())),
+            trees: vec![],
+            batches: vec![],
+        };
 
-        self.sgraph = sgraph;
-        Ok(self.sgraph.referenced_decls(sylva_node))
+        for (sylva_node, tree_infos) in trees {
+            let Some(tree) = land.sylva(sylva_node.sylva).tree(sylva_node.tree) else {
+                continue;
+            };
+
+            if !self.computed_trees.insert(sylva_node.tree) {
+                continue;
+            }
+            pending.trees.push(sylva_node.tree);
+
+            let tree_scope = {
+                let mut sgraph = pending.sgraph.write().expect("poisoned scope graph lock");
+                let root = sgraph.root();
+                sgraph.add_scope(root)
+            };
+            let source: Arc<str> = tree_infos.source_code().into();
+
+            let calls = tree
+                .nodes()
+                .filter_map(|node| {
+                    let script = gen_aspect.get(&tree.tree[node].kind)?;
+                    let projected = script.projection()?.project(
+                        &tree_infos,
+                        &source,
+                        sylva_node.with_node_id(node),
+                    );
+                    let scope = ScriptValue::Scope(tree_scope, pending.sgraph.clone(), None);
+
+                    Some((
+                        *script,
+                        vec![ScriptValue::Projected(Arc::new(projected)), scope],
+                    ))
+                })
+                .collect();
+
+            let sent = cancel.check().map_err(ScriptError::from).and_then(|_| {
+                engine.eval_batch(calls, land.sylva_root(sylva_node.sylva), cancel.clone())
+            });
+
+            match sent {
+                Ok(batch) => pending.batches.push(batch),
+                Err(e) => {
+                    // The batches already sent are dropped along with their copy of the graph.
+                    self.forget_trees(&pending.trees);
+                    return Err(e.into());
+                }
+            }
+        }
+
+        self.pending = Some(pending);
+        Ok(true)
+    }
+
+    /// Wait for the evaluation of the batches sent by `compute_projected_graphs`. When an aspect
+    /// fails, the graph is left as it was before and the trees of the batches are computed again
+    /// one by one when their names are resolved.
+    fn finish_pending(&mut self) -> Result<(), NamesError> {
+        let Some(pending) = self.pending.take() else {
+            return Ok(());
+        };
+
+        // Every batch is awaited before reporting errors, so that no script still holds the graph.
+        let results: Vec<_> = pending
+            .batches
+            .into_iter()
+            .map(PendingBatch::wait)
+            .collect();
+
+        let evaluated = results
+            .into_iter()
+            .try_for_each(|values| -> Result<(), NamesError> {
+                values?
+                    .into_iter()
+                    .try_for_each(|value| -> Result<(), NamesError> {
+                        match value? {
+                            ScriptValue::Scope(..) | ScriptValue::Null => Ok(()),
+                            _ => Err(NamesError::UnexpectedEvalType),
+                        }
+                    })
+            });
+
+        if let Err(e) = evaluated {
+            self.forget_trees(&pending.trees);
+            return Err(e);
+        }
+
+        self.sgraph = take_shared_graph(pending.sgraph);
+        self.sgraph.solve();
+        Ok(())
+    }
+
+    fn forget_trees(&mut self, trees: &[SylvaTreeId]) {
+        for tree in trees {
+            self.computed_trees.remove(tree);
+        }
+    }
+
+    /// Build the scope graph of a tree from the binding declarations of its spec. Declarations
+    /// and references are bound in the innermost scope enclosing the node. The name of a
+    /// declaration is not a reference, even if its kind is declared as one.
+    fn compute_bindings_graph(
+        &mut self,
+        sylva_node: SylvaNode,
+        tree_infos: RawTreeInfo,
+    ) -> Option<&[SylvaNode]> {
+        let syntax = tree_infos.syntax();
+
+        if !syntax.has_bindings() {
+            return None;
+        }
+
+        let tree_scope = self.sgraph.add_scope(self.sgraph.root());
+        let mut to_visit = vec![(tree_infos.root().id, tree_scope)];
+        // Declarations are visited before the names they declare, which are their descendants.
+        let mut declared_names = HashSet::new();
+
+        while let Some((node, scope)) = to_visit.pop() {
+            let mut childs_scope = scope;
+            let bound_node = SylvaNode { node, ..sylva_node };
+
+            for binding in syntax.bindings(tree_infos.node(node).kind) {
+                match binding {
+                    Binding::Scope => childs_scope = self.sgraph.add_scope(scope),
+                    Binding::Declaration(field) => {
+                        if let Some(named) = binding_target(&tree_infos, node, field.as_deref()) {
+                            declared_names.insert(named);
+                            let name = tree_infos.node_text(named).to_string();
+                            self.sgraph.add_decl(scope, name, bound_node);
+                        }
+                    }
+                    Binding::Reference(field) => {
+                        if let Some(named) = binding_target(&tree_infos, node, field.as_deref()) {
+                            if !declared_names.contains(&named) {
+                                let name = tree_infos.node_text(named).to_string();
+                                self.sgraph.add_ref(scope, name, bound_node);
+                            }
+                        }
+                    }
+                }
+            }
+
+            to_visit.extend(
+                tree_infos
+                    .node(node)
+                    .childs
+                    .iter()
+                    .map(|&child| (child, childs_scope)),
+            );
+        }
+
+        self.sgraph.solve();
+        self.sgraph.referenced_decls(sylva_node)
+    }
+}
+
+/// Take back a graph shared with the scripts, copying it if a script kept a reference to it.
+fn take_shared_graph(sgraph: Arc<RwLock<SGraph>>) -> SGraph {
+    match Arc::try_unwrap(sgraph) {
+        Ok(sgraph) => sgraph.into_inner().expect("poisoned scope graph lock"),
+        Err(shared) => shared.read().expect("poisoned scope graph lock").clone(),
+    }
+}
+
+/// Node holding the name of a binding: the given field of the node, or the node itself if no
+/// field is given.
+fn binding_target(tree_infos: &RawTreeInfo, node: NodeId, field: Option<&str>) -> Option<NodeId> {
+    match field {
+        Some(f) => tree_infos.field_value_from_name(node, f),
+        None => Some(node),
     }
 }
 
@@ -122,6 +364,12 @@ impl Default for SylvaScopes {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, From)]
 pub struct ScopeId(petgraph::graph::NodeIndex);
 
+impl ScopeId {
+    pub fn index(self) -> usize {
+        self.0.index()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum SGraphValue {
     Decl(SylvaNode, String),
@@ -141,6 +389,13 @@ impl SGraphNode {
         })
     }
 
+    fn decls(&self) -> impl Iterator<Item = (SylvaNode, &str)> {
+        self.values.iter().filter_map(|v| match v {
+            SGraphValue::Decl(node, n) => Some((*node, n.as_str())),
+            _ => None,
+        })
+    }
+
     fn refs(&self) -> impl Iterator<Item = (SylvaNode, &str)> {
         self.values.iter().filter_map(|v| match v {
             SGraphValue::Ref(node, n) => Some((*node, n.as_str())),
@@ -244,6 +499,25 @@ impl SGraph {
     pub fn node_refs(&self, node: SylvaNode) -> Option<&[SylvaNode]> {
         self.decl_to_refs.get(&node).map(|v| v.as_slice())
     }
+
+    pub fn scopes(&self) -> impl Iterator<Item = ScopeId> + '_ {
+        self.graph.node_indices().map(Into::into)
+    }
+
+    /// Scopes in which the names missing from the given scope are looked up.
+    pub fn parent_scopes(&self, scope: ScopeId) -> impl Iterator<Item = ScopeId> + '_ {
+        self.graph
+            .neighbors_directed(scope.0, petgraph::Direction::Outgoing)
+            .map(Into::into)
+    }
+
+    pub fn scope_decls(&self, scope: ScopeId) -> impl Iterator<Item = (SylvaNode, &str)> {
+        self.graph[scope.0].decls()
+    }
+
+    pub fn scope_refs(&self, scope: ScopeId) -> impl Iterator<Item = (SylvaNode, &str)> {
+        self.graph[scope.0].refs()
+    }
 }
 
 #[cfg(test)]
@@ -372,4 +646,41 @@ mod test {
         assert_eq!(Some([node1].as_slice()), graph.referenced_decls(node2));
         assert_eq!(Some([node2].as_slice()), graph.node_refs(node1));
     }
+
+    #[test]
+    fn scope_contents() {
+        let decl = SylvaNode {
+            sylva: 0.into(),
+            tree: 0.into(),
+            node: 0.into(),
+        };
+
+        let reference = SylvaNode {
+            sylva: 0.into(),
+            tree: 0.into(),
+            node: 1.into(),
+        };
+
+        let mut graph = SGraph::new();
+        let scope = graph.add_scope(graph.root());
+        graph.add_decl(graph.root(), "foo".to_string(), decl);
+        graph.add_ref(scope, "foo".to_string(), reference);
+
+        assert_eq!(
+            vec![graph.root(), scope],
+            graph.scopes().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![graph.root()],
+            graph.parent_scopes(scope).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(decl, "foo")],
+            graph.scope_decls(graph.root()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(reference, "foo")],
+            graph.scope_refs(scope).collect::<Vec<_>>()
+        );
+    }
 }
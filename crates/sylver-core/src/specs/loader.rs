@@ -1,4 +1,6 @@
-use std::{ffi::OsStr, fs::read_to_string, path::Path, path::PathBuf, sync::Arc};
+use std::{
+    collections::BTreeMap, ffi::OsStr, fs::read_to_string, io, path::Path, path::PathBuf, sync::Arc,
+};
 
 use anyhow::{anyhow, Context};
 use log::Logger;
@@ -8,22 +10,25 @@ use serde::de::DeserializeOwned;
 use crate::{
     api::{ApiClient, RegistryItemKind},
     core::{
-        files_spec::{FileSpec, FileSpecLoader, FsFileSpecLoader},
+        files_spec::{FileSpec, FileSpecLoader, FsFileSpecLoader, MemoryFileSpecLoader},
         source::Source,
-        spec::{spec_from_files, Spec},
+        spec::{spec_from_reader, Spec},
     },
     script::python::PythonScriptEngine,
     specs::stem::{
-        language::LanguageStem, location::StemLocation, project::ProjectConfigStem,
+        language::LanguageStem,
+        location::StemLocation,
+        project::{ProjectConfigStem, ProjectLang},
         ruleset::RuleSetStem,
     },
+    specs::trust::{TrustPolicy, SIGNATURE_EXTENSION, SIGSTORE_SIGNATURE_EXTENSION},
     state::SylverState,
     util::git::GitClient,
 };
 
 #[derive(Debug, Clone)]
 pub struct SylverLoader<
-    F: FileSpecLoader = FsFileSpecLoader,
+    F: FileSpecLoader = MemoryFileSpecLoader,
     R: LocationLoader<RuleSetStem> = FullLocationLoader<RulesetStemLoader>,
     C: PathLoader<Output = ProjectConfigStem> = DefaultPathLoader<ProjectConfigStem>,
     S: LocationLoader<Spec> = FullLocationLoader<LanguageStemLoader>,
@@ -31,7 +36,7 @@ pub struct SylverLoader<
     files: F,
     rulesets: R,
     projects: C,
-    pub langs: S,
+    langs: S,
 }
 
 impl<
@@ -54,23 +59,65 @@ impl<
         self.files.load(path)
     }
 
-    pub fn load_language_spec(&self, location: &StemLocation) -> anyhow::Result<Spec> {
-        self.langs.load(location)
+    /// Loader of the files matching the file specs.
+    pub fn files(&self) -> &F {
+        &self.files
+    }
+
+    /// Load a language spec. The files of a downloaded spec are checked against the policy.
+    pub fn load_language_spec(
+        &self,
+        location: &StemLocation,
+        policy: &TrustPolicy,
+    ) -> anyhow::Result<Spec> {
+        self.langs.load_trusted(location, policy)
+    }
+
+    /// Content of the files of a language spec: the spec, its imports and its aspects.
+    pub fn language_spec_files(
+        &self,
+        location: &StemLocation,
+    ) -> anyhow::Result<BTreeMap<PathBuf, String>> {
+        self.langs.stem_files(location)
+    }
+
+    pub fn language_id(&self, location: &StemLocation) -> anyhow::Result<String> {
+        self.langs.id(location)
     }
 
-    pub fn load_ruleset(&self, path: &StemLocation) -> anyhow::Result<RuleSetStem> {
-        self.rulesets.load(path)
+    /// Load a ruleset. A downloaded ruleset is checked against the policy.
+    pub fn load_ruleset(
+        &self,
+        location: &StemLocation,
+        policy: &TrustPolicy,
+    ) -> anyhow::Result<RuleSetStem> {
+        let ruleset = self.rulesets.load_trusted(location, policy)?;
+        ruleset
+            .validate()
+            .with_context(|| format!("Invalid ruleset: {location}"))?;
+        Ok(ruleset)
     }
 
+    /// Load a configuration file. The paths of its external grammars are relative to the
+    /// directory of the file.
     pub fn load_config(&self, path: &Path) -> anyhow::Result<ProjectConfigStem> {
-        self.projects.load(path)
+        let mut config = self.projects.load(path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        for project in config.projects_mut() {
+            if let ProjectLang::External(grammar) = &mut project.language {
+                grammar.resolve_paths(dir);
+            }
+        }
+
+        Ok(config)
     }
 }
 
 impl SylverLoader {
     pub fn from_state(state: Arc<SylverState>) -> SylverLoader {
         SylverLoader::new(
-            FsFileSpecLoader::default(),
+            MemoryFileSpecLoader::overlay(FsFileSpecLoader::default()),
             FullLocationLoader::from_state(state.clone()),
             DefaultPathLoader::new("config".to_string()),
             FullLocationLoader::new(
@@ -83,9 +130,36 @@ impl SylverLoader {
     }
 }
 
+/// Path of the detached signature of a downloaded stem file, with the given extension.
+fn signature_path(path: &Path, extension: &str) -> PathBuf {
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(".");
+    signature.push(extension);
+    PathBuf::from(signature)
+}
+
+/// Read the detached signature of a downloaded stem file: the minisign one, or else the sigstore
+/// one.
+fn read_signature(path: &Path) -> Option<String> {
+    [SIGNATURE_EXTENSION, SIGSTORE_SIGNATURE_EXTENSION]
+        .into_iter()
+        .find_map(|extension| read_to_string(signature_path(path, extension)).ok())
+}
+
 pub trait PathLoader {
     type Output;
-    fn load(&self, path: &Path) -> anyhow::Result<Self::Output>;
+
+    fn load(&self, path: &Path) -> anyhow::Result<Self::Output> {
+        self.load_with(path, &mut |file: &Path| read_to_string(file))
+    }
+
+    /// Load the stem at the given path, reading each of its files with `read`.
+    fn load_with(
+        &self,
+        path: &Path,
+        read: &mut dyn FnMut(&Path) -> io::Result<String>,
+    ) -> anyhow::Result<Self::Output>;
+
     fn id(&self, path: &Path) -> anyhow::Result<String>;
     fn artefact_type(&self) -> &str;
 }
@@ -116,8 +190,16 @@ impl<T: DeserializeOwned> DefaultPathLoader<T> {
     }
 
     fn load_from_path<U: DeserializeOwned>(&self, path: &Path) -> anyhow::Result<U> {
-        let stem_str = read_to_string(path)
-            .with_context(|| format!("cannot read stem file: {}", path.display()))?;
+        self.load_from_reader(path, &mut |file: &Path| read_to_string(file))
+    }
+
+    fn load_from_reader<U: DeserializeOwned>(
+        &self,
+        path: &Path,
+        read: &mut dyn FnMut(&Path) -> io::Result<String>,
+    ) -> anyhow::Result<U> {
+        let stem_str =
+            read(path).with_context(|| format!("cannot read stem file: {}", path.display()))?;
 
         serde_yaml::from_str(&stem_str).map_err(|e| {
             let prefix = format!("Invalid stem: {}", path.display());
@@ -139,9 +221,13 @@ impl<T: DeserializeOwned> DefaultPathLoader<T> {
 impl<T: DeserializeOwned> PathLoader for DefaultPathLoader<T> {
     type Output = T;
 
-    fn load(&self, path: &Path) -> anyhow::Result<T> {
+    fn load_with(
+        &self,
+        path: &Path,
+        read: &mut dyn FnMut(&Path) -> io::Result<String>,
+    ) -> anyhow::Result<T> {
         Self::validate_extension(path)?;
-        self.load_from_path(path)
+        self.load_from_reader(path, read)
     }
 
     fn id(&self, path: &Path) -> anyhow::Result<String> {
@@ -165,6 +251,9 @@ pub trait Loader<T> {
     fn load(&self, res: &T) -> anyhow::Result<Self::Output>;
 
     fn id(&self, res: &T) -> anyhow::Result<String>;
+
+    /// Return the local path of the resource, fetching it if needed.
+    fn path(&self, res: &T) -> anyhow::Result<PathBuf>;
 }
 
 #[derive(Debug, Clone)]
@@ -228,7 +317,15 @@ impl RegistryLoader {
     }
 }
 
-pub trait LocationLoader<O>: Loader<StemLocation, Output = O> {}
+pub trait LocationLoader<O>: Loader<StemLocation, Output = O> {
+    /// Load the stem at the given location. Each file of a downloaded stem is checked against
+    /// the policy, and parsed from the content that was checked.
+    fn load_trusted(&self, location: &StemLocation, policy: &TrustPolicy) -> anyhow::Result<O>;
+
+    /// Return the content of every file read to load the stem at the given location (the stem,
+    /// the files it references and their imports).
+    fn stem_files(&self, location: &StemLocation) -> anyhow::Result<BTreeMap<PathBuf, String>>;
+}
 
 #[derive(Debug, Clone)]
 pub struct FullLocationLoader<Load: PathLoader> {
@@ -301,9 +398,70 @@ impl<L: PathLoader> Loader<StemLocation> for FullLocationLoader<L> {
             .map(|n| n.to_string_lossy().to_string())
             .ok_or_else(|| anyhow!("missing file name for stem file: {}", path.display()))
     }
+
+    fn path(&self, res: &StemLocation) -> anyhow::Result<PathBuf> {
+        self.loaded_location_path(res)
+    }
 }
 
-impl<L: PathLoader> LocationLoader<L::Output> for FullLocationLoader<L> {}
+impl<L: PathLoader> LocationLoader<L::Output> for FullLocationLoader<L> {
+    fn load_trusted(
+        &self,
+        location: &StemLocation,
+        policy: &TrustPolicy,
+    ) -> anyhow::Result<L::Output> {
+        let path = self.loaded_location_path(location)?;
+
+        if let StemLocation::Local(_) = location {
+            return self.loader.load(&path);
+        }
+
+        let location_name = location.to_string();
+        let stem_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        self.loader.load_with(&path, &mut |file: &Path| {
+            let content = std::fs::read(file)?;
+            let signature = read_signature(file);
+
+            let verified = if file == path {
+                policy.trust.verify(
+                    &location_name,
+                    &content,
+                    signature.as_deref(),
+                    policy.require_signed,
+                )
+            } else {
+                policy.trust.verify_part(
+                    &location_name,
+                    &file
+                        .strip_prefix(&stem_dir)
+                        .unwrap_or(file)
+                        .to_string_lossy(),
+                    &content,
+                    signature.as_deref(),
+                    policy.require_signed,
+                )
+            };
+
+            verified.map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e.to_string()))?;
+
+            String::from_utf8(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+    }
+
+    fn stem_files(&self, location: &StemLocation) -> anyhow::Result<BTreeMap<PathBuf, String>> {
+        let path = self.loaded_location_path(location)?;
+        let mut files = BTreeMap::new();
+
+        self.loader.load_with(&path, &mut |file: &Path| {
+            let content = read_to_string(file)?;
+            files.insert(file.to_path_buf(), content.clone());
+            Ok(content)
+        })?;
+
+        Ok(files)
+    }
+}
 
 #[derive(Debug)]
 pub struct LanguageStemLoader {
@@ -335,15 +493,23 @@ impl LanguageStemLoader {
 impl PathLoader for LanguageStemLoader {
     type Output = Spec;
 
-    fn load(&self, path: &Path) -> anyhow::Result<Spec> {
+    fn load_with(
+        &self,
+        path: &Path,
+        read: &mut dyn FnMut(&Path) -> io::Result<String>,
+    ) -> anyhow::Result<Spec> {
         if self.is_syl_fil(path) {
-            spec_from_files(&self.script_engine, None, path)
+            spec_from_reader(&self.script_engine, None, path, read)
         } else {
-            self.default_loader.load(path).and_then(|stem| {
-                let spec_path = path.with_file_name(stem.spec);
-                let aspects_path = stem.aspecs.map(|p| path.with_file_name(p));
-                spec_from_files(&self.script_engine, aspects_path.as_ref(), &spec_path)
-            })
+            let stem = self.default_loader.load_with(path, read)?;
+            let spec_path = path.with_file_name(stem.spec);
+            let aspects_path = stem.aspecs.map(|p| path.with_file_name(p));
+            spec_from_reader(
+                &self.script_engine,
+                aspects_path.as_deref(),
+                &spec_path,
+                read,
+            )
         }
     }
 
@@ -364,8 +530,12 @@ pub struct RulesetStemLoader {
 impl PathLoader for RulesetStemLoader {
     type Output = RuleSetStem;
 
-    fn load(&self, path: &Path) -> anyhow::Result<Self::Output> {
-        self.loader.load(path)
+    fn load_with(
+        &self,
+        path: &Path,
+        read: &mut dyn FnMut(&Path) -> io::Result<String>,
+    ) -> anyhow::Result<Self::Output> {
+        self.loader.load_with(path, read)
     }
 
     fn id(&self, path: &Path) -> anyhow::Result<String> {
@@ -391,7 +561,7 @@ mod tests {
     use temp_dir::TempDir;
 
     use super::*;
-    use crate::util::test::create_tmp_child;
+    use crate::{core::spec::spec_from_files, util::test::create_tmp_child};
 
     #[test]
     fn spec_from_syl() {
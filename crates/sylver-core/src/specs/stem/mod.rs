@@ -41,14 +41,24 @@ mod test {
 
     use crate::{
         builtin_langs::BuiltinLang,
-        land::ruleset::RuleCategory,
-        specs::stem::project::{ProjectConfigStem, ProjectLang, ProjectStem},
+        core::overlay::Preprocessor,
+        land::{
+            passes::AnalysisPass,
+            ruleset::{FindingMetadata, MetadataValue, RuleCategory, RuleTaxonomy},
+            sylva::ParseLimits,
+        },
+        specs::{
+            stem::project::{
+                ExternalGrammar, ProjectConfigStem, ProjectLang, ProjectStem, RuleRef,
+            },
+            trust::RulesetTrust,
+        },
     };
 
     use super::{
         language::LanguageStem,
         location::StemLocation,
-        ruleset::{RuleSetStem, RuleStem},
+        ruleset::{RuleRequirement, RuleSetStem, RuleStem},
         Stem, StemData,
     };
 
@@ -108,6 +118,7 @@ mod test {
                 data: StemData::RuleSet(RuleSetStem {
                     id: "myRuleSet".to_string(),
                     language: ProjectLang::Custom(StemLocation::local("dir/language.syl")),
+                    predicates: vec![],
                     rules: vec![
                         RuleStem {
                             id: "rule1Id".to_string(),
@@ -115,6 +126,13 @@ mod test {
                             query: "match NodeKind1".to_string(),
                             category: RuleCategory::Bug,
                             note: None,
+                            doc_url: None,
+                            requires: vec![],
+                            metadata: FindingMetadata::default(),
+                            taxonomy: RuleTaxonomy::default(),
+                            deprecated: false,
+                            replaced_by: None,
+                            fix: None,
                         },
                         RuleStem {
                             id: "rule2Id".to_string(),
@@ -122,6 +140,13 @@ mod test {
                             query: "match NodeKind2".to_string(),
                             category: RuleCategory::Style,
                             note: Some("More info".to_string()),
+                            doc_url: None,
+                            requires: vec![],
+                            metadata: FindingMetadata::default(),
+                            taxonomy: RuleTaxonomy::default(),
+                            deprecated: false,
+                            replaced_by: None,
+                            fix: None,
                         },
                     ],
                 }),
@@ -129,6 +154,192 @@ mod test {
         );
     }
 
+    #[test]
+    fn rule_metadata() {
+        let stem: Stem<RuleSetStem> = read_stem(indoc!(
+            "
+            kind: ruleset
+            id: myRuleSet
+
+            language: dir/language.syl
+
+            rules:
+                - id: rule1Id
+                  message: Rule 1 message
+                  query: match NodeKind1
+                  category: bug
+                  metadata:
+                      cwe: 89
+                      component: billing
+                      tags: [security, sql]
+        "
+        ))
+        .unwrap();
+
+        let metadata = &stem.data.rules[0].metadata;
+        assert_eq!(metadata.get("cwe"), Some(&MetadataValue::Int(89)));
+        assert_eq!(
+            metadata.get("tags"),
+            Some(&MetadataValue::Array(vec![
+                MetadataValue::String("security".to_string()),
+                MetadataValue::String("sql".to_string()),
+            ]))
+        );
+        assert_eq!(metadata.get("ticket"), None);
+
+        let first: FindingMetadata =
+            serde_json::from_str(r#"{"score": 1.5, "owner": {"team": "a", "id": 1}}"#).unwrap();
+        let second: FindingMetadata =
+            serde_json::from_str(r#"{"owner": {"id": 1, "team": "a"}, "score": 1.5}"#).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.cmp(&second), std::cmp::Ordering::Equal);
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            r#"{"owner":{"id":1,"team":"a"},"score":1.5}"#
+        );
+    }
+
+    #[test]
+    fn rule_taxonomy() {
+        let stem: Stem<RuleSetStem> = read_stem(indoc!(
+            "
+            kind: ruleset
+            id: myRuleSet
+
+            language: dir/language.syl
+
+            rules:
+                - id: rule1Id
+                  message: Rule 1 message
+                  query: match NodeKind1
+                  category: bug
+                  taxonomy:
+                      cwe: 89
+                      owasp: A03:2021
+        "
+        ))
+        .unwrap();
+
+        assert_eq!(
+            stem.data.rules[0].taxonomy,
+            RuleTaxonomy {
+                cwe: Some(89),
+                owasp: Some("A03:2021".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn rule_deprecation() {
+        let stem: Stem<RuleSetStem> = read_stem(indoc!(
+            "
+            kind: ruleset
+            id: myRuleSet
+
+            language: dir/language.syl
+
+            rules:
+                - id: oldRule
+                  message: Old message
+                  query: match NodeKind1
+                  category: style
+                  replaced_by: newRule
+
+                - id: newRule
+                  message: New message
+                  query: match NodeKind1
+                  category: style
+
+                - id: obsoleteRule
+                  message: Obsolete message
+                  query: match NodeKind2
+                  category: style
+                  deprecated: true
+        "
+        ))
+        .unwrap();
+
+        let rules = &stem.data.rules;
+        assert_eq!(rules[0].replaced_by.as_deref(), Some("newRule"));
+        assert!(rules[0].is_deprecated());
+        assert!(!rules[1].is_deprecated());
+        assert!(rules[2].is_deprecated());
+        assert!(stem.data.validate().is_ok());
+
+        let mut unknown = stem.data.clone();
+        unknown.rules[0].replaced_by = Some("missingRule".to_string());
+        assert_eq!(
+            unknown.validate().unwrap_err().to_string(),
+            "Rule oldRule is replaced by unknown rule missingRule in ruleset myRuleSet"
+        );
+
+        let mut itself = stem.data;
+        itself.rules[0].replaced_by = Some("oldRule".to_string());
+        assert!(itself.validate().is_err());
+    }
+
+    #[test]
+    fn rule_requirements() {
+        let stem: Stem<RuleSetStem> = read_stem(indoc!(
+            "
+            kind: ruleset
+            id: myRuleSet
+
+            language: dir/language.syl
+
+            rules:
+                - id: base
+                  message: Base message
+                  query: match NodeKind1
+                  category: style
+
+                - id: derived
+                  message: Derived message
+                  query: match NodeKind1 n when matched_by(n, 'base')
+                  category: style
+                  requires: [call_graph, { rule: base }]
+
+                - id: indirect
+                  message: Indirect message
+                  query: match NodeKind1 n when matched_by(n, 'derived')
+                  category: style
+                  requires: [{ rule: derived }]
+        "
+        ))
+        .unwrap();
+
+        let derived = &stem.data.rules[1];
+        assert_eq!(
+            derived.requires,
+            vec![
+                RuleRequirement::Pass(AnalysisPass::CallGraph),
+                RuleRequirement::Rule {
+                    rule: "base".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            derived.required_passes().collect::<Vec<_>>(),
+            vec![AnalysisPass::CallGraph]
+        );
+        assert_eq!(derived.required_rules().collect::<Vec<_>>(), vec!["base"]);
+        assert!(stem.data.validate().is_ok());
+
+        let mut unknown = stem.data.clone();
+        unknown.rules.remove(0);
+        assert_eq!(
+            unknown.validate().unwrap_err().to_string(),
+            "Rule derived requires unknown rule base in ruleset myRuleSet"
+        );
+
+        let mut disabled = stem.data;
+        disabled.disable_rules(&[RuleRef {
+            ruleset: "myRuleSet".to_string(),
+            rule: "base".to_string(),
+        }]);
+        assert!(disabled.rules.is_empty());
+    }
+
     #[test]
     fn git_ruleset_stem() {
         let stem: Stem<RuleSetStem> = read_stem(indoc!(
@@ -204,6 +415,13 @@ mod test {
             Stem {
                 data: ProjectConfigStem::Flat(ProjectStem {
                     language: ProjectLang::Builtin(BuiltinLang::Python),
+                    language_version: None,
+                    trust: RulesetTrust::default(),
+                    parse_limits: ParseLimits::default(),
+                    encoding: Default::default(),
+                    preprocess: None,
+                    disabled_rules: vec![],
+                    min_clone_tokens: None,
                     root: Some("dir".to_string()),
                     include: vec!["*.py".to_string()],
                     exclude: vec![],
@@ -214,6 +432,59 @@ mod test {
         )
     }
 
+    #[test]
+    fn flat_project_language_version() {
+        let stem: Stem<ProjectConfigStem> = read_stem(indoc!(
+            "
+            language: python
+            language_version: '3.8'
+            include:
+                - '*.py'
+        "
+        ))
+        .unwrap();
+
+        let project = stem.data.projects().next().unwrap();
+        assert_eq!(project.language_version.as_deref(), Some("3.8"));
+    }
+
+    #[test]
+    fn flat_project_external_grammar() {
+        let stem: Stem<ProjectConfigStem> = read_stem(indoc!(
+            "
+            language:
+                library: grammars/libtree-sitter-foo.so
+                name: foo
+                mappings: grammars/foo.yaml
+            include:
+                - '*.foo'
+        "
+        ))
+        .unwrap();
+
+        assert_eq!(
+            stem.data,
+            ProjectConfigStem::Flat(ProjectStem {
+                root: None,
+                language: ProjectLang::External(ExternalGrammar {
+                    library: "grammars/libtree-sitter-foo.so".to_string(),
+                    name: "foo".to_string(),
+                    mappings: "grammars/foo.yaml".to_string(),
+                }),
+                language_version: None,
+                trust: RulesetTrust::default(),
+                parse_limits: ParseLimits::default(),
+                encoding: Default::default(),
+                preprocess: None,
+                disabled_rules: vec![],
+                min_clone_tokens: None,
+                include: vec!["*.foo".to_string()],
+                exclude: vec![],
+                rulesets: vec![],
+            })
+        )
+    }
+
     #[test]
     fn flat_project_stem() {
         let stem: Stem<ProjectConfigStem> = read_stem(indoc!(
@@ -231,6 +502,13 @@ mod test {
                 data: ProjectConfigStem::Flat(ProjectStem {
                     root: None,
                     language: ProjectLang::Custom(StemLocation::local("lang.syl")),
+                    language_version: None,
+                    trust: RulesetTrust::default(),
+                    parse_limits: ParseLimits::default(),
+                    encoding: Default::default(),
+                    preprocess: None,
+                    disabled_rules: vec![],
+                    min_clone_tokens: None,
                     include: vec!["*.ext".to_string()],
                     exclude: vec![],
                     rulesets: vec![],
@@ -267,6 +545,13 @@ mod test {
                             language: ProjectLang::Custom(StemLocation::Local(
                                 "javascript.yml".into()
                             )),
+                            language_version: None,
+                            trust: RulesetTrust::default(),
+                            parse_limits: ParseLimits::default(),
+                            encoding: Default::default(),
+                            preprocess: None,
+                            disabled_rules: vec![],
+                            min_clone_tokens: None,
                             rulesets: vec![StemLocation::Local("default.yml".into())],
                             include: vec!["src/**/*.js".to_string()],
                             exclude: vec![],
@@ -274,6 +559,13 @@ mod test {
                         ProjectStem {
                             root: None,
                             language: ProjectLang::Custom(StemLocation::Local("golang.yml".into())),
+                            language_version: None,
+                            trust: RulesetTrust::default(),
+                            parse_limits: ParseLimits::default(),
+                            encoding: Default::default(),
+                            preprocess: None,
+                            disabled_rules: vec![],
+                            min_clone_tokens: None,
                             rulesets: vec![],
                             include: vec!["src/**/*.go".to_string()],
                             exclude: vec![],
@@ -285,6 +577,83 @@ mod test {
         )
     }
 
+    #[test]
+    fn project_ruleset_trust() {
+        let stem: Stem<ProjectConfigStem> = read_stem(indoc!(
+            "
+            language: python
+            include:
+                - '*.py'
+            rulesets:
+                - '@author/rules:1.0.0'
+            trust:
+                checksums:
+                    '@author/rules:1.0.0': sha256:2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae
+                trusted_keys:
+                    - RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3
+                sigstore_keys:
+                    - |
+                        -----BEGIN PUBLIC KEY-----
+                        MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE791q2591C5pm4hvq9yoLYVSKz+Pd
+                        S7xe27ULvbxZ6h46Gnj978QgCRVmyqaiYWmgLhQLM9ZytOQdq4xl/yMiVQ==
+                        -----END PUBLIC KEY-----
+                require_signed: true
+        "
+        ))
+        .unwrap();
+
+        let trust = &stem.data.projects().next().unwrap().trust;
+        assert_eq!(trust.checksums.len(), 1);
+        assert_eq!(trust.trusted_keys.len(), 1);
+        assert!(trust.sigstore_keys[0].starts_with("-----BEGIN PUBLIC KEY-----\n"));
+        assert!(trust.require_signed);
+    }
+
+    #[test]
+    fn project_parse_limits() {
+        let stem: Stem<ProjectConfigStem> = read_stem(indoc!(
+            "
+            language: javascript
+            include:
+                - '**/*.js'
+            parse_limits:
+                max_parse_time_ms: 2000
+                max_nodes: 500000
+        "
+        ))
+        .unwrap();
+
+        assert_eq!(
+            stem.data.projects().next().unwrap().parse_limits,
+            ParseLimits {
+                max_parse_time_ms: Some(2000),
+                max_nodes: Some(500000),
+            }
+        );
+    }
+
+    #[test]
+    fn project_preprocess() {
+        let stem: Stem<ProjectConfigStem> = read_stem(indoc!(
+            "
+            language: python
+            include:
+                - '**/*.py.j2'
+            preprocess:
+                command: [render-template, '{file}']
+        "
+        ))
+        .unwrap();
+
+        assert_eq!(
+            stem.data.projects().next().unwrap().preprocess,
+            Some(Preprocessor {
+                command: vec!["render-template".to_string(), "{file}".to_string()],
+                dir: None,
+            })
+        );
+    }
+
     fn read_stem<'de, D: serde::Deserialize<'de>>(
         stem: &'de str,
     ) -> Result<Stem<D>, serde_yaml::Error> {
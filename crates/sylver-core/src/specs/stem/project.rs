@@ -1,15 +1,54 @@
-use std::fmt::Formatter;
+use std::{fmt::Formatter, path::Path};
 
 use serde::de::value::MapAccessDeserializer;
 use serde::de::{Error, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{builtin_langs::BuiltinLang, specs::stem::location::StemLocation};
+use crate::{
+    builtin_langs::BuiltinLang,
+    core::{
+        overlay::Preprocessor,
+        source::{InputAdapter, SourceEncoding},
+    },
+    land::sylva::ParseLimits,
+    specs::{stem::location::StemLocation, trust::RulesetTrust},
+};
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum ProjectLang {
     Builtin(BuiltinLang),
     Custom(StemLocation),
+    External(ExternalGrammar),
+}
+
+/// Tree-sitter grammar loaded at runtime from a shared library.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalGrammar {
+    /// Path to the shared library exporting the grammar.
+    pub library: String,
+    /// Name of the grammar, used to find the `tree_sitter_<name>` function in the library.
+    pub name: String,
+    /// Path to the yaml file mapping the tree-sitter nodes to sylver kinds.
+    pub mappings: String,
+}
+
+impl ExternalGrammar {
+    /// Make the relative paths of the grammar relative to the given directory.
+    pub fn resolve_paths(&mut self, dir: &Path) {
+        for path in [&mut self.library, &mut self.mappings] {
+            if Path::new(path.as_str()).is_relative() {
+                *path = dir.join(path.as_str()).to_string_lossy().to_string();
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProjectLangObject {
+    External(ExternalGrammar),
+    Location(StemLocation),
 }
 
 impl Serialize for ProjectLang {
@@ -20,6 +59,7 @@ impl Serialize for ProjectLang {
         match self {
             ProjectLang::Builtin(b) => serializer.serialize_str(&b.to_string()),
             ProjectLang::Custom(c) => c.serialize(serializer),
+            ProjectLang::External(e) => e.serialize(serializer),
         }
     }
 }
@@ -49,9 +89,13 @@ impl<'de> Deserialize<'de> for ProjectLang {
             where
                 A: MapAccess<'de>,
             {
-                let location: StemLocation =
+                let lang: ProjectLangObject =
                     Deserialize::deserialize(MapAccessDeserializer::new(map))?;
-                Ok(ProjectLang::Custom(location))
+
+                Ok(match lang {
+                    ProjectLangObject::External(grammar) => ProjectLang::External(grammar),
+                    ProjectLangObject::Location(location) => ProjectLang::Custom(location),
+                })
             }
         }
 
@@ -59,6 +103,17 @@ impl<'de> Deserialize<'de> for ProjectLang {
     }
 }
 
+impl ProjectLang {
+    /// Conversion of the files of the project that are not code of its language.
+    pub fn input_adapter(&self) -> InputAdapter {
+        match self {
+            ProjectLang::Builtin(BuiltinLang::Yaml) => InputAdapter::TerraformPlan,
+            ProjectLang::Builtin(BuiltinLang::Python) => InputAdapter::Notebook,
+            _ => InputAdapter::None,
+        }
+    }
+}
+
 impl From<&str> for ProjectLang {
     fn from(value: &str) -> Self {
         value
@@ -82,6 +137,13 @@ impl ProjectConfigStem {
             ProjectConfigStem::Nested { projects } => Box::new(projects.iter()),
         }
     }
+
+    pub fn projects_mut(&'_ mut self) -> Box<dyn '_ + Iterator<Item = &mut ProjectStem>> {
+        match self {
+            ProjectConfigStem::Flat(p) => Box::new(std::iter::once(p)),
+            ProjectConfigStem::Nested { projects } => Box::new(projects.iter_mut()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
@@ -89,9 +151,44 @@ pub struct ProjectStem {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub root: Option<String>,
     pub language: ProjectLang,
+    /// Version of the language used by the project, restricting the accepted syntax.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_version: Option<String>,
     pub include: Vec<String>,
     #[serde(default)]
     pub exclude: Vec<String>,
     #[serde(default)]
     pub rulesets: Vec<StemLocation>,
+    /// Integrity requirements for the downloaded rulesets.
+    #[serde(default, skip_serializing_if = "RulesetTrust::is_empty")]
+    pub trust: RulesetTrust,
+    /// Limits above which a file of the project is skipped.
+    #[serde(default, skip_serializing_if = "ParseLimits::is_unlimited")]
+    pub parse_limits: ParseLimits,
+    /// Encoding of the files of the project.
+    #[serde(default, skip_serializing_if = "SourceEncoding::is_auto")]
+    pub encoding: SourceEncoding,
+    /// Command transforming the files of the project before they are parsed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocess: Option<Preprocessor>,
+    /// Rules of the rulesets that are not evaluated on the project.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_rules: Vec<RuleRef>,
+    /// Minimum size (in tokens) of the duplicated regions found by the `clones` function.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_clone_tokens: Option<usize>,
+}
+
+/// Rule identified by the id of its ruleset and its own id.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleRef {
+    pub ruleset: String,
+    pub rule: String,
+}
+
+impl RuleRef {
+    pub fn is(&self, ruleset: &str, rule: &str) -> bool {
+        self.ruleset == ruleset && self.rule == rule
+    }
 }
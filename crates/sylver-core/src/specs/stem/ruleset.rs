@@ -1,12 +1,25 @@
+use std::collections::HashSet;
+
+use anyhow::bail;
 use serde::{Deserialize, Serialize};
 
-use crate::{land::ruleset::RuleCategory, specs::stem::project::ProjectLang};
+use crate::{
+    land::{
+        passes::AnalysisPass,
+        ruleset::{FindingMetadata, RuleCategory, RuleTaxonomy},
+    },
+    specs::stem::project::{ProjectLang, RuleRef},
+};
 
 /// Static description of a ruleset
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct RuleSetStem {
     pub id: String,
     pub language: ProjectLang,
+    /// Definitions of the predicates that can be called from the queries of the rules
+    /// (`def name(param) = expr`).
+    #[serde(default)]
+    pub predicates: Vec<String>,
     pub rules: Vec<RuleStem>,
 }
 
@@ -15,7 +28,8 @@ pub struct RuleSetStem {
 pub struct RuleStem {
     /// Short 'technical' id.
     pub id: String,
-    /// User friendly diagnostic message.
+    /// User friendly diagnostic message. `{expr}` placeholders are replaced by the value of the
+    /// expression on the matched node, e.g. `Function {f.name} is too long`.
     pub message: String,
     /// Code of the query matching violating nodes.
     pub query: String,
@@ -23,4 +37,111 @@ pub struct RuleStem {
     pub category: RuleCategory,
     /// Additional node
     pub note: Option<String>,
+    /// Link to the documentation of the rule.
+    pub doc_url: Option<String>,
+    /// Analysis passes that must be run, and rules that must be evaluated, before evaluating the
+    /// query.
+    #[serde(default)]
+    pub requires: Vec<RuleRequirement>,
+    /// Custom data reported along with the findings of the rule.
+    #[serde(default, skip_serializing_if = "FindingMetadata::is_empty")]
+    pub metadata: FindingMetadata,
+    /// CWE and OWASP classifications of the rule.
+    #[serde(default, skip_serializing_if = "RuleTaxonomy::is_empty")]
+    pub taxonomy: RuleTaxonomy,
+    /// Deprecated rules are still evaluated, but a warning is emitted when they are enabled.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    /// Id of the rule of the same ruleset that supersedes this one. Implies `deprecated`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<String>,
+    /// Replacement of the text of the matched nodes, applied by `sylver check --fix`. Like in the
+    /// message, `{expr}` placeholders are replaced by the value of the expression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+/// Requirement of a rule: an analysis pass (`scope_graph`), or another rule of the ruleset
+/// (`rule: other-rule`) whose matches the query reads with `matched_by`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RuleRequirement {
+    Pass(AnalysisPass),
+    Rule { rule: String },
+}
+
+impl RuleSetStem {
+    /// Check that the rules are only replaced by, and only require, other rules of the ruleset.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for rule in &self.rules {
+            for required in rule.required_rules() {
+                if !self.rules.iter().any(|r| r.id == required) {
+                    bail!(
+                        "Rule {} requires unknown rule {required} in ruleset {}",
+                        rule.id,
+                        self.id
+                    );
+                }
+            }
+        }
+
+        for rule in &self.rules {
+            let Some(replacement) = &rule.replaced_by else {
+                continue;
+            };
+
+            if replacement == &rule.id {
+                bail!("Rule {} is replaced by itself", rule.id);
+            }
+
+            if !self.rules.iter().any(|r| &r.id == replacement) {
+                bail!(
+                    "Rule {} is replaced by unknown rule {replacement} in ruleset {}",
+                    rule.id,
+                    self.id
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the rules disabled in a project, along with the rules that require them.
+    pub fn disable_rules(&mut self, disabled: &[RuleRef]) {
+        let id = &self.id;
+        self.rules
+            .retain(|rule| !disabled.iter().any(|d| d.is(id, &rule.id)));
+
+        loop {
+            let remaining: HashSet<String> = self.rules.iter().map(|r| r.id.clone()).collect();
+            let count = self.rules.len();
+
+            self.rules
+                .retain(|rule| rule.required_rules().all(|r| remaining.contains(r)));
+
+            if self.rules.len() == count {
+                break;
+            }
+        }
+    }
+}
+
+impl RuleStem {
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated || self.replaced_by.is_some()
+    }
+
+    pub fn required_passes(&self) -> impl Iterator<Item = AnalysisPass> + '_ {
+        self.requires.iter().filter_map(|r| match r {
+            RuleRequirement::Pass(pass) => Some(*pass),
+            RuleRequirement::Rule { .. } => None,
+        })
+    }
+
+    pub fn required_rules(&self) -> impl Iterator<Item = &str> {
+        self.requires.iter().filter_map(|r| match r {
+            RuleRequirement::Rule { rule } => Some(rule.as_str()),
+            RuleRequirement::Pass(_) => None,
+        })
+    }
 }
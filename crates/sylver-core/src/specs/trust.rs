@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use minisign_verify::{PublicKey, Signature};
+use p256::{
+    ecdsa::{signature::Verifier, Signature as EcdsaSignature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Extension of the detached minisign signature of a downloaded stem file.
+pub const SIGNATURE_EXTENSION: &str = "minisig";
+
+/// Extension of the detached sigstore signature of a downloaded stem file, as written by
+/// `cosign sign-blob --output-signature`.
+pub const SIGSTORE_SIGNATURE_EXTENSION: &str = "sig";
+
+/// Integrity requirements for the rulesets and language specs that are downloaded (from the
+/// registry or from a git repository) before they are used.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesetTrust {
+    /// Expected SHA-256 of the stem file, by location (e.g. `@author/name:1.0.0`). The other files
+    /// of a stem (spec, aspects, imports) are pinned as `<location>#<path relative to the stem>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+    /// Minisign public keys accepted for the signature of the rulesets.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_keys: Vec<String>,
+    /// Sigstore (cosign) public keys accepted for the signature of the rulesets, in PEM format.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sigstore_keys: Vec<String>,
+    /// Reject the rulesets that are not signed by one of the trusted keys.
+    #[serde(default)]
+    pub require_signed: bool,
+}
+
+impl RulesetTrust {
+    pub fn is_empty(&self) -> bool {
+        self == &RulesetTrust::default()
+    }
+
+    /// Check a file read by a downloaded stem, other than the stem file itself. Once the stem is
+    /// pinned, each of its files must be pinned or signed too.
+    pub fn verify_part(
+        &self,
+        location: &str,
+        file: &str,
+        content: &[u8],
+        signature: Option<&str>,
+        require_signed: bool,
+    ) -> anyhow::Result<()> {
+        let key = format!("{location}#{file}");
+
+        if signature.is_none()
+            && self.checksums.contains_key(location)
+            && !self.checksums.contains_key(&key)
+        {
+            bail!("File {file} of {location} is neither pinned nor signed");
+        }
+
+        self.verify(&key, content, signature, require_signed)
+    }
+
+    /// Check the content of a downloaded ruleset against its pinned checksum and, if any, its
+    /// detached signature.
+    pub fn verify(
+        &self,
+        location: &str,
+        content: &[u8],
+        signature: Option<&str>,
+        require_signed: bool,
+    ) -> anyhow::Result<()> {
+        if let Some(expected) = self.checksums.get(location) {
+            let expected = expected.strip_prefix("sha256:").unwrap_or(expected);
+            let actual = hex::encode(Sha256::digest(content));
+
+            if !actual.eq_ignore_ascii_case(expected) {
+                bail!(
+                    "Checksum mismatch for ruleset {location}: expected {expected}, got {actual}"
+                );
+            }
+        }
+
+        match signature {
+            Some(signature) => self.verify_signature(location, content, signature),
+            None if require_signed || self.require_signed => {
+                bail!("Ruleset {location} is not signed")
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Check a detached signature: a minisign signature, or a base64-encoded sigstore signature
+    /// made with a cosign key pair.
+    fn verify_signature(
+        &self,
+        location: &str,
+        content: &[u8],
+        signature: &str,
+    ) -> anyhow::Result<()> {
+        let signed_by_trusted_key = match Signature::decode(signature) {
+            Ok(signature) => self.trusted_keys.iter().any(|key| {
+                PublicKey::from_base64(key)
+                    .and_then(|key| key.verify(content, &signature, false))
+                    .is_ok()
+            }),
+            Err(minisign_error) => {
+                let signature = BASE64
+                    .decode(signature.trim())
+                    .ok()
+                    .and_then(|der| EcdsaSignature::from_der(&der).ok())
+                    .ok_or_else(|| {
+                        anyhow!("Invalid signature for ruleset {location}: {minisign_error}")
+                    })?;
+                let signature = signature.normalize_s().unwrap_or(signature);
+
+                self.sigstore_keys.iter().any(|key| {
+                    VerifyingKey::from_public_key_pem(key.trim())
+                        .map_or(false, |key| key.verify(content, &signature).is_ok())
+                })
+            }
+        };
+
+        if !signed_by_trusted_key {
+            bail!("Ruleset {location} is not signed by a trusted key");
+        }
+
+        Ok(())
+    }
+}
+
+/// Trust requirements applied when loading a stem: those of the project, and the global
+/// `--require-signed` policy.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TrustPolicy {
+    pub trust: RulesetTrust,
+    pub require_signed: bool,
+}
+
+impl TrustPolicy {
+    pub fn new(trust: RulesetTrust, require_signed: bool) -> TrustPolicy {
+        TrustPolicy {
+            trust,
+            require_signed,
+        }
+    }
+
+    /// Policy of the stems that don't belong to a project.
+    pub fn global(require_signed: bool) -> TrustPolicy {
+        TrustPolicy::new(RulesetTrust::default(), require_signed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::btreemap;
+
+    use super::*;
+
+    static CONTENT: &[u8] = b"id: ruleset\n";
+
+    static PUBLIC_KEY: &str = "RWQBAgMEBQYHCAekPuHHop2lKAhTX+yDAFEpB9jHGFDt8BLe13MtOAwc";
+
+    /// Minisign signature of `CONTENT` by `PUBLIC_KEY`.
+    static SIGNATURE: &str = "untrusted comment: signature from sylver test key
+RUQBAgMEBQYHCLPYnx4XEwXzkfnDXfQKi7aIYPSJx7Kmn5qjUdi1tBhR71RYSmZB4+24jNsWRMnZBkYwNCdZU8Xq2RsoeC6+eQI=
+trusted comment: timestamp:0\tfile:ruleset.yaml
+b13DpFMZkEsj/YC6bDTg3DpibhgObuKuKTBhg1togN8G1OuFGGAFDz4DeFgBbW54Nhi+rb4iiz/mXx9f/fGFCA==
+";
+
+    #[test]
+    fn pinned_checksum() {
+        let checksum = hex::encode(Sha256::digest(CONTENT));
+        let trust = RulesetTrust {
+            checksums: btreemap! {
+                "@author/rules:1.0.0".to_string() => format!("sha256:{checksum}"),
+            },
+            ..Default::default()
+        };
+
+        assert!(trust
+            .verify("@author/rules:1.0.0", CONTENT, None, false)
+            .is_ok());
+        assert!(trust
+            .verify("@author/rules:1.0.0", b"id: tampered\n", None, false)
+            .is_err());
+        assert!(trust
+            .verify("@author/other:1.0.0", b"id: other\n", None, false)
+            .is_ok());
+    }
+
+    #[test]
+    fn required_signature() {
+        let trust = RulesetTrust::default();
+
+        assert!(trust.verify("@author/rules", CONTENT, None, true).is_err());
+
+        let trust = RulesetTrust {
+            require_signed: true,
+            ..Default::default()
+        };
+
+        assert!(trust.verify("@author/rules", CONTENT, None, false).is_err());
+    }
+
+    #[test]
+    fn valid_signature() {
+        let trust = RulesetTrust {
+            trusted_keys: vec![PUBLIC_KEY.to_string()],
+            require_signed: true,
+            ..Default::default()
+        };
+
+        assert!(trust
+            .verify("@author/rules", CONTENT, Some(SIGNATURE), true)
+            .is_ok());
+        assert!(trust
+            .verify("@author/rules", b"id: tampered\n", Some(SIGNATURE), true)
+            .is_err());
+        assert!(RulesetTrust::default()
+            .verify("@author/rules", CONTENT, Some(SIGNATURE), false)
+            .is_err());
+    }
+
+    static SIGSTORE_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE791q2591C5pm4hvq9yoLYVSKz+Pd
+S7xe27ULvbxZ6h46Gnj978QgCRVmyqaiYWmgLhQLM9ZytOQdq4xl/yMiVQ==
+-----END PUBLIC KEY-----
+";
+
+    /// Cosign signature of `CONTENT` by `SIGSTORE_PUBLIC_KEY`.
+    static SIGSTORE_SIGNATURE: &str = "MEUCIQDcoMFgjZbBBkA5P7L9elQiUELs0UuIN+4P6iDup7WuUAIgKflflEY\
+        ymOtfU0Msl2KQfSydE2EsKWbwQF8ahwp2vOc=\n";
+
+    #[test]
+    fn valid_sigstore_signature() {
+        let trust = RulesetTrust {
+            sigstore_keys: vec![SIGSTORE_PUBLIC_KEY.to_string()],
+            require_signed: true,
+            ..Default::default()
+        };
+
+        assert!(trust
+            .verify("@author/rules", CONTENT, Some(SIGSTORE_SIGNATURE), true)
+            .is_ok());
+        assert!(trust
+            .verify(
+                "@author/rules",
+                b"id: tampered\n",
+                Some(SIGSTORE_SIGNATURE),
+                true
+            )
+            .is_err());
+        assert!(trust
+            .verify("@author/rules", CONTENT, Some(SIGNATURE), true)
+            .is_err());
+        assert!(RulesetTrust::default()
+            .verify("@author/rules", CONTENT, Some(SIGSTORE_SIGNATURE), false)
+            .is_err());
+    }
+
+    #[test]
+    fn pinned_stem_files() {
+        let checksum = |content: &[u8]| format!("sha256:{}", hex::encode(Sha256::digest(content)));
+        let trust = RulesetTrust {
+            checksums: btreemap! {
+                "@author/lang".to_string() => checksum(b"id: lang\n"),
+                "@author/lang#aspects.py".to_string() => checksum(b"pass\n"),
+            },
+            ..Default::default()
+        };
+
+        assert!(trust
+            .verify_part("@author/lang", "aspects.py", b"pass\n", None, false)
+            .is_ok());
+        assert!(trust
+            .verify_part("@author/lang", "aspects.py", b"import os\n", None, false)
+            .is_err());
+        assert!(trust
+            .verify_part("@author/lang", "spec.syl", b"node A {}\n", None, false)
+            .is_err());
+    }
+
+    #[test]
+    fn invalid_signature() {
+        let trust = RulesetTrust::default();
+
+        assert!(trust
+            .verify("@author/rules", CONTENT, Some("not a signature"), false)
+            .is_err());
+    }
+}
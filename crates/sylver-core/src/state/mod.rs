@@ -6,10 +6,12 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::script::python::PythonScriptEngine;
+use crate::{
+    core::pos::PosEncoder, script::python::PythonScriptEngine, util::cancel::CancellationToken,
+};
 use log::{FancyLogger, Logger};
 
-use crate::util::fs::find_upward_path;
+use crate::util::fs::{find_upward_path, PathPrefixMap};
 
 pub static DEFAULT_SYLVER_CONFIG_NAMES: [&str; 2] = ["sylver.yml", "sylver.yaml"];
 
@@ -17,12 +19,24 @@ static SYLVER_DIR_NAME: &str = ".sylver";
 static DL_DIR_NAME: &str = "dl";
 static REPOS_DIR_NAME: &str = "repos";
 static REGISTRY_DIR_NAME: &str = "registry";
+static FIX_JOURNAL_DIR_NAME: &str = "fix_journal";
+static SYMBOL_INDEX_FILE_NAME: &str = "symbols.json";
+static HOOK_CACHE_FILE_NAME: &str = "hook_cache.json";
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct SylverSettings {
     pub color_output: bool,
     pub config_override: Option<PathBuf>,
     pub backend_url: String,
+    pub pos_encoder: PosEncoder,
+    /// Report the time spent in each rule and the memoization statistics.
+    pub profile: bool,
+    /// Reject the downloaded rulesets that are not signed by a trusted key.
+    pub require_signed: bool,
+    /// Rewrite of the paths of the reported files.
+    pub path_map: PathPrefixMap,
+    /// Run the preprocess commands of the projects.
+    pub allow_preprocess: bool,
 }
 
 impl Default for SylverSettings {
@@ -31,6 +45,11 @@ impl Default for SylverSettings {
             color_output: true,
             config_override: None,
             backend_url: "https://api.sylver.dev".to_string(),
+            pos_encoder: PosEncoder::default(),
+            profile: false,
+            require_signed: false,
+            path_map: PathPrefixMap::default(),
+            allow_preprocess: false,
         }
     }
 }
@@ -42,6 +61,8 @@ pub struct SylverState<L: Logger = FancyLogger> {
     pub locations: Locations,
     pub script_engine: Arc<PythonScriptEngine>,
     pub logger: L,
+    /// Cancelled when the current command should stop (interruption, timeout...).
+    pub cancel: CancellationToken,
 }
 
 impl<L: Logger> SylverState<L> {
@@ -61,6 +82,7 @@ impl<L: Logger> SylverState<L> {
             logger,
             locations,
             script_engine: Arc::new(PythonScriptEngine::default()),
+            cancel: CancellationToken::default(),
         })
     }
 }
@@ -81,6 +103,12 @@ pub struct Locations {
     pub repos: PathBuf,
     /// Directory for storing artefacts downloaded from the registry.
     pub registry_artefacts: PathBuf,
+    /// Journal of the last set of files modified by sylver, used for rollbacks.
+    pub fix_journal: PathBuf,
+    /// Persisted symbol index of the project.
+    pub symbol_index: PathBuf,
+    /// Files found clean by the previous runs of the pre-commit hook.
+    pub hook_cache: PathBuf,
     /// sylver.yaml file... or it's equivalent
     pub config_file: Option<PathBuf>,
 }
@@ -92,6 +120,9 @@ impl Locations {
     ) -> anyhow::Result<Locations> {
         let repos = sylver_dir.join(DL_DIR_NAME).join(REPOS_DIR_NAME);
         let registry_artefacts = sylver_dir.join(DL_DIR_NAME).join(REGISTRY_DIR_NAME);
+        let fix_journal = sylver_dir.join(FIX_JOURNAL_DIR_NAME);
+        let symbol_index = sylver_dir.join(SYMBOL_INDEX_FILE_NAME);
+        let hook_cache = sylver_dir.join(HOOK_CACHE_FILE_NAME);
 
         let config_file = match &settings.config_override {
             Some(p) => {
@@ -105,6 +136,9 @@ impl Locations {
             sylver_dir,
             repos,
             registry_artefacts,
+            fix_journal,
+            symbol_index,
+            hook_cache,
             config_file,
         })
     }
@@ -175,6 +209,21 @@ mod test {
                     .join(".sylver")
                     .join("dl")
                     .join("registry"),
+                fix_journal: Path::new("..")
+                    .canonicalize()
+                    .unwrap()
+                    .join(".sylver")
+                    .join("fix_journal"),
+                symbol_index: Path::new("..")
+                    .canonicalize()
+                    .unwrap()
+                    .join(".sylver")
+                    .join("symbols.json"),
+                hook_cache: Path::new("..")
+                    .canonicalize()
+                    .unwrap()
+                    .join(".sylver")
+                    .join("hook_cache.json"),
                 config_file: None
             }
         );
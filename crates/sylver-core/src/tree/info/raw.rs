@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use crate::{
     core::{
         pos::InclPosRange,
@@ -20,6 +22,21 @@ impl<'t> RawTreeInfo<'t> {
     pub fn new(source: &'t SourceTree, syntax: &'t Syntax) -> Self {
         RawTreeInfo { source, syntax }
     }
+
+    pub fn syntax(&self) -> &'t Syntax {
+        self.syntax
+    }
+
+    /// Code of the whole tree.
+    pub fn source_code(&self) -> &'t str {
+        self.source.source.src()
+    }
+
+    /// Byte range of the text of the node in the code of the tree.
+    pub fn node_text_range(&self, node: NodeId) -> Range<usize> {
+        self.source
+            .node_text_range(node, self.syntax.trivial_tags())
+    }
 }
 
 impl<'t> TreeInfo<'t> for RawTreeInfo<'t> {
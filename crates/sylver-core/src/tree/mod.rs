@@ -86,6 +86,10 @@ impl Tree {
             .unwrap_or_else(|| panic!("Non existing node id: {id:?}"))
     }
 
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
     /// Return an iterator over the node's ids.
     pub fn bottom_up_node_ids(&'_ self) -> impl '_ + Iterator<Item = NodeId> {
         self.nodes.ids().map(Into::into)
@@ -0,0 +1,71 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, Copy, Eq, PartialEq)]
+#[error("Operation cancelled")]
+pub struct Cancelled;
+
+/// Shared flag used to stop long-running operations.
+///
+/// Clones of a token observe the same state: cancelling one of them cancels all of them.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Return an error if the token has been cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Cancel the token once the given duration has elapsed.
+    pub fn cancel_after(&self, timeout: Duration) {
+        let token = self.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(timeout);
+            token.cancel();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_clones() {
+        let token = CancellationToken::new();
+        let other = token.clone();
+
+        assert_eq!(other.check(), Ok(()));
+
+        token.cancel();
+
+        assert!(other.is_cancelled());
+        assert_eq!(other.check(), Err(Cancelled));
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    collections::BTreeMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use serde::Serialize;
+
+use crate::util::transaction::FileTransaction;
+
+/// Replacement of a span of a file, proposed by the fix of a rule.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct FixEdit {
+    pub file: PathBuf,
+    pub rule: String,
+    /// Byte range of the replaced text.
+    pub span: Range<usize>,
+    pub replacement: String,
+    /// Edits with a higher priority win the conflicts resolved with `FixStrategy::Priority`.
+    pub priority: u32,
+}
+
+/// Resolution of the conflicts between overlapping edits.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixStrategy {
+    /// Apply the edit proposed first.
+    First,
+    /// Apply the edit with the highest priority, or the one proposed first on ties.
+    Priority,
+    /// Apply none of the overlapping edits.
+    SkipConflicts,
+}
+
+/// Group of overlapping edits of a file, and how it was resolved.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct FixConflict {
+    pub file: PathBuf,
+    pub strategy: FixStrategy,
+    /// Overlapping edits, in the order they were proposed.
+    pub edits: Vec<ConflictingEdit>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct ConflictingEdit {
+    pub rule: String,
+    pub span: Range<usize>,
+    pub applied: bool,
+}
+
+/// Edits to apply once the conflicts are resolved.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FixPlan {
+    pub edits: Vec<FixEdit>,
+    pub conflicts: Vec<FixConflict>,
+}
+
+impl FixPlan {
+    /// Resolve the overlaps between the given edits, listed in the order they were proposed.
+    pub fn new(edits: Vec<FixEdit>, strategy: FixStrategy) -> FixPlan {
+        let mut by_file: BTreeMap<PathBuf, Vec<(usize, FixEdit)>> = BTreeMap::new();
+
+        for (index, edit) in edits.into_iter().enumerate() {
+            by_file
+                .entry(edit.file.clone())
+                .or_default()
+                .push((index, edit));
+        }
+
+        let mut plan = FixPlan::default();
+
+        for (file, mut file_edits) in by_file {
+            file_edits.sort_by_key(|(index, e)| (e.span.start, e.span.end, *index));
+
+            for group in overlapping_groups(file_edits) {
+                if group.len() == 1 {
+                    plan.edits.extend(group.into_iter().map(|(_, e)| e));
+                    continue;
+                }
+
+                let applied = resolve(&group, strategy);
+
+                let mut conflict_edits: Vec<(usize, ConflictingEdit)> = group
+                    .iter()
+                    .enumerate()
+                    .map(|(pos, (index, e))| {
+                        let edit = ConflictingEdit {
+                            rule: e.rule.clone(),
+                            span: e.span.clone(),
+                            applied: applied.contains(&pos),
+                        };
+                        (*index, edit)
+                    })
+                    .collect();
+                conflict_edits.sort_by_key(|(index, _)| *index);
+
+                plan.conflicts.push(FixConflict {
+                    file: file.clone(),
+                    strategy,
+                    edits: conflict_edits.into_iter().map(|(_, e)| e).collect(),
+                });
+
+                plan.edits.extend(
+                    group
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(pos, _)| applied.contains(pos))
+                        .map(|(_, (_, e))| e),
+                );
+            }
+        }
+
+        plan
+    }
+
+    /// Stage the edited content of each file in a transaction, `read` returning the current
+    /// content of a file.
+    pub fn transaction(
+        &self,
+        read: impl Fn(&Path) -> anyhow::Result<String>,
+    ) -> anyhow::Result<FileTransaction> {
+        let mut by_file: BTreeMap<&Path, Vec<&FixEdit>> = BTreeMap::new();
+
+        for edit in &self.edits {
+            by_file.entry(&edit.file).or_default().push(edit);
+        }
+
+        let mut transaction = FileTransaction::new();
+
+        for (file, edits) in by_file {
+            let content = read(file)?;
+            transaction.write(file, apply_edits(file, &content, edits)?);
+        }
+
+        Ok(transaction)
+    }
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    // Insertions at the same position conflict, as their order is undefined.
+    a.start == b.start || (a.start < b.end && b.start < a.end)
+}
+
+/// Split edits sorted by position into groups of transitively overlapping edits.
+fn overlapping_groups(edits: Vec<(usize, FixEdit)>) -> Vec<Vec<(usize, FixEdit)>> {
+    let mut groups: Vec<Vec<(usize, FixEdit)>> = vec![];
+
+    for edit in edits {
+        let overlapping = groups.last().map_or(false, |group| {
+            group.iter().any(|(_, e)| overlaps(&e.span, &edit.1.span))
+        });
+
+        match groups.last_mut() {
+            Some(group) if overlapping => group.push(edit),
+            _ => groups.push(vec![edit]),
+        }
+    }
+
+    groups
+}
+
+/// Positions in the group of the edits to apply: the edits are considered by order of preference,
+/// and applied unless they overlap an edit that is already applied.
+fn resolve(group: &[(usize, FixEdit)], strategy: FixStrategy) -> Vec<usize> {
+    let mut preferred: Vec<usize> = (0..group.len()).collect();
+
+    match strategy {
+        FixStrategy::First => preferred.sort_by_key(|&pos| group[pos].0),
+        FixStrategy::Priority => {
+            preferred.sort_by_key(|&pos| (std::cmp::Reverse(group[pos].1.priority), group[pos].0))
+        }
+        FixStrategy::SkipConflicts => return vec![],
+    }
+
+    let mut applied: Vec<usize> = vec![];
+
+    for pos in preferred {
+        let span = &group[pos].1.span;
+
+        if applied.iter().all(|&a| !overlaps(&group[a].1.span, span)) {
+            applied.push(pos);
+        }
+    }
+
+    applied
+}
+
+fn apply_edits(file: &Path, content: &str, mut edits: Vec<&FixEdit>) -> anyhow::Result<String> {
+    edits.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+
+    let mut edited = content.to_string();
+
+    for edit in edits {
+        if edit.span.end > edited.len()
+            || !edited.is_char_boundary(edit.span.start)
+            || !edited.is_char_boundary(edit.span.end)
+        {
+            bail!(
+                "Invalid fix span {:?} of rule {} in {}",
+                edit.span,
+                edit.rule,
+                file.display()
+            );
+        }
+
+        edited.replace_range(edit.span.clone(), &edit.replacement);
+    }
+
+    Ok(edited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(rule: &str, span: Range<usize>, replacement: &str, priority: u32) -> FixEdit {
+        FixEdit {
+            file: PathBuf::from("main.py"),
+            rule: rule.to_string(),
+            span,
+            replacement: replacement.to_string(),
+            priority,
+        }
+    }
+
+    fn edits() -> Vec<FixEdit> {
+        vec![
+            edit("rename", 0..3, "bar", 1),
+            edit("quote", 2..6, "'x'", 2),
+            edit("spacing", 8..10, " ", 1),
+        ]
+    }
+
+    fn applied_rules(plan: &FixPlan) -> Vec<&str> {
+        plan.edits.iter().map(|e| e.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn conflict_strategies() {
+        let first = FixPlan::new(edits(), FixStrategy::First);
+        assert_eq!(applied_rules(&first), vec!["rename", "spacing"]);
+        assert_eq!(first.conflicts.len(), 1);
+        assert_eq!(
+            first.conflicts[0].edits,
+            vec![
+                ConflictingEdit {
+                    rule: "rename".to_string(),
+                    span: 0..3,
+                    applied: true,
+                },
+                ConflictingEdit {
+                    rule: "quote".to_string(),
+                    span: 2..6,
+                    applied: false,
+                },
+            ]
+        );
+
+        let priority = FixPlan::new(edits(), FixStrategy::Priority);
+        assert_eq!(applied_rules(&priority), vec!["quote", "spacing"]);
+
+        let skip = FixPlan::new(edits(), FixStrategy::SkipConflicts);
+        assert_eq!(applied_rules(&skip), vec!["spacing"]);
+        assert!(skip.conflicts[0].edits.iter().all(|e| !e.applied));
+    }
+
+    #[test]
+    fn apply_plan() {
+        let plan = FixPlan::new(edits(), FixStrategy::First);
+        let transaction = plan.transaction(|_| Ok("foo = 1;  x".to_string())).unwrap();
+
+        assert_eq!(transaction, {
+            let mut expected = FileTransaction::new();
+            expected.write("main.py", "bar = 1; x");
+            expected
+        });
+    }
+
+    #[test]
+    fn invalid_span() {
+        let plan = FixPlan::new(vec![edit("rename", 0..30, "bar", 1)], FixStrategy::First);
+
+        assert!(plan.transaction(|_| Ok("foo".to_string())).is_err());
+    }
+}
@@ -1,8 +1,11 @@
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
+use anyhow::bail;
+
 pub fn find_upward_path(mut current_dir: PathBuf, name: &OsStr) -> anyhow::Result<Option<PathBuf>> {
     let mut match_dir = dir_entry_with_name(&current_dir, name)?;
 
@@ -30,3 +33,91 @@ fn dir_entry_with_name(dir: &Path, name: &OsStr) -> anyhow::Result<Option<PathBu
 pub fn path_to_string(path: &Path) -> String {
     path.to_string_lossy().to_string()
 }
+
+/// Replacement of a path prefix, parsed from `FROM=TO`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PathPrefixMapping {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl FromStr for PathPrefixMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((from, to)) if !from.is_empty() => Ok(PathPrefixMapping {
+                from: from.into(),
+                to: to.into(),
+            }),
+            _ => bail!("Invalid path prefix mapping (expected FROM=TO): {s}"),
+        }
+    }
+}
+
+/// Rewrite of the reported paths, for analyses running in a directory that differs from the
+/// layout of the repository (containers, CI runners...).
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PathPrefixMap {
+    mappings: Vec<PathPrefixMapping>,
+}
+
+impl PathPrefixMap {
+    pub fn new(mappings: Vec<PathPrefixMapping>) -> PathPrefixMap {
+        PathPrefixMap { mappings }
+    }
+
+    /// Replace the prefix of the path. Prefixes match whole components, and the last matching
+    /// mapping is applied.
+    pub fn remap(&self, path: &Path) -> PathBuf {
+        self.mappings
+            .iter()
+            .rev()
+            .find_map(|m| {
+                let rest = path.strip_prefix(&m.from).ok()?;
+
+                Some(if rest.as_os_str().is_empty() {
+                    m.to.clone()
+                } else {
+                    m.to.join(rest)
+                })
+            })
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    /// `remap` applied to the string representation of a path.
+    pub fn remap_str(&self, path: &str) -> String {
+        path_to_string(&self.remap(Path::new(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_remapping() {
+        let map = PathPrefixMap::new(vec![
+            "/build=/other".parse().unwrap(),
+            "/build/src=src".parse().unwrap(),
+        ]);
+
+        assert_eq!(
+            map.remap(Path::new("/build/src/main.py")),
+            Path::new("src/main.py")
+        );
+        assert_eq!(
+            map.remap(Path::new("/build/lib.py")),
+            Path::new("/other/lib.py")
+        );
+        assert_eq!(
+            map.remap(Path::new("/build/srcs/a.py")),
+            Path::new("/other/srcs/a.py")
+        );
+        assert_eq!(map.remap(Path::new("/tmp/a.py")), Path::new("/tmp/a.py"));
+        assert_eq!(map.remap(Path::new("/build/src")), Path::new("src"));
+
+        assert!("no-separator".parse::<PathPrefixMapping>().is_err());
+        assert!("=src".parse::<PathPrefixMapping>().is_err());
+    }
+}
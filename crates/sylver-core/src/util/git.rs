@@ -20,6 +20,11 @@ impl GitClient {
         run_git(["rev-parse", "HEAD"]).map(|s| s.trim_end().to_string())
     }
 
+    /// Root directory of the repository containing the current directory.
+    pub fn toplevel() -> anyhow::Result<PathBuf> {
+        run_git(["rev-parse", "--show-toplevel"]).map(|s| PathBuf::from(s.trim_end()))
+    }
+
     pub fn clone_repo(
         &self,
         logger: &dyn Logger,
@@ -42,6 +47,42 @@ impl GitClient {
     }
 }
 
+/// Detached worktree of the current repository, removed when dropped.
+#[derive(Debug)]
+pub struct Worktree {
+    path: PathBuf,
+}
+
+impl Worktree {
+    /// Check out the given revision in a new worktree at the given path.
+    pub fn add(path: PathBuf, rev: &str) -> anyhow::Result<Worktree> {
+        run_git([
+            OsStr::new("worktree"),
+            OsStr::new("add"),
+            OsStr::new("--detach"),
+            path.as_os_str(),
+            OsStr::new(rev),
+        ])?;
+
+        Ok(Worktree { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let _ = run_git([
+            OsStr::new("worktree"),
+            OsStr::new("remove"),
+            OsStr::new("--force"),
+            self.path.as_os_str(),
+        ]);
+    }
+}
+
 fn repo_clone_dir(url: &str, repo_dir: &Path) -> PathBuf {
     let separator_regex = fancy_regex::Regex::new(r#"/|\\"#).unwrap();
     let dir_name = separator_regex.replace_all(url, "_").into_owned();
@@ -1,6 +1,8 @@
 pub mod archive;
+pub mod cancel;
 pub mod debug;
 pub mod depth_iter;
+pub mod edits;
 pub mod fs;
 pub mod git;
 pub mod intern_map;
@@ -9,3 +11,4 @@ pub mod macros;
 pub mod once;
 #[cfg(test)]
 pub mod test;
+pub mod transaction;
@@ -0,0 +1,241 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+
+static JOURNAL_FILE_NAME: &str = "journal.yaml";
+static STAGED_EXTENSION: &str = "sylver-tmp";
+
+/// Set of file writes that are applied together.
+///
+/// The new contents are written to temporary files next to their targets and flushed to disk
+/// before being renamed over the targets, so a crash never leaves a partially written file. The
+/// previous contents are saved to a journal, which allows rolling back the last transaction.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FileTransaction {
+    writes: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct JournalEntry {
+    path: PathBuf,
+    /// Name of the copy of the original file in the journal directory (`None` if the file was
+    /// created by the transaction).
+    backup: Option<String>,
+}
+
+impl FileTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule the replacement of the content of `path`.
+    pub fn write(&mut self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) {
+        self.writes.insert(path.into(), content.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Apply all the writes, recording the previous state of the files in `journal_dir`.
+    ///
+    /// The journal of the previous transaction is discarded. If a file cannot be replaced, the
+    /// files that were already replaced are restored.
+    pub fn commit(self, journal_dir: &Path) -> anyhow::Result<()> {
+        let entries = write_journal(journal_dir, self.writes.keys())?;
+
+        let mut staged = Vec::with_capacity(self.writes.len());
+
+        for (path, content) in &self.writes {
+            match stage(path, content) {
+                Ok(staged_path) => staged.push((staged_path, path)),
+                Err(e) => {
+                    for (staged_path, _) in staged {
+                        let _ = std::fs::remove_file(staged_path);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        for (staged_path, path) in staged {
+            if let Err(e) = std::fs::rename(&staged_path, path) {
+                let _ = std::fs::remove_file(&staged_path);
+                let error = anyhow!(e).context(format!("Failed to replace {}", path.display()));
+
+                return Err(match restore(journal_dir, &entries) {
+                    Ok(()) => error,
+                    Err(restore_error) => error.context(format!(
+                        "The files replaced so far could not be restored ({restore_error:#}), \
+                         run the rollback again"
+                    )),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Restore the files modified by the last transaction whose journal is in `journal_dir`, and
+/// return their paths.
+pub fn rollback(journal_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let journal_path = journal_dir.join(JOURNAL_FILE_NAME);
+
+    if !journal_path.exists() {
+        bail!("Nothing to roll back");
+    }
+
+    let entries: Vec<JournalEntry> = serde_yaml::from_str(&std::fs::read_to_string(&journal_path)?)
+        .context("Invalid journal")?;
+
+    restore(journal_dir, &entries)?;
+    std::fs::remove_dir_all(journal_dir)?;
+
+    Ok(entries.into_iter().map(|e| e.path).collect())
+}
+
+fn write_journal<'p>(
+    journal_dir: &Path,
+    paths: impl Iterator<Item = &'p PathBuf>,
+) -> anyhow::Result<Vec<JournalEntry>> {
+    if journal_dir.exists() {
+        std::fs::remove_dir_all(journal_dir).context("Failed to clear the previous journal")?;
+    }
+
+    std::fs::create_dir_all(journal_dir)?;
+
+    let mut entries = vec![];
+
+    for (index, path) in paths.enumerate() {
+        // The journal must stay valid if the working directory changes.
+        let path = if path.is_relative() {
+            std::env::current_dir()?.join(path)
+        } else {
+            path.clone()
+        };
+
+        let backup = if path.exists() {
+            let name = format!("{index}.bak");
+            std::fs::copy(&path, journal_dir.join(&name))
+                .with_context(|| format!("Failed to back up {}", path.display()))?;
+            File::open(journal_dir.join(&name))?.sync_all()?;
+            Some(name)
+        } else {
+            None
+        };
+
+        entries.push(JournalEntry { path, backup });
+    }
+
+    write_synced(
+        &journal_dir.join(JOURNAL_FILE_NAME),
+        serde_yaml::to_string(&entries)?.as_bytes(),
+    )?;
+
+    Ok(entries)
+}
+
+fn restore(journal_dir: &Path, entries: &[JournalEntry]) -> anyhow::Result<()> {
+    for entry in entries {
+        match &entry.backup {
+            Some(name) => {
+                let content = std::fs::read(journal_dir.join(name)).with_context(|| {
+                    format!("Failed to read the backup of {}", entry.path.display())
+                })?;
+                let staged_path = stage(&entry.path, &content)?;
+                std::fs::rename(staged_path, &entry.path)
+                    .with_context(|| format!("Failed to restore {}", entry.path.display()))?;
+            }
+            None if entry.path.exists() => std::fs::remove_file(&entry.path)?,
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn stage(path: &Path, content: &[u8]) -> anyhow::Result<PathBuf> {
+    let mut staged_name = path.file_name().unwrap_or_default().to_os_string();
+    staged_name.push(".");
+    staged_name.push(STAGED_EXTENSION);
+
+    let staged_path = path.with_file_name(staged_name);
+    write_synced(&staged_path, content)
+        .with_context(|| format!("Failed to write {}", staged_path.display()))?;
+
+    Ok(staged_path)
+}
+
+fn write_synced(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::*;
+    use crate::util::test::create_tmp_child;
+
+    #[test]
+    fn commit_and_rollback() {
+        let d = TempDir::new().unwrap();
+        let journal = d.path().join("journal");
+
+        let existing = create_tmp_child(&d, "existing.txt", "old").unwrap();
+        let created = d.path().join("created.txt");
+
+        let mut transaction = FileTransaction::new();
+        transaction.write(&existing, "new");
+        transaction.write(&created, "content");
+        transaction.commit(&journal).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(&created).unwrap(), "content");
+
+        let restored = rollback(&journal).unwrap();
+
+        assert_eq!(restored.len(), 2);
+        assert_eq!(std::fs::read_to_string(&existing).unwrap(), "old");
+        assert!(!created.exists());
+        assert!(!journal.exists());
+    }
+
+    #[test]
+    fn binary_rollback() {
+        let d = TempDir::new().unwrap();
+        let journal = d.path().join("journal");
+        let path = d.path().join("data.bin");
+        let binary = vec![0xff, 0xfe, 0x00, 0x80];
+        std::fs::write(&path, &binary).unwrap();
+
+        let mut transaction = FileTransaction::new();
+        transaction.write(&path, "text");
+        transaction.commit(&journal).unwrap();
+
+        rollback(&journal).unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), binary);
+    }
+
+    #[test]
+    fn rollback_without_journal() {
+        let d = TempDir::new().unwrap();
+
+        assert!(rollback(&d.path().join("journal")).is_err());
+    }
+}
@@ -93,7 +93,7 @@ fn eval_parser_test_spec(test_spec: ParserTestSpec) {
             for r in &parse_res.reports {
                 println!(
                     "{}",
-                    render_report(true, r, &parse_res.tree.source).unwrap()
+                    render_report(true, &Default::default(), r, &parse_res.tree.source).unwrap()
                 );
             }
             panic!("Parsing error");
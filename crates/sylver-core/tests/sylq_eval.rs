@@ -18,6 +18,7 @@ use sylver_core::{
     pretty_print::tree::render_node,
     query::{expr::EvalError, language::compile::compile, SylvaNode},
     script::python::PythonScriptEngine,
+    util::cancel::CancellationToken,
 };
 use sylver_dsl::sylq::parse_query;
 
@@ -40,7 +41,13 @@ fn test_sylq_eval(expr_input: &str) {
     let mut land_builder = LandBuilder::new();
     let spec_id = land_builder.add_spec(spec.clone());
     let parser = ParserRunner::new(DEFAULT_START_RULE, &spec.syntax).unwrap();
-    let sylva = Sylva::build_concurrently(SylvaParser::Custom(parser), sources).unwrap();
+    let sylva = Sylva::build_concurrently(
+        SylvaParser::Custom(parser),
+        sources,
+        &CancellationToken::default(),
+        &(),
+    )
+    .unwrap();
     let sylva_id = land_builder
         .add_sylva(sylva, LandSpecId::CustomLangId(spec_id))
         .unwrap();
@@ -51,7 +58,13 @@ fn test_sylq_eval(expr_input: &str) {
 
     let compiled = compile(land.spec(LandSpecId::CustomLangId(spec_id)), &expr).unwrap();
 
-    let res = filter_sylva(&land, PythonScriptEngine::default(), sylva_id, &compiled);
+    let res = filter_sylva(
+        &land,
+        PythonScriptEngine::default(),
+        sylva_id,
+        &compiled,
+        &CancellationToken::default(),
+    );
 
     let expected_output = std::fs::read_to_string(expr_relative.with_extension("output")).unwrap();
     let output_str = res_to_string(&land, sylva_id, res);
@@ -16,9 +16,12 @@ use pest::{
 use pest_derive::*;
 use thiserror::Error;
 
-use crate::util::*;
+use crate::{
+    meta::imports::{ImportDecl, ImportLocation, ImportResolver},
+    util::*,
+};
 
-type MetaParserRes<T> = Result<T, MetaParserErr>;
+pub(crate) type MetaParserRes<T> = Result<T, MetaParserErr>;
 
 #[derive(Debug, Error)]
 pub enum MetaParserErr {
@@ -34,6 +37,16 @@ pub enum MetaParserErr {
     UnknownTermType(String),
     #[error("Missing argument: {0}")]
     MissingArgument(String),
+    #[error("Invalid format template: {0}")]
+    InvalidFormat(String),
+    #[error("{0}: Unknown standard fragment: {1}")]
+    UnknownFragment(ImportLocation, String),
+    #[error("{0}: Relative import outside of a spec file: {1}")]
+    RelativeImport(ImportLocation, String),
+    #[error("{0}: Could not import {1}: {2}")]
+    Import(ImportLocation, String, io::Error),
+    #[error("{0}: Import cycle through {1}")]
+    ImportCycle(ImportLocation, String),
 }
 
 impl From<pest::error::Error<Rule>> for MetaParserErr {
@@ -58,6 +71,9 @@ pub enum Decl {
     Node(NodeDecl),
     Terminal(TermDecl),
     Rule(RuleDecl),
+    Format(FormatDecl),
+    Binding(BindingDecl),
+    Alias(AliasDecl),
 }
 
 impl Decl {
@@ -66,6 +82,9 @@ impl Decl {
             Decl::Node(n) => &n.name,
             Decl::Terminal(t) => &t.name,
             Decl::Rule(r) => &r.name,
+            Decl::Format(f) => &f.node_type,
+            Decl::Binding(b) => &b.node_type,
+            Decl::Alias(a) => &a.name,
         }
     }
 
@@ -210,6 +229,52 @@ pub enum TermDeclData {
     Comment,
 }
 
+/// Template used to print the nodes of a given type, in which `{field}` is replaced by the
+/// printed field, `{>field}` by the printed field indented by one level and `{field:sep}` by the
+/// elements of a list field separated by `sep`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FormatDecl {
+    pub node_type: String,
+    pub parts: Vec<FormatPart>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum FormatPart {
+    Text(String),
+    Field(FormatField),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FormatField {
+    pub name: String,
+    pub indent: bool,
+    pub separator: String,
+}
+
+/// Role of the nodes of a given type in name resolution.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BindingDecl {
+    pub node_type: String,
+    pub binding: Binding,
+}
+
+/// The name of declarations and references is the text of the given field, or the text of the
+/// node itself when no field is given.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Binding {
+    /// The node's descendants are bound in a new scope, nested in the enclosing one.
+    Scope,
+    Declaration(Option<String>),
+    Reference(Option<String>),
+}
+
+/// Former name of a renamed node type, under which queries can still refer to it.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AliasDecl {
+    pub name: String,
+    pub node_type: String,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct RuleDecl {
     pub name: String,
@@ -411,10 +476,33 @@ impl Hash for TermContent {
 }
 
 pub fn parse_meta_file(path: impl AsRef<str>) -> MetaParserRes<Vec<Decl>> {
-    parse(&read_to_string(Path::new(path.as_ref()))?)
+    let path = Path::new(path.as_ref());
+    parse_with_imports(&read_to_string(path)?, Some(path))
 }
 
+/// Parse a spec. Only the standard fragments can be imported, see `parse_with_imports`.
 pub fn parse(input: &str) -> MetaParserRes<Vec<Decl>> {
+    parse_with_imports(input, None)
+}
+
+/// Parse a spec, replacing its imports with the declarations of the imported specs. Imports
+/// starting with `std/` refer to the bundled fragments, other imports are relative to the
+/// directory of `path`, the file the spec was read from.
+pub fn parse_with_imports(input: &str, path: Option<&Path>) -> MetaParserRes<Vec<Decl>> {
+    parse_with_imports_using(input, path, &mut |import: &Path| read_to_string(import))
+}
+
+/// Parse a spec like `parse_with_imports`, reading the imported files with `read`.
+pub fn parse_with_imports_using(
+    input: &str,
+    path: Option<&Path>,
+    read: &mut dyn FnMut(&Path) -> io::Result<String>,
+) -> MetaParserRes<Vec<Decl>> {
+    ImportResolver::new(path, read)?.resolve(input, path)
+}
+
+/// Parse the imports and the declarations of a spec.
+pub(crate) fn parse_statements(input: &str) -> MetaParserRes<(Vec<ImportDecl>, Vec<Decl>)> {
     main(
         (MetaParser::parse(Rule::main, input)?)
             .next_child()?
@@ -422,11 +510,44 @@ pub fn parse(input: &str) -> MetaParserRes<Vec<Decl>> {
     )
 }
 
-fn main(pairs: Pairs<Rule>) -> MetaParserRes<Vec<Decl>> {
-    pairs
-        .filter(|p| p.as_rule() != Rule::EOI)
-        .map(main_sub)
-        .collect()
+/// Parse a type literal, such as `List<Node1 | Node2>`.
+pub fn parse_type_lit(input: &str) -> MetaParserRes<TypeLit> {
+    type_lit(
+        MetaParser::parse(Rule::type_lit_main, input)?
+            .next_child()?
+            .into_inner()
+            .next_child()?
+            .into_inner(),
+    )
+}
+
+fn main(pairs: Pairs<Rule>) -> MetaParserRes<(Vec<ImportDecl>, Vec<Decl>)> {
+    let mut imports = vec![];
+    let mut decls = vec![];
+
+    for pair in pairs.filter(|p| p.as_rule() != Rule::EOI) {
+        if pair.as_rule() == Rule::import_decl {
+            imports.push(import_decl(pair)?);
+        } else {
+            decls.push(main_sub(pair)?);
+        }
+    }
+
+    Ok((imports, decls))
+}
+
+fn import_decl(pair: Pair<Rule>) -> MetaParserRes<ImportDecl> {
+    let (line, column) = pair.as_span().start_pos().line_col();
+
+    let path = pair
+        .into_inner()
+        .next_child()?
+        .into_inner()
+        .next_child()?
+        .as_str()
+        .to_string();
+
+    Ok(ImportDecl { path, line, column })
 }
 
 fn main_sub(pair: Pair<Rule>) -> MetaParserRes<Decl> {
@@ -440,11 +561,152 @@ fn main_sub(pair: Pair<Rule>) -> MetaParserRes<Decl> {
         }
         Rule::node_decl => Decl::Node(node_decl(pair.into_inner())?),
         Rule::rule_decl => Decl::Rule(rule_decl(pair.into_inner())?),
-        r => unexpected_rule(r, vec![Rule::terminal, Rule::node_decl, Rule::rule_decl])?,
+        Rule::format_decl => Decl::Format(format_decl(pair.into_inner())?),
+        Rule::binding_decl => Decl::Binding(binding_decl(pair.into_inner())?),
+        Rule::alias_decl => Decl::Alias(alias_decl(pair.into_inner())?),
+        r => unexpected_rule(
+            r,
+            vec![
+                Rule::terminal,
+                Rule::node_decl,
+                Rule::rule_decl,
+                Rule::format_decl,
+                Rule::binding_decl,
+                Rule::alias_decl,
+            ],
+        )?,
     };
     Ok(res)
 }
 
+fn format_decl(mut pairs: Pairs<Rule>) -> MetaParserRes<FormatDecl> {
+    let node_type = t_name(pairs.next_child()?);
+    let template = pairs.next_child()?.into_inner().next_child()?.as_str();
+
+    Ok(FormatDecl {
+        node_type,
+        parts: format_parts(&unescape_template(template))?,
+    })
+}
+
+fn binding_decl(mut pairs: Pairs<Rule>) -> MetaParserRes<BindingDecl> {
+    let binding = pairs.next_child()?;
+    let rule = binding.as_rule();
+    let mut childs = binding.into_inner();
+    let node_type = t_name(childs.next_child()?);
+    let field = childs.next().map(t_name);
+
+    let binding = match rule {
+        Rule::scope_binding => Binding::Scope,
+        Rule::declare_binding => Binding::Declaration(field),
+        Rule::reference_binding => Binding::Reference(field),
+        r => unexpected_rule(
+            r,
+            vec![
+                Rule::scope_binding,
+                Rule::declare_binding,
+                Rule::reference_binding,
+            ],
+        )?,
+    };
+
+    Ok(BindingDecl { node_type, binding })
+}
+
+fn alias_decl(mut pairs: Pairs<Rule>) -> MetaParserRes<AliasDecl> {
+    let name = t_name(pairs.next_child()?);
+    let node_type = t_name(pairs.next_child()?);
+
+    Ok(AliasDecl { name, node_type })
+}
+
+fn unescape_template(template: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+fn format_parts(template: &str) -> MetaParserRes<Vec<FormatPart>> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+            '{' => {
+                let mut placeholder = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => {
+                            return Err(MetaParserErr::InvalidFormat(format!(
+                                "unclosed placeholder in \"{template}\""
+                            )))
+                        }
+                    }
+                }
+
+                if !text.is_empty() {
+                    parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                }
+
+                parts.push(FormatPart::Field(format_field(&placeholder)?));
+            }
+            '}' => {
+                return Err(MetaParserErr::InvalidFormat(format!(
+                    "unmatched '}}' in \"{template}\""
+                )))
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        parts.push(FormatPart::Text(text));
+    }
+
+    Ok(parts)
+}
+
+fn format_field(placeholder: &str) -> MetaParserRes<FormatField> {
+    let (indent, field) = match placeholder.strip_prefix('>') {
+        Some(field) => (true, field),
+        None => (false, placeholder),
+    };
+
+    let (name, separator) = field.split_once(':').unwrap_or((field, ""));
+    let name = name.trim();
+
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(MetaParserErr::InvalidFormat(format!(
+            "invalid placeholder {{{placeholder}}}"
+        )));
+    }
+
+    Ok(FormatField {
+        name: name.to_string(),
+        indent,
+        separator: separator.to_string(),
+    })
+}
+
 fn rule_decl(mut pairs: Pairs<Rule>) -> MetaParserRes<RuleDecl> {
     let name = t_name(pairs.next_child()?);
     let mut alternatives = vec![rule_expr(pairs.next_child()?.into_inner())?];
@@ -1142,6 +1404,87 @@ pub mod test {
         )
     }
 
+    #[test]
+    fn format_decl_parts() {
+        test_parser(
+            MetaParser::parse(Rule::format_decl, r#"format Block = "{{\n{>stmts:\n}\n}}""#),
+            format_decl,
+            FormatDecl {
+                node_type: "Block".into(),
+                parts: vec![
+                    FormatPart::Text("{\n".into()),
+                    FormatPart::Field(FormatField {
+                        name: "stmts".into(),
+                        indent: true,
+                        separator: "\n".into(),
+                    }),
+                    FormatPart::Text("\n}".into()),
+                ],
+            },
+        )
+    }
+
+    #[test]
+    fn invalid_format_template() {
+        assert!(parse(r#"format Block = "{stmts""#).is_err());
+        assert!(parse(r#"format Block = "stmts}""#).is_err());
+        assert!(parse(r#"format Block = "{a b}""#).is_err());
+    }
+
+    #[test]
+    fn binding_decls() {
+        let decls = parse(indoc!(
+            "
+            scope Block
+            declare Function.name
+            reference Identifier
+        "
+        ))
+        .unwrap();
+
+        assert_eq!(
+            decls,
+            vec![
+                Decl::Binding(BindingDecl {
+                    node_type: "Block".into(),
+                    binding: Binding::Scope,
+                }),
+                Decl::Binding(BindingDecl {
+                    node_type: "Function".into(),
+                    binding: Binding::Declaration(Some("name".into())),
+                }),
+                Decl::Binding(BindingDecl {
+                    node_type: "Identifier".into(),
+                    binding: Binding::Reference(None),
+                }),
+            ]
+        );
+
+        assert!(parse("scope Block.body").is_err());
+    }
+
+    #[test]
+    fn alias_decl() {
+        assert_eq!(
+            parse("alias FunctionDef = Function").unwrap(),
+            vec![Decl::Alias(AliasDecl {
+                name: "FunctionDef".into(),
+                node_type: "Function".into(),
+            })]
+        );
+
+        assert!(parse("alias FunctionDef").is_err());
+    }
+
+    #[test]
+    fn standalone_type_lit() {
+        assert_eq!(
+            parse_type_lit("List<Node1 | Node2>").unwrap().to_string(),
+            "List<Node1 | Node2>"
+        );
+        assert!(parse_type_lit("List<Node1").is_err());
+    }
+
     #[test]
     fn parameterized_type() {
         test_parser(
@@ -0,0 +1,232 @@
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::meta::ast::{parse_statements, Decl, MetaParserErr, MetaParserRes};
+
+static STD_PREFIX: &str = "std/";
+
+/// Fragments bundled with sylver, importable as `std/<name>`:
+/// - `whitespace`: ignored `WHITESPACE`, including line returns.
+/// - `identifiers`: C-style `IDENTIFIER`.
+/// - `numbers`: `INT_LIT`, `HEX_LIT`, `BIN_LIT` and `FLOAT_LIT`, with `_` digit separators.
+/// - `strings`: double-quoted `STRING_LIT` and single-quoted `CHAR_LIT`, with escapes.
+/// - `c_comments`: `LINE_COMMENT` (`// ...`) and `BLOCK_COMMENT` (`/* ... */`).
+static STD_FRAGMENTS: &[(&str, &str)] = &[
+    ("whitespace", include_str!("std/whitespace.syl")),
+    ("identifiers", include_str!("std/identifiers.syl")),
+    ("numbers", include_str!("std/numbers.syl")),
+    ("strings", include_str!("std/strings.syl")),
+    ("c_comments", include_str!("std/c_comments.syl")),
+];
+
+/// Source of the standard fragment with the given name (without the `std/` prefix).
+pub fn std_fragment(name: &str) -> Option<&'static str> {
+    STD_FRAGMENTS
+        .iter()
+        .find(|(fragment, _)| *fragment == name)
+        .map(|(_, source)| *source)
+}
+
+/// `import` statement of a spec.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct ImportDecl {
+    pub path: String,
+    /// Position of the statement, starting at 1.
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Position of an import statement, reported with the import errors.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImportLocation {
+    /// File of the importing spec, if it was read from a file.
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for ImportLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file.display())?;
+        }
+
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Inline the imported specs. Each spec is imported at most once, so that fragments shared by
+/// several imports don't declare their terminals twice.
+pub(crate) struct ImportResolver<'r> {
+    imported: HashSet<String>,
+    /// Specs whose imports are being resolved.
+    stack: Vec<String>,
+    read: &'r mut dyn FnMut(&Path) -> io::Result<String>,
+}
+
+impl<'r> ImportResolver<'r> {
+    pub fn new(
+        root: Option<&Path>,
+        read: &'r mut dyn FnMut(&Path) -> io::Result<String>,
+    ) -> MetaParserRes<ImportResolver<'r>> {
+        let stack = match root {
+            Some(path) => vec![canonical_key(path)?],
+            None => vec![],
+        };
+
+        Ok(ImportResolver {
+            imported: HashSet::new(),
+            stack,
+            read,
+        })
+    }
+
+    /// Parse the given spec, `path` being the file it was read from. Its relative imports are
+    /// resolved from the directory of the file.
+    pub fn resolve(&mut self, input: &str, path: Option<&Path>) -> MetaParserRes<Vec<Decl>> {
+        let (imports, decls) = parse_statements(input)?;
+        let mut resolved = vec![];
+
+        for import in imports {
+            let location = ImportLocation {
+                file: path.map(Path::to_path_buf),
+                line: import.line,
+                column: import.column,
+            };
+
+            let (key, source, import_path) = load(
+                &import.path,
+                &location,
+                path.and_then(Path::parent),
+                &mut *self.read,
+            )?;
+
+            if self.stack.contains(&key) {
+                return Err(MetaParserErr::ImportCycle(location, import.path));
+            }
+
+            if !self.imported.insert(key.clone()) {
+                continue;
+            }
+
+            self.stack.push(key);
+            resolved.extend(self.resolve(&source, import_path.as_deref())?);
+            self.stack.pop();
+        }
+
+        resolved.extend(decls);
+
+        Ok(resolved)
+    }
+}
+
+/// Key identifying the imported spec, its source, and the file it was read from.
+fn load(
+    import: &str,
+    location: &ImportLocation,
+    dir: Option<&Path>,
+    read: &mut dyn FnMut(&Path) -> io::Result<String>,
+) -> MetaParserRes<(String, String, Option<PathBuf>)> {
+    if let Some(name) = import.strip_prefix(STD_PREFIX) {
+        return match std_fragment(name) {
+            Some(source) => Ok((import.to_string(), source.to_string(), None)),
+            None => Err(MetaParserErr::UnknownFragment(
+                location.clone(),
+                import.to_string(),
+            )),
+        };
+    }
+
+    let dir =
+        dir.ok_or_else(|| MetaParserErr::RelativeImport(location.clone(), import.to_string()))?;
+    let path = dir.join(import);
+    let import_err = |e| MetaParserErr::Import(location.clone(), import.to_string(), e);
+
+    let source = read(&path).map_err(import_err)?;
+    let key = canonical_key(&path).map_err(|e| match e {
+        MetaParserErr::IO(e) => import_err(e),
+        e => e,
+    })?;
+
+    Ok((key, source, Some(path)))
+}
+
+fn canonical_key(path: &Path) -> MetaParserRes<String> {
+    Ok(path.canonicalize()?.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+    use crate::meta::{parse, parse_meta_file};
+
+    fn test_res(file: &str) -> String {
+        format!("{}/test_res/imports/{file}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn std_fragments_parse() {
+        for (name, source) in STD_FRAGMENTS {
+            assert!(parse(source).is_ok(), "invalid fragment: {name}");
+        }
+    }
+
+    #[test]
+    fn std_import() {
+        let decls = parse(indoc!(
+            r#"
+            import "std/identifiers"
+            import "std/identifiers"
+
+            node Identifier { }
+
+            rule main = Identifier { IDENTIFIER }
+        "#
+        ))
+        .unwrap();
+
+        assert_eq!(
+            decls.iter().map(Decl::name).collect::<Vec<_>>(),
+            vec!["IDENTIFIER", "Identifier", "main"]
+        );
+
+        assert_eq!(
+            parse("node A { }\n  import \"std/unknown\"")
+                .unwrap_err()
+                .to_string(),
+            "2:3: Unknown standard fragment: std/unknown"
+        );
+        assert!(matches!(
+            parse(r#"import "common.syl""#),
+            Err(MetaParserErr::RelativeImport(_, _))
+        ));
+    }
+
+    #[test]
+    fn file_imports() {
+        let decls = parse_meta_file(test_res("main.syl")).unwrap();
+
+        assert_eq!(
+            decls.iter().map(Decl::name).collect::<Vec<_>>(),
+            vec!["WHITESPACE", "IDENTIFIER", "Identifier", "main"]
+        );
+
+        assert_eq!(
+            parse_meta_file(test_res("cycle_a.syl"))
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "{}:1:1: Import cycle through cycle_a.syl",
+                Path::new(&test_res("cycle_a.syl"))
+                    .with_file_name("cycle_b.syl")
+                    .display()
+            )
+        );
+    }
+}
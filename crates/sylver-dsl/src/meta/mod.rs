@@ -1,3 +1,5 @@
 pub(crate) mod ast;
+mod imports;
 
 pub use ast::*;
+pub use imports::{std_fragment, ImportLocation};
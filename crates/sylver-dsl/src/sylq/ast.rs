@@ -75,6 +75,8 @@ pub enum NodePatternFieldDesc {
 pub enum NodePatternFieldValue {
     Pattern(QueryPattern),
     Text(String),
+    /// Regex matched against the text of the field.
+    Regex(ExprRegex),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -86,11 +88,23 @@ pub enum Expr {
     RegexLit(ExprRegex),
     DotAccess(bool, Box<Expr>, String),
     DotCall(bool, Box<Expr>, String, Vec<Arg>),
+    Call(String, Vec<Arg>),
+    Let(String, Box<Expr>, Box<Expr>),
     Not(Box<Expr>),
     Binop(Box<Expr>, Op, Box<Expr>),
     Is(Box<Expr>, Box<QueryPattern>),
     ArrayIndex(Box<Expr>, Box<Expr>),
     ArrayQuant(ArrayQuantQuant, Box<Expr>, Box<QueryPattern>),
+    /// `node has axis::pattern`: true if a node on the given axis matches the pattern.
+    Axis(Box<Expr>, Axis, Box<QueryPattern>),
+}
+
+/// Reusable predicate, defined with `def name(param1, param2) = expr`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PredicateDef {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Expr,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -103,6 +117,12 @@ pub enum Arg {
 pub struct ExprRegex(fancy_regex::Regex);
 
 impl ExprRegex {
+    pub fn new(pattern: &str) -> SylqParserRes<ExprRegex> {
+        fancy_regex::Regex::new(pattern)
+            .map(ExprRegex)
+            .map_err(|err| SylqParserError::RegexErr(Box::new(err)))
+    }
+
     pub fn is_match(&self, txt: &str) -> bool {
         let match_res = self.0.is_match(txt);
         matches!(match_res, Ok(true))
@@ -146,6 +166,14 @@ pub enum ArrayQuantQuant {
     All,
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Axis {
+    Ancestor,
+    Descendant,
+    PrecedingSibling,
+    FollowingSibling,
+}
+
 pub fn parse_query(query_code: impl AsRef<str>) -> SylqParserRes<QueryPattern> {
     query(
         SylqParser::parse(Rule::query, query_code.as_ref())?
@@ -155,6 +183,25 @@ pub fn parse_query(query_code: impl AsRef<str>) -> SylqParserRes<QueryPattern> {
     )
 }
 
+pub fn parse_predicate_def(def_code: impl AsRef<str>) -> SylqParserRes<PredicateDef> {
+    predicate_def(
+        SylqParser::parse(Rule::predicate_def, def_code.as_ref())?
+            .next()
+            .unwrap()
+            .into_inner(),
+    )
+}
+
+/// Parse an expression embedded in a message template.
+pub fn parse_template_expr(expr_code: impl AsRef<str>) -> SylqParserRes<Expr> {
+    expr(
+        SylqParser::parse(Rule::template_expr, expr_code.as_ref())?
+            .next()
+            .unwrap()
+            .into_inner(),
+    )
+}
+
 pub fn parse_expr(expr_code: impl AsRef<str>) -> Expr {
     expr(
         SylqParser::parse(Rule::expr, expr_code.as_ref())
@@ -166,6 +213,24 @@ pub fn parse_expr(expr_code: impl AsRef<str>) -> Expr {
     .unwrap()
 }
 
+fn predicate_def(mut pairs: Pairs<Rule>) -> SylqParserRes<PredicateDef> {
+    let name = pair_text(pairs.next().unwrap());
+
+    let mut params = vec![];
+    let mut next = pairs.next().unwrap();
+
+    if next.as_rule() == Rule::predicate_params {
+        params = next.into_inner().map(pair_text).collect();
+        next = pairs.next().unwrap();
+    }
+
+    Ok(PredicateDef {
+        name,
+        params,
+        body: expr(next.into_inner())?,
+    })
+}
+
 fn query(mut pairs: Pairs<Rule>) -> SylqParserRes<QueryPattern> {
     let child = pairs.next().unwrap();
 
@@ -258,10 +323,26 @@ fn node_pattern_val_field(mut pairs: Pairs<Rule>) -> SylqParserRes<NodePatternFi
 
     let value = match value_pair.as_rule() {
         Rule::string_literal => NodePatternFieldValue::Text(string_literal(value_pair)?),
+        Rule::regex_literal => NodePatternFieldValue::Regex(ExprRegex::new(
+            &value_pair
+                .into_inner()
+                .next()
+                .unwrap()
+                .as_str()
+                .replace(r#"\`"#, "`"),
+        )?),
+        Rule::slash_regex_literal => NodePatternFieldValue::Regex(ExprRegex::new(
+            &value_pair
+                .into_inner()
+                .next()
+                .unwrap()
+                .as_str()
+                .replace(r#"\/"#, "/"),
+        )?),
         Rule::query_pattern => NodePatternFieldValue::Pattern(query_pattern(
             value_pair.into_inner().next().unwrap().into_inner(),
         )?),
-        r => panic!("Unexpected rule {r:?}, expected query pattern or string literal"),
+        r => panic!("Unexpected rule {r:?}, expected query pattern, string or regex literal"),
     };
 
     Ok(NodePatternField { desc, value })
@@ -291,10 +372,16 @@ fn expr(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
         }
         Rule::term => term(child.into_inner()),
         Rule::is_expr => is_expr(child.into_inner()),
+        Rule::axis_expr => axis_expr(child.into_inner()),
         Rule::array_index => array_index(child.into_inner()),
         Rule::array_quant_expr => array_quant(child.into_inner()),
         Rule::regex_literal => regex_literal(child.into_inner()),
         Rule::not_expr => not_expr(child.into_inner()),
+        Rule::let_expr => let_expr(child.into_inner()),
+        Rule::call => {
+            let (callee_name, args) = call(child.into_inner())?;
+            Ok(Expr::Call(callee_name, args))
+        }
         r => panic!("Unexpected rule: {r:?}"),
     }
 }
@@ -318,6 +405,23 @@ fn is_expr(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
     Ok(Expr::Is(Box::new(operand), Box::new(pattern)))
 }
 
+fn axis_expr(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
+    let operand = expr(pairs.next().unwrap().into_inner())?;
+    let axis = axis(pairs.next().unwrap());
+    let pattern = query(pairs.next().unwrap().into_inner())?;
+    Ok(Expr::Axis(Box::new(operand), axis, Box::new(pattern)))
+}
+
+fn axis(pair: Pair<Rule>) -> Axis {
+    match pair.as_str() {
+        "ancestor" => Axis::Ancestor,
+        "descendant" => Axis::Descendant,
+        "preceding-sibling" => Axis::PrecedingSibling,
+        "following-sibling" => Axis::FollowingSibling,
+        a => panic!("Invalid axis: {a}"),
+    }
+}
+
 fn array_index(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
     let array = expr(pairs.next().unwrap().into_inner())?;
     let index = expr(pairs.next().unwrap().into_inner())?;
@@ -334,15 +438,20 @@ fn array_quant(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
 fn regex_literal(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
     let regex_txt = pairs.next().unwrap().as_str().replace(r#"\`"#, "`");
 
-    fancy_regex::Regex::new(&regex_txt)
-        .map(|r| Expr::RegexLit(ExprRegex(r)))
-        .map_err(|err| SylqParserError::RegexErr(Box::new(err)))
+    ExprRegex::new(&regex_txt).map(Expr::RegexLit)
 }
 
 fn not_expr(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
     expr(pairs.next().unwrap().into_inner()).map(|e| Expr::Not(Box::new(e)))
 }
 
+fn let_expr(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
+    let name = pair_text(pairs.next().unwrap());
+    let value = expr(pairs.next().unwrap().into_inner())?;
+    let body = expr(pairs.next().unwrap().into_inner())?;
+    Ok(Expr::Let(name, Box::new(value), Box::new(body)))
+}
+
 fn array_quant_quant(pair: Pair<Rule>) -> ArrayQuantQuant {
     match pair.as_str() {
         "no" => ArrayQuantQuant::No,
@@ -376,17 +485,7 @@ fn term(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
                 let mut childs = p.into_inner();
                 let safe = is_safe_dot(childs.next().unwrap());
 
-                let mut call_childs = childs.next().unwrap().into_inner();
-                let callee_name = call_childs.next().unwrap().as_str().to_string();
-
-                let args = if let Some(arg_pairs) = call_childs.next() {
-                    arg_pairs
-                        .into_inner()
-                        .map(arg)
-                        .collect::<SylqParserRes<Vec<Arg>>>()?
-                } else {
-                    vec![]
-                };
+                let (callee_name, args) = call(childs.next().unwrap().into_inner())?;
 
                 current_expr = Expr::DotCall(safe, Box::new(current_expr), callee_name, args);
             }
@@ -401,6 +500,21 @@ fn term(mut pairs: Pairs<Rule>) -> SylqParserRes<Expr> {
     Ok(current_expr)
 }
 
+fn call(mut pairs: Pairs<Rule>) -> SylqParserRes<(String, Vec<Arg>)> {
+    let callee_name = pairs.next().unwrap().as_str().to_string();
+
+    let args = if let Some(arg_pairs) = pairs.next() {
+        arg_pairs
+            .into_inner()
+            .map(arg)
+            .collect::<SylqParserRes<Vec<Arg>>>()?
+    } else {
+        vec![]
+    };
+
+    Ok((callee_name, args))
+}
+
 fn arg(arg: Pair<Rule>) -> SylqParserRes<Arg> {
     match arg.as_rule() {
         Rule::unnamed_arg => Ok(Arg::Unnamed(expr(arg.into_inner())?)),
@@ -570,6 +684,82 @@ pub mod test {
         )
     }
 
+    #[test]
+    fn function_call() {
+        test_parser(
+            SylqParser::parse(Rule::expr, "contains_text(node, 'TODO')"),
+            expr,
+            Expr::Call(
+                "contains_text".to_string(),
+                vec![
+                    Arg::Unnamed(Expr::Identifier("node".to_string())),
+                    Arg::Unnamed(Expr::StringLit("TODO".to_string())),
+                ],
+            ),
+        )
+    }
+
+    #[test]
+    fn let_expr() {
+        test_parser(
+            SylqParser::parse(Rule::expr, "let t = n.text in t == 'a' || t == 'b'"),
+            expr,
+            Expr::Let(
+                "t".to_string(),
+                Box::new(Expr::DotAccess(
+                    false,
+                    Box::new(Expr::Identifier("n".to_string())),
+                    "text".to_string(),
+                )),
+                Box::new(Expr::Binop(
+                    Box::new(Expr::Binop(
+                        Box::new(Expr::Identifier("t".to_string())),
+                        Op::EqEq,
+                        Box::new(Expr::StringLit("a".to_string())),
+                    )),
+                    Op::Or,
+                    Box::new(Expr::Binop(
+                        Box::new(Expr::Identifier("t".to_string())),
+                        Op::EqEq,
+                        Box::new(Expr::StringLit("b".to_string())),
+                    )),
+                )),
+            ),
+        )
+    }
+
+    #[test]
+    fn predicate_definition() {
+        assert_eq!(
+            parse_predicate_def("def is_test_function(f, prefix) = f.name.text == prefix").unwrap(),
+            PredicateDef {
+                name: "is_test_function".to_string(),
+                params: vec!["f".to_string(), "prefix".to_string()],
+                body: Expr::Binop(
+                    Box::new(Expr::DotAccess(
+                        false,
+                        Box::new(Expr::DotAccess(
+                            false,
+                            Box::new(Expr::Identifier("f".to_string())),
+                            "name".to_string(),
+                        )),
+                        "text".to_string(),
+                    )),
+                    Op::EqEq,
+                    Box::new(Expr::Identifier("prefix".to_string())),
+                ),
+            }
+        );
+    }
+
+    #[test]
+    fn predicate_definition_without_params() {
+        let def = parse_predicate_def("def always() = 1 == 1").unwrap();
+
+        assert_eq!(def.name, "always");
+        assert!(def.params.is_empty());
+    }
+
     #[test]
     fn safe_dot_call() {
         test_parser(
@@ -656,6 +846,45 @@ pub mod test {
         )
     }
 
+    #[test]
+    fn axis_expr() {
+        test_parser(
+            SylqParser::parse(Rule::expr, "n has preceding-sibling::{ Node }"),
+            expr,
+            Expr::Axis(
+                Box::new(Expr::Identifier("n".to_string())),
+                Axis::PrecedingSibling,
+                Box::new(QueryPattern {
+                    node_pattern: NodePatternsWithBinding {
+                        binding: None,
+                        node_patterns: NonEmpty::new(NodePattern {
+                            kind_pattern: KindPattern::KindName("Node".to_string()),
+                            fields: vec![],
+                        }),
+                    },
+                    predicate: None,
+                }),
+            ),
+        )
+    }
+
+    #[test]
+    fn nested_axis_exprs() {
+        let query = parse_query(
+            "match Call c when c has ancestor::{ Loop l when l has ancestor::Handler }",
+        )
+        .unwrap();
+
+        let Some(Expr::Axis(_, Axis::Ancestor, loop_pattern)) = query.predicate else {
+            panic!("Expected an ancestor axis");
+        };
+
+        assert!(matches!(
+            loop_pattern.predicate,
+            Some(Expr::Axis(_, Axis::Ancestor, _))
+        ));
+    }
+
     #[test]
     fn ht_operator() {
         test_parser(
@@ -952,6 +1181,23 @@ pub mod test {
         )
     }
 
+    #[test]
+    fn field_regex_patterns() {
+        let field_value = |query: &str| {
+            let query = parse_query(query).unwrap();
+            query.node_pattern.node_patterns[0].fields[0].value.clone()
+        };
+
+        assert_eq!(
+            field_value("match Function(name: /^test_\\//)"),
+            NodePatternFieldValue::Regex(ExprRegex::new("^test_/").unwrap())
+        );
+        assert_eq!(
+            field_value("match Function(name: `^test_`)"),
+            NodePatternFieldValue::Regex(ExprRegex::new("^test_").unwrap())
+        );
+    }
+
     #[test]
     fn is_predicate() {
         assert_eq!(
@@ -1026,4 +1272,18 @@ pub mod test {
             }
         )
     }
+
+    #[test]
+    fn template_expr() {
+        assert_eq!(
+            parse_template_expr("f.name").unwrap(),
+            Expr::DotAccess(
+                false,
+                Box::new(Expr::Identifier("f".to_string())),
+                "name".to_string(),
+            )
+        );
+
+        assert!(parse_template_expr("f.name f").is_err());
+    }
 }
@@ -11,3 +11,168 @@ pub fn javascript_language() -> Language {
 pub fn yaml_language() -> Language {
     tree_sitter_yaml::language()
 }
+
+pub fn cmake_language() -> Language {
+    tree_sitter_cmake::language()
+}
+
+pub fn solidity_language() -> Language {
+    tree_sitter_solidity::language()
+}
+
+pub fn erlang_language() -> Language {
+    tree_sitter_erlang::language()
+}
+
+pub fn asm_language() -> Language {
+    tree_sitter_asm::language()
+}
+
+pub fn ada_language() -> Language {
+    tree_sitter_ada::language()
+}
+
+pub fn powershell_language() -> Language {
+    tree_sitter_powershell::language()
+}
+
+pub fn cobol_language() -> Language {
+    tree_sitter_cobol::language()
+}
+
+pub fn latex_language() -> Language {
+    tree_sitter_latex::language()
+}
+
+pub fn fsharp_language() -> Language {
+    tree_sitter_fsharp::language()
+}
+
+pub fn crystal_language() -> Language {
+    tree_sitter_crystal::language()
+}
+
+pub fn scheme_language() -> Language {
+    tree_sitter_scheme::language()
+}
+
+pub fn rescript_language() -> Language {
+    tree_sitter_rescript::language()
+}
+
+pub fn jinja_language() -> Language {
+    tree_sitter_jinja2::language()
+}
+
+pub fn nginx_language() -> Language {
+    tree_sitter_nginx::language()
+}
+
+pub fn thrift_language() -> Language {
+    tree_sitter_thrift::language()
+}
+
+pub fn elm_language() -> Language {
+    tree_sitter_elm::language()
+}
+
+pub fn html_language() -> Language {
+    tree_sitter_html::language()
+}
+
+pub fn xml_language() -> Language {
+    tree_sitter_xml::language_xml()
+}
+
+pub fn d_language() -> Language {
+    tree_sitter_d::language()
+}
+
+pub fn nim_language() -> Language {
+    tree_sitter_nim::language()
+}
+
+pub fn v_language() -> Language {
+    tree_sitter_v::language()
+}
+
+pub fn cue_language() -> Language {
+    tree_sitter_cue::language()
+}
+
+pub fn jsonnet_language() -> Language {
+    tree_sitter_jsonnet::language()
+}
+
+pub fn clojure_language() -> Language {
+    tree_sitter_clojure::language()
+}
+
+pub fn make_language() -> Language {
+    tree_sitter_make::language()
+}
+
+pub fn tcl_language() -> Language {
+    tree_sitter_tcl::language()
+}
+
+pub fn awk_language() -> Language {
+    tree_sitter_awk::language()
+}
+
+pub fn swift_language() -> Language {
+    tree_sitter_swift::language()
+}
+
+pub fn typescript_language() -> Language {
+    tree_sitter_typescript::language_typescript()
+}
+
+pub fn tsx_language() -> Language {
+    tree_sitter_typescript::language_tsx()
+}
+
+/// Content of the `node-types.json` file of the Python grammar.
+pub fn python_node_types() -> &'static str {
+    tree_sitter_python::NODE_TYPES
+}
+
+pub fn javascript_node_types() -> &'static str {
+    tree_sitter_javascript::NODE_TYPES
+}
+
+pub fn typescript_node_types() -> &'static str {
+    tree_sitter_typescript::TYPESCRIPT_NODE_TYPES
+}
+
+pub fn tsx_node_types() -> &'static str {
+    tree_sitter_typescript::TSX_NODE_TYPES
+}
+
+pub fn solidity_node_types() -> &'static str {
+    tree_sitter_solidity::NODE_TYPES
+}
+
+pub fn erlang_node_types() -> &'static str {
+    tree_sitter_erlang::NODE_TYPES
+}
+
+pub fn swift_node_types() -> &'static str {
+    tree_sitter_swift::NODE_TYPES
+}
+
+pub fn thrift_node_types() -> &'static str {
+    tree_sitter_thrift::NODE_TYPES
+}
+
+pub fn elm_node_types() -> &'static str {
+    tree_sitter_elm::NODE_TYPES
+}
+
+pub fn d_node_types() -> &'static str {
+    tree_sitter_d::NODE_TYPES
+}
+
+pub fn cue_node_types() -> &'static str {
+    tree_sitter_cue::NODE_TYPES
+}